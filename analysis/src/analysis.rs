@@ -6,11 +6,36 @@ use fsio::FsIo;
 use crate::{
     analyzer::core_analyzer::Analyzer,
     measure_time,
-    utils::{computing_device::ComputingDevice, features::*},
+    utils::{
+        computing_device::ComputingDevice,
+        features::*,
+        key::{MusicalKey, estimate_key},
+        loudness::Loudness,
+        waveform::Waveform,
+    },
 };
 
+/// Rate every source is resampled to, after a mono mixdown, before any
+/// feature is extracted. Feature vectors are only comparable because of it.
+pub const ANALYSIS_SAMPLE_RATE: u32 = 11025;
+
+/// Version of the extracted features, bump it whenever a change makes new
+/// results incomparable with stored ones so the library gets analyzed again.
+///
+/// * `1` - Resampled per window, spectral features used the source rate.
+/// * `2` - Streaming resampling to `ANALYSIS_SAMPLE_RATE`, tempo estimation.
+/// * `3` - Key estimation.
+/// * `4` - EBU R128 loudness.
+/// * `5` - Octave band energy ratios and crest factor.
+pub const ANALYSIS_FEATURE_VERSION: i32 = 5;
+
+/// Crest factors are normalized by this, in dB, a little above what the
+/// most dynamic recordings reach.
+const MAX_CREST_FACTOR_DB: f32 = 30.0;
+
 #[derive(Debug, Clone, Copy)]
 pub struct AudioStat {
+    /// Always `ANALYSIS_SAMPLE_RATE`, the rate features are extracted at.
     pub sample_rate: u32,
     pub duration: f64,
     pub total_samples: usize,
@@ -22,7 +47,7 @@ pub struct AnalysisParameter {
     pub overlap_size: usize,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct AnalysisResult {
     pub stat: AudioStat,
     pub parameters: AnalysisParameter,
@@ -42,6 +67,21 @@ pub struct AnalysisResult {
     pub perceptual_sharpness: f32,
     pub perceptual_loudness: [f32; 24],
     pub mfcc: [f32; 13],
+    /// Share of the spectral energy in every octave band, see
+    /// [`OCTAVE_BAND_CENTERS`].
+    pub octave_bands: [f32; 7],
+    /// Mean peak to RMS ratio of the windows, `None` from the legacy
+    /// analyzers.
+    pub crest_factor: Option<f32>,
+    /// Estimated tempo in beats per minute, `None` if no steady beat was found.
+    pub bpm: Option<f32>,
+    /// Estimated key, `None` if the chroma vector is flat.
+    pub key: Option<MusicalKey>,
+    /// EBU R128 loudness measured on the source channels.
+    pub loudness: Option<Loudness>,
+    /// Peaks of the source for drawing its waveform, `None` from the legacy
+    /// analyzers.
+    pub waveform: Option<Waveform>,
 }
 
 pub fn analyze_audio(
@@ -63,7 +103,7 @@ pub fn analyze_audio(
     let audio_desc = measure_time!(
         &format!("[{computing_device:?}] Analyzer"),
         analyzer.process(fsio, file_path)
-    );
+    )?;
 
     if audio_desc.is_none() {
         return Ok(None);
@@ -82,6 +122,8 @@ pub fn analyze_audio(
     let spectral_spread = spectral_spread(&amp_spectrum);
     let spectral_skewness = spectral_skewness(&amp_spectrum);
     let spectral_kurtosis = spectral_kurtosis(&amp_spectrum);
+    let octave_bands =
+        octave_band_ratios(&amp_spectrum, audio_desc.sample_rate as f32, window_size);
 
     // Generate chroma filter bank and calculate chromagram
     let chroma_filter_bank = create_chroma_filter_bank(
@@ -138,10 +180,16 @@ pub fn analyze_audio(
         perceptual_spread,
         perceptual_sharpness,
         mfcc,
+        octave_bands,
+        crest_factor: audio_desc.crest_factor,
+        bpm: audio_desc.bpm,
+        key: estimate_key(&chromagram),
+        loudness: audio_desc.loudness,
+        waveform: audio_desc.waveform,
     }))
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct NormalizedAnalysisResult {
     pub stat: AudioStat,
     pub parameters: AnalysisParameter,
@@ -157,6 +205,9 @@ pub struct NormalizedAnalysisResult {
     pub spectral_skewness: f32,
     pub spectral_kurtosis: f32,
     pub chroma: [f32; 12],
+    pub octave_bands: [f32; 7],
+    /// The crest factor in dB over [`MAX_CREST_FACTOR_DB`], between 0 and 1.
+    pub crest_factor: f32,
 }
 
 pub fn normalize_analysis_result(result: &AnalysisResult) -> NormalizedAnalysisResult {
@@ -192,11 +243,18 @@ pub fn normalize_analysis_result(result: &AnalysisResult) -> NormalizedAnalysisR
         .try_into()
         .expect("Expected a Vec of length 12");
 
+    // Crest factors are compared in dB, the ratio itself grows too fast
+    let normalized_crest_factor = result
+        .crest_factor
+        .filter(|x| *x > 0.0)
+        .map(|x| (20.0 * x.log10() / MAX_CREST_FACTOR_DB).clamp(0.0, 1.0))
+        .unwrap_or(0.0);
+
     // Create and return the normalized analysis result
     NormalizedAnalysisResult {
         stat: result.stat,
         parameters: result.parameters,
-        raw: *result,
+        raw: result.clone(),
         zcr: normalized_zcr,
         energy: normalized_energy,
         spectral_centroid: normalized_spectral_centroid,
@@ -208,5 +266,7 @@ pub fn normalize_analysis_result(result: &AnalysisResult) -> NormalizedAnalysisR
         spectral_skewness: normalized_spectral_skewness,
         spectral_kurtosis: normalized_spectral_kurtosis,
         chroma: normalized_chroma,
+        octave_bands: result.octave_bands,
+        crest_factor: normalized_crest_factor,
     }
 }
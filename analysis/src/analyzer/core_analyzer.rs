@@ -1,5 +1,6 @@
 use std::sync::{Arc, Mutex};
 
+use anyhow::{Context, Result};
 use log::debug;
 
 use rubato::{FftFixedInOut, Resampler};
@@ -20,13 +21,18 @@ use tokio_util::sync::CancellationToken;
 use fsio::FsIo;
 
 use crate::{
+    analysis::ANALYSIS_SAMPLE_RATE,
     analyzer::{
         cpu_sub_analyzer::CpuSubAnalyzer, gpu_sub_analyzer::GpuSubAnalyzer,
         sub_analyzer::SubAnalyzer,
     },
     utils::{
-        audio_description::AudioDescription, audio_metadata_reader::*,
+        audio_description::AudioDescription,
+        audio_metadata_reader::*,
         computing_device::ComputingDevice,
+        loudness::LoudnessMeter,
+        tempo::TempoTracker,
+        waveform::{WAVEFORM_PEAKS, WaveformBuilder},
     },
 };
 
@@ -34,14 +40,14 @@ macro_rules! check_cancellation {
     ($self:expr) => {
         if $self.is_cancelled || ($self.fn_is_cancelled)() {
             $self.is_cancelled = true;
-            return;
+            return Ok(());
         }
     };
 
     ($self:expr,$func:expr) => {{
         if $self.is_cancelled || ($self.fn_is_cancelled)() {
             $self.is_cancelled = true;
-            return;
+            return Ok(());
         }
         $func;
     }};
@@ -52,6 +58,9 @@ pub struct Analyzer {
     pub window_size: usize,
     overlap_size: usize,
     pub avg_spectrum: Vec<Complex<f32>>,
+    /// Mono samples at the source rate waiting to be resampled.
+    resampler_input_buffer: Vec<f32>,
+    /// Mono samples at `ANALYSIS_SAMPLE_RATE` waiting to be windowed.
     sample_buffer: Vec<f32>,
 
     fn_is_cancelled: Box<dyn Fn() -> bool>,
@@ -59,16 +68,26 @@ pub struct Analyzer {
 
     // Processing state
     pub count: usize,
+    /// Samples produced at `ANALYSIS_SAMPLE_RATE`.
     total_samples: usize,
+    /// Samples decoded at the source rate.
+    total_source_samples: usize,
     sample_rate: u32,
     duration_in_seconds: f64,
     pub total_rms: f32,
     pub total_zcr: usize,
     pub total_energy: f32,
-    pub actual_data_size: usize,
-    resample_ratio: f64,
-    pub resampler: Option<FftFixedInOut<f32>>,
-    pub resampler_output_buffer: Vec<Vec<f32>>,
+    pub total_crest_factor: f32,
+    tempo: Option<TempoTracker>,
+    /// Measured on the source channels, before the mixdown.
+    loudness: Option<LoudnessMeter>,
+    /// Fed with the mixdown at the source rate.
+    waveform: Option<WaveformBuilder>,
+    frame_buffer: Vec<f32>,
+    resampler: Option<FftFixedInOut<f32>>,
+    resampler_output_buffer: Vec<Vec<f32>>,
+    /// Leading resampler output frames that are only filter delay.
+    resampler_delay: usize,
     sub_analyzer: Arc<Mutex<dyn SubAnalyzer>>,
 }
 
@@ -93,6 +112,7 @@ impl Analyzer {
             window_size,
             overlap_size,
             avg_spectrum: vec![Complex::new(0.0, 0.0); window_size],
+            resampler_input_buffer: Vec::new(),
             sample_buffer: Vec::with_capacity(window_size),
 
             fn_is_cancelled: Box::new(move || {
@@ -104,15 +124,20 @@ impl Analyzer {
 
             count: 0,
             total_samples: 0,
+            total_source_samples: 0,
             sample_rate: 0,
             duration_in_seconds: 0.0,
             total_rms: 0.0,
             total_zcr: 0,
             total_energy: 0.0,
-            actual_data_size: 0,
-            resample_ratio: 0.0,
+            total_crest_factor: 0.0,
+            tempo: None,
+            loudness: None,
+            waveform: None,
+            frame_buffer: Vec::new(),
             resampler: None,
             resampler_output_buffer: vec![],
+            resampler_delay: 0,
 
             sub_analyzer: if computing_device == ComputingDevice::Gpu {
                 Arc::new(Mutex::new(GpuSubAnalyzer::new(window_size, batch_size)))
@@ -122,7 +147,7 @@ impl Analyzer {
         }
     }
 
-    pub fn process(&mut self, fsio: &FsIo, file_path: &str) -> Option<AudioDescription> {
+    pub fn process(&mut self, fsio: &FsIo, file_path: &str) -> Result<Option<AudioDescription>> {
         let mut format = get_format(fsio, file_path).expect("no supported audio tracks");
         let track = format
             .tracks()
@@ -142,20 +167,26 @@ impl Analyzer {
         let track_id = track.id;
 
         if self.is_cancelled || (self.fn_is_cancelled)() {
-            return None;
+            return Ok(None);
         }
 
-        self.process_audio_stream(&mut format, &mut decoder, track_id);
+        self.process_audio_stream(&mut format, &mut decoder, track_id)?;
 
-        Some(AudioDescription {
-            sample_rate: self.sample_rate,
+        // Features are extracted at the internal rate, so that is the rate
+        // the description refers to
+        Ok(Some(AudioDescription {
+            sample_rate: ANALYSIS_SAMPLE_RATE,
             duration: self.duration_in_seconds,
             total_samples: self.total_samples,
             spectrum: self.avg_spectrum.clone(),
             rms: self.total_rms / self.count as f32,
             zcr: self.total_zcr / self.count,
             energy: self.total_energy / self.count as f32,
-        })
+            crest_factor: Some(self.total_crest_factor / self.count as f32),
+            bpm: self.tempo.as_ref().and_then(|x| x.estimate()),
+            loudness: self.loudness.as_ref().map(|x| x.finish()),
+            waveform: self.waveform.take().map(|x| x.finish()),
+        }))
     }
 
     fn process_audio_chunk(&mut self, chunk: &[f32], force: bool) {
@@ -165,29 +196,121 @@ impl Analyzer {
             .process_audio_chunk(self, chunk, force);
     }
 
-    fn process_audio_buffer<T>(&mut self, buf: &AudioBuffer<T>)
+    fn process_audio_buffer<T>(&mut self, buf: &AudioBuffer<T>) -> Result<()>
     where
         T: Sample + IntoSample<f32>,
     {
         let frames = buf.frames();
         let num_channels = buf.spec().channels.count();
 
+        if self.loudness.is_none() {
+            self.loudness = Some(LoudnessMeter::new(self.sample_rate, num_channels));
+        }
+
         for frame_idx in 0..frames {
-            let mixed_sample: f32 = (0..num_channels)
-                .map(|ch| IntoSample::<f32>::into_sample(buf.chan(ch)[frame_idx]))
-                .sum::<f32>()
-                / num_channels as f32;
-
-            self.sample_buffer.push(mixed_sample);
-            self.total_samples += 1;
-
-            while self.sample_buffer.len() >= self.actual_data_size {
-                let chunk: Vec<f32> = self.sample_buffer[..self.actual_data_size].to_vec();
-                self.process_audio_chunk(&chunk, false);
-                self.sample_buffer
-                    .drain(..(self.window_size - self.overlap_size));
+            self.frame_buffer.clear();
+            self.frame_buffer.extend(
+                (0..num_channels).map(|ch| IntoSample::<f32>::into_sample(buf.chan(ch)[frame_idx])),
+            );
+            if let Some(loudness) = self.loudness.as_mut() {
+                loudness.push_frame(&self.frame_buffer);
+            }
+
+            let mixed_sample: f32 = self.frame_buffer.iter().sum::<f32>() / num_channels as f32;
+            if let Some(waveform) = self.waveform.as_mut() {
+                waveform.push(mixed_sample);
+            }
+
+            self.resampler_input_buffer.push(mixed_sample);
+            self.total_source_samples += 1;
+
+            let input_frames = self.resampler.as_ref().unwrap().input_frames_next();
+            if self.resampler_input_buffer.len() >= input_frames {
+                self.resample(false)?;
+                self.process_windows();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resample the pending source samples to `ANALYSIS_SAMPLE_RATE`. With
+    /// `flush`, the remaining samples are zero padded and the filter delay is
+    /// drained, and the output is trimmed to the length of the source.
+    fn resample(&mut self, flush: bool) -> Result<()> {
+        let expected_samples = (self.total_source_samples as f64 * ANALYSIS_SAMPLE_RATE as f64
+            / self.sample_rate as f64)
+            .round() as usize;
+        let remaining = expected_samples.saturating_sub(self.total_samples);
+
+        let resampler = self.resampler.as_mut().unwrap();
+        let mut output: Vec<f32> = vec![];
+
+        if flush {
+            // The source may end right at a chunk boundary, with nothing
+            // left but the filter delay
+            let pending = std::mem::take(&mut self.resampler_input_buffer);
+            if !pending.is_empty() {
+                let (_, output_frames) = resampler
+                    .process_partial_into_buffer(
+                        Some(&[pending.as_slice()][..]),
+                        &mut self.resampler_output_buffer,
+                        None,
+                    )
+                    .with_context(|| "Failed to resample the last samples")?;
+                output.extend_from_slice(&self.resampler_output_buffer[0][..output_frames]);
+            }
+
+            while output.len() < self.resampler_delay + remaining {
+                let (_, output_frames) = resampler
+                    .process_partial_into_buffer(
+                        None::<&[&[f32]]>,
+                        &mut self.resampler_output_buffer,
+                        None,
+                    )
+                    .with_context(|| "Failed to drain the resampler")?;
+                output.extend_from_slice(&self.resampler_output_buffer[0][..output_frames]);
+            }
+        } else {
+            let input_frames = resampler.input_frames_next();
+            let (_, output_frames) = resampler
+                .process_into_buffer(
+                    &[&self.resampler_input_buffer[..input_frames]],
+                    &mut self.resampler_output_buffer,
+                    None,
+                )
+                .with_context(|| "Failed to resample the source")?;
+            self.resampler_input_buffer.drain(..input_frames);
+            output.extend_from_slice(&self.resampler_output_buffer[0][..output_frames]);
+        }
+
+        let skipped = self.resampler_delay.min(output.len());
+        self.resampler_delay -= skipped;
+        let mut output = &output[skipped..];
+
+        if flush {
+            output = &output[..remaining.min(output.len())];
+        }
+
+        if let Some(tempo) = self.tempo.as_mut() {
+            for &sample in output {
+                tempo.push(sample);
             }
         }
+
+        self.sample_buffer.extend_from_slice(output);
+        self.total_samples += output.len();
+
+        Ok(())
+    }
+
+    fn process_windows(&mut self) {
+        while self.sample_buffer.len() >= self.window_size {
+            let chunk: Vec<f32> = self.sample_buffer[..self.window_size].to_vec();
+            self.process_audio_chunk(&chunk, false);
+            self.sample_buffer
+                .drain(..(self.window_size - self.overlap_size));
+        }
     }
 
     fn process_audio_stream(
@@ -195,21 +318,28 @@ impl Analyzer {
         format: &mut Box<dyn FormatReader>,
         decoder: &mut Box<dyn Decoder>,
         track_id: u32,
-    ) {
-        self.resample_ratio = 11025_f64 / self.sample_rate as f64;
-        self.actual_data_size = ((self.window_size) as f64 / self.resample_ratio).ceil() as usize;
-
-        self.resampler = Some(
-            FftFixedInOut::<f32>::new(self.sample_rate as usize, 11025, self.actual_data_size, 1)
-                .unwrap(),
-        );
-        self.resampler_output_buffer = self
-            .resampler
-            .as_mut()
-            .unwrap()
-            .output_buffer_allocate(true);
-
-        self.actual_data_size = self.resampler.as_mut().unwrap().input_frames_max();
+    ) -> Result<()> {
+        self.tempo = Some(TempoTracker::new(ANALYSIS_SAMPLE_RATE));
+        self.waveform = Some(WaveformBuilder::new(WAVEFORM_PEAKS));
+
+        // Every source is brought to the same rate before any feature is
+        // extracted, so feature vectors stay comparable across sample rates
+        let resampler = FftFixedInOut::<f32>::new(
+            self.sample_rate as usize,
+            ANALYSIS_SAMPLE_RATE as usize,
+            self.window_size,
+            1,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to create a resampler from {} Hz to {ANALYSIS_SAMPLE_RATE} Hz",
+                self.sample_rate
+            )
+        })?;
+        self.resampler_output_buffer = resampler.output_buffer_allocate(true);
+        self.resampler_delay = resampler.output_delay();
+        self.resampler_input_buffer = Vec::with_capacity(resampler.input_frames_max());
+        self.resampler = Some(resampler);
 
         // Decode loop.
         loop {
@@ -251,61 +381,62 @@ impl Analyzer {
             match decoded {
                 AudioBufferRef::U8(buf) => {
                     debug!("Decoded buffer type: U8, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::U16(buf) => {
                     debug!("Decoded buffer type: U16, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::U24(buf) => {
                     debug!("Decoded buffer type: U24, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::U32(buf) => {
                     debug!("Decoded buffer type: U32, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::S8(buf) => {
                     debug!("Decoded buffer type: S8, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::S16(buf) => {
                     debug!("Decoded buffer type: S16, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::S24(buf) => {
                     debug!("Decoded buffer type: S24, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::S32(buf) => {
                     debug!("Decoded buffer type: S32, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::F32(buf) => {
                     debug!("Decoded buffer type: F32, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
                 AudioBufferRef::F64(buf) => {
                     debug!("Decoded buffer type: F64, length: {}", buf.frames());
-                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref()));
+                    check_cancellation!(self, self.process_audio_buffer(buf.as_ref())?);
                 }
             }
         }
 
+        check_cancellation!(self, self.resample(true)?);
+        check_cancellation!(self, self.process_windows());
+
         if !self.sample_buffer.is_empty() {
-            // Pad to the nearest multiple of 1024
-            let target_size = self.total_samples.div_ceil(1024) * 1024;
-            while self.sample_buffer.len() < target_size {
-                self.sample_buffer.push(0.0);
-            }
+            // Pad the last partial window with silence
+            self.sample_buffer.resize(self.window_size, 0.0);
 
-            // Only process up to target_size
-            let chunk: Vec<f32> =
-                self.sample_buffer[..target_size.min(self.actual_data_size)].to_vec();
+            let chunk: Vec<f32> = self.sample_buffer.clone();
             check_cancellation!(self, self.process_audio_chunk(&chunk, true));
         }
 
-        debug!("Total samples: {}", self.total_samples);
+        debug!(
+            "Total samples: {} ({} at source rate)",
+            self.total_samples, self.total_source_samples
+        );
 
         if self.count == 0 {
             panic!("No audio data processed");
@@ -316,5 +447,7 @@ impl Analyzer {
             *value /= self.count as f32;
         }
         debug!("Final average spectrum calculated");
+
+        Ok(())
     }
 }
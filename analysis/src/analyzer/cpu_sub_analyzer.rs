@@ -4,9 +4,9 @@ use crate::utils::hanning_window::build_hanning_window;
 use std::sync::Arc;
 
 use realfft::{RealFftPlanner, RealToComplex};
-use rubato::Resampler;
 use rustfft::num_complex::Complex;
 
+use crate::utils::features::crest_factor;
 use crate::utils::features::energy;
 use crate::utils::features::rms;
 use crate::utils::features::zcr;
@@ -39,24 +39,16 @@ impl CpuSubAnalyzer {
 
 impl SubAnalyzer for CpuSubAnalyzer {
     fn process_audio_chunk(&mut self, core_analyzer: &mut Analyzer, chunk: &[f32], force: bool) {
-        let _ = &core_analyzer
-            .resampler
-            .as_mut()
-            .unwrap()
-            .process_into_buffer(&[chunk], &mut core_analyzer.resampler_output_buffer, None)
-            .unwrap();
-
-        let resampled_chunk = &core_analyzer.resampler_output_buffer[0];
-
-        core_analyzer.total_rms += rms(resampled_chunk);
-        core_analyzer.total_zcr += zcr(resampled_chunk);
-        core_analyzer.total_energy += energy(resampled_chunk);
+        core_analyzer.total_rms += rms(chunk);
+        core_analyzer.total_zcr += zcr(chunk);
+        core_analyzer.total_energy += energy(chunk);
+        core_analyzer.total_crest_factor += crest_factor(chunk);
 
         let start_idx = self.batch_cache_buffer_count * core_analyzer.window_size;
         let buffer_slice =
             &mut self.fft_input_buffer[start_idx..start_idx + core_analyzer.window_size];
         for (i, sample) in buffer_slice.iter_mut().enumerate() {
-            *sample = resampled_chunk[i] * self.hanning_window[i];
+            *sample = chunk[i] * self.hanning_window[i];
         }
 
         self.batch_cache_buffer_count += 1;
@@ -1,10 +1,9 @@
-use rubato::Resampler;
 use rustfft::num_complex::Complex;
 
 use crate::{
     analyzer::{core_analyzer::Analyzer, sub_analyzer::SubAnalyzer},
     utils::{
-        features::{energy, rms, zcr},
+        features::{crest_factor, energy, rms, zcr},
         hanning_window::build_hanning_window,
     },
 };
@@ -31,22 +30,16 @@ impl GpuSubAnalyzer {
 
 impl SubAnalyzer for GpuSubAnalyzer {
     fn process_audio_chunk(&mut self, core_analyzer: &mut Analyzer, chunk: &[f32], force: bool) {
-        let resampled_chunk = &core_analyzer
-            .resampler
-            .as_mut()
-            .unwrap()
-            .process(&[chunk], None)
-            .unwrap()[0];
-
-        core_analyzer.total_rms += rms(resampled_chunk);
-        core_analyzer.total_zcr += zcr(resampled_chunk);
-        core_analyzer.total_energy += energy(resampled_chunk);
+        core_analyzer.total_rms += rms(chunk);
+        core_analyzer.total_zcr += zcr(chunk);
+        core_analyzer.total_energy += energy(chunk);
+        core_analyzer.total_crest_factor += crest_factor(chunk);
 
         let start_idx = self.batch_cache_buffer_count * core_analyzer.window_size;
         let buffer_slice =
             &mut self.batch_fft_buffer[start_idx..start_idx + core_analyzer.window_size];
         for (i, sample) in buffer_slice.iter_mut().enumerate() {
-            *sample = Complex::new(resampled_chunk[i] * self.hanning_window[i], 0.0);
+            *sample = Complex::new(chunk[i] * self.hanning_window[i], 0.0);
         }
 
         self.batch_cache_buffer_count += 1;
@@ -96,7 +89,10 @@ mod tests {
 
         let mut analyzer =
             Analyzer::new(ComputingDevice::Cpu, window_size, overlap_size, None, None);
-        let gpu_result = measure_time!("GPU FFT", analyzer.process(&fsio, file_path).unwrap());
+        let gpu_result = measure_time!(
+            "GPU FFT",
+            analyzer.process(&fsio, file_path).unwrap().unwrap()
+        );
 
         let gpu_result1 = measure_time!(
             "GPU FFT1",
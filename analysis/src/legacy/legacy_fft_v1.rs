@@ -283,5 +283,9 @@ pub fn fft(
         rms: total_rms / count as f32,
         zcr: total_zcr / count,
         energy: total_energy / count as f32,
+        crest_factor: None,
+        bpm: None,
+        loudness: None,
+        waveform: None,
     })
 }
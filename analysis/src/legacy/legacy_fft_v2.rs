@@ -167,6 +167,10 @@ impl FFTProcessor {
             rms: self.total_rms / self.count as f32,
             zcr: self.total_zcr / self.count,
             energy: self.total_energy / self.count as f32,
+            crest_factor: None,
+            bpm: None,
+            loudness: None,
+            waveform: None,
         })
     }
 
@@ -19,7 +19,9 @@ mod tests {
         let mut analyzer =
             Analyzer::new(ComputingDevice::Cpu, window_size, overlap_size, None, None);
 
-        let cpu_result = measure_time!("CPU FFT", analyzer.process(&fsio, file_path)).unwrap();
+        let cpu_result = measure_time!("CPU FFT", analyzer.process(&fsio, file_path))
+            .unwrap()
+            .unwrap();
 
         let legacy_cpu_result = measure_time!(
             "LEGACY CPU FFT",
@@ -62,7 +64,9 @@ mod tests {
         let mut analyzer =
             Analyzer::new(ComputingDevice::Gpu, window_size, overlap_size, None, None);
 
-        let gpu_result = measure_time!("GPU FFT", analyzer.process(&fsio, file_path)).unwrap();
+        let gpu_result = measure_time!("GPU FFT", analyzer.process(&fsio, file_path))
+            .unwrap()
+            .unwrap();
 
         let legacy_cpu_result = measure_time!(
             "LEGACY CPU FFT",
@@ -105,12 +109,16 @@ mod tests {
         let mut analyzer =
             Analyzer::new(ComputingDevice::Cpu, window_size, overlap_size, None, None);
 
-        let cpu_result = measure_time!("CPU FFT", analyzer.process(&fsio, file_path)).unwrap();
+        let cpu_result = measure_time!("CPU FFT", analyzer.process(&fsio, file_path))
+            .unwrap()
+            .unwrap();
 
         let mut analyzer =
             Analyzer::new(ComputingDevice::Gpu, window_size, overlap_size, None, None);
 
-        let gpu_result = measure_time!("GPU FFT", analyzer.process(&fsio, file_path)).unwrap();
+        let gpu_result = measure_time!("GPU FFT", analyzer.process(&fsio, file_path))
+            .unwrap()
+            .unwrap();
 
         info!("CPU result: {cpu_result:?}");
         info!("GPU result: {gpu_result:?}");
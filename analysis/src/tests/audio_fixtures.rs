@@ -0,0 +1,283 @@
+//! Deterministic synthetic audio for regression tests of the analysis
+//! pipeline. Every fixture can be rendered to a 16-bit PCM WAV file and
+//! knows the features the analyzer is expected to report for it.
+
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::analysis::ANALYSIS_SAMPLE_RATE;
+
+static FIXTURE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Sine {
+        frequency: f32,
+    },
+    /// Exponential sweep from `start` to `end` Hz over the whole fixture.
+    Sweep {
+        start: f32,
+        end: f32,
+    },
+    /// Root, third and fifth of an equal tempered triad, evenly mixed.
+    Triad {
+        root: f32,
+        minor: bool,
+    },
+    /// Uniform white noise from a seeded generator.
+    WhiteNoise {
+        seed: u64,
+    },
+    /// Short sawtooth bursts on every beat.
+    Clicks {
+        bpm: f32,
+    },
+    Silence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioFixture {
+    pub waveform: Waveform,
+    /// Peak amplitude, `1.0` is full scale.
+    pub amplitude: f32,
+    pub sample_rate: u32,
+    /// Every channel carries the same signal.
+    pub channels: u16,
+    pub seconds: f32,
+}
+
+impl AudioFixture {
+    fn new(waveform: Waveform, amplitude: f32) -> Self {
+        AudioFixture {
+            waveform,
+            amplitude,
+            sample_rate: 44100,
+            channels: 2,
+            seconds: 10.0,
+        }
+    }
+
+    pub fn sine(frequency: f32, amplitude: f32) -> Self {
+        Self::new(Waveform::Sine { frequency }, amplitude)
+    }
+
+    pub fn sweep(start: f32, end: f32, amplitude: f32) -> Self {
+        Self::new(Waveform::Sweep { start, end }, amplitude)
+    }
+
+    pub fn triad(root: f32, minor: bool, amplitude: f32) -> Self {
+        Self::new(Waveform::Triad { root, minor }, amplitude)
+    }
+
+    pub fn white_noise(amplitude: f32, seed: u64) -> Self {
+        Self::new(Waveform::WhiteNoise { seed }, amplitude)
+    }
+
+    pub fn clicks(bpm: f32, amplitude: f32) -> Self {
+        Self::new(Waveform::Clicks { bpm }, amplitude)
+    }
+
+    pub fn silence() -> Self {
+        Self::new(Waveform::Silence, 0.0)
+    }
+
+    pub fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn with_channels(mut self, channels: u16) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    pub fn with_seconds(mut self, seconds: f32) -> Self {
+        self.seconds = seconds;
+        self
+    }
+
+    pub fn total_frames(&self) -> usize {
+        (self.sample_rate as f32 * self.seconds) as usize
+    }
+
+    /// The signal of a single channel.
+    pub fn samples(&self) -> Vec<f32> {
+        let rate = self.sample_rate as f32;
+        let total = self.total_frames();
+
+        match self.waveform {
+            Waveform::Sine { frequency } => (0..total)
+                .map(|i| self.amplitude * (2.0 * PI * frequency * i as f32 / rate).sin())
+                .collect(),
+            Waveform::Sweep { start, end } => {
+                let k = (end / start).ln() / self.seconds;
+                (0..total)
+                    .map(|i| {
+                        let t = i as f32 / rate;
+                        let phase = 2.0 * PI * start * ((k * t).exp() - 1.0) / k;
+                        self.amplitude * phase.sin()
+                    })
+                    .collect()
+            }
+            Waveform::Triad { root, minor } => {
+                let third = if minor { 3.0 } else { 4.0 };
+                let frequencies = [0.0, third, 7.0].map(|x| root * 2f32.powf(x / 12.0));
+                (0..total)
+                    .map(|i| {
+                        let t = i as f32 / rate;
+                        frequencies
+                            .iter()
+                            .map(|frequency| (2.0 * PI * frequency * t).sin())
+                            .sum::<f32>()
+                            * self.amplitude
+                            / 3.0
+                    })
+                    .collect()
+            }
+            Waveform::WhiteNoise { seed } => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                (0..total)
+                    .map(|_| self.amplitude * rng.gen_range(-1.0..1.0))
+                    .collect()
+            }
+            Waveform::Clicks { bpm } => {
+                let period = (rate * 60.0 / bpm) as usize;
+                let click = (rate * 0.02) as usize;
+                (0..total)
+                    .map(|i| {
+                        if i % period < click {
+                            self.amplitude * ((i % 50) as f32 / 25.0 - 1.0)
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect()
+            }
+            Waveform::Silence => vec![0.0; total],
+        }
+    }
+
+    /// Expected RMS of an analysis window, after the input is brought to
+    /// the analysis rate.
+    pub fn expected_rms(&self) -> Option<f32> {
+        match self.waveform {
+            Waveform::Sine { .. } | Waveform::Sweep { .. } => Some(self.amplitude / 2f32.sqrt()),
+            // Three uncorrelated sines of a third of the amplitude each
+            Waveform::Triad { .. } => Some(self.amplitude / 6f32.sqrt()),
+            // Resampling drops everything above the new Nyquist frequency,
+            // and with it that share of the noise power
+            Waveform::WhiteNoise { .. } => {
+                let kept = (ANALYSIS_SAMPLE_RATE as f32 / self.sample_rate as f32).min(1.0);
+                Some(self.amplitude / 3f32.sqrt() * kept.sqrt())
+            }
+            Waveform::Clicks { .. } => None,
+            Waveform::Silence => Some(0.0),
+        }
+    }
+
+    /// Expected zero crossings of an analysis window of `window_size`
+    /// samples at the analysis rate.
+    pub fn expected_zcr(&self, window_size: usize) -> Option<f32> {
+        let crossings_per_second = |frequency: f32| 2.0 * frequency;
+        let window_seconds = window_size as f32 / ANALYSIS_SAMPLE_RATE as f32;
+
+        match self.waveform {
+            Waveform::Sine { frequency } => Some(crossings_per_second(frequency) * window_seconds),
+            Waveform::Sweep { start, end } => {
+                // Time average of the instantaneous frequency
+                let mean_frequency = (end - start) / (end / start).ln();
+                Some(crossings_per_second(mean_frequency) * window_seconds)
+            }
+            Waveform::WhiteNoise { .. } => Some((window_size - 1) as f32 / 2.0),
+            Waveform::Triad { .. } | Waveform::Clicks { .. } => None,
+            Waveform::Silence => Some(0.0),
+        }
+    }
+
+    /// Expected tempo, `None` if the signal has no beat.
+    pub fn expected_bpm(&self) -> Option<f32> {
+        match self.waveform {
+            Waveform::Clicks { bpm } => Some(bpm),
+            _ => None,
+        }
+    }
+
+    /// Expected true peak in dBTP, `None` for silence.
+    pub fn expected_true_peak(&self) -> Option<f32> {
+        match self.waveform {
+            Waveform::Sine { .. } | Waveform::Sweep { .. } => Some(20.0 * self.amplitude.log10()),
+            _ => None,
+        }
+    }
+
+    /// Write the fixture as a 16-bit PCM WAV file.
+    pub fn write_wav(&self, path: &Path) -> std::io::Result<()> {
+        let samples = self.samples();
+        let channels = self.channels.max(1);
+        let block_align = channels * 2;
+        let data_size = (samples.len() * block_align as usize) as u32;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(36 + data_size).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&channels.to_le_bytes())?;
+        writer.write_all(&self.sample_rate.to_le_bytes())?;
+        writer.write_all(&(self.sample_rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&16u16.to_le_bytes())?;
+
+        writer.write_all(b"data")?;
+        writer.write_all(&data_size.to_le_bytes())?;
+        for sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            for _ in 0..channels {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// Write the fixture to a temporary file, removed once the returned
+    /// handle is dropped.
+    pub fn write_temp_wav(&self) -> TempWav {
+        let path = std::env::temp_dir().join(format!(
+            "rune-analysis-fixture-{}-{}.wav",
+            std::process::id(),
+            FIXTURE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        self.write_wav(&path)
+            .unwrap_or_else(|e| panic!("Failed to write fixture {path:?}: {e}"));
+
+        TempWav { path }
+    }
+}
+
+pub struct TempWav {
+    path: PathBuf,
+}
+
+impl TempWav {
+    pub fn path(&self) -> &str {
+        self.path
+            .to_str()
+            .expect("Temporary path is not valid UTF-8")
+    }
+}
+
+impl Drop for TempWav {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{PI, SQRT_2};
+
+    use fsio::FsIo;
+
+    use crate::{
+        analysis::{ANALYSIS_SAMPLE_RATE, analyze_audio, normalize_analysis_result},
+        tests::audio_fixtures::AudioFixture,
+        utils::{
+            computing_device::ComputingDevice,
+            features::{OCTAVE_BAND_CENTERS, crest_factor, octave_band_ratios},
+        },
+    };
+
+    const WINDOW_SIZE: usize = 1024;
+
+    /// A spectrum with a single bin as close as possible to `frequency`.
+    fn single_bin_spectrum(frequency: f32) -> Vec<f32> {
+        let mut spectrum = vec![0.0; WINDOW_SIZE / 2];
+        let bin = (frequency * WINDOW_SIZE as f32 / ANALYSIS_SAMPLE_RATE as f32).round();
+        spectrum[bin as usize] = 1.0;
+        spectrum
+    }
+
+    #[test]
+    fn test_octave_band_ratios() {
+        for (band, center) in OCTAVE_BAND_CENTERS.iter().enumerate() {
+            let ratios = octave_band_ratios(
+                &single_bin_spectrum(*center),
+                ANALYSIS_SAMPLE_RATE as f32,
+                WINDOW_SIZE,
+            );
+            assert_eq!(ratios[band], 1.0, "{center} Hz: {ratios:?}");
+        }
+
+        let mut spectrum = single_bin_spectrum(250.0);
+        spectrum
+            .iter_mut()
+            .zip(single_bin_spectrum(2000.0))
+            .for_each(|(x, y)| *x += y);
+        let ratios = octave_band_ratios(&spectrum, ANALYSIS_SAMPLE_RATE as f32, WINDOW_SIZE);
+        assert_eq!(ratios[2], 0.5);
+        assert_eq!(ratios[5], 0.5);
+
+        let silence = vec![0.0; WINDOW_SIZE / 2];
+        assert_eq!(
+            octave_band_ratios(&silence, ANALYSIS_SAMPLE_RATE as f32, WINDOW_SIZE),
+            [0.0; 7]
+        );
+    }
+
+    #[test]
+    fn test_crest_factor() {
+        let sine: Vec<f32> = (0..WINDOW_SIZE)
+            .map(|i| 0.5 * (2.0 * PI * 32.0 * i as f32 / WINDOW_SIZE as f32).sin())
+            .collect();
+        assert!((crest_factor(&sine) - SQRT_2).abs() < 1e-3);
+
+        let square: Vec<f32> = (0..WINDOW_SIZE)
+            .map(|i| if i % 2 == 0 { 0.5 } else { -0.5 })
+            .collect();
+        assert!((crest_factor(&square) - 1.0).abs() < 1e-5);
+
+        assert_eq!(crest_factor(&[0.0; WINDOW_SIZE]), 0.0);
+    }
+
+    #[test]
+    fn test_sine_band_features() {
+        let wav = AudioFixture::sine(1000.0, 0.5).write_temp_wav();
+        let result = analyze_audio(
+            &FsIo::new(),
+            wav.path(),
+            WINDOW_SIZE,
+            WINDOW_SIZE / 2,
+            ComputingDevice::Cpu,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert!(
+            result.octave_bands[4] > 0.9,
+            "Unexpected bands: {:?}",
+            result.octave_bands
+        );
+        let crest = result.crest_factor.unwrap();
+        assert!(
+            (crest - SQRT_2).abs() < 0.05,
+            "Unexpected crest factor: {crest}"
+        );
+
+        // 3 dB out of 30
+        let normalized = normalize_analysis_result(&result);
+        assert!((normalized.crest_factor - 0.1).abs() < 0.01);
+        assert_eq!(normalized.octave_bands, result.octave_bands);
+    }
+}
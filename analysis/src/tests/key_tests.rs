@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod tests {
+    use crate::utils::key::{KeyMode, MusicalKey, estimate_key};
+
+    /// Chroma of a triad-heavy passage: tonic, third and fifth dominate.
+    fn triad_chroma(tonic: usize, minor: bool) -> [f32; 12] {
+        let mut chroma = [0.1; 12];
+        chroma[tonic % 12] = 1.0;
+        chroma[(tonic + if minor { 3 } else { 4 }) % 12] = 0.7;
+        chroma[(tonic + 7) % 12] = 0.8;
+        chroma
+    }
+
+    #[test]
+    fn test_estimate_key() {
+        let key = estimate_key(&triad_chroma(0, false)).unwrap();
+        assert_eq!((key.tonic, key.mode), (0, KeyMode::Major));
+        assert_eq!(key.to_string(), "C major");
+
+        let key = estimate_key(&triad_chroma(1, true)).unwrap();
+        assert_eq!((key.tonic, key.mode), (1, KeyMode::Minor));
+        assert_eq!(key.to_string(), "C# minor");
+    }
+
+    #[test]
+    fn test_flat_chroma() {
+        assert!(estimate_key(&[0.5; 12]).is_none());
+    }
+
+    #[test]
+    fn test_from_parts() {
+        assert_eq!(MusicalKey::from_parts(9, 1).unwrap().to_string(), "A minor");
+        assert!(MusicalKey::from_parts(12, 0).is_none());
+        assert!(MusicalKey::from_parts(0, 2).is_none());
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::utils::loudness::LoudnessMeter;
+
+    fn measure_sine(
+        sample_rate: u32,
+        channels: usize,
+        amplitude: f32,
+        seconds: f32,
+    ) -> LoudnessMeter {
+        let mut meter = LoudnessMeter::new(sample_rate, channels);
+        let mut frame = vec![0.0; channels];
+
+        for i in 0..(sample_rate as f32 * seconds) as usize {
+            let sample = amplitude * (2.0 * PI * 1000.0 * i as f32 / sample_rate as f32).sin();
+            frame.fill(sample);
+            meter.push_frame(&frame);
+        }
+
+        meter
+    }
+
+    #[test]
+    fn test_stereo_sine_loudness() {
+        // A 1kHz stereo sine at -20 dBFS reads -20 LUFS
+        let loudness = measure_sine(48000, 2, 0.1, 10.0).finish();
+
+        let integrated = loudness.integrated.unwrap();
+        assert!(
+            (integrated + 20.0).abs() < 0.2,
+            "Unexpected integrated loudness: {integrated}"
+        );
+        assert!(
+            (loudness.true_peak + 20.0).abs() < 0.2,
+            "Unexpected true peak: {}",
+            loudness.true_peak
+        );
+        assert!(loudness.range < 0.5, "Unexpected range: {}", loudness.range);
+    }
+
+    #[test]
+    fn test_silence_is_gated() {
+        let loudness = measure_sine(44100, 2, 0.0, 5.0).finish();
+        assert!(loudness.integrated.is_none());
+        assert_eq!(loudness.range, 0.0);
+    }
+}
@@ -1,2 +1,10 @@
 pub mod analyzer_tests;
+#[cfg(test)]
+pub mod audio_fixtures;
+pub mod band_tests;
 pub mod fft_tests;
+pub mod key_tests;
+pub mod loudness_tests;
+pub mod pipeline_tests;
+pub mod tempo_tests;
+pub mod waveform_tests;
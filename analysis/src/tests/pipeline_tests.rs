@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod tests {
+    use fsio::FsIo;
+
+    use crate::{
+        analysis::analyze_audio,
+        analyzer::core_analyzer::Analyzer,
+        tests::audio_fixtures::AudioFixture,
+        utils::{audio_description::AudioDescription, computing_device::ComputingDevice},
+    };
+
+    const WINDOW_SIZE: usize = 1024;
+    const OVERLAP_SIZE: usize = 512;
+
+    fn describe(fixture: &AudioFixture) -> AudioDescription {
+        let wav = fixture.write_temp_wav();
+        Analyzer::new(ComputingDevice::Cpu, WINDOW_SIZE, OVERLAP_SIZE, None, None)
+            .process(&FsIo::new(), wav.path())
+            .unwrap()
+            .expect("Analysis was cancelled")
+    }
+
+    fn assert_close(name: &str, actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{name}: expected {expected} ± {tolerance}, got {actual}"
+        );
+    }
+
+    /// Compare everything the fixture knows about with the analyzer output,
+    /// `relative` is the tolerance of the RMS and ZCR.
+    fn assert_features(fixture: &AudioFixture, relative: f32) {
+        let description = describe(fixture);
+
+        if let Some(rms) = fixture.expected_rms() {
+            assert_close("RMS", description.rms, rms, rms * relative + 1e-3);
+        }
+        if let Some(zcr) = fixture.expected_zcr(WINDOW_SIZE) {
+            assert_close("ZCR", description.zcr as f32, zcr, zcr * relative + 2.0);
+        }
+        match fixture.expected_bpm() {
+            Some(bpm) => assert_close("BPM", description.bpm.unwrap(), bpm, 2.0),
+            None => assert!(description.bpm.is_none(), "{:?}", description.bpm),
+        }
+        if let Some(true_peak) = fixture.expected_true_peak() {
+            let loudness = description.loudness.unwrap();
+            assert_close("True peak", loudness.true_peak, true_peak, 0.5);
+        }
+    }
+
+    #[test]
+    fn test_sine_features() {
+        assert_features(&AudioFixture::sine(440.0, 0.5), 0.03);
+        assert_features(&AudioFixture::sine(1000.0, 0.25).with_channels(1), 0.03);
+    }
+
+    #[test]
+    fn test_sweep_features() {
+        assert_features(&AudioFixture::sweep(100.0, 4000.0, 0.5), 0.05);
+    }
+
+    #[test]
+    fn test_noise_features() {
+        // The anti-aliasing filter of the resampler takes away a little more
+        // than the ideal cutoff, so noise only matches roughly
+        assert_features(&AudioFixture::white_noise(0.5, 42), 0.1);
+    }
+
+    #[test]
+    fn test_click_track_features() {
+        assert_features(&AudioFixture::clicks(120.0, 0.8).with_seconds(30.0), 0.05);
+    }
+
+    #[test]
+    fn test_silence_features() {
+        let fixture = AudioFixture::silence();
+        assert_features(&fixture, 0.0);
+
+        let loudness = describe(&fixture).loudness.unwrap();
+        assert!(loudness.integrated.is_none());
+    }
+
+    #[test]
+    fn test_sample_rate_invariance() {
+        let reference = describe(&AudioFixture::sine(440.0, 0.5));
+
+        for sample_rate in [22050, 48000, 96000] {
+            let description =
+                describe(&AudioFixture::sine(440.0, 0.5).with_sample_rate(sample_rate));
+            assert_close("RMS", description.rms, reference.rms, reference.rms * 0.01);
+            assert_close("ZCR", description.zcr as f32, reference.zcr as f32, 2.0);
+        }
+    }
+
+    #[test]
+    fn test_key_sample_rate_invariance() {
+        let analyze = |sample_rate: u32| {
+            let wav = AudioFixture::triad(440.0, true, 0.5)
+                .with_sample_rate(sample_rate)
+                .write_temp_wav();
+            analyze_audio(
+                &FsIo::new(),
+                wav.path(),
+                WINDOW_SIZE,
+                OVERLAP_SIZE,
+                ComputingDevice::Cpu,
+                None,
+            )
+            .unwrap()
+            .expect("Analysis was cancelled")
+        };
+
+        // The same chord ripped at both rates must land in the same key
+        let reference = analyze(44100);
+        let reference_key = reference.key.expect("No key detected at 44.1 kHz");
+        let result = analyze(48000);
+        let key = result.key.expect("No key detected at 48 kHz");
+        assert_eq!(
+            (key.tonic, key.mode),
+            (reference_key.tonic, reference_key.mode)
+        );
+
+        for (pitch_class, (actual, expected)) in result
+            .chromagram
+            .iter()
+            .zip(reference.chromagram)
+            .enumerate()
+        {
+            assert_close(&format!("Chroma {pitch_class}"), *actual, expected, 0.05);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use crate::{tests::audio_fixtures::AudioFixture, utils::tempo::TempoTracker};
+
+    fn click_track(sample_rate: u32, bpm: f32, seconds: f32) -> Vec<f32> {
+        let total = (sample_rate as f32 * seconds) as usize;
+        let period = (sample_rate as f32 * 60.0 / bpm) as usize;
+        let click = (sample_rate as f32 * 0.02) as usize;
+
+        (0..total)
+            .map(|i| {
+                if i % period < click {
+                    ((i % 50) as f32 / 25.0 - 1.0) * 0.8
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    fn estimate(samples: &[f32], sample_rate: u32) -> Option<f32> {
+        let mut tracker = TempoTracker::new(sample_rate);
+        for &sample in samples {
+            tracker.push(sample);
+        }
+        tracker.estimate()
+    }
+
+    #[test]
+    fn test_click_track_tempo() {
+        for bpm in [90.0, 120.0, 140.0] {
+            let detected = estimate(&click_track(22050, bpm, 30.0), 22050).unwrap();
+            assert!(
+                (detected - bpm).abs() < 2.0,
+                "Expected {bpm} BPM, detected {detected}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_tempo() {
+        assert!(estimate(&vec![0.0; 22050 * 10], 22050).is_none());
+        assert!(estimate(&click_track(22050, 120.0, 2.0), 22050).is_none());
+    }
+
+    #[test]
+    fn test_no_tempo_without_beat() {
+        for fixture in [
+            AudioFixture::sine(440.0, 0.5),
+            AudioFixture::triad(440.0, true, 0.5),
+            AudioFixture::sweep(100.0, 4000.0, 0.5),
+            AudioFixture::white_noise(0.5, 42),
+        ] {
+            let fixture = fixture.with_sample_rate(22050).with_seconds(30.0);
+            let detected = estimate(&fixture.samples(), 22050);
+            assert!(detected.is_none(), "{fixture:?} detected at {detected:?}");
+        }
+    }
+}
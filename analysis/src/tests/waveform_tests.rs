@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use fsio::FsIo;
+
+    use crate::{
+        analysis::analyze_audio,
+        tests::audio_fixtures::AudioFixture,
+        utils::{
+            computing_device::ComputingDevice,
+            waveform::{WAVEFORM_PEAKS, Waveform, WaveformBuilder},
+        },
+    };
+
+    fn build(samples: impl IntoIterator<Item = f32>, peaks: usize) -> Waveform {
+        let mut builder = WaveformBuilder::new(peaks);
+        samples.into_iter().for_each(|x| builder.push(x));
+        builder.finish()
+    }
+
+    #[test]
+    fn test_waveform_peaks() {
+        // A ramp keeps the peaks in order whatever the length
+        for len in [10, 99, 100, 101, 1000, 12345] {
+            let waveform = build((0..len).map(|i| i as f32 / len as f32), 100);
+            assert_eq!(waveform.peaks.len(), len.min(100), "len: {len}");
+            assert_eq!(waveform.peaks[0].0, 0.0);
+            assert_eq!(
+                waveform.peaks.last().unwrap().1,
+                (len - 1) as f32 / len as f32
+            );
+            assert!(waveform.peaks.windows(2).all(|x| x[0].1 <= x[1].0));
+        }
+
+        // A spike shows up in the peak that covers it
+        let mut samples = vec![0.0; 10000];
+        samples[5000] = -0.8;
+        let waveform = build(samples, 100);
+        assert_eq!(waveform.peaks[50], (-0.8, 0.0));
+        assert_eq!(waveform.peaks[49], (0.0, 0.0));
+
+        assert!(build([], 100).peaks.is_empty());
+    }
+
+    #[test]
+    fn test_waveform_bytes() {
+        let waveform = build((0..5000).map(|i| (i as f32 / 100.0).sin()), WAVEFORM_PEAKS);
+        let bytes = waveform.to_bytes().unwrap();
+        assert!(bytes.len() < WAVEFORM_PEAKS * 2, "{} bytes", bytes.len());
+
+        let decoded = Waveform::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.peaks.len(), waveform.peaks.len());
+        for (x, y) in decoded.peaks.iter().zip(&waveform.peaks) {
+            assert!((x.0 - y.0).abs() < 0.01 && (x.1 - y.1).abs() < 0.01);
+        }
+
+        assert_eq!(Waveform::from_bytes(&[]).unwrap(), Waveform::default());
+        assert!(Waveform::from_bytes(&[0xff, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_sine_waveform() {
+        let wav = AudioFixture::sine(440.0, 0.5).write_temp_wav();
+        let result = analyze_audio(
+            &FsIo::new(),
+            wav.path(),
+            1024,
+            512,
+            ComputingDevice::Cpu,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+
+        let waveform = result.waveform.unwrap();
+        assert_eq!(waveform.peaks.len(), WAVEFORM_PEAKS);
+        for (min, max) in waveform.peaks {
+            assert!((min + 0.5).abs() < 0.01, "min: {min}");
+            assert!((max - 0.5).abs() < 0.01, "max: {max}");
+        }
+    }
+}
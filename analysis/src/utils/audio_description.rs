@@ -1,4 +1,7 @@
 use rustfft::num_complex::Complex;
+
+use crate::utils::{loudness::Loudness, waveform::Waveform};
+
 pub struct AudioDescription {
     pub sample_rate: u32,
     pub duration: f64,
@@ -7,6 +10,12 @@ pub struct AudioDescription {
     pub rms: f32,
     pub zcr: usize,
     pub energy: f32,
+    /// Mean crest factor of the windows, `None` from the legacy analyzers.
+    pub crest_factor: Option<f32>,
+    pub bpm: Option<f32>,
+    pub loudness: Option<Loudness>,
+    /// Peaks of the source, `None` from the legacy analyzers.
+    pub waveform: Option<Waveform>,
 }
 
 impl std::fmt::Debug for AudioDescription {
@@ -19,6 +28,13 @@ impl std::fmt::Debug for AudioDescription {
             .field("rms", &self.rms)
             .field("zcr", &self.zcr)
             .field("energy", &self.energy)
+            .field("crest_factor", &self.crest_factor)
+            .field("bpm", &self.bpm)
+            .field("loudness", &self.loudness)
+            .field(
+                "waveform_len",
+                &self.waveform.as_ref().map(|x| x.peaks.len()),
+            )
             .finish()
     }
 }
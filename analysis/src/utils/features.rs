@@ -54,6 +54,19 @@ pub fn energy(signal: &[f32]) -> f32 {
     energy
 }
 
+/// Ratio of the peak to the RMS of a signal, high for punchy, dynamic
+/// material and low for compressed material. Zero for silence.
+pub fn crest_factor(signal: &[f32]) -> f32 {
+    let rms = rms(signal);
+    if rms <= 0.0 {
+        return 0.0;
+    }
+
+    let peak = signal.iter().fold(0.0_f32, |peak, x| peak.max(x.abs()));
+
+    peak / rms
+}
+
 // Spectral Features
 
 pub fn amp_spectrum(complex_spectrum: &[Complex<f32>], buffer_size: usize) -> Vec<f32> {
@@ -149,6 +162,34 @@ pub fn spectral_kurtosis(amp_spectrum: &[f32]) -> f32 {
     numerator / denominator
 }
 
+/// Centre frequencies of the octave bands of [`octave_band_ratios`], the
+/// highest one still fits under the Nyquist frequency of the analysis rate.
+pub const OCTAVE_BAND_CENTERS: [f32; 7] = [62.5, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0];
+
+/// Share of the spectral energy in every octave band of
+/// [`OCTAVE_BAND_CENTERS`], summing to 1. A band spans half an octave on
+/// both sides of its centre, energy outside of the bands is left out. All
+/// zeros for a silent spectrum.
+pub fn octave_band_ratios(amp_spectrum: &[f32], sample_rate: f32, buffer_size: usize) -> [f32; 7] {
+    let mut bands = [0.0; 7];
+    for (k, amp) in amp_spectrum.iter().enumerate() {
+        let frequency = k as f32 * sample_rate / buffer_size as f32;
+        let band = OCTAVE_BAND_CENTERS.iter().position(|center| {
+            frequency >= center / f32::consts::SQRT_2 && frequency < center * f32::consts::SQRT_2
+        });
+        if let Some(band) = band {
+            bands[band] += amp.powi(2);
+        }
+    }
+
+    let total: f32 = bands.iter().sum();
+    if total > 0.0 {
+        bands.iter_mut().for_each(|x| *x /= total);
+    }
+
+    bands
+}
+
 pub fn chroma(amp_spectrum: &[f32], chroma_filter_bank: &[Vec<f32>]) -> Vec<f32> {
     let mut chromagram: Vec<f32> = chroma_filter_bank
         .iter()
@@ -0,0 +1,118 @@
+use std::fmt;
+
+/// Krumhansl-Kessler major key profile, starting from the tonic.
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+/// Krumhansl-Kessler minor key profile, starting from the tonic.
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMode {
+    Major,
+    Minor,
+}
+
+impl KeyMode {
+    pub fn to_i32(self) -> i32 {
+        match self {
+            KeyMode::Major => 0,
+            KeyMode::Minor => 1,
+        }
+    }
+
+    pub fn from_i32(x: i32) -> Option<Self> {
+        match x {
+            0 => Some(KeyMode::Major),
+            1 => Some(KeyMode::Minor),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyMode::Major => write!(f, "major"),
+            KeyMode::Minor => write!(f, "minor"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MusicalKey {
+    /// Pitch class of the tonic, `0` is C.
+    pub tonic: u8,
+    pub mode: KeyMode,
+    /// Correlation between the chroma vector and the key profile.
+    pub confidence: f32,
+}
+
+impl MusicalKey {
+    /// Rebuild a key from the stored pitch class and mode.
+    pub fn from_parts(tonic: i32, mode: i32) -> Option<Self> {
+        Some(MusicalKey {
+            tonic: u8::try_from(tonic).ok().filter(|x| *x < 12)?,
+            mode: KeyMode::from_i32(mode)?,
+            confidence: 0.0,
+        })
+    }
+}
+
+impl fmt::Display for MusicalKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", PITCH_CLASSES[self.tonic as usize], self.mode)
+    }
+}
+
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / 12.0;
+    let mean_b = b.iter().sum::<f32>() / 12.0;
+
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+
+    let denominator = (variance_a * variance_b).sqrt();
+    if denominator <= f32::EPSILON {
+        0.0
+    } else {
+        covariance / denominator
+    }
+}
+
+/// Estimate the key of a chroma vector (index `0` is C) by correlating it
+/// with the Krumhansl-Kessler profiles of all 24 keys. `None` if the chroma
+/// vector is flat.
+pub fn estimate_key(chroma: &[f32; 12]) -> Option<MusicalKey> {
+    let mut best: Option<MusicalKey> = None;
+
+    for (mode, profile) in [
+        (KeyMode::Major, &MAJOR_PROFILE),
+        (KeyMode::Minor, &MINOR_PROFILE),
+    ] {
+        for tonic in 0..12 {
+            let rotated: [f32; 12] = std::array::from_fn(|i| profile[(i + 12 - tonic) % 12]);
+            let confidence = correlation(chroma, &rotated);
+
+            if best.is_none_or(|x| confidence > x.confidence) {
+                best = Some(MusicalKey {
+                    tonic: tonic as u8,
+                    mode,
+                    confidence,
+                });
+            }
+        }
+    }
+
+    best.filter(|x| x.confidence > 0.0)
+}
@@ -0,0 +1,262 @@
+use std::f64::consts::PI;
+
+/// Length of a gating step, blocks are built from consecutive steps.
+const STEP_SECONDS: f64 = 0.1;
+/// Momentary block, 400ms with 75% overlap.
+const BLOCK_STEPS: usize = 4;
+/// Short-term block used by the loudness range, 3s.
+const SHORT_TERM_STEPS: usize = 30;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+
+/// Oversampling factor of the true peak meter.
+const OVERSAMPLING: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+
+/// Loudness of a track as specified by EBU R128.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS, `None` if everything is below the
+    /// absolute gate.
+    pub integrated: Option<f32>,
+    /// Maximum true peak in dBTP.
+    pub true_peak: f32,
+    /// Loudness range in LU.
+    pub range: f32,
+}
+
+/// Second order IIR section, transposed direct form II.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad { b, a, z: [0.0; 2] }
+    }
+
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The two stages of the ITU-R BS.1770 K-weighting filter, derived for the
+/// given sample rate.
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    // High shelf modelling the acoustic effect of the head
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // RLB high pass
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// Polyphase coefficients of a windowed sinc interpolator, indexed by phase.
+fn interpolation_filter() -> Vec<[f64; TAPS_PER_PHASE]> {
+    let taps = OVERSAMPLING * TAPS_PER_PHASE;
+    let center = (taps - 1) as f64 / 2.0;
+
+    let coefficient = |n: usize| {
+        let t = (n as f64 - center) / OVERSAMPLING as f64;
+        let sinc = if t.abs() < 1e-9 {
+            1.0
+        } else {
+            (PI * t).sin() / (PI * t)
+        };
+        let window = 0.5 - 0.5 * (2.0 * PI * n as f64 / (taps - 1) as f64).cos();
+        sinc * window
+    };
+
+    (0..OVERSAMPLING)
+        .map(|phase| std::array::from_fn(|k| coefficient(phase + k * OVERSAMPLING)))
+        .collect()
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(1e-20).log10()
+}
+
+struct ChannelState {
+    filters: [Biquad; 2],
+    weight: f64,
+    history: [f64; TAPS_PER_PHASE],
+    history_index: usize,
+}
+
+/// Streaming EBU R128 loudness meter.
+pub struct LoudnessMeter {
+    channels: Vec<ChannelState>,
+    interpolation: Vec<[f64; TAPS_PER_PHASE]>,
+    step_size: usize,
+    step_fill: usize,
+    step_energy: f64,
+    /// Channel weighted mean square of each finished step.
+    steps: Vec<f64>,
+    peak: f64,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = (0..channels.max(1))
+            .map(|index| ChannelState {
+                filters: k_weighting(sample_rate as f64),
+                // Surround channels of a 5.1 layout are weighted up, the LFE
+                // channel is left out
+                weight: match (channels, index) {
+                    (6, 3) => 0.0,
+                    (6, 4) | (6, 5) => 1.41,
+                    _ => 1.0,
+                },
+                history: [0.0; TAPS_PER_PHASE],
+                history_index: 0,
+            })
+            .collect();
+
+        LoudnessMeter {
+            channels,
+            interpolation: interpolation_filter(),
+            step_size: ((sample_rate as f64 * STEP_SECONDS).round() as usize).max(1),
+            step_fill: 0,
+            step_energy: 0.0,
+            steps: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Push one frame of interleaved samples, one per channel.
+    pub fn push_frame(&mut self, frame: &[f32]) {
+        for (channel, &sample) in self.channels.iter_mut().zip(frame) {
+            let x = sample as f64;
+
+            let y = channel
+                .filters
+                .iter_mut()
+                .fold(x, |value, filter| filter.process(value));
+            self.step_energy += channel.weight * y * y;
+
+            self.peak = self.peak.max(x.abs());
+
+            channel.history[channel.history_index] = x;
+            channel.history_index = (channel.history_index + 1) % TAPS_PER_PHASE;
+            for phase in &self.interpolation {
+                let interpolated: f64 = phase
+                    .iter()
+                    .enumerate()
+                    .map(|(k, h)| {
+                        let index =
+                            (channel.history_index + TAPS_PER_PHASE - 1 - k) % TAPS_PER_PHASE;
+                        h * channel.history[index]
+                    })
+                    .sum();
+                self.peak = self.peak.max(interpolated.abs());
+            }
+        }
+
+        self.step_fill += 1;
+        if self.step_fill >= self.step_size {
+            self.steps.push(self.step_energy / self.step_size as f64);
+            self.step_energy = 0.0;
+            self.step_fill = 0;
+        }
+    }
+
+    /// Mean power of every window of `size` consecutive steps.
+    fn blocks(&self, size: usize) -> Vec<f64> {
+        self.steps
+            .windows(size)
+            .map(|x| x.iter().sum::<f64>() / size as f64)
+            .collect()
+    }
+
+    fn integrated(&self) -> Option<f64> {
+        let blocks: Vec<f64> = self
+            .blocks(BLOCK_STEPS)
+            .into_iter()
+            .filter(|x| power_to_lufs(*x) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let relative_gate =
+            power_to_lufs(blocks.iter().sum::<f64>() / blocks.len() as f64) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|x| power_to_lufs(*x) > relative_gate)
+            .collect();
+        if gated.is_empty() {
+            return None;
+        }
+
+        Some(power_to_lufs(
+            gated.iter().sum::<f64>() / gated.len() as f64,
+        ))
+    }
+
+    fn range(&self) -> f64 {
+        let blocks: Vec<f64> = self
+            .blocks(SHORT_TERM_STEPS)
+            .into_iter()
+            .filter(|x| power_to_lufs(*x) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return 0.0;
+        }
+
+        let relative_gate = power_to_lufs(blocks.iter().sum::<f64>() / blocks.len() as f64)
+            + RANGE_RELATIVE_GATE_LU;
+        let mut loudness: Vec<f64> = blocks
+            .into_iter()
+            .map(power_to_lufs)
+            .filter(|x| *x > relative_gate)
+            .collect();
+        if loudness.len() < 2 {
+            return 0.0;
+        }
+
+        loudness.sort_by(f64::total_cmp);
+        let percentile = |p: f64| loudness[((loudness.len() - 1) as f64 * p).round() as usize];
+        percentile(0.95) - percentile(0.10)
+    }
+
+    pub fn finish(&self) -> Loudness {
+        Loudness {
+            integrated: self.integrated().map(|x| x as f32),
+            true_peak: (20.0 * self.peak.max(1e-10).log10()) as f32,
+            range: self.range() as f32,
+        }
+    }
+}
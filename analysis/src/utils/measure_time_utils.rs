@@ -12,7 +12,7 @@ macro_rules! measure_time {
         let start = std::time::Instant::now();
         let result = $func;
         let duration = start.elapsed();
-        log::debug!("{} time ost: {:?}", $name, duration);
+        log::debug!("{} time cost: {:?}", $name, duration);
         result
     }};
 
@@ -0,0 +1,154 @@
+/// Length of one onset envelope frame in seconds.
+const FRAME_SECONDS: f32 = 0.01;
+/// Only the beginning of a track is used, tempo rarely changes enough
+/// afterwards to be worth the memory.
+const MAX_ANALYZED_SECONDS: f32 = 240.0;
+/// Tracks shorter than this do not contain enough beats to be reliable.
+const MIN_ANALYZED_SECONDS: f32 = 6.0;
+
+const MIN_BPM: f32 = 60.0;
+const MAX_BPM: f32 = 200.0;
+/// Center of the tempo prior, estimations are biased towards it to avoid
+/// picking half or double of the perceived tempo.
+const PRIOR_BPM: f32 = 120.0;
+/// Width of the tempo prior, in octaves.
+const PRIOR_WIDTH: f32 = 1.0;
+/// Share of the onset envelope variance that has to repeat at the beat
+/// period, noise and sweeps stay well below it.
+const MIN_PERIODICITY: f32 = 0.2;
+/// How many times the autocorrelation at the beat period has to exceed
+/// its median over the tempo range. Steady tones correlate at every lag
+/// and never stand out this far.
+const MIN_PROMINENCE: f32 = 4.0;
+
+/// Streaming tempo estimator.
+///
+/// Mono samples are reduced to an onset strength envelope (the half-wave
+/// rectified difference of the log energy of consecutive 10ms frames), whose
+/// autocorrelation peaks at the beat period.
+pub struct TempoTracker {
+    frame_size: usize,
+    frame_rate: f32,
+    frame_energy: f32,
+    frame_fill: usize,
+    previous_log_energy: Option<f32>,
+    max_frames: usize,
+    onsets: Vec<f32>,
+}
+
+impl TempoTracker {
+    pub fn new(sample_rate: u32) -> Self {
+        let frame_size = ((sample_rate as f32 * FRAME_SECONDS).round() as usize).max(1);
+
+        TempoTracker {
+            frame_size,
+            frame_rate: sample_rate as f32 / frame_size as f32,
+            frame_energy: 0.0,
+            frame_fill: 0,
+            previous_log_energy: None,
+            max_frames: (MAX_ANALYZED_SECONDS / FRAME_SECONDS) as usize,
+            onsets: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, sample: f32) {
+        if self.onsets.len() >= self.max_frames {
+            return;
+        }
+
+        self.frame_energy += sample * sample;
+        self.frame_fill += 1;
+
+        if self.frame_fill < self.frame_size {
+            return;
+        }
+
+        let log_energy = (self.frame_energy / self.frame_size as f32 + 1e-10).ln();
+        if let Some(previous) = self.previous_log_energy {
+            self.onsets.push((log_energy - previous).max(0.0));
+        }
+
+        self.previous_log_energy = Some(log_energy);
+        self.frame_energy = 0.0;
+        self.frame_fill = 0;
+    }
+
+    /// Estimate the tempo in beats per minute, `None` if the audio is too
+    /// short or has no steady beat.
+    pub fn estimate(&self) -> Option<f32> {
+        let frame_rate = self.frame_rate;
+        if (self.onsets.len() as f32) < MIN_ANALYZED_SECONDS * frame_rate {
+            return None;
+        }
+
+        let mean = self.onsets.iter().sum::<f32>() / self.onsets.len() as f32;
+        let envelope: Vec<f32> = self.onsets.iter().map(|x| x - mean).collect();
+
+        let autocorrelation = |lag: usize| -> f32 {
+            if lag >= envelope.len() {
+                return 0.0;
+            }
+
+            envelope[lag..]
+                .iter()
+                .zip(&envelope)
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+                / (envelope.len() - lag) as f32
+        };
+
+        let zero_lag = autocorrelation(0);
+        if zero_lag <= f32::EPSILON {
+            return None;
+        }
+
+        let min_lag = (60.0 * frame_rate / MAX_BPM).floor() as usize;
+        let max_lag = (60.0 * frame_rate / MIN_BPM).ceil() as usize;
+
+        // Reinforce each period with its double so that the beat wins over
+        // off-beat subdivisions, then weight by the tempo prior
+        let scores: Vec<f32> = (min_lag.saturating_sub(1)..=max_lag + 1)
+            .map(|lag| {
+                if lag == 0 {
+                    return 0.0;
+                }
+
+                let bpm = 60.0 * frame_rate / lag as f32;
+                let prior = (-0.5 * ((bpm / PRIOR_BPM).log2() / PRIOR_WIDTH).powi(2)).exp();
+                (autocorrelation(lag) + 0.5 * autocorrelation(lag * 2)) * prior
+            })
+            .collect();
+
+        let (best, &best_score) = scores
+            .iter()
+            .enumerate()
+            .skip(1)
+            .take(scores.len() - 2)
+            .max_by(|a, b| a.1.total_cmp(b.1))?;
+
+        // Any signal has some autocorrelation peak, only a clear one is a beat
+        let peak = autocorrelation(min_lag.saturating_sub(1) + best);
+        let mut magnitudes: Vec<f32> = (min_lag..=max_lag)
+            .map(|lag| autocorrelation(lag).abs())
+            .collect();
+        magnitudes.sort_by(f32::total_cmp);
+        let median = magnitudes[magnitudes.len() / 2];
+        if best_score <= 0.0 || peak < MIN_PERIODICITY * zero_lag || peak < MIN_PROMINENCE * median
+        {
+            return None;
+        }
+
+        // Parabolic interpolation around the peak for sub-frame precision
+        let (left, right) = (scores[best - 1], scores[best + 1]);
+        let denominator = left - 2.0 * best_score + right;
+        let offset = if denominator.abs() > f32::EPSILON {
+            (0.5 * (left - right) / denominator).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+
+        let lag = (min_lag.saturating_sub(1) + best) as f32 + offset;
+        Some(60.0 * frame_rate / lag)
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{Read, Write};
+
+use anyhow::{Result, bail};
+use flate2::{Compression, read::DeflateDecoder, write::DeflateEncoder};
+
+/// Number of peaks kept per track.
+pub const WAVEFORM_PEAKS: usize = 1000;
+
+/// Version of the layout written by [`Waveform::to_bytes`].
+const WAVEFORM_FORMAT_VERSION: u8 = 1;
+
+/// The lowest and the highest sample of every slice of a track, enough to
+/// draw its waveform without decoding it again.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Waveform {
+    /// `(min, max)` pairs between -1 and 1, in the order of the track.
+    pub peaks: Vec<(f32, f32)>,
+}
+
+fn merge_peaks(peaks: &[(f32, f32)]) -> (f32, f32) {
+    peaks
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), (x, y)| {
+            (min.min(*x), max.max(*y))
+        })
+}
+
+fn quantize(x: f32) -> u8 {
+    (x.clamp(-1.0, 1.0) * 127.0).round() as i8 as u8
+}
+
+fn dequantize(x: u8) -> f32 {
+    x as i8 as f32 / 127.0
+}
+
+impl Waveform {
+    /// Quantize every peak to a byte and deflate them.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut encoder = DeflateEncoder::new(vec![WAVEFORM_FORMAT_VERSION], Compression::best());
+        for (min, max) in &self.peaks {
+            encoder.write_all(&[quantize(*min), quantize(*max)])?;
+        }
+
+        Ok(encoder.finish()?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some((&version, compressed)) = bytes.split_first() else {
+            return Ok(Waveform::default());
+        };
+        if version != WAVEFORM_FORMAT_VERSION {
+            bail!("Unknown waveform format: {version}");
+        }
+
+        let mut raw = vec![];
+        DeflateDecoder::new(compressed).read_to_end(&mut raw)?;
+
+        Ok(Waveform {
+            peaks: raw
+                .chunks_exact(2)
+                .map(|x| (dequantize(x[0]), dequantize(x[1])))
+                .collect(),
+        })
+    }
+}
+
+/// Collects the peaks of a track while it is decoded. The length of the
+/// track isn't trusted, instead every pair of peaks is merged whenever
+/// there are twice as many as needed, and the rest are merged down to the
+/// wanted number at the end.
+pub struct WaveformBuilder {
+    peaks_wanted: usize,
+    /// Samples covered by every peak.
+    samples_per_peak: usize,
+    /// Samples in `current` so far.
+    pending: usize,
+    current: (f32, f32),
+    peaks: Vec<(f32, f32)>,
+}
+
+impl WaveformBuilder {
+    pub fn new(peaks_wanted: usize) -> Self {
+        WaveformBuilder {
+            peaks_wanted: peaks_wanted.max(1),
+            samples_per_peak: 1,
+            pending: 0,
+            current: (0.0, 0.0),
+            peaks: Vec::with_capacity(peaks_wanted * 2),
+        }
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        self.current = if self.pending == 0 {
+            (sample, sample)
+        } else {
+            (self.current.0.min(sample), self.current.1.max(sample))
+        };
+        self.pending += 1;
+
+        if self.pending < self.samples_per_peak {
+            return;
+        }
+
+        self.peaks.push(self.current);
+        self.pending = 0;
+        if self.peaks.len() >= self.peaks_wanted * 2 {
+            self.peaks = self.peaks.chunks(2).map(merge_peaks).collect();
+            self.samples_per_peak *= 2;
+        }
+    }
+
+    pub fn finish(mut self) -> Waveform {
+        if self.pending > 0 {
+            self.peaks.push(self.current);
+        }
+
+        let len = self.peaks.len();
+        if len <= self.peaks_wanted {
+            return Waveform { peaks: self.peaks };
+        }
+
+        Waveform {
+            peaks: (0..self.peaks_wanted)
+                .map(|i| {
+                    let start = i * len / self.peaks_wanted;
+                    let end = (i + 1) * len / self.peaks_wanted;
+                    merge_peaks(&self.peaks[start..end])
+                })
+                .collect(),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::{path::PathBuf, sync::Arc};
 
 use fsio::FsIo;
 use log::{error, info};
+use metadata::backend::MetadataBackends;
 use tracing_subscriber::filter::EnvFilter;
 
 use analysis::utils::computing_device::ComputingDevice;
@@ -40,6 +41,7 @@ async fn main() {
     // Scan the audio library
     let _ = scan_audio_library(
         &fsio,
+        &MetadataBackends::default(),
         &main_db,
         &root_path,
         true,
@@ -60,6 +62,7 @@ async fn main() {
         ComputingDevice::Gpu,
         empty_analysis_progress_callback,
         None,
+        None,
     )
     .await
     .expect("Audio analysis failed");
@@ -39,7 +39,7 @@ async fn main() {
 
             println!("= {}", to_unix_path_string(d.actual_path.clone()).unwrap());
             println!("|- Description");
-            println!("|  |- Hash: {}", d.get_crc(&fsio).unwrap());
+            println!("|  |- Hash: {}", d.get_hash(&fsio).unwrap());
             println!("|  |- Last Modified: {}", d.last_modified);
             println!("|- Metadata");
         }
@@ -5,6 +5,7 @@ use database::{
     connection::connect_main_db,
 };
 use fsio::FsIo;
+use metadata::backend::MetadataBackends;
 
 #[tokio::main]
 async fn main() {
@@ -20,6 +21,7 @@ async fn main() {
 
     let _ = scan_audio_library(
         &fsio,
+        &MetadataBackends::default(),
         &main_db,
         &root_path,
         true,
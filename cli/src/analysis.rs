@@ -1,12 +1,45 @@
 use std::path::Path;
 use std::sync::Arc;
 
+use log::info;
+
 use analysis::utils::computing_device::ComputingDevice;
-use database::actions::analysis::{analysis_audio_library, empty_progress_callback};
+use database::actions::analysis::{analysis_audio_library, analyze_album, analyze_files};
+use database::actions::mixes::query_mix_media_files;
 use database::actions::recommendation::sync_recommendation;
 use database::connection::{MainDbConnection, RecommendationDbConnection};
+use database::progress::ProgressEta;
 use fsio::FsIo;
 
+use crate::format::format_duration;
+
+/// Items processed between two progress logs.
+const PROGRESS_LOG_INTERVAL: usize = 100;
+
+/// Files looked up at once when resolving a playlist or a folder.
+const SELECTION_PAGE_SIZE: usize = 1000;
+
+/// A progress callback logging how far `task` got, and how long it will
+/// probably take to finish.
+pub fn log_progress(task: &'static str) -> impl Fn(usize, usize) + Send + Sync + 'static {
+    let eta = ProgressEta::new();
+
+    move |processed, total| {
+        let left = eta.update(processed, total);
+        if processed % PROGRESS_LOG_INTERVAL != 0 && processed != total {
+            return;
+        }
+
+        match left {
+            Some(left) => info!(
+                "{task}: {processed}/{total}, about {} left",
+                format_duration(left.as_secs_f64())
+            ),
+            None => info!("{task}: {processed}/{total}"),
+        }
+    }
+}
+
 pub async fn analyze_audio_library(
     computing_device: ComputingDevice,
     fsio: Arc<FsIo>,
@@ -14,15 +47,17 @@ pub async fn analyze_audio_library(
     analysis_db: &RecommendationDbConnection,
     path: &Path,
     node_id: &str,
+    batch_size: usize,
 ) {
     if let Err(e) = analysis_audio_library(
         fsio,
         main_db,
         path,
         node_id,
-        15,
+        batch_size,
         computing_device,
-        empty_progress_callback,
+        log_progress("Analysis"),
+        None,
         None,
     )
     .await
@@ -42,3 +77,129 @@ pub async fn analyze_audio_library(
 
     println!("Audio analysis completed successfully");
 }
+
+/// Files of the library to analyze before the rest of it.
+#[derive(Debug, Clone)]
+pub enum AnalysisSelection {
+    Files(Vec<i32>),
+    Album(i32),
+    Playlist(i32),
+    /// A folder relative to the library, its subfolders included.
+    Directory(String),
+}
+
+/// The files matching a mix query, in the order of the mix.
+async fn query_file_ids(
+    main_db: &MainDbConnection,
+    analysis_db: &RecommendationDbConnection,
+    query: (String, String),
+) -> anyhow::Result<Vec<i32>> {
+    let mut file_ids = Vec::new();
+    loop {
+        let page = query_mix_media_files(
+            main_db,
+            analysis_db,
+            vec![query.clone()],
+            file_ids.len(),
+            SELECTION_PAGE_SIZE,
+        )
+        .await?;
+        let last_page = page.len() < SELECTION_PAGE_SIZE;
+        file_ids.extend(page.into_iter().map(|x| x.id));
+
+        if last_page {
+            return Ok(file_ids);
+        }
+    }
+}
+
+async fn report_selection_analysis(
+    main_db: &MainDbConnection,
+    analysis_db: &RecommendationDbConnection,
+    result: anyhow::Result<usize>,
+) {
+    match result {
+        Ok(0) => println!("Everything selected is already analyzed"),
+        Ok(analyzed) => {
+            println!("Analyzed {analyzed} files");
+
+            if let Err(e) = sync_recommendation(main_db, analysis_db).await {
+                eprintln!("Sync recommendation failed: {e}");
+            }
+        }
+        Err(e) => eprintln!("Audio analysis failed: {e}"),
+    }
+}
+
+/// Analyze the files of an album, a playlist or a folder that aren't
+/// analyzed yet, instead of waiting for the library run to get to them.
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_selection(
+    computing_device: ComputingDevice,
+    fsio: Arc<FsIo>,
+    main_db: &MainDbConnection,
+    analysis_db: &RecommendationDbConnection,
+    path: &Path,
+    node_id: &str,
+    batch_size: usize,
+    selection: AnalysisSelection,
+) {
+    let file_ids = match selection {
+        AnalysisSelection::Album(album_id) => {
+            let result = analyze_album(
+                fsio,
+                main_db,
+                path,
+                node_id,
+                album_id,
+                batch_size,
+                computing_device,
+                log_progress("Analysis"),
+                None,
+                None,
+            )
+            .await;
+            return report_selection_analysis(main_db, analysis_db, result).await;
+        }
+        AnalysisSelection::Files(file_ids) => Ok(file_ids),
+        AnalysisSelection::Playlist(id) => {
+            query_file_ids(
+                main_db,
+                analysis_db,
+                ("lib::playlist".to_string(), id.to_string()),
+            )
+            .await
+        }
+        AnalysisSelection::Directory(directory) => {
+            query_file_ids(
+                main_db,
+                analysis_db,
+                ("lib::directory.deep".to_string(), directory),
+            )
+            .await
+        }
+    };
+
+    let file_ids = match file_ids {
+        Ok(file_ids) => file_ids,
+        Err(e) => {
+            eprintln!("Failed to find the selected files: {e}");
+            return;
+        }
+    };
+
+    let result = analyze_files(
+        fsio,
+        main_db,
+        path,
+        node_id,
+        &file_ids,
+        batch_size,
+        computing_device,
+        log_progress("Analysis"),
+        None,
+        None,
+    )
+    .await;
+    report_selection_analysis(main_db, analysis_db, result).await;
+}
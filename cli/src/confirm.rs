@@ -0,0 +1,54 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use log::error;
+
+/// Decides whether a command may delete or rewrite files and records.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Confirm {
+    /// Go ahead without asking, for scripts
+    pub assume_yes: bool,
+    /// Refuse every destructive action
+    pub read_only: bool,
+}
+
+impl Confirm {
+    /// Whether `action` may run at all, refused with `--read-only`.
+    pub fn allows(&self, action: &str) -> bool {
+        if self.read_only {
+            error!("Refusing to {action} in read-only mode");
+            return false;
+        }
+
+        true
+    }
+
+    /// Print `summary`, the counts of what is about to change, and ask
+    /// whether to go ahead. Without a terminal to ask on, only `--yes` lets
+    /// the action run.
+    pub fn confirm(&self, action: &str, summary: &str) -> bool {
+        if !self.allows(action) {
+            return false;
+        }
+
+        println!("{summary}");
+        if self.assume_yes {
+            return true;
+        }
+        if !io::stdin().is_terminal() {
+            error!("Refusing to {action} without confirmation, pass --yes to go ahead");
+            return false;
+        }
+
+        print!("Continue? [y/N]: ");
+        if io::stdout().flush().is_err() {
+            return false;
+        }
+
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer).is_err() {
+            return false;
+        }
+
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    }
+}
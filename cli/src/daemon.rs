@@ -0,0 +1,597 @@
+//! `rune daemon` plays the library without the app, taking commands from
+//! `rune ctl` or any other program over a local socket, like mpd.
+//!
+//! Every command is one line of text, like `enqueue 12 34` or `status`,
+//! and gets one line of JSON back. Arguments with whitespace are wrapped in
+//! double quotes, like `enqueue "Some Artist/track.flac"`, with `\"` and
+//! `\\` escaping quotes and backslashes inside them. The socket is a Unix domain socket, or a
+//! named pipe on Windows.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_util::sync::CancellationToken;
+
+use database::{
+    actions::{
+        file::{get_file_by_id, get_file_id_from_path},
+        metadata::get_metadata_summary_by_file_ids,
+        settings::{OUTPUT_DEVICE_KEY, get_setting},
+    },
+    connection::MainDbConnection,
+};
+use playback::{
+    player::{Playable, PlaybackState, Player, PlayingItem},
+    strategies::AddMode,
+};
+
+use crate::format::format_duration;
+
+/// A command sent to the daemon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaemonCommand {
+    Play,
+    Pause,
+    /// Pause while playing, play otherwise.
+    Toggle,
+    Next,
+    Previous,
+    Stop,
+    /// Append tracks to the queue, by file ID or by path.
+    Enqueue(Vec<String>),
+    Clear,
+    Status,
+}
+
+impl DaemonCommand {
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = split_arguments(line)?.into_iter();
+        let Some(name) = words.next() else {
+            bail!("Empty command");
+        };
+
+        let command = match name {
+            "play" => DaemonCommand::Play,
+            "pause" => DaemonCommand::Pause,
+            "toggle" => DaemonCommand::Toggle,
+            "next" => DaemonCommand::Next,
+            "previous" => DaemonCommand::Previous,
+            "stop" => DaemonCommand::Stop,
+            "clear" => DaemonCommand::Clear,
+            "status" => DaemonCommand::Status,
+            "enqueue" => {
+                let tracks: Vec<String> = words.collect();
+                if tracks.is_empty() {
+                    bail!("Nothing to enqueue");
+                }
+                return Ok(DaemonCommand::Enqueue(tracks));
+            }
+            _ => bail!("Unknown command: {name}"),
+        };
+
+        if words.next().is_some() {
+            bail!("Unexpected arguments for {name}");
+        }
+        Ok(command)
+    }
+
+    /// The line sent for the command.
+    pub fn to_line(&self) -> String {
+        match self {
+            DaemonCommand::Play => "play".to_owned(),
+            DaemonCommand::Pause => "pause".to_owned(),
+            DaemonCommand::Toggle => "toggle".to_owned(),
+            DaemonCommand::Next => "next".to_owned(),
+            DaemonCommand::Previous => "previous".to_owned(),
+            DaemonCommand::Stop => "stop".to_owned(),
+            DaemonCommand::Enqueue(tracks) => {
+                let tracks: Vec<String> = tracks.iter().map(|x| quote_argument(x)).collect();
+                format!("enqueue {}", tracks.join(" "))
+            }
+            DaemonCommand::Clear => "clear".to_owned(),
+            DaemonCommand::Status => "status".to_owned(),
+        }
+    }
+}
+
+/// Split a command line at whitespace, keeping quoted arguments whole.
+fn split_arguments(line: &str) -> Result<Vec<String>> {
+    let mut arguments = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|x| x.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Ok(arguments);
+        };
+
+        let mut argument = String::new();
+        if first == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(x) => argument.push(x),
+                        None => bail!("Unterminated quote"),
+                    },
+                    Some(x) => argument.push(x),
+                    None => bail!("Unterminated quote"),
+                }
+            }
+            if chars.peek().is_some_and(|x| !x.is_whitespace()) {
+                bail!("Missing space after a quoted argument");
+            }
+        } else {
+            while let Some(x) = chars.next_if(|x| !x.is_whitespace()) {
+                argument.push(x);
+            }
+        }
+        arguments.push(argument);
+    }
+}
+
+/// Quote an argument if [`split_arguments`] would otherwise break it up.
+fn quote_argument(argument: &str) -> String {
+    if !argument.is_empty() && !argument.starts_with('"') && !argument.contains(char::is_whitespace)
+    {
+        return argument.to_owned();
+    }
+
+    let escaped = argument.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackStatus {
+    pub file_id: i32,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// Duration in seconds.
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    /// `Playing`, `Paused` or `Stopped`.
+    pub state: String,
+    /// Position in the current track, in seconds.
+    pub position: f64,
+    /// Index of the current track in the queue.
+    pub index: Option<usize>,
+    pub queue_length: usize,
+    pub track: Option<TrackStatus>,
+}
+
+impl DaemonStatus {
+    /// A line like `Playing: Title - Artist (1:02 / 3:45) [2/10]`.
+    pub fn summary(&self) -> String {
+        let Some(track) = &self.track else {
+            return format!("{} [{} queued]", self.state, self.queue_length);
+        };
+
+        format!(
+            "{}: {} - {} ({} / {}) [{}/{}]",
+            self.state,
+            track.title,
+            track.artist,
+            format_duration(self.position),
+            format_duration(track.duration),
+            self.index.map_or(0, |x| x + 1),
+            self.queue_length
+        )
+    }
+}
+
+/// The answer to every command, `status` is set for the `status` command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonReply {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<DaemonStatus>,
+}
+
+impl DaemonReply {
+    fn done() -> Self {
+        DaemonReply {
+            ok: true,
+            error: None,
+            status: None,
+        }
+    }
+
+    fn failed(e: anyhow::Error) -> Self {
+        DaemonReply {
+            ok: false,
+            error: Some(format!("{e:#}")),
+            status: None,
+        }
+    }
+}
+
+/// The socket the daemon of a library listens on, unless another one is
+/// given.
+#[cfg(unix)]
+pub fn default_socket_path(lib_path: &Path) -> PathBuf {
+    lib_path.join(".rune").join("daemon.sock")
+}
+
+#[cfg(windows)]
+pub fn default_socket_path(_lib_path: &Path) -> PathBuf {
+    PathBuf::from(r"\\.\pipe\rune-daemon")
+}
+
+struct Daemon {
+    main_db: MainDbConnection,
+    lib_path: PathBuf,
+    player: Mutex<Player>,
+}
+
+impl Daemon {
+    async fn handle(&self, command: DaemonCommand) -> Result<DaemonReply> {
+        match command {
+            DaemonCommand::Play => self.player.lock().unwrap().play(),
+            DaemonCommand::Pause => self.player.lock().unwrap().pause(),
+            DaemonCommand::Toggle => {
+                let player = self.player.lock().unwrap();
+                if player.get_status().state == PlaybackState::Playing {
+                    player.pause();
+                } else {
+                    player.play();
+                }
+            }
+            DaemonCommand::Next => self.player.lock().unwrap().next(),
+            DaemonCommand::Previous => self.player.lock().unwrap().previous(),
+            DaemonCommand::Stop => self.player.lock().unwrap().stop(),
+            DaemonCommand::Clear => self.player.lock().unwrap().clear_playlist(),
+            DaemonCommand::Enqueue(tracks) => self.enqueue(&tracks).await?,
+            DaemonCommand::Status => {
+                return Ok(DaemonReply {
+                    status: Some(self.status().await?),
+                    ..DaemonReply::done()
+                });
+            }
+        }
+
+        Ok(DaemonReply::done())
+    }
+
+    async fn enqueue(&self, tracks: &[String]) -> Result<()> {
+        let mut items = Vec::with_capacity(tracks.len());
+        for track in tracks {
+            let file_id = match track.parse::<i32>() {
+                Ok(file_id) => file_id,
+                Err(_) => get_file_id_from_path(&self.main_db, &self.lib_path, Path::new(track))
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e))?,
+            };
+            let file = get_file_by_id(&self.main_db, file_id)
+                .await?
+                .with_context(|| format!("File not found: {file_id}"))?;
+            let path =
+                dunce::canonicalize(self.lib_path.join(&file.directory).join(&file.file_name))
+                    .with_context(|| format!("File not reachable: {file_id}"))?;
+
+            items.push((PlayingItem::InLibrary(file_id), path));
+        }
+
+        info!("Enqueuing {} tracks", items.len());
+        self.player
+            .lock()
+            .unwrap()
+            .add_to_playlist(items, AddMode::AppendToEnd);
+        Ok(())
+    }
+
+    async fn status(&self) -> Result<DaemonStatus> {
+        let status = self.player.lock().unwrap().get_status();
+
+        let track = match status.item {
+            Some(PlayingItem::InLibrary(file_id)) => {
+                get_metadata_summary_by_file_ids(&self.main_db, vec![file_id])
+                    .await?
+                    .into_iter()
+                    .next()
+                    .map(|x| TrackStatus {
+                        file_id,
+                        title: x.title,
+                        artist: x.artist,
+                        album: x.album,
+                        duration: x.duration,
+                    })
+            }
+            _ => None,
+        };
+
+        Ok(DaemonStatus {
+            state: status.state.to_string(),
+            position: status.position.as_secs_f64(),
+            index: status.index,
+            queue_length: status.playlist.len(),
+            track,
+        })
+    }
+}
+
+/// Answer the commands of one client until it disconnects.
+async fn serve_client<S>(daemon: Arc<Daemon>, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Failed to read a daemon command: {e}");
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match DaemonCommand::parse(&line) {
+            Ok(command) => daemon
+                .handle(command)
+                .await
+                .unwrap_or_else(DaemonReply::failed),
+            Err(e) => DaemonReply::failed(e),
+        };
+
+        let mut reply = match serde_json::to_string(&reply) {
+            Ok(reply) => reply,
+            Err(e) => {
+                error!("Failed to encode a daemon reply: {e}");
+                break;
+            }
+        };
+        reply.push('\n');
+        if let Err(e) = writer.write_all(reply.as_bytes()).await {
+            warn!("Failed to send a daemon reply: {e}");
+            break;
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn accept_clients(
+    daemon: Arc<Daemon>,
+    socket: &Path,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    use tokio::net::{UnixListener, UnixStream};
+
+    if socket.exists() {
+        // Left behind by a daemon that didn't shut down cleanly
+        if UnixStream::connect(socket).await.is_ok() {
+            bail!("Another daemon is listening on {}", socket.display());
+        }
+        std::fs::remove_file(socket)
+            .with_context(|| format!("Failed to remove stale socket: {}", socket.display()))?;
+    } else if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+    info!("Daemon listening on {}", socket.display());
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = cancel_token.cancelled() => break,
+        };
+
+        match accepted {
+            Ok((stream, _)) => {
+                tokio::spawn(serve_client(Arc::clone(&daemon), stream));
+            }
+            Err(e) => error!("Failed to accept a daemon client: {e}"),
+        }
+    }
+
+    let _ = std::fs::remove_file(socket);
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn accept_clients(
+    daemon: Arc<Daemon>,
+    socket: &Path,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(socket)
+        .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+    info!("Daemon listening on {}", socket.display());
+
+    loop {
+        let connected = tokio::select! {
+            connected = server.connect() => connected,
+            _ = cancel_token.cancelled() => break,
+        };
+        if let Err(e) = connected {
+            error!("Failed to accept a daemon client: {e}");
+            continue;
+        }
+
+        // A pipe instance serves one client, the next one waits on a new one
+        let next = ServerOptions::new()
+            .create(socket)
+            .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+        let client = std::mem::replace(&mut server, next);
+        tokio::spawn(serve_client(Arc::clone(&daemon), client));
+    }
+
+    Ok(())
+}
+
+/// Play through the output device selected in the app and answer the
+/// commands sent to `socket`, until the token is cancelled.
+pub async fn run_daemon(
+    main_db: MainDbConnection,
+    lib_path: &Path,
+    socket: &Path,
+    gapless: bool,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    let mut player = Player::new(None);
+    player.set_gapless_enabled(gapless);
+    let device = get_setting(&main_db, OUTPUT_DEVICE_KEY)
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to get the output device: {e}");
+            None
+        });
+    player.set_output_device(device);
+
+    let daemon = Arc::new(Daemon {
+        main_db,
+        lib_path: lib_path.to_path_buf(),
+        player: Mutex::new(player),
+    });
+
+    let result = accept_clients(Arc::clone(&daemon), socket, cancel_token).await;
+    daemon.player.lock().unwrap().stop();
+    result
+}
+
+/// Send a command to a running daemon and wait for its reply.
+pub async fn send_daemon_command(socket: &Path, command: &DaemonCommand) -> Result<DaemonReply> {
+    #[cfg(unix)]
+    let stream = tokio::net::UnixStream::connect(socket).await;
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new().open(socket);
+    let stream = stream.with_context(|| format!("No daemon listening on {}", socket.display()))?;
+
+    let (reader, mut writer) = tokio::io::split(stream);
+    writer
+        .write_all(format!("{}\n", command.to_line()).as_bytes())
+        .await?;
+
+    let reply = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .with_context(|| "The daemon closed the connection without replying")?;
+    serde_json::from_str(&reply).with_context(|| format!("Invalid daemon reply: {reply}"))
+}
+
+/// Run `rune ctl`, printing the status as a line or as JSON.
+pub async fn control_daemon(socket: &Path, command: DaemonCommand, json: bool) {
+    match send_daemon_command(socket, &command).await {
+        Ok(reply) if !reply.ok => {
+            error!("{}", reply.error.unwrap_or_default());
+        }
+        Ok(reply) => {
+            let Some(status) = reply.status else {
+                return;
+            };
+            if json {
+                match serde_json::to_string_pretty(&status) {
+                    Ok(x) => println!("{x}"),
+                    Err(e) => error!("Failed to encode the status: {e}"),
+                }
+            } else {
+                println!("{}", status.summary());
+            }
+        }
+        Err(e) => error!("{e:#}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(DaemonCommand::parse("play").unwrap(), DaemonCommand::Play);
+        assert_eq!(
+            DaemonCommand::parse("  status \r").unwrap(),
+            DaemonCommand::Status
+        );
+        assert_eq!(
+            DaemonCommand::parse("enqueue 12 Artist/track.flac").unwrap(),
+            DaemonCommand::Enqueue(vec!["12".to_owned(), "Artist/track.flac".to_owned()])
+        );
+
+        assert!(DaemonCommand::parse("").is_err());
+        assert!(DaemonCommand::parse("enqueue").is_err());
+        assert!(DaemonCommand::parse("pause now").is_err());
+        assert!(DaemonCommand::parse("shuffle").is_err());
+
+        assert_eq!(
+            DaemonCommand::parse(r#"enqueue "Some Artist/My Song.flac" 3"#).unwrap(),
+            DaemonCommand::Enqueue(vec!["Some Artist/My Song.flac".to_owned(), "3".to_owned()])
+        );
+        assert!(DaemonCommand::parse(r#"enqueue "Some Artist"#).is_err());
+        assert!(DaemonCommand::parse(r#"enqueue "a"b"#).is_err());
+    }
+
+    #[test]
+    fn test_command_round_trip() {
+        for command in [
+            DaemonCommand::Toggle,
+            DaemonCommand::Previous,
+            DaemonCommand::Enqueue(vec!["1".to_owned(), "2".to_owned()]),
+            DaemonCommand::Enqueue(vec![
+                "Some Artist/Live at \"The Hall\".flac".to_owned(),
+                "C:\\Music\\track one.flac".to_owned(),
+                "\"quoted\"".to_owned(),
+                String::new(),
+            ]),
+        ] {
+            assert_eq!(DaemonCommand::parse(&command.to_line()).unwrap(), command);
+        }
+    }
+
+    #[test]
+    fn test_status_summary() {
+        let mut status = DaemonStatus {
+            state: "Playing".to_owned(),
+            position: 62.0,
+            index: Some(1),
+            queue_length: 10,
+            track: Some(TrackStatus {
+                file_id: 7,
+                title: "Title".to_owned(),
+                artist: "Artist".to_owned(),
+                album: "Album".to_owned(),
+                duration: 225.0,
+            }),
+        };
+        assert_eq!(
+            status.summary(),
+            "Playing: Title - Artist (1:02 / 3:45) [2/10]"
+        );
+
+        status.track = None;
+        status.state = "Stopped".to_owned();
+        assert_eq!(status.summary(), "Stopped [10 queued]");
+    }
+
+    #[test]
+    fn test_reply_skips_empty_fields() {
+        assert_eq!(
+            serde_json::to_string(&DaemonReply::done()).unwrap(),
+            r#"{"ok":true}"#
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use log::{error, info, warn};
+use prettytable::{Table, row};
+use rust_decimal::prelude::ToPrimitive;
+
+use database::actions::duplicates::{
+    DURATION_TOLERANCE, DuplicateGroup, find_fingerprint_duplicates, find_hash_duplicates,
+    find_metadata_duplicates, mark_files_as_duplicated, merge_duplicate_groups,
+};
+use database::actions::file::get_files_by_ids;
+use database::actions::metadata::remove_file_record;
+use database::connection::MainDbConnection;
+use database::entities::media_files;
+
+use crate::confirm::Confirm;
+use crate::format::format_duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DedupeResolution {
+    /// Only print the groups
+    Report,
+    /// Move every file but the kept one to a folder and forget it
+    Move(PathBuf),
+    /// Flag every file but the kept one as duplicated
+    Mark,
+}
+
+impl DedupeResolution {
+    pub fn parse(resolve: &str, target: Option<&PathBuf>) -> Result<Self, String> {
+        match resolve {
+            "report" => Ok(DedupeResolution::Report),
+            "mark" => Ok(DedupeResolution::Mark),
+            "move" => match target {
+                Some(target) => Ok(DedupeResolution::Move(target.clone())),
+                None => Err("Moving duplicates needs a --target folder".to_string()),
+            },
+            _ => Err(format!(
+                "Unknown resolution '{resolve}', expected 'report', 'move' or 'mark'"
+            )),
+        }
+    }
+}
+
+pub struct DedupeOptions<'a> {
+    pub lib_path: &'a Path,
+    /// Also group files by fingerprint similarity, above this threshold
+    pub fingerprint_threshold: Option<f32>,
+    pub resolution: DedupeResolution,
+    pub interactive: bool,
+    pub confirm: Confirm,
+}
+
+struct GroupFile {
+    file: media_files::Model,
+    path: PathBuf,
+    /// Average bitrate in kbps, from the file size and duration
+    bitrate: Option<u64>,
+}
+
+fn estimate_bitrate(path: &Path, duration: f64) -> Option<u64> {
+    let size = fs::metadata(path).ok()?.len();
+    if duration <= 0.0 {
+        return None;
+    }
+
+    Some((size as f64 * 8.0 / duration / 1000.0).round() as u64)
+}
+
+/// The file to keep, the one with the highest bitrate and then the highest
+/// sample rate.
+fn best_file(files: &[GroupFile]) -> usize {
+    files
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, x)| (x.bitrate.unwrap_or(0), x.file.sample_rate))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+fn print_group(index: usize, group: &DuplicateGroup, files: &[GroupFile], keep: usize) {
+    println!(
+        "Group {} (matched by {})",
+        index + 1,
+        group.matched_by.as_str()
+    );
+
+    let mut table = Table::new();
+    table.add_row(row![
+        "#",
+        "Keep",
+        "ID",
+        "Path",
+        "Bitrate",
+        "Sample Rate",
+        "Duration"
+    ]);
+    for (file_index, x) in files.iter().enumerate() {
+        table.add_row(row![
+            file_index + 1,
+            if file_index == keep { "*" } else { "" },
+            x.file.id,
+            x.path.display(),
+            x.bitrate
+                .map(|x| format!("{x} kbps"))
+                .unwrap_or_else(|| "-".to_string()),
+            x.file.sample_rate,
+            format_duration(x.file.duration.to_f64().unwrap_or(0.0))
+        ]);
+    }
+    table.printstd();
+}
+
+/// Ask which file of the group to keep, `None` to leave the group alone.
+fn ask_keep(files: &[GroupFile], keep: usize) -> Option<usize> {
+    let stdin = io::stdin();
+
+    loop {
+        print!(
+            "Keep which file? [1-{}, s to skip, default {}]: ",
+            files.len(),
+            keep + 1
+        );
+        io::stdout().flush().ok()?;
+
+        let mut answer = String::new();
+        if stdin.lock().read_line(&mut answer).ok()? == 0 {
+            return None;
+        }
+
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Some(keep);
+        }
+        if answer.eq_ignore_ascii_case("s") {
+            return None;
+        }
+        match answer.parse::<usize>() {
+            Ok(x) if (1..=files.len()).contains(&x) => return Some(x - 1),
+            _ => println!("Please enter a number between 1 and {}", files.len()),
+        }
+    }
+}
+
+/// Move a file into `target`, falling back to copying when the folder is on
+/// another device.
+fn move_file(file: &GroupFile, target: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(target)?;
+
+    let file_name = &file.file.file_name;
+    let mut destination = target.join(file_name);
+    if destination.exists() {
+        destination = target.join(format!("{}-{file_name}", file.file.id));
+    }
+
+    if fs::rename(&file.path, &destination).is_err() {
+        fs::copy(&file.path, &destination)?;
+        fs::remove_file(&file.path)?;
+    }
+
+    Ok(destination)
+}
+
+async fn resolve_group(
+    main_db: &MainDbConnection,
+    files: &[GroupFile],
+    keep: usize,
+    resolution: &DedupeResolution,
+) -> usize {
+    let duplicates: Vec<&GroupFile> = files
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != keep)
+        .map(|(_, x)| x)
+        .collect();
+
+    match resolution {
+        DedupeResolution::Report => 0,
+        DedupeResolution::Mark => {
+            let ids: Vec<i32> = duplicates.iter().map(|x| x.file.id).collect();
+            match mark_files_as_duplicated(main_db, &ids).await {
+                Ok(marked) => {
+                    if (marked as usize) < ids.len() {
+                        warn!(
+                            "{} files have no fingerprint to mark, run `fingerprint compute` first",
+                            ids.len() - marked as usize
+                        );
+                    }
+                    marked as usize
+                }
+                Err(e) => {
+                    error!("Failed to mark duplicates: {e:#}");
+                    0
+                }
+            }
+        }
+        DedupeResolution::Move(target) => {
+            let mut moved = 0;
+            for duplicate in duplicates {
+                match move_file(duplicate, target) {
+                    Ok(destination) => {
+                        info!("Moved {:?} to {destination:?}", duplicate.path);
+                        // The record stays in the trash, it can be restored
+                        // with the file
+                        if let Err(e) = remove_file_record(main_db, duplicate.file.id).await {
+                            error!("Failed to remove file {}: {e:#}", duplicate.file.id);
+                        }
+                        moved += 1;
+                    }
+                    Err(e) => error!("Failed to move {:?}: {e}", duplicate.path),
+                }
+            }
+            moved
+        }
+    }
+}
+
+/// Find duplicate tracks and resolve them, keeping the best copy of each.
+pub async fn dedupe(main_db: &MainDbConnection, options: DedupeOptions<'_>) {
+    let DedupeOptions {
+        lib_path,
+        fingerprint_threshold,
+        resolution,
+        interactive,
+        confirm,
+    } = options;
+
+    if resolution != DedupeResolution::Report && !confirm.allows("resolve duplicates") {
+        return;
+    }
+
+    let mut groups = Vec::new();
+    match find_hash_duplicates(main_db).await {
+        Ok(x) => groups.extend(x),
+        Err(e) => error!("Failed to find files with the same content: {e:#}"),
+    }
+    match find_metadata_duplicates(main_db, DURATION_TOLERANCE).await {
+        Ok(x) => groups.extend(x),
+        Err(e) => error!("Failed to find files with the same tags: {e:#}"),
+    }
+    if let Some(threshold) = fingerprint_threshold {
+        match find_fingerprint_duplicates(main_db, threshold).await {
+            Ok(x) => groups.extend(x),
+            Err(e) => error!("Failed to find files that sound the same: {e:#}"),
+        }
+    }
+    let groups = merge_duplicate_groups(groups);
+
+    if groups.is_empty() {
+        info!("No duplicates found");
+        return;
+    }
+
+    // Every group is confirmed on its own in interactive mode
+    let duplicates: usize = groups.iter().map(|x| x.file_ids.len() - 1).sum();
+    let summary = match &resolution {
+        DedupeResolution::Report => None,
+        DedupeResolution::Mark => Some(format!(
+            "Up to {duplicates} files in {} duplicate groups will be marked as duplicated",
+            groups.len()
+        )),
+        DedupeResolution::Move(target) => Some(format!(
+            "Up to {duplicates} files in {} duplicate groups will be moved to {target:?} and removed from the library",
+            groups.len()
+        )),
+    };
+    let confirmed = match summary {
+        Some(summary) if !interactive => confirm.confirm("resolve duplicates", &summary),
+        _ => true,
+    };
+    if !confirmed {
+        return;
+    }
+
+    let mut resolved = 0;
+    for (index, group) in groups.iter().enumerate() {
+        let files = match get_files_by_ids(main_db, &group.file_ids).await {
+            Ok(files) => files,
+            Err(e) => {
+                error!("Failed to retrieve files: {e:#}");
+                continue;
+            }
+        };
+        // An earlier group may have moved some of these files away
+        if files.len() < 2 {
+            continue;
+        }
+
+        let files: Vec<GroupFile> = files
+            .into_iter()
+            .map(|file| {
+                let path = lib_path.join(&file.directory).join(&file.file_name);
+                let bitrate = estimate_bitrate(&path, file.duration.to_f64().unwrap_or(0.0));
+                GroupFile {
+                    file,
+                    path,
+                    bitrate,
+                }
+            })
+            .collect();
+
+        let keep = best_file(&files);
+        print_group(index, group, &files, keep);
+
+        let keep = if interactive && resolution != DedupeResolution::Report {
+            match ask_keep(&files, keep) {
+                Some(keep) => keep,
+                None => continue,
+            }
+        } else {
+            keep
+        };
+
+        resolved += resolve_group(main_db, &files, keep, &resolution).await;
+    }
+
+    info!("{} duplicate groups found", groups.len());
+    match resolution {
+        DedupeResolution::Report => {}
+        DedupeResolution::Mark => info!("{resolved} files marked as duplicated"),
+        DedupeResolution::Move(target) => info!("{resolved} files moved to {target:?}"),
+    }
+}
@@ -0,0 +1,245 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use log::{error, info};
+
+/// Sample rate of the generated files, low enough to keep them tiny while
+/// still being decoded like any other track.
+const SAMPLE_RATE: u32 = 8000;
+
+const ARTISTS: [&str; 12] = [
+    "Aurora Lane",
+    "Boreal",
+    "Cirrus & The Drifts",
+    "Dusk Collective",
+    "Ember",
+    "Fjördur",
+    "Glass Harbor",
+    "Hoshino Kaito",
+    "Ivory Static",
+    "Jun Amano",
+    "Kestrel",
+    "Les Lueurs",
+];
+const GENRES: [&str; 8] = [
+    "Ambient",
+    "Classical",
+    "Electronic",
+    "Folk",
+    "Hip-Hop",
+    "Jazz",
+    "Rock",
+    "J-Pop",
+];
+const WORDS: [&str; 16] = [
+    "Night", "Signal", "River", "Echo", "Paper", "Summer", "Static", "Harbor", "Neon", "Glass",
+    "Winter", "Garden", "Orbit", "Velvet", "Hollow", "Lantern",
+];
+
+/// A small deterministic generator, so the same seed always produces the
+/// same library.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
+
+#[derive(Debug, Default)]
+struct SyntheticTrack {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    genre: Option<String>,
+    year: Option<u32>,
+    track_number: Option<usize>,
+    seconds: u32,
+}
+
+/// Escape a tag value for use in a file name.
+fn file_name_part(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect()
+}
+
+fn info_chunk(id: &[u8; 4], value: &str) -> Vec<u8> {
+    // Values are NUL terminated and chunks are padded to an even size
+    let mut data = value.as_bytes().to_vec();
+    data.push(0);
+    if data.len() % 2 == 1 {
+        data.push(0);
+    }
+
+    let mut chunk = Vec::with_capacity(data.len() + 8);
+    chunk.extend_from_slice(id);
+    chunk.extend_from_slice(&(value.len() as u32 + 1).to_le_bytes());
+    chunk.extend_from_slice(&data);
+    chunk
+}
+
+/// Write a silent 8-bit mono WAV file, tagged with a `LIST/INFO` chunk.
+fn write_silent_wav(path: &Path, track: &SyntheticTrack) -> Result<()> {
+    let mut info = b"INFO".to_vec();
+    let tags = [
+        (b"INAM", track.title.clone()),
+        (b"IART", track.artist.clone()),
+        (b"IPRD", track.album.clone()),
+        (b"IGNR", track.genre.clone()),
+        (b"ICRD", track.year.map(|x| x.to_string())),
+        (b"ITRK", track.track_number.map(|x| x.to_string())),
+    ];
+    for (id, value) in tags {
+        if let Some(value) = value {
+            info.extend(info_chunk(id, &value));
+        }
+    }
+
+    let data_len = SAMPLE_RATE * track.seconds;
+    let riff_len = 4 + (8 + 16) + (8 + info.len() as u32) + (8 + data_len + data_len % 2);
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&riff_len.to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // Mono
+    writer.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    writer.write_all(&SAMPLE_RATE.to_le_bytes())?; // Byte rate
+    writer.write_all(&1u16.to_le_bytes())?; // Block align
+    writer.write_all(&8u16.to_le_bytes())?; // Bits per sample
+
+    writer.write_all(b"LIST")?;
+    writer.write_all(&(info.len() as u32).to_le_bytes())?;
+    writer.write_all(&info)?;
+
+    // Unsigned 8-bit PCM is silent at 128
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    writer.write_all(&vec![128u8; (data_len + data_len % 2) as usize])?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Lay out `tracks` tracks the way real libraries tend to look: mostly
+/// `Artist/Album/NN - Title`, with some compilations, loose files and
+/// untagged tracks mixed in.
+fn plan_library(tracks: usize, seed: u64) -> Vec<(PathBuf, SyntheticTrack)> {
+    let mut rng = SplitMix64(seed);
+    let mut plan = Vec::with_capacity(tracks);
+    let mut album_index = 0;
+
+    while plan.len() < tracks {
+        let artist = rng.pick(&ARTISTS);
+        let album = format!("{} {}", rng.pick(&WORDS), rng.pick(&WORDS));
+        let genre = rng.pick(&GENRES);
+        let year = 1960 + rng.below(65) as u32;
+        let compilation = rng.chance(10);
+        let album_size = (4 + rng.below(17)).min(tracks - plan.len());
+
+        for number in 1..=album_size {
+            let title = format!("{} {} {}", rng.pick(&WORDS), rng.pick(&WORDS), plan.len());
+            let track_artist = if compilation {
+                rng.pick(&ARTISTS).to_owned()
+            } else if rng.chance(8) {
+                // Featured artists exercise the artist splitter
+                format!("{artist} feat. {}", rng.pick(&ARTISTS))
+            } else {
+                artist.to_owned()
+            };
+
+            let mut track = SyntheticTrack {
+                title: Some(title.clone()),
+                artist: Some(track_artist),
+                album: Some(album.clone()),
+                genre: Some(genre.to_owned()),
+                year: Some(year),
+                track_number: Some(number),
+                seconds: 1 + rng.below(3) as u32,
+            };
+
+            let path = if rng.chance(2) {
+                // Untagged files only have their file name to go by
+                track = SyntheticTrack {
+                    seconds: track.seconds,
+                    ..Default::default()
+                };
+                PathBuf::from("Unsorted").join(format!("track_{:06}.wav", plan.len()))
+            } else {
+                let album_artist = if compilation {
+                    "Various Artists"
+                } else {
+                    artist
+                };
+                PathBuf::from(file_name_part(album_artist))
+                    .join(file_name_part(&format!("{album} ({album_index})")))
+                    .join(format!("{number:02} - {}.wav", file_name_part(&title)))
+            };
+
+            plan.push((path, track));
+        }
+
+        album_index += 1;
+    }
+
+    plan
+}
+
+/// Create a synthetic library of silent, tagged tracks in `path`.
+pub fn generate_library(path: &Path, tracks: usize, seed: u64) -> Result<usize> {
+    if path.exists()
+        && fs::read_dir(path)
+            .with_context(|| format!("Failed to read {path:?}"))?
+            .next()
+            .is_some()
+    {
+        bail!("Refusing to generate into a non-empty directory: {path:?}");
+    }
+
+    let plan = plan_library(tracks, seed);
+    for (index, (rel_path, track)) in plan.iter().enumerate() {
+        let file_path = path.join(rel_path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_silent_wav(&file_path, track)
+            .with_context(|| format!("Failed to write {file_path:?}"))?;
+
+        if (index + 1) % 1000 == 0 {
+            info!("{} of {} tracks generated", index + 1, plan.len());
+        }
+    }
+
+    Ok(plan.len())
+}
+
+pub fn gen_library(path: &Path, tracks: usize, seed: u64) {
+    match generate_library(path, tracks, seed) {
+        Ok(count) => info!("Generated {count} tracks in {path:?}, scan it to import them"),
+        Err(e) => error!("Failed to generate library: {e:#}"),
+    }
+}
@@ -0,0 +1,96 @@
+use log::{error, info};
+use prettytable::{Table, row};
+
+use database::{
+    actions::{
+        index_suggestions::get_index_suggestions,
+        recommendation::{
+            check_recommendation_db, compact_recommendation_db, maintain_recommendation_db,
+            rebuild_recommendation_db,
+        },
+    },
+    connection::{MainDbConnection, RecommendationDbConnection},
+};
+
+/// Print the indexes suggested from the slow statements of earlier
+/// commands, see `database.slow_query_ms`.
+async fn print_index_suggestions(main_db: &MainDbConnection) {
+    let suggestions = match get_index_suggestions(main_db).await {
+        Ok(suggestions) => suggestions,
+        Err(e) => {
+            error!("Failed to load the index suggestions: {e:#}");
+            return;
+        }
+    };
+    if suggestions.is_empty() {
+        return;
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["Table", "Columns", "Statement"]);
+    for suggestion in &suggestions {
+        table.add_row(row![
+            suggestion.table,
+            suggestion.columns.join(", "),
+            suggestion.create_statement()
+        ]);
+    }
+    table.printstd();
+    info!("Slow statements were missing these indexes");
+}
+
+/// Report the state of the recommendation database and the indexes missing
+/// from the main database, and repair and compact the recommendation
+/// database with `fix`. `rebuild` builds the index from scratch instead of
+/// repairing it.
+pub async fn doctor(
+    main_db: &MainDbConnection,
+    recommend_db: RecommendationDbConnection,
+    fix: bool,
+    rebuild: bool,
+) {
+    let health = match check_recommendation_db(main_db, &recommend_db).await {
+        Ok(health) => health,
+        Err(e) => {
+            error!("Failed to check the recommendation database: {e:#}");
+            return;
+        }
+    };
+
+    let mut table = Table::new();
+    table.add_row(row!["Check", "Count"]);
+    table.add_row(row!["Vectors", health.vectors]);
+    table.add_row(row!["Orphaned vectors", health.orphaned_vectors]);
+    table.add_row(row!["Missing vectors", health.missing_vectors]);
+    table.printstd();
+
+    print_index_suggestions(main_db).await;
+
+    if !fix && !rebuild {
+        if !health.is_healthy() {
+            info!("Run with --fix to repair the recommendation database");
+        }
+        return;
+    }
+
+    let repaired = if rebuild {
+        rebuild_recommendation_db(main_db, &recommend_db)
+            .await
+            .map(|_| ())
+    } else {
+        maintain_recommendation_db(main_db, &recommend_db)
+            .await
+            .map(|_| ())
+    };
+    if let Err(e) = repaired {
+        error!("Failed to repair the recommendation database: {e:#}");
+        return;
+    }
+
+    match compact_recommendation_db(recommend_db) {
+        Ok((before, after)) => {
+            info!("Recommendation database compacted: {before} -> {after} bytes")
+        }
+        Err(e) => error!("Failed to compact the recommendation database: {e:#}"),
+    }
+}
@@ -0,0 +1,38 @@
+use log::{error, info};
+
+use database::actions::enrich::{EnrichOptions, MusicBrainzClient, enrich_library};
+use database::connection::MainDbConnection;
+
+const USER_AGENT: &str = concat!(
+    "Rune/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/Losses/rune )"
+);
+
+/// Fill in the tags missing from the library with data from MusicBrainz.
+pub async fn enrich(main_db: &MainDbConnection, options: EnrichOptions) {
+    let client = match MusicBrainzClient::new(USER_AGENT) {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create the MusicBrainz client: {e:#}");
+            return;
+        }
+    };
+
+    let report = enrich_library(
+        main_db,
+        &client,
+        &options,
+        |processed, total| info!("Looked up {processed}/{total} files"),
+        None,
+    )
+    .await;
+
+    match report {
+        Ok(report) => info!(
+            "{} of {} files matched, {} tags added",
+            report.matched, report.looked_up, report.filled
+        ),
+        Err(e) => error!("Failed to enrich the library: {e:#}"),
+    }
+}
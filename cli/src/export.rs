@@ -0,0 +1,287 @@
+//! Playlist files written by `recommend` and `mix` when `--format` is given.
+//!
+//! JSON and XSPF playlists carry the metadata summary of every track, M3U8
+//! playlists only list the paths and are written by the commands themselves.
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::Utc;
+use serde::Serialize;
+
+use database::actions::metadata::MetadataSummary;
+
+use crate::format::format_duration;
+use crate::recommend::check_and_correct_extension;
+
+pub const SUPPORTED_FORMATS: &str = "'json', 'm3u8' and 'xspf'";
+
+/// Version of the JSON playlist schema, bumped on breaking changes.
+pub const JSON_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    Json,
+    M3u8,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "json" => Some(PlaylistFormat::Json),
+            "m3u8" | "m3u" => Some(PlaylistFormat::M3u8),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlaylistFormat::Json => "json",
+            PlaylistFormat::M3u8 => "m3u8",
+            PlaylistFormat::Xspf => "xspf",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistTrack {
+    pub id: i32,
+    /// Absolute path of the file.
+    pub path: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub genre: String,
+    pub track_number: i32,
+    /// Duration in seconds.
+    pub duration: f64,
+    pub duration_formatted: String,
+    pub cover_art_id: Option<i32>,
+    pub key: Option<String>,
+    /// Distance to the seed track, only set for recommendations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance: Option<f32>,
+}
+
+impl PlaylistTrack {
+    pub fn new(lib_path: &Path, summary: MetadataSummary, distance: Option<f32>) -> Self {
+        let path = lib_path.join(&summary.directory).join(&summary.file_name);
+
+        PlaylistTrack {
+            id: summary.id,
+            path: path.to_string_lossy().into_owned(),
+            title: summary.title,
+            artist: summary.artist,
+            album: summary.album,
+            genre: summary.genre,
+            track_number: summary.track_number,
+            duration: summary.duration,
+            duration_formatted: format_duration(summary.duration),
+            cover_art_id: summary.cover_art_id,
+            key: summary.key,
+            distance,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonPlaylist<'a> {
+    version: u32,
+    /// RFC 3339 timestamp.
+    created_at: String,
+    /// Duration of all tracks in seconds.
+    total_duration: f64,
+    tracks: &'a [PlaylistTrack],
+}
+
+fn write_json(file: &mut File, tracks: &[PlaylistTrack]) -> io::Result<()> {
+    let playlist = JsonPlaylist {
+        version: JSON_SCHEMA_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        total_duration: tracks.iter().map(|x| x.duration).sum(),
+        tracks,
+    };
+
+    serde_json::to_writer_pretty(&mut *file, &playlist)?;
+    writeln!(file)
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Percent-encode a path for a URI, keeping the `/` separators.
+fn encode_uri_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+
+    let mut result = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                result.push(byte as char)
+            }
+            _ => {
+                let _ = write!(result, "%{byte:02X}");
+            }
+        }
+    }
+
+    result
+}
+
+/// The `location` of a track, relative to the playlist when possible so
+/// the playlist can be moved together with the library.
+fn xspf_location(track_path: &Path, output_dir: Option<&Path>) -> String {
+    match output_dir.and_then(|x| pathdiff::diff_paths(track_path, x)) {
+        Some(relative_path) => encode_uri_path(&relative_path),
+        None => {
+            let path = encode_uri_path(track_path);
+            if path.starts_with('/') {
+                format!("file://{path}")
+            } else {
+                format!("file:///{path}")
+            }
+        }
+    }
+}
+
+fn write_xspf(
+    file: &mut File,
+    output_dir: Option<&Path>,
+    tracks: &[PlaylistTrack],
+) -> io::Result<()> {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    let _ = writeln!(xml, "  <date>{}</date>", Utc::now().to_rfc3339());
+    xml.push_str("  <trackList>\n");
+
+    for track in tracks {
+        let location = xspf_location(Path::new(&track.path), output_dir);
+
+        xml.push_str("    <track>\n");
+        let _ = writeln!(xml, "      <location>{}</location>", escape_xml(&location));
+        let _ = writeln!(xml, "      <title>{}</title>", escape_xml(&track.title));
+        let _ = writeln!(
+            xml,
+            "      <creator>{}</creator>",
+            escape_xml(&track.artist)
+        );
+        let _ = writeln!(xml, "      <album>{}</album>", escape_xml(&track.album));
+        if track.track_number > 0 {
+            let _ = writeln!(xml, "      <trackNum>{}</trackNum>", track.track_number);
+        }
+        // XSPF durations are in milliseconds
+        let _ = writeln!(
+            xml,
+            "      <duration>{}</duration>",
+            (track.duration * 1000.0).round() as u64
+        );
+        xml.push_str("    </track>\n");
+    }
+
+    xml.push_str("  </trackList>\n");
+    xml.push_str("</playlist>\n");
+
+    file.write_all(xml.as_bytes())
+}
+
+/// Write a JSON or XSPF playlist, correcting the extension of `output_path`
+/// if needed.
+pub fn save_playlist(format: PlaylistFormat, output_path: &Path, tracks: &[PlaylistTrack]) {
+    let extension = format.extension();
+    let corrected_path = check_and_correct_extension(output_path, extension);
+    if corrected_path != *output_path {
+        eprintln!("Warning: Output file extension corrected to .{extension}");
+    }
+
+    if let Some(parent) = corrected_path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        eprintln!("Failed to create directories: {e}");
+        return;
+    }
+
+    let mut file = match File::create(&corrected_path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to create file: {e}");
+            return;
+        }
+    };
+
+    let result = match format {
+        PlaylistFormat::Json => write_json(&mut file, tracks),
+        PlaylistFormat::Xspf => write_xspf(&mut file, corrected_path.parent(), tracks),
+        PlaylistFormat::M3u8 => {
+            eprintln!("M3U8 playlists are written by the command itself");
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to write to file: {e}");
+        return;
+    }
+
+    println!(
+        "Playlist saved to {} file: {}",
+        extension.to_uppercase(),
+        corrected_path.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(PlaylistFormat::parse("XSPF"), Some(PlaylistFormat::Xspf));
+        assert_eq!(PlaylistFormat::parse("m3u"), Some(PlaylistFormat::M3u8));
+        assert_eq!(PlaylistFormat::parse("pls"), None);
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml("Tom & Jerry <Live>"),
+            "Tom &amp; Jerry &lt;Live&gt;"
+        );
+        assert_eq!(escape_xml("\"It's\""), "&quot;It&apos;s&quot;");
+    }
+
+    #[test]
+    fn test_xspf_location() {
+        let track = Path::new("/music/Aurora/Album 1/01 Intro #1.flac");
+
+        assert_eq!(
+            xspf_location(track, Some(Path::new("/music/playlists"))),
+            "../Aurora/Album%201/01%20Intro%20%231.flac"
+        );
+        assert_eq!(
+            xspf_location(track, None),
+            "file:///music/Aurora/Album%201/01%20Intro%20%231.flac"
+        );
+        assert_eq!(
+            xspf_location(Path::new("/music/Björk.flac"), None),
+            "file:///music/Bj%C3%B6rk.flac"
+        );
+    }
+}
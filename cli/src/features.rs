@@ -0,0 +1,269 @@
+//! Analysis vectors written by `export-features`, one row per analyzed file
+//! with a column per feature, for use with pandas, polars and the like.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow_array::{ArrayRef, Float32Array, Int32Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+
+use database::actions::analysis::{
+    ANALYSIS_VECTOR_SIZE, analysis_feature_names, get_analysis_features,
+};
+use database::actions::metadata::get_metadata_summary_by_files;
+use database::connection::MainDbConnection;
+
+use crate::recommend::check_and_correct_extension;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureFormat {
+    Csv,
+    Parquet,
+}
+
+impl FeatureFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "csv" => Some(FeatureFormat::Csv),
+            "parquet" => Some(FeatureFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FeatureFormat::Csv => "csv",
+            FeatureFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeatureRow {
+    pub file_id: i32,
+    pub file_hash: String,
+    /// Path of the file relative to the library.
+    pub path: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub genre: String,
+    pub features: [f32; ANALYSIS_VECTOR_SIZE],
+}
+
+/// Columns written before the features.
+const METADATA_COLUMNS: [&str; 7] = [
+    "file_id",
+    "file_hash",
+    "path",
+    "title",
+    "artist",
+    "album",
+    "genre",
+];
+
+async fn get_feature_rows(main_db: &MainDbConnection) -> Result<Vec<FeatureRow>> {
+    let features = get_analysis_features(main_db).await?;
+
+    let files = features.iter().map(|(file, _)| file.clone()).collect();
+    let mut summaries: HashMap<i32, _> = get_metadata_summary_by_files(main_db, files)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+
+    Ok(features
+        .into_iter()
+        .map(|(file, features)| {
+            let (title, artist, album, genre) = match summaries.remove(&file.id) {
+                Some(x) => (x.title, x.artist, x.album, x.genre),
+                None => Default::default(),
+            };
+
+            FeatureRow {
+                file_id: file.id,
+                path: Path::new(&file.directory)
+                    .join(&file.file_name)
+                    .to_string_lossy()
+                    .into_owned(),
+                file_hash: file.file_hash,
+                title,
+                artist,
+                album,
+                genre,
+                features,
+            }
+        })
+        .collect())
+}
+
+fn write_csv<W: Write>(writer: W, rows: &[FeatureRow]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+
+    let mut header: Vec<String> = METADATA_COLUMNS.iter().map(|x| x.to_string()).collect();
+    header.extend(analysis_feature_names());
+    writer.write_record(&header)?;
+
+    for row in rows {
+        let mut record = vec![
+            row.file_id.to_string(),
+            row.file_hash.clone(),
+            row.path.clone(),
+            row.title.clone(),
+            row.artist.clone(),
+            row.album.clone(),
+            row.genre.clone(),
+        ];
+        record.extend(row.features.iter().map(|x| x.to_string()));
+        writer.write_record(&record)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_parquet<W: Write + Send>(writer: W, rows: &[FeatureRow]) -> Result<()> {
+    let string_column = |f: fn(&FeatureRow) -> &str| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(rows.iter().map(f)))
+    };
+
+    let mut fields = vec![Field::new("file_id", DataType::Int32, false)];
+    fields.extend(
+        METADATA_COLUMNS[1..]
+            .iter()
+            .map(|x| Field::new(*x, DataType::Utf8, false)),
+    );
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(Int32Array::from_iter_values(rows.iter().map(|x| x.file_id))),
+        string_column(|x| x.file_hash.as_str()),
+        string_column(|x| x.path.as_str()),
+        string_column(|x| x.title.as_str()),
+        string_column(|x| x.artist.as_str()),
+        string_column(|x| x.album.as_str()),
+        string_column(|x| x.genre.as_str()),
+    ];
+
+    for (index, name) in analysis_feature_names().into_iter().enumerate() {
+        fields.push(Field::new(name, DataType::Float32, false));
+        columns.push(Arc::new(Float32Array::from_iter_values(
+            rows.iter().map(|x| x.features[index]),
+        )));
+    }
+
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+/// Write the analysis vectors of every analyzed file, correcting the
+/// extension of `output_path` if needed.
+pub async fn export_features(main_db: &MainDbConnection, format: &str, output_path: &Path) {
+    let Some(format) = FeatureFormat::parse(format) else {
+        eprintln!("Unsupported format: {format}, expected 'csv' or 'parquet'");
+        return;
+    };
+
+    let rows = match get_feature_rows(main_db).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Failed to retrieve analysis results: {e:#}");
+            return;
+        }
+    };
+    if rows.is_empty() {
+        eprintln!("No analyzed files found, run `analyze` first");
+        return;
+    }
+
+    let extension = format.extension();
+    let corrected_path = check_and_correct_extension(output_path, extension);
+    if corrected_path != *output_path {
+        eprintln!("Warning: Output file extension corrected to .{extension}");
+    }
+
+    if let Some(parent) = corrected_path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        eprintln!("Failed to create directories: {e}");
+        return;
+    }
+
+    let file = match File::create(&corrected_path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to create file: {e}");
+            return;
+        }
+    };
+
+    let result = match format {
+        FeatureFormat::Csv => write_csv(file, &rows),
+        FeatureFormat::Parquet => write_parquet(file, &rows),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to write to file: {e:#}");
+        return;
+    }
+
+    println!(
+        "Exported features of {} files to {}",
+        rows.len(),
+        corrected_path.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(file_id: i32) -> FeatureRow {
+        FeatureRow {
+            file_id,
+            file_hash: format!("hash{file_id}"),
+            path: format!("Artist/Album, Live/{file_id:02}.flac"),
+            title: "Title".to_string(),
+            artist: "Artist".to_string(),
+            album: "Album, Live".to_string(),
+            genre: String::new(),
+            features: std::array::from_fn(|i| i as f32 / 2.0),
+        }
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(FeatureFormat::parse("CSV"), Some(FeatureFormat::Csv));
+        assert_eq!(
+            FeatureFormat::parse("parquet"),
+            Some(FeatureFormat::Parquet)
+        );
+        assert_eq!(FeatureFormat::parse("json"), None);
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, &[row(1), row(2)]).unwrap();
+
+        let text = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+
+        let header: Vec<&str> = lines[0].split(',').collect();
+        assert_eq!(header.len(), METADATA_COLUMNS.len() + ANALYSIS_VECTOR_SIZE);
+        assert_eq!(header[0], "file_id");
+        assert_eq!(header[7], "rms");
+        assert_eq!(header[67], "mfcc_12");
+
+        assert!(lines[1].starts_with("1,hash1,\"Artist/Album, Live/01.flac\","));
+        assert!(lines[1].ends_with(",29.5,30"));
+    }
+}
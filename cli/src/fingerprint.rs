@@ -0,0 +1,84 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use log::{error, info};
+use prettytable::{Table, row};
+
+use database::{
+    actions::fingerprint::{
+        Configuration, compare_all_pairs, compute_file_fingerprints, encode_acoustid_fingerprint,
+        get_duplicate_files, get_fingerprint_by_file_id, mark_duplicate_files,
+    },
+    connection::MainDbConnection,
+};
+use fsio::FsIo;
+
+/// Fingerprints compared per page when looking for duplicates.
+const COMPARE_PAGE_SIZE: u64 = 1000;
+
+pub async fn compute_fingerprints(
+    fsio: Arc<FsIo>,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    batch_size: usize,
+) {
+    match compute_file_fingerprints(fsio, main_db, lib_path, "", batch_size, |_, _| {}, None).await
+    {
+        Ok(count) => info!("{count} fingerprints computed"),
+        Err(e) => error!("Failed to compute fingerprints: {e:#}"),
+    }
+}
+
+pub async fn show_fingerprint(main_db: &MainDbConnection, file_id: i32) {
+    match get_fingerprint_by_file_id(main_db, file_id).await {
+        Ok(Some(fingerprint)) => {
+            let (fingerprint, duration) = encode_acoustid_fingerprint(fingerprint);
+            println!("DURATION={}", duration.round() as u32);
+            println!("FINGERPRINT={fingerprint}");
+        }
+        Ok(None) => error!("No fingerprint for file {file_id}, run `fingerprint compute` first"),
+        Err(e) => error!("Failed to retrieve fingerprint: {e:#}"),
+    }
+}
+
+/// Compare every pair of fingerprints and list the files similar enough to
+/// be duplicates.
+pub async fn find_duplicates(main_db: &MainDbConnection, threshold: f32, batch_size: usize) {
+    let config = Configuration::default();
+    if let Err(e) = compare_all_pairs(
+        main_db,
+        batch_size,
+        |_, _| {},
+        &config,
+        None,
+        COMPARE_PAGE_SIZE,
+    )
+    .await
+    {
+        error!("Failed to compare fingerprints: {e:#}");
+        return;
+    }
+
+    if let Err(e) = mark_duplicate_files(main_db, threshold, |_, _| {}).await {
+        error!("Failed to mark duplicate files: {e:#}");
+        return;
+    }
+
+    let files = match get_duplicate_files(main_db).await {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Failed to retrieve duplicate files: {e:#}");
+            return;
+        }
+    };
+
+    let mut table = Table::new();
+    table.add_row(row!["ID", "Path"]);
+    for file in files {
+        table.add_row(row![
+            file.id,
+            format!("{}/{}", file.directory, file.file_name)
+        ]);
+    }
+    table.printstd();
+}
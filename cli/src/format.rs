@@ -0,0 +1,169 @@
+//! Human readable formatting of durations, numbers and dates in the command
+//! outputs. JSON outputs keep the raw values next to the formatted ones, so
+//! scripts never have to parse localized strings.
+
+use std::env;
+
+use chrono::{DateTime, Local};
+
+/// Separators and date layout of a locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub decimal_separator: char,
+    pub group_separator: Option<char>,
+    /// A `chrono` format string.
+    pub date_format: &'static str,
+}
+
+impl Default for Locale {
+    /// The `C` locale, ISO 8601 dates and no digit grouping.
+    fn default() -> Self {
+        Locale {
+            decimal_separator: '.',
+            group_separator: None,
+            date_format: "%Y-%m-%d %H:%M:%S",
+        }
+    }
+}
+
+impl Locale {
+    /// Pick the locale from `LC_ALL`, `LC_TIME` or `LANG`, in that order.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|x| env::var(x).ok())
+            .find(|x| !x.is_empty())
+            .map(|x| Self::from_tag(&x))
+            .unwrap_or_default()
+    }
+
+    /// Parse a POSIX locale name such as `de_DE.UTF-8` or a language tag
+    /// such as `en-GB`. Unknown languages fall back to the `C` locale.
+    pub fn from_tag(tag: &str) -> Self {
+        let tag = tag.split(['.', '@']).next().unwrap_or_default();
+        let mut parts = tag.split(['_', '-']);
+        let language = parts.next().unwrap_or_default().to_lowercase();
+        let region = parts.next().unwrap_or_default().to_uppercase();
+
+        let (decimal_separator, group_separator, date_format) = match language.as_str() {
+            "en" => match region.as_str() {
+                "US" => ('.', Some(','), "%m/%d/%Y %H:%M"),
+                _ => ('.', Some(','), "%d/%m/%Y %H:%M"),
+            },
+            "de" => (',', Some('.'), "%d.%m.%Y %H:%M"),
+            "fr" => (',', Some(' '), "%d/%m/%Y %H:%M"),
+            "es" | "it" | "pt" => (',', Some('.'), "%d/%m/%Y %H:%M"),
+            "nl" => (',', Some('.'), "%d-%m-%Y %H:%M"),
+            "ru" | "uk" | "pl" => (',', Some(' '), "%d.%m.%Y %H:%M"),
+            "ja" | "zh" => ('.', Some(','), "%Y/%m/%d %H:%M"),
+            "ko" => ('.', Some(','), "%Y. %m. %d. %H:%M"),
+            _ => return Self::default(),
+        };
+
+        Locale {
+            decimal_separator,
+            group_separator,
+            date_format,
+        }
+    }
+
+    /// Format a number with a fixed number of decimals, grouping the
+    /// integer part by thousands.
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        if !value.is_finite() {
+            return value.to_string();
+        }
+
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = match formatted.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (formatted.as_str(), None),
+        };
+
+        let mut result = String::new();
+        if value.is_sign_negative() && formatted.chars().any(|x| x.is_ascii_digit() && x != '0') {
+            result.push('-');
+        }
+
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0
+                && (integer.len() - i) % 3 == 0
+                && let Some(separator) = self.group_separator
+            {
+                result.push(separator);
+            }
+            result.push(digit);
+        }
+
+        if let Some(fraction) = fraction {
+            result.push(self.decimal_separator);
+            result.push_str(fraction);
+        }
+
+        result
+    }
+
+    /// Format an RFC 3339 timestamp in the local time zone, the input is
+    /// returned as is if it can't be parsed.
+    pub fn format_timestamp(&self, timestamp: &str) -> String {
+        match DateTime::parse_from_rfc3339(timestamp) {
+            Ok(x) => x.with_timezone(&Local).format(self.date_format).to_string(),
+            Err(_) => timestamp.to_string(),
+        }
+    }
+}
+
+/// Format a duration in seconds as `m:ss`, or `h:mm:ss` from one hour on.
+pub fn format_duration(seconds: f64) -> String {
+    let total_seconds = if seconds.is_finite() && seconds > 0.0 {
+        seconds.floor() as u64
+    } else {
+        0
+    };
+
+    let hours = total_seconds / 3600;
+    let minutes = total_seconds % 3600 / 60;
+    let remaining_seconds = total_seconds % 60;
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{remaining_seconds:02}")
+    } else {
+        format!("{minutes}:{remaining_seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0.0), "0:00");
+        assert_eq!(format_duration(65.9), "1:05");
+        assert_eq!(format_duration(3600.0), "1:00:00");
+        assert_eq!(format_duration(-3.0), "0:00");
+        assert_eq!(format_duration(f64::NAN), "0:00");
+    }
+
+    #[test]
+    fn test_format_number() {
+        let c = Locale::default();
+        assert_eq!(c.format_number(1234567.891, 2), "1234567.89");
+
+        let en = Locale::from_tag("en_US.UTF-8");
+        assert_eq!(en.format_number(1234567.891, 2), "1,234,567.89");
+        assert_eq!(en.format_number(-999.0, 0), "-999");
+        assert_eq!(en.format_number(-0.001, 2), "0.00");
+
+        let de = Locale::from_tag("de-DE");
+        assert_eq!(de.format_number(1234.5, 1), "1.234,5");
+    }
+
+    #[test]
+    fn test_from_tag() {
+        assert_eq!(Locale::from_tag("C"), Locale::default());
+        assert_eq!(Locale::from_tag("POSIX"), Locale::default());
+        assert_eq!(Locale::from_tag("en_GB").date_format, "%d/%m/%Y %H:%M");
+        assert_eq!(Locale::from_tag("fr_FR@euro").decimal_separator, ',');
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use log::error;
+use prettytable::{Table, row};
+use serde::Serialize;
+
+use database::{
+    actions::{
+        metadata::get_metadata_summary_by_file_ids,
+        stats::{get_most_played, get_recently_played},
+    },
+    connection::MainDbConnection,
+};
+
+use crate::format::Locale;
+
+#[derive(Serialize)]
+struct HistoryItem {
+    id: i32,
+    artist: String,
+    title: String,
+    play_count: i64,
+    /// RFC 3339 timestamp.
+    last_played_at: String,
+    last_played_at_formatted: String,
+}
+
+pub async fn show_history(main_db: &MainDbConnection, most_played: bool, num: u64, json: bool) {
+    let history = if most_played {
+        get_most_played(main_db, num).await
+    } else {
+        get_recently_played(main_db, num).await
+    };
+
+    let history = match history {
+        Ok(history) => history,
+        Err(e) => {
+            error!("Failed to retrieve play history: {e}");
+            return;
+        }
+    };
+
+    let file_ids = history.iter().map(|x| x.media_file_id).collect();
+    let mut summaries: HashMap<i32, _> =
+        match get_metadata_summary_by_file_ids(main_db, file_ids).await {
+            Ok(summaries) => summaries.into_iter().map(|x| (x.id, x)).collect(),
+            Err(e) => {
+                error!("Failed to retrieve metadata summary: {e}");
+                return;
+            }
+        };
+
+    let locale = Locale::from_env();
+    let items: Vec<HistoryItem> = history
+        .into_iter()
+        .map(|item| {
+            let (artist, title) = match summaries.remove(&item.media_file_id) {
+                Some(summary) => (summary.artist, summary.title),
+                None => Default::default(),
+            };
+
+            HistoryItem {
+                id: item.media_file_id,
+                artist,
+                title,
+                play_count: item.play_count,
+                last_played_at_formatted: locale.format_timestamp(&item.last_played_at),
+                last_played_at: item.last_played_at,
+            }
+        })
+        .collect();
+
+    if json {
+        match serde_json::to_string_pretty(&items) {
+            Ok(json) => println!("{json}"),
+            Err(e) => error!("Failed to serialize play history: {e}"),
+        }
+        return;
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["ID", "Artist", "Title", "Plays", "Last Played"]);
+
+    for item in items {
+        table.add_row(row![
+            item.id,
+            item.artist,
+            item.title,
+            locale.format_number(item.play_count as f64, 0),
+            item.last_played_at_formatted
+        ]);
+    }
+
+    table.printstd();
+}
@@ -0,0 +1,81 @@
+use log::error;
+use prettytable::{Table, row};
+use serde::Serialize;
+
+use database::{actions::metadata::get_metadata_summary_by_file_ids, connection::MainDbConnection};
+
+use crate::format::format_duration;
+
+#[derive(Serialize)]
+struct TrackInfo {
+    id: i32,
+    artist: String,
+    album: String,
+    title: String,
+    track_number: i32,
+    /// Duration in seconds.
+    duration: f64,
+    duration_formatted: String,
+    key: Option<String>,
+    cover_art_id: Option<i32>,
+}
+
+pub async fn show_info(main_db: &MainDbConnection, file_ids: Vec<i32>, json: bool) {
+    let summaries = match get_metadata_summary_by_file_ids(main_db, file_ids).await {
+        Ok(summaries) => summaries,
+        Err(e) => {
+            error!("Failed to retrieve metadata summary: {e}");
+            return;
+        }
+    };
+
+    let tracks: Vec<TrackInfo> = summaries
+        .into_iter()
+        .map(|summary| TrackInfo {
+            id: summary.id,
+            artist: summary.artist,
+            album: summary.album,
+            title: summary.title,
+            track_number: summary.track_number,
+            duration: summary.duration,
+            duration_formatted: format_duration(summary.duration),
+            key: summary.key,
+            cover_art_id: summary.cover_art_id,
+        })
+        .collect();
+
+    if json {
+        match serde_json::to_string_pretty(&tracks) {
+            Ok(json) => println!("{json}"),
+            Err(e) => error!("Failed to serialize track information: {e}"),
+        }
+        return;
+    }
+
+    let mut table = Table::new();
+    table.add_row(row![
+        "ID",
+        "Artist",
+        "Album",
+        "Title",
+        "Track Number",
+        "Duration",
+        "Key",
+        "Cover Art ID"
+    ]);
+
+    for track in tracks {
+        table.add_row(row![
+            track.id,
+            track.artist,
+            track.album,
+            track.title,
+            track.track_number,
+            track.duration_formatted,
+            track.key.unwrap_or_default(),
+            track.cover_art_id.unwrap_or_default()
+        ]);
+    }
+
+    table.printstd();
+}
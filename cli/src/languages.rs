@@ -0,0 +1,24 @@
+use log::{error, info};
+
+use database::{
+    actions::metadata_languages::{get_metadata_languages, set_metadata_languages},
+    connection::MainDbConnection,
+};
+
+pub async fn show_metadata_languages(main_db: &MainDbConnection) {
+    match get_metadata_languages(main_db).await {
+        Ok(languages) if languages.is_empty() => info!("Showing the original tags"),
+        Ok(languages) => info!("Showing tags in: {}", languages.join(", ")),
+        Err(e) => error!("Failed to retrieve the metadata languages: {e:#}"),
+    }
+}
+
+/// Save the display languages, the original tags are shown for an empty
+/// list.
+pub async fn update_metadata_languages(main_db: &MainDbConnection, languages: &[String]) {
+    match set_metadata_languages(main_db, languages).await {
+        Ok(languages) if languages.is_empty() => info!("Showing the original tags again"),
+        Ok(languages) => info!("Showing tags in: {}", languages.join(", ")),
+        Err(e) => error!("Failed to save the metadata languages: {e:#}"),
+    }
+}
@@ -1,5 +1,25 @@
 pub mod analysis;
+pub mod confirm;
+pub mod daemon;
+pub mod dedupe;
+pub mod dev;
+pub mod doctor;
+pub mod enrich;
+pub mod export;
+pub mod features;
+pub mod fingerprint;
+pub mod format;
+pub mod history;
 pub mod index;
+pub mod info;
+pub mod languages;
 pub mod mix;
 pub mod playback;
+pub mod playlist_mirror;
+pub mod profile;
 pub mod recommend;
+pub mod serve;
+pub mod source;
+pub mod splitting;
+pub mod tag;
+pub mod trash;
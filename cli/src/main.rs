@@ -1,27 +1,75 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use clap::{Parser, Subcommand};
 use dunce::canonicalize;
 use log::{error, info};
-use prettytable::{Table, row};
 use tracing_subscriber::filter::EnvFilter;
 
+use analysis::measure_time;
+
+use config::{RuneConfig, load_config};
 use database::{
     actions::{
-        cover_art::scan_cover_arts,
-        metadata::{empty_progress_callback, get_metadata_summary_by_file_ids, scan_audio_library},
-        search::search_for,
+        cover_art::{scan_cover_arts, scan_cover_arts_on_library_changes},
+        enrich::EnrichOptions,
+        file::{RandomFileFilter, RandomWeighting, get_analysis_cluster},
+        index::index_on_library_changes,
+        lyrics::index_lyrics,
+        metadata::{TagChange, scan_audio_library},
+        playlist_mirror::watch_playlist_mirrors,
+        recommendation::{maintain_recommendation_db_if_needed, sync_recommendation_on_events},
+        search::{convert_to_collection_types, optimize_search_index, search_for},
+        seek_table::index_seek_tables,
+        stats::MAX_RATING,
+        tag_conflicts::TagResolution,
+        watch::watch_audio_library,
+    },
+    connection::{
+        MainDbConnection, RecommendationDbConnection, connect_main_db, connect_recommendation_db,
     },
-    connection::{connect_main_db, connect_recommendation_db},
+    events::EventBus,
+    query_profile::attach_query_profile,
 };
 use fsio::FsIo;
+use metadata::backend::MetadataBackends;
+use playback::strategies::{RepeatMode, ShuffleMode};
+use tokio_util::sync::CancellationToken;
 
 use rune::{
     analysis::*,
+    confirm::Confirm,
+    daemon::{DaemonCommand, control_daemon, default_socket_path, run_daemon},
+    dedupe::{DedupeOptions, DedupeResolution, dedupe},
+    dev::gen_library,
+    doctor::doctor,
+    enrich::enrich,
+    features::export_features,
+    fingerprint::{compute_fingerprints, find_duplicates, show_fingerprint},
+    history::show_history,
     index::index_audio_library,
-    mix::{RecommendMixOptions, mixes},
+    info::show_info,
+    languages::{show_metadata_languages, update_metadata_languages},
+    mix::{
+        RecommendMixOptions, daily_mixes, list_mix_presets, list_mixes, mixes, preset_mix,
+        remove_mix_preset, run_mix, save_mix, save_mix_preset,
+    },
     playback::*,
+    playlist_mirror::{add_mirror, list_mirrors, remove_mirror, resolve_mirror, sync_mirrors},
+    profile::{print_profile, record_slow_statements},
     recommend::*,
+    serve::serve,
+    source::{add_source, list_sources, remove_source},
+    splitting::{
+        reapply_splitting_rules, reset_splitting_rules, show_splitting_rules,
+        update_splitting_rules,
+    },
+    tag::{edit_tags, parse_assignments, resolve_conflicts, show_tag_conflicts},
+    trash::{confirm_and_purge_trash, list_trash, purge_trash, restore_trash},
 };
 
 #[derive(Parser)]
@@ -32,6 +80,22 @@ struct Cli {
     #[arg()]
     library: Option<PathBuf>,
 
+    /// Override a config value, e.g. `library.analysis_batch_size=8`
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    overrides: Vec<String>,
+
+    /// Log the time spent in every stage and report the slowest SQL statements
+    #[arg(long, global = true)]
+    profile: bool,
+
+    /// Go ahead with commands that delete or rewrite files without asking
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Refuse every command that changes the library or its database, for scripts
+    #[arg(long, global = true)]
+    read_only: bool,
+
     /// The subcommand to run
     #[command(subcommand)]
     command: Commands,
@@ -43,13 +107,52 @@ enum Commands {
     Scan,
 
     /// Index the audio files in the library
-    Index,
+    Index {
+        /// Compact the search index afterwards and report its size
+        #[arg(long)]
+        optimize: bool,
+    },
+
+    /// Watch the library and update it as files are added, changed or removed
+    Watch {
+        /// Seconds the library has to stay quiet before changes are applied
+        #[arg(short, long)]
+        debounce: Option<u64>,
+    },
 
     /// Analyze the audio files in the library
     Analyze {
         /// The compute device to use (cpu/gpu)
-        #[arg(short, long, default_value = "gpu")]
-        computing_device: String,
+        #[arg(short, long)]
+        computing_device: Option<String>,
+
+        /// Only analyze the files of these IDs
+        #[arg(long, num_args = 1.., group = "selection")]
+        file_id: Vec<i32>,
+
+        /// Only analyze the album of this ID
+        #[arg(long, group = "selection")]
+        album_id: Option<i32>,
+
+        /// Only analyze the playlist of this ID
+        #[arg(long, group = "selection")]
+        playlist_id: Option<i32>,
+
+        /// Only analyze this folder of the library and its subfolders,
+        /// relative paths start from the library root
+        #[arg(long, group = "selection")]
+        directory: Option<PathBuf>,
+    },
+
+    /// Export the analysis vectors of the library for use in other tools
+    ExportFeatures {
+        /// The output format, 'csv' or 'parquet'
+        #[arg(short, long, default_value = "csv")]
+        format: String,
+
+        /// The file to write to
+        #[arg(short, long)]
+        output: PathBuf,
     },
 
     /// Show information of the track in the library
@@ -57,6 +160,25 @@ enum Commands {
         /// A list of file IDs to retrieve information for
         #[arg(short, long, num_args = 1..)]
         file_ids: Vec<i32>,
+
+        /// Print JSON with both raw and formatted values instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the play history of the library
+    History {
+        /// Show the most played tracks instead of the recently played ones
+        #[arg(short, long)]
+        most_played: bool,
+
+        /// The number of tracks to show
+        #[arg(short, long, default_value_t = 20)]
+        num: u64,
+
+        /// Print JSON with both raw and formatted values instead of a table
+        #[arg(long)]
+        json: bool,
     },
 
     /// Play audio files in the library
@@ -65,26 +187,120 @@ enum Commands {
         #[arg()]
         mode: Option<String>,
 
-        /// The ID of the file to play (used with playById mode)
+        /// The ID of the file to play (used with id mode), or of the artist or
+        /// album to start a radio of (used with artist-radio and album-radio modes)
         #[arg(short, long)]
         id: Option<i32>,
+
+        /// Play the album of this ID in track order, instead of a mode
+        #[arg(long, group = "selection")]
+        album_id: Option<i32>,
+
+        /// Play the playlist of this ID, instead of a mode
+        #[arg(long, group = "selection")]
+        playlist_id: Option<i32>,
+
+        /// Play the saved mix of this ID, instead of a mode
+        #[arg(long, group = "selection")]
+        mix: Option<i32>,
+
+        /// Play these files of the library in order, instead of a mode
+        #[arg(long, num_args = 1.., group = "selection")]
+        file: Vec<PathBuf>,
+
+        /// Only pick tracks of these artist IDs (used with random mode)
+        #[arg(long, num_args = 1..)]
+        artist: Vec<i32>,
+
+        /// Only pick tracks of these genre IDs (used with random mode)
+        #[arg(long, num_args = 1..)]
+        genre: Vec<i32>,
+
+        /// Only pick liked tracks (used with random mode)
+        #[arg(long)]
+        liked: bool,
+
+        /// Only pick tracks rated at least this, from 0 to 5 (used with random mode)
+        #[arg(long, value_parser = clap::value_parser!(i32).range(0..=MAX_RATING as i64))]
+        min_rating: Option<i32>,
+
+        /// Only pick tracks that sound like this file ID (used with random mode)
+        #[arg(long)]
+        similar_to: Option<i32>,
+
+        /// Skip tracks played within this many days (used with random mode)
+        #[arg(long)]
+        not_played_within: Option<u64>,
+
+        /// How to weight the random pick: uniform, rating or inverse_play_count
+        #[arg(long, default_value = "uniform")]
+        weighting: RandomWeighting,
+
+        /// Queue the next track ahead of time so album tracks play without gaps
+        #[arg(long)]
+        gapless: bool,
+
+        /// Crossfade consecutive tracks for this many seconds (0-12)
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u64).range(0..=12))]
+        crossfade: u64,
+
+        /// What to repeat: off, one or all
+        #[arg(long, default_value = "off")]
+        repeat: RepeatMode,
+
+        /// How to shuffle the tracks: off, uniform or weighted (by rating)
+        #[arg(long, default_value = "off")]
+        shuffle: ShuffleMode,
+
+        /// The output device to play through, see `devices`
+        #[arg(long)]
+        device: Option<String>,
     },
 
+    /// List the audio output devices
+    Devices,
+
     /// Recommend music
     Recommend {
-        /// The ID of the item to get recommendations for
-        #[arg(short, long, group = "recommend_group")]
-        item_id: Option<i32>,
+        /// The ID of the item to get recommendations for, can be repeated
+        /// to recommend around all of them
+        #[arg(short, long)]
+        item_id: Vec<i32>,
+
+        /// The file path of the music to get recommendations for, can be
+        /// repeated to recommend around all of them
+        #[arg(short = 'p', long)]
+        file_path: Vec<PathBuf>,
 
-        /// The file path of the music to get recommendations for
-        #[arg(short = 'p', long, group = "recommend_group")]
-        file_path: Option<PathBuf>,
+        /// The ID of an item the recommendations should not be like, can be
+        /// repeated
+        #[arg(long, value_name = "ITEM_ID")]
+        exclude_like: Vec<i32>,
+
+        /// The file path of music the recommendations should not be like,
+        /// can be repeated
+        #[arg(long, value_name = "FILE_PATH")]
+        exclude_like_path: Vec<PathBuf>,
 
         /// The number of recommendations to retrieve
         #[arg(short, long, default_value_t = 10)]
         num: usize,
 
-        /// The format of the output (json or m3u8)
+        /// Only recommend tracks the user has liked
+        #[arg(long)]
+        liked: bool,
+
+        /// Only recommend tracks of the genre, can be repeated
+        #[arg(long = "genre", value_name = "GENRE")]
+        genres: Vec<String>,
+
+        /// Weigh a feature group (energy, spectral, chroma, perceptual,
+        /// loudness, timbre or bands), e.g. `chroma=2` for the same mood or
+        /// `timbre=2` for the same sound, can be repeated
+        #[arg(long = "weight", value_name = "GROUP=WEIGHT")]
+        weights: Vec<String>,
+
+        /// The format of the output (json, m3u8 or xspf)
         #[arg(short, long)]
         format: Option<String>,
 
@@ -93,17 +309,34 @@ enum Commands {
         output: Option<PathBuf>,
     },
 
-    /// Recommend mixes
+    /// Recommend mixes, or save, list and run saved mixes
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Mix {
+        #[command(subcommand)]
+        action: Option<MixAction>,
+
         /// The mix parameters to get recommendations for
-        #[arg(short, long)]
-        mix_parameters: String,
+        #[arg(short, long, required_unless_present_any = ["daily", "preset"])]
+        mix_parameters: Option<String>,
+
+        /// Use the parameters of a mix preset instead, like `Focus`, see
+        /// `mix presets`
+        #[arg(short, long, conflicts_with_all = ["mix_parameters", "daily"])]
+        preset: Option<String>,
+
+        /// Show the daily mixes, generated from the analysis and play history
+        #[arg(long, conflicts_with_all = ["mix_parameters", "format"])]
+        daily: bool,
+
+        /// Generate the daily mixes again even if they were generated today
+        #[arg(long, requires = "daily")]
+        regenerate: bool,
 
         /// The number of recommendations to retrieve
         #[arg(short, long, default_value_t = 10)]
         num: usize,
 
-        /// The format of the output (json or m3u8)
+        /// The format of the output (json, m3u8 or xspf)
         #[arg(short, long)]
         format: Option<String>,
 
@@ -114,31 +347,636 @@ enum Commands {
 
     /// Search the audio library
     Search {
-        /// The search query string
+        /// The search query string, terms can be scoped to a field and
+        /// joined with OR or excluded with NOT, e.g.
+        /// `artist:radiohead album:"ok computer" NOT live`
         #[arg(short, long)]
         query: String,
 
+        /// Only search these collection types (track, artist, album,
+        /// directory or playlist), can be repeated
+        #[arg(short = 't', long = "type", value_name = "TYPE")]
+        types: Vec<String>,
+
         /// The number of results to retrieve per collection type
         #[arg(short, long, default_value_t = 10)]
         num: usize,
     },
+
+    /// Manage extra roots of the library, such as an SD card or a NAS
+    Source {
+        #[command(subcommand)]
+        action: SourceAction,
+    },
+
+    /// List, restore or purge files removed from the library
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+
+    /// Compute acoustic fingerprints and find duplicate tracks
+    Fingerprint {
+        #[command(subcommand)]
+        action: FingerprintAction,
+    },
+
+    /// Find tracks stored more than once, by content, tags or fingerprint
+    Dedupe {
+        /// Also group files with similar fingerprints, see `fingerprint duplicates`
+        #[arg(long)]
+        fingerprint: bool,
+
+        /// Similarity above which two fingerprints are duplicates, between 0 and 1
+        #[arg(long, default_value_t = 0.85)]
+        threshold: f32,
+
+        /// What to do with the copies of lower bitrate, 'report', 'move' or 'mark'
+        #[arg(short, long, default_value = "report")]
+        resolve: String,
+
+        /// The folder to move duplicates to with `--resolve move`
+        #[arg(short, long)]
+        target: Option<PathBuf>,
+
+        /// Ask which file to keep for every group
+        #[arg(short, long)]
+        interactive: bool,
+    },
+
+    /// Fill in missing album artists, dates, track numbers and MBIDs from MusicBrainz
+    Enrich {
+        /// An AcoustID API key, to identify fingerprinted files by how they sound
+        #[arg(long)]
+        acoustid_key: Option<String>,
+
+        /// Score a match needs, between 0 and 1
+        #[arg(long, default_value_t = 0.8)]
+        min_score: f64,
+
+        /// Look up at most this many files
+        #[arg(short, long)]
+        limit: Option<u64>,
+    },
+
+    /// Edit the tags of files, both in the files and the library
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+
+    /// Keep playlists in sync with M3U files edited by other apps
+    PlaylistMirror {
+        #[command(subcommand)]
+        action: PlaylistMirrorAction,
+    },
+
+    /// Show or pick the languages of multi-language tags, e.g. `ja-Latn en`
+    Languages {
+        /// Languages to show, most preferred first
+        languages: Vec<String>,
+
+        /// Show the original tags again
+        #[arg(long, conflicts_with = "languages")]
+        reset: bool,
+    },
+
+    /// Configure how artist and genre tags are split into several names
+    Splitting {
+        #[command(subcommand)]
+        action: SplittingAction,
+    },
+
+    /// Check the recommendation database for vectors of removed files, and
+    /// list the indexes slow statements were missing
+    Doctor {
+        /// Drop orphaned vectors, rebuild the index and compact the database
+        #[arg(long)]
+        fix: bool,
+
+        /// Build the index from scratch out of the analysis results, then compact it
+        #[arg(long)]
+        rebuild: bool,
+    },
+
+    /// Serve the library as a read-only JSON API for other apps and scripts
+    Serve {
+        /// The address to listen on
+        #[arg(short, long, default_value = "127.0.0.1:7863")]
+        addr: SocketAddr,
+    },
+
+    /// Play the library in the background, controlled with `ctl` over a local socket
+    Daemon {
+        /// The socket to listen on, `<library>/.rune/daemon.sock` by default
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// Queue the next track ahead of time so album tracks play without gaps
+        #[arg(long)]
+        gapless: bool,
+
+        /// Don't watch the library for changes while playing
+        #[arg(long)]
+        no_watch: bool,
+    },
+
+    /// Control a running daemon
+    Ctl {
+        #[command(subcommand)]
+        action: CtlAction,
+
+        /// The socket the daemon listens on, `<library>/.rune/daemon.sock` by default
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+
+    /// Tools for developing and benchmarking Rune
+    Dev {
+        #[command(subcommand)]
+        action: DevAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum MixAction {
+    /// Save mix parameters as a mix
+    Save {
+        /// The name of the mix
+        #[arg(short, long)]
+        name: String,
+
+        /// The mix parameters, like `lib::artist(1);lib::played>=5`
+        #[arg(short, long)]
+        mix_parameters: String,
+
+        /// The group to show the mix in
+        #[arg(short, long, default_value = "Mixes")]
+        group: String,
+
+        /// An image to show for the mix instead of the covers of its tracks
+        #[arg(short, long)]
+        cover: Option<PathBuf>,
+    },
+
+    /// List the saved mixes with their parameters
+    List,
+
+    /// List the mix presets, built-in and saved
+    Presets,
+
+    /// Save mix parameters as a preset, replacing the preset with the same name
+    SavePreset {
+        /// The name of the preset
+        #[arg(short, long)]
+        name: String,
+
+        /// The mix parameters, like `lib::all(true);filter::tempo(60-100)`
+        #[arg(short, long)]
+        mix_parameters: String,
+    },
+
+    /// Remove a saved preset, bringing back the built-in one it replaced
+    RemovePreset {
+        /// The name of the preset
+        #[arg()]
+        name: String,
+    },
+
+    /// Get recommendations from a saved mix
+    Run {
+        /// The ID or the UUID of the mix
+        #[arg()]
+        mix: String,
+
+        /// The number of recommendations to retrieve
+        #[arg(short, long, default_value_t = 10)]
+        num: usize,
+
+        /// The format of the output (json, m3u8 or xspf)
+        #[arg(short, long)]
+        format: Option<String>,
+
+        /// The output file path (required if format is specified)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SourceAction {
+    /// Add a directory as a source, it is imported on the next scan
+    Add {
+        /// A name to show for the source
+        #[arg(short, long)]
+        name: String,
+
+        /// The directory to add
+        #[arg()]
+        path: PathBuf,
+    },
+
+    /// List the sources and whether they can be reached
+    List,
+
+    /// Remove a source together with its files from the library
+    Remove {
+        /// The ID of the source
+        #[arg()]
+        id: i32,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashAction {
+    /// List the removed files that can still be restored
+    List,
+
+    /// Restore removed files
+    Restore {
+        /// The IDs of the trash entries to restore
+        #[arg(num_args = 1.., required_unless_present = "all")]
+        ids: Vec<i32>,
+
+        /// Restore every file in the trash
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Drop removed files kept for longer than the retention period
+    Purge {
+        /// Days to keep removed files, defaults to `library.trash_retention_days`
+        #[arg(long)]
+        days: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    /// Set tags of the matching files
+    Set {
+        /// Files to edit, a glob over paths relative to the library, e.g. 'Artist/*.flac'
+        #[arg()]
+        pattern: String,
+
+        /// The tags to set, e.g. `album_artist="Various Artists"`
+        #[arg(required = true, value_name = "KEY=VALUE")]
+        assignments: Vec<String>,
+
+        /// Only list the files that would be edited
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Remove tags from the matching files
+    Remove {
+        /// Files to edit, a glob over paths relative to the library
+        #[arg()]
+        pattern: String,
+
+        /// The tags to remove, e.g. `comment`
+        #[arg(required = true)]
+        keys: Vec<String>,
+
+        /// Only list the files that would be edited
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// List the tags that differ between the database and the matching files
+    Conflicts {
+        /// Files to compare, a glob over paths relative to the library
+        #[arg()]
+        pattern: String,
+    },
+
+    /// Settle the tags that differ between the database and the matching files
+    Resolve {
+        /// Files to resolve, a glob over paths relative to the library
+        #[arg()]
+        pattern: String,
+
+        /// The side to keep: `file`, `database` or `merge` to keep both
+        #[arg(long = "use", value_name = "SIDE")]
+        side: String,
+
+        /// Only resolve these tags, all conflicting tags if omitted
+        #[arg(long = "key")]
+        keys: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum FingerprintAction {
+    /// Fingerprint the files that don't have a fingerprint yet
+    Compute,
+
+    /// Print the fingerprint of a file in the format of `fpcalc`, for AcoustID lookups
+    Show {
+        /// The ID of the file
+        #[arg()]
+        file_id: i32,
+    },
+
+    /// Compare the fingerprints and list the duplicate files
+    Duplicates {
+        /// Similarity above which two files are duplicates, between 0 and 1
+        #[arg(long, default_value_t = 0.85)]
+        threshold: f32,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlaylistMirrorAction {
+    /// Mirror a playlist to a file, an existing file is read into an empty playlist
+    Add {
+        /// The ID of the playlist
+        #[arg()]
+        playlist_id: i32,
+
+        /// The M3U file to keep in sync
+        #[arg()]
+        path: PathBuf,
+    },
+
+    /// List the mirrored playlists and whether they are in conflict
+    List,
+
+    /// Stop mirroring a playlist, the file is kept
+    Remove {
+        /// The ID of the playlist
+        #[arg()]
+        playlist_id: i32,
+    },
+
+    /// Sync every mirrored playlist once
+    Sync,
+
+    /// Resume syncing a conflicting playlist by keeping one side
+    Resolve {
+        /// The ID of the playlist
+        #[arg()]
+        playlist_id: i32,
+
+        /// The side to keep, 'file' or 'playlist'
+        #[arg(short, long)]
+        keep: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SplittingAction {
+    /// Show the splitting rules of the library
+    Show,
+
+    /// Replace the given lists of the splitting rules, the others are kept
+    Set {
+        /// A separator between artists, repeat for several
+        #[arg(long = "artist-delimiter")]
+        artist_delimiters: Vec<String>,
+
+        /// A separator between genres, repeat for several
+        #[arg(long = "genre-delimiter")]
+        genre_delimiters: Vec<String>,
+
+        /// A name never split, like 'AC/DC', repeat for several
+        #[arg(long = "exception")]
+        exceptions: Vec<String>,
+    },
+
+    /// Go back to the default splitting rules
+    Reset,
+
+    /// Split the stored artist and genre tags of every file again
+    Reapply,
+}
+
+#[derive(Subcommand)]
+enum CtlAction {
+    /// Start or resume playing
+    Play,
+    /// Pause playing
+    Pause,
+    /// Pause while playing, play otherwise
+    Toggle,
+    /// Skip to the next track
+    Next,
+    /// Go back to the previous track
+    Previous,
+    /// Stop playing
+    Stop,
+    /// Append tracks to the queue
+    Enqueue {
+        /// File IDs, or paths of files in the library
+        #[arg(required = true)]
+        tracks: Vec<String>,
+    },
+    /// Empty the queue
+    Clear,
+    /// Show the current track and the position in the queue
+    Status {
+        /// Print JSON instead of a line
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+impl CtlAction {
+    fn to_command(&self) -> DaemonCommand {
+        match self {
+            CtlAction::Play => DaemonCommand::Play,
+            CtlAction::Pause => DaemonCommand::Pause,
+            CtlAction::Toggle => DaemonCommand::Toggle,
+            CtlAction::Next => DaemonCommand::Next,
+            CtlAction::Previous => DaemonCommand::Previous,
+            CtlAction::Stop => DaemonCommand::Stop,
+            CtlAction::Enqueue { tracks } => DaemonCommand::Enqueue(tracks.clone()),
+            CtlAction::Clear => DaemonCommand::Clear,
+            CtlAction::Status { .. } => DaemonCommand::Status,
+        }
+    }
+}
+
+impl Commands {
+    /// Whether the command may write to the library or its database, which
+    /// `--read-only` refuses.
+    fn writes_library(&self) -> bool {
+        match self {
+            Commands::Scan
+            | Commands::Index { .. }
+            | Commands::Watch { .. }
+            | Commands::Analyze { .. }
+            | Commands::Enrich { .. }
+            | Commands::Dev { .. } => true,
+            Commands::ExportFeatures { .. }
+            | Commands::Info { .. }
+            | Commands::History { .. }
+            | Commands::Play { .. }
+            | Commands::Devices
+            | Commands::Recommend { .. }
+            | Commands::Search { .. }
+            | Commands::Serve { .. }
+            | Commands::Ctl { .. } => false,
+            Commands::Mix { action, daily, .. } => match action {
+                Some(MixAction::Save { .. })
+                | Some(MixAction::SavePreset { .. })
+                | Some(MixAction::RemovePreset { .. }) => true,
+                Some(MixAction::List) | Some(MixAction::Presets) | Some(MixAction::Run { .. }) => {
+                    false
+                }
+                // Daily mixes are generated once a day when shown
+                None => *daily,
+            },
+            Commands::Source { action } => !matches!(action, SourceAction::List),
+            Commands::Trash { action } => !matches!(action, TrashAction::List),
+            Commands::Fingerprint { action } => matches!(action, FingerprintAction::Compute),
+            Commands::Dedupe { resolve, .. } => resolve != "report",
+            Commands::Tag { action } => match action {
+                TagAction::Set { dry_run, .. } | TagAction::Remove { dry_run, .. } => !dry_run,
+                TagAction::Conflicts { .. } => false,
+                TagAction::Resolve { .. } => true,
+            },
+            Commands::PlaylistMirror { action } => !matches!(action, PlaylistMirrorAction::List),
+            Commands::Languages { languages, reset } => *reset || !languages.is_empty(),
+            Commands::Splitting { action } => !matches!(action, SplittingAction::Show),
+            Commands::Doctor { fix, rebuild } => *fix || *rebuild,
+            Commands::Daemon { no_watch, .. } => !no_watch,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum DevAction {
+    /// Fill the library path with tiny silent tracks carrying varied tags
+    GenLibrary {
+        /// How many tracks to generate
+        #[arg(short, long, default_value_t = 10000)]
+        tracks: usize,
+
+        /// The same seed always generates the same library
+        #[arg(short, long, default_value_t = 0)]
+        seed: u64,
+    },
+}
+
+/// A token cancelled once Ctrl+C is pressed.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel_token = CancellationToken::new();
+    tokio::spawn({
+        let cancel_token = cancel_token.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel_token.cancel();
+            }
+        }
+    });
+
+    cancel_token
+}
+
+/// Keep the library, its search index, cover arts and recommendations up
+/// to date with the files until the token is cancelled.
+async fn watch_library(
+    fsio: &Arc<FsIo>,
+    main_db: &MainDbConnection,
+    analysis_db: &RecommendationDbConnection,
+    canonicalized_path: &Path,
+    config: &RuneConfig,
+    debounce: Option<u64>,
+    cancel_token: CancellationToken,
+) {
+    // Subscribe before watching, so no change goes unnoticed
+    let events = EventBus::default();
+    let index_events = events.subscribe();
+    let cover_art_events = events.subscribe();
+    let recommendation_events = events.subscribe();
+
+    let debounce = Duration::from_secs(debounce.unwrap_or(config.library.watch_debounce_secs));
+    let (library, mirrors, index, cover_arts, recommendation) = tokio::join!(
+        watch_audio_library(
+            fsio,
+            &MetadataBackends::default(),
+            main_db,
+            canonicalized_path,
+            &events,
+            debounce,
+            cancel_token.clone(),
+        ),
+        watch_playlist_mirrors(
+            main_db,
+            canonicalized_path,
+            "",
+            debounce,
+            Duration::from_secs(config.library.playlist_mirror_poll_secs),
+            cancel_token.clone(),
+        ),
+        index_on_library_changes(main_db, index_events, cancel_token.clone()),
+        scan_cover_arts_on_library_changes(
+            Arc::clone(fsio),
+            main_db,
+            canonicalized_path,
+            "",
+            cover_art_events,
+            cancel_token.clone(),
+        ),
+        sync_recommendation_on_events(
+            main_db,
+            analysis_db,
+            recommendation_events,
+            cancel_token.clone(),
+        ),
+    );
+
+    if let Err(e) = library {
+        error!("Failed to watch library: {e:#?}");
+    }
+    if let Err(e) = mirrors {
+        error!("Failed to watch playlist mirrors: {e:#?}");
+    }
+    for (name, result) in [
+        ("index changed files", index),
+        ("scan cover arts", cover_arts),
+        ("sync recommendations", recommendation),
+    ] {
+        if let Err(e) = result {
+            error!("Failed to {name}: {e:#?}");
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
-    let filter = EnvFilter::new(
+    let mut filter = EnvFilter::new(
         "symphonia_format_ogg=off,symphonia_core=off,symphonia_bundle_mp3::demuxer=off,tantivy::directory=off,tantivy::indexer=off,sea_orm_migration::migrator=off,info",
     );
+    if cli.profile {
+        // Stage timings are logged by `measure_time!` at the debug level
+        filter = filter.add_directive("rune_cli=debug".parse().unwrap());
+    }
 
     tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_test_writer()
         .init();
+    if cli.read_only && cli.command.writes_library() {
+        error!("Refusing to run a command that changes the library in read-only mode");
+        return;
+    }
+
     // Determine the path from either the option or the positional argument
     let path = cli.library.expect("Path is required");
 
+    // The library doesn't exist yet, so there is nothing to connect to
+    if let Commands::Dev {
+        action: DevAction::GenLibrary { tracks, seed },
+    } = &cli.command
+    {
+        gen_library(&path, *tracks, *seed);
+        return;
+    }
+
     let canonicalized_path = match canonicalize(&path) {
         Ok(path) => path,
         Err(e) => {
@@ -147,6 +985,16 @@ async fn main() {
         }
     };
 
+    // Only the running daemon is talked to
+    if let Commands::Ctl { action, socket } = &cli.command {
+        let socket = socket
+            .clone()
+            .unwrap_or_else(|| default_socket_path(&canonicalized_path));
+        let json = matches!(action, CtlAction::Status { json: true });
+        control_daemon(&socket, action.to_command(), json).await;
+        return;
+    }
+
     let lib_path = match canonicalized_path.to_str() {
         Some(path) => path,
         _ => {
@@ -156,8 +1004,21 @@ async fn main() {
     };
     let fsio = Arc::new(FsIo::new());
 
+    let config = match load_config(Some(&canonicalized_path), &cli.overrides) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to load config: {e:#}");
+            return;
+        }
+    };
+    provider_client::configure(
+        config.network.offline,
+        config.network.max_requests_per_second,
+    );
+    let db_path = config.paths.database_dir.as_ref().and_then(|x| x.to_str());
+
     // TODO: INTEGRATING THE CLIENT ID LATER
-    let main_db = match connect_main_db(lib_path, None, "").await {
+    let mut main_db = match connect_main_db(lib_path, db_path, "").await {
         Ok(db) => db,
         Err(e) => {
             error!("Failed to connect to main database: {e}");
@@ -165,7 +1026,7 @@ async fn main() {
         }
     };
 
-    let analysis_db = match connect_recommendation_db(lib_path, None) {
+    let analysis_db = match connect_recommendation_db(lib_path, db_path) {
         Ok(db) => db,
         Err(e) => {
             error!("Failed to connect to analysis database: {e}");
@@ -173,88 +1034,349 @@ async fn main() {
         }
     };
 
+    // Only statements run by the command itself are of interest, so the
+    // profile starts after the migrations
+    let slow_threshold = (config.database.slow_query_ms > 0)
+        .then(|| Duration::from_millis(config.database.slow_query_ms));
+    let query_profile = (cli.profile || slow_threshold.is_some())
+        .then(|| attach_query_profile(&mut main_db, slow_threshold));
+    let confirm = Confirm {
+        assume_yes: cli.yes,
+        read_only: cli.read_only,
+    };
+    let started_at = Instant::now();
+
     match &cli.command {
         Commands::Scan => {
-            let _ = scan_audio_library(
-                &fsio,
-                &main_db,
-                &path,
-                true,
-                false,
-                empty_progress_callback,
-                None,
-            )
-            .await;
-            let _ = scan_cover_arts(fsio, &main_db, &path, "", 10, |_now, _total| {}, None).await;
+            let _ = measure_time!(
+                "Scan",
+                scan_audio_library(
+                    &fsio,
+                    &MetadataBackends::default(),
+                    &main_db,
+                    &path,
+                    true,
+                    false,
+                    log_progress("Scan"),
+                    None,
+                )
+                .await
+            );
+            measure_time!(
+                "Purge trash",
+                purge_trash(&main_db, config.library.trash_retention_days).await
+            );
+            let batch_size = config.batch_size(config.library.cover_art_batch_size);
+            let _ = measure_time!(
+                "Cover arts",
+                scan_cover_arts(
+                    Arc::clone(&fsio),
+                    &main_db,
+                    &path,
+                    "",
+                    batch_size,
+                    log_progress("Cover arts"),
+                    None,
+                )
+                .await
+            );
+            let _ = measure_time!(
+                "Seek tables",
+                index_seek_tables(
+                    Arc::clone(&fsio),
+                    &main_db,
+                    &path,
+                    batch_size,
+                    |_now, _total| {},
+                    None,
+                )
+                .await
+            );
+            let _ = measure_time!(
+                "Lyrics",
+                index_lyrics(fsio, &main_db, &path, batch_size, |_now, _total| {}, None).await
+            );
+            // Vectors of the files removed by the scan are only dropped once
+            // enough of them pile up
+            if let Err(e) = maintain_recommendation_db_if_needed(&main_db, &analysis_db).await {
+                error!("Failed to maintain the recommendation database: {e:#}");
+            }
             info!("Library scanned successfully.");
         }
-        Commands::Index => {
-            index_audio_library(&main_db).await;
+        Commands::Index { optimize } => {
+            measure_time!("Index", index_audio_library(&main_db).await);
+            if *optimize {
+                match measure_time!(
+                    "Optimize search index",
+                    optimize_search_index(&main_db).await
+                ) {
+                    Ok(report) => info!(
+                        "Search index: {} -> {} documents, {} -> {} bytes, {} orphaned documents removed",
+                        report.before.documents,
+                        report.after.documents,
+                        report.before.size_bytes,
+                        report.after.size_bytes,
+                        report.removed_orphans
+                    ),
+                    Err(e) => error!("Failed to optimize the search index: {e:#}"),
+                }
+            }
         }
-        Commands::Analyze { computing_device } => {
-            analyze_audio_library(
-                computing_device.as_str().into(),
-                fsio,
+        Commands::Watch { debounce } => {
+            watch_library(
+                &fsio,
                 &main_db,
                 &analysis_db,
-                &path,
-                "",
+                &canonicalized_path,
+                &config,
+                *debounce,
+                cancel_on_ctrl_c(),
             )
             .await;
         }
-        Commands::Info { file_ids } => {
-            match get_metadata_summary_by_file_ids(&main_db, file_ids.to_vec()).await {
-                Ok(summaries) => {
-                    let mut table = Table::new();
-                    table.add_row(row![
-                        "ID",
-                        "Artist",
-                        "Album",
-                        "Title",
-                        "Track Number",
-                        "Duration",
-                        "Cover Art ID"
-                    ]);
-
-                    for summary in summaries {
-                        table.add_row(row![
-                            summary.id,
-                            summary.artist,
-                            summary.album,
-                            summary.title,
-                            summary.track_number,
-                            summary.duration,
-                            summary.cover_art_id.unwrap_or_default()
-                        ]);
-                    }
+        Commands::Analyze {
+            computing_device,
+            file_id,
+            album_id,
+            playlist_id,
+            directory,
+        } => {
+            let computing_device = computing_device
+                .as_deref()
+                .unwrap_or(&config.library.computing_device);
 
-                    table.printstd();
-                }
-                Err(e) => {
-                    error!("Failed to retrieve metadata summary: {e}");
+            let selection = match (album_id, playlist_id, directory) {
+                (Some(id), _, _) => Some(AnalysisSelection::Album(*id)),
+                (_, Some(id), _) => Some(AnalysisSelection::Playlist(*id)),
+                (_, _, Some(directory)) => {
+                    let directory = match canonicalize(canonicalized_path.join(directory)) {
+                        Ok(directory) => directory,
+                        Err(e) => {
+                            error!("Failed to canonicalize {}: {e}", directory.display());
+                            return;
+                        }
+                    };
+                    let Ok(directory) = directory.strip_prefix(&canonicalized_path) else {
+                        error!("{} is outside of the library", directory.display());
+                        return;
+                    };
+                    Some(AnalysisSelection::Directory(
+                        directory.to_string_lossy().replace('\\', "/"),
+                    ))
                 }
+                _ if !file_id.is_empty() => Some(AnalysisSelection::Files(file_id.clone())),
+                _ => None,
+            };
+
+            if let Some(selection) = selection {
+                measure_time!(
+                    "Analyze",
+                    analyze_selection(
+                        computing_device.into(),
+                        fsio,
+                        &main_db,
+                        &analysis_db,
+                        &path,
+                        "",
+                        config.batch_size(config.library.analysis_batch_size),
+                        selection,
+                    )
+                    .await
+                );
+            } else {
+                measure_time!(
+                    "Analyze",
+                    analyze_audio_library(
+                        computing_device.into(),
+                        fsio,
+                        &main_db,
+                        &analysis_db,
+                        &path,
+                        "",
+                        config.batch_size(config.library.analysis_batch_size),
+                    )
+                    .await
+                );
             }
         }
+        Commands::ExportFeatures { format, output } => {
+            measure_time!(
+                "Export features",
+                export_features(&main_db, format, output).await
+            );
+        }
+        Commands::Info { file_ids, json } => {
+            show_info(&main_db, file_ids.to_vec(), *json).await;
+        }
+        Commands::History {
+            most_played,
+            num,
+            json,
+        } => {
+            show_history(&main_db, *most_played, *num, *json).await;
+        }
         // In the main function, update the match statement for Commands::Play
-        Commands::Play { mode, id } => match mode.as_deref() {
+        Commands::Play {
+            mode,
+            id,
+            album_id,
+            playlist_id,
+            mix,
+            file,
+            artist,
+            genre,
+            liked,
+            min_rating,
+            similar_to,
+            not_played_within,
+            weighting,
+            gapless,
+            crossfade,
+            repeat,
+            shuffle,
+            device,
+        } => match mode.as_deref() {
+            _ if album_id.is_some()
+                || playlist_id.is_some()
+                || mix.is_some()
+                || !file.is_empty() =>
+            {
+                let selection = match (album_id, playlist_id, mix) {
+                    (Some(id), _, _) => PlaySelection::Album(*id),
+                    (_, Some(id), _) => PlaySelection::Playlist(*id),
+                    (_, _, Some(id)) => PlaySelection::Mix(*id),
+                    _ => PlaySelection::Files(file.clone()),
+                };
+
+                play_selection(
+                    &main_db,
+                    &analysis_db,
+                    &canonicalized_path,
+                    selection,
+                    PlaybackOptions {
+                        gapless: *gapless,
+                        crossfade: Duration::from_secs(*crossfade),
+                        repeat: *repeat,
+                        shuffle: *shuffle,
+                        device: device.clone(),
+                    },
+                )
+                .await;
+            }
             Some("random") => {
-                play_random(&main_db, &canonicalized_path).await;
+                let cluster = match similar_to {
+                    Some(seed) => match get_analysis_cluster(&analysis_db, *seed, 100) {
+                        Ok(cluster) => Some(cluster),
+                        Err(e) => {
+                            error!("Failed to get similar tracks: {e}");
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+
+                let not_played_within = match not_played_within {
+                    Some(days) => match days.checked_mul(24 * 60 * 60) {
+                        Some(seconds) => Some(Duration::from_secs(seconds)),
+                        None => {
+                            error!("Period too long: {days} days");
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+
+                let filter = RandomFileFilter {
+                    artist_ids: artist.clone(),
+                    genre_ids: genre.clone(),
+                    liked_only: *liked,
+                    min_rating: *min_rating,
+                    cluster,
+                    not_played_within,
+                    weighting: *weighting,
+                };
+
+                play_random(
+                    &main_db,
+                    &canonicalized_path,
+                    &filter,
+                    PlaybackOptions {
+                        gapless: *gapless,
+                        crossfade: Duration::from_secs(*crossfade),
+                        repeat: *repeat,
+                        shuffle: *shuffle,
+                        device: device.clone(),
+                    },
+                )
+                .await;
             }
             Some("id") => {
                 if let Some(file_id) = id {
-                    play_by_id(&main_db, &canonicalized_path, *file_id).await;
+                    play_by_id(
+                        &main_db,
+                        &canonicalized_path,
+                        *file_id,
+                        PlaybackOptions {
+                            gapless: *gapless,
+                            crossfade: Duration::from_secs(*crossfade),
+                            repeat: *repeat,
+                            shuffle: *shuffle,
+                            device: device.clone(),
+                        },
+                    )
+                    .await;
                 } else {
                     error!("File ID is required for playById mode.");
                 }
             }
+            Some(radio @ ("artist-radio" | "album-radio")) => {
+                let Some(seed_id) = id else {
+                    error!("An ID is required for {radio} mode.");
+                    return;
+                };
+
+                let options = PlaybackOptions {
+                    gapless: *gapless,
+                    crossfade: Duration::from_secs(*crossfade),
+                    repeat: *repeat,
+                    shuffle: *shuffle,
+                    device: device.clone(),
+                };
+
+                if radio == "artist-radio" {
+                    start_artist_radio(
+                        &main_db,
+                        &analysis_db,
+                        &canonicalized_path,
+                        *seed_id,
+                        options,
+                    )
+                    .await;
+                } else {
+                    start_album_radio(
+                        &main_db,
+                        &analysis_db,
+                        &canonicalized_path,
+                        *seed_id,
+                        options,
+                    )
+                    .await;
+                }
+            }
             _ => {
                 info!("Mode not implemented!");
             }
         },
+        Commands::Devices => list_devices(),
         Commands::Recommend {
             item_id,
             file_path,
+            exclude_like,
+            exclude_like_path,
             num,
+            liked,
+            genres,
+            weights,
             format,
             output,
         } => {
@@ -264,9 +1386,14 @@ async fn main() {
                 RecommendMusicOptions {
                     canonicalized_path: &canonicalized_path,
                     path: &path,
-                    item_id: *item_id,
-                    file_path: file_path.as_ref(),
+                    item_ids: item_id,
+                    file_paths: file_path,
+                    excluded_ids: exclude_like,
+                    excluded_paths: exclude_like_path,
                     num: *num,
+                    liked_only: *liked,
+                    genres,
+                    weights,
                     format: format.as_ref().map(|x| x.as_str()),
                     output: output.as_ref(),
                 },
@@ -274,32 +1401,406 @@ async fn main() {
             .await;
         }
         Commands::Mix {
+            action,
             mix_parameters,
+            preset,
+            daily,
+            regenerate,
             num,
             format,
             output,
+        } => match (action, mix_parameters) {
+            (
+                Some(MixAction::Save {
+                    name,
+                    mix_parameters,
+                    group,
+                    cover,
+                }),
+                _,
+            ) => {
+                save_mix(&main_db, name, group, mix_parameters, cover.as_deref()).await;
+            }
+            (Some(MixAction::List), _) => {
+                list_mixes(&main_db).await;
+            }
+            (Some(MixAction::Presets), _) => {
+                list_mix_presets(&main_db).await;
+            }
+            (
+                Some(MixAction::SavePreset {
+                    name,
+                    mix_parameters,
+                }),
+                _,
+            ) => {
+                save_mix_preset(&main_db, name, mix_parameters).await;
+            }
+            (Some(MixAction::RemovePreset { name }), _) => {
+                remove_mix_preset(&main_db, name).await;
+            }
+            (
+                Some(MixAction::Run {
+                    mix,
+                    num,
+                    format,
+                    output,
+                }),
+                _,
+            ) => {
+                run_mix(
+                    &main_db,
+                    &analysis_db,
+                    mix,
+                    RecommendMixOptions {
+                        lib_path: &canonicalized_path,
+                        num: *num,
+                        format: format.as_ref().map(|x| x.as_str()),
+                        output: output.as_ref(),
+                    },
+                )
+                .await;
+            }
+            (None, Some(mix_parameters)) if !*daily => {
+                mixes(
+                    &main_db,
+                    &analysis_db,
+                    mix_parameters,
+                    RecommendMixOptions {
+                        lib_path: &canonicalized_path,
+                        num: *num,
+                        format: format.as_ref().map(|x| x.as_str()),
+                        output: output.as_ref(),
+                    },
+                )
+                .await;
+            }
+            (None, _) => match preset {
+                Some(preset) => {
+                    preset_mix(
+                        &main_db,
+                        &analysis_db,
+                        preset,
+                        RecommendMixOptions {
+                            lib_path: &canonicalized_path,
+                            num: *num,
+                            format: format.as_ref().map(|x| x.as_str()),
+                            output: output.as_ref(),
+                        },
+                    )
+                    .await;
+                }
+                None => {
+                    daily_mixes(&main_db, *regenerate).await;
+                }
+            },
+        },
+        Commands::Search { query, types, num } => {
+            let types = convert_to_collection_types(types.clone());
+            let types = if types.is_empty() { None } else { Some(types) };
+
+            match search_for(&main_db, query, types, *num).await {
+                Ok(results) => {
+                    for (collection_type, items) in results {
+                        let items: Vec<String> = items
+                            .iter()
+                            .map(|x| format!("{} ({:.2})", x.id, x.score))
+                            .collect();
+                        info!("{collection_type:?}: {}", items.join(", "));
+                    }
+                }
+                Err(e) => {
+                    error!("Search failed: {e}");
+                }
+            }
+        }
+        Commands::Source { action } => match action {
+            SourceAction::Add { name, path } => {
+                add_source(&fsio, &main_db, &canonicalized_path, name, path).await;
+            }
+            SourceAction::List => {
+                list_sources(&main_db).await;
+            }
+            SourceAction::Remove { id } => {
+                remove_source(&main_db, *id).await;
+            }
+        },
+        Commands::Trash { action } => match action {
+            TrashAction::List => {
+                list_trash(&main_db).await;
+            }
+            TrashAction::Restore { ids, all } => {
+                restore_trash(&main_db, ids.to_vec(), *all).await;
+            }
+            TrashAction::Purge { days } => {
+                confirm_and_purge_trash(
+                    &main_db,
+                    days.unwrap_or(config.library.trash_retention_days),
+                    confirm,
+                )
+                .await;
+            }
+        },
+        Commands::Doctor { fix, rebuild } => {
+            doctor(&main_db, analysis_db, *fix, *rebuild).await;
+        }
+        Commands::Fingerprint { action } => {
+            let batch_size = config.batch_size(config.library.analysis_batch_size);
+            match action {
+                FingerprintAction::Compute => {
+                    measure_time!(
+                        "Fingerprints",
+                        compute_fingerprints(fsio, &main_db, &path, batch_size).await
+                    );
+                }
+                FingerprintAction::Show { file_id } => {
+                    show_fingerprint(&main_db, *file_id).await;
+                }
+                FingerprintAction::Duplicates { threshold } => {
+                    find_duplicates(&main_db, *threshold, batch_size).await;
+                }
+            }
+        }
+        Commands::Dedupe {
+            fingerprint,
+            threshold,
+            resolve,
+            target,
+            interactive,
         } => {
-            mixes(
+            let resolution = match DedupeResolution::parse(resolve, target.as_ref()) {
+                Ok(resolution) => resolution,
+                Err(e) => {
+                    error!("{e}");
+                    return;
+                }
+            };
+
+            dedupe(
                 &main_db,
-                &analysis_db,
-                RecommendMixOptions {
-                    mix_parameters,
-                    num: *num,
-                    format: format.as_ref().map(|x| x.as_str()),
-                    output: output.as_ref(),
+                DedupeOptions {
+                    lib_path: &canonicalized_path,
+                    fingerprint_threshold: fingerprint.then_some(*threshold),
+                    resolution,
+                    interactive: *interactive,
+                    confirm,
                 },
             )
             .await;
         }
-        Commands::Search { query, num } => match search_for(&main_db, query, None, *num).await {
-            Ok(results) => {
-                for (collection_type, ids) in results {
-                    info!("{collection_type:?}: {ids:?}");
+        Commands::Enrich {
+            acoustid_key,
+            min_score,
+            limit,
+        } => {
+            enrich(
+                &main_db,
+                EnrichOptions {
+                    acoustid_key: acoustid_key.clone(),
+                    min_score: *min_score,
+                    limit: *limit,
+                },
+            )
+            .await;
+        }
+        Commands::Tag { action } => {
+            let (pattern, changes, dry_run) = match action {
+                TagAction::Set {
+                    pattern,
+                    assignments,
+                    dry_run,
+                } => match parse_assignments(assignments) {
+                    Ok(changes) => (pattern, changes, dry_run),
+                    Err(e) => {
+                        error!("{e}");
+                        return;
+                    }
+                },
+                TagAction::Remove {
+                    pattern,
+                    keys,
+                    dry_run,
+                } => {
+                    let changes = keys
+                        .iter()
+                        .map(|key| TagChange::Remove { key: key.clone() })
+                        .collect();
+                    (pattern, changes, dry_run)
+                }
+                TagAction::Conflicts { pattern } => {
+                    show_tag_conflicts(&fsio, &main_db, &canonicalized_path, pattern).await;
+                    return;
+                }
+                TagAction::Resolve {
+                    pattern,
+                    side,
+                    keys,
+                } => {
+                    match side.parse::<TagResolution>() {
+                        Ok(resolution) => {
+                            resolve_conflicts(
+                                &fsio,
+                                &main_db,
+                                &canonicalized_path,
+                                pattern,
+                                keys,
+                                resolution,
+                                confirm,
+                            )
+                            .await
+                        }
+                        Err(e) => error!("{e}"),
+                    }
+                    return;
                 }
+            };
+
+            edit_tags(
+                &fsio,
+                &main_db,
+                &canonicalized_path,
+                pattern,
+                changes,
+                *dry_run,
+                confirm,
+            )
+            .await;
+        }
+        Commands::Serve { addr } => {
+            serve(
+                &canonicalized_path,
+                main_db.clone(),
+                analysis_db.clone(),
+                *addr,
+            )
+            .await;
+        }
+        Commands::Daemon {
+            socket,
+            gapless,
+            no_watch,
+        } => {
+            let cancel_token = cancel_on_ctrl_c();
+            let socket = socket
+                .clone()
+                .unwrap_or_else(|| default_socket_path(&canonicalized_path));
+
+            let watch = async {
+                if !*no_watch {
+                    watch_library(
+                        &fsio,
+                        &main_db,
+                        &analysis_db,
+                        &canonicalized_path,
+                        &config,
+                        None,
+                        cancel_token.clone(),
+                    )
+                    .await;
+                }
+            };
+            let daemon = async {
+                let result = run_daemon(
+                    main_db.clone(),
+                    &canonicalized_path,
+                    &socket,
+                    *gapless,
+                    cancel_token.clone(),
+                )
+                .await;
+                // Nothing to keep watching for without the daemon
+                cancel_token.cancel();
+                result
+            };
+
+            let (_, daemon) = tokio::join!(watch, daemon);
+            if let Err(e) = daemon {
+                error!("Daemon failed: {e:#}");
             }
-            Err(e) => {
-                error!("Search failed: {e}");
+        }
+        Commands::Dev { .. } => unreachable!("Dev commands run before connecting"),
+        Commands::Ctl { .. } => unreachable!("Ctl commands run before connecting"),
+        Commands::PlaylistMirror { action } => match action {
+            PlaylistMirrorAction::Add { playlist_id, path } => {
+                add_mirror(&main_db, &canonicalized_path, *playlist_id, path).await;
+            }
+            PlaylistMirrorAction::List => {
+                list_mirrors(&main_db).await;
+            }
+            PlaylistMirrorAction::Remove { playlist_id } => {
+                remove_mirror(&main_db, *playlist_id).await;
+            }
+            PlaylistMirrorAction::Sync => {
+                sync_mirrors(&main_db, &canonicalized_path).await;
+            }
+            PlaylistMirrorAction::Resolve { playlist_id, keep } => {
+                resolve_mirror(&main_db, &canonicalized_path, *playlist_id, keep).await;
+            }
+        },
+        Commands::Languages { languages, reset } => {
+            if *reset {
+                update_metadata_languages(&main_db, &[]).await;
+            } else if languages.is_empty() {
+                show_metadata_languages(&main_db).await;
+            } else {
+                update_metadata_languages(&main_db, languages).await;
+            }
+        }
+        Commands::Splitting { action } => match action {
+            SplittingAction::Show => {
+                show_splitting_rules(&main_db).await;
+            }
+            SplittingAction::Set {
+                artist_delimiters,
+                genre_delimiters,
+                exceptions,
+            } => {
+                update_splitting_rules(&main_db, artist_delimiters, genre_delimiters, exceptions)
+                    .await;
+            }
+            SplittingAction::Reset => {
+                reset_splitting_rules(&main_db).await;
+            }
+            SplittingAction::Reapply => {
+                measure_time!("Reapply splitting", reapply_splitting_rules(&main_db).await);
             }
         },
     }
+
+    if let Some(query_profile) = query_profile {
+        if cli.profile {
+            print_profile(&query_profile, started_at.elapsed());
+        }
+        if slow_threshold.is_some() {
+            record_slow_statements(&main_db, &query_profile).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refused(args: &[&str]) -> bool {
+        let cli =
+            Cli::try_parse_from([&["rune", "/music", "--read-only"][..], args].concat()).unwrap();
+        cli.read_only && cli.command.writes_library()
+    }
+
+    #[test]
+    fn test_read_only_refuses_writes() {
+        // Scanning also purges the trash and cleans up removed files
+        assert!(refused(&["scan"]));
+        assert!(refused(&["source", "remove", "1"]));
+        assert!(refused(&["trash", "restore", "--all"]));
+        assert!(refused(&["enrich"]));
+        assert!(refused(&["playlist-mirror", "sync"]));
+        assert!(refused(&["doctor", "--fix"]));
+        assert!(refused(&["tag", "set", "*.flac", "genre=Jazz"]));
+
+        assert!(!refused(&["search", "--query", "jazz"]));
+        assert!(!refused(&["trash", "list"]));
+        assert!(!refused(&["doctor"]));
+        assert!(!refused(&["tag", "set", "--dry-run", "*.flac", "a=b"]));
+    }
 }
@@ -6,15 +6,22 @@ use log::error;
 use prettytable::{row, Table};
 use rust_decimal::prelude::ToPrimitive;
 
+use database::actions::daily_mix::generate_daily_mixes;
 use database::actions::metadata::get_metadata_summary_by_file_ids;
-use database::actions::mixes::query_mix_media_files;
+use database::actions::mix_presets::{get_mix_preset, get_mix_presets, set_mix_preset};
+use database::actions::mixes::{
+    create_mix, get_all_mixes, get_mix_by_id, get_mix_by_uuid, get_mix_queries_by_mix_id,
+    parse_mix_parameters, query_mix_media_files, replace_mix_queries,
+};
 use database::connection::{MainDbConnection, RecommendationDbConnection};
 use database::entities::media_files;
 
+use crate::export::{PlaylistFormat, PlaylistTrack, SUPPORTED_FORMATS, save_playlist};
+use crate::format::format_duration;
 use crate::recommend::check_and_correct_extension;
 
 pub struct RecommendMixOptions<'a> {
-    pub mix_parameters: &'a str,
+    pub lib_path: &'a Path,
     pub num: usize,
     pub format: Option<&'a str>,
     pub output: Option<&'a PathBuf>,
@@ -23,39 +30,202 @@ pub struct RecommendMixOptions<'a> {
 pub async fn mixes(
     main_db: &MainDbConnection,
     recommend_db: &RecommendationDbConnection,
+    mix_parameters: &str,
+    options: RecommendMixOptions<'_>,
+) {
+    let queries = parse_mix_parameters(mix_parameters);
+
+    recommend_mix(main_db, recommend_db, queries, options).await;
+}
+
+/// Get recommendations from the parameters of a mix preset.
+pub async fn preset_mix(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    name: &str,
+    options: RecommendMixOptions<'_>,
+) {
+    let preset = match get_mix_preset(main_db, name).await {
+        Ok(Some(preset)) => preset,
+        Ok(None) => {
+            eprintln!("No mix preset named '{name}', see `mix presets`");
+            return;
+        }
+        Err(e) => {
+            eprintln!("Failed to get mix presets: {e}");
+            return;
+        }
+    };
+
+    recommend_mix(main_db, recommend_db, preset.queries(), options).await;
+}
+
+pub async fn list_mix_presets(main_db: &MainDbConnection) {
+    let presets = match get_mix_presets(main_db).await {
+        Ok(presets) => presets,
+        Err(e) => {
+            eprintln!("Failed to list mix presets: {e}");
+            return;
+        }
+    };
+
+    let mut table = Table::new();
+    table.add_row(row!["Name", "Parameters", "Built-in"]);
+
+    for preset in presets {
+        table.add_row(row![
+            preset.name,
+            preset.parameters,
+            if preset.builtin { "Yes" } else { "No" }
+        ]);
+    }
+
+    table.printstd();
+}
+
+pub async fn save_mix_preset(main_db: &MainDbConnection, name: &str, mix_parameters: &str) {
+    match set_mix_preset(main_db, name, Some(mix_parameters)).await {
+        Ok(()) => println!("Mix preset '{name}' saved"),
+        Err(e) => eprintln!("Failed to save mix preset: {e}"),
+    }
+}
+
+pub async fn remove_mix_preset(main_db: &MainDbConnection, name: &str) {
+    match set_mix_preset(main_db, name, None).await {
+        Ok(()) => println!("Mix preset '{name}' removed"),
+        Err(e) => eprintln!("Failed to remove mix preset: {e}"),
+    }
+}
+
+/// Save the mix parameters as a mix, to run it again later by its ID.
+pub async fn save_mix(
+    main_db: &MainDbConnection,
+    name: &str,
+    group: &str,
+    mix_parameters: &str,
+    cover: Option<&Path>,
+) {
+    let queries = parse_mix_parameters(mix_parameters);
+    if queries.is_empty() {
+        eprintln!("No valid mix parameters given");
+        return;
+    }
+
+    let cover = cover.map(|x| x.to_string_lossy().into_owned());
+    let mix = match create_mix(
+        main_db,
+        "",
+        name.to_owned(),
+        group.to_owned(),
+        false,
+        99,
+        false,
+        cover,
+    )
+    .await
+    {
+        Ok(mix) => mix,
+        Err(e) => {
+            eprintln!("Failed to save mix: {e}");
+            return;
+        }
+    };
+
+    if let Err(e) = replace_mix_queries(main_db, "", mix.id, queries, None).await {
+        eprintln!("Failed to save mix parameters: {e}");
+        return;
+    }
+
+    println!("Mix saved with ID {} ({})", mix.id, mix.hlc_uuid);
+}
+
+pub async fn list_mixes(main_db: &MainDbConnection) {
+    let mixes = match get_all_mixes(main_db).await {
+        Ok(mixes) => mixes,
+        Err(e) => {
+            eprintln!("Failed to list mixes: {e}");
+            return;
+        }
+    };
+
+    let mut table = Table::new();
+    table.add_row(row!["ID", "UUID", "Name", "Group", "Parameters", "Cover"]);
+
+    for mix in mixes {
+        let queries = match get_mix_queries_by_mix_id(main_db, mix.id).await {
+            Ok(queries) => queries,
+            Err(e) => {
+                eprintln!("Failed to get the parameters of mix {}: {e}", mix.id);
+                return;
+            }
+        };
+        let parameters = queries
+            .iter()
+            .map(|x| format!("{}({})", x.operator, x.parameter))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        table.add_row(row![
+            mix.id,
+            mix.hlc_uuid,
+            mix.name.trim_start_matches('\u{200B}'),
+            mix.group.trim_start_matches('\u{200B}'),
+            parameters,
+            mix.cover.unwrap_or_default()
+        ]);
+    }
+
+    table.printstd();
+}
+
+/// Run a saved mix, found by its ID or its UUID.
+pub async fn run_mix(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    mix: &str,
+    options: RecommendMixOptions<'_>,
+) {
+    let mix = match mix.parse::<i32>() {
+        Ok(id) => get_mix_by_id(main_db, id).await,
+        Err(_) => get_mix_by_uuid(main_db, mix).await,
+    };
+    let mix = match mix {
+        Ok(mix) => mix,
+        Err(e) => {
+            eprintln!("Failed to find mix: {e}");
+            return;
+        }
+    };
+
+    let queries = match get_mix_queries_by_mix_id(main_db, mix.id).await {
+        Ok(queries) => queries
+            .into_iter()
+            .map(|x| (x.operator, x.parameter))
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to get the parameters of mix {}: {e}", mix.id);
+            return;
+        }
+    };
+
+    recommend_mix(main_db, recommend_db, queries, options).await;
+}
+
+async fn recommend_mix(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    queries: Vec<(String, String)>,
     options: RecommendMixOptions<'_>,
 ) {
     let RecommendMixOptions {
-        mix_parameters,
+        lib_path,
         num,
         format,
         output,
     } = options;
 
-    // Convert mix_parameters to Vec<(String, String)>
-    let mix_parameters_vec: Vec<(String, String)> = mix_parameters
-        .split(';')
-        .filter_map(|param| {
-            // Trim leading and trailing whitespace
-            let param = param.trim();
-            // Find the position of the first '(' and the last ')'
-            if let Some(start) = param.find('(') {
-                if let Some(end) = param.rfind(')') {
-                    // Extract the operator and parameter
-                    let operator = &param[..start];
-                    let parameter = &param[start + 1..end];
-                    // Trim leading and trailing whitespace and handle escape issues
-                    let operator = operator.trim().replace("\\(", "(").replace("\\)", ")");
-                    let parameter = parameter.trim().replace("\\(", "(").replace("\\)", ")");
-                    return Some((operator.to_string(), parameter.to_string()));
-                }
-            }
-            None
-        })
-        .collect();
-
     let files: Vec<media_files::Model> =
-        match query_mix_media_files(main_db, recommend_db, mix_parameters_vec, 0, num).await {
+        match query_mix_media_files(main_db, recommend_db, queries, 0, num).await {
             Ok(recommendations) => recommendations,
             Err(e) => {
                 eprintln!("Failed to get recommendations: {e}");
@@ -63,12 +233,15 @@ pub async fn mixes(
             }
         };
 
-    match format {
-        Some("m3u8") => {
+    match format.map(PlaylistFormat::parse) {
+        Some(Some(PlaylistFormat::M3u8)) => {
             save_mixes_as_m3u8(output, &files).await;
         }
-        Some(_) => {
-            eprintln!("Unsupported format. Supported formats are 'json' and 'm3u8'.");
+        Some(Some(format)) => {
+            save_mixes_as_playlist(main_db, lib_path, output, format, &files).await;
+        }
+        Some(None) => {
+            eprintln!("Unsupported format. Supported formats are {SUPPORTED_FORMATS}.");
         }
         _none => {
             display_mixes_in_table(main_db, &files).await;
@@ -76,6 +249,26 @@ pub async fn mixes(
     }
 }
 
+pub async fn daily_mixes(main_db: &MainDbConnection, regenerate: bool) {
+    let mixes = match generate_daily_mixes(main_db, "", regenerate).await {
+        Ok(mixes) => mixes,
+        Err(e) => {
+            eprintln!("Failed to generate daily mixes: {e}");
+            return;
+        }
+    };
+
+    if mixes.is_empty() {
+        println!("Not enough analyzed tracks for daily mixes, analyze the library first");
+        return;
+    }
+
+    for mix in mixes {
+        println!("{}", mix.mix.name.trim_start_matches('\u{200B}'));
+        display_files_in_table(main_db, mix.file_ids).await;
+    }
+}
+
 pub async fn save_mixes_as_m3u8(output: Option<&PathBuf>, files: &Vec<media_files::Model>) {
     let output_path = match output {
         Some(path) => path,
@@ -133,21 +326,44 @@ pub async fn save_mixes_as_m3u8(output: Option<&PathBuf>, files: &Vec<media_file
     );
 }
 
-fn format_time(seconds: f64) -> String {
-    let total_seconds = seconds.floor() as i32;
-
-    let minutes = total_seconds / 60;
-    let remaining_seconds = total_seconds % 60;
+pub async fn save_mixes_as_playlist(
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    output: Option<&PathBuf>,
+    format: PlaylistFormat,
+    files: &[media_files::Model],
+) {
+    let output_path = match output {
+        Some(path) => path,
+        _none => {
+            eprintln!("Output file path is required when format is specified");
+            return;
+        }
+    };
 
-    let minutes_str = format!("{minutes:02}");
-    let seconds_str = format!("{remaining_seconds:02}");
+    let file_ids = files.iter().map(|x| x.id).collect::<Vec<_>>();
+    let tracks: Vec<PlaylistTrack> = match get_metadata_summary_by_file_ids(main_db, file_ids).await
+    {
+        Ok(summaries) => summaries
+            .into_iter()
+            .map(|summary| PlaylistTrack::new(lib_path, summary, None))
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to retrieve metadata summary: {e}");
+            return;
+        }
+    };
 
-    format!("{minutes_str}:{seconds_str}")
+    save_playlist(format, output_path, &tracks);
 }
 
 pub async fn display_mixes_in_table(main_db: &MainDbConnection, files: &[media_files::Model]) {
     let file_ids = files.iter().map(|x| x.id).collect::<Vec<_>>();
 
+    display_files_in_table(main_db, file_ids).await;
+}
+
+async fn display_files_in_table(main_db: &MainDbConnection, file_ids: Vec<i32>) {
     match get_metadata_summary_by_file_ids(main_db, file_ids).await {
         Ok(summaries) => {
             let mut table = Table::new();
@@ -157,6 +373,7 @@ pub async fn display_mixes_in_table(main_db: &MainDbConnection, files: &[media_f
                 "Album",
                 "Track Number",
                 "Duration",
+                "Key",
                 "Cover Art ID"
             ]);
 
@@ -166,12 +383,13 @@ pub async fn display_mixes_in_table(main_db: &MainDbConnection, files: &[media_f
                     summary.title,
                     summary.album,
                     summary.track_number,
-                    format_time(
+                    format_duration(
                         summary
                             .duration
                             .to_f64()
                             .expect("Failed to convert duration")
                     ),
+                    summary.key.unwrap_or_default(),
                     summary.cover_art_id.unwrap_or_default()
                 ]);
             }
@@ -1,5 +1,7 @@
 use std::{
-    path::Path,
+    collections::HashMap,
+    io::{self, BufRead, IsTerminal},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     thread,
     time::Duration,
@@ -8,20 +10,87 @@ use std::{
 use dunce::canonicalize;
 use futures::future::join_all;
 use log::{debug, error, info};
+use prettytable::{Table, row};
 use tokio::task;
 
 use database::{
-    actions::file::{get_file_by_id, get_random_files},
-    connection::MainDbConnection,
+    actions::{
+        file::{
+            RandomFileFilter, RandomWeighting, get_file_by_id, get_file_id_from_path,
+            get_file_weights, get_random_files,
+        },
+        metadata::get_metadata_summary_by_file_ids,
+        mixes::{get_mix_queries_by_mix_id, query_mix_media_files},
+        radio::{RADIO_LENGTH, RadioSeed, get_radio_tracks},
+        settings::{OUTPUT_DEVICE_KEY, get_setting},
+    },
+    connection::{MainDbConnection, RecommendationDbConnection},
 };
 use playback::{
-    player::{Playable, Player, PlayingItem},
-    strategies::AddMode,
+    output_stream::list_output_devices,
+    player::{Playable, PlaybackState, Player, PlayingItem},
+    strategies::{AddMode, RepeatMode, ShuffleMode},
 };
 
-async fn play_files(main_db: &MainDbConnection, canonicalized_path: &Path, file_ids: Vec<i32>) {
+use crate::format::format_duration;
+
+/// Most tracks a selection of the `play` command queues.
+const MAX_SELECTION_SIZE: usize = 4096;
+
+/// What the keys typed while playing do, each followed by Enter.
+const PLAYBACK_KEYS: &str = "n: next, b: previous, p: pause or resume, l: list the queue, q: quit";
+
+/// Playback settings of the `play` command.
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackOptions {
+    pub gapless: bool,
+    pub crossfade: Duration,
+    pub repeat: RepeatMode,
+    /// A weighted shuffle favours highly rated tracks.
+    pub shuffle: ShuffleMode,
+    /// The output device to play through, the one selected in the app if
+    /// `None`.
+    pub device: Option<String>,
+}
+
+/// Print the output devices that `play --device` accepts.
+pub fn list_devices() {
+    match list_output_devices() {
+        Ok(devices) if devices.is_empty() => println!("No output devices found"),
+        Ok(devices) => {
+            for device in devices {
+                if device.is_default {
+                    println!("{} (default)", device.name);
+                } else {
+                    println!("{}", device.name);
+                }
+            }
+        }
+        Err(e) => error!("Failed to list output devices: {e}"),
+    }
+}
+
+async fn play_files(
+    main_db: &MainDbConnection,
+    canonicalized_path: &Path,
+    file_ids: Vec<i32>,
+    options: PlaybackOptions,
+) {
     let player = Player::new(None);
     let player = Arc::new(Mutex::new(player));
+    player.lock().unwrap().set_gapless_enabled(options.gapless);
+    player.lock().unwrap().set_crossfade(options.crossfade);
+
+    let device = match options.device {
+        Some(device) => Some(device),
+        None => get_setting(main_db, OUTPUT_DEVICE_KEY)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Failed to get the output device: {e}");
+                None
+            }),
+    };
+    player.lock().unwrap().set_output_device(device);
 
     let file_futures = file_ids.into_iter().map(|id| async move {
         match get_file_by_id(main_db, id).await {
@@ -38,6 +107,20 @@ async fn play_files(main_db: &MainDbConnection, canonicalized_path: &Path, file_
         .into_iter()
         .filter_map(|file| file.flatten())
         .collect();
+    let queue = get_queue_labels(main_db, &files.iter().map(|x| x.id).collect::<Vec<_>>()).await;
+
+    if options.shuffle == ShuffleMode::Weighted {
+        let file_ids: Vec<i32> = files.iter().map(|file| file.id).collect();
+        match get_file_weights(main_db, &file_ids, RandomWeighting::Rating).await {
+            Ok(weights) => player.lock().unwrap().update_shuffle_weights(
+                weights
+                    .into_iter()
+                    .map(|(id, weight)| (PlayingItem::InLibrary(id), weight))
+                    .collect(),
+            ),
+            Err(e) => error!("Failed to get shuffle weights: {e}"),
+        }
+    }
 
     player.lock().unwrap().add_to_playlist(
         files
@@ -52,34 +135,133 @@ async fn play_files(main_db: &MainDbConnection, canonicalized_path: &Path, file_
             .collect(),
         AddMode::AppendToEnd,
     );
+    // The shuffle order is drawn from the queue, so set it after the tracks
+    player.lock().unwrap().set_repeat_mode(options.repeat);
+    player.lock().unwrap().set_shuffle_mode(options.shuffle);
 
     player.lock().unwrap().play();
 
     let status_receiver = player.lock().unwrap().subscribe_status();
+    let current = Arc::new(Mutex::new((None, PlaybackState::Stopped)));
 
     info!("Initializing event listeners");
-    task::spawn(async move {
-        while let Ok(status) = status_receiver.recv().await {
-            debug!("Player status updated: {status:?}");
+    task::spawn({
+        let current = Arc::clone(&current);
+        let queue = queue.clone();
+        async move {
+            while let Ok(status) = status_receiver.recv().await {
+                debug!("Player status updated: {status:?}");
+
+                let position = status.position;
 
-            let position = status.position;
+                debug!(
+                    "State: {}, seconds: {}",
+                    status.state,
+                    position.as_secs_f32()
+                );
 
-            debug!(
-                "State: {}, seconds: {}",
-                status.state,
-                position.as_secs_f32()
-            );
+                let mut current = current.lock().unwrap();
+                if status.index != current.0 {
+                    if let Some(label) = status.index.and_then(|x| queue.get(x)) {
+                        info!("Now playing: {label}");
+                    }
+                }
+                *current = (status.index, status.state);
+            }
         }
     });
 
-    thread::sleep(Duration::from_millis(30000));
+    // Without a terminal to read keys from, play for a while like before
+    if !io::stdin().is_terminal() {
+        thread::sleep(Duration::from_millis(30000));
+        return;
+    }
+
+    print_queue(&queue, None);
+    println!("{PLAYBACK_KEYS}");
+    if let Err(e) = task::spawn_blocking(move || control_playback(&player, &current, &queue)).await
+    {
+        error!("Failed to read playback keys: {e}");
+    }
 }
 
-pub async fn play_random(main_db: &MainDbConnection, canonicalized_path: &Path) {
-    match get_random_files(main_db, 30).await {
+/// "Title - Artist" of every file, in the order of `file_ids`.
+async fn get_queue_labels(main_db: &MainDbConnection, file_ids: &[i32]) -> Vec<String> {
+    let summaries = match get_metadata_summary_by_file_ids(main_db, file_ids.to_vec()).await {
+        Ok(summaries) => summaries,
+        Err(e) => {
+            error!("Failed to retrieve metadata summary: {e}");
+            Vec::new()
+        }
+    };
+    let summaries: HashMap<i32, _> = summaries.into_iter().map(|x| (x.id, x)).collect();
+
+    file_ids
+        .iter()
+        .map(|id| match summaries.get(id) {
+            Some(x) => format!(
+                "{} - {} ({})",
+                x.title,
+                x.artist,
+                format_duration(x.duration)
+            ),
+            None => format!("File {id}"),
+        })
+        .collect()
+}
+
+fn print_queue(queue: &[String], current: Option<usize>) {
+    let mut table = Table::new();
+    table.add_row(row!["#", "Track"]);
+    for (index, label) in queue.iter().enumerate() {
+        let marker = if current == Some(index) { ">" } else { "" };
+        table.add_row(row![format!("{marker}{}", index + 1), label]);
+    }
+    table.printstd();
+}
+
+/// Apply the keys typed in the terminal until `q` or the end of input.
+fn control_playback(
+    player: &Mutex<Player>,
+    current: &Mutex<(Option<usize>, PlaybackState)>,
+    queue: &[String],
+) {
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        match line.trim() {
+            "n" => player.lock().unwrap().next(),
+            "b" => player.lock().unwrap().previous(),
+            "p" => {
+                let state = current.lock().unwrap().1.clone();
+                if state == PlaybackState::Playing {
+                    player.lock().unwrap().pause();
+                } else {
+                    player.lock().unwrap().play();
+                }
+            }
+            "l" => print_queue(queue, current.lock().unwrap().0),
+            "q" => break,
+            "" => {}
+            _ => println!("{PLAYBACK_KEYS}"),
+        }
+    }
+
+    player.lock().unwrap().stop();
+}
+
+pub async fn play_random(
+    main_db: &MainDbConnection,
+    canonicalized_path: &Path,
+    filter: &RandomFileFilter,
+    options: PlaybackOptions,
+) {
+    match get_random_files(main_db, 30, filter).await {
         Ok(files) => {
             let file_ids = files.into_iter().map(|file| file.id).collect();
-            play_files(main_db, canonicalized_path, file_ids).await;
+            play_files(main_db, canonicalized_path, file_ids, options).await;
         }
         Err(e) => {
             error!("Failed to get random files: {e}");
@@ -87,6 +269,130 @@ pub async fn play_random(main_db: &MainDbConnection, canonicalized_path: &Path)
     }
 }
 
-pub async fn play_by_id(main_db: &MainDbConnection, canonicalized_path: &Path, id: i32) {
-    play_files(main_db, canonicalized_path, vec![id]).await;
+pub async fn play_by_id(
+    main_db: &MainDbConnection,
+    canonicalized_path: &Path,
+    id: i32,
+    options: PlaybackOptions,
+) {
+    play_files(main_db, canonicalized_path, vec![id], options).await;
+}
+
+async fn play_radio(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    canonicalized_path: &Path,
+    seed: RadioSeed,
+    options: PlaybackOptions,
+) {
+    match get_radio_tracks(main_db, recommend_db, seed, RADIO_LENGTH).await {
+        Ok(files) if files.is_empty() => info!("Nothing to play for {seed:?}"),
+        Ok(files) => {
+            let file_ids = files.into_iter().map(|file| file.id).collect();
+            play_files(main_db, canonicalized_path, file_ids, options).await;
+        }
+        Err(e) => {
+            error!("Failed to get radio tracks: {e:#}");
+        }
+    }
+}
+
+/// Play the tracks that sound like an artist.
+pub async fn start_artist_radio(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    canonicalized_path: &Path,
+    artist_id: i32,
+    options: PlaybackOptions,
+) {
+    play_radio(
+        main_db,
+        recommend_db,
+        canonicalized_path,
+        RadioSeed::Artist(artist_id),
+        options,
+    )
+    .await;
+}
+
+/// Play the tracks that sound like an album.
+pub async fn start_album_radio(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    canonicalized_path: &Path,
+    album_id: i32,
+    options: PlaybackOptions,
+) {
+    play_radio(
+        main_db,
+        recommend_db,
+        canonicalized_path,
+        RadioSeed::Album(album_id),
+        options,
+    )
+    .await;
+}
+
+/// Tracks picked by the `play` command besides the `random` and `id` modes.
+#[derive(Debug, Clone)]
+pub enum PlaySelection {
+    Album(i32),
+    Playlist(i32),
+    /// A saved mix, by its ID.
+    Mix(i32),
+    /// Files of the library, absolute or relative to it.
+    Files(Vec<PathBuf>),
+}
+
+async fn get_selected_file_ids(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    canonicalized_path: &Path,
+    selection: &PlaySelection,
+) -> anyhow::Result<Vec<i32>> {
+    let queries = match selection {
+        PlaySelection::Album(id) => vec![
+            ("lib::album".to_string(), id.to_string()),
+            ("sort::track_number".to_string(), "true".to_string()),
+        ],
+        PlaySelection::Playlist(id) => vec![("lib::playlist".to_string(), id.to_string())],
+        PlaySelection::Mix(id) => get_mix_queries_by_mix_id(main_db, *id)
+            .await?
+            .into_iter()
+            .map(|x| (x.operator, x.parameter))
+            .collect(),
+        PlaySelection::Files(paths) => {
+            let mut file_ids = Vec::new();
+            for path in paths {
+                match get_file_id_from_path(main_db, canonicalized_path, path).await {
+                    Ok(id) => file_ids.push(id),
+                    Err(e) => error!("Skipping {}: {e}", path.display()),
+                }
+            }
+            return Ok(file_ids);
+        }
+    };
+
+    Ok(
+        query_mix_media_files(main_db, recommend_db, queries, 0, MAX_SELECTION_SIZE)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect(),
+    )
+}
+
+/// Play an album, a playlist, a mix or a list of files, in their order.
+pub async fn play_selection(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    canonicalized_path: &Path,
+    selection: PlaySelection,
+    options: PlaybackOptions,
+) {
+    match get_selected_file_ids(main_db, recommend_db, canonicalized_path, &selection).await {
+        Ok(file_ids) if file_ids.is_empty() => info!("Nothing to play for {selection:?}"),
+        Ok(file_ids) => play_files(main_db, canonicalized_path, file_ids, options).await,
+        Err(e) => error!("Failed to get the tracks of {selection:?}: {e:#}"),
+    }
 }
@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use log::{error, info, warn};
+use prettytable::{Table, row};
+
+use database::{
+    actions::playlist_mirror::{
+        MirrorSide, MirrorSyncOutcome, add_playlist_mirror, list_playlist_mirrors,
+        remove_playlist_mirror, resolve_playlist_mirror_conflict, sync_playlist_mirrors,
+    },
+    connection::MainDbConnection,
+};
+
+use crate::format::Locale;
+
+fn report_outcome(path: &str, outcome: &MirrorSyncOutcome) {
+    match outcome {
+        MirrorSyncOutcome::Unchanged => info!("{path}: up to date"),
+        MirrorSyncOutcome::Exported => info!("{path}: written from the playlist"),
+        MirrorSyncOutcome::Imported { unmatched_paths } => {
+            info!("{path}: read into the playlist");
+            for unmatched in unmatched_paths {
+                warn!("No track found for {unmatched}");
+            }
+        }
+        MirrorSyncOutcome::Conflict => warn!(
+            "{path}: both the file and the playlist changed, resolve the conflict to resume syncing"
+        ),
+    }
+}
+
+pub async fn add_mirror(
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    playlist_id: i32,
+    path: &Path,
+) {
+    // Mirrors are watched from anywhere, so relative paths can't be kept
+    let path = match std::path::absolute(path) {
+        Ok(path) => path,
+        Err(e) => {
+            error!("Invalid mirror path {path:?}: {e}");
+            return;
+        }
+    };
+
+    match add_playlist_mirror(main_db, lib_path, "", playlist_id, &path).await {
+        Ok((mirror, outcome)) => report_outcome(&mirror.path, &outcome),
+        Err(e) => error!("Failed to mirror playlist: {e:#}"),
+    }
+}
+
+pub async fn list_mirrors(main_db: &MainDbConnection) {
+    let mirrors = match list_playlist_mirrors(main_db).await {
+        Ok(mirrors) => mirrors,
+        Err(e) => {
+            error!("Failed to retrieve playlist mirrors: {e}");
+            return;
+        }
+    };
+
+    let locale = Locale::from_env();
+    let mut table = Table::new();
+    table.add_row(row!["Playlist ID", "Path", "Status", "Synced At"]);
+
+    for mirror in mirrors {
+        table.add_row(row![
+            mirror.playlist_id,
+            mirror.path,
+            if mirror.conflict { "conflict" } else { "ok" },
+            mirror
+                .synced_at
+                .map(|x| locale.format_timestamp(&x))
+                .unwrap_or_default()
+        ]);
+    }
+
+    table.printstd();
+}
+
+pub async fn remove_mirror(main_db: &MainDbConnection, playlist_id: i32) {
+    match remove_playlist_mirror(main_db, playlist_id).await {
+        Ok(_) => info!("Playlist {playlist_id} is no longer mirrored"),
+        Err(e) => error!("Failed to remove playlist mirror: {e:#}"),
+    }
+}
+
+pub async fn sync_mirrors(main_db: &MainDbConnection, lib_path: &Path) {
+    match sync_playlist_mirrors(main_db, lib_path, "").await {
+        Ok(outcomes) => {
+            for (mirror, outcome) in outcomes {
+                report_outcome(&mirror.path, &outcome);
+            }
+        }
+        Err(e) => error!("Failed to sync playlist mirrors: {e:#}"),
+    }
+}
+
+pub async fn resolve_mirror(
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    playlist_id: i32,
+    keep: &str,
+) {
+    let keep: MirrorSide = match keep.parse() {
+        Ok(keep) => keep,
+        Err(e) => {
+            error!("{e}");
+            return;
+        }
+    };
+
+    match resolve_playlist_mirror_conflict(main_db, lib_path, "", playlist_id, keep).await {
+        Ok(outcome) => info!("Conflict of playlist {playlist_id} resolved: {outcome:?}"),
+        Err(e) => error!("Failed to resolve playlist mirror conflict: {e:#}"),
+    }
+}
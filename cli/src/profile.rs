@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use log::{error, info};
+use prettytable::{Table, row};
+
+use database::{
+    actions::index_suggestions::record_slow_queries, connection::MainDbConnection,
+    query_profile::QueryProfile,
+};
+
+/// Statements listed in the profile report.
+const SLOWEST_STATEMENTS: usize = 10;
+
+/// Longest SQL text shown before it is cut off.
+const MAX_SQL_LENGTH: usize = 120;
+
+fn shorten_sql(sql: &str) -> String {
+    let sql = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    if sql.chars().count() <= MAX_SQL_LENGTH {
+        return sql;
+    }
+
+    let mut shortened: String = sql.chars().take(MAX_SQL_LENGTH).collect();
+    shortened.push('…');
+    shortened
+}
+
+/// Print where the time of a command went, stage timings are logged by
+/// `measure_time!` while the command runs.
+pub fn print_profile(profile: &QueryProfile, elapsed: Duration) {
+    let (count, total) = profile.summary();
+    info!("Command finished in {elapsed:?}, {count} SQL statements took {total:?}");
+
+    let mut table = Table::new();
+    table.add_row(row!["Total", "Count", "Max", "Slow", "Failed", "SQL"]);
+
+    for stats in profile.slowest(SLOWEST_STATEMENTS) {
+        table.add_row(row![
+            format!("{:?}", stats.total),
+            stats.count,
+            format!("{:?}", stats.max),
+            stats.slow,
+            stats.failed,
+            shorten_sql(&stats.sql)
+        ]);
+    }
+
+    table.printstd();
+}
+
+/// Look at the plans of the statements that went over the slow threshold
+/// and remember the indexes they are missing for `rune doctor`.
+pub async fn record_slow_statements(main_db: &MainDbConnection, profile: &QueryProfile) {
+    match record_slow_queries(main_db, profile).await {
+        Ok(suggestions) if !suggestions.is_empty() => {
+            info!(
+                "{} new index suggestions, run `doctor` to see them",
+                suggestions.len()
+            )
+        }
+        Ok(_) => {}
+        Err(e) => error!("Failed to look into the slow SQL statements: {e:#}"),
+    }
+}
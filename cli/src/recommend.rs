@@ -1,24 +1,78 @@
 use prettytable::{format, row, Table};
-use serde_json::json;
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use database::actions::file::get_file_id_from_path;
 use database::actions::file::get_files_by_ids;
-use database::actions::recommendation::get_recommendation_by_file_id;
+use database::actions::metadata::get_metadata_summary_by_file_ids;
+use database::actions::recommendation::{
+    FeatureGroup, FeatureWeights, GENRE_FILTER_OVERSAMPLING, LIKED_ONLY_OVERSAMPLING,
+    get_recommendation_by_file_id, get_recommendation_by_seeds,
+    get_weighted_recommendation_by_file_id, retain_genre_recommendations,
+    retain_liked_recommendations,
+};
 use database::connection::{MainDbConnection, RecommendationDbConnection};
 
+use crate::export::{PlaylistFormat, PlaylistTrack, SUPPORTED_FORMATS, save_playlist};
+use crate::format::Locale;
+
 pub struct RecommendMusicOptions<'a> {
     pub canonicalized_path: &'a Path,
     pub path: &'a Path,
-    pub item_id: Option<i32>,
-    pub file_path: Option<&'a PathBuf>,
+    pub item_ids: &'a [i32],
+    pub file_paths: &'a [PathBuf],
+    /// Items the recommendations should not be like.
+    pub excluded_ids: &'a [i32],
+    pub excluded_paths: &'a [PathBuf],
     pub num: usize,
+    pub liked_only: bool,
+    pub genres: &'a [String],
+    pub weights: &'a [String],
     pub format: Option<&'a str>,
     pub output: Option<&'a PathBuf>,
 }
 
+/// Parse `GROUP=WEIGHT` pairs like `chroma=2` into feature weights.
+pub fn parse_feature_weights(weights: &[String]) -> Result<FeatureWeights, String> {
+    let mut result = FeatureWeights::default();
+    for x in weights {
+        let (group, weight) = x
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid weight '{x}', expected GROUP=WEIGHT"))?;
+        let Some(group) = FeatureGroup::parse(group) else {
+            let groups: Vec<&str> = FeatureGroup::ALL.iter().map(|x| x.as_str()).collect();
+            return Err(format!(
+                "Unknown feature group '{group}', expected one of: {}",
+                groups.join(", ")
+            ));
+        };
+        let weight: f32 = weight
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid weight '{weight}' for {}", group.as_str()))?;
+        result = result.with(group, weight);
+    }
+
+    Ok(result)
+}
+
+/// The IDs of the given items and of the files at the given paths.
+async fn resolve_file_ids(
+    main_db: &MainDbConnection,
+    path: &Path,
+    item_ids: &[i32],
+    file_paths: &[PathBuf],
+) -> Result<Vec<i32>, String> {
+    let mut ids = item_ids.to_vec();
+    for file_path in file_paths {
+        ids.push(get_file_id_from_path(main_db, path, file_path).await?);
+    }
+
+    Ok(ids)
+}
+
 pub async fn recommend_music(
     main_db: &MainDbConnection,
     recommend_db: &RecommendationDbConnection,
@@ -27,35 +81,92 @@ pub async fn recommend_music(
     let RecommendMusicOptions {
         canonicalized_path,
         path,
-        item_id,
-        file_path,
+        item_ids,
+        file_paths,
+        excluded_ids,
+        excluded_paths,
         num,
+        liked_only,
+        genres,
+        weights,
         format,
         output,
     } = options;
-    let file_id = if let Some(item_id) = item_id {
-        item_id
-    } else if let Some(file_path) = file_path {
-        match get_file_id_from_path(main_db, path, file_path).await {
-            Ok(id) => id,
+    let weights = match parse_feature_weights(weights) {
+        Ok(weights) => weights,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    let seeds = match resolve_file_ids(main_db, path, item_ids, file_paths).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    if seeds.is_empty() {
+        eprintln!("Either item_id or file_path must be provided.");
+        return;
+    }
+    let excluded = match resolve_file_ids(main_db, path, excluded_ids, excluded_paths).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    let mut search_n = num;
+    if liked_only {
+        search_n *= LIKED_ONLY_OVERSAMPLING;
+    }
+    if !genres.is_empty() {
+        search_n *= GENRE_FILTER_OVERSAMPLING;
+    }
+    let recommendations = match (seeds.as_slice(), excluded.is_empty()) {
+        ([file_id], true) if weights.is_uniform() => {
+            get_recommendation_by_file_id(recommend_db, *file_id, search_n)
+        }
+        ([file_id], true) => {
+            get_weighted_recommendation_by_file_id(recommend_db, *file_id, search_n, &weights)
+        }
+        _ => get_recommendation_by_seeds(recommend_db, &seeds, &excluded, search_n, &weights),
+    };
+    let recommendations: Vec<(u32, f32)> = match recommendations {
+        Ok(recommendations) => recommendations,
+        Err(e) => {
+            eprintln!("Failed to get recommendations: {e}");
+            return;
+        }
+    };
+
+    let recommendations = if genres.is_empty() {
+        recommendations
+    } else {
+        // Leave the cut to the liked filter when both are used
+        let keep = if liked_only { search_n } else { num };
+        match retain_genre_recommendations(main_db, recommendations, genres, keep).await {
+            Ok(recommendations) => recommendations,
             Err(e) => {
-                eprintln!("{e}");
+                eprintln!("Failed to filter recommendations by genre: {e}");
                 return;
             }
         }
-    } else {
-        eprintln!("Either item_id or file_path must be provided.");
-        return;
     };
 
-    let recommendations: Vec<(u32, f32)> =
-        match get_recommendation_by_file_id(recommend_db, file_id, num) {
+    let recommendations = if liked_only {
+        match retain_liked_recommendations(main_db, recommendations, num).await {
             Ok(recommendations) => recommendations,
             Err(e) => {
-                eprintln!("Failed to get recommendations: {e}");
+                eprintln!("Failed to filter liked recommendations: {e}");
                 return;
             }
-        };
+        }
+    } else {
+        recommendations
+    };
 
     // Get file details of recommendations
     let ids: Vec<i32> = recommendations.iter().map(|(id, _)| *id as i32).collect();
@@ -67,15 +178,22 @@ pub async fn recommend_music(
         }
     };
 
-    match format {
-        Some("json") => {
-            save_recommendations_as_json(canonicalized_path, output, &recommendations).await;
-        }
-        Some("m3u8") => {
+    match format.map(PlaylistFormat::parse) {
+        Some(Some(PlaylistFormat::M3u8)) => {
             save_recommendations_as_m3u8(canonicalized_path, output, path, &files).await;
         }
-        Some(_) => {
-            eprintln!("Unsupported format. Supported formats are 'json' and 'm3u8'.");
+        Some(Some(format)) => {
+            save_recommendations_as_playlist(
+                main_db,
+                canonicalized_path,
+                output,
+                format,
+                &recommendations,
+            )
+            .await;
+        }
+        Some(None) => {
+            eprintln!("Unsupported format. Supported formats are {SUPPORTED_FORMATS}.");
         }
         _none => {
             display_recommendations_in_table(path, &recommendations, &files);
@@ -83,10 +201,12 @@ pub async fn recommend_music(
     }
 }
 
-pub async fn save_recommendations_as_json(
+pub async fn save_recommendations_as_playlist(
+    main_db: &MainDbConnection,
     canonicalized_path: &Path,
     output: Option<&PathBuf>,
-    recommendations: &Vec<(u32, f32)>,
+    format: PlaylistFormat,
+    recommendations: &[(u32, f32)],
 ) {
     let output_path = match output {
         Some(path) => path,
@@ -96,33 +216,28 @@ pub async fn save_recommendations_as_json(
         }
     };
 
-    let corrected_path = check_and_correct_extension(&canonicalized_path.join(output_path), "json");
-    if corrected_path != *output_path {
-        eprintln!("Warning: Output file extension corrected to .json");
-    }
-
-    if let Some(parent) = corrected_path.parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            eprintln!("Failed to create directories: {e}");
-            return;
-        }
-    }
-
-    let json_data = json!(recommendations);
-    let mut file = match File::create(&corrected_path) {
-        Ok(file) => file,
+    let ids: Vec<i32> = recommendations.iter().map(|(id, _)| *id as i32).collect();
+    let summaries = match get_metadata_summary_by_file_ids(main_db, ids).await {
+        Ok(summaries) => summaries,
         Err(e) => {
-            eprintln!("Failed to create file: {e}");
+            eprintln!("Failed to retrieve metadata summary: {e}");
             return;
         }
     };
 
-    if let Err(e) = file.write_all(json_data.to_string().as_bytes()) {
-        eprintln!("Failed to write to file: {e}");
-        return;
-    }
+    let distances: HashMap<i32, f32> = recommendations
+        .iter()
+        .map(|(id, distance)| (*id as i32, *distance))
+        .collect();
+    let tracks: Vec<PlaylistTrack> = summaries
+        .into_iter()
+        .map(|summary| {
+            let distance = distances.get(&summary.id).copied();
+            PlaylistTrack::new(canonicalized_path, summary, distance)
+        })
+        .collect();
 
-    println!("Recommendations saved to JSON file.");
+    save_playlist(format, &canonicalized_path.join(output_path), &tracks);
 }
 
 pub async fn save_recommendations_as_m3u8(
@@ -195,6 +310,7 @@ pub fn display_recommendations_in_table(
     let mut table = Table::new();
     table.add_row(row!["ID", "Distance", "File Path"]);
     table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    let locale = Locale::from_env();
 
     for (id, distance) in recommendations {
         let file_info = files.iter().find(|f| f.id == *id as i32);
@@ -202,7 +318,7 @@ pub fn display_recommendations_in_table(
             let file_path = path.join(&file_info.directory).join(&file_info.file_name);
             table.add_row(row![
                 format!("{:0>5}", id),
-                format!("{:.4}", distance),
+                locale.format_number(*distance as f64, 4),
                 file_path.display()
             ]);
         }
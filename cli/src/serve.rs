@@ -0,0 +1,505 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path as FsPath, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio_util::io::ReaderStream;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+use database::actions::{
+    cover_art::{get_cover_art_by_id, get_cover_art_id_by_track_id},
+    file::{get_file_by_id, get_media_files},
+    metadata::{MetadataSummary, get_metadata_summary_by_file_ids, get_metadata_summary_by_files},
+    mixes::query_mix_media_files,
+    playlists::get_all_playlists,
+    recommendation::get_recommendation_by_file_id,
+    search::{convert_to_collection_types, search_for},
+};
+use database::connection::{MainDbConnection, RecommendationDbConnection};
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Bitrates in kbps a stream can be transcoded to.
+const TRANSCODE_BITRATES: std::ops::RangeInclusive<u32> = 32..=320;
+
+struct ServeState {
+    lib_path: PathBuf,
+    main_db: MainDbConnection,
+    recommend_db: RecommendationDbConnection,
+}
+
+enum ApiError {
+    Internal(String),
+    NotFound(String),
+    BadRequest(String),
+}
+
+impl<E: std::fmt::Display> From<E> for ApiError {
+    fn from(e: E) -> Self {
+        ApiError::Internal(e.to_string())
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
+            ApiError::NotFound(e) => (StatusCode::NOT_FOUND, e),
+            ApiError::BadRequest(e) => (StatusCode::BAD_REQUEST, e),
+        };
+
+        (status, Json(ErrorResponse { message })).into_response()
+    }
+}
+
+#[derive(Serialize)]
+struct TrackResponse {
+    id: i32,
+    directory: String,
+    file_name: String,
+    artist: String,
+    album: String,
+    genre: String,
+    title: String,
+    track_number: i32,
+    /// Duration in seconds.
+    duration: f64,
+    cover_art_id: Option<i32>,
+    key: Option<String>,
+    available: bool,
+}
+
+impl From<MetadataSummary> for TrackResponse {
+    fn from(x: MetadataSummary) -> Self {
+        TrackResponse {
+            id: x.id,
+            directory: x.directory,
+            file_name: x.file_name,
+            artist: x.artist,
+            album: x.album,
+            genre: x.genre,
+            title: x.title,
+            track_number: x.track_number,
+            duration: x.duration,
+            cover_art_id: x.cover_art_id,
+            key: x.key,
+            available: x.available,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PlaylistResponse {
+    id: i32,
+    name: String,
+    group: String,
+}
+
+#[derive(Serialize)]
+struct SearchItemResponse {
+    id: i64,
+    score: f64,
+}
+
+#[derive(Serialize)]
+struct RecommendationResponse {
+    id: u32,
+    distance: f32,
+}
+
+#[derive(Deserialize)]
+struct PageParams {
+    cursor: Option<usize>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    /// Comma separated collection types, every type if missing
+    types: Option<String>,
+    n: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct RecommendationParams {
+    n: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct StreamParams {
+    /// Transcode to `opus` or `mp3`, the original file if missing
+    format: Option<String>,
+    /// Bitrate of the transcoded stream in kbps
+    bitrate: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TranscodeFormat {
+    Opus,
+    Mp3,
+}
+
+impl TranscodeFormat {
+    fn parse(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "opus" => Some(TranscodeFormat::Opus),
+            "mp3" => Some(TranscodeFormat::Mp3),
+            _ => None,
+        }
+    }
+
+    fn default_bitrate(self) -> u32 {
+        match self {
+            TranscodeFormat::Opus => 96,
+            TranscodeFormat::Mp3 => 192,
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            TranscodeFormat::Opus => "audio/ogg",
+            TranscodeFormat::Mp3 => "audio/mpeg",
+        }
+    }
+
+    /// Arguments for ffmpeg to transcode the first audio stream of `path`
+    /// and write it to stdout.
+    fn ffmpeg_args(self, path: &FsPath, bitrate: u32) -> Vec<String> {
+        let (codec, container) = match self {
+            TranscodeFormat::Opus => ("libopus", "ogg"),
+            TranscodeFormat::Mp3 => ("libmp3lame", "mp3"),
+        };
+
+        [
+            "-v",
+            "error",
+            "-i",
+            &path.to_string_lossy(),
+            "-map",
+            "0:a:0",
+            "-c:a",
+            codec,
+            "-b:a",
+            &format!("{bitrate}k"),
+            "-f",
+            container,
+            "pipe:1",
+        ]
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+    }
+}
+
+fn page_size(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Guess the MIME type of a cover art from its magic number.
+fn cover_art_mime(binary: &[u8]) -> &'static str {
+    match binary {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, rest @ ..] if rest.starts_with(b"WEBP") => {
+            "image/webp"
+        }
+        [0x42, 0x4d, ..] => "image/bmp",
+        _ => "image/jpeg",
+    }
+}
+
+async fn list_files_handler(
+    State(state): State<Arc<ServeState>>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Vec<TrackResponse>>, ApiError> {
+    let files = get_media_files(
+        &state.main_db,
+        params.cursor.unwrap_or_default(),
+        page_size(params.limit),
+    )
+    .await?;
+    let summaries = get_metadata_summary_by_files(&state.main_db, files).await?;
+
+    Ok(Json(summaries.into_iter().map(Into::into).collect()))
+}
+
+async fn file_handler(
+    State(state): State<Arc<ServeState>>,
+    Path(file_id): Path<i32>,
+) -> Result<Json<TrackResponse>, ApiError> {
+    get_metadata_summary_by_file_ids(&state.main_db, vec![file_id])
+        .await?
+        .into_iter()
+        .next()
+        .map(|x| Json(x.into()))
+        .ok_or_else(|| ApiError::NotFound(format!("File {file_id} not found")))
+}
+
+async fn cover_art_handler(
+    State(state): State<Arc<ServeState>>,
+    Path(file_id): Path<i32>,
+) -> Result<Response, ApiError> {
+    let cover_art_id = get_cover_art_id_by_track_id(&state.main_db, file_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("File {file_id} has no cover art")))?;
+    let binary = get_cover_art_by_id(&state.main_db, cover_art_id)
+        .await?
+        .filter(|x| !x.is_empty())
+        .ok_or_else(|| ApiError::NotFound(format!("File {file_id} has no cover art")))?;
+
+    Ok(([(header::CONTENT_TYPE, cover_art_mime(&binary))], binary).into_response())
+}
+
+async fn search_handler(
+    State(state): State<Arc<ServeState>>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<HashMap<String, Vec<SearchItemResponse>>>, ApiError> {
+    let types = params
+        .types
+        .map(|x| convert_to_collection_types(x.split(',').map(|x| x.trim().to_owned()).collect()))
+        .filter(|x| !x.is_empty());
+    let results = search_for(&state.main_db, &params.q, types, page_size(params.n)).await?;
+
+    Ok(Json(
+        results
+            .into_iter()
+            .map(|(collection_type, items)| {
+                let items = items
+                    .into_iter()
+                    .map(|x| SearchItemResponse {
+                        id: x.id,
+                        score: x.score,
+                    })
+                    .collect();
+                (collection_type.to_string(), items)
+            })
+            .collect(),
+    ))
+}
+
+async fn list_playlists_handler(
+    State(state): State<Arc<ServeState>>,
+) -> Result<Json<Vec<PlaylistResponse>>, ApiError> {
+    let playlists = get_all_playlists(&state.main_db).await?;
+
+    Ok(Json(
+        playlists
+            .into_iter()
+            .map(|x| PlaylistResponse {
+                id: x.id,
+                name: x.name,
+                group: x.group,
+            })
+            .collect(),
+    ))
+}
+
+async fn playlist_files_handler(
+    State(state): State<Arc<ServeState>>,
+    Path(playlist_id): Path<i32>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Vec<TrackResponse>>, ApiError> {
+    let files = query_mix_media_files(
+        &state.main_db,
+        &state.recommend_db,
+        vec![("lib::playlist".to_owned(), playlist_id.to_string())],
+        params.cursor.unwrap_or_default(),
+        page_size(params.limit),
+    )
+    .await?;
+    let summaries = get_metadata_summary_by_files(&state.main_db, files).await?;
+
+    Ok(Json(summaries.into_iter().map(Into::into).collect()))
+}
+
+async fn recommendations_handler(
+    State(state): State<Arc<ServeState>>,
+    Path(file_id): Path<i32>,
+    Query(params): Query<RecommendationParams>,
+) -> Result<Json<Vec<RecommendationResponse>>, ApiError> {
+    let recommendations =
+        get_recommendation_by_file_id(&state.recommend_db, file_id, page_size(params.n))?;
+
+    Ok(Json(
+        recommendations
+            .into_iter()
+            .map(|(id, distance)| RecommendationResponse { id, distance })
+            .collect(),
+    ))
+}
+
+/// Stream a file as it is, with range requests, or transcoded with ffmpeg
+/// if a format is asked for. Transcoded streams can't be seeked.
+async fn stream_handler(
+    State(state): State<Arc<ServeState>>,
+    Path(file_id): Path<i32>,
+    Query(params): Query<StreamParams>,
+    request: Request,
+) -> Result<Response, ApiError> {
+    let file = get_file_by_id(&state.main_db, file_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("File {file_id} not found")))?;
+    let path = state.lib_path.join(&file.directory).join(&file.file_name);
+
+    let Some(format) = params.format else {
+        let response = ServeFile::new(&path).oneshot(request).await?;
+        return Ok(response.map(Body::new));
+    };
+
+    let format = TranscodeFormat::parse(&format)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unsupported format '{format}'")))?;
+    let bitrate = params.bitrate.unwrap_or(format.default_bitrate());
+    if !TRANSCODE_BITRATES.contains(&bitrate) {
+        return Err(ApiError::BadRequest(format!(
+            "Bitrate must be between {} and {} kbps",
+            TRANSCODE_BITRATES.start(),
+            TRANSCODE_BITRATES.end()
+        )));
+    }
+    if !path.is_file() {
+        return Err(ApiError::NotFound(format!(
+            "File {file_id} is not reachable"
+        )));
+    }
+
+    let mut child = Command::new("ffmpeg")
+        .args(format.ffmpeg_args(&path, bitrate))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ApiError::Internal(format!("Failed to start ffmpeg: {e}")))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| ApiError::Internal("Failed to read from ffmpeg".to_owned()))?;
+
+    // ffmpeg exits by itself once the client hangs up and the pipe breaks
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => warn!("Transcoding file {file_id}: {status}"),
+            Ok(_) => {}
+            Err(e) => warn!("Transcoding file {file_id}: {e}"),
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.mime()),
+            (header::ACCEPT_RANGES, "none"),
+        ],
+        Body::from_stream(ReaderStream::new(stdout)),
+    )
+        .into_response())
+}
+
+fn router(state: Arc<ServeState>) -> Router {
+    Router::new()
+        .route("/api/files", get(list_files_handler))
+        .route("/api/files/{file_id}", get(file_handler))
+        .route("/api/files/{file_id}/cover", get(cover_art_handler))
+        .route(
+            "/api/files/{file_id}/recommendations",
+            get(recommendations_handler),
+        )
+        .route("/api/tracks/{file_id}/stream", get(stream_handler))
+        .route("/api/search", get(search_handler))
+        .route("/api/playlists", get(list_playlists_handler))
+        .route(
+            "/api/playlists/{playlist_id}/files",
+            get(playlist_files_handler),
+        )
+        .with_state(state)
+}
+
+/// Serve the library as a read-only JSON API until Ctrl+C is pressed.
+pub async fn serve(
+    lib_path: &FsPath,
+    main_db: MainDbConnection,
+    recommend_db: RecommendationDbConnection,
+    addr: SocketAddr,
+) {
+    let state = Arc::new(ServeState {
+        lib_path: lib_path.to_owned(),
+        main_db,
+        recommend_db,
+    });
+
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to listen on {addr}: {e}");
+            return;
+        }
+    };
+    info!("Serving the library on http://{addr}/api");
+
+    let result = axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Server stopped unexpectedly: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_size() {
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(0)), 1);
+        assert_eq!(page_size(Some(20)), 20);
+        assert_eq!(page_size(Some(1_000_000)), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_cover_art_mime() {
+        assert_eq!(cover_art_mime(b"\x89PNG\r\n\x1a\n"), "image/png");
+        assert_eq!(cover_art_mime(b"GIF89a"), "image/gif");
+        assert_eq!(cover_art_mime(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(cover_art_mime(b"\xff\xd8\xff\xe0"), "image/jpeg");
+    }
+
+    #[test]
+    fn test_transcode_format() {
+        assert_eq!(TranscodeFormat::parse("OPUS"), Some(TranscodeFormat::Opus));
+        assert_eq!(TranscodeFormat::parse("mp3"), Some(TranscodeFormat::Mp3));
+        assert_eq!(TranscodeFormat::parse("flac"), None);
+
+        let args = TranscodeFormat::Opus.ffmpeg_args(FsPath::new("a b.flac"), 64);
+        assert_eq!(
+            args,
+            [
+                "-v", "error", "-i", "a b.flac", "-map", "0:a:0", "-c:a", "libopus", "-b:a", "64k",
+                "-f", "ogg", "pipe:1"
+            ]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use log::{error, info};
+use prettytable::{Table, row};
+
+use database::{
+    actions::sources::{add_library_source, refresh_source_availability, remove_library_source},
+    connection::MainDbConnection,
+};
+use fsio::FsIo;
+
+use crate::format::Locale;
+
+pub async fn add_source(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    name: &str,
+    path: &Path,
+) {
+    match add_library_source(fsio, main_db, lib_path, name, path).await {
+        Ok(source) => info!(
+            "Source {} added with ID {}, scan the library to import its files",
+            source.name, source.id
+        ),
+        Err(e) => error!("Failed to add source: {e:#}"),
+    }
+}
+
+pub async fn list_sources(main_db: &MainDbConnection) {
+    let sources = match refresh_source_availability(main_db).await {
+        Ok(sources) => sources,
+        Err(e) => {
+            error!("Failed to retrieve sources: {e}");
+            return;
+        }
+    };
+
+    let locale = Locale::from_env();
+    let mut table = Table::new();
+    table.add_row(row!["ID", "Name", "Path", "Status", "Last Seen"]);
+
+    for source in sources {
+        table.add_row(row![
+            source.id,
+            source.name,
+            source.path,
+            if source.available { "online" } else { "offline" },
+            source
+                .last_seen_at
+                .map(|x| locale.format_timestamp(&x))
+                .unwrap_or_default()
+        ]);
+    }
+
+    table.printstd();
+}
+
+pub async fn remove_source(main_db: &MainDbConnection, id: i32) {
+    match remove_library_source(main_db, id).await {
+        Ok(true) => info!("Source {id} removed"),
+        Ok(false) => error!("Source {id} not found"),
+        Err(e) => error!("Failed to remove source: {e}"),
+    }
+}
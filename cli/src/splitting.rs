@@ -0,0 +1,75 @@
+use log::{error, info};
+use prettytable::{Table, row};
+
+use database::{
+    actions::splitting::{get_splitting_rules, reapply_splitting, set_splitting_rules},
+    connection::MainDbConnection,
+};
+
+pub async fn show_splitting_rules(main_db: &MainDbConnection) {
+    let rules = match get_splitting_rules(main_db).await {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!("Failed to retrieve splitting rules: {e:#}");
+            return;
+        }
+    };
+
+    let mut table = Table::new();
+    table.add_row(row!["Rule", "Values"]);
+    for (name, values) in [
+        ("Artist delimiters", &rules.artist_delimiters),
+        ("Genre delimiters", &rules.genre_delimiters),
+        ("Exceptions", &rules.exceptions),
+    ] {
+        let values: Vec<String> = values.iter().map(|x| format!("{x:?}")).collect();
+        table.add_row(row![name, values.join(" ")]);
+    }
+
+    table.printstd();
+}
+
+/// Replace the given lists of the splitting rules, the others are kept.
+pub async fn update_splitting_rules(
+    main_db: &MainDbConnection,
+    artist_delimiters: &[String],
+    genre_delimiters: &[String],
+    exceptions: &[String],
+) {
+    let mut rules = match get_splitting_rules(main_db).await {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!("Failed to retrieve splitting rules: {e:#}");
+            return;
+        }
+    };
+
+    if !artist_delimiters.is_empty() {
+        rules.artist_delimiters = artist_delimiters.to_vec();
+    }
+    if !genre_delimiters.is_empty() {
+        rules.genre_delimiters = genre_delimiters.to_vec();
+    }
+    if !exceptions.is_empty() {
+        rules.exceptions = exceptions.to_vec();
+    }
+
+    match set_splitting_rules(main_db, Some(&rules)).await {
+        Ok(_) => info!("Splitting rules saved, run `splitting reapply` to update the library"),
+        Err(e) => error!("Failed to save splitting rules: {e}"),
+    }
+}
+
+pub async fn reset_splitting_rules(main_db: &MainDbConnection) {
+    match set_splitting_rules(main_db, None).await {
+        Ok(_) => info!("Splitting rules reset, run `splitting reapply` to update the library"),
+        Err(e) => error!("Failed to reset splitting rules: {e}"),
+    }
+}
+
+pub async fn reapply_splitting_rules(main_db: &MainDbConnection) {
+    match reapply_splitting(main_db, None).await {
+        Ok(count) => info!("Splitting rules reapplied to {count} files"),
+        Err(e) => error!("Failed to reapply splitting rules: {e:#}"),
+    }
+}
@@ -0,0 +1,225 @@
+use std::path::Path;
+
+use log::{error, info};
+
+use database::{
+    actions::{
+        file::get_files_by_glob,
+        metadata::{TagChange, update_tags},
+        tag_conflicts::{TagResolution, get_tag_conflicts, resolve_tag_conflicts},
+    },
+    connection::MainDbConnection,
+};
+use fsio::FsIo;
+use metadata::backend::MetadataBackends;
+
+use crate::confirm::Confirm;
+
+/// Parse `key=value` assignments of `tag set`.
+pub fn parse_assignments(assignments: &[String]) -> Result<Vec<TagChange>, String> {
+    assignments
+        .iter()
+        .map(|x| match x.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(TagChange::Set {
+                key: key.trim().to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(format!("Invalid assignment '{x}', expected KEY=VALUE")),
+        })
+        .collect()
+}
+
+/// Write the changes into every file matching `pattern`, a glob over the
+/// paths relative to the library. Tags without a standard item can only be
+/// written to the files that already have them, so they are checked per
+/// file.
+pub async fn edit_tags(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    pattern: &str,
+    changes: Vec<TagChange>,
+    dry_run: bool,
+    confirm: Confirm,
+) {
+    if !dry_run && !confirm.allows("write tags") {
+        return;
+    }
+
+    let files = match get_files_by_glob(main_db, pattern).await {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Failed to retrieve files: {e}");
+            return;
+        }
+    };
+
+    if files.is_empty() {
+        info!("No files match {pattern}");
+        return;
+    }
+
+    if !dry_run
+        && !confirm.confirm(
+            "write tags",
+            &format!(
+                "{} tag changes will be written into {} files",
+                changes.len(),
+                files.len()
+            ),
+        )
+    {
+        return;
+    }
+
+    let backends = MetadataBackends::default();
+    let mut updated = 0;
+    for file in &files {
+        let path = Path::new(&file.directory).join(&file.file_name);
+        if dry_run {
+            println!("{}", path.display());
+            continue;
+        }
+
+        match update_tags(fsio, &backends, main_db, lib_path, file.id, &changes).await {
+            Ok(_) => {
+                info!("Updated {}", path.display());
+                updated += 1;
+            }
+            Err(e) => error!("Failed to update {}: {e:#}", path.display()),
+        }
+    }
+
+    if dry_run {
+        info!("{} files would be updated", files.len());
+    } else {
+        info!("{updated} of {} files updated", files.len());
+    }
+}
+
+/// Print the tags that differ between the database and the files matching
+/// `pattern`.
+pub async fn show_tag_conflicts(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    pattern: &str,
+) {
+    let files = match get_files_by_glob(main_db, pattern).await {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Failed to retrieve files: {e}");
+            return;
+        }
+    };
+    let file_ids: Vec<i32> = files.iter().map(|x| x.id).collect();
+
+    let conflicts = match get_tag_conflicts(
+        fsio,
+        &MetadataBackends::default(),
+        main_db,
+        lib_path,
+        &file_ids,
+    )
+    .await
+    {
+        Ok(conflicts) => conflicts,
+        Err(e) => {
+            error!("Failed to compare tags: {e:#}");
+            return;
+        }
+    };
+
+    for file_conflicts in &conflicts {
+        let Some(file) = files.iter().find(|x| x.id == file_conflicts.file_id) else {
+            continue;
+        };
+        println!(
+            "{}",
+            Path::new(&file.directory).join(&file.file_name).display()
+        );
+        for conflict in &file_conflicts.conflicts {
+            println!(
+                "  {}: database {:?}, file {:?}",
+                conflict.key, conflict.database_values, conflict.file_values
+            );
+        }
+    }
+
+    info!(
+        "{} of {} files have conflicting tags",
+        conflicts.len(),
+        files.len()
+    );
+}
+
+/// Resolve the conflicting tags of the files matching `pattern`, only
+/// those in `keys` if it isn't empty.
+pub async fn resolve_conflicts(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    pattern: &str,
+    keys: &[String],
+    resolution: TagResolution,
+    confirm: Confirm,
+) {
+    if !confirm.allows("resolve tag conflicts") {
+        return;
+    }
+
+    let file_ids: Vec<i32> = match get_files_by_glob(main_db, pattern).await {
+        Ok(files) => files.into_iter().map(|x| x.id).collect(),
+        Err(e) => {
+            error!("Failed to retrieve files: {e}");
+            return;
+        }
+    };
+
+    let backends = MetadataBackends::default();
+    let conflicting_ids: Vec<i32> =
+        match get_tag_conflicts(fsio, &backends, main_db, lib_path, &file_ids).await {
+            Ok(conflicts) => conflicts.into_iter().map(|x| x.file_id).collect(),
+            Err(e) => {
+                error!("Failed to compare tags: {e:#}");
+                return;
+            }
+        };
+    if conflicting_ids.is_empty() {
+        info!("No conflicting tags in {} files", file_ids.len());
+        return;
+    }
+
+    let summary = format!(
+        "{} of {} files have conflicting tags, they will be resolved with the {} values",
+        conflicting_ids.len(),
+        file_ids.len(),
+        match resolution {
+            TagResolution::File => "file",
+            TagResolution::Database => "database",
+            TagResolution::Merge => "merged",
+        }
+    );
+    if !confirm.confirm("resolve tag conflicts", &summary) {
+        return;
+    }
+
+    match resolve_tag_conflicts(
+        fsio,
+        &backends,
+        main_db,
+        lib_path,
+        &conflicting_ids,
+        keys,
+        resolution,
+    )
+    .await
+    {
+        Ok(report) => info!(
+            "{} files resolved, {} failed",
+            report.resolved.len(),
+            report.failed.len()
+        ),
+        Err(e) => error!("Failed to resolve tag conflicts: {e:#}"),
+    }
+}
@@ -0,0 +1,93 @@
+use log::{error, info};
+use prettytable::{Table, row};
+
+use database::{
+    actions::trash::{
+        count_expired_deleted_files, get_deleted_files, purge_deleted_files, restore_deleted_files,
+    },
+    connection::MainDbConnection,
+};
+
+use crate::confirm::Confirm;
+use crate::format::Locale;
+
+pub async fn list_trash(main_db: &MainDbConnection) {
+    let files = match get_deleted_files(main_db).await {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Failed to retrieve deleted files: {e}");
+            return;
+        }
+    };
+
+    let locale = Locale::from_env();
+    let mut table = Table::new();
+    table.add_row(row!["ID", "File ID", "Path", "Deleted At"]);
+
+    for file in files {
+        table.add_row(row![
+            file.id,
+            file.file_id,
+            format!("{}/{}", file.directory, file.file_name),
+            locale.format_timestamp(&file.deleted_at)
+        ]);
+    }
+
+    table.printstd();
+}
+
+pub async fn restore_trash(main_db: &MainDbConnection, ids: Vec<i32>, all: bool) {
+    let ids = if all {
+        match get_deleted_files(main_db).await {
+            Ok(files) => files.into_iter().map(|x| x.id).collect(),
+            Err(e) => {
+                error!("Failed to retrieve deleted files: {e}");
+                return;
+            }
+        }
+    } else {
+        ids
+    };
+
+    match restore_deleted_files(main_db, &ids).await {
+        Ok(file_ids) => info!("{} files restored", file_ids.len()),
+        Err(e) => error!("Failed to restore deleted files: {e:#}"),
+    }
+}
+
+pub async fn purge_trash(main_db: &MainDbConnection, retention_days: u64) {
+    match purge_deleted_files(main_db, retention_days).await {
+        Ok(count) => info!("{count} deleted files purged"),
+        Err(e) => error!("Failed to purge deleted files: {e}"),
+    }
+}
+
+/// Purge the trash on request, after confirming how many files go for good.
+pub async fn confirm_and_purge_trash(
+    main_db: &MainDbConnection,
+    retention_days: u64,
+    confirm: Confirm,
+) {
+    if !confirm.allows("purge the trash") {
+        return;
+    }
+
+    let count = match count_expired_deleted_files(main_db, retention_days).await {
+        Ok(count) => count,
+        Err(e) => {
+            error!("Failed to count deleted files: {e}");
+            return;
+        }
+    };
+    if count == 0 {
+        info!("No deleted files older than {retention_days} days");
+        return;
+    }
+
+    let summary = format!(
+        "{count} deleted files older than {retention_days} days will be purged and can't be restored"
+    );
+    if confirm.confirm("purge the trash", &summary) {
+        purge_trash(main_db, retention_days).await;
+    }
+}
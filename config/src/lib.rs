@@ -0,0 +1,383 @@
+//! Layered configuration for Rune.
+//!
+//! Settings are read from `rune.toml` files and environment variables, with
+//! later layers overriding earlier ones:
+//!
+//! 1. Built-in defaults
+//! 2. The system file, `/etc/rune/rune.toml` on Unix
+//! 3. The user file in the platform config directory
+//! 4. The library file, `<library>/.rune/rune.toml`
+//! 5. `RUNE_<SECTION>_<KEY>` environment variables, e.g. `RUNE_SERVER_ADDR`
+//! 6. `section.key=value` overrides, usually passed on the command line
+//!
+//! Every layer may set any subset of the keys.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use directories::ProjectDirs;
+use log::info;
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+pub const CONFIG_FILE_NAME: &str = "rune.toml";
+pub const ENV_PREFIX: &str = "RUNE_";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibraryConfig {
+    /// Files analyzed in parallel, `0` derives it from the CPU count.
+    pub analysis_batch_size: usize,
+    /// Cover arts extracted in parallel, `0` derives it from the CPU count.
+    pub cover_art_batch_size: usize,
+    /// Share of the CPU cores used when a batch size is derived.
+    pub workload_factor: f32,
+    /// `cpu` or `gpu`.
+    pub computing_device: String,
+    /// Seconds the library has to stay quiet before watched changes apply.
+    pub watch_debounce_secs: u64,
+    /// Seconds between checks of mirrored playlists for edits made in Rune.
+    pub playlist_mirror_poll_secs: u64,
+    /// Days removed files are kept in the trash before they are purged.
+    pub trash_retention_days: u64,
+}
+
+impl Default for LibraryConfig {
+    fn default() -> Self {
+        Self {
+            analysis_batch_size: 0,
+            cover_art_batch_size: 0,
+            workload_factor: 0.75,
+            computing_device: "gpu".to_owned(),
+            watch_debounce_secs: 2,
+            playlist_mirror_poll_secs: 10,
+            trash_retention_days: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Address the server listens on.
+    pub addr: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:7863".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathsConfig {
+    /// Directory of certificates, permissions and the node ID, defaults to
+    /// the platform config directory.
+    pub config_dir: Option<PathBuf>,
+    /// Directory of the databases, defaults to `<library>/.rune`.
+    pub database_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeaturesConfig {
+    /// Serve Prometheus metrics on `/metrics`.
+    pub metrics: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self { metrics: true }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Keep the online integrations like MusicBrainz and scrobbling from
+    /// sending any request.
+    pub offline: bool,
+    /// Requests per second across all online services, `0` for no limit.
+    pub max_requests_per_second: f64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            offline: false,
+            max_requests_per_second: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// Statements taking at least this many milliseconds are logged with
+    /// their plans, `0` turns the timing off.
+    pub slow_query_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuneConfig {
+    pub library: LibraryConfig,
+    pub server: ServerConfig,
+    pub paths: PathsConfig,
+    pub features: FeaturesConfig,
+    pub network: NetworkConfig,
+    pub database: DatabaseConfig,
+}
+
+fn derive_batch_size(configured: usize, workload_factor: f32, num_cores: usize) -> usize {
+    if configured > 0 {
+        return configured;
+    }
+
+    let batch_size = (num_cores as f32 * workload_factor).round() as usize;
+    batch_size.clamp(1, 1000)
+}
+
+impl RuneConfig {
+    /// Resolve a configured batch size, deriving it from the CPU count and
+    /// the workload factor when it is `0`.
+    pub fn batch_size(&self, configured: usize) -> usize {
+        let num_cores = std::thread::available_parallelism().map_or(1, |x| x.get());
+        derive_batch_size(configured, self.library.workload_factor, num_cores)
+    }
+}
+
+/// The system and user level config files, in the order they are applied.
+pub fn global_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    #[cfg(unix)]
+    paths.push(PathBuf::from("/etc/rune").join(CONFIG_FILE_NAME));
+
+    if let Some(proj_dirs) = ProjectDirs::from("ci", "not", "rune") {
+        paths.push(proj_dirs.config_dir().join(CONFIG_FILE_NAME));
+    }
+
+    paths
+}
+
+/// The config file of a library.
+pub fn library_config_path(lib_path: &Path) -> PathBuf {
+    lib_path.join(".rune").join(CONFIG_FILE_NAME)
+}
+
+/// Merge `overlay` into `base`, nested tables are merged key by key.
+fn merge_table(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge_table(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Parse a value the way it would be written in TOML, falling back to a
+/// plain string so that `RUNE_SERVER_ADDR=0.0.0.0:7863` needs no quotes.
+fn parse_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {raw}"))
+        .ok()
+        .and_then(|mut x| x.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_owned()))
+}
+
+/// Set a dotted `section.key` in `table`.
+fn set_key(table: &mut Table, key: &str, value: Value) -> Result<()> {
+    let (section, key) = key
+        .split_once('.')
+        .ok_or_else(|| anyhow!("Config keys look like section.key, got: {key}"))?;
+
+    if section.is_empty() || key.is_empty() {
+        bail!("Config keys look like section.key, got: {section}.{key}");
+    }
+
+    let section = table
+        .entry(section.to_owned())
+        .or_insert_with(|| Value::Table(Table::new()));
+    match section {
+        Value::Table(section) => {
+            section.insert(key.to_owned(), value);
+            Ok(())
+        }
+        _ => bail!("Config section is not a table: {section}"),
+    }
+}
+
+/// Builds a [`RuneConfig`] out of its layers.
+#[derive(Debug, Default)]
+pub struct ConfigLoader {
+    table: Table,
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a config file, missing files are skipped.
+    pub fn file(mut self, path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Ok(self);
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {path:?}"))?;
+        let table = toml::from_str::<Table>(&content)
+            .with_context(|| format!("Failed to parse config file: {path:?}"))?;
+
+        info!("Loaded config file: {path:?}");
+        merge_table(&mut self.table, table);
+
+        Ok(self)
+    }
+
+    /// Apply `RUNE_<SECTION>_<KEY>` variables, other variables are ignored.
+    pub fn env<I>(mut self, vars: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        for (name, raw) in vars {
+            let Some(name) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let name = name.to_lowercase();
+            let Some((section, key)) = name.split_once('_') else {
+                continue;
+            };
+
+            set_key(
+                &mut self.table,
+                &format!("{section}.{key}"),
+                parse_value(&raw),
+            )?;
+        }
+
+        Ok(self)
+    }
+
+    /// Apply `section.key=value` overrides.
+    pub fn overrides<S: AsRef<str>>(mut self, overrides: &[S]) -> Result<Self> {
+        for item in overrides {
+            let item = item.as_ref();
+            let (key, raw) = item
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Config overrides look like section.key=value: {item}"))?;
+
+            set_key(&mut self.table, key.trim(), parse_value(raw.trim()))?;
+        }
+
+        Ok(self)
+    }
+
+    /// Apply a single value, e.g. from a dedicated command line flag.
+    pub fn set<V: Into<Value>>(mut self, key: &str, value: V) -> Result<Self> {
+        set_key(&mut self.table, key, value.into())?;
+        Ok(self)
+    }
+
+    pub fn build(self) -> Result<RuneConfig> {
+        Value::Table(self.table)
+            .try_into()
+            .with_context(|| "Invalid configuration")
+    }
+}
+
+/// Load the configuration of a library from all files and the environment,
+/// then apply the overrides.
+pub fn load_config<S: AsRef<str>>(lib_path: Option<&Path>, overrides: &[S]) -> Result<RuneConfig> {
+    let mut loader = ConfigLoader::new();
+
+    for path in global_config_paths() {
+        loader = loader.file(&path)?;
+    }
+    if let Some(lib_path) = lib_path {
+        loader = loader.file(&library_config_path(lib_path))?;
+    }
+
+    loader.env(std::env::vars())?.overrides(overrides)?.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loader_with(content: &str) -> ConfigLoader {
+        let mut loader = ConfigLoader::new();
+        merge_table(&mut loader.table, toml::from_str(content).unwrap());
+        loader
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = ConfigLoader::new().build().unwrap();
+        assert_eq!(config, RuneConfig::default());
+        assert_eq!(config.server.addr, "127.0.0.1:7863");
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let loader = loader_with(
+            r#"
+            [library]
+            analysis_batch_size = 8
+            computing_device = "cpu"
+
+            [server]
+            addr = "0.0.0.0:7863"
+            "#,
+        );
+        let config = loader
+            .env([
+                (
+                    "RUNE_LIBRARY_ANALYSIS_BATCH_SIZE".to_owned(),
+                    "4".to_owned(),
+                ),
+                ("RUNE_FEATURES_METRICS".to_owned(), "false".to_owned()),
+                ("RUNE_NETWORK_OFFLINE".to_owned(), "true".to_owned()),
+                ("RUNE_DATABASE_SLOW_QUERY_MS".to_owned(), "250".to_owned()),
+                ("HOME".to_owned(), "/home/rune".to_owned()),
+            ])
+            .unwrap()
+            .overrides(&["server.addr=192.168.1.2:8000"])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(config.library.analysis_batch_size, 4);
+        assert_eq!(config.library.computing_device, "cpu");
+        assert_eq!(config.library.watch_debounce_secs, 2);
+        assert_eq!(config.server.addr, "192.168.1.2:8000");
+        assert!(!config.features.metrics);
+        assert!(config.network.offline);
+        assert_eq!(config.database.slow_query_ms, 250);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(ConfigLoader::new().overrides(&["addr=1"]).is_err());
+        assert!(ConfigLoader::new().overrides(&["server.addr"]).is_err());
+        assert!(
+            ConfigLoader::new()
+                .overrides(&["library.watch_debounce_secs=soon"])
+                .unwrap()
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_batch_size() {
+        assert_eq!(derive_batch_size(0, 0.75, 8), 6);
+        assert_eq!(derive_batch_size(3, 0.75, 8), 3);
+        assert_eq!(derive_batch_size(0, 0.0, 8), 1);
+    }
+}
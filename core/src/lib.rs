@@ -0,0 +1,36 @@
+//! The engine of Rune for other Rust applications: open a library, scan
+//! and analyze it, query and recommend tracks and play them, without
+//! depending on the CLI or the hub.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use rune_core::{Library, engine::Playable};
+//!
+//! let library = Library::open("/music", "living-room-pc").await?;
+//! library.scan(false, |_, _| {}, None).await?;
+//!
+//! let tracks = library.search("nocturne", 10).await?;
+//! let ids: Vec<i32> = tracks.iter().map(|x| x.id).collect();
+//! let player = library.play(&ids).await?;
+//! player.pause();
+//! # Ok(())
+//! # }
+//! ```
+
+mod library;
+mod track;
+
+pub use library::{Library, LibraryOptions};
+pub use track::Track;
+
+/// Types of the engine crates that the facade hands out as they are. They
+/// change together with those crates, unlike [`Library`] and [`Track`].
+pub mod engine {
+    pub use analysis::utils::computing_device::ComputingDevice;
+    pub use database::events::{EventBus, LibraryEvent};
+    pub use metadata::backend::{FileHasher, MetadataBackends, TagReader};
+    pub use playback::player::{Playable, Player, PlayerStatus, PlayingItem};
+    pub use playback::strategies::{AddMode, RepeatMode, ShuffleMode};
+}
+
+pub use tokio_util::sync::CancellationToken;
@@ -0,0 +1,259 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail};
+use dunce::canonicalize;
+use log::{error, info};
+use tokio_util::sync::CancellationToken;
+
+use analysis::utils::computing_device::ComputingDevice;
+use config::{RuneConfig, load_config};
+use database::{
+    actions::{
+        analysis::analysis_audio_library,
+        collection::CollectionQueryType,
+        cover_art::scan_cover_arts,
+        metadata::{get_metadata_summary_by_file_ids, scan_audio_library},
+        recommendation::{get_recommendation_by_file_id, sync_recommendation},
+        search::search_for,
+        settings::{OUTPUT_DEVICE_KEY, get_setting},
+    },
+    connection::{
+        MainDbConnection, RecommendationDbConnection, connect_main_db, connect_recommendation_db,
+    },
+    events::EventBus,
+};
+use fsio::FsIo;
+use metadata::backend::MetadataBackends;
+use playback::{
+    player::{Playable, Player, PlayingItem},
+    strategies::AddMode,
+};
+
+use crate::track::{Track, order_by_ids};
+
+#[derive(Debug, Clone)]
+pub struct LibraryOptions {
+    /// Identifies this device in synced libraries.
+    pub node_id: String,
+    /// `key=value` pairs applied over the config files.
+    pub config_overrides: Vec<String>,
+    pub backends: MetadataBackends,
+}
+
+impl LibraryOptions {
+    /// Options for the device `node_id`, which must stay the same across
+    /// runs for synced changes to be attributed correctly.
+    pub fn new(node_id: impl Into<String>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            config_overrides: Vec::new(),
+            backends: MetadataBackends::default(),
+        }
+    }
+}
+
+/// An opened library with its databases.
+pub struct Library {
+    path: PathBuf,
+    node_id: String,
+    config: RuneConfig,
+    fsio: Arc<FsIo>,
+    backends: MetadataBackends,
+    events: EventBus,
+    main_db: MainDbConnection,
+    recommend_db: RecommendationDbConnection,
+}
+
+impl Library {
+    pub async fn open(path: impl AsRef<Path>, node_id: impl Into<String>) -> Result<Self> {
+        Self::open_with(path, LibraryOptions::new(node_id)).await
+    }
+
+    pub async fn open_with(path: impl AsRef<Path>, options: LibraryOptions) -> Result<Self> {
+        if options.node_id.trim().is_empty() {
+            bail!("A node id is required to open a library");
+        }
+
+        let path = canonicalize(path.as_ref())
+            .with_context(|| format!("Failed to canonicalize {:?}", path.as_ref()))?;
+        let lib_path = path.to_str().context("Library path is not valid UTF-8")?;
+
+        let config = load_config(Some(&path), &options.config_overrides)?;
+        let db_path = config.paths.database_dir.as_ref().and_then(|x| x.to_str());
+
+        let main_db = connect_main_db(lib_path, db_path, &options.node_id).await?;
+        let recommend_db = connect_recommendation_db(lib_path, db_path)?;
+
+        Ok(Self {
+            path,
+            node_id: options.node_id,
+            config,
+            fsio: Arc::new(FsIo::new()),
+            backends: options.backends,
+            events: EventBus::default(),
+            main_db,
+            recommend_db,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn config(&self) -> &RuneConfig {
+        &self.config
+    }
+
+    /// The bus the library publishes its events on, for embedders that want
+    /// to react to them.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Read new and changed files and their cover arts, reporting how many of
+    /// the files found were read so far. Returns the number of files read.
+    pub async fn scan<F>(
+        &self,
+        force: bool,
+        progress_callback: F,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<usize>
+    where
+        F: Fn(usize, usize) + Send + Sync,
+    {
+        let processed = scan_audio_library(
+            &self.fsio,
+            &self.backends,
+            &self.main_db,
+            &self.path,
+            true,
+            force,
+            progress_callback,
+            cancel_token.clone(),
+        )
+        .await?;
+
+        if cancel_token.as_ref().is_some_and(|x| x.is_cancelled()) {
+            return Ok(processed);
+        }
+
+        let batch_size = self
+            .config
+            .batch_size(self.config.library.cover_art_batch_size);
+        if let Err(e) = scan_cover_arts(
+            Arc::clone(&self.fsio),
+            &self.main_db,
+            &self.path,
+            &self.node_id,
+            batch_size,
+            |_now, _total| {},
+            cancel_token,
+        )
+        .await
+        {
+            error!("Failed to scan cover arts: {e:#}");
+        }
+
+        Ok(processed)
+    }
+
+    /// Analyze the files that haven't been analyzed yet and make them
+    /// available to recommendations. Returns the number of files analyzed.
+    pub async fn analyze<F>(
+        &self,
+        computing_device: Option<ComputingDevice>,
+        progress_callback: F,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<usize>
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        let computing_device = computing_device
+            .unwrap_or_else(|| self.config.library.computing_device.as_str().into());
+        let batch_size = self
+            .config
+            .batch_size(self.config.library.analysis_batch_size);
+
+        let analyzed = analysis_audio_library(
+            Arc::clone(&self.fsio),
+            &self.main_db,
+            &self.path,
+            &self.node_id,
+            batch_size,
+            computing_device,
+            progress_callback,
+            Some(&self.events),
+            cancel_token,
+        )
+        .await?;
+
+        sync_recommendation(&self.main_db, &self.recommend_db).await?;
+        info!("{analyzed} files analyzed");
+
+        Ok(analyzed)
+    }
+
+    /// Tracks by ID, in the order of `ids`. Unknown IDs are skipped.
+    pub async fn tracks(&self, ids: &[i32]) -> Result<Vec<Track>> {
+        let summaries = get_metadata_summary_by_file_ids(&self.main_db, ids.to_vec()).await?;
+        let tracks = summaries
+            .into_iter()
+            .map(|x| Track::from_summary(&self.path, x))
+            .collect();
+
+        Ok(order_by_ids(ids, tracks))
+    }
+
+    /// Tracks matching `query` by title, artist or album, best first.
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<Track>> {
+        let results = search_for(
+            &self.main_db,
+            query,
+            Some(vec![CollectionQueryType::Track]),
+            limit,
+        )
+        .await?;
+
+        let ids: Vec<i32> = results
+            .get(&CollectionQueryType::Track)
+            .map(|x| x.iter().map(|x| x.id as i32).collect())
+            .unwrap_or_default();
+
+        self.tracks(&ids).await
+    }
+
+    /// Tracks that sound like the given one, closest first. Only analyzed
+    /// tracks have recommendations.
+    pub async fn recommend(&self, file_id: i32, limit: usize) -> Result<Vec<Track>> {
+        let ids: Vec<i32> = get_recommendation_by_file_id(&self.recommend_db, file_id, limit)?
+            .into_iter()
+            .map(|(id, _)| id as i32)
+            .collect();
+
+        self.tracks(&ids).await
+    }
+
+    /// Start playing the tracks through the output device selected in the
+    /// app. Dropping the returned player doesn't stop it, call
+    /// `Playable::terminate` for that.
+    pub async fn play(&self, ids: &[i32]) -> Result<Player> {
+        let tracks = self.tracks(ids).await?;
+
+        let mut player = Player::new(None);
+        player.set_output_device(get_setting(&self.main_db, OUTPUT_DEVICE_KEY).await?);
+        player.add_to_playlist(
+            tracks
+                .into_iter()
+                .filter(|x| x.available)
+                .map(|x| (PlayingItem::InLibrary(x.id), x.path))
+                .collect(),
+            AddMode::AppendToEnd,
+        );
+        player.play();
+
+        Ok(player)
+    }
+}
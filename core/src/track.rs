@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use database::actions::metadata::MetadataSummary;
+
+/// A file of the library together with its main tags.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub id: i32,
+    pub path: PathBuf,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub genre: String,
+    pub track_number: i32,
+    /// Length in seconds.
+    pub duration: f64,
+    /// Estimated musical key, `None` until the file is analyzed.
+    pub key: Option<String>,
+    /// Whether the source the file is on is reachable.
+    pub available: bool,
+}
+
+impl Track {
+    pub(crate) fn from_summary(lib_path: &Path, summary: MetadataSummary) -> Self {
+        // Files of a source have absolute directories, joining them
+        // replaces the library path
+        let path = lib_path.join(&summary.directory).join(&summary.file_name);
+
+        Self {
+            id: summary.id,
+            path,
+            title: summary.title,
+            artist: summary.artist,
+            album: summary.album,
+            genre: summary.genre,
+            track_number: summary.track_number,
+            duration: summary.duration,
+            key: summary.key,
+            available: summary.available,
+        }
+    }
+}
+
+/// Put `tracks` in the order of `ids`, dropping the ones not found.
+pub(crate) fn order_by_ids(ids: &[i32], mut tracks: Vec<Track>) -> Vec<Track> {
+    ids.iter()
+        .filter_map(|id| {
+            let index = tracks.iter().position(|x| x.id == *id)?;
+            Some(tracks.swap_remove(index))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: i32) -> Track {
+        Track {
+            id,
+            path: PathBuf::from(format!("/music/{id}.flac")),
+            title: String::new(),
+            artist: String::new(),
+            album: String::new(),
+            genre: String::new(),
+            track_number: 0,
+            duration: 0.0,
+            key: None,
+            available: true,
+        }
+    }
+
+    #[test]
+    fn test_order_by_ids() {
+        let tracks = vec![track(1), track(2), track(3)];
+        let ordered = order_by_ids(&[3, 4, 1, 2], tracks);
+
+        let ids: Vec<i32> = ordered.iter().map(|x| x.id).collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+    }
+}
@@ -0,0 +1,78 @@
+cfg_if::cfg_if! {
+    if #[cfg(feature = "bench")] {
+        use std::hint::black_box;
+        use arroy::{Writer, distances::Euclidean};
+        use criterion::{criterion_group, criterion_main, Criterion};
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+        use database::actions::analysis::ANALYSIS_VECTOR_SIZE;
+        use database::actions::recommendation::{
+            FeatureWeights, get_recommendation_by_file_id, get_recommendation_by_seeds,
+        };
+        use database::connection::{RecommendationDbConnection, connect_fake_recommendation_db};
+
+        /// Recommendations should stay under 10 ms per query on a library of
+        /// this size, run with `cargo bench -p database --features bench`.
+        const LIBRARY_SIZE: u32 = 100_000;
+        const RECOMMENDATIONS: usize = 30;
+
+        fn build_index() -> RecommendationDbConnection {
+            let recommend_db = connect_fake_recommendation_db().unwrap();
+            let mut rng = StdRng::seed_from_u64(42);
+
+            let mut wtxn = recommend_db.env.write_txn().unwrap();
+            let writer = Writer::<Euclidean>::new(recommend_db.db, 0, ANALYSIS_VECTOR_SIZE);
+            for id in 0..LIBRARY_SIZE {
+                let vector: Vec<f32> = (0..ANALYSIS_VECTOR_SIZE).map(|_| rng.r#gen()).collect();
+                writer.add_item(&mut wtxn, id, &vector).unwrap();
+            }
+            writer.builder(&mut rng).build(&mut wtxn).unwrap();
+            wtxn.commit().unwrap();
+
+            recommend_db
+        }
+
+        fn recommendation_query_benchmark(c: &mut Criterion) {
+            let recommend_db = build_index();
+            let weights = FeatureWeights::default();
+
+            let mut group = c.benchmark_group("recommendation query, 100k tracks");
+            group.significance_level(0.01).sample_size(100);
+            group.bench_function("by file", |b| {
+                let mut id = 0;
+                b.iter(|| {
+                    // Step through the library so the cache doesn't hold the answer
+                    id = (id + 7919) % LIBRARY_SIZE;
+                    get_recommendation_by_file_id(
+                        &recommend_db,
+                        black_box(id as i32),
+                        RECOMMENDATIONS,
+                    )
+                    .unwrap()
+                })
+            });
+            group.bench_function("by seeds", |b| {
+                let mut id = 0;
+                b.iter(|| {
+                    id = (id + 7919) % (LIBRARY_SIZE - 3);
+                    let seeds = [id as i32, id as i32 + 1, id as i32 + 2];
+                    get_recommendation_by_seeds(
+                        &recommend_db,
+                        black_box(&seeds),
+                        &[],
+                        RECOMMENDATIONS,
+                        &weights,
+                    )
+                    .unwrap()
+                })
+            });
+            group.finish();
+        }
+
+        criterion_group!(benches, recommendation_query_benchmark);
+        criterion_main!(benches);
+    } else {
+        fn main() {
+            println!("Benchmarking is disabled. Please enable the 'bench' feature to run benchmarks.");
+        }
+    }
+}
@@ -1,17 +1,25 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, QueryOrder, QuerySelect};
 
 use crate::actions::collection::CollectionQuery;
 use crate::collection_query;
 use crate::connection::MainDbConnection;
-use crate::entities::{albums, media_file_albums};
+use crate::entities::{albums, media_file_albums, media_metadata};
 
 use super::collection::CollectionQueryType;
 use super::utils::CollectionDefinition;
 
+/// The album artist of compilations without one.
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// Different track artists an album without an album artist needs to be
+/// taken as a compilation.
+const COMPILATION_MIN_ARTISTS: usize = 3;
+
 impl CollectionDefinition for albums::Entity {
     fn group_column() -> Self::Column {
         albums::Column::Group
@@ -27,5 +35,380 @@ collection_query!(
     CollectionQueryType::Album,
     "lib::album".to_owned(),
     media_file_albums,
-    AlbumId
+    AlbumId,
+    subtitle = |x: &albums::Model| x.album_artist.clone()
 );
+
+pub async fn get_albums_groups(
+    main_db: &DatabaseConnection,
+    groups: Vec<String>,
+) -> Result<Vec<(String, Vec<(albums::Model, HashSet<i32>)>)>> {
+    Ok(get_groups_internal(main_db, groups).await?)
+}
+
+/// The files of an album in track order.
+pub async fn get_media_file_ids_of_album(
+    main_db: &DatabaseConnection,
+    album_id: i32,
+) -> Result<Vec<i32>> {
+    Ok(media_file_albums::Entity::find()
+        .select_only()
+        .column(media_file_albums::Column::MediaFileId)
+        .filter(media_file_albums::Column::AlbumId.eq(album_id))
+        .order_by_asc(media_file_albums::Column::TrackNumber)
+        .order_by_asc(media_file_albums::Column::MediaFileId)
+        .into_tuple()
+        .all(main_db)
+        .await?)
+}
+
+/// The tags of the tracks of an album that decide who the album is by.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlbumTrackTags {
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub compilation: Option<String>,
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes")
+}
+
+fn most_common<'a>(values: impl Iterator<Item = &'a str>) -> Option<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values.filter(|x| !x.trim().is_empty()) {
+        *counts.entry(value).or_default() += 1;
+    }
+
+    // Ties go to the first name alphabetically, so the result is stable
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+}
+
+/// The album artist of an album and whether it is a compilation.
+///
+/// The album artist tag wins. Albums without one are compilations if any
+/// track is tagged as part of one, or if they have several track artists
+/// and none of them is on at least half of the tracks.
+pub fn detect_album_artist(tracks: &[AlbumTrackTags]) -> (Option<String>, bool) {
+    let tagged_compilation = tracks
+        .iter()
+        .any(|x| x.compilation.as_deref().is_some_and(is_truthy));
+
+    if let Some((album_artist, _)) =
+        most_common(tracks.iter().filter_map(|x| x.album_artist.as_deref()))
+    {
+        let is_compilation =
+            tagged_compilation || album_artist.eq_ignore_ascii_case(VARIOUS_ARTISTS);
+        return (Some(album_artist.to_string()), is_compilation);
+    }
+
+    let artists: HashSet<&str> = tracks
+        .iter()
+        .filter_map(|x| x.artist.as_deref())
+        .filter(|x| !x.trim().is_empty())
+        .collect();
+    let main_artist = most_common(tracks.iter().filter_map(|x| x.artist.as_deref()));
+
+    let is_compilation = tagged_compilation
+        || (artists.len() >= COMPILATION_MIN_ARTISTS
+            && main_artist.is_some_and(|(_, count)| count * 2 < tracks.len()));
+
+    if is_compilation {
+        (Some(VARIOUS_ARTISTS.to_string()), true)
+    } else {
+        (main_artist.map(|(artist, _)| artist.to_string()), false)
+    }
+}
+
+/// Work out the album artist and compilation flag of the albums again,
+/// after their tracks changed.
+pub async fn update_album_details(main_db: &DatabaseConnection, album_ids: &[i32]) -> Result<()> {
+    if album_ids.is_empty() {
+        return Ok(());
+    }
+
+    let links: Vec<(i32, i32)> = media_file_albums::Entity::find()
+        .select_only()
+        .column(media_file_albums::Column::AlbumId)
+        .column(media_file_albums::Column::MediaFileId)
+        .filter(media_file_albums::Column::AlbumId.is_in(album_ids.to_vec()))
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    let file_ids: Vec<i32> = links.iter().map(|(_, file_id)| *file_id).collect();
+    let mut tags: HashMap<i32, AlbumTrackTags> = HashMap::new();
+    for (file_id, key, value) in media_metadata::Entity::find()
+        .select_only()
+        .column(media_metadata::Column::FileId)
+        .column(media_metadata::Column::MetaKey)
+        .column(media_metadata::Column::MetaValue)
+        .filter(media_metadata::Column::FileId.is_in(file_ids))
+        .filter(media_metadata::Column::MetaKey.is_in(["artist", "album_artist", "compilation"]))
+        .into_tuple::<(i32, String, String)>()
+        .all(main_db)
+        .await?
+    {
+        let entry = tags.entry(file_id).or_default();
+        match key.as_str() {
+            "artist" => entry.artist = Some(value),
+            "album_artist" => entry.album_artist = Some(value),
+            _ => entry.compilation = Some(value),
+        }
+    }
+
+    let mut tracks_by_album: HashMap<i32, Vec<AlbumTrackTags>> = HashMap::new();
+    for (album_id, file_id) in links {
+        tracks_by_album
+            .entry(album_id)
+            .or_default()
+            .push(tags.get(&file_id).cloned().unwrap_or_default());
+    }
+
+    for album in albums::Entity::find()
+        .filter(albums::Column::Id.is_in(album_ids.to_vec()))
+        .all(main_db)
+        .await?
+    {
+        let tracks = tracks_by_album.remove(&album.id).unwrap_or_default();
+        let (album_artist, is_compilation) = detect_album_artist(&tracks);
+
+        if album.album_artist == album_artist && album.is_compilation == is_compilation {
+            continue;
+        }
+
+        let mut active_model: albums::ActiveModel = album.into();
+        active_model.album_artist = ActiveValue::Set(album_artist);
+        active_model.is_compilation = ActiveValue::Set(is_compilation);
+        active_model.update(main_db).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use chrono::Utc;
+    use sea_orm::{ActiveValue, EntityTrait, QueryOrder};
+
+    use crate::{
+        actions::{
+            albums::{
+                AlbumTrackTags, VARIOUS_ARTISTS, detect_album_artist, get_albums_groups,
+                get_media_file_ids_of_album,
+            },
+            collection::CollectionQuery,
+            cover_art::ensure_magic_cover_art,
+            index::index_media_files,
+            stats::set_hidden,
+        },
+        entities::{albums, media_cover_art, media_files, media_metadata},
+        test_support::{
+            FakeTrack, TRACKS_PER_ALBUM, connect_test_main_db, seed_fake_tracks, seed_tracks,
+        },
+    };
+
+    fn track(artist: &str, album_artist: Option<&str>) -> AlbumTrackTags {
+        AlbumTrackTags {
+            artist: Some(artist.to_string()),
+            album_artist: album_artist.map(|x| x.to_string()),
+            compilation: None,
+        }
+    }
+
+    #[test]
+    fn test_detect_album_artist() {
+        // A single artist album
+        let tracks = vec![track("Alpha", None); 5];
+        assert_eq!(
+            detect_album_artist(&tracks),
+            (Some("Alpha".to_string()), false)
+        );
+
+        // A guest on one track doesn't make a compilation
+        let mut tracks = vec![track("Alpha", None); 4];
+        tracks.push(track("Beta", None));
+        tracks.push(track("Gamma", None));
+        assert_eq!(
+            detect_album_artist(&tracks),
+            (Some("Alpha".to_string()), false)
+        );
+
+        // Many artists without an album artist
+        let tracks: Vec<_> = ["Alpha", "Beta", "Gamma", "Delta"]
+            .into_iter()
+            .map(|x| track(x, None))
+            .collect();
+        assert_eq!(
+            detect_album_artist(&tracks),
+            (Some(VARIOUS_ARTISTS.to_string()), true)
+        );
+
+        // The album artist tag wins over the track artists
+        let tracks: Vec<_> = ["Alpha", "Beta", "Gamma", "Delta"]
+            .into_iter()
+            .map(|x| track(x, Some("Alpha & Friends")))
+            .collect();
+        assert_eq!(
+            detect_album_artist(&tracks),
+            (Some("Alpha & Friends".to_string()), false)
+        );
+
+        // The compilation flag is honoured even for a single artist
+        let mut tracks = vec![track("Alpha", None); 3];
+        tracks[0].compilation = Some("1".to_string());
+        assert_eq!(
+            detect_album_artist(&tracks),
+            (Some(VARIOUS_ARTISTS.to_string()), true)
+        );
+
+        assert_eq!(detect_album_artist(&[]), (None, false));
+    }
+
+    #[tokio::test]
+    async fn test_album_details_are_indexed() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, TRACKS_PER_ALBUM).await?;
+
+        let album = albums::Entity::find().one(&db).await?.unwrap();
+        assert_eq!(
+            album.album_artist.as_deref(),
+            Some(FakeTrack::nth(0).artist.as_str())
+        );
+        assert!(!album.is_compilation);
+
+        // Tag the album as a compilation and index it again
+        media_metadata::Entity::insert_many(file_ids.iter().map(|file_id| {
+            media_metadata::ActiveModel {
+                file_id: ActiveValue::Set(*file_id),
+                meta_key: ActiveValue::Set("compilation".to_string()),
+                meta_value: ActiveValue::Set("1".to_string()),
+                ..Default::default()
+            }
+        }))
+        .exec(&db)
+        .await?;
+        index_media_files(&db, file_ids, None).await?;
+
+        let album = albums::Entity::find_by_id(album.id)
+            .one(&db)
+            .await?
+            .unwrap();
+        assert_eq!(album.album_artist.as_deref(), Some(VARIOUS_ARTISTS));
+        assert!(album.is_compilation);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compilation_by_track_artists() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        let tracks: Vec<FakeTrack> = ["Alpha", "Beta", "Gamma", "Delta"]
+            .into_iter()
+            .enumerate()
+            .map(|(index, artist)| {
+                let mut track = FakeTrack::nth(index);
+                track.artist = artist.to_string();
+                track.album = "Mixtape".to_string();
+                track
+            })
+            .collect();
+        seed_tracks(&db, &tracks).await?;
+
+        let album = albums::Entity::find().one(&db).await?.unwrap();
+        assert_eq!(album.name, "Mixtape");
+        assert_eq!(album.album_artist.as_deref(), Some(VARIOUS_ARTISTS));
+        assert!(album.is_compilation);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_album_groups_and_tracks() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        // Seed the album in reverse so insertion order differs from track order
+        let tracks: Vec<FakeTrack> = (0..TRACKS_PER_ALBUM).rev().map(FakeTrack::nth).collect();
+        let mut file_ids = seed_tracks(&db, &tracks).await?;
+        file_ids.reverse();
+
+        let album = albums::Entity::find().one(&db).await?.unwrap();
+        assert_eq!(get_media_file_ids_of_album(&db, album.id).await?, file_ids);
+
+        let groups = get_albums_groups(&db, vec![album.group.clone()]).await?;
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].1.len(), 1);
+        assert_eq!(groups[0].1[0].0.id, album.id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_album_cover_files() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, TRACKS_PER_ALBUM * 2).await?;
+
+        let now = Utc::now().to_rfc3339();
+        let cover_art_id = media_cover_art::Entity::insert(media_cover_art::ActiveModel {
+            file_hash: ActiveValue::Set("cover".to_string()),
+            binary: ActiveValue::Set(vec![0]),
+            hlc_uuid: ActiveValue::Set("cover".to_string()),
+            created_at_hlc_ts: ActiveValue::Set(now.clone()),
+            updated_at_hlc_ts: ActiveValue::Set(now),
+            created_at_hlc_ver: ActiveValue::Set(0),
+            updated_at_hlc_ver: ActiveValue::Set(0),
+            created_at_hlc_nid: ActiveValue::Set("test".to_string()),
+            updated_at_hlc_nid: ActiveValue::Set("test".to_string()),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await?
+        .last_insert_id;
+        let magic_cover_art_id = ensure_magic_cover_art(&db, "test").await?.id;
+
+        // The first track only has the magic cover and the second one is
+        // hidden, so the third one stands for the first album
+        for (file_id, cover_art_id) in [
+            (file_ids[0], magic_cover_art_id),
+            (file_ids[1], cover_art_id),
+            (file_ids[2], cover_art_id),
+            (file_ids[3], cover_art_id),
+        ] {
+            media_files::Entity::update(media_files::ActiveModel {
+                id: ActiveValue::Unchanged(file_id),
+                cover_art_id: ActiveValue::Set(Some(cover_art_id)),
+                ..Default::default()
+            })
+            .exec(&db)
+            .await?;
+        }
+        set_hidden(&db, file_ids[1], true).await?;
+
+        let album_ids: Vec<i32> = albums::Entity::find()
+            .order_by_asc(albums::Column::Name)
+            .all(&db)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        assert_eq!(album_ids.len(), 2);
+
+        let cover_file_ids = albums::Model::get_cover_file_ids(&db, &album_ids)
+            .await?
+            .unwrap();
+        assert_eq!(cover_file_ids.len(), 1);
+        assert_eq!(cover_file_ids.get(&album_ids[0]), Some(&file_ids[2]));
+
+        // The lookup is limited to the requested albums
+        let cover_file_ids = albums::Model::get_cover_file_ids(&db, &album_ids[1..])
+            .await?
+            .unwrap();
+        assert!(cover_file_ids.is_empty());
+
+        Ok(())
+    }
+}
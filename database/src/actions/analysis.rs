@@ -1,25 +1,48 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{Context, Result, bail};
 use fsio::FsIo;
 use futures::future::join_all;
-use log::info;
+use log::{error, info};
+use migration::OnConflict;
 use paste::paste;
 use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::prelude::ToPrimitive;
 use sea_orm::entity::prelude::*;
-use sea_orm::{ActiveValue, QueryOrder, QuerySelect};
+use sea_orm::{ActiveValue, QueryOrder, QuerySelect, QueryTrait, Select};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use seq_macro::seq;
+use tokio::sync::broadcast::Receiver;
+use tokio::task;
 use tokio_util::sync::CancellationToken;
 
-use analysis::analysis::{NormalizedAnalysisResult, analyze_audio, normalize_analysis_result};
+use analysis::analysis::{
+    ANALYSIS_FEATURE_VERSION, NormalizedAnalysisResult, analyze_audio, normalize_analysis_result,
+};
 use analysis::utils::computing_device::ComputingDevice;
 
-use crate::entities::{media_analysis, media_files};
+use crate::actions::albums::get_media_file_ids_of_album;
+use crate::actions::checkpoint::ANALYSIS_TASK;
+use crate::actions::waveform::upsert_waveform;
+use crate::checkpointed_media_files_processing;
+use crate::entities::{
+    media_analysis, media_file_albums, media_file_waveform, media_files, media_metadata,
+};
+use crate::events::{EventBus, LibraryEvent, next_events};
 use crate::parallel_media_files_processing;
 
+/// How many tracks from the current one on are analyzed ahead of the rest.
+const QUEUED_ANALYSIS_AHEAD: usize = 8;
+
+/// How many queued tracks are looked up at once.
+const QUEUED_LOOKUP_PAGE_SIZE: usize = 256;
+
+/// How many selected files are bound in a single query, well under the
+/// limit SQLite puts on the number of parameters.
+const SELECTION_CHUNK_SIZE: usize = 500;
+
 pub fn empty_progress_callback(_processed: usize, _total: usize) {}
 
 /// Analyze the audio library by reading existing files, checking if they have been analyzed,
@@ -32,6 +55,7 @@ pub fn empty_progress_callback(_processed: usize, _total: usize) {}
 /// * `lib_path` - The root path for the audio files.
 /// * `batch_size` - The number of files to process in each batch.
 /// * `progress_callback` - A callback function to report progress.
+/// * `events` - An optional event bus to publish the analyzed files on.
 /// * `cancel_token` - An optional cancellation token to support task cancellation.
 ///
 /// # Returns
@@ -45,6 +69,7 @@ pub async fn analysis_audio_library<F>(
     batch_size: usize,
     computing_device: ComputingDevice,
     progress_callback: F,
+    events: Option<&EventBus>,
     cancel_token: Option<CancellationToken>,
 ) -> Result<usize>
 where
@@ -54,49 +79,379 @@ where
 
     info!("Starting audio library analysis with batch size: {batch_size}");
 
-    let existed_ids: Vec<i32> = media_analysis::Entity::find()
-        .select_only()
-        .column(media_analysis::Column::FileId)
+    let existed_ids: Vec<i32> = up_to_date_analysis()
         .distinct()
         .into_tuple::<i32>()
         .all(main_db)
         .await?;
 
-    let cursor_query =
-        media_files::Entity::find().filter(media_files::Column::Id.is_not_in(existed_ids));
+    // Checked again for every page, so files analyzed ahead of their turn
+    // are skipped
+    let cursor_query = media_files::Entity::find()
+        .filter(media_files::Column::Id.not_in_subquery(up_to_date_analysis().into_query()));
+
+    let lib_path = Arc::new(lib_path.to_path_buf());
+    let node_id = Arc::new(node_id.to_owned());
+
+    // Tracks queued for playback are analyzed first, while the run lasts.
+    // Subscribe before it starts, so no queue change is missed
+    let queued_receiver = events.map(|x| (x, x.subscribe()));
+    let run_finished = CancellationToken::new();
+    let queued = async {
+        if let Some((events, receiver)) = queued_receiver {
+            analyze_queued_files(
+                Arc::clone(&fsio),
+                main_db,
+                Arc::clone(&lib_path),
+                computing_device,
+                events,
+                receiver,
+                cancel_token.clone(),
+                &run_finished,
+            )
+            .await;
+        }
+    };
+
+    let run = async {
+        let _finished = run_finished.clone().drop_guard();
+        let analyzed: Result<usize> = checkpointed_media_files_processing!(
+            ANALYSIS_TASK,
+            main_db,
+            batch_size,
+            progress_callback,
+            cancel_token,
+            cursor_query,
+            lib_path,
+            fsio,
+            node_id,
+            move |fsio, file, lib_path, cancel_token| {
+                analysis_file(fsio, file, lib_path, computing_device, cancel_token)
+            },
+            |db,
+             file: media_files::Model,
+             _node_id,
+             analysis_result: Result<Option<NormalizedAnalysisResult>>| async move {
+                match analysis_result {
+                    Ok(analysis_result) => {
+                        if let Some(x) = analysis_result {
+                            match insert_analysis_result(db, file.id, x).await {
+                                Ok(_) => debug!("Finished analysis: {}", file.id),
+                                Err(e) => error!("Failed to insert analysis result: {e}"),
+                            }
+                        };
+                    }
+                    Err(e) => error!("Failed to analyze track: {e}"),
+                }
+            }
+        );
+        analyzed
+    };
+    let (analyzed, _) = futures::join!(run, queued);
+    let analyzed = analyzed?;
+
+    if let Some(events) = events {
+        let file_ids: Vec<i32> = media_analysis::Entity::find()
+            .select_only()
+            .column(media_analysis::Column::FileId)
+            .filter(media_analysis::Column::FeatureVersion.gte(ANALYSIS_FEATURE_VERSION))
+            .filter(media_analysis::Column::FileId.is_not_in(existed_ids))
+            .distinct()
+            .into_tuple::<i32>()
+            .all(main_db)
+            .await?;
+
+        if !file_ids.is_empty() {
+            events.publish(LibraryEvent::AnalysisFinished { file_ids });
+        }
+    }
+
+    Ok(analyzed)
+}
+
+/// Analyze only the given files, skipping the ones with an up to date
+/// analysis, so a selection can be analyzed before the whole library run
+/// gets to it. Nothing is checkpointed, the library run picks up whatever is
+/// left if this is cancelled.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `lib_path` - The root path for the audio files.
+/// * `file_ids` - The IDs of the files to analyze.
+/// * `batch_size` - The number of files to process in each batch.
+/// * `progress_callback` - A callback function to report progress.
+/// * `events` - An optional event bus to publish the analyzed files on.
+/// * `cancel_token` - An optional cancellation token to support task cancellation.
+///
+/// # Returns
+/// * `Result<usize>` - The number of files processed.
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_files<F>(
+    fsio: Arc<FsIo>,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    file_ids: &[i32],
+    batch_size: usize,
+    computing_device: ComputingDevice,
+    progress_callback: F,
+    events: Option<&EventBus>,
+    cancel_token: Option<CancellationToken>,
+) -> Result<usize>
+where
+    F: Fn(usize, usize) + Send + Sync + 'static,
+{
+    let progress_callback = Arc::new(progress_callback);
+
+    info!(
+        "Starting analysis of {} selected files with batch size: {batch_size}",
+        file_ids.len()
+    );
+
+    // Selections can be whole libraries, so IDs are bound a chunk at a time
+    let mut pending_ids: Vec<i32> = Vec::new();
+    for ids in file_ids.chunks(SELECTION_CHUNK_SIZE) {
+        pending_ids.extend(
+            media_files::Entity::find()
+                .select_only()
+                .column(media_files::Column::Id)
+                .filter(media_files::Column::Id.is_in(ids.to_vec()))
+                .filter(media_files::Column::Id.not_in_subquery(up_to_date_analysis().into_query()))
+                .into_tuple::<i32>()
+                .all(main_db)
+                .await?,
+        );
+    }
+    if pending_ids.is_empty() {
+        return Ok(0);
+    }
 
     let lib_path = Arc::new(lib_path.to_path_buf());
     let node_id = Arc::new(node_id.to_owned());
+    let total = pending_ids.len();
+
+    let mut analyzed = 0;
+    let mut done = 0;
+    for ids in pending_ids.chunks(SELECTION_CHUNK_SIZE) {
+        if cancel_token.as_ref().is_some_and(|x| x.is_cancelled()) {
+            break;
+        }
+
+        let cursor_query =
+            media_files::Entity::find().filter(media_files::Column::Id.is_in(ids.to_vec()));
+        let chunk_progress = {
+            let progress_callback = Arc::clone(&progress_callback);
+            Arc::new(move |processed: usize, _total: usize| {
+                progress_callback(done + processed, total)
+            })
+        };
+
+        let chunk_analyzed: Result<usize> = parallel_media_files_processing!(
+            main_db,
+            batch_size,
+            chunk_progress,
+            cancel_token,
+            cursor_query,
+            lib_path,
+            fsio,
+            node_id,
+            move |fsio, file, lib_path, cancel_token| {
+                analysis_file(fsio, file, lib_path, computing_device, cancel_token)
+            },
+            |db,
+             file: media_files::Model,
+             _node_id,
+             analysis_result: Result<Option<NormalizedAnalysisResult>>| async move {
+                match analysis_result {
+                    Ok(analysis_result) => {
+                        if let Some(x) = analysis_result {
+                            match insert_analysis_result(db, file.id, x).await {
+                                Ok(_) => debug!("Finished analysis: {}", file.id),
+                                Err(e) => error!("Failed to insert analysis result: {e}"),
+                            }
+                        };
+                    }
+                    Err(e) => error!("Failed to analyze track: {e}"),
+                }
+            }
+        );
+        analyzed += chunk_analyzed?;
+        done += ids.len();
+    }
+
+    if let Some(events) = events {
+        let mut file_ids: Vec<i32> = Vec::new();
+        for ids in pending_ids.chunks(SELECTION_CHUNK_SIZE) {
+            file_ids.extend(
+                up_to_date_analysis()
+                    .filter(media_analysis::Column::FileId.is_in(ids.to_vec()))
+                    .distinct()
+                    .into_tuple::<i32>()
+                    .all(main_db)
+                    .await?,
+            );
+        }
+
+        if !file_ids.is_empty() {
+            events.publish(LibraryEvent::AnalysisFinished { file_ids });
+        }
+    }
+
+    Ok(analyzed)
+}
+
+/// Analyze the tracks of an album that aren't analyzed yet, see
+/// [`analyze_files`].
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_album<F>(
+    fsio: Arc<FsIo>,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    album_id: i32,
+    batch_size: usize,
+    computing_device: ComputingDevice,
+    progress_callback: F,
+    events: Option<&EventBus>,
+    cancel_token: Option<CancellationToken>,
+) -> Result<usize>
+where
+    F: Fn(usize, usize) + Send + Sync + 'static,
+{
+    let file_ids = get_media_file_ids_of_album(main_db, album_id).await?;
 
-    parallel_media_files_processing!(
+    analyze_files(
+        fsio,
         main_db,
+        lib_path,
+        node_id,
+        &file_ids,
         batch_size,
+        computing_device,
         progress_callback,
+        events,
         cancel_token,
-        cursor_query,
-        lib_path,
-        fsio,
-        node_id,
-        move |fsio, file, lib_path, cancel_token| {
-            analysis_file(fsio, file, lib_path, computing_device, cancel_token)
-        },
-        |db,
-         file: media_files::Model,
-         _node_id,
-         analysis_result: Result<Option<NormalizedAnalysisResult>>| async move {
-            match analysis_result {
-                Ok(analysis_result) => {
-                    if let Some(x) = analysis_result {
-                        match insert_analysis_result(db, file.id, x).await {
-                            Ok(_) => debug!("Finished analysis: {}", file.id),
-                            Err(e) => error!("Failed to insert analysis result: {e}"),
-                        }
-                    };
-                }
-                Err(e) => error!("Failed to analyze track: {e}"),
+    )
+    .await
+}
+
+/// Analysis results that are up to date, selecting their file IDs.
+///
+/// Results of older feature versions are not comparable with new ones, so
+/// those files are analyzed again, as well as files analyzed before their
+/// waveform was kept.
+fn up_to_date_analysis() -> Select<media_analysis::Entity> {
+    media_analysis::Entity::find()
+        .select_only()
+        .column(media_analysis::Column::FileId)
+        .filter(media_analysis::Column::FeatureVersion.gte(ANALYSIS_FEATURE_VERSION))
+        .filter(
+            media_analysis::Column::FileId.in_subquery(
+                media_file_waveform::Entity::find()
+                    .select_only()
+                    .column(media_file_waveform::Column::MediaFileId)
+                    .into_query(),
+            ),
+        )
+}
+
+/// Analyze the tracks about to be played that aren't analyzed yet, ahead of
+/// the rest of the library, so their loudness and waveform are there by the
+/// time they start. Runs until `run_finished` is cancelled.
+///
+/// This only happens while a library analysis runs. Tracks queued outside
+/// of one are analyzed in their turn by the next run.
+#[allow(clippy::too_many_arguments)]
+async fn analyze_queued_files(
+    fsio: Arc<FsIo>,
+    main_db: &DatabaseConnection,
+    lib_path: Arc<PathBuf>,
+    computing_device: ComputingDevice,
+    events: &EventBus,
+    mut receiver: Receiver<LibraryEvent>,
+    cancel_token: Option<CancellationToken>,
+    run_finished: &CancellationToken,
+) {
+    while let Some(batch) = next_events(&mut receiver, run_finished).await {
+        // Only the latest queue matters
+        let Some(queued_ids) = batch.into_iter().rev().find_map(|x| match x {
+            LibraryEvent::TracksQueued { file_ids } => Some(file_ids),
+            _ => None,
+        }) else {
+            continue;
+        };
+
+        let files = match get_pending_queued_files(main_db, &queued_ids).await {
+            Ok(files) => files,
+            Err(e) => {
+                error!("Failed to find the queued tracks to analyze: {e}");
+                continue;
+            }
+        };
+
+        for file in files {
+            if run_finished.is_cancelled() {
+                return;
+            }
+
+            let file_id = file.id;
+
+            let fsio = Arc::clone(&fsio);
+            let lib_path = Arc::clone(&lib_path);
+            let cancel_token = cancel_token.clone();
+            let result = task::spawn_blocking(move || {
+                analysis_file(&fsio, &file, &lib_path, computing_device, cancel_token)
+            })
+            .await;
+
+            match result {
+                Ok(Ok(Some(x))) => match insert_analysis_result(main_db, file_id, x).await {
+                    Ok(_) => {
+                        info!("Analyzed queued track ahead of the library: {file_id}");
+                        events.publish(LibraryEvent::AnalysisFinished {
+                            file_ids: vec![file_id],
+                        });
+                    }
+                    Err(e) => error!("Failed to insert analysis result: {e}"),
+                },
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => error!("Failed to analyze queued track {file_id}: {e}"),
+                Err(e) => error!("Failed to spawn analysis task: {e}"),
             }
         }
-    )
+    }
+}
+
+/// The first [`QUEUED_ANALYSIS_AHEAD`] tracks of the queue that aren't
+/// analyzed yet, in queue order. Tracks already analyzed don't count, so
+/// they can't hide the pending ones behind them.
+async fn get_pending_queued_files(
+    main_db: &DatabaseConnection,
+    queued_ids: &[i32],
+) -> Result<Vec<media_files::Model>> {
+    let mut pending = Vec::new();
+
+    for ids in queued_ids.chunks(QUEUED_LOOKUP_PAGE_SIZE) {
+        let mut files: HashMap<i32, media_files::Model> = media_files::Entity::find()
+            .filter(media_files::Column::Id.is_in(ids.to_vec()))
+            .filter(media_files::Column::Id.not_in_subquery(up_to_date_analysis().into_query()))
+            .all(main_db)
+            .await?
+            .into_iter()
+            .map(|x| (x.id, x))
+            .collect();
+
+        for id in ids {
+            if let Some(file) = files.remove(id) {
+                pending.push(file);
+            }
+            if pending.len() == QUEUED_ANALYSIS_AHEAD {
+                return Ok(pending);
+            }
+        }
+    }
+
+    Ok(pending)
 }
 
 /// Process a file if it has not been analyzed yet. Perform audio analysis and store the results
@@ -137,7 +492,8 @@ fn analysis_file(
     Ok(Some(normalize_analysis_result(&analysis_result)))
 }
 
-/// Insert the normalized analysis result into the database.
+/// Insert the normalized analysis result into the database, replacing the
+/// existing result of the file if there is one.
 ///
 /// # Arguments
 /// * `main_db` - A reference to the database connection.
@@ -162,6 +518,28 @@ async fn insert_analysis_result(
         spectral_kurtosis: ActiveValue::Set(Decimal::from_f32(result.spectral_kurtosis)),
         perceptual_spread: ActiveValue::Set(Decimal::from_f32(result.raw.perceptual_spread)),
         perceptual_sharpness: ActiveValue::Set(Decimal::from_f32(result.raw.perceptual_sharpness)),
+        // Left empty for tracks without a steady beat, the feature version
+        // already keeps them from being analyzed again
+        bpm: ActiveValue::Set(result.raw.bpm.and_then(Decimal::from_f32)),
+        feature_version: ActiveValue::Set(Some(ANALYSIS_FEATURE_VERSION)),
+        key: ActiveValue::Set(result.raw.key.map(|x| x.tonic as i32)),
+        mode: ActiveValue::Set(result.raw.key.map(|x| x.mode.to_i32())),
+        integrated_loudness: ActiveValue::Set(
+            result
+                .raw
+                .loudness
+                .and_then(|x| x.integrated)
+                .and_then(Decimal::from_f32),
+        ),
+        true_peak: ActiveValue::Set(
+            result
+                .raw
+                .loudness
+                .and_then(|x| Decimal::from_f32(x.true_peak)),
+        ),
+        loudness_range: ActiveValue::Set(
+            result.raw.loudness.and_then(|x| Decimal::from_f32(x.range)),
+        ),
         ..Default::default()
     };
 
@@ -177,13 +555,39 @@ async fn insert_analysis_result(
         new_analysis.mfcc~N = ActiveValue::Set(Decimal::from_f32(result.raw.mfcc[N]));
     });
 
+    seq!(N in 0..7 {
+        new_analysis.octave_band~N = ActiveValue::Set(Decimal::from_f32(result.octave_bands[N]));
+    });
+    new_analysis.crest_factor = ActiveValue::Set(Decimal::from_f32(result.crest_factor));
+
+    // The queued tracks and the library pass may store the same file at
+    // once, so the result replaces the stored one in a single statement
+    let columns: Vec<media_analysis::Column> = media_analysis::Column::iter()
+        .filter(|x| {
+            !matches!(
+                x,
+                media_analysis::Column::Id | media_analysis::Column::FileId
+            ) && new_analysis.get(*x).is_set()
+        })
+        .collect();
     media_analysis::Entity::insert(new_analysis)
-        .exec(main_db)
+        .on_conflict(
+            OnConflict::column(media_analysis::Column::FileId)
+                .update_columns(columns)
+                .to_owned(),
+        )
+        .exec_without_returning(main_db)
         .await?;
 
+    upsert_waveform(main_db, file_id, result.raw.waveform.as_ref()).await?;
+
     Ok(())
 }
 
+/// Length of the vector built from an [`AggregatedAnalysisResult`], the
+/// dimensions of the recommendation index.
+pub const ANALYSIS_VECTOR_SIZE: usize = 69;
+
 /// Struct to store mean values of analysis results.
 #[derive(Debug)]
 pub struct AggregatedAnalysisResult {
@@ -202,9 +606,12 @@ pub struct AggregatedAnalysisResult {
     pub perceptual_sharpness: f64,
     pub perceptual_loudness: [f64; 24],
     pub mfcc: [f64; 13],
+    /// Zero for results of feature versions before 5.
+    pub octave_bands: [f64; 7],
+    pub crest_factor: f64,
 }
 
-impl From<AggregatedAnalysisResult> for [f32; 61] {
+impl From<AggregatedAnalysisResult> for [f32; ANALYSIS_VECTOR_SIZE] {
     fn from(val: AggregatedAnalysisResult) -> Self {
         [
             val.rms,
@@ -223,13 +630,45 @@ impl From<AggregatedAnalysisResult> for [f32; 61] {
         .chain(&vec![val.perceptual_spread, val.perceptual_sharpness])
         .chain(&val.perceptual_loudness)
         .chain(&val.mfcc)
+        .chain(&val.octave_bands)
+        .chain(&[val.crest_factor])
         .map(|x| *x as f32)
         .collect::<Vec<f32>>()
         .try_into()
-        .expect("Expected a Vec of length 61")
+        .expect("Expected a Vec of length ANALYSIS_VECTOR_SIZE")
     }
 }
 
+/// Names of the values in the vector built from an
+/// [`AggregatedAnalysisResult`], in the same order.
+pub fn analysis_feature_names() -> Vec<String> {
+    let mut names: Vec<String> = [
+        "rms",
+        "zcr",
+        "energy",
+        "spectral_centroid",
+        "spectral_flatness",
+        "spectral_slope",
+        "spectral_rolloff",
+        "spectral_spread",
+        "spectral_skewness",
+        "spectral_kurtosis",
+    ]
+    .iter()
+    .map(|x| x.to_string())
+    .collect();
+
+    names.extend((0..12).map(|i| format!("chroma_{i}")));
+    names.push("perceptual_spread".to_string());
+    names.push("perceptual_sharpness".to_string());
+    names.extend((0..24).map(|i| format!("perceptual_loudness_{i}")));
+    names.extend((0..13).map(|i| format!("mfcc_{i}")));
+    names.extend((0..7).map(|i| format!("octave_band_{i}")));
+    names.push("crest_factor".to_string());
+
+    names
+}
+
 impl From<media_analysis::Model> for AggregatedAnalysisResult {
     fn from(model: media_analysis::Model) -> Self {
         AggregatedAnalysisResult {
@@ -306,6 +745,18 @@ impl From<media_analysis::Model> for AggregatedAnalysisResult {
                 });
                 mfcc_array
             },
+            octave_bands: {
+                let mut bands_array = [0.0; 7];
+                seq!(N in 0..=6 {
+                    bands_array[N] = model.octave_band~N.map(|d| d.to_f64().unwrap_or(0.0)).unwrap_or(0.0);
+                });
+                bands_array
+            },
+            crest_factor: model
+                .crest_factor
+                .unwrap_or_default()
+                .to_f64()
+                .unwrap_or_default(),
         }
     }
 }
@@ -372,6 +823,34 @@ pub async fn get_analyze_count(main_db: &DatabaseConnection) -> Result<u64> {
     Ok(media_analysis::Entity::find().count(main_db).await?)
 }
 
+/// Every analyzed file together with its feature vector, ordered by file ID.
+pub async fn get_analysis_features(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<(media_files::Model, [f32; ANALYSIS_VECTOR_SIZE])>> {
+    let mut analyses: HashMap<i32, media_analysis::Model> = media_analysis::Entity::find()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.file_id, x))
+        .collect();
+
+    let files = media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(analyses.keys().copied()))
+        .order_by_asc(media_files::Column::Id)
+        .all(main_db)
+        .await?;
+
+    Ok(files
+        .into_iter()
+        .filter_map(|file| {
+            let analysis = analyses.remove(&file.id)?;
+            let vector: [f32; ANALYSIS_VECTOR_SIZE] =
+                AggregatedAnalysisResult::from(analysis).into();
+            Some((file, vector))
+        })
+        .collect())
+}
+
 /// Computes the centralized analysis result from the database.
 ///
 /// This function retrieves analysis results based on specified file IDs,
@@ -411,6 +890,8 @@ pub async fn get_centralized_analysis_result(
         perceptual_sharpness: 0.0,
         perceptual_loudness: [0.0; 24],
         mfcc: [0.0; 13],
+        octave_bands: [0.0; 7],
+        crest_factor: 0.0,
     };
 
     let mut count = AggregatedAnalysisResult {
@@ -429,6 +910,8 @@ pub async fn get_centralized_analysis_result(
         perceptual_sharpness: 0.0,
         perceptual_loudness: [0.0; 24],
         mfcc: [0.0; 13],
+        octave_bands: [0.0; 7],
+        crest_factor: 0.0,
     };
 
     for result in analysis_results {
@@ -448,6 +931,15 @@ pub async fn get_centralized_analysis_result(
         process_array!(sum, count, result, perceptual_loudness, 24);
         process_array!(sum, count, result, mfcc, 13);
         process_array!(sum, count, result, chroma, 12);
+
+        // Named `octave_band{N}` in the table and `octave_bands` here
+        seq!(N in 0..7 {
+            if let Some(value) = result.octave_band~N {
+                sum.octave_bands[N] += value.to_f64().expect("Unable to convert parameter");
+                count.octave_bands[N] += 1.0;
+            }
+        });
+        process_field!(sum, count, result, crest_factor);
     }
 
     Ok(AggregatedAnalysisResult {
@@ -466,6 +958,8 @@ pub async fn get_centralized_analysis_result(
         chroma: calculate_array_mean!(sum, count, chroma, 12),
         perceptual_loudness: calculate_array_mean!(sum, count, perceptual_loudness, 24),
         mfcc: calculate_array_mean!(sum, count, mfcc, 13),
+        octave_bands: calculate_array_mean!(sum, count, octave_bands, 7),
+        crest_factor: calculate_mean!(sum, count, crest_factor),
     })
 }
 
@@ -505,7 +999,7 @@ pub async fn get_percentile(
 pub async fn get_percentile_analysis_result(
     main_db: &DatabaseConnection,
     percentile: f64,
-) -> Result<[f32; 61]> {
+) -> Result<[f32; ANALYSIS_VECTOR_SIZE]> {
     let columns: Vec<media_analysis::Column> = [
         media_analysis::Column::Rms,
         media_analysis::Column::Zcr,
@@ -532,6 +1026,10 @@ pub async fn get_percentile_analysis_result(
     .chain(seq!(N in 0..13 {[
         #(media_analysis::Column::Mfcc~N,)*
     ]}))
+    .chain(seq!(N in 0..7 {[
+        #(media_analysis::Column::OctaveBand~N,)*
+    ]}))
+    .chain([media_analysis::Column::CrestFactor])
     .collect();
 
     let total_files = media_files::Entity::find()
@@ -550,17 +1048,474 @@ pub async fn get_percentile_analysis_result(
         virtual_point.push(percentile.with_context(|| "Unable to calculate percentiles")?);
     }
 
-    if virtual_point.len() != 61 {
+    if virtual_point.len() != ANALYSIS_VECTOR_SIZE {
         bail!(
             "Failed to convert virtual_point to array: incorrect length (got {}, expected {})",
             virtual_point.len(),
-            61
+            ANALYSIS_VECTOR_SIZE
         );
     }
 
-    let virtual_point: [f32; 61] = virtual_point
+    let virtual_point: [f32; ANALYSIS_VECTOR_SIZE] = virtual_point
         .try_into()
         .expect("Length checked above, this should never fail");
 
     Ok(virtual_point)
 }
+
+/// Loudness ReplayGain gains bring tracks to, in LUFS.
+const REPLAY_GAIN_REFERENCE_LUFS: f64 = -18.0;
+
+/// Loudness of a file and of the album it belongs to, for ReplayGain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessSummary {
+    /// Integrated loudness of the track in LUFS.
+    pub track_loudness: f64,
+    /// True peak of the track in dBTP.
+    pub track_peak: f64,
+    pub album_loudness: Option<f64>,
+    pub album_peak: Option<f64>,
+}
+
+/// Fetches the loudness of the given files. Files without a loudness
+/// measurement fall back to their ReplayGain tags, files without either are
+/// left out.
+///
+/// The loudness of an album is the duration weighted energy average of all
+/// measured tracks of the album, its peak is the highest track peak.
+pub async fn get_loudness_by_file_ids(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, LoudnessSummary>> {
+    let to_f64 = |x: Option<Decimal>| x.and_then(|x| x.to_f64());
+
+    let album_links = media_file_albums::Entity::find()
+        .filter(media_file_albums::Column::MediaFileId.is_in(file_ids.to_vec()))
+        .all(main_db)
+        .await
+        .with_context(|| "Failed to query albums of files")?;
+
+    let album_ids: Vec<i32> = album_links.iter().map(|x| x.album_id).collect();
+    let album_tracks = media_file_albums::Entity::find()
+        .filter(media_file_albums::Column::AlbumId.is_in(album_ids))
+        .all(main_db)
+        .await
+        .with_context(|| "Failed to query album tracks")?;
+
+    let mut all_file_ids: Vec<i32> = file_ids.to_vec();
+    all_file_ids.extend(album_tracks.iter().map(|x| x.media_file_id));
+    all_file_ids.sort_unstable();
+    all_file_ids.dedup();
+
+    let analyses: HashMap<i32, (f64, f64)> = media_analysis::Entity::find()
+        .filter(media_analysis::Column::FileId.is_in(all_file_ids.clone()))
+        .all(main_db)
+        .await
+        .with_context(|| "Failed to query loudness")?
+        .into_iter()
+        .filter_map(|x| {
+            Some((
+                x.file_id,
+                (to_f64(x.integrated_loudness)?, to_f64(x.true_peak)?),
+            ))
+        })
+        .collect();
+
+    let durations: HashMap<i32, f64> = media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(all_file_ids))
+        .all(main_db)
+        .await
+        .with_context(|| "Failed to query file durations")?
+        .into_iter()
+        .map(|x| (x.id, x.duration.to_f64().unwrap_or_default()))
+        .collect();
+
+    // album_id -> (energy seconds, seconds, peak)
+    let mut albums: HashMap<i32, (f64, f64, f64)> = HashMap::new();
+    for track in &album_tracks {
+        let Some((loudness, peak)) = analyses.get(&track.media_file_id) else {
+            continue;
+        };
+        let duration = durations
+            .get(&track.media_file_id)
+            .copied()
+            .filter(|x| *x > 0.0)
+            .unwrap_or(1.0);
+
+        let album = albums
+            .entry(track.album_id)
+            .or_insert((0.0, 0.0, f64::NEG_INFINITY));
+        album.0 += duration * 10f64.powf(loudness / 10.0);
+        album.1 += duration;
+        album.2 = album.2.max(*peak);
+    }
+
+    let album_of_file: HashMap<i32, i32> = album_links
+        .iter()
+        .map(|x| (x.media_file_id, x.album_id))
+        .collect();
+
+    let mut result: HashMap<i32, LoudnessSummary> = file_ids
+        .iter()
+        .filter_map(|file_id| {
+            let (track_loudness, track_peak) = *analyses.get(file_id)?;
+            let album = album_of_file
+                .get(file_id)
+                .and_then(|x| albums.get(x))
+                .filter(|x| x.1 > 0.0);
+
+            Some((
+                *file_id,
+                LoudnessSummary {
+                    track_loudness,
+                    track_peak,
+                    album_loudness: album.map(|x| 10.0 * (x.0 / x.1).log10()),
+                    album_peak: album.map(|x| x.2),
+                },
+            ))
+        })
+        .collect();
+
+    let unmeasured: Vec<i32> = file_ids
+        .iter()
+        .filter(|x| !result.contains_key(x))
+        .copied()
+        .collect();
+    if !unmeasured.is_empty() {
+        result.extend(get_tagged_loudness(main_db, &unmeasured).await?);
+    }
+
+    Ok(result)
+}
+
+/// Parse a ReplayGain gain like `-6.50 dB`.
+fn parse_replay_gain(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().parse().ok()
+}
+
+/// Parse a ReplayGain peak, a linear amplitude, into dBTP.
+fn parse_replay_gain_peak(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|x| *x > 0.0)
+        .map(|x| 20.0 * x.log10())
+}
+
+/// The loudness of files without a measurement, taken from their ReplayGain
+/// tags. A gain brings the track to [`REPLAY_GAIN_REFERENCE_LUFS`], so the
+/// track was that much quieter. Without a peak tag full scale is assumed.
+async fn get_tagged_loudness(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, LoudnessSummary>> {
+    let mut tags: HashMap<i32, HashMap<String, String>> = HashMap::new();
+    for entry in media_metadata::Entity::find()
+        .filter(media_metadata::Column::FileId.is_in(file_ids.to_vec()))
+        .filter(media_metadata::Column::MetaKey.is_in([
+            "replaygain_track_gain",
+            "replaygain_track_peak",
+            "replaygain_album_gain",
+            "replaygain_album_peak",
+        ]))
+        .all(main_db)
+        .await
+        .with_context(|| "Failed to query ReplayGain tags")?
+    {
+        tags.entry(entry.file_id)
+            .or_default()
+            .insert(entry.meta_key, entry.meta_value);
+    }
+
+    Ok(tags
+        .into_iter()
+        .filter_map(|(file_id, tags)| {
+            let tag = |key: &str| tags.get(key).map(|x| x.as_str());
+            let track_gain = tag("replaygain_track_gain").and_then(parse_replay_gain)?;
+            let track_peak = tag("replaygain_track_peak")
+                .and_then(parse_replay_gain_peak)
+                .unwrap_or(0.0);
+            let album_gain = tag("replaygain_album_gain").and_then(parse_replay_gain);
+
+            Some((
+                file_id,
+                LoudnessSummary {
+                    track_loudness: REPLAY_GAIN_REFERENCE_LUFS - track_gain,
+                    track_peak,
+                    album_loudness: album_gain.map(|x| REPLAY_GAIN_REFERENCE_LUFS - x),
+                    album_peak: album_gain.map(|_| {
+                        tag("replaygain_album_peak")
+                            .and_then(parse_replay_gain_peak)
+                            .unwrap_or(0.0)
+                    }),
+                },
+            ))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, sync::Arc};
+
+    use anyhow::Result;
+    use sea_orm::{ActiveValue, EntityTrait, PaginatorTrait};
+    use tempfile::tempdir;
+
+    use ::analysis::utils::computing_device::ComputingDevice;
+    use ::fsio::FsIo;
+
+    use crate::{
+        actions::{
+            analysis::{
+                QUEUED_ANALYSIS_AHEAD, analysis_file, analyze_album, analyze_files,
+                empty_progress_callback, get_analyze_count, get_loudness_by_file_ids,
+                get_pending_queued_files, insert_analysis_result,
+            },
+            metadata::get_metadata_summary_by_file_ids,
+            waveform::upsert_waveform,
+        },
+        entities::{albums, artists, media_analysis, media_files, media_metadata},
+        events::EventBus,
+        test_support::{
+            FakeTrack, TEST_NODE_ID, TRACKS_PER_ALBUM, connect_test_main_db, seed_fake_analysis,
+            seed_fake_library, seed_fake_tracks, seed_tracks,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_seed_fake_tracks() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 25).await?;
+        assert_eq!(file_ids.len(), 25);
+
+        let summaries = get_metadata_summary_by_file_ids(&db, file_ids.clone()).await?;
+        let first = summaries.iter().find(|x| x.id == file_ids[0]).unwrap();
+        let expected = FakeTrack::nth(0);
+        assert_eq!(first.title, expected.title);
+        assert_eq!(first.album, expected.album);
+        assert_eq!(first.artist, expected.artist);
+        assert!(first.key.is_none());
+
+        // Tracks are indexed into albums and artists
+        assert_eq!(
+            albums::Entity::find().count(&db).await?,
+            25usize.div_ceil(TRACKS_PER_ALBUM) as u64
+        );
+        assert_eq!(artists::Entity::find().count(&db).await?, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_seed_fake_library() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_library(&db, 12).await?;
+        assert_eq!(get_analyze_count(&db).await?, 12);
+
+        let summaries = get_metadata_summary_by_file_ids(&db, file_ids.clone()).await?;
+        assert!(summaries.iter().all(|x| x.key.is_some()));
+
+        let loudness = get_loudness_by_file_ids(&db, &file_ids).await?;
+        assert_eq!(loudness.len(), 12);
+
+        for summary in loudness.values() {
+            let album_loudness = summary.album_loudness.unwrap();
+            let album_peak = summary.album_peak.unwrap();
+            assert!((-24.0..-6.0).contains(&album_loudness));
+            assert!(album_peak >= summary.track_peak);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_loudness_falls_back_to_replay_gain_tags() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        let tags = [
+            (file_ids[0], "replaygain_track_gain", "-6.50 dB"),
+            (file_ids[0], "replaygain_track_peak", "0.5"),
+            (file_ids[0], "replaygain_album_gain", "-7.00 dB"),
+            (file_ids[1], "replaygain_track_gain", "+2.00 dB"),
+        ];
+        media_metadata::Entity::insert_many(tags.map(|(file_id, key, value)| {
+            media_metadata::ActiveModel {
+                file_id: ActiveValue::Set(file_id),
+                meta_key: ActiveValue::Set(key.to_string()),
+                meta_value: ActiveValue::Set(value.to_string()),
+                ..Default::default()
+            }
+        }))
+        .exec(&db)
+        .await?;
+
+        let loudness = get_loudness_by_file_ids(&db, &file_ids).await?;
+        assert_eq!(
+            loudness.len(),
+            2,
+            "untagged files without analysis are left out"
+        );
+
+        let first = loudness[&file_ids[0]];
+        assert!((first.track_loudness - -11.5).abs() < 1e-6);
+        assert!((first.track_peak - -6.0206).abs() < 1e-3);
+        assert!((first.album_loudness.unwrap() - -11.0).abs() < 1e-6);
+        assert_eq!(first.album_peak, Some(0.0));
+
+        let second = loudness[&file_ids[1]];
+        assert!((second.track_loudness - -20.0).abs() < 1e-6);
+        assert_eq!(second.track_peak, 0.0);
+        assert_eq!(second.album_loudness, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_isolated_databases() -> Result<()> {
+        let first = connect_test_main_db().await?;
+        let second = connect_test_main_db().await?;
+
+        seed_fake_tracks(&first, 3).await?;
+        assert_eq!(get_analyze_count(&second).await?, 0);
+        assert_eq!(albums::Entity::find().count(&second).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_analyze_selection_skips_missing_files() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+        let lib_dir = tempdir()?;
+        let fsio = Arc::new(FsIo::new());
+        let events = EventBus::default();
+        let mut receiver = events.subscribe();
+
+        // Nothing selected, nothing to do
+        let analyzed = analyze_files(
+            Arc::clone(&fsio),
+            &db,
+            lib_dir.path(),
+            TEST_NODE_ID,
+            &[],
+            4,
+            ComputingDevice::Cpu,
+            empty_progress_callback,
+            Some(&events),
+            None,
+        )
+        .await?;
+        assert_eq!(analyzed, 0);
+
+        // Albums without tracks are fine too
+        let analyzed = analyze_album(
+            Arc::clone(&fsio),
+            &db,
+            lib_dir.path(),
+            TEST_NODE_ID,
+            -1,
+            4,
+            ComputingDevice::Cpu,
+            empty_progress_callback,
+            Some(&events),
+            None,
+        )
+        .await?;
+        assert_eq!(analyzed, 0);
+
+        // The fake tracks aren't on disk, so failing them stores nothing
+        analyze_files(
+            fsio,
+            &db,
+            lib_dir.path(),
+            TEST_NODE_ID,
+            &file_ids[..1],
+            4,
+            ComputingDevice::Cpu,
+            empty_progress_callback,
+            Some(&events),
+            None,
+        )
+        .await?;
+        assert_eq!(get_analyze_count(&db).await?, 0);
+        assert!(receiver.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pending_queued_files_skip_analyzed_ones() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, QUEUED_ANALYSIS_AHEAD + 4).await?;
+
+        // The head of the queue is analyzed already
+        for file_id in &file_ids[..4] {
+            seed_fake_analysis(&db, *file_id).await?;
+            upsert_waveform(&db, *file_id, None).await?;
+        }
+
+        let mut queued_ids = file_ids.clone();
+        queued_ids.reverse();
+        let pending: Vec<i32> = get_pending_queued_files(&db, &queued_ids)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        assert_eq!(pending, queued_ids[..QUEUED_ANALYSIS_AHEAD]);
+
+        let pending: Vec<i32> = get_pending_queued_files(&db, &file_ids)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        assert_eq!(pending, file_ids[4..]);
+
+        assert!(get_pending_queued_files(&db, &[]).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_analysis_result_replaces_stored_one() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let track = FakeTrack {
+            file_name: "startup_0.ogg".to_owned(),
+            directory: String::new(),
+            ..FakeTrack::nth(0)
+        };
+        let file_id = seed_tracks(&db, &[track]).await?[0];
+        let file = media_files::Entity::find_by_id(file_id)
+            .one(&db)
+            .await?
+            .unwrap();
+
+        let result = analysis_file(
+            &FsIo::new(),
+            &file,
+            Path::new("../assets"),
+            ComputingDevice::Cpu,
+            None,
+        )?
+        .unwrap();
+
+        insert_analysis_result(&db, file_id, result.clone()).await?;
+
+        // A track without a steady beat keeps its tempo empty
+        let mut result = result;
+        result.raw.bpm = None;
+        insert_analysis_result(&db, file_id, result).await?;
+        assert_eq!(media_analysis::Entity::find().count(&db).await?, 1);
+        let analysis = media_analysis::Entity::find().one(&db).await?.unwrap();
+        assert_eq!(analysis.bpm, None);
+
+        Ok(())
+    }
+}
@@ -1,8 +1,12 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use sea_orm::prelude::*;
+use sea_orm::sea_query::Query;
+use sea_orm::{QueryOrder, QuerySelect};
+
+use ::metadata::artist::ArtistRole;
 
 use crate::actions::collection::{CollectionQuery, CollectionQueryType};
 use crate::collection_query;
@@ -28,3 +32,218 @@ collection_query!(
     media_file_artists,
     ArtistId
 );
+
+pub async fn get_artists_groups(
+    main_db: &DatabaseConnection,
+    groups: Vec<String>,
+) -> Result<Vec<(String, Vec<(artists::Model, HashSet<i32>)>)>> {
+    Ok(get_groups_internal(main_db, groups).await?)
+}
+
+/// The artists credited in a role on any track, by name. With
+/// `ArtistRole::Composer` this lists the composers of the library.
+pub async fn get_artists_by_role(
+    main_db: &DatabaseConnection,
+    role: ArtistRole,
+) -> Result<Vec<artists::Model>> {
+    Ok(artists::Entity::find()
+        .filter(
+            artists::Column::Id.in_subquery(
+                Query::select()
+                    .column(media_file_artists::Column::ArtistId)
+                    .from(media_file_artists::Entity)
+                    .and_where(media_file_artists::Column::Role.eq(role.as_str()))
+                    .to_owned(),
+            ),
+        )
+        .order_by_asc(artists::Column::Name)
+        .all(main_db)
+        .await?)
+}
+
+/// The files an artist is credited on, only in `role` if given.
+pub async fn get_media_file_ids_of_artist(
+    main_db: &DatabaseConnection,
+    artist_id: i32,
+    role: Option<ArtistRole>,
+) -> Result<Vec<i32>> {
+    let mut query = media_file_artists::Entity::find()
+        .select_only()
+        .column(media_file_artists::Column::MediaFileId)
+        .distinct()
+        .filter(media_file_artists::Column::ArtistId.eq(artist_id));
+    if let Some(role) = role {
+        query = query.filter(media_file_artists::Column::Role.eq(role.as_str()));
+    }
+
+    Ok(query
+        .order_by_asc(media_file_artists::Column::MediaFileId)
+        .into_tuple()
+        .all(main_db)
+        .await?)
+}
+
+/// Everyone credited on a file and their roles, main artists first.
+pub async fn get_artist_credits_of_file(
+    main_db: &DatabaseConnection,
+    file_id: i32,
+) -> Result<Vec<(artists::Model, ArtistRole)>> {
+    let links = media_file_artists::Entity::find()
+        .filter(media_file_artists::Column::MediaFileId.eq(file_id))
+        .order_by_asc(media_file_artists::Column::Id)
+        .all(main_db)
+        .await?;
+
+    let artist_ids: Vec<i32> = links.iter().map(|x| x.artist_id).collect();
+    let artists: HashMap<i32, artists::Model> = artists::Entity::find()
+        .filter(artists::Column::Id.is_in(artist_ids))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+
+    let mut credits: Vec<(artists::Model, ArtistRole)> = links
+        .into_iter()
+        .filter_map(|link| {
+            let role = ArtistRole::parse(&link.role).unwrap_or(ArtistRole::Main);
+            artists.get(&link.artist_id).map(|x| (x.clone(), role))
+        })
+        .collect();
+    credits.sort_by_key(|(_, role)| ArtistRole::ALL.iter().position(|x| x == role));
+
+    Ok(credits)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::{ActiveValue, EntityTrait};
+
+    use ::metadata::{
+        artist::{ArtistCredit, ArtistRole, artist_credits, remixer_from_title},
+        splitting::SplittingRules,
+    };
+
+    use crate::{
+        actions::{
+            artists::{
+                get_artist_credits_of_file, get_artists_by_role, get_media_file_ids_of_artist,
+            },
+            index::index_media_files,
+        },
+        entities::media_metadata,
+        test_support::{FakeTrack, connect_test_main_db, seed_tracks},
+    };
+
+    fn credit(name: &str, role: ArtistRole) -> ArtistCredit {
+        ArtistCredit {
+            name: name.to_string(),
+            role,
+        }
+    }
+
+    #[test]
+    fn test_artist_credits() {
+        let rules = SplittingRules::default();
+
+        assert_eq!(
+            artist_credits("Alpha feat. Beta", "Song", None, None, &rules),
+            vec![
+                credit("Alpha", ArtistRole::Main),
+                credit("Beta", ArtistRole::Featured)
+            ]
+        );
+        assert_eq!(
+            artist_credits(
+                "Alpha; Beta (ft. Gamma)",
+                "Song",
+                Some("Delta"),
+                None,
+                &rules
+            ),
+            vec![
+                credit("Alpha", ArtistRole::Main),
+                credit("Beta", ArtistRole::Main),
+                credit("Gamma", ArtistRole::Featured),
+                credit("Delta", ArtistRole::Composer),
+            ]
+        );
+
+        // A singer-songwriter is credited in both roles, but not featured on their own track
+        assert_eq!(
+            artist_credits("Alpha featuring Alpha", "Song", Some("Alpha"), None, &rules),
+            vec![
+                credit("Alpha", ArtistRole::Main),
+                credit("Alpha", ArtistRole::Composer),
+            ]
+        );
+
+        assert_eq!(
+            remixer_from_title("Song (Epsilon Remix)"),
+            Some("Epsilon".to_string())
+        );
+        assert_eq!(remixer_from_title("Song (Live)"), None);
+        assert_eq!(
+            artist_credits("Alpha", "Song [Epsilon remix]", None, Some("Zeta"), &rules),
+            vec![
+                credit("Alpha", ArtistRole::Main),
+                credit("Zeta", ArtistRole::Remixer),
+            ]
+        );
+
+        assert_eq!(ArtistRole::parse("Composer"), Some(ArtistRole::Composer));
+        assert_eq!(ArtistRole::parse("producer"), None);
+    }
+
+    #[tokio::test]
+    async fn test_artist_roles_are_indexed() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        let mut first = FakeTrack::nth(0);
+        first.artist = "Alpha feat. Beta".to_string();
+        let mut second = FakeTrack::nth(1);
+        second.artist = "Beta".to_string();
+        let file_ids = seed_tracks(&db, &[first, second]).await?;
+
+        media_metadata::Entity::insert(media_metadata::ActiveModel {
+            file_id: ActiveValue::Set(file_ids[0]),
+            meta_key: ActiveValue::Set("composer".to_string()),
+            meta_value: ActiveValue::Set("Gamma".to_string()),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await?;
+        index_media_files(&db, file_ids.clone(), None).await?;
+
+        let credits: Vec<(String, ArtistRole)> = get_artist_credits_of_file(&db, file_ids[0])
+            .await?
+            .into_iter()
+            .map(|(artist, role)| (artist.name, role))
+            .collect();
+        assert_eq!(
+            credits,
+            vec![
+                ("Alpha".to_string(), ArtistRole::Main),
+                ("Beta".to_string(), ArtistRole::Featured),
+                ("Gamma".to_string(), ArtistRole::Composer),
+            ]
+        );
+
+        let composers = get_artists_by_role(&db, ArtistRole::Composer).await?;
+        assert_eq!(composers.len(), 1);
+        assert_eq!(composers[0].name, "Gamma");
+
+        let beta = get_artists_by_role(&db, ArtistRole::Featured).await?[0].id;
+        assert_eq!(
+            get_media_file_ids_of_artist(&db, beta, None).await?,
+            file_ids
+        );
+        assert_eq!(
+            get_media_file_ids_of_artist(&db, beta, Some(ArtistRole::Main)).await?,
+            vec![file_ids[1]]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! Progress of long running library tasks, written as they go so a task cut
+//! short by a crash picks up where it stopped instead of starting over.
+//! A checkpoint is removed once its task runs to the end.
+
+use anyhow::Result;
+use chrono::Utc;
+use migration::OnConflict;
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, QueryOrder, QuerySelect, Select};
+
+use crate::entities::{media_files, task_checkpoints};
+
+pub const SCAN_TASK: &str = "scan";
+pub const ANALYSIS_TASK: &str = "analysis";
+pub const COVER_ART_TASK: &str = "cover_art";
+
+/// Files handled between two checkpoints of tasks walking the database.
+pub const CHECKPOINT_CHUNK_SIZE: u64 = 200;
+
+pub async fn get_checkpoint(
+    main_db: &DatabaseConnection,
+    task: &str,
+) -> Result<Option<task_checkpoints::Model>> {
+    Ok(task_checkpoints::Entity::find()
+        .filter(task_checkpoints::Column::Task.eq(task))
+        .one(main_db)
+        .await?)
+}
+
+pub async fn list_checkpoints(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<task_checkpoints::Model>> {
+    Ok(task_checkpoints::Entity::find()
+        .order_by_asc(task_checkpoints::Column::Task)
+        .all(main_db)
+        .await?)
+}
+
+pub async fn save_checkpoint(
+    main_db: &DatabaseConnection,
+    task: &str,
+    scope: &str,
+    position: i64,
+    processed: i64,
+) -> Result<()> {
+    let model = task_checkpoints::ActiveModel {
+        task: ActiveValue::Set(task.to_string()),
+        scope: ActiveValue::Set(scope.to_string()),
+        position: ActiveValue::Set(position),
+        processed: ActiveValue::Set(processed),
+        updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+        ..Default::default()
+    };
+    task_checkpoints::Entity::insert(model)
+        .on_conflict(
+            OnConflict::column(task_checkpoints::Column::Task)
+                .update_columns([
+                    task_checkpoints::Column::Scope,
+                    task_checkpoints::Column::Position,
+                    task_checkpoints::Column::Processed,
+                    task_checkpoints::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(main_db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn clear_checkpoint(main_db: &DatabaseConnection, task: &str) -> Result<()> {
+    task_checkpoints::Entity::delete_many()
+        .filter(task_checkpoints::Column::Task.eq(task))
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
+/// The last file ID of the next chunk of `query` after `after`, `None` once
+/// every file has been handled.
+pub async fn next_chunk_end(
+    main_db: &DatabaseConnection,
+    query: Select<media_files::Entity>,
+    after: i32,
+) -> Result<Option<i32>> {
+    let ids: Vec<i32> = query
+        .select_only()
+        .column(media_files::Column::Id)
+        .filter(media_files::Column::Id.gt(after))
+        .order_by_asc(media_files::Column::Id)
+        .limit(CHECKPOINT_CHUNK_SIZE)
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    Ok(ids.last().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use tokio_util::sync::CancellationToken;
+
+    use ::fsio::FsIo;
+
+    use crate::{
+        actions::{
+            checkpoint::{
+                COVER_ART_TASK, SCAN_TASK, clear_checkpoint, get_checkpoint, list_checkpoints,
+                save_checkpoint,
+            },
+            cover_art::scan_cover_arts,
+        },
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_save_and_clear_checkpoints() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        save_checkpoint(&db, SCAN_TASK, "/music", 12, 12).await?;
+        save_checkpoint(&db, COVER_ART_TASK, "", 7, 5).await?;
+        save_checkpoint(&db, SCAN_TASK, "/music", 24, 24).await?;
+
+        let checkpoints = list_checkpoints(&db).await?;
+        let tasks: Vec<&str> = checkpoints.iter().map(|x| x.task.as_str()).collect();
+        assert_eq!(tasks, vec![COVER_ART_TASK, SCAN_TASK]);
+
+        let scan = get_checkpoint(&db, SCAN_TASK).await?.unwrap();
+        assert_eq!(scan.scope, "/music");
+        assert_eq!(scan.position, 24);
+
+        clear_checkpoint(&db, SCAN_TASK).await?;
+        assert!(get_checkpoint(&db, SCAN_TASK).await?.is_none());
+        assert_eq!(list_checkpoints(&db).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cover_art_scan_resumes_from_checkpoint() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let fsio = Arc::new(FsIo::new());
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        // An earlier run stopped after the second file
+        save_checkpoint(&db, COVER_ART_TASK, "", file_ids[1] as i64, 2).await?;
+
+        // A cancelled run leaves the checkpoint in place
+        let cancel_token = CancellationToken::new();
+        cancel_token.cancel();
+        scan_cover_arts(
+            Arc::clone(&fsio),
+            &db,
+            lib_dir.path(),
+            "test",
+            2,
+            |_, _| {},
+            Some(cancel_token),
+        )
+        .await?;
+        let checkpoint = get_checkpoint(&db, COVER_ART_TASK).await?.unwrap();
+        assert_eq!(checkpoint.position, file_ids[1] as i64);
+
+        let processed = scan_cover_arts(
+            Arc::clone(&fsio),
+            &db,
+            lib_dir.path(),
+            "test",
+            2,
+            |_, _| {},
+            None,
+        )
+        .await?;
+        assert_eq!(processed, 1);
+        assert!(get_checkpoint(&db, COVER_ART_TASK).await?.is_none());
+
+        Ok(())
+    }
+}
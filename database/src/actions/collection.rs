@@ -1,4 +1,8 @@
-use std::{collections::HashSet, fmt, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -81,6 +85,7 @@ pub struct UnifiedCollection {
     pub queries: Vec<(String, String)>,
     pub collection_type: CollectionQueryType,
     pub readonly: bool,
+    pub subtitle: Option<String>,
 }
 
 impl UnifiedCollection {
@@ -95,6 +100,7 @@ impl UnifiedCollection {
             queries: T::query_builder(main_db, model.id()).await?,
             collection_type: T::collection_type(),
             readonly,
+            subtitle: model.subtitle(),
         };
 
         Ok(collection)
@@ -152,9 +158,23 @@ pub trait CollectionQuery: Send + Sync + 'static {
     ) -> Result<Vec<Self>>
     where
         Self: std::marker::Sized;
+    /// A visible file with a cover art for each of the collections, the one
+    /// with the lowest ID, found with a single grouped query. Collections
+    /// without cover arts are left out. `None` for collections whose tracks
+    /// are only known by evaluating their queries.
+    async fn get_cover_file_ids(
+        _main_db: &MainDbConnection,
+        _ids: &[i32],
+    ) -> Result<Option<HashMap<i32, i32>>> {
+        Ok(None)
+    }
     fn id(&self) -> i32;
     fn name(&self) -> &str;
     fn readonly(&self) -> bool;
+    /// A second line shown under the name, like the artist of an album.
+    fn subtitle(&self) -> Option<String> {
+        None
+    }
 }
 
 #[macro_export]
@@ -165,6 +185,7 @@ macro_rules! collection_query {
         $query_operator:expr,
         $related_entity:ident,
         $relation_column_name:ident
+        $(, subtitle = $subtitle:expr)?
     ) => {
         // First generate the get_groups function
         async fn get_groups_internal(
@@ -268,6 +289,38 @@ macro_rules! collection_query {
                     .map_err(|e| anyhow::anyhow!("Failed to get collection groups: {e}"))
             }
 
+            async fn get_cover_file_ids(
+                main_db: &MainDbConnection,
+                ids: &[i32],
+            ) -> Result<Option<std::collections::HashMap<i32, i32>>> {
+                use anyhow::Context;
+                use sea_orm::{JoinType, QuerySelect, RelationTrait};
+                use $crate::actions::cover_art::get_magic_cover_art_id;
+                use $crate::actions::stats::hidden_files_subquery;
+                use $crate::entities::media_files;
+
+                let mut select = <$related_entity::Entity>::find()
+                    .select_only()
+                    .column(<$related_entity::Column>::$relation_column_name)
+                    .column_as(<$related_entity::Column>::MediaFileId.min(), "media_file_id")
+                    .join(JoinType::InnerJoin, <$related_entity::Relation>::MediaFiles.def())
+                    .filter(<$related_entity::Column>::$relation_column_name.is_in(ids.to_vec()))
+                    .filter(media_files::Column::CoverArtId.is_not_null())
+                    .filter(media_files::Column::Id.not_in_subquery(hidden_files_subquery()));
+                if let Some(magic_cover_art_id) = get_magic_cover_art_id(main_db).await {
+                    select = select.filter(media_files::Column::CoverArtId.ne(magic_cover_art_id));
+                }
+
+                let cover_file_ids = select
+                    .group_by(<$related_entity::Column>::$relation_column_name)
+                    .into_tuple::<(i32, i32)>()
+                    .all(main_db)
+                    .await
+                    .with_context(|| "Failed to get the cover files of the collections")?;
+
+                Ok(Some(cover_file_ids.into_iter().collect()))
+            }
+
             async fn get_by_ids(main_db: &MainDbConnection, ids: &[i32]) -> Result<Vec<Self>> {
                 use anyhow::Context;
 
@@ -337,6 +390,12 @@ macro_rules! collection_query {
             fn readonly(&self) -> bool {
                 false
             }
+
+            $(
+                fn subtitle(&self) -> Option<String> {
+                    ($subtitle)(self)
+                }
+            )?
         }
     };
 }
@@ -7,20 +7,26 @@ use std::{
 
 use anyhow::{Context, Result};
 use chrono::Utc;
-use log::info;
+use log::{error, info};
 use once_cell::sync::Lazy;
 use sea_orm::{
     ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait,
-    QueryFilter,
+    QueryFilter, QueryOrder, QuerySelect, sea_query::Query,
 };
+use tokio::sync::broadcast::Receiver;
 use tokio_util::sync::CancellationToken;
 
 use ::fsio::FsIo;
-use ::metadata::cover_art::{CoverArt, extract_cover_art_binary, get_primary_color};
+use ::metadata::cover_art::{
+    CoverArt, extract_cover_art_binary, get_perceptual_hash, get_primary_color,
+    perceptual_hash_distance, resize_cover_art, resize_cover_art_to_sizes,
+};
 
 use crate::{
-    entities::{media_cover_art, media_files},
-    parallel_media_files_processing,
+    actions::checkpoint::COVER_ART_TASK,
+    checkpointed_media_files_processing,
+    entities::{media_cover_art, media_file_albums, media_files},
+    events::{LibraryEvent, next_events},
 };
 
 use super::utils::DatabaseExecutor;
@@ -53,6 +59,7 @@ pub async fn ensure_magic_cover_art(
             file_hash: ActiveValue::Set(String::new()),
             binary: ActiveValue::Set(Vec::new()),
             primary_color: ActiveValue::Set(Some(0)),
+            perceptual_hash: ActiveValue::Set(None),
             hlc_uuid: ActiveValue::Set(node_id.to_owned()),
             created_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
             updated_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
@@ -85,6 +92,17 @@ pub async fn ensure_magic_cover_art_id(main_db: &DatabaseConnection, node_id: &s
 pub static COVER_TEMP_DIR: Lazy<PathBuf> =
     Lazy::new(|| env::temp_dir().join("rune").join("cover_arts"));
 
+/// The edge lengths the album grid decodes cover arts at. Tiles ask for the
+/// power of two nearest to their size in physical pixels, so these cover
+/// the usual tile sizes at pixel ratios from 1 to 3.
+pub const ALBUM_GRID_THUMBNAIL_SIZES: [u32; 3] = [128, 256, 512];
+
+/// Where the thumbnail of a cover art is baked, next to the full sized
+/// cover art at `COVER_TEMP_DIR/{file_hash}`.
+pub fn cover_art_thumbnail_path(file_hash: &str, size: u32) -> PathBuf {
+    COVER_TEMP_DIR.join(format!("{file_hash}_{size}"))
+}
+
 fn bake_cover_art_by_cover_arts(
     fsio: &FsIo,
     cover_arts: Vec<media_cover_art::Model>,
@@ -156,6 +174,206 @@ pub async fn bake_cover_art_by_media_files(
     Ok(file_id_to_path)
 }
 
+/// Bake downscaled cover arts of the given files into the temporary
+/// directory, next to the full sized ones.
+///
+/// # Arguments
+/// * `fsio` - The file system abstraction.
+/// * `main_db` - The main database connection.
+/// * `files` - The files whose cover arts should be baked.
+/// * `size` - The maximum edge length of the thumbnails in pixels.
+///
+/// # Returns
+/// * A map from file id to thumbnail path, empty for files without cover art.
+pub async fn bake_cover_art_thumbnails_by_media_files(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    files: &[media_files::Model],
+    size: u32,
+) -> Result<HashMap<i32, String>> {
+    let cover_art_ids: Vec<i32> = files.iter().filter_map(|x| x.cover_art_id).collect();
+    let cover_arts: Vec<media_cover_art::Model> = media_cover_art::Entity::find()
+        .filter(media_cover_art::Column::Id.is_in(cover_art_ids))
+        .all(main_db)
+        .await?;
+
+    fsio.create_dir_all(&COVER_TEMP_DIR)?;
+
+    let mut cover_art_id_to_path: HashMap<i32, String> = HashMap::new();
+    for cover_art in cover_arts {
+        if cover_art.file_hash.is_empty() {
+            continue;
+        }
+
+        let path = cover_art_thumbnail_path(&cover_art.file_hash, size);
+        if !path.exists() {
+            let thumbnail = match resize_cover_art(&cover_art.binary, size) {
+                Ok(x) => x,
+                Err(e) => {
+                    info!("Unable to resize cover art {}: {e}", cover_art.id);
+                    cover_art.binary.clone()
+                }
+            };
+            fs::write(&path, thumbnail)?;
+        }
+
+        cover_art_id_to_path.insert(cover_art.id, path.to_string_lossy().to_string());
+    }
+
+    Ok(files
+        .iter()
+        .map(|file| {
+            let path = file
+                .cover_art_id
+                .and_then(|x| cover_art_id_to_path.get(&x).cloned())
+                .unwrap_or_default();
+            (file.id, path)
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThumbnailStatus {
+    /// Cover arts with every thumbnail baked.
+    pub ready: usize,
+    /// Cover arts shown for albums.
+    pub total: usize,
+}
+
+/// The ids and hashes of the cover arts of tracks that belong to an album,
+/// without the placeholder of files without one.
+async fn get_album_cover_arts(main_db: &DatabaseConnection) -> Result<Vec<(i32, String)>> {
+    let album_files = Query::select()
+        .column(media_file_albums::Column::MediaFileId)
+        .from(media_file_albums::Entity)
+        .to_owned();
+    let cover_art_ids: Vec<i32> = media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::CoverArtId)
+        .distinct()
+        .filter(media_files::Column::CoverArtId.is_not_null())
+        .filter(media_files::Column::Id.in_subquery(album_files))
+        .into_tuple::<Option<i32>>()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    Ok(media_cover_art::Entity::find()
+        .select_only()
+        .column(media_cover_art::Column::Id)
+        .column(media_cover_art::Column::FileHash)
+        .filter(media_cover_art::Column::Id.is_in(cover_art_ids))
+        .filter(media_cover_art::Column::FileHash.ne(String::new()))
+        .order_by_asc(media_cover_art::Column::Id)
+        .into_tuple()
+        .all(main_db)
+        .await?)
+}
+
+fn has_thumbnails(file_hash: &str, sizes: &[u32]) -> bool {
+    sizes
+        .iter()
+        .all(|size| cover_art_thumbnail_path(file_hash, *size).exists())
+}
+
+/// Count the album cover arts whose thumbnails of every size in `sizes`
+/// are baked.
+pub async fn get_album_thumbnail_status(
+    main_db: &DatabaseConnection,
+    sizes: &[u32],
+) -> Result<ThumbnailStatus> {
+    let cover_arts = get_album_cover_arts(main_db).await?;
+
+    Ok(ThumbnailStatus {
+        ready: cover_arts
+            .iter()
+            .filter(|(_, file_hash)| has_thumbnails(file_hash, sizes))
+            .count(),
+        total: cover_arts.len(),
+    })
+}
+
+/// Bake the thumbnails of every album cover art ahead of time, so the album
+/// grid doesn't decode the full sized cover arts on first scroll. Cover
+/// arts that already have every size are skipped, and cover arts that
+/// can't be decoded are copied as they are, like on demand.
+///
+/// # Arguments
+/// * `progress_callback` - Called with the processed and pending cover
+///   arts.
+///
+/// # Returns
+/// * The number of cover arts baked.
+pub async fn prerender_album_thumbnails<F>(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    sizes: &[u32],
+    batch_size: usize,
+    progress_callback: F,
+    cancel_token: Option<CancellationToken>,
+) -> Result<usize>
+where
+    F: Fn(usize, usize),
+{
+    let pending: Vec<i32> = get_album_cover_arts(main_db)
+        .await?
+        .into_iter()
+        .filter(|(_, file_hash)| !has_thumbnails(file_hash, sizes))
+        .map(|(id, _)| id)
+        .collect();
+    let total = pending.len();
+    if total == 0 {
+        return Ok(0);
+    }
+
+    info!("Baking thumbnails of {total} album cover arts");
+    fsio.create_dir_all(&COVER_TEMP_DIR)?;
+
+    let mut processed = 0;
+    for ids in pending.chunks(batch_size.max(1)) {
+        if cancel_token.as_ref().is_some_and(|x| x.is_cancelled()) {
+            info!("Baking thumbnails cancelled after {processed} cover arts");
+            break;
+        }
+
+        let cover_arts = media_cover_art::Entity::find()
+            .filter(media_cover_art::Column::Id.is_in(ids.to_vec()))
+            .all(main_db)
+            .await?;
+
+        let sizes = sizes.to_vec();
+        let count = cover_arts.len();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            for cover_art in cover_arts {
+                let thumbnails = match resize_cover_art_to_sizes(&cover_art.binary, &sizes) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        info!("Unable to resize cover art {}: {e}", cover_art.id);
+                        vec![cover_art.binary.clone(); sizes.len()]
+                    }
+                };
+
+                for (size, thumbnail) in sizes.iter().zip(thumbnails) {
+                    fs::write(
+                        cover_art_thumbnail_path(&cover_art.file_hash, *size),
+                        thumbnail,
+                    )?;
+                }
+            }
+
+            Ok(())
+        })
+        .await??;
+
+        processed += count;
+        progress_callback(processed, total);
+    }
+
+    Ok(processed)
+}
+
 pub async fn bake_cover_art_by_file_ids(
     fsio: &FsIo,
     main_db: &DatabaseConnection,
@@ -230,6 +448,9 @@ pub async fn insert_extract_result(
                 file_hash: ActiveValue::Set(cover_art.crc.clone()),
                 binary: ActiveValue::Set(cover_art.data.clone()),
                 primary_color: ActiveValue::Set(Some(cover_art.primary_color)),
+                perceptual_hash: ActiveValue::Set(
+                    get_perceptual_hash(&cover_art.data).ok().map(|x| x as i64),
+                ),
                 hlc_uuid: ActiveValue::Set(node_id.to_owned()),
                 created_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
                 updated_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
@@ -287,7 +508,8 @@ where
     let lib_path = Arc::new(lib_path.to_path_buf());
     let node_id = Arc::new(node_id.to_owned());
 
-    parallel_media_files_processing!(
+    checkpointed_media_files_processing!(
+        COVER_ART_TASK,
         main_db,
         batch_size,
         progress_callback,
@@ -315,6 +537,65 @@ where
     )
 }
 
+/// Extract the cover arts of the files updated by `LibraryChanged` events,
+/// until cancelled.
+pub async fn scan_cover_arts_on_library_changes(
+    fsio: Arc<FsIo>,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    mut receiver: Receiver<LibraryEvent>,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    let magic_cover_art_id = ensure_magic_cover_art_id(main_db, node_id).await?;
+
+    while let Some(events) = next_events(&mut receiver, &cancel_token).await {
+        let file_ids: Vec<i32> = events
+            .into_iter()
+            .flat_map(|x| match x {
+                LibraryEvent::LibraryChanged { updated, .. } => updated,
+                _ => Vec::new(),
+            })
+            .collect();
+        if file_ids.is_empty() {
+            continue;
+        }
+
+        let files = media_files::Entity::find()
+            .filter(media_files::Column::Id.is_in(file_ids))
+            .all(main_db)
+            .await?;
+
+        for file in files {
+            let fsio = Arc::clone(&fsio);
+            let lib_path = lib_path.to_path_buf();
+            let extract_file = file.clone();
+            let result = match tokio::task::spawn_blocking(move || {
+                extract_cover_art_by_file_id(&fsio, &lib_path, &extract_file)
+            })
+            .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("Failed to extract cover art for file ID: {}: {e}", file.id);
+                    continue;
+                }
+            };
+
+            if let Err(e) =
+                insert_extract_result(main_db, &file, magic_cover_art_id, result, node_id).await
+            {
+                error!(
+                    "Failed to process cover art for file ID: {}: {}",
+                    file.id, e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub async fn remove_cover_art_by_file_id<E>(main_db: &E, file_id: i32) -> Result<()>
 where
     E: DatabaseExecutor + sea_orm::ConnectionTrait,
@@ -411,3 +692,230 @@ pub async fn get_primary_color_by_cover_art_id(
         None => Err(anyhow::anyhow!("No primary color found")),
     }
 }
+
+/// Find the cover arts in the library that look the most like an image.
+///
+/// Only the stored hashes are compared. Cover arts scanned before perceptual
+/// hashes were introduced are hashed once, and the result is written back to
+/// the database.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `image_data` - The encoded image to look up, e.g. a screenshot of a cover.
+/// * `max_distance` - The maximum number of differing hash bits to accept.
+/// * `limit` - The maximum number of results.
+///
+/// # Returns
+/// * `Result<Vec<(i32, u32)>>` - Pairs of cover art ID and hash distance,
+///   closest first.
+pub async fn search_cover_arts_by_image(
+    main_db: &DatabaseConnection,
+    image_data: &[u8],
+    max_distance: u32,
+    limit: usize,
+) -> Result<Vec<(i32, u32)>> {
+    let target = get_perceptual_hash(image_data).context("Failed to decode the query image")?;
+
+    // Only the hashes are read, the image blobs are left in the database
+    let mut hashes: Vec<(i32, Option<i64>)> = media_cover_art::Entity::find()
+        .select_only()
+        .column(media_cover_art::Column::Id)
+        .column(media_cover_art::Column::PerceptualHash)
+        .filter(media_cover_art::Column::FileHash.ne(String::new()))
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    let missing: Vec<i32> = hashes
+        .iter()
+        .filter(|(_, hash)| hash.is_none())
+        .map(|(id, _)| *id)
+        .collect();
+    if !missing.is_empty() {
+        let backfilled = backfill_perceptual_hashes(main_db, &missing).await?;
+        for (id, hash) in hashes.iter_mut() {
+            if hash.is_none() {
+                *hash = backfilled.get(id).copied();
+            }
+        }
+    }
+
+    let mut result: Vec<(i32, u32)> = hashes
+        .into_iter()
+        .filter_map(|(id, hash)| {
+            hash.map(|hash| (id, perceptual_hash_distance(target, hash as u64)))
+        })
+        .filter(|(_, distance)| *distance <= max_distance)
+        .collect();
+
+    result.sort_by_key(|(id, distance)| (*distance, *id));
+    result.truncate(limit);
+
+    Ok(result)
+}
+
+/// How many cover arts are loaded at once while backfilling hashes.
+const BACKFILL_BATCH_SIZE: usize = 64;
+
+/// Hash the cover arts stored before perceptual hashes were introduced,
+/// a batch at a time, and write the hashes back.
+async fn backfill_perceptual_hashes(
+    main_db: &DatabaseConnection,
+    cover_art_ids: &[i32],
+) -> Result<HashMap<i32, i64>> {
+    let mut result = HashMap::new();
+
+    for ids in cover_art_ids.chunks(BACKFILL_BATCH_SIZE) {
+        let cover_arts = media_cover_art::Entity::find()
+            .filter(media_cover_art::Column::Id.is_in(ids.to_vec()))
+            .all(main_db)
+            .await?;
+
+        for cover_art in cover_arts {
+            let id = cover_art.id;
+            let Ok(hash) = get_perceptual_hash(&cover_art.binary) else {
+                continue;
+            };
+            let hash = hash as i64;
+
+            let mut cover_art_active: media_cover_art::ActiveModel = cover_art.into();
+            cover_art_active.perceptual_hash = ActiveValue::Set(Some(hash));
+            media_cover_art::Entity::update(cover_art_active)
+                .exec(main_db)
+                .await
+                .with_context(|| format!("Failed to store perceptual hash: {id}"))?;
+
+            result.insert(id, hash);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Group the albums of the library by the cover arts of their tracks.
+pub async fn get_album_ids_by_cover_art_ids(
+    main_db: &DatabaseConnection,
+    cover_art_ids: &[i32],
+) -> Result<HashMap<i32, Vec<i32>>> {
+    let files: HashMap<i32, i32> = media_files::Entity::find()
+        .filter(media_files::Column::CoverArtId.is_in(cover_art_ids.to_vec()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .filter_map(|x| x.cover_art_id.map(|cover_art_id| (x.id, cover_art_id)))
+        .collect();
+
+    let links = media_file_albums::Entity::find()
+        .filter(media_file_albums::Column::MediaFileId.is_in(files.keys().copied()))
+        .all(main_db)
+        .await?;
+
+    let mut result: HashMap<i32, Vec<i32>> = HashMap::new();
+    for link in links {
+        if let Some(cover_art_id) = files.get(&link.media_file_id) {
+            let album_ids = result.entry(*cover_art_id).or_default();
+            if !album_ids.contains(&link.album_id) {
+                album_ids.push(link.album_id);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+    use chrono::Utc;
+    use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait};
+    use uuid::Uuid;
+
+    use ::fsio::FsIo;
+
+    use crate::{
+        actions::cover_art::{
+            ThumbnailStatus, cover_art_thumbnail_path, ensure_magic_cover_art_id,
+            get_album_thumbnail_status, prerender_album_thumbnails,
+        },
+        entities::{media_cover_art, media_files},
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    const SIZES: [u32; 2] = [64, 128];
+
+    /// Insert a cover art that can't be decoded, under a unique hash since
+    /// thumbnails are baked into the shared temporary directory.
+    async fn seed_cover_art(db: &DatabaseConnection) -> Result<media_cover_art::Model> {
+        let now = Utc::now().to_rfc3339();
+        Ok(media_cover_art::ActiveModel {
+            id: ActiveValue::NotSet,
+            file_hash: ActiveValue::Set(format!("thumbnail-test-{}", Uuid::new_v4())),
+            binary: ActiveValue::Set(b"not really an image".to_vec()),
+            primary_color: ActiveValue::Set(None),
+            perceptual_hash: ActiveValue::Set(None),
+            hlc_uuid: ActiveValue::Set(Uuid::new_v4().to_string()),
+            created_at_hlc_ts: ActiveValue::Set(now.clone()),
+            created_at_hlc_ver: ActiveValue::Set(0),
+            created_at_hlc_nid: ActiveValue::Set("test".to_string()),
+            updated_at_hlc_ts: ActiveValue::Set(now),
+            updated_at_hlc_ver: ActiveValue::Set(0),
+            updated_at_hlc_nid: ActiveValue::Set("test".to_string()),
+        }
+        .insert(db)
+        .await?)
+    }
+
+    async fn set_cover_art(db: &DatabaseConnection, file_id: i32, cover_art_id: i32) -> Result<()> {
+        let mut file: media_files::ActiveModel = media_files::Entity::find_by_id(file_id)
+            .one(db)
+            .await?
+            .unwrap()
+            .into();
+        file.cover_art_id = ActiveValue::Set(Some(cover_art_id));
+        file.update(db).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prerender_album_thumbnails() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let fsio = FsIo::new();
+
+        // Two albums share a cover art, a file without one has the placeholder
+        let file_ids = seed_fake_tracks(&db, 12).await?;
+        let cover_art = seed_cover_art(&db).await?;
+        set_cover_art(&db, file_ids[0], cover_art.id).await?;
+        set_cover_art(&db, file_ids[11], cover_art.id).await?;
+        let magic_id = ensure_magic_cover_art_id(&db, "test").await?;
+        set_cover_art(&db, file_ids[1], magic_id).await?;
+
+        assert_eq!(
+            get_album_thumbnail_status(&db, &SIZES).await?,
+            ThumbnailStatus { ready: 0, total: 1 }
+        );
+
+        let baked = prerender_album_thumbnails(&fsio, &db, &SIZES, 10, |_, _| {}, None).await?;
+        assert_eq!(baked, 1);
+        assert_eq!(
+            get_album_thumbnail_status(&db, &SIZES).await?,
+            ThumbnailStatus { ready: 1, total: 1 }
+        );
+        // Nothing is left to bake
+        assert_eq!(
+            prerender_album_thumbnails(&fsio, &db, &SIZES, 10, |_, _| {}, None).await?,
+            0
+        );
+
+        // Cover arts that can't be decoded are copied as they are
+        for size in SIZES {
+            let path = cover_art_thumbnail_path(&cover_art.file_hash, size);
+            assert_eq!(fs::read(&path)?, cover_art.binary);
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,404 @@
+//! Daily mixes, generated again once a day.
+//!
+//! The analyzed tracks are grouped with k-means over their standardized
+//! analysis vectors. Tracks count more the more often they were played
+//! through, so the centres lean towards what is actually listened to. Every
+//! group becomes a mix of the tracks around its centre, stored as a locked
+//! mix of `lib::track` queries in the [`DAILY_MIX_GROUP`] group.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sea_orm::prelude::*;
+use sea_orm::{QueryOrder, QuerySelect};
+
+use crate::actions::analysis::{ANALYSIS_VECTOR_SIZE, AggregatedAnalysisResult};
+use crate::actions::mixes::{create_mix, replace_mix_queries};
+use crate::actions::settings::{DAILY_MIXES_DATE_KEY, get_setting, set_setting};
+use crate::actions::stats::{get_most_played, hidden_files_subquery};
+use crate::entities::{media_analysis, mix_queries, mixes};
+
+/// Group of the daily mixes, hidden from the user created groups.
+pub const DAILY_MIX_GROUP: &str = "\u{200B}Daily";
+
+/// How many daily mixes are generated when the library is large enough.
+pub const DAILY_MIX_MIN_COUNT: usize = 4;
+pub const DAILY_MIX_MAX_COUNT: usize = 6;
+
+/// How many tracks a daily mix holds at most.
+pub const DAILY_MIX_LENGTH: usize = 50;
+
+/// How many analyzed tracks every daily mix needs, smaller libraries get
+/// fewer mixes.
+const DAILY_MIX_MIN_TRACKS: usize = 10;
+
+const KMEANS_ITERATIONS: usize = 20;
+
+/// How many of the most played tracks weigh on the mixes.
+const HISTORY_TRACKS: u64 = 1000;
+
+/// A daily mix and its tracks, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyMix {
+    pub mix: mixes::Model,
+    pub file_ids: Vec<i32>,
+}
+
+/// Get the stored daily mixes, without generating them.
+pub async fn get_daily_mixes(main_db: &DatabaseConnection) -> Result<Vec<DailyMix>> {
+    let mixes = mixes::Entity::find()
+        .filter(mixes::Column::Group.eq(DAILY_MIX_GROUP))
+        .order_by_asc(mixes::Column::Id)
+        .all(main_db)
+        .await?;
+
+    let mut result = Vec::with_capacity(mixes.len());
+    for mix in mixes {
+        let file_ids = mix_queries::Entity::find()
+            .filter(mix_queries::Column::MixId.eq(mix.id))
+            .filter(mix_queries::Column::Operator.eq("lib::track"))
+            .order_by_asc(mix_queries::Column::Id)
+            .all(main_db)
+            .await?
+            .into_iter()
+            .filter_map(|x| x.parameter.parse().ok())
+            .collect();
+
+        result.push(DailyMix { mix, file_ids });
+    }
+
+    Ok(result)
+}
+
+/// Get the daily mixes of today, generating them if they were generated on
+/// another day or if `force` is set.
+///
+/// # Returns
+/// * `Result<Vec<DailyMix>>` - The daily mixes, the most listened to first.
+///   Empty if too few tracks are analyzed.
+pub async fn generate_daily_mixes(
+    main_db: &DatabaseConnection,
+    node_id: &str,
+    force: bool,
+) -> Result<Vec<DailyMix>> {
+    let today = Local::now().date_naive();
+    let generated_on = get_setting(main_db, DAILY_MIXES_DATE_KEY).await?;
+    if !force && generated_on.as_deref() == Some(today.to_string().as_str()) {
+        return get_daily_mixes(main_db).await;
+    }
+
+    let (file_ids, vectors) = get_analysis_vectors(main_db).await?;
+
+    let play_counts: HashMap<i32, i64> = get_most_played(main_db, HISTORY_TRACKS)
+        .await?
+        .into_iter()
+        .map(|x| (x.media_file_id, x.play_count))
+        .collect();
+    let weights: Vec<f32> = file_ids
+        .iter()
+        .map(|id| 1.0 + (play_counts.get(id).copied().unwrap_or(0) as f32).ln_1p())
+        .collect();
+
+    // The mixes change every day, but not when generated again the same day
+    let mut rng = StdRng::seed_from_u64(today.num_days_from_ce() as u64);
+    let clusters = cluster_tracks(&vectors, &weights, &mut rng);
+
+    let old_mix_ids: Vec<i32> = mixes::Entity::find()
+        .select_only()
+        .column(mixes::Column::Id)
+        .filter(mixes::Column::Group.eq(DAILY_MIX_GROUP))
+        .into_tuple()
+        .all(main_db)
+        .await?;
+    mix_queries::Entity::delete_many()
+        .filter(mix_queries::Column::MixId.is_in(old_mix_ids.clone()))
+        .exec(main_db)
+        .await?;
+    mixes::Entity::delete_many()
+        .filter(mixes::Column::Id.is_in(old_mix_ids))
+        .exec(main_db)
+        .await?;
+
+    let mut result = Vec::with_capacity(clusters.len());
+    for (index, members) in clusters.into_iter().enumerate() {
+        let mix = create_mix(
+            main_db,
+            node_id,
+            format!("\u{200B}Daily Mix {}", index + 1),
+            DAILY_MIX_GROUP.to_string(),
+            false,
+            99,
+            true,
+            None,
+        )
+        .await
+        .with_context(|| "Failed to create daily mix")?;
+
+        let mix_file_ids: Vec<i32> = members.into_iter().map(|i| file_ids[i]).collect();
+        replace_mix_queries(
+            main_db,
+            node_id,
+            mix.id,
+            mix_file_ids
+                .iter()
+                .map(|id| ("lib::track".to_string(), id.to_string()))
+                .collect(),
+            None,
+        )
+        .await
+        .with_context(|| "Failed to store daily mix tracks")?;
+
+        result.push(DailyMix {
+            mix,
+            file_ids: mix_file_ids,
+        });
+    }
+
+    set_setting(main_db, DAILY_MIXES_DATE_KEY, Some(&today.to_string())).await?;
+
+    Ok(result)
+}
+
+/// The analysis vectors of the visible analyzed files, standardized so every
+/// value weighs the same in the distances.
+async fn get_analysis_vectors(main_db: &DatabaseConnection) -> Result<(Vec<i32>, Vec<Vec<f32>>)> {
+    let analyses = media_analysis::Entity::find()
+        .filter(media_analysis::Column::FileId.not_in_subquery(hidden_files_subquery()))
+        .order_by_asc(media_analysis::Column::FileId)
+        .all(main_db)
+        .await?;
+
+    let file_ids: Vec<i32> = analyses.iter().map(|x| x.file_id).collect();
+    let mut vectors: Vec<Vec<f32>> = analyses
+        .into_iter()
+        .map(|x| {
+            let vector: [f32; ANALYSIS_VECTOR_SIZE] = AggregatedAnalysisResult::from(x).into();
+            vector.to_vec()
+        })
+        .collect();
+
+    if vectors.is_empty() {
+        return Ok((file_ids, vectors));
+    }
+
+    for dim in 0..vectors[0].len() {
+        let n = vectors.len() as f32;
+        let mean = vectors.iter().map(|x| x[dim]).sum::<f32>() / n;
+        let variance = vectors.iter().map(|x| (x[dim] - mean).powi(2)).sum::<f32>() / n;
+        let std = if variance > f32::EPSILON {
+            variance.sqrt()
+        } else {
+            1.0
+        };
+
+        for vector in vectors.iter_mut() {
+            vector[dim] = (vector[dim] - mean) / std;
+        }
+    }
+
+    Ok((file_ids, vectors))
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Pick an index with a probability proportional to its score.
+fn pick_weighted(scores: &[f32], rng: &mut StdRng) -> usize {
+    let total: f32 = scores.iter().sum();
+    if total <= 0.0 {
+        return rng.gen_range(0..scores.len());
+    }
+
+    let mut target = rng.gen_range(0.0..total);
+    for (i, score) in scores.iter().enumerate() {
+        if target < *score {
+            return i;
+        }
+        target -= score;
+    }
+
+    scores.len() - 1
+}
+
+/// Group the tracks with weighted k-means and pick the tracks of every
+/// mix.
+///
+/// # Returns
+/// The indices of the tracks of every mix, the mixes with the most plays
+/// first.
+fn cluster_tracks(vectors: &[Vec<f32>], weights: &[f32], rng: &mut StdRng) -> Vec<Vec<usize>> {
+    let k = (vectors.len() / DAILY_MIX_MIN_TRACKS).min(DAILY_MIX_MAX_COUNT);
+    if k == 0 {
+        return Vec::new();
+    }
+
+    // k-means++, favouring the played tracks as centres
+    let mut centres = vec![vectors[pick_weighted(weights, rng)].clone()];
+    while centres.len() < k {
+        let scores: Vec<f32> = vectors
+            .iter()
+            .zip(weights)
+            .map(|(x, w)| {
+                let nearest = centres
+                    .iter()
+                    .map(|c| squared_distance(x, c))
+                    .fold(f32::MAX, f32::min);
+                nearest * w
+            })
+            .collect();
+        centres.push(vectors[pick_weighted(&scores, rng)].clone());
+    }
+
+    let mut assignments = vec![0; vectors.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut changed = false;
+        for (i, vector) in vectors.iter().enumerate() {
+            let nearest = (0..k)
+                .min_by(|a, b| {
+                    squared_distance(vector, &centres[*a])
+                        .total_cmp(&squared_distance(vector, &centres[*b]))
+                })
+                .unwrap_or(0);
+            if assignments[i] != nearest {
+                assignments[i] = nearest;
+                changed = true;
+            }
+        }
+
+        for (c, centre) in centres.iter_mut().enumerate() {
+            let mut sum = vec![0.0; centre.len()];
+            let mut total = 0.0;
+            for (i, vector) in vectors.iter().enumerate() {
+                if assignments[i] != c {
+                    continue;
+                }
+                for (s, x) in sum.iter_mut().zip(vector) {
+                    *s += x * weights[i];
+                }
+                total += weights[i];
+            }
+            if total > 0.0 {
+                *centre = sum.into_iter().map(|x| x / total).collect();
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    // Rank the groups by how much they were played, small groups and groups
+    // nobody played only fill up the minimum count
+    let mut clusters: Vec<(f32, Vec<usize>)> = (0..k)
+        .map(|c| {
+            let members: Vec<usize> = (0..vectors.len())
+                .filter(|i| assignments[*i] == c)
+                .collect();
+            let plays = members.iter().map(|i| weights[*i] - 1.0).sum();
+            (plays, members)
+        })
+        .filter(|(_, members)| !members.is_empty())
+        .collect();
+    clusters.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.len().cmp(&a.1.len())));
+
+    let mut result = Vec::with_capacity(clusters.len());
+    for (plays, mut members) in clusters {
+        if result.len() >= DAILY_MIX_MIN_COUNT
+            && (plays <= 0.0 || members.len() < DAILY_MIX_MIN_TRACKS)
+        {
+            continue;
+        }
+
+        // The tracks around the centre first, played tracks pulled closer,
+        // with some noise to change the mix from day to day
+        let centre = &centres[assignments[members[0]]];
+        let scores: HashMap<usize, f32> = members
+            .iter()
+            .map(|i| {
+                let distance = squared_distance(&vectors[*i], centre).sqrt();
+                (*i, distance / weights[*i] * rng.gen_range(0.75..1.25))
+            })
+            .collect();
+        members.sort_by(|a, b| scores[a].total_cmp(&scores[b]));
+        members.truncate(DAILY_MIX_LENGTH);
+
+        result.push(members);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    use crate::{
+        actions::{
+            daily_mix::{
+                DAILY_MIX_GROUP, DAILY_MIX_LENGTH, DAILY_MIX_MAX_COUNT, DAILY_MIX_MIN_COUNT,
+                generate_daily_mixes, get_daily_mixes,
+            },
+            stats::{record_playback_complete, record_playback_start},
+        },
+        entities::mixes,
+        test_support::{TEST_NODE_ID, connect_test_main_db, seed_fake_library, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_daily_mixes_need_analyzed_tracks() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        seed_fake_tracks(&db, 30).await?;
+
+        let mixes = generate_daily_mixes(&db, TEST_NODE_ID, false).await?;
+        assert!(mixes.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_daily_mixes_are_generated_once_a_day() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_library(&db, 60).await?;
+
+        for _ in 0..5 {
+            record_playback_start(&db, file_ids[0]).await?;
+            record_playback_complete(&db, file_ids[0]).await?;
+        }
+
+        let daily = generate_daily_mixes(&db, TEST_NODE_ID, false).await?;
+        assert!((DAILY_MIX_MIN_COUNT..=DAILY_MIX_MAX_COUNT).contains(&daily.len()));
+        for mix in &daily {
+            assert_eq!(mix.mix.group, DAILY_MIX_GROUP);
+            assert!(mix.mix.locked);
+            assert!(!mix.file_ids.is_empty() && mix.file_ids.len() <= DAILY_MIX_LENGTH);
+            assert!(mix.file_ids.iter().all(|id| file_ids.contains(id)));
+        }
+
+        // The mix around the played track comes first
+        assert!(daily[0].file_ids.contains(&file_ids[0]));
+
+        // The same day the stored mixes are returned
+        assert_eq!(generate_daily_mixes(&db, TEST_NODE_ID, false).await?, daily);
+        assert_eq!(get_daily_mixes(&db).await?, daily);
+
+        // Forcing replaces them
+        let regenerated = generate_daily_mixes(&db, TEST_NODE_ID, true).await?;
+        let stored = mixes::Entity::find()
+            .filter(mixes::Column::Group.eq(DAILY_MIX_GROUP))
+            .all(&db)
+            .await?;
+        assert_eq!(stored.len(), regenerated.len());
+        assert!(
+            stored
+                .iter()
+                .all(|x| daily.iter().all(|y| y.mix.id != x.id))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+//! Tracks stored more than once in the library, found by their content, by
+//! their tags, or by how they sound.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::Result;
+use sea_orm::prelude::*;
+use sea_orm::{QueryOrder, QuerySelect, sea_query::Expr};
+
+use crate::actions::fingerprint::group_similar_files;
+use crate::actions::metadata::get_metadata_summary_by_files;
+use crate::entities::{media_file_fingerprint, media_file_similarity, media_files};
+
+/// Seconds the durations of two tracks with the same artist and title may
+/// differ by to still count as the same recording.
+pub const DURATION_TOLERANCE: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateMatch {
+    Hash,
+    Metadata,
+    Fingerprint,
+}
+
+impl DuplicateMatch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicateMatch::Hash => "hash",
+            DuplicateMatch::Metadata => "metadata",
+            DuplicateMatch::Fingerprint => "fingerprint",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub matched_by: DuplicateMatch,
+    /// Sorted in ascending order.
+    pub file_ids: Vec<i32>,
+}
+
+impl DuplicateGroup {
+    fn new(matched_by: DuplicateMatch, mut file_ids: Vec<i32>) -> Self {
+        file_ids.sort();
+        Self {
+            matched_by,
+            file_ids,
+        }
+    }
+}
+
+/// Files with exactly the same content.
+pub async fn find_hash_duplicates(main_db: &DatabaseConnection) -> Result<Vec<DuplicateGroup>> {
+    let files: Vec<(i32, String)> = media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::Id)
+        .column(media_files::Column::FileHash)
+        .order_by_asc(media_files::Column::Id)
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    let mut groups: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+    for (id, file_hash) in files {
+        if !file_hash.is_empty() {
+            groups.entry(file_hash).or_default().push(id);
+        }
+    }
+
+    Ok(groups
+        .into_values()
+        .filter(|x| x.len() > 1)
+        .map(|x| DuplicateGroup::new(DuplicateMatch::Hash, x))
+        .collect())
+}
+
+/// Files tagged with the same artist and title, ignoring case, whose
+/// durations are within `tolerance` seconds of each other.
+pub async fn find_metadata_duplicates(
+    main_db: &DatabaseConnection,
+    tolerance: f64,
+) -> Result<Vec<DuplicateGroup>> {
+    let files = media_files::Entity::find().all(main_db).await?;
+    let summaries = get_metadata_summary_by_files(main_db, files).await?;
+
+    let mut tracks: BTreeMap<(String, String), Vec<(f64, i32)>> = BTreeMap::new();
+    for summary in summaries {
+        let artist = summary.artist.trim().to_lowercase();
+        let title = summary.title.trim().to_lowercase();
+        if artist.is_empty() || title.is_empty() {
+            continue;
+        }
+
+        tracks
+            .entry((artist, title))
+            .or_default()
+            .push((summary.duration, summary.id));
+    }
+
+    let mut groups = Vec::new();
+    for mut recordings in tracks.into_values() {
+        recordings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // Split wherever two consecutive durations are too far apart, a live
+        // take and a radio edit share their tags but not their length
+        let mut group: Vec<i32> = Vec::new();
+        let mut last_duration = f64::NEG_INFINITY;
+        for (duration, id) in recordings {
+            if duration - last_duration > tolerance {
+                if group.len() > 1 {
+                    groups.push(DuplicateGroup::new(DuplicateMatch::Metadata, group));
+                }
+                group = Vec::new();
+            }
+
+            group.push(id);
+            last_duration = duration;
+        }
+
+        if group.len() > 1 {
+            groups.push(DuplicateGroup::new(DuplicateMatch::Metadata, group));
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Files whose fingerprints were compared as at least `threshold` similar,
+/// see `compare_all_pairs`.
+pub async fn find_fingerprint_duplicates(
+    main_db: &DatabaseConnection,
+    threshold: f32,
+) -> Result<Vec<DuplicateGroup>> {
+    let similarities = media_file_similarity::Entity::find()
+        .filter(media_file_similarity::Column::Similarity.gte(threshold))
+        .all(main_db)
+        .await?;
+
+    let mut groups: Vec<DuplicateGroup> = group_similar_files(&similarities)
+        .into_iter()
+        .map(|x| DuplicateGroup::new(DuplicateMatch::Fingerprint, x))
+        .collect();
+    groups.sort_by(|a, b| a.file_ids.cmp(&b.file_ids));
+
+    Ok(groups)
+}
+
+/// Drop the groups holding the same files as an earlier group, so files
+/// found by several methods are only reported once.
+pub fn merge_duplicate_groups(groups: Vec<DuplicateGroup>) -> Vec<DuplicateGroup> {
+    let mut seen: HashSet<Vec<i32>> = HashSet::new();
+
+    groups
+        .into_iter()
+        .filter(|x| seen.insert(x.file_ids.clone()))
+        .collect()
+}
+
+/// Flag files as duplicated, the same way `mark_duplicate_files` does. Only
+/// fingerprinted files can be flagged, returns the number flagged.
+pub async fn mark_files_as_duplicated(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<u64> {
+    let result = media_file_fingerprint::Entity::update_many()
+        .col_expr(media_file_fingerprint::Column::IsDuplicated, Expr::value(1))
+        .filter(media_file_fingerprint::Column::MediaFileId.is_in(file_ids.to_vec()))
+        .exec(main_db)
+        .await?;
+
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::{ActiveValue, EntityTrait};
+
+    use crate::{
+        actions::duplicates::{
+            DURATION_TOLERANCE, DuplicateGroup, DuplicateMatch, find_fingerprint_duplicates,
+            find_hash_duplicates, find_metadata_duplicates, mark_files_as_duplicated,
+            merge_duplicate_groups,
+        },
+        entities::{media_file_fingerprint, media_file_similarity, media_files},
+        test_support::{FakeTrack, connect_test_main_db, seed_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_find_duplicates() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        let original = FakeTrack::nth(0);
+        let copy = FakeTrack {
+            file_name: "copy.flac".to_string(),
+            duration: original.duration + 0.5,
+            ..original.clone()
+        };
+        // Same tags, but a different recording
+        let live = FakeTrack {
+            file_name: "live.flac".to_string(),
+            duration: original.duration + 60.0,
+            ..original.clone()
+        };
+        let file_ids = seed_tracks(
+            &db,
+            &[original, copy, live, FakeTrack::nth(5), FakeTrack::nth(6)],
+        )
+        .await?;
+
+        for file_id in [file_ids[3], file_ids[4]] {
+            media_files::Entity::update(media_files::ActiveModel {
+                id: ActiveValue::Unchanged(file_id),
+                file_hash: ActiveValue::Set("same content".to_string()),
+                ..Default::default()
+            })
+            .exec(&db)
+            .await?;
+        }
+
+        let by_hash = find_hash_duplicates(&db).await?;
+        assert_eq!(by_hash.len(), 1);
+        assert_eq!(by_hash[0].matched_by, DuplicateMatch::Hash);
+        assert_eq!(by_hash[0].file_ids, vec![file_ids[3], file_ids[4]]);
+
+        let by_metadata = find_metadata_duplicates(&db, DURATION_TOLERANCE).await?;
+        assert_eq!(by_metadata.len(), 1);
+        assert_eq!(by_metadata[0].file_ids, vec![file_ids[0], file_ids[1]]);
+
+        media_file_similarity::Entity::insert(media_file_similarity::ActiveModel {
+            file_id1: ActiveValue::Set(file_ids[1]),
+            file_id2: ActiveValue::Set(file_ids[0]),
+            similarity: ActiveValue::Set(0.95),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await?;
+
+        let by_fingerprint = find_fingerprint_duplicates(&db, 0.85).await?;
+        assert_eq!(by_fingerprint[0].file_ids, vec![file_ids[0], file_ids[1]]);
+        assert!(find_fingerprint_duplicates(&db, 0.99).await?.is_empty());
+
+        // Files found by several methods are reported once
+        let groups = merge_duplicate_groups([by_hash, by_metadata, by_fingerprint].concat());
+        assert_eq!(
+            groups,
+            vec![
+                DuplicateGroup {
+                    matched_by: DuplicateMatch::Hash,
+                    file_ids: vec![file_ids[3], file_ids[4]],
+                },
+                DuplicateGroup {
+                    matched_by: DuplicateMatch::Metadata,
+                    file_ids: vec![file_ids[0], file_ids[1]],
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mark_files_as_duplicated() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_tracks(&db, &[FakeTrack::nth(0), FakeTrack::nth(1)]).await?;
+
+        media_file_fingerprint::Entity::insert(media_file_fingerprint::ActiveModel {
+            media_file_id: ActiveValue::Set(file_ids[0]),
+            fingerprint: ActiveValue::Set(vec![0; 16]),
+            is_duplicated: ActiveValue::Set(0),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await?;
+
+        // Only fingerprinted files can be flagged
+        assert_eq!(mark_files_as_duplicated(&db, &file_ids).await?, 1);
+
+        let fingerprint = media_file_fingerprint::Entity::find()
+            .one(&db)
+            .await?
+            .unwrap();
+        assert_eq!(fingerprint.is_duplicated, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+//! Tags missing from the files, filled in from MusicBrainz. Only missing
+//! values are added, what the files are tagged with always wins. Values
+//! added here are replaced once the file changes and its tags are read
+//! again.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use log::{info, warn};
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, QueryOrder, QuerySelect};
+use tokio_util::sync::CancellationToken;
+
+pub use tag_editor::music_brainz::recording::{MbRecording, MusicBrainzClient};
+use tag_editor::music_brainz::{api::identify, recording::credited_name};
+
+use crate::actions::fingerprint::{Configuration, get_fingerprint_by_file_id};
+use crate::actions::metadata::get_metadata_summary_by_files;
+use crate::actions::summaries::refresh_media_summaries;
+use crate::entities::{media_files, media_metadata};
+
+/// Files with this key were matched already and aren't looked up again.
+pub const RECORDING_ID_KEY: &str = "musicbrainz_recording_id";
+
+/// Seconds the length of a recording found by searching may differ from the
+/// file to still be accepted.
+const LENGTH_TOLERANCE: f64 = 10.0;
+
+/// Candidates taken from a search by artist and title.
+const SEARCH_LIMIT: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct EnrichOptions {
+    /// Identify fingerprinted files through AcoustID first.
+    pub acoustid_key: Option<String>,
+    /// Score a match needs, between 0 and 1.
+    pub min_score: f64,
+    /// Files to look up at most in this run.
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnrichReport {
+    pub looked_up: usize,
+    pub matched: usize,
+    /// Tags added over all files.
+    pub filled: usize,
+}
+
+/// The tags of a file as known from MusicBrainz. The release named `album`
+/// is preferred when the recording appears on several.
+pub fn enrichment_from_recording(
+    recording: &MbRecording,
+    album: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let mut values = vec![(RECORDING_ID_KEY, recording.id.clone())];
+
+    if let Some(credit) = recording.artist_credit.first() {
+        values.push(("musicbrainz_artist_id", credit.artist.id.clone()));
+    }
+
+    let release = album
+        .and_then(|album| {
+            recording
+                .releases
+                .iter()
+                .find(|x| x.title.eq_ignore_ascii_case(album))
+        })
+        .or(recording.releases.first());
+
+    if let Some(release) = release {
+        values.push(("musicbrainz_album_id", release.id.clone()));
+
+        let album_artist = credited_name(&release.artist_credit);
+        if !album_artist.is_empty() {
+            values.push(("album_artist", album_artist));
+        }
+
+        if let Some(date) = release.date.as_ref().filter(|x| !x.is_empty()) {
+            values.push(("date", date.clone()));
+        }
+
+        let track = release.media.iter().find_map(|x| x.track.first());
+        let track_number = track.and_then(|x| {
+            x.number
+                .clone()
+                .or_else(|| x.position.map(|x| x.to_string()))
+        });
+        if let Some(track_number) = track_number {
+            values.push(("track_number", track_number));
+        }
+    }
+
+    values
+}
+
+/// Add the `values` a file doesn't have a tag for yet. Returns the number
+/// of tags added.
+pub async fn apply_enrichment(
+    main_db: &DatabaseConnection,
+    file_id: i32,
+    values: &[(&str, String)],
+) -> Result<usize> {
+    let existing: HashSet<String> = media_metadata::Entity::find()
+        .select_only()
+        .column(media_metadata::Column::MetaKey)
+        .filter(media_metadata::Column::FileId.eq(file_id))
+        .filter(media_metadata::Column::MetaValue.ne(""))
+        .into_tuple()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let new_metadata: Vec<media_metadata::ActiveModel> = values
+        .iter()
+        .filter(|(key, _)| !existing.contains(*key))
+        .map(|(key, value)| media_metadata::ActiveModel {
+            file_id: ActiveValue::Set(file_id),
+            meta_key: ActiveValue::Set(key.to_string()),
+            meta_value: ActiveValue::Set(value.clone()),
+            ..Default::default()
+        })
+        .collect();
+
+    let filled = new_metadata.len();
+    if filled > 0 {
+        media_metadata::Entity::insert_many(new_metadata)
+            .exec(main_db)
+            .await?;
+        refresh_media_summaries(main_db, &[file_id]).await?;
+    }
+
+    Ok(filled)
+}
+
+/// Files that haven't been matched with a recording yet.
+pub async fn get_files_to_enrich(
+    main_db: &DatabaseConnection,
+    limit: Option<u64>,
+) -> Result<Vec<media_files::Model>> {
+    let matched_ids: Vec<i32> = media_metadata::Entity::find()
+        .select_only()
+        .column(media_metadata::Column::FileId)
+        .filter(media_metadata::Column::MetaKey.eq(RECORDING_ID_KEY))
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    Ok(media_files::Entity::find()
+        .filter(media_files::Column::Id.is_not_in(matched_ids))
+        .order_by_asc(media_files::Column::Id)
+        .limit(limit)
+        .all(main_db)
+        .await?)
+}
+
+async fn identify_by_fingerprint(
+    main_db: &DatabaseConnection,
+    client: &MusicBrainzClient,
+    acoustid_key: &str,
+    file: &media_files::Model,
+    min_score: f64,
+) -> Result<Option<MbRecording>> {
+    let Some(fingerprint) = get_fingerprint_by_file_id(main_db, file.id).await? else {
+        return Ok(None);
+    };
+
+    let duration = file.duration.to_u32().unwrap_or(0);
+    let results = identify(
+        acoustid_key,
+        fingerprint,
+        &Configuration::default(),
+        duration,
+    )
+    .await?;
+
+    let recording_id = results
+        .into_iter()
+        .filter(|x| x.score >= min_score)
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .and_then(|x| x.recordings)
+        .and_then(|x| x.into_iter().next())
+        .map(|x| x.id);
+
+    match recording_id {
+        Some(id) => client.lookup_recording(&id).await,
+        None => Ok(None),
+    }
+}
+
+async fn search_by_tags(
+    client: &MusicBrainzClient,
+    artist: &str,
+    title: &str,
+    duration: f64,
+    min_score: f64,
+) -> Result<Option<MbRecording>> {
+    if artist.is_empty() || title.is_empty() {
+        return Ok(None);
+    }
+
+    let recordings = client
+        .search_recordings(artist, title, SEARCH_LIMIT)
+        .await?;
+
+    Ok(recordings.into_iter().find(|x| {
+        let score = x.score.unwrap_or(0) as f64 / 100.0;
+        let length_matches = x
+            .length
+            .is_none_or(|length| (length as f64 / 1000.0 - duration).abs() <= LENGTH_TOLERANCE);
+
+        score >= min_score && length_matches
+    }))
+}
+
+/// Look up the files that haven't been matched yet, by fingerprint if an
+/// AcoustID key is given and by artist and title otherwise, and add the
+/// tags they are missing.
+pub async fn enrich_library<F>(
+    main_db: &DatabaseConnection,
+    client: &MusicBrainzClient,
+    options: &EnrichOptions,
+    progress_callback: F,
+    cancel_token: Option<CancellationToken>,
+) -> Result<EnrichReport>
+where
+    F: Fn(usize, usize),
+{
+    let files = get_files_to_enrich(main_db, options.limit).await?;
+    let total = files.len();
+    info!("Looking up {total} files on MusicBrainz");
+
+    let summaries = get_metadata_summary_by_files(main_db, files.clone()).await?;
+    let mut report = EnrichReport::default();
+
+    for (file, summary) in files.iter().zip(summaries) {
+        if cancel_token.as_ref().is_some_and(|x| x.is_cancelled()) {
+            info!("Enrichment cancelled.");
+            break;
+        }
+
+        let mut recording = None;
+        if let Some(acoustid_key) = &options.acoustid_key {
+            match identify_by_fingerprint(main_db, client, acoustid_key, file, options.min_score)
+                .await
+            {
+                Ok(x) => recording = x,
+                Err(e) => warn!("Failed to identify file {}: {e:#}", file.id),
+            }
+        }
+        if recording.is_none() {
+            match search_by_tags(
+                client,
+                &summary.artist,
+                &summary.title,
+                summary.duration,
+                options.min_score,
+            )
+            .await
+            {
+                Ok(x) => recording = x,
+                Err(e) => warn!("Failed to search for file {}: {e:#}", file.id),
+            }
+        }
+
+        report.looked_up += 1;
+        if let Some(recording) = recording {
+            let album = Some(summary.album.as_str()).filter(|x| !x.is_empty());
+            let values = enrichment_from_recording(&recording, album);
+            report.filled += apply_enrichment(main_db, file.id, &values).await?;
+            report.matched += 1;
+        }
+
+        progress_callback(report.looked_up, total);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use crate::{
+        actions::enrich::{
+            MbRecording, RECORDING_ID_KEY, apply_enrichment, enrichment_from_recording,
+            get_files_to_enrich,
+        },
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    fn fake_recording() -> Result<MbRecording> {
+        let credit =
+            |id: &str, name: &str| json!({ "name": name, "artist": { "id": id, "name": name } });
+
+        Ok(serde_json::from_value(json!({
+            "id": "recording-1",
+            "title": "Track 0",
+            "score": 100,
+            "length": 120000,
+            "artist-credit": [credit("artist-1", "Artist")],
+            "releases": [
+                {
+                    "id": "release-compilation",
+                    "title": "Greatest Hits",
+                    "date": "2010",
+                    "artist-credit": [credit("various", "Various Artists")],
+                    "media": [{ "position": 1, "track-count": 20, "track": [{ "id": "t1", "number": "17" }] }]
+                },
+                {
+                    "id": "release-album",
+                    "title": "Album 0",
+                    "date": "2001-05-04",
+                    "artist-credit": [credit("artist-1", "Artist")],
+                    "media": [{ "position": 1, "track-count": 10, "track": [{ "id": "t2", "number": "3" }] }]
+                }
+            ]
+        }))?)
+    }
+
+    #[test]
+    fn test_enrichment_prefers_the_tagged_album() -> Result<()> {
+        let recording = fake_recording()?;
+
+        let values = enrichment_from_recording(&recording, Some("album 0"));
+        assert!(values.contains(&(RECORDING_ID_KEY, "recording-1".to_string())));
+        assert!(values.contains(&("musicbrainz_album_id", "release-album".to_string())));
+        assert!(values.contains(&("album_artist", "Artist".to_string())));
+        assert!(values.contains(&("date", "2001-05-04".to_string())));
+        assert!(values.contains(&("track_number", "3".to_string())));
+
+        // Without a tagged album the first release is used
+        let values = enrichment_from_recording(&recording, None);
+        assert!(values.contains(&("musicbrainz_album_id", "release-compilation".to_string())));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_apply_enrichment_only_fills_missing_tags() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        let values = enrichment_from_recording(&fake_recording()?, Some("Album 0"));
+        // The track number of the file is kept
+        assert_eq!(
+            apply_enrichment(&db, file_ids[0], &values).await?,
+            values.len() - 1
+        );
+        assert_eq!(apply_enrichment(&db, file_ids[0], &values).await?, 0);
+
+        let files = get_files_to_enrich(&db, None).await?;
+        assert_eq!(
+            files.iter().map(|x| x.id).collect::<Vec<_>>(),
+            vec![file_ids[1]]
+        );
+
+        Ok(())
+    }
+}
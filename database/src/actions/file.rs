@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::path::Path;
+use std::time::Duration;
 
 use anyhow::Result;
 use metadata::describe::FileDescription;
@@ -10,9 +11,16 @@ use sea_orm::{
     QueryTrait,
 };
 
-use migration::{Func, SimpleExpr};
+use chrono::{DateTime, Utc};
+use migration::{Expr, Func, Query, SimpleExpr};
+use rand::Rng;
 
-use crate::entities::media_files;
+use crate::actions::recommendation::get_recommendation_by_file_id;
+use crate::actions::stats::hidden_files_subquery;
+use crate::connection::RecommendationDbConnection;
+use crate::entities::{
+    media_file_artists, media_file_genres, media_file_stats, media_files, play_history,
+};
 use crate::{get_by_id, get_by_ids, get_first_n};
 
 get_by_ids!(get_files_by_ids, media_files);
@@ -38,22 +46,283 @@ pub async fn get_ordered_files_by_ids(
     Ok(ordered_files)
 }
 
+const WEIGHTED_POOL_FACTOR: usize = 4;
+
+/// Optional constraints applied when drawing random files from the library.
+///
+/// Every constraint is combined with `AND`; the default value matches the
+/// whole library.
+#[derive(Debug, Clone, Default)]
+pub struct RandomFileFilter {
+    /// Only draw tracks of these artists.
+    pub artist_ids: Vec<i32>,
+    /// Only draw tracks of these genres.
+    pub genre_ids: Vec<i32>,
+    /// Only draw tracks the user has liked.
+    pub liked_only: bool,
+    /// Only draw tracks rated at least this, unrated tracks never match.
+    pub min_rating: Option<i32>,
+    /// Only draw tracks among these files, usually the neighbourhood of a
+    /// seed track in the recommendation index, see [`get_analysis_cluster`].
+    pub cluster: Option<Vec<i32>>,
+    /// Skip tracks that were started within this period, whether they were
+    /// played through or skipped.
+    pub not_played_within: Option<Duration>,
+    /// How likely each matching track is to be drawn.
+    pub weighting: RandomWeighting,
+}
+
+/// The probability model used by [`get_random_files`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RandomWeighting {
+    /// Every track is equally likely.
+    #[default]
+    Uniform,
+    /// Favour liked and highly rated tracks, and tracks that are usually
+    /// played through rather than skipped.
+    Rating,
+    /// Favour tracks that have been played the least.
+    InversePlayCount,
+}
+
+impl RandomWeighting {
+    fn weight(&self, stats: Option<&media_file_stats::Model>) -> f64 {
+        let (liked, skipped, played_through) = stats
+            .map(|x| (x.liked, x.skipped.max(0), x.played_through.max(0)))
+            .unwrap_or_default();
+
+        match self {
+            RandomWeighting::Uniform => 1.0,
+            RandomWeighting::Rating => {
+                let ratio = (1 + played_through) as f64 / (1 + skipped) as f64;
+                let ratio = if liked { ratio * 2.0 } else { ratio };
+
+                // Unrated tracks count as an average rating
+                match stats.and_then(|x| x.rating) {
+                    Some(rating) => ratio * (1 + rating.max(0)) as f64 / 4.0,
+                    None => ratio,
+                }
+            }
+            RandomWeighting::InversePlayCount => 1.0 / (1 + played_through) as f64,
+        }
+    }
+}
+
+impl std::str::FromStr for RandomWeighting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "uniform" => Ok(RandomWeighting::Uniform),
+            "rating" => Ok(RandomWeighting::Rating),
+            "inverse_play_count" => Ok(RandomWeighting::InversePlayCount),
+            _ => anyhow::bail!("Unknown random weighting: {s}"),
+        }
+    }
+}
+
+/// Draw `n` distinct items, each with a probability proportional to its
+/// weight (Efraimidis-Spirakis sampling). Items with a non positive weight
+/// are never drawn.
+pub fn weighted_sample<T, R: Rng>(items: Vec<(T, f64)>, n: usize, rng: &mut R) -> Vec<T> {
+    let mut keyed: Vec<(f64, T)> = items
+        .into_iter()
+        .filter(|(_, weight)| *weight > 0.0)
+        .map(|(item, weight)| (rng.r#gen::<f64>().powf(1.0 / weight), item))
+        .collect();
+
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keyed.into_iter().take(n).map(|(_, item)| item).collect()
+}
+
+/// Collect the files whose analysis vectors are the closest to a seed track,
+/// the seed itself included.
+pub fn get_analysis_cluster(
+    recommend_db: &RecommendationDbConnection,
+    seed_file_id: i32,
+    size: usize,
+) -> Result<Vec<i32>> {
+    let mut cluster: Vec<i32> = get_recommendation_by_file_id(recommend_db, seed_file_id, size)?
+        .into_iter()
+        .map(|(id, _)| id as i32)
+        .collect();
+
+    if !cluster.contains(&seed_file_id) {
+        cluster.push(seed_file_id);
+    }
+
+    Ok(cluster)
+}
+
 pub async fn get_random_files(
     db: &DatabaseConnection,
     n: usize,
+    filter: &RandomFileFilter,
 ) -> Result<Vec<media_files::Model>, sea_orm::DbErr> {
-    let mut query: sea_orm::sea_query::SelectStatement =
-        media_files::Entity::find().as_query().to_owned();
+    let mut select = media_files::Entity::find()
+        .filter(media_files::Column::Id.not_in_subquery(hidden_files_subquery()));
+
+    if !filter.artist_ids.is_empty() {
+        select = select.filter(
+            media_files::Column::Id.in_subquery(
+                Query::select()
+                    .column(media_file_artists::Column::MediaFileId)
+                    .from(media_file_artists::Entity)
+                    .and_where(
+                        media_file_artists::Column::ArtistId.is_in(filter.artist_ids.clone()),
+                    )
+                    .to_owned(),
+            ),
+        );
+    }
+
+    if !filter.genre_ids.is_empty() {
+        select = select.filter(
+            media_files::Column::Id.in_subquery(
+                Query::select()
+                    .column(media_file_genres::Column::MediaFileId)
+                    .from(media_file_genres::Entity)
+                    .and_where(media_file_genres::Column::GenreId.is_in(filter.genre_ids.clone()))
+                    .to_owned(),
+            ),
+        );
+    }
+
+    if filter.liked_only {
+        select = select.filter(
+            media_files::Column::Id.in_subquery(
+                Query::select()
+                    .column(media_file_stats::Column::MediaFileId)
+                    .from(media_file_stats::Entity)
+                    .and_where(media_file_stats::Column::Liked.eq(true))
+                    .to_owned(),
+            ),
+        );
+    }
+
+    if let Some(min_rating) = filter.min_rating {
+        select = select.filter(
+            media_files::Column::Id.in_subquery(
+                Query::select()
+                    .column(media_file_stats::Column::MediaFileId)
+                    .from(media_file_stats::Entity)
+                    .and_where(media_file_stats::Column::Rating.gte(min_rating))
+                    .to_owned(),
+            ),
+        );
+    }
+
+    if let Some(cluster) = &filter.cluster {
+        select = select.filter(media_files::Column::Id.is_in(cluster.clone()));
+    }
+
+    if let Some(period) = filter.not_played_within {
+        // `started_at` is always written by `Utc::now().to_rfc3339()`, so
+        // comparing the strings preserves the chronological order.
+        let period = chrono::Duration::from_std(period).unwrap_or(chrono::Duration::MAX);
+        let threshold = Utc::now()
+            .checked_sub_signed(period)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+            .to_rfc3339();
+        select = select.filter(
+            media_files::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(play_history::Column::MediaFileId)
+                    .from(play_history::Entity)
+                    .and_where(play_history::Column::StartedAt.gt(threshold))
+                    .to_owned(),
+            ),
+        );
+    }
+
+    // Weighted draws pick from a larger uniformly random pool so that
+    // favoured tracks have something to win against.
+    let pool_size = match filter.weighting {
+        RandomWeighting::Uniform => n,
+        _ => n.saturating_mul(WEIGHTED_POOL_FACTOR),
+    };
+
+    let mut query: sea_orm::sea_query::SelectStatement = select.as_query().to_owned();
     let select = query
         .order_by_expr(SimpleExpr::FunctionCall(Func::random()), Order::Asc)
-        .limit(n as u64);
+        .limit(pool_size as u64);
     let statement = db.get_database_backend().build(select);
 
     let files = media_files::Model::find_by_statement(statement)
         .all(db)
         .await?;
 
-    Ok(files)
+    if filter.weighting == RandomWeighting::Uniform {
+        return Ok(files);
+    }
+
+    let stats: HashMap<i32, media_file_stats::Model> = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.is_in(files.iter().map(|x| x.id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|x| (x.media_file_id, x))
+        .collect();
+
+    let weighted = files
+        .into_iter()
+        .map(|file| {
+            let weight = filter.weighting.weight(stats.get(&file.id));
+            (file, weight)
+        })
+        .collect();
+
+    Ok(weighted_sample(weighted, n, &mut rand::thread_rng()))
+}
+
+/// Get the weight of every file under `weighting`, e.g. for a weighted
+/// shuffle of a queue. Every requested file has an entry.
+pub async fn get_file_weights(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+    weighting: RandomWeighting,
+) -> Result<HashMap<i32, f64>> {
+    let stats: HashMap<i32, media_file_stats::Model> = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.is_in(file_ids.to_vec()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.media_file_id, x))
+        .collect();
+
+    Ok(file_ids
+        .iter()
+        .map(|id| (*id, weighting.weight(stats.get(id))))
+        .collect())
+}
+
+/// Get the files most recently played through or skipped, newest first.
+///
+/// # Arguments
+/// * `main_db` - The main database connection.
+/// * `limit` - The maximum number of files to return.
+///
+/// # Returns
+/// * The recently played files.
+pub async fn get_recently_played_files(
+    main_db: &DatabaseConnection,
+    limit: u64,
+) -> Result<Vec<media_files::Model>> {
+    let file_ids: Vec<i32> = media_file_stats::Entity::find()
+        .filter(
+            media_file_stats::Column::PlayedThrough
+                .gt(0)
+                .or(media_file_stats::Column::Skipped.gt(0)),
+        )
+        .order_by_desc(media_file_stats::Column::UpdatedAt)
+        .limit(limit)
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| x.media_file_id)
+        .collect();
+
+    get_ordered_files_by_ids(main_db, &file_ids).await
 }
 
 pub async fn get_file_by_path(
@@ -126,6 +395,7 @@ pub async fn get_media_files(
     page_size: usize,
 ) -> Result<Vec<media_files::Model>, sea_orm::DbErr> {
     media_files::Entity::find()
+        .filter(media_files::Column::Id.not_in_subquery(hidden_files_subquery()))
         .cursor_by(media_files::Column::Id)
         .after(cursor as i32)
         .first(page_size as u64)
@@ -133,12 +403,29 @@ pub async fn get_media_files(
         .await
 }
 
+/// Files whose path relative to the library matches a glob pattern, as
+/// understood by SQLite. `*` matches across directories as well.
+pub async fn get_files_by_glob(
+    main_db: &DatabaseConnection,
+    pattern: &str,
+) -> Result<Vec<media_files::Model>, sea_orm::DbErr> {
+    media_files::Entity::find()
+        .filter(Expr::cust_with_values(
+            "(CASE WHEN directory = '' THEN file_name ELSE directory || '/' || file_name END) GLOB ?",
+            [pattern],
+        ))
+        .order_by_asc(media_files::Column::Id)
+        .all(main_db)
+        .await
+}
+
 pub async fn get_reverse_listed_media_files(
     main_db: &DatabaseConnection,
     cursor: usize,
     page_size: usize,
 ) -> Result<Vec<media_files::Model>, sea_orm::DbErr> {
     media_files::Entity::find()
+        .filter(media_files::Column::Id.not_in_subquery(hidden_files_subquery()))
         .order_by(media_files::Column::Id, Order::Desc)
         .offset(cursor as u64)
         .limit(page_size as u64)
@@ -179,6 +466,33 @@ pub async fn get_file_ids_by_descriptions(
     Ok(file_ids)
 }
 
+/// Map content hashes to the ids of the local files carrying them.
+///
+/// # Arguments
+/// * `db` - The main database connection.
+/// * `hashes` - The file hashes to look up.
+///
+/// # Returns
+/// * A map from hash to file id, hashes missing from the library are absent.
+pub async fn get_file_ids_by_hashes(
+    db: &DatabaseConnection,
+    hashes: &[String],
+) -> Result<HashMap<String, i32>, DbErr> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let file_entries = media_files::Entity::find()
+        .filter(media_files::Column::FileHash.is_in(hashes.to_vec()))
+        .all(db)
+        .await?;
+
+    Ok(file_entries
+        .into_iter()
+        .map(|entry| (entry.file_hash, entry.id))
+        .collect())
+}
+
 pub async fn get_duration_by_file_id(
     db: &DatabaseConnection,
     file_id: i32,
@@ -196,3 +510,111 @@ pub async fn get_duration_by_file_id(
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::actions::stats::{record_playback_start, set_liked, set_rating};
+    use crate::test_support::{connect_test_main_db, seed_fake_tracks};
+
+    #[test]
+    fn test_weighted_sample_is_distinct_and_bounded() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let items = (0..10).map(|x| (x, 1.0)).collect();
+        let mut picked = weighted_sample(items, 5, &mut rng);
+
+        assert_eq!(picked.len(), 5);
+        picked.sort();
+        picked.dedup();
+        assert_eq!(picked.len(), 5);
+    }
+
+    #[test]
+    fn test_weighted_sample_prefers_heavy_items() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut heavy_first = 0;
+        for _ in 0..1000 {
+            let picked = weighted_sample(vec![("light", 1.0), ("heavy", 9.0)], 1, &mut rng);
+            if picked == ["heavy"] {
+                heavy_first += 1;
+            }
+        }
+
+        assert!(heavy_first > 800, "heavy item drawn {heavy_first} times");
+    }
+
+    #[test]
+    fn test_weighted_sample_skips_zero_weight() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let picked = weighted_sample(vec![(1, 0.0), (2, 1.0)], 2, &mut rng);
+
+        assert_eq!(picked, vec![2]);
+    }
+
+    async fn random_file_ids(
+        db: &DatabaseConnection,
+        filter: &RandomFileFilter,
+    ) -> Result<Vec<i32>> {
+        let mut ids: Vec<i32> = get_random_files(db, 10, filter)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    #[tokio::test]
+    async fn test_random_files_by_rating_and_recent_plays() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 4).await?;
+
+        set_rating(&db, file_ids[0], Some(5)).await?;
+        set_rating(&db, file_ids[1], Some(3)).await?;
+        set_rating(&db, file_ids[3], Some(4)).await?;
+
+        let rated = RandomFileFilter {
+            min_rating: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            random_file_ids(&db, &rated).await?,
+            vec![file_ids[0], file_ids[3]]
+        );
+
+        // Liking touches the stats but is not a play
+        record_playback_start(&db, file_ids[0]).await?;
+        set_liked(&db, file_ids[3], true).await?;
+
+        let not_played = RandomFileFilter {
+            not_played_within: Some(Duration::from_secs(24 * 60 * 60)),
+            ..rated
+        };
+        assert_eq!(random_file_ids(&db, &not_played).await?, vec![file_ids[3]]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_weights_follow_rating() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        set_rating(&db, file_ids[0], Some(5)).await?;
+        set_rating(&db, file_ids[1], Some(0)).await?;
+
+        let weights = get_file_weights(&db, &file_ids, RandomWeighting::Rating).await?;
+        assert_eq!(weights.len(), 3);
+        assert!(weights[&file_ids[0]] > weights[&file_ids[2]]);
+        assert!(weights[&file_ids[2]] > weights[&file_ids[1]]);
+
+        let weights = get_file_weights(&db, &file_ids, RandomWeighting::Uniform).await?;
+        assert!(weights.values().all(|x| *x == 1.0));
+
+        Ok(())
+    }
+}
@@ -16,7 +16,8 @@ use tokio_util::sync::CancellationToken;
 
 pub use tag_editor::music_brainz::fingerprint::{Configuration, Segment};
 use tag_editor::music_brainz::fingerprint::{
-    calc_fingerprint, calculate_similarity_score, get_track_duration_in_secs, match_fingerprints,
+    calc_fingerprint, calculate_similarity_score, encode_fingerprint, get_track_duration_in_secs,
+    match_fingerprints,
 };
 
 use crate::entities::prelude::{MediaFileFingerprint, MediaFileSimilarity, MediaFiles};
@@ -129,6 +130,34 @@ pub async fn has_fingerprint(main_db: &DatabaseConnection, file_id: i32) -> Resu
         > 0)
 }
 
+/// The stored fingerprint of a file, `None` if it wasn't computed yet.
+pub async fn get_fingerprint_by_file_id(
+    main_db: &DatabaseConnection,
+    file_id: i32,
+) -> Result<Option<Vec<u32>>> {
+    let fingerprint = media_file_fingerprint::Entity::find()
+        .filter(media_file_fingerprint::Column::MediaFileId.eq(file_id))
+        .one(main_db)
+        .await?;
+
+    fingerprint
+        .map(|x| bytes_to_u32s(x.fingerprint))
+        .transpose()
+}
+
+/// Compress a fingerprint the way `fpcalc` does, which is what the AcoustID
+/// lookup API expects. Returns the encoded fingerprint and the duration it
+/// covers in seconds.
+pub fn encode_acoustid_fingerprint(fingerprint: Vec<u32>) -> (String, f32) {
+    let config = Configuration::default();
+    let duration = get_track_duration_in_secs(&fingerprint, &config);
+
+    (
+        encode_fingerprint(fingerprint, &config, false, false),
+        duration,
+    )
+}
+
 pub async fn get_fingerprint_count(main_db: &DatabaseConnection) -> Result<u64> {
     Ok(media_file_fingerprint::Entity::find()
         .count(main_db)
@@ -429,7 +458,7 @@ where
     Ok(marked_count)
 }
 
-fn group_similar_files(similarities: &[media_file_similarity::Model]) -> Vec<Vec<i32>> {
+pub(crate) fn group_similar_files(similarities: &[media_file_similarity::Model]) -> Vec<Vec<i32>> {
     let mut adjacency_list: HashMap<i32, Vec<i32>> = HashMap::new();
 
     // Build an adjacency list for our similarity graph
@@ -593,3 +622,53 @@ where
     info!("Reset duplicate marks for {updated_count} files");
     Ok(updated_count)
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::{ActiveValue, EntityTrait};
+
+    use crate::{
+        actions::fingerprint::{encode_acoustid_fingerprint, get_fingerprint_by_file_id},
+        entities::media_file_fingerprint,
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_encode_stored_fingerprint() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        let fingerprint: Vec<u32> = (0..240).map(|x| x * 0x0101_0101).collect();
+        media_file_fingerprint::Entity::insert(media_file_fingerprint::ActiveModel {
+            media_file_id: ActiveValue::Set(file_ids[0]),
+            fingerprint: ActiveValue::Set(
+                fingerprint.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            ),
+            is_duplicated: ActiveValue::Set(0),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await?;
+
+        let stored = get_fingerprint_by_file_id(&db, file_ids[0]).await?.unwrap();
+        assert_eq!(stored, fingerprint);
+        assert!(
+            get_fingerprint_by_file_id(&db, file_ids[1])
+                .await?
+                .is_none()
+        );
+
+        let (encoded, duration) = encode_acoustid_fingerprint(stored);
+        // Compressed fingerprints are URL safe base64 without padding
+        assert!(!encoded.is_empty());
+        assert!(
+            encoded
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        );
+        assert!(duration > 0.0);
+
+        Ok(())
+    }
+}
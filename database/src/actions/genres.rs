@@ -28,3 +28,123 @@ collection_query!(
     media_file_genres,
     GenreId
 );
+
+pub async fn get_genres_groups(
+    main_db: &DatabaseConnection,
+    groups: Vec<String>,
+) -> Result<Vec<(String, Vec<(genres::Model, HashSet<i32>)>)>> {
+    Ok(get_groups_internal(main_db, groups).await?)
+}
+
+/// IDs of the genres with the given names, ignoring case.
+pub async fn get_genre_ids_by_names(
+    main_db: &DatabaseConnection,
+    names: &[String],
+) -> Result<Vec<i32>> {
+    let names: HashSet<String> = names.iter().map(|x| x.trim().to_lowercase()).collect();
+
+    Ok(genres::Entity::find()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .filter(|x| names.contains(&x.name.to_lowercase()))
+        .map(|x| x.id)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::{EntityTrait, PaginatorTrait};
+
+    use ::metadata::{genre::split_genres, splitting::SplittingRules};
+
+    use crate::{
+        actions::{genres::get_genres_groups, recommendation::retain_genre_recommendations},
+        entities::{genres, media_file_genres},
+        test_support::{FakeTrack, connect_test_main_db, seed_fake_tracks, seed_tracks},
+    };
+
+    #[test]
+    fn test_split_genres() {
+        let rules = SplittingRules::default();
+
+        assert_eq!(
+            split_genres("Rock; Pop/Jazz\\\\Rock", &rules),
+            vec!["Rock".to_string(), "Pop".to_string(), "Jazz".to_string()]
+        );
+        assert_eq!(
+            split_genres(" Ambient ", &rules),
+            vec!["Ambient".to_string()]
+        );
+        assert!(split_genres("", &rules).is_empty());
+        assert!(split_genres(" ; / ", &rules).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_multi_genre_tracks_are_indexed() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        let mut track = FakeTrack::nth(0);
+        track.genre = "Rock; Pop".to_string();
+        seed_tracks(&db, &[track]).await?;
+
+        let mut names: Vec<String> = genres::Entity::find()
+            .all(&db)
+            .await?
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["Pop".to_string(), "Rock".to_string()]);
+        assert_eq!(media_file_genres::Entity::find().count(&db).await?, 2);
+
+        let genre = genres::Entity::find().one(&db).await?.unwrap();
+        let groups = get_genres_groups(&db, vec![genre.group.clone()]).await?;
+        assert_eq!(groups.len(), 1);
+        assert!(groups[0].1.iter().any(|(x, _)| x.id == genre.id));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retain_genre_recommendations() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 8).await?;
+
+        let recommendations: Vec<(u32, f32)> = file_ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id as u32, index as f32))
+            .collect();
+
+        // Genres rotate, so every fourth track is Jazz
+        let jazz = retain_genre_recommendations(
+            &db,
+            recommendations.clone(),
+            &["jazz".to_string()],
+            usize::MAX,
+        )
+        .await?;
+        assert_eq!(
+            jazz,
+            vec![recommendations[2], recommendations[6]],
+            "genres are matched ignoring case"
+        );
+
+        let limited = retain_genre_recommendations(
+            &db,
+            recommendations.clone(),
+            &["Ambient".to_string(), "Rock".to_string()],
+            2,
+        )
+        .await?;
+        assert_eq!(limited, vec![recommendations[0], recommendations[3]]);
+
+        let unknown =
+            retain_genre_recommendations(&db, recommendations, &["Polka".to_string()], 10).await?;
+        assert!(unknown.is_empty());
+
+        Ok(())
+    }
+}
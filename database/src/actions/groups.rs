@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use log::info;
+use sea_orm::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{QuerySelect, TransactionTrait};
+
+use crate::actions::settings::{GROUP_NAME_VERSION_KEY, get_setting, set_setting};
+use crate::actions::utils::generate_group_name;
+use crate::entities::{albums, artists, genres};
+
+/// Version of [`generate_group_name`], bump it whenever the groups of
+/// existing names change so they are computed again on startup.
+///
+/// * `1` - The first letter of the transliterated name.
+/// * `2` - Names starting with kana are grouped by their kana row.
+pub const GROUP_NAME_VERSION: i32 = 2;
+
+macro_rules! regroup_entity {
+    ($txn:expr, $entity:ident) => {{
+        let rows: Vec<(i32, String, String)> = $entity::Entity::find()
+            .select_only()
+            .columns([
+                $entity::Column::Id,
+                $entity::Column::Name,
+                $entity::Column::Group,
+            ])
+            .into_tuple()
+            .all($txn)
+            .await?;
+
+        let mut changed: HashMap<String, Vec<i32>> = HashMap::new();
+        for (id, name, group) in rows {
+            let new_group = generate_group_name(&name);
+            if new_group != group {
+                changed.entry(new_group).or_default().push(id);
+            }
+        }
+
+        let mut updated = 0;
+        for (group, ids) in changed {
+            updated += ids.len();
+            $entity::Entity::update_many()
+                .col_expr($entity::Column::Group, Expr::value(group))
+                .filter($entity::Column::Id.is_in(ids))
+                .exec($txn)
+                .await?;
+        }
+
+        updated
+    }};
+}
+
+/// Compute the group of every artist, album and genre again, keeping the
+/// ones that didn't change.
+///
+/// # Returns
+/// * `Result<usize>` - How many collections moved to another group.
+pub async fn regroup_collections(main_db: &DatabaseConnection) -> Result<usize> {
+    let txn = main_db.begin().await?;
+    let updated = regroup_entity!(&txn, artists)
+        + regroup_entity!(&txn, albums)
+        + regroup_entity!(&txn, genres);
+    txn.commit().await?;
+
+    Ok(updated)
+}
+
+/// Regroup the collections if they were grouped by an older version of
+/// [`generate_group_name`].
+///
+/// # Returns
+/// * `Result<usize>` - How many collections moved to another group.
+pub async fn upgrade_collection_groups(main_db: &DatabaseConnection) -> Result<usize> {
+    let version = get_setting(main_db, GROUP_NAME_VERSION_KEY)
+        .await?
+        .and_then(|x| x.parse::<i32>().ok())
+        .unwrap_or(1);
+    if version >= GROUP_NAME_VERSION {
+        return Ok(0);
+    }
+
+    let updated = regroup_collections(main_db).await?;
+
+    set_setting(
+        main_db,
+        GROUP_NAME_VERSION_KEY,
+        Some(&GROUP_NAME_VERSION.to_string()),
+    )
+    .await?;
+    info!("Collection groups upgraded to version {GROUP_NAME_VERSION}, {updated} items regrouped");
+
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, sea_query::Expr};
+
+    use crate::{
+        actions::{
+            groups::upgrade_collection_groups,
+            settings::{GROUP_NAME_VERSION_KEY, set_setting},
+            utils::{generate_group_name, kana_row},
+        },
+        entities::{albums, artists},
+        test_support::{FakeTrack, connect_test_main_db, seed_tracks},
+    };
+
+    #[test]
+    fn test_group_names_by_script() {
+        let cases = [
+            ("Radiohead", "R"),
+            ("aurora", "A"),
+            ("1979", "#"),
+            ("北京欢迎你", "B"),
+            ("Мумий Тролль", "M"),
+            ("さくら", "さ"),
+            ("ガガガSP", "か"),
+            ("ぁいうえお", "あ"),
+            ("ンダ", "わ"),
+            ("ヴィジュアル", "あ"),
+            ("ーー", "#"),
+        ];
+
+        for (name, group) in cases {
+            assert_eq!(generate_group_name(name), group, "name: {name}");
+        }
+
+        assert_eq!(kana_row('ぽ'), Some('は'));
+        assert_eq!(kana_row('ッ'), Some('た'));
+        assert_eq!(kana_row('北'), None);
+    }
+
+    async fn artist_group(db: &DatabaseConnection, name: &str) -> Result<String> {
+        Ok(artists::Entity::find()
+            .filter(artists::Column::Name.eq(name))
+            .one(db)
+            .await?
+            .unwrap()
+            .group)
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_regroups_kana_names() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        let mut track = FakeTrack::nth(0);
+        track.artist = "さくら".to_string();
+        track.album = "ハルカ".to_string();
+        seed_tracks(&db, &[track, FakeTrack::nth(10)]).await?;
+
+        assert_eq!(artist_group(&db, "さくら").await?, "さ");
+
+        // Grouped by the first version, by the romanized name
+        artists::Entity::update_many()
+            .col_expr(artists::Column::Group, Expr::value("S"))
+            .filter(artists::Column::Name.eq("さくら"))
+            .exec(&db)
+            .await?;
+        albums::Entity::update_many()
+            .col_expr(albums::Column::Group, Expr::value("H"))
+            .filter(albums::Column::Name.eq("ハルカ"))
+            .exec(&db)
+            .await?;
+        set_setting(&db, GROUP_NAME_VERSION_KEY, Some("1")).await?;
+
+        assert_eq!(upgrade_collection_groups(&db).await?, 2);
+        assert_eq!(upgrade_collection_groups(&db).await?, 0);
+
+        assert_eq!(artist_group(&db, "さくら").await?, "さ");
+        assert_eq!(artist_group(&db, &FakeTrack::nth(10).artist).await?, "B");
+        let album = albums::Entity::find()
+            .filter(albums::Column::Name.eq("ハルカ"))
+            .one(&db)
+            .await?
+            .unwrap();
+        assert_eq!(album.group, "は");
+
+        Ok(())
+    }
+}
@@ -3,18 +3,30 @@ use std::collections::{HashMap, HashSet};
 use anyhow::{Error, Result};
 use log::{error, info};
 use migration::OnConflict;
-use sea_orm::{prelude::*, DatabaseTransaction, QuerySelect};
 use sea_orm::{DatabaseConnection, Set, TransactionTrait};
+use sea_orm::{DatabaseTransaction, QuerySelect, prelude::*};
+use tokio::sync::broadcast::Receiver;
 use tokio_util::sync::CancellationToken;
 
+use ::metadata::splitting::SplittingRules;
+
+use crate::actions::albums::update_album_details;
 use crate::actions::collection::CollectionQueryType;
 use crate::actions::search::{add_term, remove_term};
+use crate::actions::splitting::get_splitting_rules;
+use crate::actions::summaries::sync_media_summaries;
 use crate::actions::utils::generate_group_name;
 use crate::entities::{
     albums, artists, genres, media_file_albums, media_file_artists, media_file_genres, media_files,
+    media_metadata,
 };
+use crate::events::{LibraryEvent, next_events};
+
+use super::metadata::{MetadataSummary, get_metadata_summary_by_file_ids};
 
-use super::metadata::{get_metadata_summary_by_file_ids, MetadataSummary};
+/// Tags crediting artists besides the artist tag, they aren't part of the
+/// metadata summary.
+const CREDIT_TAGS: [&str; 2] = ["composer", "remixer"];
 
 /// Indexes media files by processing their metadata and updating database records for artists, albums, and genres.
 ///
@@ -49,6 +61,11 @@ pub async fn index_media_files(
 
     // Retrieve metadata summaries for the given file IDs.
     let metadata_summaries = get_metadata_summary_by_file_ids(main_db, file_ids.clone()).await?;
+    let mut credit_tags = get_credit_tags(main_db, &file_ids).await?;
+    let rules = get_splitting_rules(main_db).await?;
+
+    // Albums whose tracks changed, their album artists are worked out once all files are indexed.
+    let mut touched_album_ids: HashSet<i32> = HashSet::new();
 
     for summary in metadata_summaries {
         // Start a new transaction for each file to ensure individual processing atomicity.
@@ -70,17 +87,20 @@ pub async fn index_media_files(
         }
 
         // Process artists for the current media file.
-        let artist_result = process_artists(&txn, &summary, cancel_token).await;
+        let tags = credit_tags.remove(&summary.id).unwrap_or_default();
+        let artist_result = process_artists(&txn, &summary, &tags, &rules, cancel_token).await;
         // Process album for the current media file.
         let album_result = process_album(&txn, &summary).await;
         // Process genres for the current media file.
-        let genre_result = process_genres(&txn, &summary, cancel_token).await;
+        let genre_result = process_genres(&txn, &summary, &rules, cancel_token).await;
 
         // Commit transaction if all processing is successful, otherwise rollback.
         match (artist_result, album_result, genre_result) {
-            (Ok(_), Ok(_), Ok(_)) => {
+            (Ok(_), Ok(album_ids), Ok(_)) => {
                 if let Err(e) = txn.commit().await {
                     error!("Commit failed for file {}: {}", summary.id, e);
+                } else {
+                    touched_album_ids.extend(album_ids);
                 }
             }
             _ => {
@@ -90,11 +110,42 @@ pub async fn index_media_files(
         }
     }
 
+    let touched_album_ids: Vec<i32> = touched_album_ids.into_iter().collect();
+    if let Err(e) = update_album_details(main_db, &touched_album_ids).await {
+        error!("Failed to update album details: {e}");
+    }
+
     Ok(())
 }
 
+/// The composer and remixer tags of the files, by file ID and tag.
+async fn get_credit_tags(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, HashMap<String, String>>> {
+    let mut tags: HashMap<i32, HashMap<String, String>> = HashMap::new();
+    for (file_id, key, value) in media_metadata::Entity::find()
+        .select_only()
+        .column(media_metadata::Column::FileId)
+        .column(media_metadata::Column::MetaKey)
+        .column(media_metadata::Column::MetaValue)
+        .filter(media_metadata::Column::FileId.is_in(file_ids.to_vec()))
+        .filter(media_metadata::Column::MetaKey.is_in(CREDIT_TAGS))
+        .into_tuple::<(i32, String, String)>()
+        .all(main_db)
+        .await?
+    {
+        tags.entry(file_id).or_default().insert(key, value);
+    }
+
+    Ok(tags)
+}
+
 /// Processes artist information from metadata summary, updating artist records and associations.
 ///
+/// The artist tag is split into main and featured artists, and the composer and remixer tags,
+/// or a remix named in the title, credit artists in those roles.
+///
 /// This function parses artist names from the metadata summary, identifies existing artists,
 /// inserts new artists if necessary, and updates the relationships between media files and artists
 /// in the database. It also handles search term indexing for new artists.
@@ -103,6 +154,8 @@ pub async fn index_media_files(
 ///
 /// * `txn`: A reference to the database transaction.
 /// * `summary`: A reference to the metadata summary of the media file.
+/// * `tags`: The composer and remixer tags of the media file.
+/// * `rules`: How artist tags are split into several artists.
 /// * `cancel_token`: An optional cancellation token to stop the operation prematurely.
 ///
 /// # Returns
@@ -111,17 +164,26 @@ pub async fn index_media_files(
 async fn process_artists(
     txn: &DatabaseTransaction,
     summary: &MetadataSummary,
+    tags: &HashMap<String, String>,
+    rules: &SplittingRules,
     cancel_token: Option<&CancellationToken>,
 ) -> Result<()> {
-    // Split and deduplicate artist names from the metadata summary.
-    let artist_names: Vec<String> = {
-        let names = metadata::artist::split_artists(&summary.artist);
-        names
-            .into_iter()
-            .collect::<HashSet<_>>() // Deduplicate artist names using HashSet.
-            .into_iter()
-            .collect() // Convert HashSet back to Vec for ordered processing.
-    };
+    // Work out who is credited in which role.
+    let credits = metadata::artist::artist_credits(
+        &summary.artist,
+        &summary.title,
+        tags.get("composer").map(|x| x.as_str()),
+        tags.get("remixer").map(|x| x.as_str()),
+        rules,
+    );
+
+    // Deduplicate artist names, an artist may have several roles.
+    let artist_names: Vec<String> = credits
+        .iter()
+        .map(|x| x.name.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
 
     // If no artist names are found, return early.
     if artist_names.is_empty() {
@@ -180,14 +242,14 @@ async fn process_artists(
         .all(txn)
         .await?;
 
-    // Collect artist IDs and add search terms for newly inserted artists.
-    let mut artist_ids = Vec::new();
+    // Map artist names to IDs and add search terms for newly inserted artists.
+    let mut artist_ids = HashMap::new();
     for artist in final_artists {
         // Add search term only for artists that were newly inserted in this process.
         if !existing_map.contains_key(&artist.name) {
             add_term(txn, CollectionQueryType::Artist, artist.id, &artist.name).await?;
         }
-        artist_ids.push(artist.id);
+        artist_ids.insert(artist.name, artist.id);
     }
 
     // Clean up existing artist associations for the media file before creating new ones.
@@ -196,17 +258,24 @@ async fn process_artists(
         .exec(txn)
         .await?;
 
-    // Insert new artist associations for the media file.
-    if !artist_ids.is_empty() {
-        media_file_artists::Entity::insert_many(artist_ids.into_iter().map(|artist_id| {
-            media_file_artists::ActiveModel {
-                media_file_id: Set(summary.id), // Set media file ID.
-                artist_id: Set(artist_id),      // Set artist ID.
-                ..Default::default()
-            }
-        }))
-        .exec(txn)
-        .await?;
+    // Insert new artist associations for the media file, one per role.
+    let links: Vec<_> = credits
+        .iter()
+        .filter_map(|credit| {
+            artist_ids
+                .get(&credit.name)
+                .map(|artist_id| media_file_artists::ActiveModel {
+                    media_file_id: Set(summary.id),              // Set media file ID.
+                    artist_id: Set(*artist_id),                  // Set artist ID.
+                    role: Set(credit.role.as_str().to_string()), // Set the role of the artist.
+                    ..Default::default()
+                })
+        })
+        .collect();
+    if !links.is_empty() {
+        media_file_artists::Entity::insert_many(links)
+            .exec(txn)
+            .await?;
     }
 
     Ok(())
@@ -225,8 +294,9 @@ async fn process_artists(
 ///
 /// # Returns
 ///
-/// Returns `Ok(())` if album processing is successful, or an `Err(Error)` if any error occurs.
-async fn process_album(txn: &DatabaseTransaction, summary: &MetadataSummary) -> Result<()> {
+/// Returns the IDs of the albums the media file was moved out of and into, whose album
+/// artists may have changed, or an `Err(Error)` if any error occurs.
+async fn process_album(txn: &DatabaseTransaction, summary: &MetadataSummary) -> Result<Vec<i32>> {
     let album_name = &summary.album;
     let album = albums::ActiveModel {
         name: Set(album_name.clone()),               // Set album name.
@@ -257,6 +327,16 @@ async fn process_album(txn: &DatabaseTransaction, summary: &MetadataSummary) ->
         }
     };
 
+    // Remember the albums the media file leaves, so their details are updated too.
+    let mut touched_album_ids: Vec<i32> = media_file_albums::Entity::find()
+        .select_only()
+        .column(media_file_albums::Column::AlbumId)
+        .filter(media_file_albums::Column::MediaFileId.eq(summary.id))
+        .into_tuple()
+        .all(txn)
+        .await?;
+    touched_album_ids.push(album_id);
+
     // Clean up existing album associations for the media file before creating new ones.
     media_file_albums::Entity::delete_many()
         .filter(media_file_albums::Column::MediaFileId.eq(summary.id)) // Delete associations for the current media file.
@@ -273,7 +353,7 @@ async fn process_album(txn: &DatabaseTransaction, summary: &MetadataSummary) ->
     .exec(txn)
     .await?;
 
-    Ok(())
+    Ok(touched_album_ids)
 }
 
 /// Processes genre information from metadata summary, updating genre records and associations.
@@ -286,6 +366,7 @@ async fn process_album(txn: &DatabaseTransaction, summary: &MetadataSummary) ->
 ///
 /// * `txn`: A reference to the database transaction.
 /// * `summary`: A reference to the metadata summary of the media file.
+/// * `rules`: How genre tags are split into several genres.
 /// * `cancel_token`: An optional cancellation token to stop the operation prematurely.
 ///
 /// # Returns
@@ -294,11 +375,12 @@ async fn process_album(txn: &DatabaseTransaction, summary: &MetadataSummary) ->
 async fn process_genres(
     txn: &DatabaseTransaction,
     summary: &MetadataSummary,
+    rules: &SplittingRules,
     cancel_token: Option<&CancellationToken>,
 ) -> Result<()> {
     // Split and deduplicate genre names from the metadata summary.
     let genre_names: Vec<String> = {
-        let names = metadata::genre::split_genres(&summary.genre);
+        let names = metadata::genre::split_genres(&summary.genre, rules);
         names
             .into_iter()
             .collect::<HashSet<_>>() // Deduplicate genre names using HashSet.
@@ -695,6 +777,160 @@ pub async fn perform_library_maintenance(
         return Err(e);
     }
 
+    // Summarize the files indexed before summaries were stored.
+    match sync_media_summaries(db).await {
+        Ok(0) => {}
+        Ok(count) => info!("Summarized {count} files"),
+        Err(e) => {
+            error!("Failed to summarize files: {e}");
+            return Err(e);
+        }
+    }
+
     info!("Library maintenance completed successfully");
     Ok(())
 }
+
+/// Indexes the files of `LibraryChanged` events and cleans up after removed files, until
+/// the cancellation token is cancelled.
+///
+/// # Arguments
+///
+/// * `db`: A reference to the database connection.
+/// * `receiver`: A subscription to the event bus, taken before the events are published.
+/// * `cancel_token`: A cancellation token to stop listening.
+///
+/// # Returns
+///
+/// Returns `Ok(())` once cancelled. Failures of single updates are logged and don't stop it.
+pub async fn index_on_library_changes(
+    db: &DatabaseConnection,
+    mut receiver: Receiver<LibraryEvent>,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    while let Some(events) = next_events(&mut receiver, &cancel_token).await {
+        let mut updated: Vec<i32> = Vec::new();
+        let mut removed = 0;
+        for event in events {
+            if let LibraryEvent::LibraryChanged {
+                updated: file_ids,
+                removed: count,
+            } = event
+            {
+                updated.extend(file_ids);
+                removed += count;
+            }
+        }
+
+        if !updated.is_empty()
+            && let Err(e) = index_media_files(db, updated, Some(&cancel_token)).await
+        {
+            error!("Unable to index changed files: {e:#}");
+        }
+
+        // Albums, artists and genres may have lost their last track
+        if removed > 0
+            && let Err(e) = perform_library_maintenance(db, Some(&cancel_token)).await
+        {
+            error!("Unable to clean up after removed files: {e:#}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use sea_orm::{EntityTrait, PaginatorTrait};
+    use tokio_util::sync::CancellationToken;
+
+    use crate::{
+        actions::index::index_on_library_changes,
+        entities::{albums, media_file_albums},
+        events::{EventBus, LibraryEvent, next_events},
+        test_support::{TRACKS_PER_ALBUM, connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_next_events_returns_queued_events() {
+        let events = EventBus::default();
+        let mut receiver = events.subscribe();
+        let cancel_token = CancellationToken::new();
+
+        events.publish(LibraryEvent::AnalysisFinished { file_ids: vec![1] });
+        events.publish(LibraryEvent::LibraryChanged {
+            updated: vec![2],
+            removed: 0,
+        });
+
+        let received = next_events(&mut receiver, &cancel_token).await;
+        assert_eq!(
+            received,
+            Some(vec![
+                LibraryEvent::AnalysisFinished { file_ids: vec![1] },
+                LibraryEvent::LibraryChanged {
+                    updated: vec![2],
+                    removed: 0,
+                },
+            ])
+        );
+
+        cancel_token.cancel();
+        assert_eq!(next_events(&mut receiver, &cancel_token).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_next_events_ends_without_publishers() {
+        let events = EventBus::default();
+        let mut receiver = events.subscribe();
+        drop(events);
+
+        assert_eq!(
+            next_events(&mut receiver, &CancellationToken::new()).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_on_library_changes() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, TRACKS_PER_ALBUM).await?;
+
+        // Forget the index, so only the subscriber can bring it back
+        media_file_albums::Entity::delete_many().exec(&db).await?;
+        albums::Entity::delete_many().exec(&db).await?;
+
+        let events = EventBus::default();
+        let cancel_token = CancellationToken::new();
+        let subscriber = tokio::spawn({
+            let db = db.clone();
+            let receiver = events.subscribe();
+            let cancel_token = cancel_token.clone();
+            async move { index_on_library_changes(&db, receiver, cancel_token).await }
+        });
+
+        events.publish(LibraryEvent::LibraryChanged {
+            updated: file_ids,
+            removed: 0,
+        });
+
+        let mut indexed = 0;
+        for _ in 0..50 {
+            indexed = media_file_albums::Entity::find().count(&db).await?;
+            if indexed == TRACKS_PER_ALBUM as u64 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(indexed, TRACKS_PER_ALBUM as u64);
+        assert_eq!(albums::Entity::find().count(&db).await?, 1);
+
+        cancel_token.cancel();
+        subscriber.await??;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+//! Indexes worth adding, found from the plans of slow statements.
+//!
+//! A slow statement whose plan scans a whole table while filtering it by
+//! some of its columns is a sign an index on those columns is missing.
+//! Only columns written as `"table"."column"`, the way sea-orm writes them,
+//! are picked up. Suggestions are kept in the settings until an index
+//! covering them exists, so `rune doctor` can report them later.
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use sea_orm::{DatabaseConnection, DbBackend, FromQueryResult, Statement};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::settings::{INDEX_SUGGESTIONS_KEY, get_setting, set_setting};
+use crate::query_profile::QueryProfile;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexSuggestion {
+    pub table: String,
+    pub columns: Vec<String>,
+    /// The slow statement the suggestion came from.
+    pub sql: String,
+}
+
+impl IndexSuggestion {
+    pub fn index_name(&self) -> String {
+        format!("idx_{}_{}", self.table, self.columns.join("_"))
+    }
+
+    /// The statement creating the suggested index.
+    pub fn create_statement(&self) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|x| format!("\"{x}\"")).collect();
+        format!(
+            "CREATE INDEX \"{}\" ON \"{}\" ({});",
+            self.index_name(),
+            self.table,
+            columns.join(", ")
+        )
+    }
+
+    fn same_index(&self, other: &IndexSuggestion) -> bool {
+        self.table == other.table && self.columns == other.columns
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct PlanRow {
+    detail: String,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct NameRow {
+    name: String,
+}
+
+/// The lines of the plan SQLite picks for `statement`.
+pub async fn explain_query_plan(
+    main_db: &DatabaseConnection,
+    statement: &Statement,
+) -> Result<Vec<String>> {
+    let values = statement
+        .values
+        .as_ref()
+        .map(|x| x.0.clone())
+        .unwrap_or_default();
+    let rows = PlanRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        format!("EXPLAIN QUERY PLAN {}", statement.sql),
+        values,
+    ))
+    .all(main_db)
+    .await?;
+
+    Ok(rows.into_iter().map(|x| x.detail).collect())
+}
+
+/// The table scanned by a line of a plan, and its alias if it has one.
+/// Scans through a covering index are left out.
+fn parse_scan(detail: &str) -> Option<(&str, Option<&str>)> {
+    let rest = detail.strip_prefix("SCAN ")?;
+    let rest = rest.strip_prefix("TABLE ").unwrap_or(rest);
+    if rest.contains(" USING ") {
+        return None;
+    }
+
+    let mut words = rest.split_whitespace();
+    let table = words.next()?;
+    let alias = match words.next() {
+        Some("AS") => words.next(),
+        _ => None,
+    };
+
+    Some((table, alias))
+}
+
+/// Columns of `table` compared for equality in `sql`, in the order they
+/// first appear.
+fn equality_columns(sql: &str, table: &str) -> Vec<String> {
+    let prefix = format!("\"{table}\".\"");
+    let mut columns: Vec<String> = vec![];
+
+    let mut start = 0;
+    while let Some(found) = sql[start..].find(&prefix) {
+        let column_start = start + found + prefix.len();
+        let Some(column_len) = sql[column_start..].find('"') else {
+            break;
+        };
+        let column_end = column_start + column_len;
+        start = column_end + 1;
+
+        let before = sql[..column_start - prefix.len()].trim_end();
+        let after = sql[start..].trim_start();
+        let compared = before.ends_with(" =")
+            || after.starts_with("= ")
+            || after.starts_with("IN ")
+            || after.starts_with("IN(");
+        let column = &sql[column_start..column_end];
+        if compared && !columns.iter().any(|x| x == column) {
+            columns.push(column.to_owned());
+        }
+    }
+
+    columns
+}
+
+/// Indexes that would spare the full table scans in `plan`, the plan of
+/// `sql`.
+pub fn suggest_indexes(sql: &str, plan: &[String]) -> Vec<IndexSuggestion> {
+    let mut suggestions: Vec<IndexSuggestion> = vec![];
+    for (table, alias) in plan.iter().filter_map(|x| parse_scan(x)) {
+        let mut columns = equality_columns(sql, alias.unwrap_or(table));
+        // The primary key already has an index
+        columns.retain(|x| x != "id");
+        if columns.is_empty() {
+            continue;
+        }
+
+        let suggestion = IndexSuggestion {
+            table: table.to_owned(),
+            columns,
+            sql: sql.to_owned(),
+        };
+        if !suggestions.iter().any(|x| x.same_index(&suggestion)) {
+            suggestions.push(suggestion);
+        }
+    }
+
+    suggestions
+}
+
+/// Whether an existing index starts with the columns of `suggestion`.
+async fn is_indexed(main_db: &DatabaseConnection, suggestion: &IndexSuggestion) -> Result<bool> {
+    let indexes = NameRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        "SELECT name FROM pragma_index_list(?);",
+        [suggestion.table.clone().into()],
+    ))
+    .all(main_db)
+    .await?;
+
+    for index in indexes {
+        let columns: Vec<String> = NameRow::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "SELECT name FROM pragma_index_info(?) ORDER BY seqno;",
+            [index.name.into()],
+        ))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| x.name)
+        .collect();
+
+        if columns.starts_with(&suggestion.columns) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+async fn load_index_suggestions(main_db: &DatabaseConnection) -> Result<Vec<IndexSuggestion>> {
+    let Some(value) = get_setting(main_db, INDEX_SUGGESTIONS_KEY).await? else {
+        return Ok(vec![]);
+    };
+
+    serde_json::from_str(&value).with_context(|| "Failed to parse the saved index suggestions")
+}
+
+async fn save_index_suggestions(
+    main_db: &DatabaseConnection,
+    suggestions: &[IndexSuggestion],
+) -> Result<()> {
+    let value = if suggestions.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(suggestions)?)
+    };
+    set_setting(main_db, INDEX_SUGGESTIONS_KEY, value.as_deref()).await
+}
+
+/// Log the plans of the slow statements in `profile`, and save the indexes
+/// they are missing.
+///
+/// # Returns
+/// * `Result<Vec<IndexSuggestion>>` - The suggestions that weren't saved
+///   before.
+pub async fn record_slow_queries(
+    main_db: &DatabaseConnection,
+    profile: &QueryProfile,
+) -> Result<Vec<IndexSuggestion>> {
+    let mut saved = load_index_suggestions(main_db).await?;
+    let mut added = vec![];
+
+    for statement in profile.slow_statements() {
+        let plan = match explain_query_plan(main_db, &statement).await {
+            Ok(plan) => plan,
+            Err(e) => {
+                warn!("Failed to explain {}: {e:#}", statement.sql);
+                continue;
+            }
+        };
+        info!("Plan of {}:\n  {}", statement.sql, plan.join("\n  "));
+
+        for suggestion in suggest_indexes(&statement.sql, &plan) {
+            if saved.iter().any(|x| x.same_index(&suggestion))
+                || is_indexed(main_db, &suggestion).await?
+            {
+                continue;
+            }
+
+            warn!("Missing index: {}", suggestion.create_statement());
+            saved.push(suggestion.clone());
+            added.push(suggestion);
+        }
+    }
+
+    if !added.is_empty() {
+        save_index_suggestions(main_db, &saved).await?;
+    }
+
+    Ok(added)
+}
+
+/// The saved suggestions, dropping the ones an index covers by now.
+pub async fn get_index_suggestions(main_db: &DatabaseConnection) -> Result<Vec<IndexSuggestion>> {
+    let saved = load_index_suggestions(main_db).await?;
+
+    let mut suggestions = Vec::with_capacity(saved.len());
+    for suggestion in &saved {
+        if !is_indexed(main_db, suggestion).await? {
+            suggestions.push(suggestion.clone());
+        }
+    }
+
+    if suggestions.len() != saved.len() {
+        save_index_suggestions(main_db, &suggestions).await?;
+    }
+
+    Ok(suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use sea_orm::{ConnectionTrait, prelude::*};
+
+    use crate::{
+        actions::index_suggestions::{get_index_suggestions, record_slow_queries, suggest_indexes},
+        entities::media_metadata,
+        query_profile::attach_query_profile,
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[test]
+    fn test_suggest_indexes() {
+        let sql = "SELECT \"media_metadata\".\"meta_value\" FROM \"media_metadata\" \
+                   WHERE \"media_metadata\".\"file_id\" = ? AND \"media_metadata\".\"meta_key\" IN (?, ?)";
+
+        let suggestions = suggest_indexes(sql, &["SCAN media_metadata".to_owned()]);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].table, "media_metadata");
+        assert_eq!(suggestions[0].columns, vec!["file_id", "meta_key"]);
+        assert_eq!(
+            suggestions[0].create_statement(),
+            "CREATE INDEX \"idx_media_metadata_file_id_meta_key\" ON \"media_metadata\" (\"file_id\", \"meta_key\");"
+        );
+
+        // Searches and covering index scans already use an index
+        assert!(
+            suggest_indexes(
+                sql,
+                &[
+                    "SEARCH media_metadata USING INDEX idx_x (file_id=?)".to_owned(),
+                    "SCAN media_metadata USING COVERING INDEX idx_y".to_owned(),
+                ]
+            )
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_slow_queries() -> Result<()> {
+        let mut db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        // Every statement counts as slow
+        let profile = attach_query_profile(&mut db, Some(Duration::ZERO));
+        media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.eq(file_ids[0]))
+            .filter(media_metadata::Column::MetaKey.eq("title"))
+            .all(&db)
+            .await?;
+
+        let added = record_slow_queries(&db, &profile).await?;
+        assert!(
+            added
+                .iter()
+                .any(|x| x.table == "media_metadata" && x.columns == ["file_id", "meta_key"]),
+            "suggestions: {added:?}"
+        );
+
+        // Kept for later, but only once
+        assert_eq!(get_index_suggestions(&db).await?.len(), added.len());
+        assert!(record_slow_queries(&db, &profile).await?.is_empty());
+
+        // Gone once the index exists
+        db.execute_unprepared(
+            "CREATE INDEX idx_media_metadata_file_key ON media_metadata (file_id, meta_key, meta_value);",
+        )
+        .await?;
+        assert!(
+            get_index_suggestions(&db)
+                .await?
+                .iter()
+                .all(|x| x.table != "media_metadata")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+//! A 2D map of the library built from the analysis vectors.
+//!
+//! The vectors are standardized per dimension, projected onto their first
+//! two principal components and grouped with k-means, so that tracks that
+//! sound alike end up close to each other and share a cluster label.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sea_orm::prelude::*;
+
+use crate::actions::analysis::{ANALYSIS_VECTOR_SIZE, AggregatedAnalysisResult};
+use crate::entities::{genres, media_analysis, media_file_genres};
+
+const DIMENSIONS: usize = ANALYSIS_VECTOR_SIZE;
+const POWER_ITERATIONS: usize = 100;
+const KMEANS_ITERATIONS: usize = 50;
+/// Upper bound of the cluster count picked automatically.
+pub const MAX_AUTO_CLUSTERS: usize = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryMapPoint {
+    pub file_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub cluster: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryMapCluster {
+    pub id: usize,
+    pub size: usize,
+    /// Center of the cluster on the map.
+    pub x: f32,
+    pub y: f32,
+    /// The most common genre of the cluster, if any track has one.
+    pub genre: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LibraryMap {
+    pub points: Vec<LibraryMapPoint>,
+    pub clusters: Vec<LibraryMapCluster>,
+}
+
+type Vector = [f32; DIMENSIONS];
+
+/// Scale every dimension to zero mean and unit variance, so loud features
+/// like the spectral centroid don't drown out the others.
+fn standardize(vectors: &mut [Vector]) {
+    let n = vectors.len() as f32;
+
+    for d in 0..DIMENSIONS {
+        let mean = vectors.iter().map(|x| x[d]).sum::<f32>() / n;
+        let variance = vectors.iter().map(|x| (x[d] - mean).powi(2)).sum::<f32>() / n;
+        let std = variance.sqrt();
+
+        for x in vectors.iter_mut() {
+            x[d] = if std > f32::EPSILON {
+                (x[d] - mean) / std
+            } else {
+                0.0
+            };
+        }
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+/// The first two principal components of standardized vectors, found by
+/// power iteration on the covariance matrix.
+fn principal_components(vectors: &[Vector]) -> [Vector; 2] {
+    let n = vectors.len() as f32;
+
+    let mut covariance = vec![[0.0f32; DIMENSIONS]; DIMENSIONS];
+    for x in vectors {
+        for i in 0..DIMENSIONS {
+            for j in i..DIMENSIONS {
+                covariance[i][j] += x[i] * x[j] / n;
+            }
+        }
+    }
+    for i in 0..DIMENSIONS {
+        for j in 0..i {
+            covariance[i][j] = covariance[j][i];
+        }
+    }
+
+    let mut components = [[0.0f32; DIMENSIONS]; 2];
+    for component in 0..2 {
+        // A fixed start keeps the map stable between calls
+        let mut v: Vector = std::array::from_fn(|i| 1.0 / (i + 1) as f32);
+
+        for _ in 0..POWER_ITERATIONS {
+            let mut next: Vector = std::array::from_fn(|i| dot(&covariance[i], &v));
+
+            // Deflate, so the second component is orthogonal to the first
+            if component == 1 {
+                let projection = dot(&next, &components[0]);
+                for (x, c) in next.iter_mut().zip(&components[0]) {
+                    *x -= projection * c;
+                }
+            }
+
+            let norm = dot(&next, &next).sqrt();
+            if norm <= f32::EPSILON {
+                break;
+            }
+            v = next.map(|x| x / norm);
+        }
+
+        components[component] = v;
+    }
+
+    components
+}
+
+/// Group vectors with k-means, seeded with k-means++.
+fn k_means(vectors: &[Vector], k: usize) -> Vec<usize> {
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let mut centroids: Vec<Vector> = vec![vectors[rng.gen_range(0..vectors.len())]];
+    while centroids.len() < k {
+        let weights: Vec<f32> = vectors
+            .iter()
+            .map(|x| {
+                centroids
+                    .iter()
+                    .map(|c| squared_distance(x, c))
+                    .fold(f32::MAX, f32::min)
+            })
+            .collect();
+        let total: f32 = weights.iter().sum();
+        if total <= f32::EPSILON {
+            // Fewer distinct vectors than clusters
+            break;
+        }
+
+        let mut target = rng.gen_range(0.0..total);
+        let index = weights
+            .iter()
+            .position(|w| {
+                target -= w;
+                target <= 0.0
+            })
+            .unwrap_or(vectors.len() - 1);
+        centroids.push(vectors[index]);
+    }
+
+    let mut labels = vec![0; vectors.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut changed = false;
+        for (x, label) in vectors.iter().zip(labels.iter_mut()) {
+            let nearest = centroids
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (i, squared_distance(x, c)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            if nearest != *label {
+                *label = nearest;
+                changed = true;
+            }
+        }
+
+        let mut sums = vec![[0.0f32; DIMENSIONS]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+        for (x, label) in vectors.iter().zip(&labels) {
+            counts[*label] += 1;
+            for (s, v) in sums[*label].iter_mut().zip(x) {
+                *s += v;
+            }
+        }
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            // Empty clusters keep their centroid
+            if count > 0 {
+                *centroid = sum.map(|x| x / count as f32);
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    labels
+}
+
+/// The cluster count picked when none is given, growing slowly with the
+/// size of the library.
+pub fn auto_cluster_count(tracks: usize) -> usize {
+    ((tracks as f64 / 2.0).sqrt().round() as usize).clamp(1, MAX_AUTO_CLUSTERS)
+}
+
+async fn get_dominant_genres(
+    main_db: &DatabaseConnection,
+    points: &[LibraryMapPoint],
+    cluster_count: usize,
+) -> Result<Vec<Option<String>>> {
+    let file_clusters: HashMap<i32, usize> =
+        points.iter().map(|x| (x.file_id, x.cluster)).collect();
+
+    let mut counts: Vec<HashMap<i32, usize>> = vec![HashMap::new(); cluster_count];
+    for x in media_file_genres::Entity::find()
+        .filter(media_file_genres::Column::MediaFileId.is_in(file_clusters.keys().copied()))
+        .all(main_db)
+        .await?
+    {
+        if let Some(cluster) = file_clusters.get(&x.media_file_id) {
+            *counts[*cluster].entry(x.genre_id).or_default() += 1;
+        }
+    }
+
+    let genre_names: HashMap<i32, String> = genres::Entity::find()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x.name))
+        .collect();
+
+    Ok(counts
+        .into_iter()
+        .map(|x| {
+            x.into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .and_then(|(genre_id, _)| genre_names.get(&genre_id).cloned())
+        })
+        .collect())
+}
+
+/// Project every analyzed track onto a 2D map and group them into
+/// `clusters` clusters, picked automatically if `None`.
+pub async fn get_library_map(
+    main_db: &DatabaseConnection,
+    clusters: Option<usize>,
+) -> Result<LibraryMap> {
+    let analyses = media_analysis::Entity::find().all(main_db).await?;
+    if analyses.is_empty() {
+        return Ok(LibraryMap::default());
+    }
+
+    let file_ids: Vec<i32> = analyses.iter().map(|x| x.file_id).collect();
+    let mut vectors: Vec<Vector> = analyses
+        .into_iter()
+        .map(|x| AggregatedAnalysisResult::from(x).into())
+        .collect();
+
+    standardize(&mut vectors);
+    let components = principal_components(&vectors);
+
+    let cluster_count = clusters
+        .unwrap_or_else(|| auto_cluster_count(vectors.len()))
+        .clamp(1, vectors.len());
+    let labels = k_means(&vectors, cluster_count);
+
+    let points: Vec<LibraryMapPoint> = file_ids
+        .into_iter()
+        .zip(&vectors)
+        .zip(labels)
+        .map(|((file_id, x), cluster)| LibraryMapPoint {
+            file_id,
+            x: dot(x, &components[0]),
+            y: dot(x, &components[1]),
+            cluster,
+        })
+        .collect();
+
+    let genres = get_dominant_genres(main_db, &points, cluster_count).await?;
+    let clusters = genres
+        .into_iter()
+        .enumerate()
+        .filter_map(|(id, genre)| {
+            let members: Vec<&LibraryMapPoint> =
+                points.iter().filter(|x| x.cluster == id).collect();
+            if members.is_empty() {
+                return None;
+            }
+
+            let size = members.len();
+            Some(LibraryMapCluster {
+                id,
+                size,
+                x: members.iter().map(|x| x.x).sum::<f32>() / size as f32,
+                y: members.iter().map(|x| x.y).sum::<f32>() / size as f32,
+                genre,
+            })
+        })
+        .collect();
+
+    Ok(LibraryMap { points, clusters })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(values: &[(usize, f32)]) -> Vector {
+        let mut x = [0.0; DIMENSIONS];
+        for (i, v) in values {
+            x[*i] = *v;
+        }
+        x
+    }
+
+    #[test]
+    fn test_principal_components_follow_variance() {
+        // Most of the spread lies along dimension 3, some along dimension 7
+        let vectors: Vec<Vector> = (0..20)
+            .map(|i| {
+                let t = i as f32 - 9.5;
+                vector(&[(3, t * 4.0), (7, if i % 2 == 0 { 1.0 } else { -1.0 })])
+            })
+            .collect();
+
+        let components = principal_components(&vectors);
+        assert!(components[0][3].abs() > 0.99);
+        assert!(components[1][7].abs() > 0.99);
+        assert!(dot(&components[0], &components[1]).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_k_means_separates_groups() {
+        let vectors: Vec<Vector> = (0..10)
+            .map(|i| {
+                let offset = if i < 5 { -10.0 } else { 10.0 };
+                vector(&[(0, offset + i as f32 * 0.1), (1, offset)])
+            })
+            .collect();
+
+        let labels = k_means(&vectors, 2);
+        assert!(labels[..5].iter().all(|x| *x == labels[0]));
+        assert!(labels[5..].iter().all(|x| *x == labels[5]));
+        assert_ne!(labels[0], labels[5]);
+    }
+
+    #[test]
+    fn test_auto_cluster_count() {
+        assert_eq!(auto_cluster_count(1), 1);
+        assert_eq!(auto_cluster_count(50), 5);
+        assert_eq!(auto_cluster_count(100_000), MAX_AUTO_CLUSTERS);
+    }
+}
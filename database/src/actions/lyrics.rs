@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use fsio::FsIo;
+use log::info;
+use sea_orm::{
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QuerySelect,
+};
+use tokio_util::sync::CancellationToken;
+
+use ::lyric::{
+    parser::{find_lyrics_sidecar, parse_lyrics_by_format},
+    sylt::read_sylt_lyrics,
+    types::LyricFile,
+};
+use ::metadata::reader::get_lyrics;
+
+use crate::entities::{media_files, media_lyrics};
+use crate::parallel_media_files_processing;
+
+/// Format of lyrics without any timing.
+const PLAIN_FORMAT: &str = "txt";
+
+/// Lyrics found for a file, together with the sidecar they were read from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileLyrics {
+    pub sidecar: Option<(PathBuf, String)>,
+    pub format: String,
+    pub content: String,
+    pub synced: bool,
+}
+
+/// The sidecar lyric file of an audio file and its modification time.
+fn sidecar_state(file_path: &Path) -> Option<(PathBuf, String)> {
+    let sidecar = find_lyrics_sidecar(file_path)?;
+    let modified = std::fs::metadata(&sidecar)
+        .and_then(|x| x.modified())
+        .ok()?;
+
+    Some((sidecar, DateTime::<Utc>::from(modified).to_rfc3339()))
+}
+
+fn is_synced(format: &str, content: &str) -> bool {
+    parse_lyrics_by_format(format, content)
+        .map(|x| !x.lyrics.is_empty())
+        .unwrap_or(false)
+}
+
+/// Read the lyrics of a file. Synchronized lyrics win over plain ones, and
+/// otherwise `SYLT` tags come first, then `USLT` tags, then sidecar files.
+pub fn read_file_lyrics(file_path: &Path) -> Result<FileLyrics> {
+    let sidecar = sidecar_state(file_path);
+    let mut candidates: Vec<(String, String)> = Vec::new();
+
+    if let Ok(Some(content)) = read_sylt_lyrics(file_path) {
+        candidates.push(("lrc".to_owned(), content));
+    }
+    if let Ok(Some(content)) = get_lyrics(file_path) {
+        candidates.push(("lrc".to_owned(), content));
+    }
+    if let Some((path, _)) = &sidecar {
+        let format = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        candidates.push((format, std::fs::read_to_string(path)?));
+    }
+
+    let candidates: Vec<(String, String, bool)> = candidates
+        .into_iter()
+        .filter(|(_, content)| !content.trim().is_empty())
+        .map(|(format, content)| {
+            let synced = is_synced(&format, &content);
+            (format, content, synced)
+        })
+        .collect();
+
+    let best = candidates
+        .iter()
+        .find(|(_, _, synced)| *synced)
+        .or(candidates.first());
+
+    Ok(match best {
+        Some((format, content, true)) => FileLyrics {
+            sidecar,
+            format: format.clone(),
+            content: content.clone(),
+            synced: true,
+        },
+        Some((_, content, false)) => FileLyrics {
+            sidecar,
+            format: PLAIN_FORMAT.to_owned(),
+            content: content.clone(),
+            synced: false,
+        },
+        None => FileLyrics {
+            sidecar,
+            ..Default::default()
+        },
+    })
+}
+
+/// Files whose lyrics were never read, or whose file or sidecar changed
+/// since.
+async fn get_unindexed_file_ids(main_db: &DatabaseConnection, lib_path: &Path) -> Result<Vec<i32>> {
+    type IndexedState = (String, Option<String>, Option<String>);
+
+    let indexed: HashMap<i32, IndexedState> = media_lyrics::Entity::find()
+        .select_only()
+        .column(media_lyrics::Column::MediaFileId)
+        .column(media_lyrics::Column::FileHash)
+        .column(media_lyrics::Column::SidecarPath)
+        .column(media_lyrics::Column::SidecarModified)
+        .into_tuple::<(i32, String, Option<String>, Option<String>)>()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|(id, file_hash, path, modified)| (id, (file_hash, path, modified)))
+        .collect();
+
+    let files: Vec<(i32, String, String, String)> = media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::Id)
+        .column(media_files::Column::Directory)
+        .column(media_files::Column::FileName)
+        .column(media_files::Column::FileHash)
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    Ok(files
+        .into_iter()
+        .filter(|(id, directory, file_name, file_hash)| {
+            let Some((indexed_hash, indexed_path, indexed_modified)) = indexed.get(id) else {
+                return true;
+            };
+            if indexed_hash != file_hash {
+                return true;
+            }
+
+            // Sidecars can be added, edited or removed without touching the
+            // audio file
+            let sidecar = sidecar_state(&lib_path.join(directory).join(file_name));
+            let sidecar =
+                sidecar.map(|(path, modified)| (path.to_string_lossy().into_owned(), modified));
+            sidecar.as_ref().map(|x| &x.0) != indexed_path.as_ref()
+                || sidecar.as_ref().map(|x| &x.1) != indexed_modified.as_ref()
+        })
+        .map(|(id, _, _, _)| id)
+        .collect())
+}
+
+fn read_lyrics_of_file(
+    lib_path: &Path,
+    file: &media_files::Model,
+    cancel_token: Option<CancellationToken>,
+) -> Result<FileLyrics> {
+    if let Some(token) = &cancel_token
+        && token.is_cancelled()
+    {
+        return Err(anyhow!("Operation cancelled"));
+    }
+
+    read_file_lyrics(&lib_path.join(&file.directory).join(&file.file_name))
+}
+
+async fn upsert_lyrics(
+    main_db: &DatabaseConnection,
+    file: &media_files::Model,
+    lyrics: FileLyrics,
+) -> Result<()> {
+    media_lyrics::Entity::delete_many()
+        .filter(media_lyrics::Column::MediaFileId.eq(file.id))
+        .exec(main_db)
+        .await?;
+
+    let (sidecar_path, sidecar_modified) = match lyrics.sidecar {
+        Some((path, modified)) => (Some(path.to_string_lossy().into_owned()), Some(modified)),
+        None => (None, None),
+    };
+
+    let model = media_lyrics::ActiveModel {
+        media_file_id: ActiveValue::Set(file.id),
+        file_hash: ActiveValue::Set(file.file_hash.clone()),
+        sidecar_path: ActiveValue::Set(sidecar_path),
+        sidecar_modified: ActiveValue::Set(sidecar_modified),
+        format: ActiveValue::Set(lyrics.format),
+        content: ActiveValue::Set(lyrics.content),
+        synced: ActiveValue::Set(lyrics.synced),
+        ..Default::default()
+    };
+    media_lyrics::Entity::insert(model).exec(main_db).await?;
+
+    Ok(())
+}
+
+/// Read the embedded and sidecar lyrics of the files that changed since the
+/// last run. Files without lyrics are recorded too, so they are skipped
+/// until they or their sidecar change.
+pub async fn index_lyrics<F>(
+    fsio: Arc<FsIo>,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    batch_size: usize,
+    progress_callback: F,
+    cancel_token: Option<CancellationToken>,
+) -> Result<usize>
+where
+    F: Fn(usize, usize) + Send + Sync + 'static,
+{
+    info!("Starting lyrics indexing with batch size: {batch_size}");
+
+    let progress_callback = Arc::new(progress_callback);
+
+    let file_ids = get_unindexed_file_ids(main_db, lib_path).await?;
+    let cursor_query = media_files::Entity::find().filter(media_files::Column::Id.is_in(file_ids));
+
+    let lib_path = Arc::new(lib_path.to_path_buf());
+    let node_id = Arc::new(());
+
+    parallel_media_files_processing!(
+        main_db,
+        batch_size,
+        progress_callback,
+        cancel_token,
+        cursor_query,
+        lib_path,
+        fsio,
+        node_id,
+        move |_fsio, file, lib_path, cancel_token| {
+            read_lyrics_of_file(lib_path, file, cancel_token)
+        },
+        |db, file: media_files::Model, _node_id, result: Result<FileLyrics>| async move {
+            match result {
+                Ok(lyrics) => match upsert_lyrics(db, &file, lyrics).await {
+                    Ok(_) => debug!("Indexed lyrics for file: {}", file.id),
+                    Err(e) => error!("Failed to save lyrics: {e:#?}"),
+                },
+                Err(e) => error!("Failed to read lyrics: {e:#?}"),
+            }
+        }
+    )
+}
+
+/// Stored lyrics of a file, `None` when the file hasn't been indexed yet.
+pub async fn get_lyrics_by_file_id(
+    main_db: &DatabaseConnection,
+    file_id: i32,
+) -> Result<Option<media_lyrics::Model>> {
+    Ok(media_lyrics::Entity::find()
+        .filter(media_lyrics::Column::MediaFileId.eq(file_id))
+        .one(main_db)
+        .await?)
+}
+
+/// Parse stored lyrics. Plain lyrics have no lines to show in sync, so they
+/// come out empty.
+pub fn parse_stored_lyrics(lyrics: &media_lyrics::Model) -> Result<LyricFile> {
+    match lyrics.format.as_str() {
+        "" | PLAIN_FORMAT => Ok(LyricFile::new()),
+        format => parse_lyrics_by_format(format, &lyrics.content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    use ::fsio::FsIo;
+
+    use crate::{
+        actions::lyrics::{get_lyrics_by_file_id, index_lyrics, parse_stored_lyrics},
+        test_support::{FakeTrack, connect_test_main_db, seed_tracks},
+    };
+
+    fn loose_track(index: usize, file_name: &str) -> FakeTrack {
+        FakeTrack {
+            file_name: file_name.to_string(),
+            directory: String::new(),
+            ..FakeTrack::nth(index)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_sidecar_lyrics() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+
+        fs::write(lib_dir.path().join("song.flac"), b"not a flac file")?;
+        fs::write(
+            lib_dir.path().join("song.lrc"),
+            "[00:01.00]First line\n[00:03.50]Second line\n",
+        )?;
+        fs::write(lib_dir.path().join("silent.flac"), b"not a flac file")?;
+
+        let file_ids = seed_tracks(
+            &db,
+            &[loose_track(0, "song.flac"), loose_track(1, "silent.flac")],
+        )
+        .await?;
+
+        let fsio = Arc::new(FsIo::new());
+        let indexed =
+            index_lyrics(Arc::clone(&fsio), &db, lib_dir.path(), 2, |_, _| {}, None).await?;
+        assert_eq!(indexed, 2);
+
+        let lyrics = get_lyrics_by_file_id(&db, file_ids[0]).await?.unwrap();
+        assert_eq!(lyrics.format, "lrc");
+        assert!(lyrics.synced);
+        let parsed = parse_stored_lyrics(&lyrics)?;
+        assert_eq!(parsed.lyrics.len(), 2);
+        assert_eq!(parsed.lyrics[1].text, "Second line");
+        let start: i32 = parsed.lyrics[1].start_time.clone().into();
+        assert_eq!(start, 3500);
+
+        // Files without lyrics are recorded, so they aren't read again
+        let silent = get_lyrics_by_file_id(&db, file_ids[1]).await?.unwrap();
+        assert!(!silent.synced);
+        assert!(parse_stored_lyrics(&silent)?.lyrics.is_empty());
+
+        let indexed =
+            index_lyrics(Arc::clone(&fsio), &db, lib_dir.path(), 2, |_, _| {}, None).await?;
+        assert_eq!(indexed, 0);
+
+        // Editing a sidecar is picked up without touching the audio file
+        let sidecar = lib_dir.path().join("song.lrc");
+        fs::write(&sidecar, "[00:02.00]Edited line\n")?;
+        File::options()
+            .write(true)
+            .open(&sidecar)?
+            .set_modified(SystemTime::now() + Duration::from_secs(60))?;
+
+        let indexed = index_lyrics(fsio, &db, lib_dir.path(), 2, |_, _| {}, None).await?;
+        assert_eq!(indexed, 1);
+        let lyrics = get_lyrics_by_file_id(&db, file_ids[0]).await?.unwrap();
+        assert_eq!(parse_stored_lyrics(&lyrics)?.lyrics[0].text, "Edited line");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plain_sidecar_is_not_synced() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+
+        fs::write(lib_dir.path().join("song.flac"), b"not a flac file")?;
+        fs::write(
+            lib_dir.path().join("song.lrc"),
+            "Just some words\nwithout timing\n",
+        )?;
+        let file_id = seed_tracks(&db, &[loose_track(0, "song.flac")]).await?[0];
+
+        let fsio = Arc::new(FsIo::new());
+        index_lyrics(fsio, &db, lib_dir.path(), 2, |_, _| {}, None).await?;
+
+        let lyrics = get_lyrics_by_file_id(&db, file_id).await?.unwrap();
+        assert_eq!(lyrics.format, "txt");
+        assert!(!lyrics.synced);
+        assert!(lyrics.content.starts_with("Just some words"));
+        assert!(parse_stored_lyrics(&lyrics)?.lyrics.is_empty());
+
+        Ok(())
+    }
+}
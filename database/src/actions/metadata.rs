@@ -4,33 +4,48 @@ use std::{
 };
 
 use anyhow::{Context, Result, bail};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 use sea_orm::{
-    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait,
-    entity::prelude::*,
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect,
+    TransactionTrait, entity::prelude::*,
 };
 use tokio_util::sync::CancellationToken;
 
-use ::fsio::{FsIo, FsNode};
+use ::analysis::utils::key::MusicalKey;
+use ::fsio::FsIo;
 use ::metadata::{
+    backend::MetadataBackends,
     describe::{FileDescription, describe_file},
-    reader::get_metadata,
+    reader::normalize_tag_key,
     scanner::AudioScanner,
+    writer::write_tags,
 };
 
+pub use ::metadata::writer::TagChange;
+
 use crate::actions::{
+    checkpoint::{SCAN_TASK, clear_checkpoint, get_checkpoint, save_checkpoint},
     collection::CollectionQueryType,
     cover_art::remove_cover_art_by_file_id,
-    file::get_file_ids_by_descriptions,
+    file::{get_file_by_id, get_file_ids_by_descriptions},
     index::{index_media_files, perform_library_maintenance},
     logging::{LogLevel, insert_log},
+    metadata_languages::{LocalizedField, get_language_variants, get_metadata_languages},
     search::{add_term, remove_term},
+    sources::{
+        assign_files_to_source, available_files_condition, get_unavailable_source_ids,
+        has_source_files, is_root_reachable, refresh_source_availability, source_directory,
+    },
+    stats::{get_track_offsets_by_file_ids, import_tag_rating},
+    summaries::{get_media_summaries, refresh_media_summaries},
+    trash::move_to_trash,
 };
 use crate::entities::{
-    albums, artists, media_file_albums, media_file_artists, media_files, media_metadata,
+    albums, artists, library_sources, media_analysis, media_file_albums, media_file_artists,
+    media_files, media_metadata, media_raw_tags,
 };
 
 use super::cover_art::get_magic_cover_art_id;
@@ -42,12 +57,13 @@ pub struct FileMetadata {
     pub metadata: Vec<(String, String)>,
 }
 
-pub fn read_metadata(fs_node: &FsNode) -> Result<FileMetadata> {
-    match get_metadata(fs_node, None)
-        .with_context(|| format!("Unable to read metadata: {:#?}", fs_node.path))
+pub fn read_metadata(description: &FileDescription) -> Result<FileMetadata> {
+    match description
+        .read_tags()
+        .with_context(|| format!("Unable to read metadata: {:#?}", description.actual_path))
     {
         Ok(metadata) => Ok(FileMetadata {
-            path: fs_node.path.clone(),
+            path: description.raw_node.path.clone(),
             metadata,
         }),
         Err(e) => Err(e),
@@ -128,8 +144,8 @@ pub async fn sync_file_descriptions(
                             description.file_name.clone()
                         );
 
-                        let new_hash = match description.get_crc(fsio).with_context(|| {
-                            format!("Failed to get CRC: {}", description.file_name)
+                        let new_hash = match description.get_hash(fsio).with_context(|| {
+                            format!("Failed to get hash: {}", description.file_name)
                         }) {
                             Ok(hash) => hash,
                             Err(e) => {
@@ -226,13 +242,9 @@ pub async fn sync_file_descriptions(
                                 continue;
                             }
 
-                            let file_metadata =
-                                read_metadata(&description.raw_node).with_context(|| {
-                                    format!(
-                                        "Unable to parse file metadata: {:?}",
-                                        description.rel_path
-                                    )
-                                });
+                            let file_metadata = read_metadata(description).with_context(|| {
+                                format!("Unable to parse file metadata: {:?}", description.rel_path)
+                            });
 
                             match file_metadata {
                                 Ok(x) => {
@@ -283,7 +295,7 @@ pub async fn sync_file_descriptions(
                         description.file_name.clone()
                     );
 
-                    let file_metadata = read_metadata(&description.raw_node).with_context(|| {
+                    let file_metadata = read_metadata(description).with_context(|| {
                         format!(
                             "Unable to parse metadata: {}",
                             description.rel_path.clone().display()
@@ -413,7 +425,7 @@ pub async fn process_files(
                             "File's last modified date has changed, checking hash: {}",
                             description.file_name.clone()
                         );
-                        let new_hash = description.get_crc(fsio)?;
+                        let new_hash = description.get_hash(fsio)?;
                         if existing_file.file_hash == new_hash {
                             // If the hash is the same, update the last modified date
                             debug!(
@@ -448,7 +460,7 @@ pub async fn process_files(
 
                             remove_cover_art_by_file_id(&txn, existing_file.id).await?;
 
-                            let file_metadata = read_metadata(&description.raw_node).with_context(|| {
+                            let file_metadata = read_metadata(description).with_context(|| {
                                 format!("Unable to parse file metadata: {:?}", description.rel_path)
                             });
 
@@ -483,7 +495,7 @@ pub async fn process_files(
                         description.file_name.clone()
                     );
 
-                    let file_metadata = read_metadata(&description.raw_node).with_context(|| {
+                    let file_metadata = read_metadata(description).with_context(|| {
                         format!("Unable to parse file metadata: {:?}", description.rel_path)
                     });
 
@@ -565,8 +577,8 @@ where
     active_model.last_modified = ActiveValue::Set(description.last_modified.clone());
 
     match description
-        .get_crc(fsio)
-        .with_context(|| "Failed to get CRC")
+        .get_hash(fsio)
+        .with_context(|| "Failed to get hash")
     {
         Ok(crc) => active_model.file_hash = ActiveValue::Set(crc),
         Err(e) => {
@@ -599,9 +611,7 @@ where
     }
 
     // Delete existing metadata
-    if let Err(e) = media_metadata::Entity::delete_many()
-        .filter(media_metadata::Column::FileId.eq(existing_file.id))
-        .exec(db)
+    if let Err(e) = delete_file_metadata(db, existing_file.id)
         .await
         .with_context(|| "Failed to delete existing metadata")
     {
@@ -617,39 +627,124 @@ where
     }
 
     // Insert new metadata
-    let new_metadata: Vec<media_metadata::ActiveModel> = metadata
-        .metadata
-        .clone()
+    if let Err(e) = insert_file_metadata(db, existing_file.id, &metadata.metadata)
+        .await
+        .with_context(|| "Failed to insert new metadata while executing updating")
+    {
+        error!("{e:?}");
+        insert_log(
+            db,
+            LogLevel::Error,
+            "actions::metadata::update_file_metadata".to_string(),
+            format!("{e:#?}"),
+        )
+        .await?;
+        return Err(e);
+    }
+
+    import_tag_rating(db, existing_file.id, &metadata.metadata).await?;
+
+    refresh_media_summaries(db, &[existing_file.id]).await
+}
+
+/// Normalize the keys of the tags read from a file, see
+/// [`normalize_tag_key`].
+///
+/// # Returns
+/// The tags without the duplicates left by the normalization, and the
+/// spelling in the file of every key that changed, by normalized key.
+pub fn normalize_metadata(
+    metadata: &[(String, String)],
+) -> (Vec<(String, String)>, HashMap<String, String>) {
+    let mut tags: Vec<(String, String)> = Vec::with_capacity(metadata.len());
+    let mut raw_keys = HashMap::new();
+
+    for (key, value) in metadata {
+        let normalized = normalize_tag_key(key);
+        if normalized != *key {
+            raw_keys.entry(normalized.clone()).or_insert(key.clone());
+        }
+        if !tags.iter().any(|(k, v)| *k == normalized && v == value) {
+            tags.push((normalized, value.clone()));
+        }
+    }
+
+    (tags, raw_keys)
+}
+
+/// Store the tags read from a file under their normalized keys, keeping
+/// the spelling of the keys that changed for writing the tags back.
+async fn insert_file_metadata<E>(db: &E, file_id: i32, metadata: &[(String, String)]) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    let (tags, raw_keys) = normalize_metadata(metadata);
+
+    let new_metadata: Vec<media_metadata::ActiveModel> = tags
         .into_iter()
         .map(|(key, value)| media_metadata::ActiveModel {
-            file_id: ActiveValue::Set(existing_file.id),
+            file_id: ActiveValue::Set(file_id),
             meta_key: ActiveValue::Set(key),
             meta_value: ActiveValue::Set(value),
             ..Default::default()
         })
         .collect();
-
     if !new_metadata.is_empty() {
-        if let Err(e) = media_metadata::Entity::insert_many(new_metadata.clone())
+        media_metadata::Entity::insert_many(new_metadata)
+            .exec(db)
+            .await?;
+    }
+
+    let new_raw_tags: Vec<media_raw_tags::ActiveModel> = raw_keys
+        .into_iter()
+        .map(|(meta_key, raw_key)| media_raw_tags::ActiveModel {
+            media_file_id: ActiveValue::Set(file_id),
+            meta_key: ActiveValue::Set(meta_key),
+            raw_key: ActiveValue::Set(raw_key),
+            ..Default::default()
+        })
+        .collect();
+    if !new_raw_tags.is_empty() {
+        media_raw_tags::Entity::insert_many(new_raw_tags)
             .exec(db)
-            .await
-            .with_context(|| "Failed to insert new metadata while executing updating")
-        {
-            error!("{e:?}");
-            insert_log(
-                db,
-                LogLevel::Error,
-                "actions::metadata::update_file_metadata".to_string(),
-                format!("{e:#?}"),
-            )
             .await?;
-            return Err(e);
-        }
     }
 
     Ok(())
 }
 
+/// Delete the stored tags of a file along with the spelling of their keys.
+async fn delete_file_metadata<E>(db: &E, file_id: i32) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    media_metadata::Entity::delete_many()
+        .filter(media_metadata::Column::FileId.eq(file_id))
+        .exec(db)
+        .await?;
+    media_raw_tags::Entity::delete_many()
+        .filter(media_raw_tags::Column::MediaFileId.eq(file_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// The spelling in the file of the keys of a file that were normalized, by
+/// normalized key.
+pub async fn get_raw_tag_keys<E>(db: &E, file_id: i32) -> Result<HashMap<String, String>>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    Ok(media_raw_tags::Entity::find()
+        .filter(media_raw_tags::Column::MediaFileId.eq(file_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|x| (x.meta_key, x.raw_key))
+        .collect())
+}
+
 pub async fn update_file_codec_information<E>(
     fsio: &FsIo,
     db: &E,
@@ -732,9 +827,9 @@ where
     let (sample_rate, duration_in_seconds) = description.get_codec_information(fsio)?;
 
     description
-        .get_crc(fsio)
-        .with_context(|| format!("Failed to get CRC: {}", description.file_name))?;
-    let new_hash = if let Ok(hash) = description.get_crc(fsio) {
+        .get_hash(fsio)
+        .with_context(|| format!("Failed to get hash: {}", description.file_name))?;
+    let new_hash = if let Ok(hash) = description.get_hash(fsio) {
         hash.clone()
     } else {
         bail!("");
@@ -779,79 +874,176 @@ where
     let file_id = inserted_file.last_insert_id;
 
     // Insert metadata
-    let new_metadata: Vec<media_metadata::ActiveModel> = metadata
-        .metadata
-        .clone()
-        .into_iter()
-        .map(|(key, value)| media_metadata::ActiveModel {
-            file_id: ActiveValue::Set(file_id),
-            meta_key: ActiveValue::Set(key),
-            meta_value: ActiveValue::Set(value),
-            ..Default::default()
-        })
-        .collect();
+    insert_file_metadata(main_db, file_id, &metadata.metadata)
+        .await
+        .with_context(|| format!("Failed to insert new metadata: {}", description.file_name))?;
 
-    if !new_metadata.is_empty() {
-        media_metadata::Entity::insert_many(new_metadata)
-            .exec(main_db)
-            .await
-            .with_context(|| format!("Failed to insert new metadata: {}", description.file_name))?;
-    }
+    import_tag_rating(main_db, file_id, &metadata.metadata).await?;
 
-    Ok(())
+    refresh_media_summaries(main_db, &[file_id]).await
 }
 
 async fn clean_up_database(main_db: &DatabaseConnection, root_path: &Path) -> Result<()> {
-    let db_files = media_files::Entity::find().all(main_db).await?;
+    // A missing file on an unreachable root says nothing about the file, so
+    // reachability is checked again right before anything is deleted
+    let root_reachable = is_root_reachable(root_path, has_source_files(main_db, None).await?);
+    if !root_reachable {
+        warn!("Library root is unreachable, its files are kept: {root_path:?}");
+    }
+    refresh_source_availability(main_db).await?;
+
+    // Files of offline sources are kept until the source is removed
+    let db_files = media_files::Entity::find()
+        .filter(available_files_condition(main_db).await?)
+        .all(main_db)
+        .await?;
 
     for db_file in db_files {
+        if db_file.source_id.is_none() && !root_reachable {
+            continue;
+        }
+
         let full_path = root_path
             .join(PathBuf::from(&db_file.directory))
             .join(PathBuf::from(&db_file.file_name));
         if !full_path.exists() {
             info!("Cleaning {}", full_path.to_str().unwrap_or_default());
-            // Delete the file record
-            media_files::Entity::delete_by_id(db_file.id)
-                .exec(main_db)
-                .await?;
-
-            remove_term(main_db, CollectionQueryType::Track, db_file.id).await?
+            remove_file_record(main_db, db_file.id).await?;
         }
     }
 
     Ok(())
 }
 
-pub fn empty_progress_callback(_processed: usize) {}
+/// Delete the record of a file which no longer exists, along with its
+/// search term. A snapshot is kept in the trash, see `trash`.
+pub async fn remove_file_record(main_db: &DatabaseConnection, file_id: i32) -> Result<()> {
+    move_to_trash(main_db, file_id).await?;
 
-pub async fn scan_audio_library<F>(
+    media_files::Entity::delete_by_id(file_id)
+        .exec(main_db)
+        .await?;
+
+    remove_term(main_db, CollectionQueryType::Track, file_id).await
+}
+
+/// Apply tag changes to the metadata stored for a file, without touching
+/// the file itself.
+pub async fn apply_tag_changes<E>(db: &E, file_id: i32, changes: &[TagChange]) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    for change in changes.iter().map(TagChange::normalized) {
+        media_metadata::Entity::delete_many()
+            .filter(media_metadata::Column::FileId.eq(file_id))
+            .filter(media_metadata::Column::MetaKey.eq(change.key()))
+            .exec(db)
+            .await?;
+
+        if let TagChange::Set { key, value } = &change {
+            let model = media_metadata::ActiveModel {
+                file_id: ActiveValue::Set(file_id),
+                meta_key: ActiveValue::Set(key.clone()),
+                meta_value: ActiveValue::Set(value.clone()),
+                ..Default::default()
+            };
+            media_metadata::Entity::insert(model).exec(db).await?;
+        }
+    }
+
+    refresh_media_summaries(db, &[file_id]).await
+}
+
+/// Write tag changes into a file and its stored metadata. The file is
+/// written first, a file that can't be written leaves the database as it
+/// was.
+pub async fn update_tags(
     fsio: &FsIo,
+    backends: &MetadataBackends,
     main_db: &DatabaseConnection,
     lib_path: &Path,
-    cleanup: bool,
+    file_id: i32,
+    changes: &[TagChange],
+) -> Result<()> {
+    let file = get_file_by_id(main_db, file_id)
+        .await?
+        .with_context(|| format!("File not found: {file_id}"))?;
+    let path = lib_path.join(&file.directory).join(&file.file_name);
+
+    let changes: Vec<TagChange> = changes.iter().map(TagChange::normalized).collect();
+    let raw_keys = get_raw_tag_keys(main_db, file_id).await?;
+    write_tags(&path, &changes, &raw_keys)?;
+
+    // The file changed on disk, record its new state so the next scan
+    // doesn't read it again
+    let node = fsio.canonicalize(&path)?;
+    let mut description = describe_file(&node, &None)?.with_backends(backends);
+    let file_hash = description.get_hash(fsio)?;
+
+    let txn = main_db.begin().await?;
+    apply_tag_changes(&txn, file_id, &changes).await?;
+
+    let file_name = file.file_name.clone();
+    let mut active_model: media_files::ActiveModel = file.into();
+    active_model.last_modified = ActiveValue::Set(description.last_modified.clone());
+    active_model.file_hash = ActiveValue::Set(file_hash);
+    active_model.update(&txn).await?;
+    txn.commit().await?;
+
+    match changes.iter().rev().find(|x| x.key() == "track_title") {
+        Some(TagChange::Set { value, .. }) => {
+            add_term(main_db, CollectionQueryType::Track, file_id, value).await?
+        }
+        Some(TagChange::Remove { .. }) => {
+            add_term(main_db, CollectionQueryType::Track, file_id, &file_name).await?
+        }
+        None => {}
+    }
+
+    index_media_files(main_db, vec![file_id], None).await
+}
+
+pub fn empty_progress_callback(_processed: usize, _total: usize) {}
+
+/// Scan the files `scanner` found under `root_path`, either the library root
+/// or the root of a source. Returns the number of processed files.
+async fn scan_root<F>(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    root_path: &Path,
+    mut scanner: AudioScanner<'_>,
+    source: Option<&library_sources::Model>,
     force: bool,
     progress_callback: F,
-    cancel_token: Option<CancellationToken>,
+    cancel_token: Option<&CancellationToken>,
 ) -> Result<usize>
 where
-    F: Fn(usize) + Send + Sync,
+    F: Fn(usize),
 {
-    let root_path_str = lib_path.to_str().expect("Invalid UTF-8 sequence in path");
-    let mut scanner = AudioScanner::new(fsio, &root_path_str)?;
-
-    info!("Starting audio library scan");
-
-    // Get the total number of files to scan (assuming AudioScanner has this method)
+    let root_path_str = root_path.to_str().expect("Invalid UTF-8 sequence in path");
     let mut processed_files = 0;
 
+    // Skip the files read before an interrupted scan of this root
+    if let Some(checkpoint) = get_checkpoint(main_db, SCAN_TASK).await?
+        && checkpoint.scope == root_path_str
+    {
+        info!(
+            "Resuming scan of {root_path_str} after {} files",
+            checkpoint.position
+        );
+        processed_files = scanner.read_files(checkpoint.position as usize).len();
+        progress_callback(processed_files);
+    }
+
     // Read audio files at a time until no more files are available.
     while !scanner.has_ended() {
         // Check if the cancellation token has been triggered
-        if let Some(ref token) = cancel_token {
-            if token.is_cancelled() {
-                info!("Scan cancelled.");
-                return Ok(processed_files);
-            }
+        if let Some(token) = cancel_token
+            && token.is_cancelled()
+        {
+            info!("Scan cancelled.");
+            return Ok(processed_files);
         }
 
         debug!("Reading metadata for the next 12 files");
@@ -859,10 +1051,17 @@ where
         let mut descriptions: Vec<Option<FileDescription>> = files
             .clone()
             .into_iter()
-            .map(|file| describe_file(&file, &Some(lib_path.to_path_buf())))
-            .map(|result| result.ok())
+            .map(|file| describe_file(&file, &Some(root_path.to_path_buf())))
+            .map(|result| result.ok().map(|x| x.with_backends(backends)))
             .collect();
 
+        // Files of a source are stored with absolute directories
+        if let Some(source) = source {
+            for description in descriptions.iter_mut().flatten() {
+                description.directory = source_directory(&source.path, &description.directory);
+            }
+        }
+
         match sync_file_descriptions(fsio, main_db, &mut descriptions, force)
             .await
             .with_context(|| "Unable to describe files")
@@ -877,7 +1076,11 @@ where
 
         let file_ids = get_file_ids_by_descriptions(main_db, &descriptions).await?;
 
-        match index_media_files(main_db, file_ids, cancel_token.as_ref())
+        if let Some(source) = source {
+            assign_files_to_source(main_db, &file_ids, source.id).await?;
+        }
+
+        match index_media_files(main_db, file_ids, cancel_token)
             .await
             .with_context(|| "Unable to index files")
         {
@@ -887,11 +1090,124 @@ where
 
         // Update the number of processed files
         processed_files += files.len();
+        save_checkpoint(
+            main_db,
+            SCAN_TASK,
+            root_path_str,
+            processed_files as i64,
+            processed_files as i64,
+        )
+        .await?;
 
         // Call the progress callback if it is provided
         progress_callback(processed_files);
     }
 
+    Ok(processed_files)
+}
+
+pub async fn scan_audio_library<F>(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    cleanup: bool,
+    force: bool,
+    progress_callback: F,
+    cancel_token: Option<CancellationToken>,
+) -> Result<usize>
+where
+    F: Fn(usize, usize) + Send + Sync,
+{
+    info!("Starting audio library scan");
+
+    let is_cancelled = || cancel_token.as_ref().is_some_and(|x| x.is_cancelled());
+
+    let root_reachable = is_root_reachable(lib_path, has_source_files(main_db, None).await?);
+    if !root_reachable {
+        warn!("Skipping unreachable library root: {lib_path:?}");
+    }
+
+    let mut sources = Vec::new();
+    for source in refresh_source_availability(main_db).await? {
+        if source.available {
+            sources.push(source);
+        } else {
+            info!("Skipping offline source: {}", source.name);
+        }
+    }
+
+    // Every root is listed before reading any file, so that the progress
+    // has a total to estimate the time left from
+    let lib_path_str = lib_path.to_str().expect("Invalid UTF-8 sequence in path");
+    let root_scanner = if root_reachable {
+        Some(AudioScanner::new(fsio, &lib_path_str)?)
+    } else {
+        None
+    };
+    let source_scanners: Vec<_> = sources
+        .iter()
+        .filter_map(|source| match AudioScanner::new(fsio, &source.path) {
+            Ok(scanner) => Some((source, scanner)),
+            Err(e) => {
+                error!("Unable to scan source {}: {e:#?}", source.name);
+                None
+            }
+        })
+        .collect();
+    let total_files: usize = root_scanner
+        .iter()
+        .chain(source_scanners.iter().map(|(_, x)| x))
+        .map(|x| x.total())
+        .sum();
+
+    let mut processed_files = 0;
+    if let Some(scanner) = root_scanner {
+        processed_files = scan_root(
+            fsio,
+            backends,
+            main_db,
+            lib_path,
+            scanner,
+            None,
+            force,
+            |x| progress_callback(x, total_files),
+            cancel_token.as_ref(),
+        )
+        .await?;
+    }
+
+    for (source, scanner) in source_scanners {
+        if is_cancelled() {
+            return Ok(processed_files);
+        }
+
+        info!("Scanning source: {}", source.name);
+        let scanned_files = scan_root(
+            fsio,
+            backends,
+            main_db,
+            Path::new(&source.path),
+            scanner,
+            Some(source),
+            force,
+            |x| progress_callback(processed_files + x, total_files),
+            cancel_token.as_ref(),
+        )
+        .await;
+
+        match scanned_files {
+            Ok(scanned_files) => processed_files += scanned_files,
+            Err(e) => error!("Unable to scan source {}: {e:#?}", source.name),
+        }
+    }
+
+    if is_cancelled() {
+        return Ok(processed_files);
+    }
+
+    clear_checkpoint(main_db, SCAN_TASK).await?;
+
     if cleanup {
         info!("Starting cleanup process.");
         match clean_up_database(main_db, lib_path)
@@ -931,6 +1247,10 @@ pub struct MetadataSummary {
     pub track_number: i32,
     pub duration: f64,
     pub cover_art_id: Option<i32>,
+    /// Estimated musical key, e.g. `C# minor`, `None` if not analyzed.
+    pub key: Option<String>,
+    /// Whether the source of the file is reachable, see `library_sources`.
+    pub available: bool,
 }
 
 pub async fn get_metadata_summary_by_files(
@@ -941,74 +1261,76 @@ pub async fn get_metadata_summary_by_files(
     let file_ids: Vec<i32> = files.iter().map(|file| file.id).collect();
     let magic_cover_art_id = get_magic_cover_art_id(db).await;
 
-    // Fetch all metadata entries for the given file IDs
-    let metadata_entries: Vec<media_metadata::Model> = media_metadata::Entity::find()
-        .filter(media_metadata::Column::FileId.is_in(file_ids.clone()).and(
-            media_metadata::Column::MetaKey.is_in([
-                "artist",
-                "album",
-                "genre",
-                "track_title",
-                "disc_number",
-                "track_number",
-            ]),
-        ))
+    let summaries = get_media_summaries(db, &file_ids).await?;
+
+    // Tags in the preferred languages replace the original ones
+    let languages = get_metadata_languages(db).await?;
+    let variants_map = get_language_variants(db, &file_ids, &languages).await?;
+
+    // Fetch the estimated keys of analyzed files
+    let key_map: HashMap<i32, String> = media_analysis::Entity::find()
+        .select_only()
+        .column(media_analysis::Column::FileId)
+        .column(media_analysis::Column::Key)
+        .column(media_analysis::Column::Mode)
+        .filter(media_analysis::Column::FileId.is_in(file_ids.clone()))
+        .into_tuple::<(i32, Option<i32>, Option<i32>)>()
         .all(db)
-        .await?;
+        .await?
+        .into_iter()
+        .filter_map(|(file_id, key, mode)| {
+            MusicalKey::from_parts(key?, mode?).map(|x| (file_id, x.to_string()))
+        })
+        .collect();
 
-    // Create a map for metadata entries
-    let mut metadata_map: HashMap<i32, HashMap<String, String>> = HashMap::new();
-    for entry in metadata_entries {
-        metadata_map
-            .entry(entry.file_id)
-            .or_default()
-            .insert(entry.meta_key, entry.meta_value);
-    }
+    let unavailable_sources = get_unavailable_source_ids(db).await?;
+
+    // Trimmed files report the length that is actually played
+    let offsets_map = get_track_offsets_by_file_ids(db, &file_ids).await?;
 
     // Prepare the final result
     let mut results: Vec<MetadataSummary> = Vec::new();
     for file in files {
         let file_id = file.id;
-        let _metadata: HashMap<String, String> = HashMap::new();
-        let metadata = metadata_map.get(&file_id).unwrap_or(&_metadata);
-        let duration = file.duration;
+        let metadata = summaries.get(&file_id);
+        let duration = file
+            .duration
+            .to_f64()
+            .expect("Unable to convert track duration");
+        let duration = match offsets_map.get(&file_id) {
+            Some(offsets) => offsets.trimmed_duration(duration),
+            None => duration,
+        };
 
         let cover_art_id = file.cover_art_id;
-
-        let parsed_disk_number = metadata
-            .get("disc_number")
-            .and_then(|s| extract_number(s))
-            .unwrap_or(0);
-
-        let parsed_track_number = metadata
-            .get("track_number")
-            .and_then(|s| extract_number(s))
-            .unwrap_or(0);
-
-        let track_number = parsed_disk_number * 1000 + parsed_track_number;
+        let variants = variants_map.get(&file_id);
+        let variant = |field: LocalizedField| variants.and_then(|x| x.get(&field)).cloned();
 
         let summary = MetadataSummary {
             id: file_id,
             directory: file.directory.clone(),
             file_name: file.file_name.clone(),
-            artist: metadata.get("artist").cloned().unwrap_or_default(),
-            album: metadata.get("album").cloned().unwrap_or_default(),
-            genre: metadata
-                .get("genre")
-                .cloned()
-                .unwrap_or_default()
-                .to_uppercase(),
-            title: metadata
-                .get("track_title")
-                .cloned()
+            artist: variant(LocalizedField::Artist)
+                .or_else(|| metadata.map(|x| x.artist.clone()))
+                .unwrap_or_default(),
+            album: variant(LocalizedField::Album)
+                .or_else(|| metadata.map(|x| x.album.clone()))
+                .unwrap_or_default(),
+            genre: metadata.map(|x| x.genre.to_uppercase()).unwrap_or_default(),
+            title: variant(LocalizedField::Title)
+                .or_else(|| metadata.and_then(|x| x.title.clone()))
                 .unwrap_or(file.file_name.clone()),
-            track_number,
-            duration: duration.to_f64().expect("Unable to convert track duration"),
+            track_number: metadata.map(|x| x.track_number).unwrap_or(0),
+            duration,
             cover_art_id: if cover_art_id == magic_cover_art_id {
                 None
             } else {
                 cover_art_id
             },
+            key: key_map.get(&file_id).cloned(),
+            available: file
+                .source_id
+                .is_none_or(|x| !unavailable_sources.contains(&x)),
         };
 
         results.push(summary);
@@ -1086,3 +1408,342 @@ pub async fn get_parsed_file_by_id(
 
     Ok((file, artists, album))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::Path,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use anyhow::{Result, bail};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+    use tempfile::tempdir;
+
+    use ::fsio::{FsIo, FsNode};
+    use ::metadata::{
+        backend::{FileHasher, MetadataBackends, PartialHasher, TagReader, parse_ffprobe_tags},
+        describe::describe_file,
+    };
+
+    use crate::{
+        actions::{
+            file::get_files_by_glob,
+            metadata::{
+                TagChange, apply_tag_changes, normalize_metadata, read_metadata,
+                update_file_metadata, update_tags,
+            },
+        },
+        entities::{media_files, media_metadata},
+        test_support::{FakeTrack, connect_test_main_db, seed_fake_tracks, seed_tracks},
+    };
+
+    #[derive(Default)]
+    struct FakeHasher {
+        calls: AtomicUsize,
+    }
+
+    impl FileHasher for FakeHasher {
+        fn hash(&self, _fsio: &FsIo, path: &Path) -> Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match path.file_name().and_then(|x| x.to_str()) {
+                Some(name) => Ok(format!("fake-{name}")),
+                None => bail!("No file name"),
+            }
+        }
+    }
+
+    struct FakeTagReader;
+
+    impl TagReader for FakeTagReader {
+        fn read_tags(&self, fs_node: &FsNode) -> Result<Vec<(String, String)>> {
+            Ok(vec![
+                ("track_title".to_string(), fs_node.filename.clone()),
+                ("artist".to_string(), "Fake Artist".to_string()),
+            ])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_injected_backends_are_used() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let fsio = FsIo::new();
+        let lib_dir = tempdir()?;
+        let path = lib_dir.path().join("song.flac");
+        fs::write(&path, "not really audio")?;
+
+        let hasher = Arc::new(FakeHasher::default());
+        let backends = MetadataBackends::new(hasher.clone(), Arc::new(FakeTagReader));
+
+        let node = fsio.canonicalize(&path)?;
+        let lib_path = Some(fsio.canonicalize_path(lib_dir.path())?);
+        let mut description = describe_file(&node, &lib_path)?.with_backends(&backends);
+
+        // The hash is computed once and kept
+        assert_eq!(description.get_hash(&fsio)?, "fake-song.flac");
+        assert_eq!(description.get_hash(&fsio)?, "fake-song.flac");
+        assert_eq!(hasher.calls.load(Ordering::SeqCst), 1);
+
+        let metadata = read_metadata(&description)?;
+        assert_eq!(
+            metadata.metadata[0],
+            ("track_title".to_string(), "song.flac".to_string())
+        );
+
+        let file_id = seed_fake_tracks(&db, 1).await?[0];
+        let existing_file = media_files::Entity::find_by_id(file_id)
+            .one(&db)
+            .await?
+            .unwrap();
+        update_file_metadata(&fsio, &db, &existing_file, &mut description, &metadata).await?;
+
+        let file = media_files::Entity::find_by_id(file_id)
+            .one(&db)
+            .await?
+            .unwrap();
+        assert_eq!(file.file_hash, "fake-song.flac");
+
+        let artists: Vec<String> = media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.eq(file_id))
+            .filter(media_metadata::Column::MetaKey.eq("artist"))
+            .all(&db)
+            .await?
+            .into_iter()
+            .map(|x| x.meta_value)
+            .collect();
+        assert_eq!(artists, vec!["Fake Artist"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_hasher() -> Result<()> {
+        let fsio = FsIo::new();
+        let dir = tempdir()?;
+
+        let mut content = vec![1u8; 1024 * 512];
+        let original = dir.path().join("original.flac");
+        fs::write(&original, &content)?;
+
+        // Edits in the middle go unnoticed, edits at either end don't
+        content[1024 * 256] = 2;
+        let middle = dir.path().join("middle.flac");
+        fs::write(&middle, &content)?;
+
+        let last = content.len() - 1;
+        content[last] = 2;
+        let tail = dir.path().join("tail.flac");
+        fs::write(&tail, &content)?;
+
+        let hash = PartialHasher.hash(&fsio, &original)?;
+        assert_eq!(PartialHasher.hash(&fsio, &middle)?, hash);
+        assert_ne!(PartialHasher.hash(&fsio, &tail)?, hash);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_ffprobe_tags() -> Result<()> {
+        let output = br#"{
+            "format": {
+                "filename": "song.opus",
+                "tags": {
+                    "TITLE": "Song",
+                    "ARTIST": "Artist",
+                    "track": "3/12",
+                    "encoder": "Lavf60.3.100"
+                }
+            }
+        }"#;
+
+        let mut tags = parse_ffprobe_tags(output)?;
+        tags.sort();
+        assert_eq!(
+            tags,
+            vec![
+                ("artist".to_string(), "Artist".to_string()),
+                ("track_number".to_string(), "3/12".to_string()),
+                ("track_title".to_string(), "Song".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    async fn get_tag(
+        db: &sea_orm::DatabaseConnection,
+        file_id: i32,
+        key: &str,
+    ) -> Result<Vec<String>> {
+        Ok(media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.eq(file_id))
+            .filter(media_metadata::Column::MetaKey.eq(key))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|x| x.meta_value)
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn test_apply_tag_changes() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_id = seed_fake_tracks(&db, 1).await?[0];
+
+        apply_tag_changes(
+            &db,
+            file_id,
+            &[
+                TagChange::Set {
+                    key: "track_title".to_string(),
+                    value: "Renamed".to_string(),
+                },
+                TagChange::Set {
+                    key: "album_artist".to_string(),
+                    value: "Various Artists".to_string(),
+                },
+                TagChange::Remove {
+                    key: "genre".to_string(),
+                },
+            ],
+        )
+        .await?;
+
+        assert_eq!(get_tag(&db, file_id, "track_title").await?, vec!["Renamed"]);
+        assert_eq!(
+            get_tag(&db, file_id, "album_artist").await?,
+            vec!["Various Artists"]
+        );
+        assert!(get_tag(&db, file_id, "genre").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unwritable_file_keeps_database() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let file_id = seed_fake_tracks(&db, 1).await?[0];
+
+        // The seeded file doesn't exist on disk
+        let result = update_tags(
+            &FsIo::new(),
+            &MetadataBackends::default(),
+            &db,
+            lib_dir.path(),
+            file_id,
+            &[TagChange::Set {
+                key: "track_title".to_string(),
+                value: "Renamed".to_string(),
+            }],
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(get_tag(&db, file_id, "track_title").await?, vec!["Track 0"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_files_by_glob() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        let mut root_track = FakeTrack::nth(10);
+        root_track.directory = String::new();
+        let mut tracks: Vec<FakeTrack> = (0..3).map(FakeTrack::nth).collect();
+        tracks.push(root_track);
+        let file_ids = seed_tracks(&db, &tracks).await?;
+
+        let pattern = format!("{}/*", tracks[0].directory);
+        let ids: Vec<i32> = get_files_by_glob(&db, &pattern)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        let expected: Vec<i32> = tracks
+            .iter()
+            .zip(&file_ids)
+            .filter(|(x, _)| x.directory == tracks[0].directory)
+            .map(|(_, id)| *id)
+            .collect();
+        assert_eq!(ids, expected);
+
+        // Files at the root have no leading slash
+        let root = get_files_by_glob(&db, "0010.flac").await?;
+        assert_eq!(root.len(), 1);
+        assert_eq!(root[0].id, file_ids[3]);
+
+        assert!(get_files_by_glob(&db, "*.mp3").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_metadata_merges_spellings() {
+        let metadata: Vec<(String, String)> = [
+            ("ALBUM", "Album 0"),
+            ("album", "Album 0"),
+            ("Artist", "Artist A"),
+            ("artist", "Artist B"),
+            ("REPLAYGAIN REFERENCE-LOUDNESS", "89.0 dB"),
+            ("track_title", "Track 0"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let (tags, raw_keys) = normalize_metadata(&metadata);
+
+        let expected: Vec<(String, String)> = [
+            ("album", "Album 0"),
+            ("artist", "Artist A"),
+            ("artist", "Artist B"),
+            ("replaygain_reference_loudness", "89.0 dB"),
+            ("track_title", "Track 0"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(tags, expected);
+
+        assert_eq!(raw_keys.len(), 3);
+        assert_eq!(raw_keys["album"], "ALBUM");
+        assert_eq!(raw_keys["artist"], "Artist");
+        assert_eq!(
+            raw_keys["replaygain_reference_loudness"],
+            "REPLAYGAIN REFERENCE-LOUDNESS"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tag_changes_use_normalized_keys() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_id = seed_fake_tracks(&db, 1).await?[0];
+
+        apply_tag_changes(
+            &db,
+            file_id,
+            &[
+                TagChange::Set {
+                    key: "TRACK_TITLE".to_string(),
+                    value: "Renamed".to_string(),
+                },
+                TagChange::Remove {
+                    key: "Genre".to_string(),
+                },
+            ],
+        )
+        .await?;
+
+        assert_eq!(get_tag(&db, file_id, "track_title").await?, vec!["Renamed"]);
+        assert!(get_tag(&db, file_id, "TRACK_TITLE").await?.is_empty());
+        assert!(get_tag(&db, file_id, "genre").await?.is_empty());
+
+        Ok(())
+    }
+}
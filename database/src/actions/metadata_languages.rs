@@ -0,0 +1,270 @@
+//! Display languages of multi-language tags.
+//!
+//! Files may carry the same tag in several languages next to the original,
+//! like `TITLE-ja-Latn` or `TXXX:TITLE (romaji)` beside `TITLE`. Keys are
+//! normalized when stored, so those end up as `title_ja_latn` and
+//! `title_(romaji)`. The user picks the languages to show in order, the
+//! first variant a file has replaces the original in its summary, and files
+//! without any keep the original.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+use crate::actions::settings::{METADATA_LANGUAGES_KEY, get_setting, set_setting};
+use crate::entities::media_metadata;
+
+/// How many files are looked up per query.
+const LANGUAGE_BATCH_SIZE: usize = 500;
+
+/// The summarized fields that can be shown in another language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LocalizedField {
+    Title,
+    Artist,
+    Album,
+}
+
+impl LocalizedField {
+    /// The keys whose variants replace the field, `TXXX:TITLE` frames are
+    /// stored as `title` rather than `track_title`.
+    fn base_keys(&self) -> &'static [&'static str] {
+        match self {
+            LocalizedField::Title => &["track_title", "title"],
+            LocalizedField::Artist => &["artist"],
+            LocalizedField::Album => &["album"],
+        }
+    }
+}
+
+const LOCALIZED_FIELDS: [LocalizedField; 3] = [
+    LocalizedField::Title,
+    LocalizedField::Artist,
+    LocalizedField::Album,
+];
+
+/// Normalize a language the way tag keys are, `ja-Latn` becomes `ja_latn`.
+pub fn normalize_language(language: &str) -> String {
+    language
+        .trim()
+        .trim_matches(|x| x == '(' || x == ')')
+        .to_ascii_lowercase()
+        .replace([' ', '-'], "_")
+}
+
+/// The keys a variant of `base` in `language` may be stored with.
+fn variant_keys(base: &str, language: &str) -> [String; 3] {
+    [
+        format!("{base}_{language}"),
+        format!("{base}:{language}"),
+        format!("{base}_({language})"),
+    ]
+}
+
+/// The preferred display languages, most preferred first. Empty if the
+/// original tags are shown.
+pub async fn get_metadata_languages(main_db: &DatabaseConnection) -> Result<Vec<String>> {
+    Ok(get_setting(main_db, METADATA_LANGUAGES_KEY)
+        .await?
+        .map(|x| parse_metadata_languages(&x))
+        .unwrap_or_default())
+}
+
+/// Parse a comma separated list of languages, dropping empty and repeated
+/// ones.
+pub fn parse_metadata_languages(value: &str) -> Vec<String> {
+    let mut languages: Vec<String> = vec![];
+    for language in value.split(',').map(normalize_language) {
+        if !language.is_empty() && !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+
+    languages
+}
+
+/// Save the preferred display languages, an empty list shows the original
+/// tags again.
+///
+/// # Returns
+/// * `Result<Vec<String>>` - The languages as saved.
+pub async fn set_metadata_languages<S: AsRef<str>>(
+    main_db: &DatabaseConnection,
+    languages: &[S],
+) -> Result<Vec<String>> {
+    let value = languages
+        .iter()
+        .map(|x| x.as_ref())
+        .collect::<Vec<_>>()
+        .join(",");
+    let languages = parse_metadata_languages(&value);
+
+    let value = if languages.is_empty() {
+        None
+    } else {
+        Some(languages.join(","))
+    };
+    set_setting(main_db, METADATA_LANGUAGES_KEY, value.as_deref()).await?;
+
+    Ok(languages)
+}
+
+/// Pick the variant of every field in the first of `languages` the file has.
+pub fn select_language_variants(
+    metadata: &HashMap<String, String>,
+    languages: &[String],
+) -> HashMap<LocalizedField, String> {
+    let mut selected = HashMap::new();
+    for field in LOCALIZED_FIELDS {
+        let value = languages.iter().find_map(|language| {
+            field
+                .base_keys()
+                .iter()
+                .flat_map(|base| variant_keys(base, language))
+                .find_map(|key| metadata.get(&key))
+                .filter(|x| !x.trim().is_empty())
+        });
+        if let Some(value) = value {
+            selected.insert(field, value.clone());
+        }
+    }
+
+    selected
+}
+
+/// The variants of the files in the preferred languages, files without any
+/// are left out.
+pub async fn get_language_variants(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+    languages: &[String],
+) -> Result<HashMap<i32, HashMap<LocalizedField, String>>> {
+    if languages.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let keys: Vec<String> = LOCALIZED_FIELDS
+        .iter()
+        .flat_map(|x| x.base_keys())
+        .flat_map(|base| languages.iter().flat_map(|x| variant_keys(base, x)))
+        .collect();
+
+    let mut metadata_map: HashMap<i32, HashMap<String, String>> = HashMap::new();
+    for chunk in file_ids.chunks(LANGUAGE_BATCH_SIZE) {
+        let entries = media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.is_in(chunk.to_vec()))
+            .filter(media_metadata::Column::MetaKey.is_in(keys.clone()))
+            .all(main_db)
+            .await?;
+
+        for entry in entries {
+            metadata_map
+                .entry(entry.file_id)
+                .or_default()
+                .insert(entry.meta_key, entry.meta_value);
+        }
+    }
+
+    Ok(metadata_map
+        .into_iter()
+        .map(|(id, metadata)| (id, select_language_variants(&metadata, languages)))
+        .filter(|(_, variants)| !variants.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use sea_orm::{ActiveValue, EntityTrait};
+
+    use crate::{
+        actions::{
+            metadata::{MetadataSummary, get_metadata_summary_by_files},
+            metadata_languages::{
+                LocalizedField, get_metadata_languages, parse_metadata_languages,
+                select_language_variants, set_metadata_languages,
+            },
+        },
+        entities::{media_files, media_metadata},
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[test]
+    fn test_parse_metadata_languages() {
+        assert_eq!(
+            parse_metadata_languages("ja-Latn, (romaji),,ja_latn, EN"),
+            vec!["ja_latn", "romaji", "en"]
+        );
+        assert!(parse_metadata_languages(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_select_language_variants() {
+        let metadata: HashMap<String, String> = [
+            ("track_title", "残酷な天使のテーゼ"),
+            ("track_title_ja_latn", "Zankoku na Tenshi no Te-ze"),
+            ("artist:en", "Yoko Takahashi"),
+            ("album_(romaji)", ""),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect();
+
+        let languages = parse_metadata_languages("romaji,ja-Latn,en");
+        let selected = select_language_variants(&metadata, &languages);
+
+        assert_eq!(
+            selected.get(&LocalizedField::Title).map(String::as_str),
+            Some("Zankoku na Tenshi no Te-ze")
+        );
+        assert_eq!(
+            selected.get(&LocalizedField::Artist).map(String::as_str),
+            Some("Yoko Takahashi")
+        );
+        // Empty variants don't replace anything
+        assert!(!selected.contains_key(&LocalizedField::Album));
+    }
+
+    #[tokio::test]
+    async fn test_summary_uses_preferred_languages() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        media_metadata::Entity::insert(media_metadata::ActiveModel {
+            file_id: ActiveValue::Set(file_ids[0]),
+            meta_key: ActiveValue::Set("track_title_ja_latn".to_owned()),
+            meta_value: ActiveValue::Set("Romanized".to_owned()),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await?;
+
+        let title_of = |summaries: &[MetadataSummary], id: i32| {
+            summaries.iter().find(|x| x.id == id).unwrap().title.clone()
+        };
+
+        // Nothing changes until a language is picked
+        let files = media_files::Entity::find().all(&db).await?;
+        let summaries = get_metadata_summary_by_files(&db, files.clone()).await?;
+        assert_eq!(title_of(&summaries, file_ids[0]), "Track 0");
+
+        let saved = set_metadata_languages(&db, &["ja-Latn", "en"]).await?;
+        assert_eq!(saved, vec!["ja_latn", "en"]);
+        assert_eq!(get_metadata_languages(&db).await?, saved);
+
+        let summaries = get_metadata_summary_by_files(&db, files.clone()).await?;
+        assert_eq!(title_of(&summaries, file_ids[0]), "Romanized");
+        // Files without a variant keep the original
+        assert_eq!(title_of(&summaries, file_ids[1]), "Track 1");
+
+        set_metadata_languages::<&str>(&db, &[]).await?;
+        assert!(get_metadata_languages(&db).await?.is_empty());
+        let summaries = get_metadata_summary_by_files(&db, files).await?;
+        assert_eq!(title_of(&summaries, file_ids[0]), "Track 0");
+
+        Ok(())
+    }
+}
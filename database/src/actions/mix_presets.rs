@@ -0,0 +1,218 @@
+//! Named mix parameters, so a mix can be asked for by a name like `Focus`
+//! instead of writing the parameters by hand.
+//!
+//! The built-in presets ship with the app, the user can add more or replace
+//! a built-in one by saving a preset under its name. Presets are read from
+//! the settings every time they are used, so changes apply right away.
+
+use anyhow::{Context, Result, bail};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::mixes::parse_mix_parameters;
+use crate::actions::settings::{MIX_PRESETS_KEY, get_setting, set_setting};
+
+/// The presets shipped with the app, as `(name, parameters)`.
+pub const BUILTIN_MIX_PRESETS: [(&str, &str); 3] = [
+    (
+        "Focus",
+        "lib::all(true);filter::analyzed(true);filter::tempo(60-100);sort::skipped(true)",
+    ),
+    (
+        "Party",
+        "lib::all(true);filter::analyzed(true);filter::tempo(115-140);sort::playedthrough(false)",
+    ),
+    (
+        "Sad hours",
+        "lib::all(true);filter::analyzed(true);filter::tempo(50-85);filter::liked(true)",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MixPreset {
+    pub name: String,
+    /// Mix parameters like `lib::all(true);filter::tempo(60-100)`.
+    pub parameters: String,
+    /// Shipped with the app rather than saved by the user.
+    #[serde(skip)]
+    pub builtin: bool,
+}
+
+impl MixPreset {
+    /// The parameters of the preset as `(operator, parameter)` queries.
+    pub fn queries(&self) -> Vec<(String, String)> {
+        parse_mix_parameters(&self.parameters)
+    }
+}
+
+fn builtin_mix_presets() -> impl Iterator<Item = MixPreset> {
+    BUILTIN_MIX_PRESETS
+        .iter()
+        .map(|(name, parameters)| MixPreset {
+            name: name.to_string(),
+            parameters: parameters.to_string(),
+            builtin: true,
+        })
+}
+
+/// The presets saved by the user.
+pub async fn get_user_mix_presets(main_db: &DatabaseConnection) -> Result<Vec<MixPreset>> {
+    let Some(value) = get_setting(main_db, MIX_PRESETS_KEY).await? else {
+        return Ok(vec![]);
+    };
+
+    serde_json::from_str(&value).with_context(|| "Failed to parse the saved mix presets")
+}
+
+/// Every preset, the built-in ones first. A preset saved by the user under
+/// the name of a built-in one takes its place.
+pub async fn get_mix_presets(main_db: &DatabaseConnection) -> Result<Vec<MixPreset>> {
+    let mut user_presets = get_user_mix_presets(main_db).await?;
+
+    let mut presets: Vec<MixPreset> = builtin_mix_presets()
+        .map(|builtin| {
+            match user_presets
+                .iter()
+                .position(|x| x.name.eq_ignore_ascii_case(&builtin.name))
+            {
+                Some(index) => user_presets.remove(index),
+                None => builtin,
+            }
+        })
+        .collect();
+    presets.append(&mut user_presets);
+
+    Ok(presets)
+}
+
+/// Find a preset by its name, ignoring the case.
+pub async fn get_mix_preset(main_db: &DatabaseConnection, name: &str) -> Result<Option<MixPreset>> {
+    Ok(get_mix_presets(main_db)
+        .await?
+        .into_iter()
+        .find(|x| x.name.eq_ignore_ascii_case(name.trim())))
+}
+
+/// Save `parameters` as the preset named `name`, replacing the preset of
+/// the user with the same name, or remove it if `parameters` is `None`.
+/// Removing the preset that replaced a built-in one brings the built-in
+/// preset back.
+pub async fn set_mix_preset(
+    main_db: &DatabaseConnection,
+    name: &str,
+    parameters: Option<&str>,
+) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("The name of a mix preset can't be empty");
+    }
+
+    let mut presets = get_user_mix_presets(main_db).await?;
+    let index = presets
+        .iter()
+        .position(|x| x.name.eq_ignore_ascii_case(name));
+
+    match (parameters, index) {
+        (Some(parameters), _) if parse_mix_parameters(parameters).is_empty() => {
+            bail!("No valid mix parameters in '{parameters}'");
+        }
+        (Some(parameters), Some(index)) => {
+            presets[index].parameters = parameters.trim().to_owned();
+        }
+        (Some(parameters), None) => presets.push(MixPreset {
+            name: name.to_owned(),
+            parameters: parameters.trim().to_owned(),
+            builtin: false,
+        }),
+        (None, Some(index)) => {
+            presets.remove(index);
+        }
+        (None, None) => bail!("No saved mix preset named '{name}'"),
+    }
+
+    let value = if presets.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&presets)?)
+    };
+    set_setting(main_db, MIX_PRESETS_KEY, value.as_deref()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        actions::mix_presets::{
+            BUILTIN_MIX_PRESETS, get_mix_preset, get_mix_presets, set_mix_preset,
+        },
+        test_support::connect_test_main_db,
+    };
+
+    #[tokio::test]
+    async fn test_builtin_presets_parse() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        let presets = get_mix_presets(&db).await?;
+        assert_eq!(presets.len(), BUILTIN_MIX_PRESETS.len());
+        for preset in presets {
+            assert!(preset.builtin);
+            assert!(
+                preset
+                    .queries()
+                    .iter()
+                    .any(|(operator, _)| operator == "filter::tempo"),
+                "preset: {}",
+                preset.name
+            );
+        }
+
+        let preset = get_mix_preset(&db, "sad HOURS").await?.unwrap();
+        assert_eq!(preset.name, "Sad hours");
+        assert_eq!(get_mix_preset(&db, "Nope").await?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_user_presets() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        set_mix_preset(
+            &db,
+            "Running",
+            Some("lib::all(true);filter::tempo(160-180)"),
+        )
+        .await?;
+        set_mix_preset(&db, "focus", Some("lib::all(true);filter::liked(true)")).await?;
+        assert!(
+            set_mix_preset(&db, "Broken", Some("not a parameter"))
+                .await
+                .is_err()
+        );
+
+        // The saved preset takes the place of the built-in one
+        let presets = get_mix_presets(&db).await?;
+        assert_eq!(presets.len(), BUILTIN_MIX_PRESETS.len() + 1);
+        assert_eq!(presets[0].name, "focus");
+        assert!(!presets[0].builtin);
+        assert_eq!(
+            presets[0].queries(),
+            vec![
+                ("lib::all".to_owned(), "true".to_owned()),
+                ("filter::liked".to_owned(), "true".to_owned()),
+            ]
+        );
+        assert_eq!(presets.last().unwrap().name, "Running");
+
+        // Removing it brings the built-in preset back
+        set_mix_preset(&db, "Focus", None).await?;
+        let preset = get_mix_preset(&db, "focus").await?.unwrap();
+        assert!(preset.builtin);
+        assert_eq!(preset.parameters, BUILTIN_MIX_PRESETS[0].1);
+
+        assert!(set_mix_preset(&db, "Focus", None).await.is_err());
+
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result, bail};
 use async_trait::async_trait;
 use chrono::Utc;
 use log::warn;
@@ -14,7 +14,9 @@ use sea_orm::{
     ActiveValue, ColumnTrait, EntityTrait, JoinType, Order, QueryFilter, QueryOrder, QuerySelect,
     QueryTrait, TransactionTrait,
 };
+use uuid::Uuid;
 
+use crate::actions::analysis::ANALYSIS_VECTOR_SIZE;
 use crate::actions::analysis::get_analyze_count;
 use crate::actions::analysis::get_percentile_analysis_result;
 use crate::actions::cover_art::get_magic_cover_art_id;
@@ -24,7 +26,7 @@ use crate::entities::media_file_fingerprint;
 use crate::entities::media_file_genres;
 use crate::entities::{
     media_analysis, media_file_albums, media_file_artists, media_file_playlists, media_file_stats,
-    media_files, mix_queries, mixes,
+    media_files, mix_queries, mixes, play_history,
 };
 
 use super::analysis::get_centralized_analysis_result;
@@ -32,7 +34,11 @@ use super::collection::CollectionQuery;
 use super::collection::CollectionQueryListMode;
 use super::collection::CollectionQueryType;
 use super::file::get_files_by_ids;
-use super::recommendation::get_recommendation_by_parameter;
+use super::playlists::smart_playlist_subquery;
+use super::recommendation::{
+    LIKED_ONLY_OVERSAMPLING, get_recommendation_by_parameter, retain_liked_recommendations,
+};
+use super::stats::{get_hidden_files, hidden_files_subquery};
 use super::utils::CollectionDefinition;
 
 impl CollectionDefinition for mixes::Entity {
@@ -129,8 +135,8 @@ impl CollectionQuery for mixes::Model {
         mode: CollectionQueryListMode,
     ) -> Result<Vec<Self>> {
         use sea_orm::{
-            sea_query::Func, sea_query::SimpleExpr, FromQueryResult, Order, QueryOrder,
-            QuerySelect, QueryTrait,
+            FromQueryResult, Order, QueryOrder, QuerySelect, QueryTrait, sea_query::Func,
+            sea_query::SimpleExpr,
         };
 
         match mode {
@@ -183,6 +189,10 @@ impl CollectionQuery for mixes::Model {
     }
 }
 
+/// Create a mix with a new UUID, which stays the same across devices and
+/// edits. `cover` is the path of the image shown for the mix, `None` to show
+/// the covers of its tracks.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_mix(
     db: &DatabaseConnection,
     node_id: &str,
@@ -191,6 +201,7 @@ pub async fn create_mix(
     scriptlet_mode: bool,
     mode: i32,
     locked: bool,
+    cover: Option<String>,
 ) -> Result<mixes::Model> {
     use mixes::ActiveModel;
 
@@ -200,7 +211,8 @@ pub async fn create_mix(
         scriptlet_mode: ActiveValue::Set(scriptlet_mode),
         mode: ActiveValue::Set(Some(mode)),
         locked: ActiveValue::Set(locked),
-        hlc_uuid: ActiveValue::Set(node_id.to_owned()),
+        cover: ActiveValue::Set(cover),
+        hlc_uuid: ActiveValue::Set(Uuid::new_v4().to_string()),
         created_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
         updated_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
         created_at_hlc_ver: ActiveValue::Set(0),
@@ -231,6 +243,19 @@ pub async fn get_mix_by_id(main_db: &DatabaseConnection, id: i32) -> Result<mixe
     }
 }
 
+pub async fn get_mix_by_uuid(main_db: &DatabaseConnection, uuid: &str) -> Result<mixes::Model> {
+    let mix = mixes::Entity::find()
+        .filter(mixes::Column::HlcUuid.eq(uuid))
+        .one(main_db)
+        .await?;
+    match mix {
+        Some(m) => Ok(m),
+        None => bail!("Mix not found"),
+    }
+}
+
+/// Update the fields of a mix that are `Some`, `Some(None)` removes the
+/// cover.
 #[allow(clippy::too_many_arguments)]
 pub async fn update_mix(
     db: &DatabaseConnection,
@@ -241,6 +266,7 @@ pub async fn update_mix(
     scriptlet_mode: Option<bool>,
     mode: Option<i32>,
     locked: Option<bool>,
+    cover: Option<Option<String>>,
 ) -> Result<mixes::Model> {
     use mixes::Entity as MixEntity;
 
@@ -265,6 +291,9 @@ pub async fn update_mix(
         if let Some(locked) = locked {
             active_model.locked = ActiveValue::Set(locked);
         }
+        if let Some(cover) = cover {
+            active_model.cover = ActiveValue::Set(cover);
+        }
 
         active_model.updated_at_hlc_ts = ActiveValue::Set(Utc::now().to_rfc3339());
         active_model.updated_at_hlc_ver = ActiveValue::Set(ver + 1);
@@ -392,6 +421,7 @@ enum QueryOperator {
     LibAlbum(i32),
     LibGenre(i32),
     LibPlaylist(i32),
+    LibSmartPlaylist(i32),
     LibTrack(i32),
     LibRandom(i32),
     LibQueue(bool),
@@ -405,6 +435,14 @@ enum QueryOperator {
     FilterLiked(bool),
     FilterWithCoverArt(bool),
     FilterAnalyzed(bool),
+    FilterTempo(f32, f32),
+    LibPlayed(Comparison, i32),
+    LibRating(Comparison, i32),
+    /// Files modified within the period. The library doesn't keep when a
+    /// file was imported, so the modification time stands in for it.
+    LibAddedWithin(chrono::Duration),
+    /// Files not started within the period, including those never played.
+    LibNotPlayedSince(chrono::Duration),
     PipeLimit(u64),
     PipeRecommend(i32),
     Unknown(String),
@@ -424,6 +462,112 @@ where
     }
 }
 
+/// Parse a `min-max` range parameter, e.g. `90-120`.
+fn parse_range_parameter(parameter: &str, operator: &str) -> Option<(f32, f32)> {
+    let range = parameter
+        .split_once('-')
+        .and_then(|(min, max)| Some((min.trim().parse().ok()?, max.trim().parse().ok()?)));
+
+    match range {
+        Some((min, max)) if min <= max => Some((min, max)),
+        _ => {
+            warn!("Unable to parse the parameter of operator: {operator}({parameter})");
+            None
+        }
+    }
+}
+
+/// How a value is compared by operators like `lib::played(>=5)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn sql(&self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+
+    fn apply(&self, column: Expr, value: i32) -> SimpleExpr {
+        match self {
+            Comparison::Less => column.lt(value),
+            Comparison::LessOrEqual => column.lte(value),
+            Comparison::Equal => column.eq(value),
+            Comparison::GreaterOrEqual => column.gte(value),
+            Comparison::Greater => column.gt(value),
+        }
+    }
+}
+
+/// Parse a comparison parameter, e.g. `>=4`. A bare number means at least
+/// that number.
+fn parse_comparison_parameter(parameter: &str, operator: &str) -> Option<(Comparison, i32)> {
+    let parameter = parameter.trim();
+    let (comparison, value) = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ]
+    .into_iter()
+    .find_map(|(prefix, comparison)| {
+        parameter
+            .strip_prefix(prefix)
+            .map(|value| (comparison, value))
+    })
+    .unwrap_or((Comparison::GreaterOrEqual, parameter));
+
+    parse_parameter::<i32>(value.trim(), operator).map(|value| (comparison, value))
+}
+
+/// Longest period a mix may look back, far longer than any library is old.
+const MAX_PERIOD_DAYS: i64 = 100 * 365;
+
+/// Parse a period parameter like `12h`, `30d` or `2w`. A bare number is a
+/// number of days. Periods longer than [`MAX_PERIOD_DAYS`] are refused.
+fn parse_period_parameter(parameter: &str, operator: &str) -> Option<chrono::Duration> {
+    let parameter = parameter.trim();
+    let (value, unit) = match parameter.char_indices().last() {
+        Some((index, unit)) if unit.is_ascii_alphabetic() => (&parameter[..index], unit),
+        _ => (parameter, 'd'),
+    };
+
+    let period = value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .and_then(|value| match unit {
+            'h' => chrono::Duration::try_hours(value),
+            'd' => chrono::Duration::try_days(value),
+            'w' => chrono::Duration::try_weeks(value),
+            _ => None,
+        });
+
+    match period {
+        Some(period)
+            if period >= chrono::Duration::zero()
+                && period <= chrono::Duration::days(MAX_PERIOD_DAYS) =>
+        {
+            Some(period)
+        }
+        _ => {
+            warn!("Unable to parse the parameter of operator: {operator}({parameter})");
+            None
+        }
+    }
+}
+
 pub async fn add_item_to_mix(
     main_db: &DatabaseConnection,
     node_id: &str,
@@ -523,6 +667,49 @@ pub async fn initialize_mix_queries(main_db: &DatabaseConnection, node_id: &str)
     Ok(())
 }
 
+/// Parse mix parameters separated by `;`, leaving out those that can't be
+/// parsed.
+pub fn parse_mix_parameters(mix_parameters: &str) -> Vec<(String, String)> {
+    mix_parameters
+        .split(';')
+        .filter_map(parse_mix_parameter)
+        .collect()
+}
+
+/// Parse one mix parameter into its operator and parameter. Besides
+/// `operator(parameter)`, comparisons like `lib::played>=5` and periods like
+/// `lib::added-within::30d` are accepted. Dashes in operators are read as
+/// underscores.
+fn parse_mix_parameter(param: &str) -> Option<(String, String)> {
+    // Trim leading and trailing whitespace
+    let param = param.trim();
+
+    let (operator, parameter) = if let Some(start) = param.find('(') {
+        // Find the position of the first '(' and the last ')'
+        let end = param.rfind(')')?;
+        // Extract the operator and parameter
+        (&param[..start], &param[start + 1..end])
+    } else if let Some(start) = param.find(['<', '>', '=']) {
+        // The comparison stays in the parameter
+        (&param[..start], &param[start..])
+    } else {
+        let (operator, parameter) = param.rsplit_once("::")?;
+        if !operator.contains("::") {
+            return None;
+        }
+        (operator, parameter)
+    };
+
+    // Trim leading and trailing whitespace and handle escape issues
+    let operator = operator
+        .trim()
+        .replace("\\(", "(")
+        .replace("\\)", ")")
+        .replace('-', "_");
+    let parameter = parameter.trim().replace("\\(", "(").replace("\\)", ")");
+    Some((operator, parameter))
+}
+
 fn parse_query(query: &(String, String)) -> QueryOperator {
     let (operator, parameter) = query;
     match operator.as_str() {
@@ -541,6 +728,9 @@ fn parse_query(query: &(String, String)) -> QueryOperator {
         "lib::playlist" => parse_parameter::<i32>(parameter, operator)
             .map(QueryOperator::LibPlaylist)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "lib::smart_playlist" => parse_parameter::<i32>(parameter, operator)
+            .map(QueryOperator::LibSmartPlaylist)
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
         "lib::track" => parse_parameter::<i32>(parameter, operator)
             .map(QueryOperator::LibTrack)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
@@ -576,6 +766,21 @@ fn parse_query(query: &(String, String)) -> QueryOperator {
         "filter::with_cover_art" => parse_parameter::<bool>(parameter, operator)
             .map(QueryOperator::FilterWithCoverArt)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "filter::tempo" => parse_range_parameter(parameter, operator)
+            .map(|(min, max)| QueryOperator::FilterTempo(min, max))
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "lib::played" => parse_comparison_parameter(parameter, operator)
+            .map(|(comparison, count)| QueryOperator::LibPlayed(comparison, count))
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "lib::rating" => parse_comparison_parameter(parameter, operator)
+            .map(|(comparison, rating)| QueryOperator::LibRating(comparison, rating))
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "lib::added_within" => parse_period_parameter(parameter, operator)
+            .map(QueryOperator::LibAddedWithin)
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "lib::not_played_since" => parse_period_parameter(parameter, operator)
+            .map(QueryOperator::LibNotPlayedSince)
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
         "pipe::limit" => parse_parameter::<u64>(parameter, operator)
             .map(QueryOperator::PipeLimit)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
@@ -690,11 +895,16 @@ pub async fn query_mix_media_files(
     let mut album_ids: Vec<i32> = vec![];
     let mut genre_ids: Vec<i32> = vec![];
     let mut playlist_ids: Vec<i32> = vec![];
+    let mut smart_playlist_ids: Vec<i32> = vec![];
     let mut track_ids: Vec<i32> = vec![];
     let mut random_count: Vec<i32> = vec![];
     let mut directories_deep: Vec<String> = vec![];
     let mut directories_shallow: Vec<String> = vec![];
     let mut playback_queue: Option<bool> = None;
+    let mut played: Vec<(Comparison, i32)> = vec![];
+    let mut ratings: Vec<(Comparison, i32)> = vec![];
+    let mut added_within: Vec<chrono::Duration> = vec![];
+    let mut not_played_since: Vec<chrono::Duration> = vec![];
 
     let mut sort_track_number_asc: Option<bool> = None;
     let mut sort_last_modified_asc: Option<bool> = None;
@@ -705,6 +915,7 @@ pub async fn query_mix_media_files(
     let mut filter_liked: Option<bool> = None;
     let mut filter_cover_art: Option<bool> = None;
     let mut filter_analyzed: Option<bool> = None;
+    let mut filter_tempo: Option<(f32, f32)> = None;
     let mut pipe_limit: Option<u64> = None;
     let mut pipe_recommend: Option<i32> = None;
 
@@ -715,11 +926,16 @@ pub async fn query_mix_media_files(
             QueryOperator::LibAlbum(id) => album_ids.push(id),
             QueryOperator::LibGenre(id) => genre_ids.push(id),
             QueryOperator::LibPlaylist(id) => playlist_ids.push(id),
+            QueryOperator::LibSmartPlaylist(id) => smart_playlist_ids.push(id),
             QueryOperator::LibTrack(id) => track_ids.push(id),
             QueryOperator::LibRandom(count) => random_count.push(count),
             QueryOperator::LibQueue(enabled) => playback_queue = Some(enabled),
             QueryOperator::LibDirectoryDeep(dir) => directories_deep.push(dir),
             QueryOperator::LibDirectoryShallow(dir) => directories_shallow.push(dir),
+            QueryOperator::LibPlayed(comparison, count) => played.push((comparison, count)),
+            QueryOperator::LibRating(comparison, rating) => ratings.push((comparison, rating)),
+            QueryOperator::LibAddedWithin(period) => added_within.push(period),
+            QueryOperator::LibNotPlayedSince(period) => not_played_since.push(period),
             QueryOperator::SortTrackNumber(asc) => sort_track_number_asc = Some(asc),
             QueryOperator::SortLastModified(asc) => sort_last_modified_asc = Some(asc),
             QueryOperator::SortDuration(asc) => sort_duration_asc = Some(asc),
@@ -728,6 +944,7 @@ pub async fn query_mix_media_files(
             QueryOperator::FilterLiked(liked) => filter_liked = Some(liked),
             QueryOperator::FilterWithCoverArt(cover_art) => filter_cover_art = Some(cover_art),
             QueryOperator::FilterAnalyzed(analyzed) => filter_analyzed = Some(analyzed),
+            QueryOperator::FilterTempo(min, max) => filter_tempo = Some((min, max)),
             QueryOperator::PipeLimit(limit) => pipe_limit = Some(limit),
             QueryOperator::PipeRecommend(recommend) => pipe_recommend = Some(recommend),
             QueryOperator::Unknown(op) => warn!("Unknown operator: {op}"),
@@ -750,9 +967,14 @@ pub async fn query_mix_media_files(
         && album_ids.is_empty()
         && track_ids.is_empty()
         && genre_ids.is_empty()
+        && smart_playlist_ids.is_empty()
         && random_count.is_empty()
         && directories_deep.is_empty()
         && directories_shallow.is_empty()
+        && played.is_empty()
+        && ratings.is_empty()
+        && added_within.is_empty()
+        && not_played_since.is_empty()
         && playlist_ids.len() == 1;
 
     let only_playlist = if only_one_playlist {
@@ -773,6 +995,15 @@ pub async fn query_mix_media_files(
                 .add(media_file_fingerprint::Column::IsDuplicated.ne(1)),
         );
 
+    // Hidden tracks only show up when they are asked for by ID
+    let mut visible_condition = Condition::any()
+        .add(Expr::cust("\"media_files\".\"id\"").not_in_subquery(hidden_files_subquery()));
+    if !track_ids.is_empty() {
+        visible_condition =
+            visible_condition.add(Expr::cust("\"media_files\".\"id\"").is_in(track_ids.clone()));
+    }
+    query = query.filter(visible_condition);
+
     // Create an OR condition to hold all the subconditions
     let mut or_condition = Condition::any();
 
@@ -812,6 +1043,12 @@ pub async fn query_mix_media_files(
         media_file_playlists::Column::MediaFileId
     );
 
+    // Filter by smart playlists if provided, their rules are evaluated here
+    for smart_playlist_id in smart_playlist_ids {
+        let subquery = smart_playlist_subquery(main_db, smart_playlist_id).await?;
+        or_condition = or_condition.add(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery));
+    }
+
     // Filter by track_ids if provided
     if !track_ids.is_empty() {
         let subquery = media_files::Entity::find()
@@ -861,6 +1098,55 @@ pub async fn query_mix_media_files(
         or_condition = or_condition.add(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery));
     }
 
+    // Filter by how often tracks were played through, unplayed tracks count
+    // as played zero times
+    for (comparison, count) in played {
+        or_condition = or_condition.add(Expr::cust_with_values(
+            format!(
+                "COALESCE((SELECT \"played_through\" FROM \"media_file_stats\" \
+                 WHERE \"media_file_stats\".\"media_file_id\" = \"media_files\".\"id\"), 0) {} ?",
+                comparison.sql()
+            ),
+            [count],
+        ));
+    }
+
+    // Filter by rating, unrated tracks never match
+    for (comparison, rating) in ratings {
+        let subquery = media_file_stats::Entity::find()
+            .select_only()
+            .filter(comparison.apply(Expr::col(media_file_stats::Column::Rating), rating))
+            .column(media_file_stats::Column::MediaFileId)
+            .into_query();
+
+        or_condition = or_condition.add(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery));
+    }
+
+    // Filter by when tracks were added, going by the modification time of
+    // their files
+    for period in added_within {
+        let since = (Utc::now() - period).timestamp();
+
+        or_condition = or_condition.add(Expr::cust_with_values(
+            "CAST(\"media_files\".\"last_modified\" AS INTEGER) >= ?",
+            [since],
+        ));
+    }
+
+    // Filter by tracks not started within the period, including those never
+    // played
+    for period in not_played_since {
+        let since = (Utc::now() - period).to_rfc3339();
+        let subquery = play_history::Entity::find()
+            .select_only()
+            .filter(play_history::Column::StartedAt.gte(since))
+            .column(play_history::Column::MediaFileId)
+            .into_query();
+
+        or_condition =
+            or_condition.add(Expr::cust("\"media_files\".\"id\"").not_in_subquery(subquery));
+    }
+
     if let Some(queue_enabled) = playback_queue {
         if queue_enabled {
             let queued_tracks = list_playback_queue(main_db).await?;
@@ -879,8 +1165,9 @@ pub async fn query_mix_media_files(
     let has_liked = filter_liked.is_some();
     let has_cover_art = filter_cover_art.is_some();
     let has_analyzed = filter_analyzed.is_some();
+    let has_tempo = filter_tempo.is_some();
 
-    if has_liked || has_cover_art || has_analyzed {
+    if has_liked || has_cover_art || has_analyzed || has_tempo {
         let mut filter = Condition::all();
 
         if !all {
@@ -899,6 +1186,16 @@ pub async fn query_mix_media_files(
             }
         }
 
+        if let Some((min, max)) = filter_tempo {
+            let subquery = media_analysis::Entity::find()
+                .select_only()
+                .filter(media_analysis::Column::Bpm.between(min, max))
+                .column(media_analysis::Column::FileId)
+                .into_query();
+
+            filter = filter.add(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery));
+        }
+
         if let Some(cover_art) = filter_cover_art {
             let magic_cover_art_id = get_magic_cover_art_id(main_db).await;
 
@@ -1000,7 +1297,7 @@ pub async fn query_mix_media_files(
             return Ok([].to_vec());
         }
 
-        let virtual_point: [f32; 61] = if recommend_group >= 0 {
+        let virtual_point: [f32; ANALYSIS_VECTOR_SIZE] = if recommend_group >= 0 {
             get_percentile_analysis_result(
                 main_db,
                 1.0 / (9 + 2) as f64 * (recommend_group + 1) as f64,
@@ -1014,19 +1311,40 @@ pub async fn query_mix_media_files(
                 .into()
         };
 
-        let recommend_n = pipe_limit.unwrap_or(30);
+        let recommend_n = pipe_limit.unwrap_or(30) as usize;
+        let liked_only = filter_liked == Some(true);
 
-        let file_ids = match get_recommendation_by_parameter(
+        let recommendations = match get_recommendation_by_parameter(
             recommend_db,
             virtual_point,
-            recommend_n as usize,
+            if liked_only {
+                recommend_n * LIKED_ONLY_OVERSAMPLING
+            } else {
+                recommend_n
+            },
         )
         .with_context(|| "Failed to get recommendation by parameters")
         {
-            Ok(x) => x.into_iter().map(|x| x.0 as i32).collect::<Vec<i32>>(),
+            Ok(x) => x,
             Err(_) => return Ok([].to_vec()),
         };
 
+        // Liked mixes should only recommend liked tracks as well
+        let recommendations = if liked_only {
+            retain_liked_recommendations(main_db, recommendations, recommend_n).await?
+        } else {
+            recommendations
+        };
+
+        // The recommendation index knows nothing about hidden tracks
+        let hidden: HashSet<i32> = get_hidden_files(main_db).await?.into_iter().collect();
+
+        let file_ids = recommendations
+            .into_iter()
+            .map(|x| x.0 as i32)
+            .filter(|x| !hidden.contains(x))
+            .collect::<Vec<i32>>();
+
         let media_files = get_files_by_ids(main_db, &file_ids).await?;
 
         // Create a hash map to store files by their ID
@@ -1109,3 +1427,176 @@ pub async fn query_mix_media_files(
 
     Ok(sorted_files)
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use chrono::Utc;
+    use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
+
+    use crate::{
+        actions::{
+            mixes::{create_mix, get_mix_by_uuid, query_mix_media_files, update_mix},
+            stats::{record_playback_complete, record_playback_start, set_rating},
+        },
+        connection::{
+            MainDbConnection, RecommendationDbConnection, connect_fake_recommendation_db,
+        },
+        entities::media_files,
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    async fn query_ids(
+        db: &MainDbConnection,
+        recommend_db: &RecommendationDbConnection,
+        operator: &str,
+        parameter: &str,
+    ) -> Result<Vec<i32>> {
+        let queries = vec![(operator.to_string(), parameter.to_string())];
+        let mut ids: Vec<i32> = query_mix_media_files(db, recommend_db, queries, 0, 100)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    #[tokio::test]
+    async fn test_playback_and_date_operators() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let recommend_db = connect_fake_recommendation_db()?;
+
+        let file_ids = seed_fake_tracks(&db, 4).await?;
+
+        for _ in 0..3 {
+            record_playback_start(&db, file_ids[0]).await?;
+            record_playback_complete(&db, file_ids[0]).await?;
+        }
+        record_playback_start(&db, file_ids[1]).await?;
+        record_playback_complete(&db, file_ids[1]).await?;
+
+        set_rating(&db, file_ids[2], Some(5)).await?;
+        set_rating(&db, file_ids[3], Some(2)).await?;
+
+        let mut recent: media_files::ActiveModel = media_files::Entity::find_by_id(file_ids[3])
+            .one(&db)
+            .await?
+            .unwrap()
+            .into();
+        recent.last_modified = ActiveValue::Set(Utc::now().timestamp().to_string());
+        recent.update(&db).await?;
+
+        let rdb = &recommend_db;
+
+        assert_eq!(
+            query_ids(&db, rdb, "lib::played", ">=2").await?,
+            vec![file_ids[0]]
+        );
+        assert_eq!(
+            query_ids(&db, rdb, "lib::played", "1").await?,
+            file_ids[..2].to_vec()
+        );
+        assert_eq!(
+            query_ids(&db, rdb, "lib::played", "<1").await?,
+            file_ids[2..].to_vec()
+        );
+
+        assert_eq!(
+            query_ids(&db, rdb, "lib::rating", ">=4").await?,
+            vec![file_ids[2]]
+        );
+        assert_eq!(
+            query_ids(&db, rdb, "lib::rating", "<=2").await?,
+            vec![file_ids[3]]
+        );
+
+        assert_eq!(
+            query_ids(&db, rdb, "lib::added_within", "30d").await?,
+            vec![file_ids[3]]
+        );
+        assert_eq!(
+            query_ids(&db, rdb, "lib::not_played_since", "90d").await?,
+            file_ids[2..].to_vec()
+        );
+
+        // Periods reaching before any representable date are refused
+        assert!(super::parse_period_parameter("36500d", "lib::added_within").is_some());
+        for period in ["36501d", "99999999w", "9223372036854775807h"] {
+            assert!(super::parse_period_parameter(period, "lib::added_within").is_none());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_saved_mixes_keep_their_own_uuid() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let node_id = "00000000-0000-0000-0000-000000000001";
+
+        let first = create_mix(
+            &db,
+            node_id,
+            "Late Night".to_string(),
+            "Mixes".to_string(),
+            false,
+            99,
+            false,
+            Some("/covers/night.png".to_string()),
+        )
+        .await?;
+        let second = create_mix(
+            &db,
+            node_id,
+            "Workout".to_string(),
+            "Mixes".to_string(),
+            false,
+            99,
+            false,
+            None,
+        )
+        .await?;
+
+        assert_ne!(first.hlc_uuid, node_id);
+        assert_ne!(first.hlc_uuid, second.hlc_uuid);
+        assert_eq!(first.cover.as_deref(), Some("/covers/night.png"));
+
+        // Editing a mix keeps its UUID, a missing cover is left alone
+        let renamed = update_mix(
+            &db,
+            node_id,
+            first.id,
+            Some("After Hours".to_string()),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await?;
+        assert_eq!(renamed.hlc_uuid, first.hlc_uuid);
+        assert_eq!(renamed.cover.as_deref(), Some("/covers/night.png"));
+
+        update_mix(
+            &db,
+            node_id,
+            first.id,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(None),
+        )
+        .await?;
+        let found = get_mix_by_uuid(&db, &first.hlc_uuid).await?;
+        assert_eq!(found.id, first.id);
+        assert_eq!(found.name, "After Hours");
+        assert_eq!(found.cover, None);
+
+        assert!(get_mix_by_uuid(&db, node_id).await.is_err());
+
+        Ok(())
+    }
+}
@@ -1,20 +1,44 @@
 pub mod albums;
 pub mod analysis;
 pub mod artists;
+pub mod checkpoint;
 pub mod collection;
 pub mod cover_art;
+pub mod daily_mix;
 pub mod directory;
+pub mod duplicates;
+pub mod enrich;
 pub mod file;
 pub mod fingerprint;
 pub mod genres;
+pub mod groups;
 pub mod index;
+pub mod index_suggestions;
 pub mod library;
+pub mod library_map;
 pub mod logging;
+pub mod lyrics;
 pub mod metadata;
+pub mod metadata_languages;
+pub mod mix_presets;
 pub mod mixes;
+pub mod pages;
 pub mod playback_queue;
+pub mod playlist_bundle;
+pub mod playlist_mirror;
 pub mod playlists;
+pub mod radio;
 pub mod recommendation;
 pub mod search;
+pub mod seek_table;
+pub mod settings;
+pub mod sources;
+pub mod splitting;
+pub mod stations;
 pub mod stats;
+pub mod summaries;
+pub mod tag_conflicts;
+pub mod trash;
 pub mod utils;
+pub mod watch;
+pub mod waveform;
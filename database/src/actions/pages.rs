@@ -0,0 +1,353 @@
+//! Aggregated data of album and artist pages.
+//!
+//! Each page is built from a fixed batch of queries no matter how many
+//! tracks it has, so the UI doesn't need a round-trip per section.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use sea_orm::QueryOrder;
+use sea_orm::prelude::*;
+
+use crate::actions::metadata::{MetadataSummary, extract_number, get_metadata_summary_by_files};
+use crate::actions::stats::hidden_files_subquery;
+use crate::entities::{
+    albums, artists, media_file_albums, media_file_artists, media_file_stats, media_files,
+    media_metadata, play_history,
+};
+
+/// Play statistics summed over the tracks of a page.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageStats {
+    pub played_through: i32,
+    pub skipped: i32,
+    pub liked_tracks: i32,
+    /// RFC 3339 timestamp of the latest play, `None` if never played.
+    pub last_played_at: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AlbumPage {
+    pub album: albums::Model,
+    /// Tracks ordered by disc and track number.
+    pub tracks: Vec<MetadataSummary>,
+    /// Duration of all tracks in seconds.
+    pub total_duration: f64,
+    /// Earliest year found in the `date` tag of the tracks.
+    pub year: Option<i32>,
+    pub cover_art_id: Option<i32>,
+    /// Artists of the tracks, most frequent first.
+    pub artists: Vec<artists::Model>,
+    pub stats: PageStats,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtistAlbum {
+    pub album: albums::Model,
+    pub year: Option<i32>,
+    pub cover_art_id: Option<i32>,
+    pub track_count: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtistPage {
+    pub artist: artists::Model,
+    /// Albums the artist appears on, oldest first.
+    pub albums: Vec<ArtistAlbum>,
+    /// Tracks grouped by album, then ordered by disc and track number.
+    pub tracks: Vec<MetadataSummary>,
+    /// Duration of all tracks in seconds.
+    pub total_duration: f64,
+    pub stats: PageStats,
+}
+
+async fn get_years(main_db: &DatabaseConnection, file_ids: &[i32]) -> Result<HashMap<i32, i32>> {
+    Ok(media_metadata::Entity::find()
+        .filter(media_metadata::Column::FileId.is_in(file_ids.to_vec()))
+        .filter(media_metadata::Column::MetaKey.eq("date"))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .filter_map(|x| extract_number(&x.meta_value).map(|year| (x.file_id, year)))
+        .collect())
+}
+
+async fn get_page_stats(main_db: &DatabaseConnection, file_ids: &[i32]) -> Result<PageStats> {
+    let mut stats = PageStats::default();
+
+    for x in media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.is_in(file_ids.to_vec()))
+        .all(main_db)
+        .await?
+    {
+        stats.played_through += x.played_through;
+        stats.skipped += x.skipped;
+        if x.liked {
+            stats.liked_tracks += 1;
+        }
+    }
+
+    stats.last_played_at = play_history::Entity::find()
+        .filter(play_history::Column::MediaFileId.is_in(file_ids.to_vec()))
+        .order_by_desc(play_history::Column::StartedAt)
+        .one(main_db)
+        .await?
+        .map(|x| x.started_at);
+
+    Ok(stats)
+}
+
+/// Get everything an album page shows, `None` if the album doesn't exist.
+pub async fn get_album_page(
+    main_db: &DatabaseConnection,
+    album_id: i32,
+) -> Result<Option<AlbumPage>> {
+    let Some(album) = albums::Entity::find_by_id(album_id).one(main_db).await? else {
+        return Ok(None);
+    };
+
+    let file_ids: Vec<i32> = media_file_albums::Entity::find()
+        .filter(media_file_albums::Column::AlbumId.eq(album_id))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| x.media_file_id)
+        .collect();
+
+    let files = media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(file_ids.clone()))
+        .filter(media_files::Column::Id.not_in_subquery(hidden_files_subquery()))
+        .all(main_db)
+        .await?;
+    let mut tracks = get_metadata_summary_by_files(main_db, files).await?;
+    tracks.sort_by(|a, b| {
+        a.track_number
+            .cmp(&b.track_number)
+            .then_with(|| a.file_name.cmp(&b.file_name))
+    });
+
+    let mut artist_counts: HashMap<i32, usize> = HashMap::new();
+    for x in media_file_artists::Entity::find()
+        .filter(media_file_artists::Column::MediaFileId.is_in(file_ids.clone()))
+        .all(main_db)
+        .await?
+    {
+        *artist_counts.entry(x.artist_id).or_default() += 1;
+    }
+    let mut artists = artists::Entity::find()
+        .filter(artists::Column::Id.is_in(artist_counts.keys().copied()))
+        .all(main_db)
+        .await?;
+    artists.sort_by(|a, b| {
+        artist_counts[&b.id]
+            .cmp(&artist_counts[&a.id])
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(Some(AlbumPage {
+        album,
+        total_duration: tracks.iter().map(|x| x.duration).sum(),
+        year: get_years(main_db, &file_ids).await?.into_values().min(),
+        cover_art_id: tracks.iter().find_map(|x| x.cover_art_id),
+        artists,
+        stats: get_page_stats(main_db, &file_ids).await?,
+        tracks,
+    }))
+}
+
+/// Get everything an artist page shows, `None` if the artist doesn't exist.
+pub async fn get_artist_page(
+    main_db: &DatabaseConnection,
+    artist_id: i32,
+) -> Result<Option<ArtistPage>> {
+    let Some(artist) = artists::Entity::find_by_id(artist_id).one(main_db).await? else {
+        return Ok(None);
+    };
+
+    let file_ids: Vec<i32> = media_file_artists::Entity::find()
+        .filter(media_file_artists::Column::ArtistId.eq(artist_id))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| x.media_file_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    let files = media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(file_ids.clone()))
+        .filter(media_files::Column::Id.not_in_subquery(hidden_files_subquery()))
+        .all(main_db)
+        .await?;
+    let mut tracks = get_metadata_summary_by_files(main_db, files).await?;
+
+    let album_map: HashMap<i32, i32> = media_file_albums::Entity::find()
+        .filter(media_file_albums::Column::MediaFileId.is_in(file_ids.clone()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.media_file_id, x.album_id))
+        .collect();
+    let years = get_years(main_db, &file_ids).await?;
+
+    let mut albums: Vec<ArtistAlbum> = albums::Entity::find()
+        .filter(albums::Column::Id.is_in(album_map.values().copied().collect::<HashSet<_>>()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|album| {
+            let album_tracks: Vec<&MetadataSummary> = tracks
+                .iter()
+                .filter(|x| album_map.get(&x.id) == Some(&album.id))
+                .collect();
+
+            ArtistAlbum {
+                year: album_tracks
+                    .iter()
+                    .filter_map(|x| years.get(&x.id).copied())
+                    .min(),
+                cover_art_id: album_tracks.iter().find_map(|x| x.cover_art_id),
+                track_count: album_tracks.len() as i32,
+                album,
+            }
+        })
+        .collect();
+    // Albums without a year go last
+    albums.sort_by(|a, b| {
+        a.year
+            .unwrap_or(i32::MAX)
+            .cmp(&b.year.unwrap_or(i32::MAX))
+            .then_with(|| a.album.name.cmp(&b.album.name))
+    });
+
+    let album_order: HashMap<i32, usize> = albums
+        .iter()
+        .enumerate()
+        .map(|(index, x)| (x.album.id, index))
+        .collect();
+    tracks.sort_by_key(|x| {
+        (
+            album_map
+                .get(&x.id)
+                .and_then(|album_id| album_order.get(album_id))
+                .copied()
+                .unwrap_or(usize::MAX),
+            x.track_number,
+            x.file_name.clone(),
+        )
+    });
+
+    Ok(Some(ArtistPage {
+        artist,
+        albums,
+        total_duration: tracks.iter().map(|x| x.duration).sum(),
+        stats: get_page_stats(main_db, &file_ids).await?,
+        tracks,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::{ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+
+    use crate::{
+        actions::{
+            pages::{get_album_page, get_artist_page},
+            stats::{increase_played_through, increase_skipped, set_liked},
+        },
+        entities::{albums, artists, media_metadata},
+        test_support::{FakeTrack, TRACKS_PER_ALBUM, connect_test_main_db, seed_fake_tracks},
+    };
+
+    async fn set_date(db: &sea_orm::DatabaseConnection, file_id: i32, date: &str) -> Result<()> {
+        media_metadata::Entity::insert(media_metadata::ActiveModel {
+            file_id: ActiveValue::Set(file_id),
+            meta_key: ActiveValue::Set("date".to_string()),
+            meta_value: ActiveValue::Set(date.to_string()),
+            ..Default::default()
+        })
+        .exec(db)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_album_page() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, TRACKS_PER_ALBUM * 2).await?;
+        set_date(&db, file_ids[3], "2005-04-01").await?;
+        set_date(&db, file_ids[4], "2004").await?;
+
+        increase_played_through(&db, file_ids[0]).await?;
+        increase_played_through(&db, file_ids[1]).await?;
+        increase_skipped(&db, file_ids[1]).await?;
+        set_liked(&db, file_ids[2], true).await?;
+        // Plays of other albums don't count
+        increase_played_through(&db, file_ids[TRACKS_PER_ALBUM]).await?;
+
+        let album = albums::Entity::find()
+            .filter(albums::Column::Name.eq(FakeTrack::nth(0).album))
+            .one(&db)
+            .await?
+            .unwrap();
+        let page = get_album_page(&db, album.id).await?.unwrap();
+
+        assert_eq!(page.tracks.len(), TRACKS_PER_ALBUM);
+        assert!(
+            page.tracks
+                .windows(2)
+                .all(|x| x[0].track_number < x[1].track_number)
+        );
+        let total_duration: f64 = (0..TRACKS_PER_ALBUM)
+            .map(|x| FakeTrack::nth(x).duration)
+            .sum();
+        assert!((page.total_duration - total_duration).abs() < 1e-6);
+        assert_eq!(page.year, Some(2004));
+        assert_eq!(page.artists.len(), 1);
+        assert_eq!(page.artists[0].name, FakeTrack::nth(0).artist);
+
+        assert_eq!(page.stats.played_through, 2);
+        assert_eq!(page.stats.skipped, 1);
+        assert_eq!(page.stats.liked_tracks, 1);
+
+        assert!(get_album_page(&db, -1).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_artist_page() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        // Artists rotate every album, so the first artist gets albums 0 and 5
+        let file_ids = seed_fake_tracks(&db, TRACKS_PER_ALBUM * 6).await?;
+        set_date(&db, file_ids[0], "2010").await?;
+        set_date(&db, file_ids[TRACKS_PER_ALBUM * 5], "1999").await?;
+
+        let artist = artists::Entity::find()
+            .filter(artists::Column::Name.eq(FakeTrack::nth(0).artist))
+            .one(&db)
+            .await?
+            .unwrap();
+        let page = get_artist_page(&db, artist.id).await?.unwrap();
+
+        let album_names: Vec<&str> = page.albums.iter().map(|x| x.album.name.as_str()).collect();
+        assert_eq!(
+            album_names,
+            vec![
+                FakeTrack::nth(TRACKS_PER_ALBUM * 5).album,
+                FakeTrack::nth(0).album
+            ]
+        );
+        assert_eq!(page.albums[0].year, Some(1999));
+        assert_eq!(page.albums[0].track_count, TRACKS_PER_ALBUM as i32);
+
+        // Tracks follow the album order
+        assert_eq!(page.tracks.len(), TRACKS_PER_ALBUM * 2);
+        assert_eq!(page.tracks[0].id, file_ids[TRACKS_PER_ALBUM * 5]);
+        assert_eq!(page.tracks[TRACKS_PER_ALBUM].id, file_ids[0]);
+
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
-use sea_orm::{prelude::*, TransactionTrait};
 use sea_orm::{EntityTrait, QueryOrder, Set};
+use sea_orm::{TransactionTrait, prelude::*};
 
 use crate::entities::playback_queue;
 
@@ -12,11 +12,25 @@ pub async fn replace_playback_queue(
 
     let txn = main_db.begin().await?;
 
+    // Keep the mark and the position of the playing track across the rewrite
+    let current = PlaybackQueueEntity::find()
+        .filter(playback_queue::Column::Current.eq(true))
+        .one(&txn)
+        .await?;
+    let position_ms = current.as_ref().map_or(0, |entry| entry.position_ms);
+    let current = current.map(|entry| entry.media_file_id);
+
     PlaybackQueueEntity::delete_many().exec(&txn).await?;
 
+    let mut current_marked = false;
     for media_file_id in media_file_ids {
+        let is_current = !current_marked && current == Some(media_file_id);
+        current_marked |= is_current;
+
         let new_entry = playback_queue::ActiveModel {
             media_file_id: Set(media_file_id),
+            current: Set(is_current),
+            position_ms: Set(if is_current { position_ms } else { 0 }),
             ..Default::default()
         };
         new_entry.insert(&txn).await?;
@@ -27,6 +41,75 @@ pub async fn replace_playback_queue(
     Ok(())
 }
 
+/// Mark the first entry of `media_file_id` as the playing track, so the
+/// queue resumes from it after a restart. A newly marked track starts from
+/// the beginning until [`set_playback_queue_position`] says otherwise.
+pub async fn set_playback_queue_current(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+) -> Result<()> {
+    use playback_queue::Entity as PlaybackQueueEntity;
+
+    let txn = main_db.begin().await?;
+
+    // Reloading the playing track, like when the queue is restored, keeps
+    // its position
+    let current = PlaybackQueueEntity::find()
+        .filter(playback_queue::Column::Current.eq(true))
+        .one(&txn)
+        .await?;
+    if current.is_some_and(|entry| entry.media_file_id == media_file_id) {
+        return Ok(());
+    }
+
+    PlaybackQueueEntity::update_many()
+        .col_expr(playback_queue::Column::Current, Expr::value(false))
+        .col_expr(playback_queue::Column::PositionMs, Expr::value(0))
+        .filter(playback_queue::Column::Current.eq(true))
+        .exec(&txn)
+        .await?;
+
+    if let Some(entry) = PlaybackQueueEntity::find()
+        .filter(playback_queue::Column::MediaFileId.eq(media_file_id))
+        .order_by_asc(playback_queue::Column::Id)
+        .one(&txn)
+        .await?
+    {
+        let mut entry: playback_queue::ActiveModel = entry.into();
+        entry.current = Set(true);
+        entry.update(&txn).await?;
+    }
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Remember how far into the playing track playback got, so the queue
+/// resumes from there after a restart or a library switch.
+pub async fn set_playback_queue_position(
+    main_db: &DatabaseConnection,
+    position_ms: i64,
+) -> Result<()> {
+    playback_queue::Entity::update_many()
+        .col_expr(playback_queue::Column::PositionMs, Expr::value(position_ms))
+        .filter(playback_queue::Column::Current.eq(true))
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
+/// How far into the playing track of the saved queue playback got, 0 if
+/// no track is marked as playing.
+pub async fn get_playback_queue_position(main_db: &DatabaseConnection) -> Result<i64> {
+    Ok(playback_queue::Entity::find()
+        .filter(playback_queue::Column::Current.eq(true))
+        .one(main_db)
+        .await?
+        .map_or(0, |entry| entry.position_ms))
+}
+
 pub async fn list_playback_queue(db: &DatabaseConnection) -> Result<Vec<i32>> {
     use playback_queue::Entity as PlaybackQueueEntity;
 
@@ -42,3 +125,94 @@ pub async fn list_playback_queue(db: &DatabaseConnection) -> Result<Vec<i32>> {
 
     Ok(media_file_ids)
 }
+
+/// Get the saved queue together with the file ID of the playing track.
+pub async fn get_saved_playback_queue(db: &DatabaseConnection) -> Result<(Vec<i32>, Option<i32>)> {
+    use playback_queue::Entity as PlaybackQueueEntity;
+
+    let entries = PlaybackQueueEntity::find()
+        .order_by_asc(playback_queue::Column::Id)
+        .all(db)
+        .await?;
+
+    let current = entries
+        .iter()
+        .find(|entry| entry.current)
+        .map(|entry| entry.media_file_id);
+    let media_file_ids = entries
+        .into_iter()
+        .map(|entry| entry.media_file_id)
+        .collect();
+
+    Ok((media_file_ids, current))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        actions::playback_queue::{
+            get_playback_queue_position, get_saved_playback_queue, list_playback_queue,
+            replace_playback_queue, set_playback_queue_current, set_playback_queue_position,
+        },
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_saved_queue_keeps_current_track() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 4).await?;
+
+        replace_playback_queue(&db, file_ids[..3].to_vec()).await?;
+        assert_eq!(get_saved_playback_queue(&db).await?.1, None);
+
+        set_playback_queue_current(&db, file_ids[1]).await?;
+        assert_eq!(
+            get_saved_playback_queue(&db).await?,
+            (file_ids[..3].to_vec(), Some(file_ids[1]))
+        );
+
+        // Reordering the queue rewrites it but keeps the mark
+        let reordered = vec![file_ids[2], file_ids[1], file_ids[0], file_ids[3]];
+        replace_playback_queue(&db, reordered.clone()).await?;
+        assert_eq!(list_playback_queue(&db).await?, reordered);
+        assert_eq!(get_saved_playback_queue(&db).await?.1, Some(file_ids[1]));
+
+        set_playback_queue_current(&db, file_ids[3]).await?;
+        assert_eq!(get_saved_playback_queue(&db).await?.1, Some(file_ids[3]));
+
+        // The mark goes away with the track
+        replace_playback_queue(&db, file_ids[..2].to_vec()).await?;
+        assert_eq!(get_saved_playback_queue(&db).await?.1, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_saved_queue_keeps_position() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        replace_playback_queue(&db, file_ids.clone()).await?;
+        // Nothing is playing yet
+        set_playback_queue_position(&db, 1000).await?;
+        assert_eq!(get_playback_queue_position(&db).await?, 0);
+
+        set_playback_queue_current(&db, file_ids[1]).await?;
+        set_playback_queue_position(&db, 42_000).await?;
+        assert_eq!(get_playback_queue_position(&db).await?, 42_000);
+
+        // Restoring the queue loads the same track again
+        set_playback_queue_current(&db, file_ids[1]).await?;
+        let reordered = vec![file_ids[1], file_ids[0], file_ids[2]];
+        replace_playback_queue(&db, reordered).await?;
+        assert_eq!(get_playback_queue_position(&db).await?, 42_000);
+
+        // Another track starts from the beginning
+        set_playback_queue_current(&db, file_ids[2]).await?;
+        assert_eq!(get_playback_queue_position(&db).await?, 0);
+
+        Ok(())
+    }
+}
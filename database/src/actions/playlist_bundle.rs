@@ -0,0 +1,555 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::Utc;
+use log::{info, warn};
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+use tag_editor::music_brainz::fingerprint::{
+    Configuration, calculate_similarity_score, get_track_duration_in_secs, match_fingerprints,
+};
+
+use crate::actions::fingerprint::bytes_to_u32s;
+use crate::actions::playlists::{create_playlist, get_playlist_by_id};
+use crate::connection::MainDbConnection;
+use crate::entities::{
+    media_file_fingerprint, media_file_playlists, media_files, media_metadata, playlists,
+};
+
+use super::utils::DatabaseExecutor;
+
+pub const PLAYLIST_BUNDLE_VERSION: u32 = 1;
+
+const BUNDLE_KEY_CONTEXT: &str = "rune 2025 playlist bundle signing key";
+const RECORDING_ID_KEY: &str = "musicbrainz_recording_id";
+const DURATION_TOLERANCE_SECS: f64 = 5.0;
+const FINGERPRINT_SIMILARITY_THRESHOLD: f32 = 0.75;
+
+/// A single track reference inside a playlist bundle.
+///
+/// Tracks are described by how they sound and what they are, never by where
+/// they live on disk, so that the receiving side can locate its own copy.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlaylistBundleTrack {
+    pub recording_mbid: Option<String>,
+    pub fingerprint: Option<String>,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlaylistBundle {
+    pub version: u32,
+    pub name: String,
+    pub group: String,
+    pub author: String,
+    pub created_at: String,
+    pub tracks: Vec<PlaylistBundleTrack>,
+    #[serde(default)]
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleMatchKind {
+    RecordingId,
+    Fingerprint,
+    Metadata,
+}
+
+#[derive(Debug, Default)]
+pub struct PlaylistBundleImportResult {
+    pub matched: Vec<(i32, BundleMatchKind)>,
+    pub unmatched: Vec<PlaylistBundleTrack>,
+    /// Tracks resolved to a local file that an earlier track already took.
+    pub duplicates: Vec<PlaylistBundleTrack>,
+}
+
+impl PlaylistBundleImportResult {
+    pub fn matched_ids(&self) -> Vec<i32> {
+        self.matched.iter().map(|(id, _)| *id).collect()
+    }
+}
+
+impl PlaylistBundle {
+    fn payload(&self) -> Result<Vec<u8>> {
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        Ok(serde_json::to_vec(&unsigned)?)
+    }
+
+    fn compute_signature(&self, secret: &str) -> Result<String> {
+        let key = blake3::derive_key(BUNDLE_KEY_CONTEXT, secret.as_bytes());
+        Ok(blake3::keyed_hash(&key, &self.payload()?)
+            .to_hex()
+            .to_string())
+    }
+
+    /// Sign the bundle with a secret shared between both parties.
+    pub fn sign(&mut self, secret: &str) -> Result<()> {
+        self.signature = self.compute_signature(secret)?;
+        Ok(())
+    }
+
+    /// Check that the bundle was produced by someone holding `secret` and
+    /// has not been modified since.
+    pub fn verify(&self, secret: &str) -> Result<()> {
+        if self.version != PLAYLIST_BUNDLE_VERSION {
+            bail!("Unsupported playlist bundle version: {}", self.version);
+        }
+        if self.signature.is_empty() {
+            bail!("Playlist bundle is not signed");
+        }
+
+        let expected = blake3::Hash::from_hex(&self.signature)
+            .context("Malformed playlist bundle signature")?;
+        let actual = blake3::Hash::from_hex(self.compute_signature(secret)?)?;
+
+        // `blake3::Hash` compares in constant time.
+        if expected != actual {
+            bail!("Playlist bundle signature mismatch");
+        }
+
+        Ok(())
+    }
+}
+
+/// Export a playlist as a signed, path independent bundle.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `node_id` - The id of the client that triggers the operation.
+/// * `playlist_id` - The ID of the playlist to export.
+/// * `secret` - The shared secret used to sign the bundle.
+///
+/// # Returns
+/// * `Result<PlaylistBundle>` - The signed bundle or an error.
+pub async fn export_playlist_bundle(
+    main_db: &MainDbConnection,
+    node_id: &str,
+    playlist_id: i32,
+    secret: &str,
+) -> Result<PlaylistBundle> {
+    let playlist = get_playlist_by_id(main_db, playlist_id)
+        .await?
+        .context("Playlist not found")?;
+
+    let items = media_file_playlists::Entity::find()
+        .filter(media_file_playlists::Column::PlaylistId.eq(playlist_id))
+        .order_by_asc(media_file_playlists::Column::Position)
+        .all(main_db)
+        .await?;
+    let file_ids: Vec<i32> = items.iter().map(|x| x.media_file_id).collect();
+
+    let files: HashMap<i32, media_files::Model> = media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(file_ids.clone()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+
+    let fingerprints: HashMap<i32, Vec<u8>> = media_file_fingerprint::Entity::find()
+        .filter(media_file_fingerprint::Column::MediaFileId.is_in(file_ids.clone()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.media_file_id, x.fingerprint))
+        .collect();
+
+    let mut metadata: HashMap<i32, HashMap<String, String>> = HashMap::new();
+    for entry in media_metadata::Entity::find()
+        .filter(media_metadata::Column::FileId.is_in(file_ids.clone()))
+        .filter(media_metadata::Column::MetaKey.is_in([
+            "artist",
+            "album",
+            "track_title",
+            RECORDING_ID_KEY,
+        ]))
+        .all(main_db)
+        .await?
+    {
+        metadata
+            .entry(entry.file_id)
+            .or_default()
+            .insert(entry.meta_key, entry.meta_value);
+    }
+
+    let empty = HashMap::new();
+    let mut tracks = Vec::with_capacity(file_ids.len());
+    for file_id in file_ids {
+        let Some(file) = files.get(&file_id) else {
+            warn!("Skipping missing file {file_id} while exporting playlist {playlist_id}");
+            continue;
+        };
+        let meta = metadata.get(&file_id).unwrap_or(&empty);
+
+        tracks.push(PlaylistBundleTrack {
+            recording_mbid: meta.get(RECORDING_ID_KEY).cloned(),
+            fingerprint: fingerprints
+                .get(&file_id)
+                .map(|x| BASE64_STANDARD.encode(x)),
+            title: meta
+                .get("track_title")
+                .cloned()
+                .unwrap_or_else(|| file.file_name.clone()),
+            artist: meta.get("artist").cloned().unwrap_or_default(),
+            album: meta.get("album").cloned().unwrap_or_default(),
+            duration: file.duration.to_f64().unwrap_or_default(),
+        });
+    }
+
+    let mut bundle = PlaylistBundle {
+        version: PLAYLIST_BUNDLE_VERSION,
+        name: playlist.name,
+        group: playlist.group,
+        author: node_id.to_owned(),
+        created_at: Utc::now().to_rfc3339(),
+        tracks,
+        signature: String::new(),
+    };
+    bundle.sign(secret)?;
+
+    Ok(bundle)
+}
+
+pub async fn write_playlist_bundle(bundle: &PlaylistBundle, path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(bundle)?;
+    tokio::fs::write(path, content)
+        .await
+        .with_context(|| format!("Failed to write playlist bundle: {}", path.display()))
+}
+
+pub async fn read_playlist_bundle(path: &Path) -> Result<PlaylistBundle> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read playlist bundle: {}", path.display()))?;
+    serde_json::from_str(&content).context("Malformed playlist bundle")
+}
+
+/// Resolve every track of a bundle against the local library.
+///
+/// Tracks are matched by MusicBrainz recording ID first, then by acoustic
+/// fingerprint among files of a similar duration, and finally by exact
+/// title/artist. Each local file is used at most once, later tracks that
+/// resolve to it are reported as duplicates.
+pub async fn match_playlist_bundle<E>(
+    main_db: &E,
+    bundle: &PlaylistBundle,
+) -> Result<PlaylistBundleImportResult>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    let config = Configuration::default();
+    let mut result = PlaylistBundleImportResult::default();
+    let mut seen = HashSet::new();
+
+    for track in &bundle.tracks {
+        let mut found = None;
+
+        if let Some(mbid) = &track.recording_mbid {
+            found = media_metadata::Entity::find()
+                .filter(media_metadata::Column::MetaKey.eq(RECORDING_ID_KEY))
+                .filter(media_metadata::Column::MetaValue.eq(mbid.as_str()))
+                .one(main_db)
+                .await?
+                .map(|x| (x.file_id, BundleMatchKind::RecordingId));
+        }
+
+        if found.is_none() {
+            if let Some(encoded) = &track.fingerprint {
+                found = match_by_fingerprint(main_db, track, encoded, &config)
+                    .await?
+                    .map(|id| (id, BundleMatchKind::Fingerprint));
+            }
+        }
+
+        if found.is_none() {
+            found = match_by_metadata(main_db, track)
+                .await?
+                .map(|id| (id, BundleMatchKind::Metadata));
+        }
+
+        // A single local copy must not stand in for several distinct tracks
+        match found {
+            Some((id, kind)) if seen.insert(id) => result.matched.push((id, kind)),
+            Some(_) => result.duplicates.push(track.clone()),
+            None => result.unmatched.push(track.clone()),
+        }
+    }
+
+    Ok(result)
+}
+
+async fn candidate_ids_by_duration<E>(main_db: &E, duration: f64) -> Result<Vec<i32>>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    let low = Decimal::from_f64_retain(duration - DURATION_TOLERANCE_SECS).unwrap_or_default();
+    let high = Decimal::from_f64_retain(duration + DURATION_TOLERANCE_SECS).unwrap_or_default();
+
+    Ok(media_files::Entity::find()
+        .filter(media_files::Column::Duration.between(low, high))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect())
+}
+
+async fn match_by_fingerprint<E>(
+    main_db: &E,
+    track: &PlaylistBundleTrack,
+    encoded: &str,
+    config: &Configuration,
+) -> Result<Option<i32>>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    let remote = match BASE64_STANDARD.decode(encoded).map(bytes_to_u32s) {
+        Ok(Ok(x)) => x,
+        _ => {
+            warn!("Ignoring malformed fingerprint of: {}", track.title);
+            return Ok(None);
+        }
+    };
+
+    let candidates = candidate_ids_by_duration(main_db, track.duration).await?;
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+
+    let mut best: Option<(i32, f32)> = None;
+    for local in media_file_fingerprint::Entity::find()
+        .filter(media_file_fingerprint::Column::MediaFileId.is_in(candidates))
+        .all(main_db)
+        .await?
+    {
+        let local_fp = bytes_to_u32s(local.fingerprint)?;
+        let segments = match_fingerprints(&remote, &local_fp, config)?;
+        let score = calculate_similarity_score(
+            &segments,
+            get_track_duration_in_secs(&remote, config)
+                .max(get_track_duration_in_secs(&local_fp, config)),
+            config,
+        );
+
+        if score >= FINGERPRINT_SIMILARITY_THRESHOLD && best.is_none_or(|(_, s)| score > s) {
+            best = Some((local.media_file_id, score));
+        }
+    }
+
+    Ok(best.map(|(id, _)| id))
+}
+
+async fn match_by_metadata<E>(main_db: &E, track: &PlaylistBundleTrack) -> Result<Option<i32>>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    if track.title.is_empty() {
+        return Ok(None);
+    }
+
+    let titled: HashSet<i32> = media_metadata::Entity::find()
+        .filter(media_metadata::Column::MetaKey.eq("track_title"))
+        .filter(media_metadata::Column::MetaValue.eq(track.title.as_str()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| x.file_id)
+        .collect();
+
+    if titled.is_empty() {
+        return Ok(None);
+    }
+
+    let mut ids: Vec<i32> = if track.artist.is_empty() {
+        titled.into_iter().collect()
+    } else {
+        media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.is_in(titled))
+            .filter(media_metadata::Column::MetaKey.eq("artist"))
+            .filter(media_metadata::Column::MetaValue.eq(track.artist.as_str()))
+            .all(main_db)
+            .await?
+            .into_iter()
+            .map(|x| x.file_id)
+            .collect()
+    };
+    ids.sort_unstable();
+
+    if ids.len() <= 1 {
+        return Ok(ids.first().copied());
+    }
+
+    // Prefer the copy whose duration is the closest to the original.
+    Ok(media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(ids))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .min_by(|a, b| {
+            let da = (a.duration.to_f64().unwrap_or_default() - track.duration).abs();
+            let db = (b.duration.to_f64().unwrap_or_default() - track.duration).abs();
+            da.total_cmp(&db)
+        })
+        .map(|x| x.id))
+}
+
+/// Verify a bundle and create a local playlist from the tracks that could be
+/// resolved against the library.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `node_id` - The id of the client that triggers the operation.
+/// * `bundle` - The bundle received from another user.
+/// * `secret` - The shared secret the bundle was signed with.
+///
+/// # Returns
+/// * `Result<(Model, PlaylistBundleImportResult)>` - The created playlist and
+///   the matching report.
+pub async fn import_playlist_bundle(
+    main_db: &MainDbConnection,
+    node_id: &str,
+    bundle: &PlaylistBundle,
+    secret: &str,
+) -> Result<(playlists::Model, PlaylistBundleImportResult)> {
+    bundle.verify(secret)?;
+
+    let txn = main_db.begin().await?;
+
+    let import = async {
+        let playlist =
+            create_playlist(&txn, node_id, bundle.name.clone(), bundle.group.clone()).await?;
+        let result = match_playlist_bundle(&txn, bundle).await?;
+
+        let models: Vec<media_file_playlists::ActiveModel> = result
+            .matched_ids()
+            .into_iter()
+            .enumerate()
+            .map(|(index, media_file_id)| media_file_playlists::ActiveModel {
+                playlist_id: ActiveValue::Set(playlist.id),
+                media_file_id: ActiveValue::Set(media_file_id),
+                position: ActiveValue::Set(index as i32),
+                ..Default::default()
+            })
+            .collect();
+
+        if !models.is_empty() {
+            media_file_playlists::Entity::insert_many(models)
+                .exec(&txn)
+                .await?;
+        }
+
+        Ok::<_, anyhow::Error>((playlist, result))
+    }
+    .await;
+
+    match import {
+        Ok((playlist, result)) => {
+            txn.commit().await?;
+            info!(
+                "Imported playlist bundle {}: {} matched, {} unmatched, {} duplicated",
+                bundle.name,
+                result.matched.len(),
+                result.unmatched.len(),
+                result.duplicates.len()
+            );
+            Ok((playlist, result))
+        }
+        Err(e) => {
+            txn.rollback().await?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{FakeTrack, connect_test_main_db, seed_fake_tracks};
+
+    fn sample_bundle() -> PlaylistBundle {
+        PlaylistBundle {
+            version: PLAYLIST_BUNDLE_VERSION,
+            name: "Road Trip".to_owned(),
+            group: "Shared".to_owned(),
+            author: "node".to_owned(),
+            created_at: "2025-06-01T00:00:00+00:00".to_owned(),
+            tracks: vec![PlaylistBundleTrack {
+                recording_mbid: Some("b1a9c0e9-d987-4042-ae91-78d6a3267d69".to_owned()),
+                fingerprint: None,
+                title: "Song".to_owned(),
+                artist: "Artist".to_owned(),
+                album: "Album".to_owned(),
+                duration: 215.0,
+            }],
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let mut bundle = sample_bundle();
+        bundle.sign("secret").unwrap();
+        assert!(bundle.verify("secret").is_ok());
+        assert!(bundle.verify("another secret").is_err());
+    }
+
+    #[test]
+    fn test_tampered_bundle_is_rejected() {
+        let mut bundle = sample_bundle();
+        bundle.sign("secret").unwrap();
+        bundle.tracks[0].title = "Other Song".to_owned();
+        assert!(bundle.verify("secret").is_err());
+    }
+
+    #[test]
+    fn test_unsigned_bundle_is_rejected() {
+        assert!(sample_bundle().verify("secret").is_err());
+    }
+
+    fn fake_bundle_track(track: &FakeTrack) -> PlaylistBundleTrack {
+        PlaylistBundleTrack {
+            recording_mbid: None,
+            fingerprint: None,
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            album: track.album.clone(),
+            duration: track.duration,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_match_reports_duplicates() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        let mut missing = fake_bundle_track(&FakeTrack::nth(0));
+        missing.title = "Missing".to_owned();
+
+        let mut bundle = sample_bundle();
+        bundle.tracks = vec![
+            fake_bundle_track(&FakeTrack::nth(0)),
+            fake_bundle_track(&FakeTrack::nth(0)),
+            missing.clone(),
+            fake_bundle_track(&FakeTrack::nth(1)),
+        ];
+
+        let result = match_playlist_bundle(&db, &bundle).await?;
+        assert_eq!(result.matched_ids(), file_ids);
+        assert_eq!(
+            result.duplicates,
+            vec![fake_bundle_track(&FakeTrack::nth(0))]
+        );
+        assert_eq!(result.unmatched, vec![missing]);
+
+        Ok(())
+    }
+}
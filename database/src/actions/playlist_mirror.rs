@@ -0,0 +1,647 @@
+//! Two-way sync between playlists and `.m3u8` files on disk.
+//!
+//! A mirrored playlist is written to its file whenever it changes in Rune,
+//! and read back whenever another app edits the file. The hashes of both
+//! sides at the last sync tell which side has moved on. When both have, the
+//! mirror is flagged as conflicting and left alone until the user picks the
+//! side to keep.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use log::{debug, error, info, warn};
+use notify::{RecursiveMode, Watcher};
+use sea_orm::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveValue, QueryOrder, TransactionTrait};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::actions::playlists::{PlaylistImportResult, import_m3u8_to_playlist};
+use crate::entities::{media_file_playlists, media_files, playlist_mirrors, playlists};
+
+/// The side kept when resolving a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorSide {
+    File,
+    Playlist,
+}
+
+impl std::str::FromStr for MirrorSide {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "file" => Ok(MirrorSide::File),
+            "playlist" => Ok(MirrorSide::Playlist),
+            _ => Err(anyhow::anyhow!("Unknown mirror side: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirrorSyncOutcome {
+    Unchanged,
+    /// The playlist was written to the file.
+    Exported,
+    /// The file was read into the playlist, with the lines that matched no
+    /// track in the library.
+    Imported {
+        unmatched_paths: Vec<String>,
+    },
+    /// Both sides changed since the last sync.
+    Conflict,
+}
+
+fn hash_content(content: &str) -> String {
+    blake3::hash(content.as_bytes()).to_hex().to_string()
+}
+
+/// Render a playlist as an extended M3U file with absolute paths, in the
+/// order of the playlist.
+pub async fn render_playlist_m3u8(
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    playlist_id: i32,
+) -> Result<String> {
+    let items = media_file_playlists::Entity::find()
+        .filter(media_file_playlists::Column::PlaylistId.eq(playlist_id))
+        .order_by_asc(media_file_playlists::Column::Position)
+        .all(main_db)
+        .await?;
+
+    let file_ids: HashSet<i32> = items.iter().map(|x| x.media_file_id).collect();
+    let files: HashMap<i32, media_files::Model> = media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(file_ids))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+
+    let mut content = String::from("#EXTM3U\n");
+    for item in items {
+        let Some(file) = files.get(&item.media_file_id) else {
+            continue;
+        };
+
+        let path = lib_path.join(&file.directory).join(&file.file_name);
+        content.push_str(&path.to_string_lossy());
+        content.push('\n');
+    }
+
+    Ok(content)
+}
+
+async fn read_mirror_file(path: &Path) -> Result<Option<String>> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {path:?}")),
+    }
+}
+
+async fn write_mirror_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    // Other apps may read the file at any time, so it is replaced in one go
+    let tmp_path = path.with_extension("m3u8.tmp");
+    tokio::fs::write(&tmp_path, content).await?;
+    tokio::fs::rename(&tmp_path, path)
+        .await
+        .with_context(|| format!("Failed to write {path:?}"))?;
+
+    Ok(())
+}
+
+/// Replace the items of a playlist with the tracks listed in a file.
+async fn import_mirror_file(
+    main_db: &DatabaseConnection,
+    node_id: &str,
+    playlist_id: i32,
+    path: &Path,
+) -> Result<PlaylistImportResult> {
+    let txn = main_db.begin().await?;
+
+    media_file_playlists::Entity::delete_many()
+        .filter(media_file_playlists::Column::PlaylistId.eq(playlist_id))
+        .exec(&txn)
+        .await?;
+
+    let import_result = import_m3u8_to_playlist(&txn, playlist_id, path).await?;
+
+    playlists::Entity::update_many()
+        .col_expr(
+            playlists::Column::UpdatedAtHlcTs,
+            Expr::value(Utc::now().to_rfc3339()),
+        )
+        .col_expr(
+            playlists::Column::UpdatedAtHlcNid,
+            Expr::value(node_id.to_owned()),
+        )
+        .col_expr(
+            playlists::Column::UpdatedAtHlcVer,
+            Expr::add(Expr::col(playlists::Column::UpdatedAtHlcVer), 1),
+        )
+        .filter(playlists::Column::Id.eq(playlist_id))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+
+    Ok(import_result)
+}
+
+async fn save_sync_state(
+    main_db: &DatabaseConnection,
+    mirror: playlist_mirrors::Model,
+    file_hash: String,
+    playlist_hash: String,
+    conflict: bool,
+) -> Result<playlist_mirrors::Model> {
+    let mut active_model: playlist_mirrors::ActiveModel = mirror.into();
+    active_model.file_hash = ActiveValue::Set(Some(file_hash));
+    active_model.playlist_hash = ActiveValue::Set(Some(playlist_hash));
+    active_model.conflict = ActiveValue::Set(conflict);
+    active_model.synced_at = ActiveValue::Set(Some(Utc::now().to_rfc3339()));
+
+    Ok(active_model.update(main_db).await?)
+}
+
+async fn export_mirror(
+    main_db: &DatabaseConnection,
+    mirror: playlist_mirrors::Model,
+    rendered: &str,
+) -> Result<MirrorSyncOutcome> {
+    write_mirror_file(Path::new(&mirror.path), rendered).await?;
+
+    let hash = hash_content(rendered);
+    save_sync_state(main_db, mirror, hash.clone(), hash, false).await?;
+
+    Ok(MirrorSyncOutcome::Exported)
+}
+
+async fn import_mirror(
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    mirror: playlist_mirrors::Model,
+    file_content: &str,
+) -> Result<MirrorSyncOutcome> {
+    let import_result = import_mirror_file(
+        main_db,
+        node_id,
+        mirror.playlist_id,
+        Path::new(&mirror.path),
+    )
+    .await?;
+
+    // The file is not rewritten, so lines matching no track survive until
+    // the playlist is edited in Rune
+    let rendered = render_playlist_m3u8(main_db, lib_path, mirror.playlist_id).await?;
+    save_sync_state(
+        main_db,
+        mirror,
+        hash_content(file_content),
+        hash_content(&rendered),
+        false,
+    )
+    .await?;
+
+    Ok(MirrorSyncOutcome::Imported {
+        unmatched_paths: import_result.unmatched_paths,
+    })
+}
+
+/// Bring a playlist and its file in line with each other.
+pub async fn sync_playlist_mirror(
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    mirror: playlist_mirrors::Model,
+) -> Result<MirrorSyncOutcome> {
+    if mirror.conflict {
+        return Ok(MirrorSyncOutcome::Conflict);
+    }
+
+    let rendered = render_playlist_m3u8(main_db, lib_path, mirror.playlist_id).await?;
+    let Some(file_content) = read_mirror_file(Path::new(&mirror.path)).await? else {
+        return export_mirror(main_db, mirror, &rendered).await;
+    };
+
+    let file_hash = hash_content(&file_content);
+    let playlist_hash = hash_content(&rendered);
+    let file_changed = mirror.file_hash.as_deref() != Some(file_hash.as_str());
+    let playlist_changed = mirror.playlist_hash.as_deref() != Some(playlist_hash.as_str());
+
+    match (file_changed, playlist_changed) {
+        (false, false) => Ok(MirrorSyncOutcome::Unchanged),
+        (true, false) => import_mirror(main_db, lib_path, node_id, mirror, &file_content).await,
+        (false, true) => export_mirror(main_db, mirror, &rendered).await,
+        // Both sides made the same edit
+        (true, true) if file_hash == playlist_hash => {
+            save_sync_state(main_db, mirror, file_hash, playlist_hash, false).await?;
+            Ok(MirrorSyncOutcome::Unchanged)
+        }
+        (true, true) => {
+            warn!(
+                "Playlist {} and {} were both changed, sync is paused",
+                mirror.playlist_id, mirror.path
+            );
+            let mut active_model: playlist_mirrors::ActiveModel = mirror.into();
+            active_model.conflict = ActiveValue::Set(true);
+            active_model.update(main_db).await?;
+
+            Ok(MirrorSyncOutcome::Conflict)
+        }
+    }
+}
+
+/// Sync every mirror, a failing mirror doesn't stop the others.
+pub async fn sync_playlist_mirrors(
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+) -> Result<Vec<(playlist_mirrors::Model, MirrorSyncOutcome)>> {
+    let mut outcomes = Vec::new();
+
+    for mirror in list_playlist_mirrors(main_db).await? {
+        match sync_playlist_mirror(main_db, lib_path, node_id, mirror.clone()).await {
+            Ok(outcome) => outcomes.push((mirror, outcome)),
+            Err(e) => error!("Failed to sync playlist mirror {}: {e:#?}", mirror.path),
+        }
+    }
+
+    Ok(outcomes)
+}
+
+pub async fn list_playlist_mirrors(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<playlist_mirrors::Model>> {
+    Ok(playlist_mirrors::Entity::find()
+        .order_by_asc(playlist_mirrors::Column::Id)
+        .all(main_db)
+        .await?)
+}
+
+pub async fn get_playlist_mirror(
+    main_db: &DatabaseConnection,
+    playlist_id: i32,
+) -> Result<Option<playlist_mirrors::Model>> {
+    Ok(playlist_mirrors::Entity::find()
+        .filter(playlist_mirrors::Column::PlaylistId.eq(playlist_id))
+        .one(main_db)
+        .await?)
+}
+
+/// Start mirroring a playlist to `path` and run the first sync.
+///
+/// An existing file is read into an empty playlist. If both already hold
+/// different tracks, the mirror starts out conflicting.
+pub async fn add_playlist_mirror(
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    playlist_id: i32,
+    path: &Path,
+) -> Result<(playlist_mirrors::Model, MirrorSyncOutcome)> {
+    if playlists::Entity::find_by_id(playlist_id)
+        .one(main_db)
+        .await?
+        .is_none()
+    {
+        bail!("Playlist not found");
+    }
+
+    if get_playlist_mirror(main_db, playlist_id).await?.is_some() {
+        bail!("Playlist {playlist_id} is already mirrored");
+    }
+
+    let Some(path) = path.to_str() else {
+        bail!("Mirror path is not valid UTF-8: {path:?}");
+    };
+
+    let mirror = playlist_mirrors::ActiveModel {
+        playlist_id: ActiveValue::Set(playlist_id),
+        path: ActiveValue::Set(path.to_owned()),
+        ..Default::default()
+    }
+    .insert(main_db)
+    .await?;
+
+    let is_empty = media_file_playlists::Entity::find()
+        .filter(media_file_playlists::Column::PlaylistId.eq(playlist_id))
+        .one(main_db)
+        .await?
+        .is_none();
+
+    let outcome = match read_mirror_file(Path::new(path)).await? {
+        Some(content) if is_empty => {
+            import_mirror(main_db, lib_path, node_id, mirror, &content).await?
+        }
+        _ => sync_playlist_mirror(main_db, lib_path, node_id, mirror).await?,
+    };
+
+    let mirror = get_playlist_mirror(main_db, playlist_id)
+        .await?
+        .context("Playlist mirror vanished while syncing")?;
+
+    info!("Mirroring playlist {playlist_id} to {path}: {outcome:?}");
+
+    Ok((mirror, outcome))
+}
+
+/// Stop mirroring a playlist, the file is left on disk.
+pub async fn remove_playlist_mirror(main_db: &DatabaseConnection, playlist_id: i32) -> Result<()> {
+    let result = playlist_mirrors::Entity::delete_many()
+        .filter(playlist_mirrors::Column::PlaylistId.eq(playlist_id))
+        .exec(main_db)
+        .await?;
+
+    if result.rows_affected == 0 {
+        bail!("Playlist {playlist_id} is not mirrored");
+    }
+
+    Ok(())
+}
+
+/// Settle a conflict by overwriting the other side with `keep`.
+pub async fn resolve_playlist_mirror_conflict(
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    playlist_id: i32,
+    keep: MirrorSide,
+) -> Result<MirrorSyncOutcome> {
+    let Some(mirror) = get_playlist_mirror(main_db, playlist_id).await? else {
+        bail!("Playlist {playlist_id} is not mirrored");
+    };
+
+    let file_content = read_mirror_file(Path::new(&mirror.path)).await?;
+    match (keep, file_content) {
+        (MirrorSide::File, Some(content)) => {
+            import_mirror(main_db, lib_path, node_id, mirror, &content).await
+        }
+        (MirrorSide::File, None) => bail!("Mirror file is gone: {}", mirror.path),
+        (MirrorSide::Playlist, _) => {
+            let rendered = render_playlist_m3u8(main_db, lib_path, playlist_id).await?;
+            export_mirror(main_db, mirror, &rendered).await
+        }
+    }
+}
+
+/// Keep every mirror in sync until cancelled.
+///
+/// The directories of the mirror files are watched for edits from other
+/// apps, while edits made in Rune are picked up every `poll_interval`. The
+/// set of watched directories follows mirrors being added or removed.
+pub async fn watch_playlist_mirrors(
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    debounce: Duration,
+    poll_interval: Duration,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .with_context(|| "Failed to create playlist mirror watcher")?;
+
+    let mut watched_dirs: HashSet<PathBuf> = HashSet::new();
+    let mut poll = tokio::time::interval(poll_interval);
+
+    info!("Watching playlist mirrors");
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            _ = poll.tick() => {},
+            event = rx.recv() => match event {
+                Some(Ok(event)) => {
+                    debug!("Playlist mirror event: {:?}", event.paths);
+                    // Editors often save in several steps
+                    loop {
+                        tokio::select! {
+                            _ = cancel_token.cancelled() => return Ok(()),
+                            event = tokio::time::timeout(debounce, rx.recv()) => match event {
+                                Ok(Some(_)) => {},
+                                Ok(None) | Err(_) => break,
+                            },
+                        }
+                    }
+                }
+                Some(Err(e)) => error!("Watcher error: {e}"),
+                None => break,
+            },
+        }
+
+        let mirrors = match list_playlist_mirrors(main_db).await {
+            Ok(mirrors) => mirrors,
+            Err(e) => {
+                error!("Failed to list playlist mirrors: {e:#?}");
+                continue;
+            }
+        };
+
+        let dirs: HashSet<PathBuf> = mirrors
+            .iter()
+            .filter_map(|x| Path::new(&x.path).parent().map(Path::to_path_buf))
+            .collect();
+        for dir in watched_dirs.difference(&dirs) {
+            let _ = watcher.unwatch(dir);
+        }
+        watched_dirs.retain(|x| dirs.contains(x));
+        for dir in dirs {
+            if watched_dirs.contains(&dir) || !dir.exists() {
+                continue;
+            }
+            match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(_) => {
+                    watched_dirs.insert(dir);
+                }
+                Err(e) => warn!("Failed to watch {dir:?}: {e}"),
+            }
+        }
+
+        for mirror in mirrors {
+            match sync_playlist_mirror(main_db, lib_path, node_id, mirror.clone()).await {
+                Ok(MirrorSyncOutcome::Unchanged) | Ok(MirrorSyncOutcome::Conflict) => {}
+                Ok(outcome) => info!("Synced playlist mirror {}: {outcome:?}", mirror.path),
+                Err(e) => error!("Failed to sync playlist mirror {}: {e:#?}", mirror.path),
+            }
+        }
+    }
+
+    info!("Stopped watching playlist mirrors");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    use crate::{
+        actions::{
+            playlist_mirror::{
+                MirrorSide, MirrorSyncOutcome, add_playlist_mirror, get_playlist_mirror,
+                render_playlist_m3u8, resolve_playlist_mirror_conflict, sync_playlist_mirror,
+            },
+            playlists::{add_item_to_playlist, create_playlist},
+        },
+        test_support::{TEST_NODE_ID, connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_playlist_mirror_two_way_sync() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        let playlist = create_playlist(&db, TEST_NODE_ID, "Mirrored".into(), "".into()).await?;
+        add_item_to_playlist(&db, TEST_NODE_ID, playlist.id, file_ids[0], None).await?;
+
+        // A missing file is written from the playlist
+        let path = lib_dir.path().join("Mirrored.m3u8");
+        let (mirror, outcome) =
+            add_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, playlist.id, &path).await?;
+        assert_eq!(outcome, MirrorSyncOutcome::Exported);
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            render_playlist_m3u8(&db, lib_dir.path(), playlist.id).await?
+        );
+        assert_eq!(
+            sync_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, mirror).await?,
+            MirrorSyncOutcome::Unchanged
+        );
+
+        // Edits in Rune are written out
+        add_item_to_playlist(&db, TEST_NODE_ID, playlist.id, file_ids[1], None).await?;
+        let mirror = get_playlist_mirror(&db, playlist.id).await?.unwrap();
+        assert_eq!(
+            sync_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, mirror).await?,
+            MirrorSyncOutcome::Exported
+        );
+        assert!(fs::read_to_string(&path)?.contains("0001.flac"));
+
+        // Edits of the file are read back
+        fs::write(
+            &path,
+            "#EXTM3U\n/elsewhere/0002.flac\n/elsewhere/missing.flac\n",
+        )?;
+        let mirror = get_playlist_mirror(&db, playlist.id).await?.unwrap();
+        assert_eq!(
+            sync_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, mirror).await?,
+            MirrorSyncOutcome::Imported {
+                unmatched_paths: vec!["/elsewhere/missing.flac".to_owned()]
+            }
+        );
+        let rendered = render_playlist_m3u8(&db, lib_dir.path(), playlist.id).await?;
+        assert_eq!(rendered.lines().count(), 2);
+        assert!(rendered.contains("0002.flac"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_playlist_mirror_conflict() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        let playlist = create_playlist(&db, TEST_NODE_ID, "Mirrored".into(), "".into()).await?;
+        add_item_to_playlist(&db, TEST_NODE_ID, playlist.id, file_ids[0], None).await?;
+
+        let path = lib_dir.path().join("Mirrored.m3u8");
+        add_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, playlist.id, &path).await?;
+
+        // Both sides change before the next sync
+        add_item_to_playlist(&db, TEST_NODE_ID, playlist.id, file_ids[1], None).await?;
+        fs::write(&path, "#EXTM3U\n/elsewhere/0002.flac\n")?;
+
+        let mirror = get_playlist_mirror(&db, playlist.id).await?.unwrap();
+        assert_eq!(
+            sync_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, mirror).await?,
+            MirrorSyncOutcome::Conflict
+        );
+
+        // Nothing is written while the conflict stands
+        let mirror = get_playlist_mirror(&db, playlist.id).await?.unwrap();
+        assert!(mirror.conflict);
+        assert_eq!(
+            sync_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, mirror).await?,
+            MirrorSyncOutcome::Conflict
+        );
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "#EXTM3U\n/elsewhere/0002.flac\n"
+        );
+
+        let outcome = resolve_playlist_mirror_conflict(
+            &db,
+            lib_dir.path(),
+            TEST_NODE_ID,
+            playlist.id,
+            MirrorSide::Playlist,
+        )
+        .await?;
+        assert_eq!(outcome, MirrorSyncOutcome::Exported);
+        assert!(
+            !get_playlist_mirror(&db, playlist.id)
+                .await?
+                .unwrap()
+                .conflict
+        );
+        assert!(fs::read_to_string(&path)?.contains("0001.flac"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_playlist_mirror_imports_into_empty_playlist() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        seed_fake_tracks(&db, 3).await?;
+
+        let path = lib_dir.path().join("Existing.m3u8");
+        fs::write(&path, "#EXTM3U\n0001.flac\n0000.flac\n")?;
+
+        let playlist = create_playlist(&db, TEST_NODE_ID, "Existing".into(), "".into()).await?;
+        let (mirror, outcome) =
+            add_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, playlist.id, &path).await?;
+        assert_eq!(
+            outcome,
+            MirrorSyncOutcome::Imported {
+                unmatched_paths: vec![]
+            }
+        );
+        assert!(mirror.synced_at.is_some());
+
+        let rendered = render_playlist_m3u8(&db, lib_dir.path(), playlist.id).await?;
+        let names: Vec<&str> = rendered
+            .lines()
+            .skip(1)
+            .filter_map(|x| x.rsplit('/').next())
+            .collect();
+        assert_eq!(names, vec!["0001.flac", "0000.flac"]);
+
+        // A playlist can only have one mirror
+        assert!(
+            add_playlist_mirror(&db, lib_dir.path(), TEST_NODE_ID, playlist.id, &path)
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -1,19 +1,24 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
-use anyhow::{bail, Result};
+use anyhow::{Context, Result, bail};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 use log::info;
+use migration::{ExprTrait, IntoCondition};
 use sea_orm::ActiveValue;
 use sea_orm::QueryOrder;
-use sea_orm::{prelude::*, TransactionTrait};
+use sea_orm::sea_query::{Alias, Condition, Expr, Func, SelectStatement, SimpleExpr};
+use sea_orm::{QuerySelect, QueryTrait, TransactionTrait, prelude::*};
 use tokio::fs::read_to_string;
 
 use crate::actions::collection::CollectionQuery;
 use crate::actions::search::{add_term, remove_term};
 use crate::connection::MainDbConnection;
-use crate::entities::{media_file_playlists, media_files, playlists};
+use crate::entities::{
+    media_analysis, media_file_playlists, media_file_stats, media_files, media_metadata, playlists,
+    smart_playlists,
+};
 use crate::{collection_query, get_by_id};
 
 use super::collection::CollectionQueryType;
@@ -517,9 +522,7 @@ pub async fn remove_item_from_playlist(
 
     let txn = main_db.begin().await?;
 
-    info!(
-        "Removing item {media_file_id}(pos: {position}) from playlist {playlist_id}"
-    );
+    info!("Removing item {media_file_id}(pos: {position}) from playlist {playlist_id}");
     let delete_result = MediaFilePlaylistEntity::delete_many()
         .filter(media_file_playlists::Column::PlaylistId.eq(playlist_id))
         .filter(media_file_playlists::Column::MediaFileId.eq(media_file_id))
@@ -562,3 +565,804 @@ pub async fn remove_item_from_playlist(
 
     Ok(())
 }
+
+/// A value of a track that smart playlist rules can test.
+#[derive(Debug, Clone)]
+pub enum SmartPlaylistField {
+    /// A tag in `media_metadata`, e.g. `genre` or `artist`.
+    Metadata(String),
+    /// An analysis result, tracks that are not analyzed never match.
+    Analysis(media_analysis::Column),
+    Duration,
+    SampleRate,
+    PlayCount,
+    SkipCount,
+    Liked,
+    Rating,
+}
+
+impl SmartPlaylistField {
+    fn parse(name: &str) -> Self {
+        use media_analysis::Column;
+
+        let name = name.to_lowercase();
+        match name.as_str() {
+            "duration" => SmartPlaylistField::Duration,
+            "sample_rate" => SmartPlaylistField::SampleRate,
+            "play_count" | "plays" => SmartPlaylistField::PlayCount,
+            "skip_count" | "skips" => SmartPlaylistField::SkipCount,
+            "liked" => SmartPlaylistField::Liked,
+            "rating" | "stars" => SmartPlaylistField::Rating,
+            "bpm" | "tempo" => SmartPlaylistField::Analysis(Column::Bpm),
+            "key" => SmartPlaylistField::Analysis(Column::Key),
+            "mode" => SmartPlaylistField::Analysis(Column::Mode),
+            "rms" => SmartPlaylistField::Analysis(Column::Rms),
+            "zcr" => SmartPlaylistField::Analysis(Column::Zcr),
+            "energy" => SmartPlaylistField::Analysis(Column::Energy),
+            "loudness" => SmartPlaylistField::Analysis(Column::IntegratedLoudness),
+            "true_peak" => SmartPlaylistField::Analysis(Column::TruePeak),
+            "loudness_range" => SmartPlaylistField::Analysis(Column::LoudnessRange),
+            "spectral_centroid" => SmartPlaylistField::Analysis(Column::SpectralCentroid),
+            "spectral_flatness" => SmartPlaylistField::Analysis(Column::SpectralFlatness),
+            "spectral_rolloff" => SmartPlaylistField::Analysis(Column::SpectralRolloff),
+            "title" => SmartPlaylistField::Metadata("track_title".to_owned()),
+            _ => SmartPlaylistField::Metadata(name),
+        }
+    }
+}
+
+/// A point in time of a track, used by `<date> in <n> days` rules.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmartPlaylistDate {
+    /// The last time the track was played through.
+    Played,
+    /// The modification time of the file.
+    Modified,
+}
+
+impl SmartPlaylistDate {
+    fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "played" | "last_played" => Ok(SmartPlaylistDate::Played),
+            "modified" | "last_modified" => Ok(SmartPlaylistDate::Modified),
+            _ => bail!("Unknown date field: {name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmartPlaylistOperator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Case insensitive substring match, only for text.
+    Contains,
+    NotContains,
+}
+
+impl SmartPlaylistOperator {
+    fn compare_numbers(&self, left: f64, right: f64) -> bool {
+        match self {
+            SmartPlaylistOperator::Eq => left == right,
+            SmartPlaylistOperator::Ne => left != right,
+            SmartPlaylistOperator::Lt => left < right,
+            SmartPlaylistOperator::Le => left <= right,
+            SmartPlaylistOperator::Gt => left > right,
+            SmartPlaylistOperator::Ge => left >= right,
+            SmartPlaylistOperator::Contains | SmartPlaylistOperator::NotContains => false,
+        }
+    }
+
+    fn compare_expr(&self, expr: SimpleExpr, value: f64) -> Result<SimpleExpr> {
+        let expr = Expr::expr(expr);
+        Ok(match self {
+            SmartPlaylistOperator::Eq => expr.eq(value),
+            SmartPlaylistOperator::Ne => expr.ne(value),
+            SmartPlaylistOperator::Lt => expr.lt(value),
+            SmartPlaylistOperator::Le => expr.lte(value),
+            SmartPlaylistOperator::Gt => expr.gt(value),
+            SmartPlaylistOperator::Ge => expr.gte(value),
+            SmartPlaylistOperator::Contains | SmartPlaylistOperator::NotContains => {
+                bail!("Contains operators only apply to text fields")
+            }
+        })
+    }
+}
+
+/// A parsed smart playlist rule, e.g.
+/// `genre = jazz AND bpm > 120 AND NOT played in 30 days`.
+#[derive(Debug, Clone)]
+pub enum SmartPlaylistRule {
+    And(Vec<SmartPlaylistRule>),
+    Or(Vec<SmartPlaylistRule>),
+    Not(Box<SmartPlaylistRule>),
+    Compare {
+        field: SmartPlaylistField,
+        operator: SmartPlaylistOperator,
+        value: String,
+    },
+    Within {
+        date: SmartPlaylistDate,
+        days: u32,
+    },
+}
+
+fn parse_number(field: &SmartPlaylistField, value: &str) -> Result<f64> {
+    value
+        .parse::<f64>()
+        .with_context(|| format!("Expected a number for {field:?}, got: {value}"))
+}
+
+/// Match files by a condition on `media_file_stats`, files without stats
+/// are treated as never played, skipped, liked or rated.
+fn stats_condition(
+    column: media_file_stats::Column,
+    operator: SmartPlaylistOperator,
+    value: f64,
+) -> Result<SimpleExpr> {
+    let condition = operator.compare_expr(Expr::col(column).into(), value)?;
+
+    if operator.compare_numbers(0.0, value) {
+        let subquery = media_file_stats::Entity::find()
+            .select_only()
+            .column(media_file_stats::Column::MediaFileId)
+            .filter(Condition::not(condition.into_condition()))
+            .into_query();
+        Ok(Expr::cust("\"media_files\".\"id\"").not_in_subquery(subquery))
+    } else {
+        let subquery = media_file_stats::Entity::find()
+            .select_only()
+            .column(media_file_stats::Column::MediaFileId)
+            .filter(condition)
+            .into_query();
+        Ok(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery))
+    }
+}
+
+fn metadata_condition(
+    key: &str,
+    operator: SmartPlaylistOperator,
+    value: &str,
+) -> Result<SimpleExpr> {
+    let meta_value = Expr::col(media_metadata::Column::MetaValue);
+    let (negated, condition) = match operator {
+        SmartPlaylistOperator::Eq | SmartPlaylistOperator::Ne => (
+            operator == SmartPlaylistOperator::Ne,
+            Expr::expr(SimpleExpr::FunctionCall(Func::lower(meta_value))).eq(value.to_lowercase()),
+        ),
+        SmartPlaylistOperator::Contains | SmartPlaylistOperator::NotContains => (
+            operator == SmartPlaylistOperator::NotContains,
+            meta_value.like(format!("%{value}%")),
+        ),
+        // Ordering only makes sense for numeric tags like `year`
+        _ => {
+            let value = parse_number(&SmartPlaylistField::Metadata(key.to_owned()), value)?;
+            (
+                false,
+                operator.compare_expr(meta_value.cast_as(Alias::new("REAL")), value)?,
+            )
+        }
+    };
+
+    let subquery = media_metadata::Entity::find()
+        .select_only()
+        .column(media_metadata::Column::FileId)
+        .filter(media_metadata::Column::MetaKey.eq(key))
+        .filter(condition)
+        .into_query();
+
+    // Files without the tag are not equal to anything either
+    if negated {
+        Ok(Expr::cust("\"media_files\".\"id\"").not_in_subquery(subquery))
+    } else {
+        Ok(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery))
+    }
+}
+
+impl SmartPlaylistRule {
+    /// Translate the rule into a condition on `media_files`, relative
+    /// dates are resolved against `now`.
+    pub fn to_condition(&self, now: DateTime<Utc>) -> Result<Condition> {
+        match self {
+            SmartPlaylistRule::And(rules) => rules
+                .iter()
+                .try_fold(Condition::all(), |acc, x| Ok(acc.add(x.to_condition(now)?))),
+            SmartPlaylistRule::Or(rules) => rules
+                .iter()
+                .try_fold(Condition::any(), |acc, x| Ok(acc.add(x.to_condition(now)?))),
+            SmartPlaylistRule::Not(rule) => Ok(rule.to_condition(now)?.not()),
+            SmartPlaylistRule::Compare {
+                field,
+                operator,
+                value,
+            } => {
+                let expr = match field {
+                    SmartPlaylistField::Metadata(key) => metadata_condition(key, *operator, value)?,
+                    SmartPlaylistField::Analysis(column) => {
+                        let value = parse_number(field, value)?;
+                        let subquery = media_analysis::Entity::find()
+                            .select_only()
+                            .column(media_analysis::Column::FileId)
+                            .filter(operator.compare_expr(Expr::col(*column).into(), value)?)
+                            .into_query();
+                        Expr::cust("\"media_files\".\"id\"").in_subquery(subquery)
+                    }
+                    SmartPlaylistField::Duration => operator.compare_expr(
+                        Expr::col((media_files::Entity, media_files::Column::Duration)).into(),
+                        parse_number(field, value)?,
+                    )?,
+                    SmartPlaylistField::SampleRate => operator.compare_expr(
+                        Expr::col((media_files::Entity, media_files::Column::SampleRate)).into(),
+                        parse_number(field, value)?,
+                    )?,
+                    SmartPlaylistField::PlayCount => stats_condition(
+                        media_file_stats::Column::PlayedThrough,
+                        *operator,
+                        parse_number(field, value)?,
+                    )?,
+                    SmartPlaylistField::SkipCount => stats_condition(
+                        media_file_stats::Column::Skipped,
+                        *operator,
+                        parse_number(field, value)?,
+                    )?,
+                    SmartPlaylistField::Rating => stats_condition(
+                        media_file_stats::Column::Rating,
+                        *operator,
+                        parse_number(field, value)?,
+                    )?,
+                    SmartPlaylistField::Liked => {
+                        let liked = value
+                            .parse::<bool>()
+                            .with_context(|| format!("Expected true or false, got: {value}"))?;
+                        stats_condition(
+                            media_file_stats::Column::Liked,
+                            *operator,
+                            if liked { 1.0 } else { 0.0 },
+                        )?
+                    }
+                };
+
+                Ok(Condition::all().add(expr))
+            }
+            SmartPlaylistRule::Within { date, days } => {
+                let since = Duration::try_days(i64::from(*days))
+                    .and_then(|x| now.checked_sub_signed(x))
+                    .with_context(|| format!("Too many days to look back: {days}"))?;
+
+                let expr = match date {
+                    // Stats only keep the time of the last change, which is
+                    // the last play as long as the track was played at all
+                    SmartPlaylistDate::Played => {
+                        let subquery = media_file_stats::Entity::find()
+                            .select_only()
+                            .column(media_file_stats::Column::MediaFileId)
+                            .filter(media_file_stats::Column::PlayedThrough.gt(0))
+                            .filter(media_file_stats::Column::UpdatedAt.gte(since.to_rfc3339()))
+                            .into_query();
+                        Expr::cust("\"media_files\".\"id\"").in_subquery(subquery)
+                    }
+                    SmartPlaylistDate::Modified => {
+                        Expr::col((media_files::Entity, media_files::Column::LastModified))
+                            .cast_as(Alias::new("INTEGER"))
+                            .gte(since.timestamp())
+                    }
+                };
+
+                Ok(Condition::all().add(expr))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RuleToken {
+    Word(String),
+    Quoted(String),
+    Operator(SmartPlaylistOperator),
+    LeftParen,
+    RightParen,
+}
+
+fn tokenize_rules(input: &str) -> Result<Vec<RuleToken>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(RuleToken::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(RuleToken::RightParen);
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => bail!("Unterminated string in rules: {input}"),
+                    }
+                }
+                tokens.push(RuleToken::Quoted(value));
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                chars.next();
+                let operator = match (c, chars.peek()) {
+                    ('!', Some('=')) => SmartPlaylistOperator::Ne,
+                    ('!', Some('~')) => SmartPlaylistOperator::NotContains,
+                    ('<', Some('=')) => SmartPlaylistOperator::Le,
+                    ('>', Some('=')) => SmartPlaylistOperator::Ge,
+                    ('!', _) => bail!("Unexpected '!' in rules: {input}"),
+                    ('=', _) => SmartPlaylistOperator::Eq,
+                    ('<', _) => SmartPlaylistOperator::Lt,
+                    ('>', _) => SmartPlaylistOperator::Gt,
+                    _ => SmartPlaylistOperator::Contains,
+                };
+                if matches!(
+                    operator,
+                    SmartPlaylistOperator::Ne
+                        | SmartPlaylistOperator::NotContains
+                        | SmartPlaylistOperator::Le
+                        | SmartPlaylistOperator::Ge
+                ) {
+                    chars.next();
+                }
+                tokens.push(RuleToken::Operator(operator));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()\"=!<>~".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(RuleToken::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct RuleParser {
+    tokens: Vec<RuleToken>,
+    position: usize,
+}
+
+impl RuleParser {
+    fn peek(&self) -> Option<&RuleToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<RuleToken> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(RuleToken::Word(x)) if x.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_word(&mut self) -> Result<String> {
+        match self.next() {
+            Some(RuleToken::Word(x)) | Some(RuleToken::Quoted(x)) => Ok(x),
+            Some(x) => bail!("Expected a value, got: {x:?}"),
+            None => bail!("Unexpected end of rules"),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<SmartPlaylistRule> {
+        let mut rules = vec![self.parse_and()?];
+        while self.eat_keyword("or") {
+            rules.push(self.parse_and()?);
+        }
+
+        Ok(if rules.len() == 1 {
+            rules.remove(0)
+        } else {
+            SmartPlaylistRule::Or(rules)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<SmartPlaylistRule> {
+        let mut rules = vec![self.parse_unary()?];
+        while self.eat_keyword("and") {
+            rules.push(self.parse_unary()?);
+        }
+
+        Ok(if rules.len() == 1 {
+            rules.remove(0)
+        } else {
+            SmartPlaylistRule::And(rules)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<SmartPlaylistRule> {
+        if self.eat_keyword("not") {
+            return Ok(SmartPlaylistRule::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.peek() == Some(&RuleToken::LeftParen) {
+            self.position += 1;
+            let rule = self.parse_or()?;
+            if self.next() != Some(RuleToken::RightParen) {
+                bail!("Expected ')' in rules");
+            }
+            return Ok(rule);
+        }
+
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<SmartPlaylistRule> {
+        let field = self.expect_word()?;
+
+        if self.eat_keyword("in") {
+            let days = self.expect_word()?;
+            let days = days
+                .parse::<u32>()
+                .with_context(|| format!("Expected a number of days, got: {days}"))?;
+            if !self.eat_keyword("days") && !self.eat_keyword("day") {
+                bail!("Expected 'days' after {field} in {days}");
+            }
+
+            return Ok(SmartPlaylistRule::Within {
+                date: SmartPlaylistDate::parse(&field)?,
+                days,
+            });
+        }
+
+        let operator = match self.next() {
+            Some(RuleToken::Operator(x)) => x,
+            Some(x) => bail!("Expected an operator after {field}, got: {x:?}"),
+            None => bail!("Expected an operator after {field}"),
+        };
+
+        Ok(SmartPlaylistRule::Compare {
+            field: SmartPlaylistField::parse(&field),
+            operator,
+            value: self.expect_word()?,
+        })
+    }
+}
+
+/// Parse smart playlist rules.
+///
+/// Predicates are `<field> <op> <value>` with `=`, `!=`, `<`, `<=`, `>`,
+/// `>=`, `~` (contains) and `!~`, or `<date> in <n> days` for `played` and
+/// `modified`. They can be combined with `AND`, `OR`, `NOT` and parentheses.
+/// Fields that are not play stats or analysis values refer to tags, and
+/// values with spaces have to be quoted.
+///
+/// # Arguments
+/// * `rules` - The rules to parse.
+///
+/// # Returns
+/// * `Result<SmartPlaylistRule>` - The parsed rule, or an error if the rules
+///   are malformed or a value does not fit its field.
+pub fn parse_smart_playlist_rules(rules: &str) -> Result<SmartPlaylistRule> {
+    let mut parser = RuleParser {
+        tokens: tokenize_rules(rules)?,
+        position: 0,
+    };
+
+    if parser.tokens.is_empty() {
+        bail!("Smart playlist rules are empty");
+    }
+
+    let rule = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        bail!("Unexpected {token:?} in rules: {rules}");
+    }
+
+    // Catch values that do not fit their fields before the rules are saved
+    rule.to_condition(Utc::now())?;
+
+    Ok(rule)
+}
+
+/// Find the files matching the given rule, ordered by path.
+pub async fn evaluate_smart_playlist_rule(
+    main_db: &DatabaseConnection,
+    rule: &SmartPlaylistRule,
+) -> Result<Vec<i32>> {
+    let file_ids = media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::Id)
+        .filter(rule.to_condition(Utc::now())?)
+        .order_by_asc(media_files::Column::Directory)
+        .order_by_asc(media_files::Column::FileName)
+        .into_tuple::<i32>()
+        .all(main_db)
+        .await?;
+
+    Ok(file_ids)
+}
+
+/// Create a new smart playlist.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `name` - The name of the new smart playlist.
+/// * `group` - The group to which the smart playlist belongs.
+/// * `rules` - The rules selecting the tracks, see [`parse_smart_playlist_rules`].
+///
+/// # Returns
+/// * `Result<Model>` - The created smart playlist model or an error.
+pub async fn create_smart_playlist(
+    main_db: &DatabaseConnection,
+    name: String,
+    group: String,
+    rules: String,
+) -> Result<smart_playlists::Model> {
+    parse_smart_playlist_rules(&rules)?;
+
+    let now = Utc::now().to_rfc3339();
+    let new_playlist = smart_playlists::ActiveModel {
+        name: ActiveValue::Set(name),
+        group: ActiveValue::Set(group),
+        rules: ActiveValue::Set(rules),
+        created_at: ActiveValue::Set(now.clone()),
+        updated_at: ActiveValue::Set(now),
+        ..Default::default()
+    };
+
+    Ok(new_playlist.insert(main_db).await?)
+}
+
+/// Get all smart playlists.
+pub async fn get_all_smart_playlists(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<smart_playlists::Model>> {
+    Ok(smart_playlists::Entity::find()
+        .order_by_asc(smart_playlists::Column::Group)
+        .all(main_db)
+        .await?)
+}
+
+/// Get a smart playlist by its ID.
+pub async fn get_smart_playlist_by_id(
+    main_db: &DatabaseConnection,
+    smart_playlist_id: i32,
+) -> Result<smart_playlists::Model> {
+    smart_playlists::Entity::find_by_id(smart_playlist_id)
+        .one(main_db)
+        .await?
+        .with_context(|| format!("Smart playlist not found: {smart_playlist_id}"))
+}
+
+/// Update an existing smart playlist, fields that are `None` are kept.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `smart_playlist_id` - The ID of the smart playlist to update.
+/// * `name` - The new name for the smart playlist.
+/// * `group` - The new group for the smart playlist.
+/// * `rules` - The new rules for the smart playlist.
+///
+/// # Returns
+/// * `Result<Model>` - The updated smart playlist model or an error.
+pub async fn update_smart_playlist(
+    main_db: &DatabaseConnection,
+    smart_playlist_id: i32,
+    name: Option<String>,
+    group: Option<String>,
+    rules: Option<String>,
+) -> Result<smart_playlists::Model> {
+    let playlist = get_smart_playlist_by_id(main_db, smart_playlist_id).await?;
+    let mut active_model: smart_playlists::ActiveModel = playlist.into();
+
+    if let Some(name) = name {
+        active_model.name = ActiveValue::Set(name);
+    }
+    if let Some(group) = group {
+        active_model.group = ActiveValue::Set(group);
+    }
+    if let Some(rules) = rules {
+        parse_smart_playlist_rules(&rules)?;
+        active_model.rules = ActiveValue::Set(rules);
+    }
+    active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
+
+    Ok(active_model.update(main_db).await?)
+}
+
+/// Remove a smart playlist by its ID.
+pub async fn remove_smart_playlist(
+    main_db: &DatabaseConnection,
+    smart_playlist_id: i32,
+) -> Result<()> {
+    let result = smart_playlists::Entity::delete_by_id(smart_playlist_id)
+        .exec(main_db)
+        .await?;
+
+    if result.rows_affected == 0 {
+        bail!("Smart playlist not found");
+    }
+
+    Ok(())
+}
+
+/// Build a subquery selecting the file IDs of a smart playlist.
+///
+/// Smart playlists only store their rules, the tracks are resolved every
+/// time the playlist is queried, so the result always reflects the current
+/// library and play stats.
+pub async fn smart_playlist_subquery(
+    main_db: &DatabaseConnection,
+    smart_playlist_id: i32,
+) -> Result<SelectStatement> {
+    let playlist = get_smart_playlist_by_id(main_db, smart_playlist_id).await?;
+    let rule = parse_smart_playlist_rules(&playlist.rules)?;
+
+    Ok(media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::Id)
+        .filter(rule.to_condition(Utc::now())?)
+        .into_query())
+}
+
+/// Resolve the tracks of a smart playlist, see [`smart_playlist_subquery`].
+pub async fn get_smart_playlist_file_ids(
+    main_db: &DatabaseConnection,
+    smart_playlist_id: i32,
+) -> Result<Vec<i32>> {
+    let playlist = get_smart_playlist_by_id(main_db, smart_playlist_id).await?;
+    let rule = parse_smart_playlist_rules(&playlist.rules)?;
+
+    evaluate_smart_playlist_rule(main_db, &rule).await
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rust_decimal::prelude::ToPrimitive;
+    use sea_orm::EntityTrait;
+
+    use crate::{
+        actions::{
+            playlists::{
+                create_smart_playlist, evaluate_smart_playlist_rule, get_smart_playlist_file_ids,
+                parse_smart_playlist_rules, update_smart_playlist,
+            },
+            stats::{increase_played_through, set_liked},
+        },
+        entities::media_analysis,
+        test_support::{FakeTrack, connect_test_main_db, seed_fake_library},
+    };
+
+    #[test]
+    fn test_parse_rules() {
+        assert!(parse_smart_playlist_rules("genre = jazz AND bpm > 120").is_ok());
+        assert!(parse_smart_playlist_rules("NOT played in 30 days").is_ok());
+        assert!(parse_smart_playlist_rules("(artist ~ \"Miles Davis\" OR liked = true)").is_ok());
+
+        assert!(parse_smart_playlist_rules("").is_err());
+        assert!(parse_smart_playlist_rules("genre = \"jazz").is_err());
+        assert!(parse_smart_playlist_rules("(genre = jazz").is_err());
+        assert!(parse_smart_playlist_rules("bpm > fast").is_err());
+        assert!(parse_smart_playlist_rules("bpm ~ 120").is_err());
+        assert!(parse_smart_playlist_rules("released in 30 days").is_err());
+        assert!(parse_smart_playlist_rules("genre = jazz rock").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_metadata_and_analysis() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_library(&db, 20).await?;
+
+        let jazz: Vec<i32> = file_ids
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| FakeTrack::nth(*index).genre == "Jazz")
+            .map(|(_, id)| *id)
+            .collect();
+
+        let rule = parse_smart_playlist_rules("genre = JAZZ")?;
+        let mut result = evaluate_smart_playlist_rule(&db, &rule).await?;
+        result.sort();
+        assert_eq!(result, jazz);
+
+        let rule = parse_smart_playlist_rules("genre != jazz")?;
+        let result = evaluate_smart_playlist_rule(&db, &rule).await?;
+        assert_eq!(result.len(), file_ids.len() - jazz.len());
+
+        let mut fast_jazz = vec![];
+        for analysis in media_analysis::Entity::find().all(&db).await? {
+            let bpm = analysis.bpm.unwrap().to_f64().unwrap();
+            if bpm > 120.0 && jazz.contains(&analysis.file_id) {
+                fast_jazz.push(analysis.file_id);
+            }
+        }
+        fast_jazz.sort();
+
+        let rule = parse_smart_playlist_rules("genre = jazz AND bpm > 120")?;
+        let mut result = evaluate_smart_playlist_rule(&db, &rule).await?;
+        result.sort();
+        assert_eq!(result, fast_jazz);
+
+        let rule = parse_smart_playlist_rules("title ~ \"Track 1\" AND NOT title = \"Track 1\"")?;
+        let result = evaluate_smart_playlist_rule(&db, &rule).await?;
+        assert_eq!(result.len(), 10);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_play_stats() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_library(&db, 8).await?;
+
+        increase_played_through(&db, file_ids[0]).await?;
+        increase_played_through(&db, file_ids[0]).await?;
+        increase_played_through(&db, file_ids[1]).await?;
+        set_liked(&db, file_ids[2], true).await?;
+
+        let smart_playlist = create_smart_playlist(
+            &db,
+            "Recently Played".to_owned(),
+            "Smart".to_owned(),
+            "played in 30 days".to_owned(),
+        )
+        .await?;
+        let mut result = get_smart_playlist_file_ids(&db, smart_playlist.id).await?;
+        result.sort();
+        assert_eq!(result, vec![file_ids[0], file_ids[1]]);
+
+        // Rules are re-evaluated every time the playlist is queried
+        increase_played_through(&db, file_ids[3]).await?;
+        let result = get_smart_playlist_file_ids(&db, smart_playlist.id).await?;
+        assert_eq!(result.len(), 3);
+
+        let smart_playlist = update_smart_playlist(
+            &db,
+            smart_playlist.id,
+            None,
+            None,
+            Some("NOT played in 30 days AND play_count = 0".to_owned()),
+        )
+        .await?;
+        let result = get_smart_playlist_file_ids(&db, smart_playlist.id).await?;
+        assert_eq!(result.len(), 5);
+
+        let rule = parse_smart_playlist_rules("play_count >= 2 OR liked = true")?;
+        let mut result = evaluate_smart_playlist_rule(&db, &rule).await?;
+        result.sort();
+        assert_eq!(result, vec![file_ids[0], file_ids[2]]);
+
+        // Fake files were modified long ago
+        let rule = parse_smart_playlist_rules("modified in 7 days")?;
+        assert!(evaluate_smart_playlist_rule(&db, &rule).await?.is_empty());
+
+        // Looking back before any representable date is refused
+        let rule = parse_smart_playlist_rules(&format!("played in {} days", u32::MAX))?;
+        assert!(evaluate_smart_playlist_rule(&db, &rule).await.is_err());
+
+        assert!(
+            update_smart_playlist(&db, smart_playlist.id, None, None, Some("bpm >".to_owned()))
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+//! Radios of a track, an artist or an album.
+//!
+//! A radio plays the tracks that sound the most like its seed, found around
+//! the centre of the analysis results of the seed's tracks. Until the seed is
+//! analyzed, the radio plays the tracks of the seed instead. An endless radio
+//! asks for more with [`get_more_radio_tracks`], leaving out what it queued
+//! recently.
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use sea_orm::DatabaseConnection;
+
+use crate::actions::mixes::query_mix_media_files;
+use crate::connection::RecommendationDbConnection;
+use crate::entities::media_files;
+
+/// How many tracks a radio queues.
+pub const RADIO_LENGTH: usize = 50;
+
+/// How many of the last queued tracks an endless radio doesn't queue again.
+pub const RADIO_REPEAT_WINDOW: usize = 200;
+
+/// What a radio is built around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadioSeed {
+    Track(i32),
+    Artist(i32),
+    Album(i32),
+}
+
+impl RadioSeed {
+    /// The mix query selecting the tracks of the seed.
+    fn query(&self) -> (String, String) {
+        match self {
+            RadioSeed::Track(id) => ("lib::track".to_string(), id.to_string()),
+            RadioSeed::Artist(id) => ("lib::artist".to_string(), id.to_string()),
+            RadioSeed::Album(id) => ("lib::album".to_string(), id.to_string()),
+        }
+    }
+}
+
+/// The tracks of a radio, at most `n` of them, the most similar first.
+pub async fn get_radio_tracks(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+    seed: RadioSeed,
+    n: usize,
+) -> Result<Vec<media_files::Model>> {
+    let recommendations = query_mix_media_files(
+        main_db,
+        recommend_db,
+        vec![
+            seed.query(),
+            ("pipe::recommend".to_string(), "-1".to_string()),
+            ("pipe::limit".to_string(), n.to_string()),
+        ],
+        0,
+        n,
+    )
+    .await
+    .with_context(|| format!("Failed to get recommendations for radio: {seed:?}"))?;
+
+    if !recommendations.is_empty() {
+        return Ok(recommendations);
+    }
+
+    query_mix_media_files(main_db, recommend_db, vec![seed.query()], 0, n)
+        .await
+        .with_context(|| format!("Failed to get tracks for radio: {seed:?}"))
+}
+
+/// More tracks for a radio, at most `n` of them, leaving out the `avoided`
+/// ones like the recently queued tracks and those the listener excluded.
+pub async fn get_more_radio_tracks(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+    seed: RadioSeed,
+    avoided: &HashSet<i32>,
+    n: usize,
+) -> Result<Vec<media_files::Model>> {
+    let mut tracks = get_radio_tracks(main_db, recommend_db, seed, n + avoided.len()).await?;
+
+    tracks.retain(|x| !avoided.contains(&x.id));
+    tracks.truncate(n);
+
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use anyhow::Result;
+    use sea_orm::{EntityTrait, QueryOrder};
+    use tempfile::tempdir;
+
+    use crate::{
+        actions::{
+            radio::{RadioSeed, get_more_radio_tracks, get_radio_tracks},
+            recommendation::sync_recommendation,
+        },
+        connection::connect_recommendation_db,
+        entities::{albums, artists},
+        test_support::{
+            TRACKS_PER_ALBUM, connect_test_main_db, seed_fake_library, seed_fake_tracks,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_radio_plays_the_seed_until_analyzed() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+
+        let file_ids = seed_fake_tracks(&db, TRACKS_PER_ALBUM * 2).await?;
+        let album = albums::Entity::find()
+            .order_by_asc(albums::Column::Id)
+            .one(&db)
+            .await?
+            .unwrap();
+
+        let mut tracks: Vec<i32> =
+            get_radio_tracks(&db, &recommend_db, RadioSeed::Album(album.id), 50)
+                .await?
+                .into_iter()
+                .map(|x| x.id)
+                .collect();
+        tracks.sort();
+        assert_eq!(tracks, file_ids[..TRACKS_PER_ALBUM].to_vec());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_radio_recommends_similar_tracks() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+
+        let file_ids = seed_fake_library(&db, TRACKS_PER_ALBUM * 3).await?;
+        sync_recommendation(&db, &recommend_db).await?;
+
+        let artist = artists::Entity::find().one(&db).await?.unwrap();
+        let tracks = get_radio_tracks(&db, &recommend_db, RadioSeed::Artist(artist.id), 15).await?;
+
+        assert_eq!(tracks.len(), 15);
+        assert!(tracks.iter().all(|x| file_ids.contains(&x.id)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_radio_leaves_out_avoided_tracks() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+
+        let file_ids = seed_fake_library(&db, TRACKS_PER_ALBUM * 3).await?;
+        sync_recommendation(&db, &recommend_db).await?;
+
+        let seed = RadioSeed::Track(file_ids[0]);
+        let first: HashSet<i32> = get_radio_tracks(&db, &recommend_db, seed, 10)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        assert_eq!(first.len(), 10);
+
+        let more = get_more_radio_tracks(&db, &recommend_db, seed, &first, 10).await?;
+
+        assert_eq!(more.len(), 10);
+        assert!(more.iter().all(|x| !first.contains(&x.id)));
+
+        Ok(())
+    }
+}
@@ -1,19 +1,28 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::time::Duration;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result, bail};
 use arroy::distances::Euclidean;
 use arroy::{Reader, Writer};
-use log::error;
-use rand::rngs::StdRng;
+use heed::{CompactionOption, RwTxn};
+use log::{error, info};
 use rand::SeedableRng;
+use rand::rngs::StdRng;
+use sea_orm::QuerySelect;
 use sea_orm::entity::prelude::*;
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
 
-use crate::actions::analysis::AggregatedAnalysisResult;
+use crate::actions::analysis::{ANALYSIS_VECTOR_SIZE, AggregatedAnalysisResult};
 use crate::connection::{MainDbConnection, RecommendationDbConnection};
-use crate::entities::{media_analysis, media_files};
+use crate::entities::{media_analysis, media_file_genres, media_file_stats, media_files};
+use crate::events::{LibraryEvent, next_events};
 
 use super::analysis::get_percentile_analysis_result;
+use super::genres::get_genre_ids_by_names;
 
 /// Get recommendations for a given item.
 ///
@@ -60,7 +69,7 @@ pub fn get_recommendation_by_file_id(
 /// * `Result<Vec<(usize, f32)>>` - A vector of recommended item IDs and their distances.
 pub fn get_recommendation_by_parameter(
     recommend_db: &RecommendationDbConnection,
-    feature_vector: [f32; 61],
+    feature_vector: [f32; ANALYSIS_VECTOR_SIZE],
     n: usize,
 ) -> Result<Vec<(u32, f32)>> {
     let env = recommend_db.env.clone();
@@ -91,6 +100,426 @@ pub fn get_recommendation_by_parameter(
     }
 }
 
+/// How many neighbours are searched per wanted recommendation when only
+/// liked files are kept, since most of them are usually filtered out.
+pub const LIKED_ONLY_OVERSAMPLING: usize = 10;
+
+/// Keep the recommendations of files the user has liked.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `recommendations` - Recommended item IDs and their distances.
+/// * `n` - The number of recommendations to keep.
+///
+/// # Returns
+/// * `Result<Vec<(u32, f32)>>` - The first `n` liked recommendations, in the original order.
+pub async fn retain_liked_recommendations(
+    main_db: &MainDbConnection,
+    recommendations: Vec<(u32, f32)>,
+    n: usize,
+) -> Result<Vec<(u32, f32)>> {
+    let file_ids: Vec<i32> = recommendations.iter().map(|(id, _)| *id as i32).collect();
+
+    let liked: HashSet<i32> = media_file_stats::Entity::find()
+        .select_only()
+        .column(media_file_stats::Column::MediaFileId)
+        .filter(media_file_stats::Column::MediaFileId.is_in(file_ids))
+        .filter(media_file_stats::Column::Liked.eq(true))
+        .into_tuple::<i32>()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(recommendations
+        .into_iter()
+        .filter(|(id, _)| liked.contains(&(*id as i32)))
+        .take(n)
+        .collect())
+}
+
+/// How many neighbours are searched per wanted recommendation when only
+/// files of some genres are kept.
+pub const GENRE_FILTER_OVERSAMPLING: usize = 10;
+
+/// Keep the recommendations of files in any of the given genres, matched by
+/// name ignoring case.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `recommendations` - Recommended item IDs and their distances.
+/// * `genres` - Names of the genres to keep.
+/// * `n` - The number of recommendations to keep.
+///
+/// # Returns
+/// * `Result<Vec<(u32, f32)>>` - The first `n` recommendations in the genres, in the original order.
+pub async fn retain_genre_recommendations(
+    main_db: &MainDbConnection,
+    recommendations: Vec<(u32, f32)>,
+    genres: &[String],
+    n: usize,
+) -> Result<Vec<(u32, f32)>> {
+    let genre_ids = get_genre_ids_by_names(main_db, genres).await?;
+    if genre_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let file_ids: Vec<i32> = recommendations.iter().map(|(id, _)| *id as i32).collect();
+
+    let in_genres: HashSet<i32> = media_file_genres::Entity::find()
+        .select_only()
+        .column(media_file_genres::Column::MediaFileId)
+        .filter(media_file_genres::Column::MediaFileId.is_in(file_ids))
+        .filter(media_file_genres::Column::GenreId.is_in(genre_ids))
+        .into_tuple::<i32>()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(recommendations
+        .into_iter()
+        .filter(|(id, _)| in_genres.contains(&(*id as i32)))
+        .take(n)
+        .collect())
+}
+
+/// Groups of related values in the analysis vector, which can be weighted
+/// against each other when looking for similar tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeatureGroup {
+    /// RMS, zero crossing rate and energy.
+    Energy,
+    /// Shape of the spectrum, from the spectral centroid to its kurtosis.
+    Spectral,
+    /// Pitch classes, close to the key and harmony of a track.
+    Chroma,
+    /// Perceptual spread and sharpness.
+    Perceptual,
+    /// Loudness of each Bark band.
+    Loudness,
+    /// MFCCs, close to the timbre of a track.
+    Timbre,
+    /// Share of the energy in each octave band, and the crest factor.
+    Bands,
+}
+
+impl FeatureGroup {
+    pub const ALL: [FeatureGroup; 7] = [
+        FeatureGroup::Energy,
+        FeatureGroup::Spectral,
+        FeatureGroup::Chroma,
+        FeatureGroup::Perceptual,
+        FeatureGroup::Loudness,
+        FeatureGroup::Timbre,
+        FeatureGroup::Bands,
+    ];
+
+    /// Indices of the group in the analysis vector, matching
+    /// [`analysis_feature_names`](super::analysis::analysis_feature_names).
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            FeatureGroup::Energy => 0..3,
+            FeatureGroup::Spectral => 3..10,
+            FeatureGroup::Chroma => 10..22,
+            FeatureGroup::Perceptual => 22..24,
+            FeatureGroup::Loudness => 24..48,
+            FeatureGroup::Timbre => 48..61,
+            FeatureGroup::Bands => 61..ANALYSIS_VECTOR_SIZE,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeatureGroup::Energy => "energy",
+            FeatureGroup::Spectral => "spectral",
+            FeatureGroup::Chroma => "chroma",
+            FeatureGroup::Perceptual => "perceptual",
+            FeatureGroup::Loudness => "loudness",
+            FeatureGroup::Timbre => "timbre",
+            FeatureGroup::Bands => "bands",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Relative weights of the feature groups when comparing tracks, 1 for the
+/// groups without one.
+///
+/// Every group counts by the mean of its squared differences, so the larger
+/// groups don't outweigh the smaller ones, and the weights are normalized to
+/// sum to 1.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeatureWeights {
+    weights: HashMap<FeatureGroup, f32>,
+}
+
+impl FeatureWeights {
+    /// Set the weight of a group, negative weights are treated as 0.
+    pub fn with(mut self, group: FeatureGroup, weight: f32) -> Self {
+        self.weights.insert(group, weight.max(0.0));
+        self
+    }
+
+    pub fn weight(&self, group: FeatureGroup) -> f32 {
+        self.weights.get(&group).copied().unwrap_or(1.0)
+    }
+
+    /// Whether every group has the same weight, so the plain distances can
+    /// be used.
+    pub fn is_uniform(&self) -> bool {
+        let first = self.weight(FeatureGroup::ALL[0]);
+        FeatureGroup::ALL.iter().all(|x| self.weight(*x) == first)
+    }
+
+    /// The factor applied to every value of the analysis vector.
+    fn scales(&self) -> Result<[f32; ANALYSIS_VECTOR_SIZE]> {
+        let total: f32 = FeatureGroup::ALL.iter().map(|x| self.weight(*x)).sum();
+        if total <= 0.0 {
+            bail!("At least one feature group must have a positive weight");
+        }
+
+        let mut scales = [0.0; ANALYSIS_VECTOR_SIZE];
+        for group in FeatureGroup::ALL {
+            let range = group.range();
+            let scale = (self.weight(group) / total / range.len() as f32).sqrt();
+            scales[range].fill(scale);
+        }
+
+        Ok(scales)
+    }
+
+    /// Reweight an analysis vector, the Euclidean distance between two
+    /// reweighted vectors is the weighted distance between the originals.
+    pub fn apply(&self, vector: &[f32]) -> Result<Vec<f32>> {
+        let scales = self.scales()?;
+        if vector.len() != scales.len() {
+            bail!(
+                "Expected an analysis vector of {} values, got {}",
+                scales.len(),
+                vector.len()
+            );
+        }
+
+        Ok(vector.iter().zip(scales).map(|(x, s)| x * s).collect())
+    }
+}
+
+/// How many neighbours are searched per wanted recommendation before they
+/// are ranked again by the weighted distance.
+pub const WEIGHTED_OVERSAMPLING: usize = 10;
+
+fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Get recommendations for a given item, comparing the feature groups by
+/// the given weights.
+///
+/// The nearest neighbours in the index are ranked again by the weighted
+/// distance, so the weights pick among tracks that are already similar.
+///
+/// # Arguments
+/// * `recommend_db` - The tuple containing the LMDB environment and the Arroy database.
+/// * `item_id` - The ID of the item for which to get recommendations.
+/// * `n` - The number of recommendations to retrieve.
+/// * `weights` - The weights of the feature groups.
+///
+/// # Returns
+/// * `Result<Vec<(u32, f32)>>` - Recommended item IDs and their weighted distances, the closest first.
+pub fn get_weighted_recommendation_by_file_id(
+    recommend_db: &RecommendationDbConnection,
+    item_id: i32,
+    n: usize,
+    weights: &FeatureWeights,
+) -> Result<Vec<(u32, f32)>> {
+    let candidates =
+        get_recommendation_by_file_id(recommend_db, item_id, n * WEIGHTED_OVERSAMPLING)?;
+
+    let env = recommend_db.env.clone();
+    let rtxn = env.read_txn()?;
+    let reader = Reader::<Euclidean>::open(&rtxn, 0, recommend_db.db)?;
+
+    let item_id: u32 = item_id
+        .try_into()
+        .with_context(|| "Failed to convert item_id to u32")?;
+    let target = reader
+        .item_vector(&rtxn, item_id)?
+        .with_context(|| format!("No analysis vector found for item: {item_id}"))?;
+    let target = weights.apply(&target)?;
+
+    let mut results = Vec::with_capacity(candidates.len());
+    for (id, _) in candidates {
+        let Some(vector) = reader.item_vector(&rtxn, id)? else {
+            continue;
+        };
+        results.push((id, euclidean_distance(&target, &weights.apply(&vector)?)));
+    }
+
+    results.sort_by(|a, b| a.1.total_cmp(&b.1));
+    results.truncate(n);
+
+    Ok(results)
+}
+
+/// How many neighbours of the centre of the seeds are searched per wanted
+/// recommendation, before they are ranked again.
+pub const SEED_OVERSAMPLING: usize = 10;
+
+/// How strongly the excluded seeds push the recommendations away, relative
+/// to the pull of the seeds.
+pub const NEGATIVE_SEED_WEIGHT: f32 = 0.5;
+
+/// The stored analysis vectors of the items, failing if none is found.
+fn get_item_vectors(
+    reader: &Reader<Euclidean>,
+    rtxn: &heed::RoTxn,
+    item_ids: &[i32],
+) -> Result<Vec<Vec<f32>>> {
+    let mut vectors = Vec::with_capacity(item_ids.len());
+    for item_id in item_ids {
+        let item_id: u32 = (*item_id)
+            .try_into()
+            .with_context(|| "Failed to convert item_id to u32")?;
+        match reader.item_vector(rtxn, item_id)? {
+            Some(vector) => vectors.push(vector),
+            None => info!("No analysis vector found for item: {item_id}"),
+        }
+    }
+
+    if vectors.is_empty() {
+        bail!("No analysis vectors found for the given items: {item_ids:?}");
+    }
+
+    Ok(vectors)
+}
+
+fn centroid(vectors: &[Vec<f32>]) -> Vec<f32> {
+    let mut result = vec![0.0; vectors[0].len()];
+    for vector in vectors {
+        for (x, y) in result.iter_mut().zip(vector) {
+            *x += y;
+        }
+    }
+    for x in result.iter_mut() {
+        *x /= vectors.len() as f32;
+    }
+
+    result
+}
+
+/// Get recommendations around the centre of several items, away from the
+/// centre of the excluded items.
+///
+/// The neighbours of the centre of the seeds are ranked again by their
+/// distance to it, minus [`NEGATIVE_SEED_WEIGHT`] times their distance to
+/// the centre of the excluded items. Both distances use the given weights.
+/// The seeds and the excluded items themselves are never recommended.
+///
+/// # Arguments
+/// * `recommend_db` - The tuple containing the LMDB environment and the Arroy database.
+/// * `seeds` - The IDs of the items to find similar ones to.
+/// * `excluded` - The IDs of the items the recommendations should not be like.
+/// * `n` - The number of recommendations to retrieve.
+/// * `weights` - The weights of the feature groups.
+///
+/// # Returns
+/// * `Result<Vec<(u32, f32)>>` - Recommended item IDs and their scores, the best first.
+pub fn get_recommendation_by_seeds(
+    recommend_db: &RecommendationDbConnection,
+    seeds: &[i32],
+    excluded: &[i32],
+    n: usize,
+    weights: &FeatureWeights,
+) -> Result<Vec<(u32, f32)>> {
+    let env = recommend_db.env.clone();
+    let rtxn = env.read_txn()?;
+    let reader = Reader::<Euclidean>::open(&rtxn, 0, recommend_db.db)?;
+
+    let weigh = |vector: Vec<f32>| {
+        if weights.is_uniform() {
+            Ok(vector)
+        } else {
+            weights.apply(&vector)
+        }
+    };
+
+    let center = centroid(&get_item_vectors(&reader, &rtxn, seeds)?);
+    let away = if excluded.is_empty() {
+        None
+    } else {
+        let vectors = get_item_vectors(&reader, &rtxn, excluded)?;
+        Some(weigh(centroid(&vectors))?)
+    };
+
+    let query: [f32; ANALYSIS_VECTOR_SIZE] = center
+        .as_slice()
+        .try_into()
+        .with_context(|| "Unexpected size of the analysis vectors")?;
+    let search_n = (n + seeds.len() + excluded.len()) * SEED_OVERSAMPLING;
+    let search_k = NonZeroUsize::new(search_n * reader.n_trees() * 15)
+        .with_context(|| "Failed to create NonZeroUsize from search_k")?;
+    let candidates = reader
+        .nns(search_n)
+        .search_k(search_k)
+        .by_vector(&rtxn, &query)?;
+
+    let center = weigh(center)?;
+    let skipped: HashSet<u32> = seeds.iter().chain(excluded).map(|x| *x as u32).collect();
+
+    let mut results = Vec::with_capacity(candidates.len());
+    for (id, _) in candidates {
+        if skipped.contains(&id) {
+            continue;
+        }
+        let Some(vector) = reader.item_vector(&rtxn, id)? else {
+            continue;
+        };
+        let vector = weigh(vector)?;
+
+        let mut score = euclidean_distance(&center, &vector);
+        if let Some(away) = &away {
+            score -= NEGATIVE_SEED_WEIGHT * euclidean_distance(away, &vector);
+        }
+        results.push((id, score));
+    }
+
+    results.sort_by(|a, b| a.1.total_cmp(&b.1));
+    results.truncate(n);
+
+    Ok(results)
+}
+
+/// Store the vectors of the analysis results and build the index over
+/// them, inside the caller's transaction.
+fn add_analysis_vectors(
+    writer: &Writer<Euclidean>,
+    wtxn: &mut RwTxn,
+    analyzes: Vec<media_analysis::Model>,
+) -> Result<()> {
+    for analysis in analyzes {
+        let file_id: u32 = analysis.file_id.try_into()?;
+        let parsed_result: AggregatedAnalysisResult = analysis.into();
+        let vector: [f32; ANALYSIS_VECTOR_SIZE] = parsed_result.into();
+
+        writer.add_item(wtxn, file_id, &vector)?;
+    }
+
+    let mut rng = StdRng::seed_from_u64(42);
+    writer.builder(&mut rng).build(wtxn)?;
+
+    Ok(())
+}
+
 /// Sync the recommendation database with the analysis data.
 ///
 /// # Arguments
@@ -115,22 +544,17 @@ pub async fn sync_recommendation(
         existing_ids.insert(analysis.file_id);
     }
 
+    let outdated = is_index_outdated(recommend_db)?;
+
     // Open a write transaction for the recommendation database
     let mut wtxn = env.write_txn()?;
-    let writer = Writer::<Euclidean>::new(arroy_db, 0, 61);
-
-    // Insert or update analysis data in the recommendation database
-    for analysis in analyzes {
-        let file_id: u32 = analysis.file_id.try_into()?;
-        let parsed_result: AggregatedAnalysisResult = analysis.into();
-        let vector: [f32; 61] = parsed_result.into();
-
-        writer.add_item(&mut wtxn, file_id, &vector)?;
+    let writer = Writer::<Euclidean>::new(arroy_db, 0, ANALYSIS_VECTOR_SIZE);
+    if outdated {
+        writer.clear(&mut wtxn)?;
     }
 
-    // Build the index
-    let mut rng = StdRng::seed_from_u64(42);
-    writer.builder(&mut rng).build(&mut wtxn)?;
+    // Insert or update analysis data in the recommendation database
+    add_analysis_vectors(&writer, &mut wtxn, analyzes)?;
 
     // Commit the transaction
     wtxn.commit()?;
@@ -141,7 +565,7 @@ pub async fn sync_recommendation(
     for id in reader.item_ids() {
         if !existing_ids.contains(&(id as i32)) {
             let mut wtxn = env.write_txn()?;
-            let writer = Writer::<Euclidean>::new(arroy_db, 0, 61);
+            let writer = Writer::<Euclidean>::new(arroy_db, 0, ANALYSIS_VECTOR_SIZE);
             writer.del_item(&mut wtxn, id)?;
             wtxn.commit()?;
         }
@@ -150,6 +574,116 @@ pub async fn sync_recommendation(
     Ok(())
 }
 
+/// Add the vectors of newly analyzed files and update the index, without
+/// touching the vectors already stored. Returns the number of vectors added.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `recommend_db` - The tuple containing the LMDB environment and the Arroy database.
+/// * `file_ids` - The analyzed files, files without an analysis are skipped.
+pub async fn add_recommendation_vectors(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    file_ids: &[i32],
+) -> Result<usize> {
+    let analyzes = media_analysis::Entity::find()
+        .filter(media_analysis::Column::FileId.is_in(file_ids.to_vec()))
+        .all(main_db)
+        .await?;
+    if analyzes.is_empty() {
+        return Ok(0);
+    }
+    // Vectors of an older size can't be mixed with the new ones
+    if is_index_outdated(recommend_db)? {
+        return rebuild_recommendation_db(main_db, recommend_db).await;
+    }
+
+    let mut wtxn = recommend_db.env.write_txn()?;
+    let writer = Writer::<Euclidean>::new(recommend_db.db, 0, ANALYSIS_VECTOR_SIZE);
+
+    let added = analyzes.len();
+    // Arroy only updates the trees the new items land in
+    add_analysis_vectors(&writer, &mut wtxn, analyzes)?;
+    wtxn.commit()?;
+
+    Ok(added)
+}
+
+/// Whether the index holds vectors of another size than
+/// [`ANALYSIS_VECTOR_SIZE`], built before the analysis gained new features.
+/// An empty index is never outdated.
+pub fn is_index_outdated(recommend_db: &RecommendationDbConnection) -> Result<bool> {
+    let rtxn = recommend_db.env.read_txn()?;
+    match Reader::<Euclidean>::open(&rtxn, 0, recommend_db.db) {
+        Ok(reader) => Ok(reader.dimensions() != ANALYSIS_VECTOR_SIZE),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Drop every vector and build the index again from the analysis results,
+/// for when incremental updates have left the trees unbalanced. Returns the
+/// number of vectors in the new index.
+pub async fn rebuild_recommendation_db(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+) -> Result<usize> {
+    let analyzes = media_analysis::Entity::find().all(main_db).await?;
+
+    let mut wtxn = recommend_db.env.write_txn()?;
+    let writer = Writer::<Euclidean>::new(recommend_db.db, 0, ANALYSIS_VECTOR_SIZE);
+    writer.clear(&mut wtxn)?;
+
+    let total = analyzes.len();
+    add_analysis_vectors(&writer, &mut wtxn, analyzes)?;
+    wtxn.commit()?;
+
+    info!("Recommendation index rebuilt with {total} vectors");
+
+    Ok(total)
+}
+
+/// Keep the recommendation database in sync with the published events until
+/// cancelled: vectors of analyzed files are added, and vectors of removed
+/// files are dropped once enough of them pile up.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `recommend_db` - The tuple containing the LMDB environment and the Arroy database.
+/// * `receiver` - A subscription to the event bus.
+/// * `cancel_token` - A cancellation token to stop listening.
+pub async fn sync_recommendation_on_events(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    mut receiver: Receiver<LibraryEvent>,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    while let Some(events) = next_events(&mut receiver, &cancel_token).await {
+        let analyzed: Vec<i32> = events
+            .iter()
+            .filter_map(|x| match x {
+                LibraryEvent::AnalysisFinished { file_ids } => Some(file_ids.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        let removed = events
+            .iter()
+            .any(|x| matches!(x, LibraryEvent::LibraryChanged { removed, .. } if *removed > 0));
+
+        if !analyzed.is_empty() {
+            if let Err(e) = add_recommendation_vectors(main_db, recommend_db, &analyzed).await {
+                error!("Recommendation synchronization failed: {e:#}");
+            }
+        } else if removed {
+            if let Err(e) = maintain_recommendation_db_if_needed(main_db, recommend_db).await {
+                error!("Failed to maintain the recommendation database: {e:#}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub async fn get_recommendation_by_percentile(
     main_db: &MainDbConnection,
     recommend_db: &RecommendationDbConnection,
@@ -166,3 +700,448 @@ pub async fn get_recommendation_by_percentile(
 
     get_recommendation_by_parameter(recommend_db, virtual_point, total_files / total_groups)
 }
+
+/// Vectors left behind by removed files before a scan maintains the
+/// recommendation database on its own.
+pub const ORPHANED_VECTORS_THRESHOLD: usize = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecommendationDbHealth {
+    pub vectors: usize,
+    /// Vectors of files which no longer exist or were never analyzed.
+    pub orphaned_vectors: usize,
+    /// Analyzed files without a vector.
+    pub missing_vectors: usize,
+}
+
+impl RecommendationDbHealth {
+    fn compare(analyzed: &HashSet<u32>, vectors: &HashSet<u32>) -> Self {
+        Self {
+            vectors: vectors.len(),
+            orphaned_vectors: vectors.difference(analyzed).count(),
+            missing_vectors: analyzed.difference(vectors).count(),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.orphaned_vectors == 0 && self.missing_vectors == 0
+    }
+}
+
+async fn get_analyzed_file_ids(main_db: &MainDbConnection) -> Result<HashSet<u32>> {
+    let file_ids: Vec<i32> = media_analysis::Entity::find()
+        .select_only()
+        .column(media_analysis::Column::FileId)
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    Ok(file_ids.into_iter().map(|x| x as u32).collect())
+}
+
+fn get_vector_ids(recommend_db: &RecommendationDbConnection) -> Result<HashSet<u32>> {
+    let rtxn = recommend_db.env.read_txn()?;
+    let writer = Writer::<Euclidean>::new(recommend_db.db, 0, ANALYSIS_VECTOR_SIZE);
+
+    Ok(writer.item_ids(&rtxn)?.into_iter().collect())
+}
+
+/// Compare the vectors of the recommendation database with the analysis
+/// results of the main database.
+pub async fn check_recommendation_db(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+) -> Result<RecommendationDbHealth> {
+    let analyzed = get_analyzed_file_ids(main_db).await?;
+    let vectors = get_vector_ids(recommend_db)?;
+
+    Ok(RecommendationDbHealth::compare(&analyzed, &vectors))
+}
+
+/// Drop the vectors of removed files, add the missing ones and rebuild the
+/// index in a single transaction. Returns the health before the repair.
+pub async fn maintain_recommendation_db(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+) -> Result<RecommendationDbHealth> {
+    let analyzed = get_analyzed_file_ids(main_db).await?;
+    let vectors = get_vector_ids(recommend_db)?;
+    let health = RecommendationDbHealth::compare(&analyzed, &vectors);
+
+    let mut wtxn = recommend_db.env.write_txn()?;
+    let writer = Writer::<Euclidean>::new(recommend_db.db, 0, ANALYSIS_VECTOR_SIZE);
+
+    for id in vectors.difference(&analyzed) {
+        writer.del_item(&mut wtxn, *id)?;
+    }
+
+    let missing: Vec<i32> = analyzed.difference(&vectors).map(|x| *x as i32).collect();
+    let analyzes = media_analysis::Entity::find()
+        .filter(media_analysis::Column::FileId.is_in(missing))
+        .all(main_db)
+        .await?;
+    for analysis in analyzes {
+        let file_id: u32 = analysis.file_id.try_into()?;
+        let parsed_result: AggregatedAnalysisResult = analysis.into();
+        let vector: [f32; ANALYSIS_VECTOR_SIZE] = parsed_result.into();
+
+        writer.add_item(&mut wtxn, file_id, &vector)?;
+    }
+
+    let mut rng = StdRng::seed_from_u64(42);
+    writer.builder(&mut rng).build(&mut wtxn)?;
+    wtxn.commit()?;
+
+    info!(
+        "Recommendation database maintained: {} orphaned vectors dropped, {} missing vectors added",
+        health.orphaned_vectors, health.missing_vectors
+    );
+
+    Ok(health)
+}
+
+/// Maintain the recommendation database if a cleanup left many vectors of
+/// removed files behind, or rebuild it if its vectors are outdated.
+pub async fn maintain_recommendation_db_if_needed(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+) -> Result<Option<RecommendationDbHealth>> {
+    let health = check_recommendation_db(main_db, recommend_db).await?;
+    if is_index_outdated(recommend_db)? {
+        rebuild_recommendation_db(main_db, recommend_db).await?;
+        return Ok(Some(health));
+    }
+    if health.orphaned_vectors < ORPHANED_VECTORS_THRESHOLD {
+        return Ok(None);
+    }
+
+    let health = maintain_recommendation_db(main_db, recommend_db).await?;
+
+    Ok(Some(health))
+}
+
+/// Rewrite the recommendation database without its free pages, returning
+/// the size of the data file before and after.
+///
+/// LMDB never shrinks its file, so this is the only way to get the space of
+/// dropped vectors back. The connection is closed, and every other clone of
+/// it must have been dropped already.
+pub fn compact_recommendation_db(recommend_db: RecommendationDbConnection) -> Result<(u64, u64)> {
+    let dir = recommend_db.env.path().to_path_buf();
+    let data_path = dir.join("data.mdb");
+    let compacted_path = dir.join("data.mdb.compact");
+
+    let before = fs::metadata(&data_path)?.len();
+    recommend_db
+        .env
+        .copy_to_path(&compacted_path, CompactionOption::Enabled)?;
+
+    let closing = recommend_db.env.prepare_for_closing();
+    if !closing.wait_timeout(Duration::from_secs(10)) {
+        fs::remove_file(&compacted_path)?;
+        bail!("The recommendation database is still in use");
+    }
+
+    fs::rename(&compacted_path, &data_path)?;
+    let after = fs::metadata(&data_path)?.len();
+
+    Ok((before, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use arroy::{Writer, distances::Euclidean};
+    use rand::{SeedableRng, rngs::StdRng};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+    use tempfile::tempdir;
+
+    use crate::{
+        actions::{
+            analysis::{ANALYSIS_VECTOR_SIZE, analysis_feature_names},
+            recommendation::{
+                FeatureGroup, FeatureWeights, add_recommendation_vectors, check_recommendation_db,
+                compact_recommendation_db, get_recommendation_by_seeds,
+                get_weighted_recommendation_by_file_id, is_index_outdated,
+                maintain_recommendation_db, maintain_recommendation_db_if_needed,
+                rebuild_recommendation_db, retain_liked_recommendations, sync_recommendation,
+            },
+            stats::set_liked,
+        },
+        connection::connect_recommendation_db,
+        entities::media_analysis,
+        test_support::{connect_test_main_db, seed_fake_library, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_maintain_drops_orphaned_vectors() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let lib_path = lib_dir.path().to_str().unwrap();
+        let recommend_db = connect_recommendation_db(lib_path, None)?;
+
+        let file_ids = seed_fake_library(&db, 12).await?;
+        sync_recommendation(&db, &recommend_db).await?;
+        assert!(
+            check_recommendation_db(&db, &recommend_db)
+                .await?
+                .is_healthy()
+        );
+
+        // Removing files drops their analysis, but not their vectors
+        media_analysis::Entity::delete_many()
+            .filter(media_analysis::Column::FileId.is_in(file_ids[..3].to_vec()))
+            .exec(&db)
+            .await?;
+
+        let health = check_recommendation_db(&db, &recommend_db).await?;
+        assert_eq!(health.vectors, 12);
+        assert_eq!(health.orphaned_vectors, 3);
+        assert_eq!(health.missing_vectors, 0);
+
+        // A few orphans aren't worth a rebuild during a scan
+        assert!(
+            maintain_recommendation_db_if_needed(&db, &recommend_db)
+                .await?
+                .is_none()
+        );
+
+        assert_eq!(
+            maintain_recommendation_db(&db, &recommend_db).await?,
+            health
+        );
+        let health = check_recommendation_db(&db, &recommend_db).await?;
+        assert_eq!(health.vectors, 9);
+        assert!(health.is_healthy());
+
+        let (before, after) = compact_recommendation_db(recommend_db)?;
+        assert!(after <= before);
+
+        // The compacted database keeps every vector
+        let recommend_db = connect_recommendation_db(lib_path, None)?;
+        let health = check_recommendation_db(&db, &recommend_db).await?;
+        assert_eq!(health.vectors, 9);
+        assert!(health.is_healthy());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_vectors_and_rebuild() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+
+        let file_ids = seed_fake_library(&db, 12).await?;
+
+        // Only the files of the event are added
+        assert_eq!(
+            add_recommendation_vectors(&db, &recommend_db, &file_ids[..4]).await?,
+            4
+        );
+        assert_eq!(
+            add_recommendation_vectors(&db, &recommend_db, &file_ids[4..8]).await?,
+            4
+        );
+        let health = check_recommendation_db(&db, &recommend_db).await?;
+        assert_eq!(health.vectors, 8);
+        assert_eq!(health.missing_vectors, 4);
+
+        assert_eq!(
+            add_recommendation_vectors(&db, &recommend_db, &[]).await?,
+            0
+        );
+
+        assert_eq!(rebuild_recommendation_db(&db, &recommend_db).await?, 12);
+        let health = check_recommendation_db(&db, &recommend_db).await?;
+        assert_eq!(health.vectors, 12);
+        assert!(health.is_healthy());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_outdated_index_is_rebuilt() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+        assert!(!is_index_outdated(&recommend_db)?);
+
+        // An index built before the octave bands were analyzed
+        let mut wtxn = recommend_db.env.write_txn()?;
+        let writer = Writer::<Euclidean>::new(recommend_db.db, 0, 61);
+        writer.add_item(&mut wtxn, 1, &[0.5; 61])?;
+        writer
+            .builder(&mut StdRng::seed_from_u64(42))
+            .build(&mut wtxn)?;
+        wtxn.commit()?;
+        assert!(is_index_outdated(&recommend_db)?);
+
+        let file_ids = seed_fake_library(&db, 4).await?;
+        assert_eq!(
+            add_recommendation_vectors(&db, &recommend_db, &file_ids[..1]).await?,
+            4
+        );
+        assert!(!is_index_outdated(&recommend_db)?);
+        assert!(
+            check_recommendation_db(&db, &recommend_db)
+                .await?
+                .is_healthy()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_feature_groups_cover_the_vector() {
+        let names = analysis_feature_names();
+
+        let mut next = 0;
+        for group in FeatureGroup::ALL {
+            let range = group.range();
+            assert_eq!(
+                range.start, next,
+                "{group:?} doesn't follow the previous group"
+            );
+            next = range.end;
+        }
+        assert_eq!(next, names.len());
+        assert_eq!(next, ANALYSIS_VECTOR_SIZE);
+
+        assert!(
+            names[FeatureGroup::Chroma.range()]
+                .iter()
+                .all(|x| x.starts_with("chroma_"))
+        );
+        assert!(
+            names[FeatureGroup::Timbre.range()]
+                .iter()
+                .all(|x| x.starts_with("mfcc_"))
+        );
+        assert_eq!(
+            names[FeatureGroup::Bands.range()].last().unwrap(),
+            "crest_factor"
+        );
+        assert_eq!(FeatureGroup::parse(" Chroma"), Some(FeatureGroup::Chroma));
+    }
+
+    #[test]
+    fn test_feature_weights_reweight_groups() -> Result<()> {
+        let uniform = FeatureWeights::default();
+        assert!(uniform.is_uniform());
+
+        // Only the chroma counts, averaged over its 12 values
+        let weights = FeatureWeights::default()
+            .with(FeatureGroup::Chroma, 1.0)
+            .with(FeatureGroup::Energy, 0.0)
+            .with(FeatureGroup::Spectral, 0.0)
+            .with(FeatureGroup::Perceptual, 0.0)
+            .with(FeatureGroup::Loudness, 0.0)
+            .with(FeatureGroup::Timbre, 0.0)
+            .with(FeatureGroup::Bands, 0.0);
+        assert!(!weights.is_uniform());
+
+        let vector = weights.apply(&[1.0; ANALYSIS_VECTOR_SIZE])?;
+        assert!(vector[..10].iter().all(|x| *x == 0.0));
+        assert!(vector[48..].iter().all(|x| *x == 0.0));
+        let norm: f32 = vector.iter().map(|x| x * x).sum();
+        assert!((norm - 1.0).abs() < 1e-5);
+
+        let none = FeatureWeights::default()
+            .with(FeatureGroup::Chroma, 0.0)
+            .with(FeatureGroup::Energy, 0.0)
+            .with(FeatureGroup::Spectral, 0.0)
+            .with(FeatureGroup::Perceptual, 0.0)
+            .with(FeatureGroup::Loudness, 0.0)
+            .with(FeatureGroup::Timbre, -1.0)
+            .with(FeatureGroup::Bands, 0.0);
+        assert!(none.apply(&[1.0; ANALYSIS_VECTOR_SIZE]).is_err());
+        assert!(weights.apply(&[1.0; 10]).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_weighted_recommendations_are_ranked_by_weighted_distance() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+
+        let file_ids = seed_fake_library(&db, 30).await?;
+        sync_recommendation(&db, &recommend_db).await?;
+
+        let weights = FeatureWeights::default()
+            .with(FeatureGroup::Chroma, 4.0)
+            .with(FeatureGroup::Loudness, 0.5);
+        let recommendations =
+            get_weighted_recommendation_by_file_id(&recommend_db, file_ids[0], 5, &weights)?;
+
+        assert_eq!(recommendations.len(), 5);
+        assert_eq!(recommendations[0], (file_ids[0] as u32, 0.0));
+        assert!(recommendations.windows(2).all(|x| x[0].1 <= x[1].1));
+        assert!(
+            recommendations
+                .iter()
+                .all(|(id, _)| file_ids.contains(&(*id as i32)))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recommendations_around_several_seeds() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+
+        let file_ids = seed_fake_library(&db, 30).await?;
+        sync_recommendation(&db, &recommend_db).await?;
+
+        let seeds = &file_ids[..3];
+        let uniform = FeatureWeights::default();
+        let recommendations = get_recommendation_by_seeds(&recommend_db, seeds, &[], 5, &uniform)?;
+
+        assert_eq!(recommendations.len(), 5);
+        assert!(recommendations.windows(2).all(|x| x[0].1 <= x[1].1));
+        assert!(
+            recommendations
+                .iter()
+                .all(|(id, _)| !seeds.contains(&(*id as i32)))
+        );
+
+        // Pushing away from the best match leaves it out of the recommendations
+        let best = recommendations[0].0 as i32;
+        let pushed = get_recommendation_by_seeds(&recommend_db, seeds, &[best], 5, &uniform)?;
+        assert_eq!(pushed.len(), 5);
+        assert!(pushed.iter().all(|(id, _)| *id as i32 != best));
+
+        assert!(get_recommendation_by_seeds(&recommend_db, &[9999], &[], 5, &uniform).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retain_liked_recommendations() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 4).await?;
+
+        for file_id in [file_ids[1], file_ids[2], file_ids[3]] {
+            set_liked(&db, file_id, true).await?;
+        }
+
+        let recommendations: Vec<(u32, f32)> = file_ids
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(index, id)| (*id as u32, index as f32))
+            .collect();
+
+        let liked = retain_liked_recommendations(&db, recommendations, 2).await?;
+        assert_eq!(
+            liked,
+            vec![(file_ids[3] as u32, 0.0), (file_ids[2] as u32, 1.0)]
+        );
+
+        Ok(())
+    }
+}
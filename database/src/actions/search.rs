@@ -1,16 +1,23 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
 use deunicode::deunicode;
-use log::warn;
+use log::{info, warn};
 use sea_orm::{
-    ColumnTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult, QueryFilter,
-    Statement,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult,
+    QueryFilter, QuerySelect, Statement, TransactionTrait,
 };
 
-use crate::entities::search_index;
+use crate::entities::{
+    media_file_albums, media_file_artists, media_file_genres, media_file_playlists, search_index,
+};
 
-use super::{collection::CollectionQueryType, utils::DatabaseExecutor};
+use super::{
+    collection::CollectionQueryType,
+    settings::{SEARCH_INDEX_VERSION_KEY, get_setting, set_setting},
+    stats::get_hidden_files,
+    utils::DatabaseExecutor,
+};
 
 pub fn convert_to_collection_types(input: Vec<String>) -> Vec<CollectionQueryType> {
     input
@@ -74,42 +81,728 @@ where
 {
     remove_term(main_db, entry_type.clone(), id).await?;
 
-    search_index::Entity::find().from_raw_sql(Statement::from_sql_and_values(
+    // The original name goes first, it is the one displayed
+    let mut docs = vec![name.to_string(), deunicode(name)];
+    docs.extend(cjk_documents(name));
+
+    let mut values = Vec::with_capacity(docs.len() * 3);
+    for doc in &docs {
+        values.push(id.to_string().into());
+        values.push(entry_type.to_string().into());
+        values.push(doc.clone().into());
+    }
+
+    search_index::Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            format!(
+                "INSERT INTO search_index (id, key, entry_type, doc) VALUES {};",
+                vec!["('', ?, ?, ?)"; docs.len()].join(", ")
+            ),
+            values,
+        ))
+        .all(main_db)
+        .await?;
+
+    Ok(())
+}
+
+/// Whether the character is written without spaces between words, like
+/// Chinese, Japanese and Korean.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11FF
+            | 0x3040..=0x30FF
+            | 0x3130..=0x318F
+            | 0x31F0..=0x31FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xAC00..=0xD7AF
+            | 0xF900..=0xFAFF
+            | 0x20000..=0x2FFFF
+    )
+}
+
+fn has_cjk(text: &str) -> bool {
+    text.chars().any(is_cjk)
+}
+
+fn push_cjk_run(run: &mut Vec<char>, tokens: &mut Vec<String>) {
+    match run.len() {
+        0 => {}
+        1 => tokens.push(run[0].to_string()),
+        _ => tokens.extend(run.windows(2).map(|x| x.iter().collect::<String>())),
+    }
+    run.clear();
+}
+
+/// Split the text into words the FTS5 tokenizer can match: runs of CJK
+/// characters become their overlapping pairs of characters, a lone
+/// character stays alone, and the rest is transliterated.
+fn cjk_tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut run: Vec<char> = Vec::new();
+    let mut other = String::new();
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !other.is_empty() {
+                tokens.push(deunicode(&other));
+                other.clear();
+            }
+            run.push(c);
+        } else {
+            push_cjk_run(&mut run, &mut tokens);
+            other.push(c);
+        }
+    }
+    push_cjk_run(&mut run, &mut tokens);
+    if !other.is_empty() {
+        tokens.push(deunicode(&other));
+    }
+
+    tokens
+}
+
+/// More documents for names with CJK characters, which the tokenizer
+/// would keep as one word per run:
+///
+/// * the name split into pairs of characters, so any part of a title
+///   longer than one character is found;
+/// * every character alone, so a single one is found too, followed by the
+///   transliteration of every pair without spaces, so `beijing` finds
+///   `北京欢迎你` like `bei jing` does.
+fn cjk_documents(name: &str) -> Vec<String> {
+    if !has_cjk(name) {
+        return Vec::new();
+    }
+
+    let tokens = cjk_tokens(name);
+    let characters: Vec<String> = name
+        .chars()
+        .filter(|x| is_cjk(*x))
+        .map(String::from)
+        .collect();
+    let romanized: Vec<String> = tokens
+        .iter()
+        .filter(|x| has_cjk(x))
+        .map(|x| deunicode(x).split_whitespace().collect())
+        .collect();
+
+    vec![
+        tokens.join(" "),
+        format!("{} {}", characters.join(" "), romanized.join(" ")),
+    ]
+}
+
+/// Version of the documents [`add_term`] writes, raised whenever they
+/// change so the existing terms are written again, see
+/// [`upgrade_search_index`].
+pub const SEARCH_INDEX_VERSION: i32 = 2;
+
+#[derive(Debug, FromQueryResult)]
+struct NameRow {
+    key: String,
+    entry_type: String,
+    doc: String,
+}
+
+/// Write the terms indexed by an older version of [`add_term`] again. Only
+/// names with CJK characters changed since the first version.
+///
+/// # Returns
+/// * `Result<usize>` - How many items were indexed again.
+pub async fn upgrade_search_index(main_db: &DatabaseConnection) -> Result<usize> {
+    let version = get_setting(main_db, SEARCH_INDEX_VERSION_KEY)
+        .await?
+        .and_then(|x| x.parse::<i32>().ok())
+        .unwrap_or(1);
+    if version >= SEARCH_INDEX_VERSION {
+        return Ok(0);
+    }
+
+    // The original name is the first document of every item
+    let names = NameRow::find_by_statement(Statement::from_string(
+        DbBackend::Sqlite,
+        "SELECT key, entry_type, doc FROM search_index WHERE rowid IN \
+         (SELECT MIN(rowid) FROM search_index GROUP BY entry_type, key);",
+    ))
+    .all(main_db)
+    .await?;
+
+    let mut upgraded = 0;
+    let txn = main_db.begin().await?;
+    for name in names.into_iter().filter(|x| has_cjk(&x.doc)) {
+        let (Ok(entry_type), Ok(id)) = (
+            name.entry_type.parse::<CollectionQueryType>(),
+            name.key.parse::<i32>(),
+        ) else {
+            warn!("Invalid document ID found!");
+            continue;
+        };
+
+        add_term(&txn, entry_type, id, &name.doc).await?;
+        upgraded += 1;
+    }
+    txn.commit().await?;
+
+    set_setting(
+        main_db,
+        SEARCH_INDEX_VERSION_KEY,
+        Some(&SEARCH_INDEX_VERSION.to_string()),
+    )
+    .await?;
+    info!(
+        "Search index upgraded to version {SEARCH_INDEX_VERSION}, {upgraded} items indexed again"
+    );
+
+    Ok(upgraded)
+}
+
+/// How many items a scoped term like `artist:radiohead` matches at most
+/// before its tracks are looked up.
+const FIELD_MATCH_LIMIT: usize = 1000;
+
+/// Types of entries with fewer results than this are searched again with
+/// typos tolerated.
+const FUZZY_FALLBACK_THRESHOLD: usize = 3;
+
+/// Every edit of a term matched with typos weighs the score down by this.
+const FUZZY_PENALTY: f64 = 0.5;
+
+/// A field a search term can be scoped to, like `artist:radiohead`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchField {
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Playlist,
+    Directory,
+}
+
+impl SearchField {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "title" | "track" => Some(SearchField::Title),
+            "artist" => Some(SearchField::Artist),
+            "album" => Some(SearchField::Album),
+            "genre" => Some(SearchField::Genre),
+            "playlist" => Some(SearchField::Playlist),
+            "directory" | "dir" => Some(SearchField::Directory),
+            _ => None,
+        }
+    }
+
+    /// The entries whose names the field is matched against.
+    fn collection_type(&self) -> CollectionQueryType {
+        match self {
+            SearchField::Title => CollectionQueryType::Track,
+            SearchField::Artist => CollectionQueryType::Artist,
+            SearchField::Album => CollectionQueryType::Album,
+            SearchField::Genre => CollectionQueryType::Genre,
+            SearchField::Playlist => CollectionQueryType::Playlist,
+            SearchField::Directory => CollectionQueryType::Directory,
+        }
+    }
+}
+
+/// A term scoped to a field. The value is an FTS5 expression, terms of the
+/// same field joined by `OR` share one filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub field: SearchField,
+    pub value: String,
+    pub negated: bool,
+}
+
+/// A search query split into its free text and its scoped terms.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// FTS5 expression of the terms without a field, `None` if there are
+    /// none.
+    pub text: Option<String>,
+    /// FTS5 expression of the terms without a field that must not match.
+    pub excluded_text: Option<String>,
+    /// The terms without a field by `OR` group, transliterated and in lower
+    /// case, for matching them with typos.
+    pub text_terms: Vec<Vec<String>>,
+    pub filters: Vec<FieldFilter>,
+}
+
+/// Quote a term as an FTS5 phrase. Terms with CJK characters are split
+/// like the names they should match, see [`cjk_documents`].
+fn fts_phrase(term: &str) -> String {
+    let phrase = if has_cjk(term) {
+        cjk_tokens(term).join(" ")
+    } else {
+        deunicode(term)
+    };
+
+    format!("\"{}\"", phrase.replace('"', "\"\""))
+}
+
+/// Split the query into words, keeping quoted phrases together, even after
+/// a field like `album:"ok computer"`.
+fn tokenize_query(query_str: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in query_str.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+impl SearchQuery {
+    /// Parse a query like `artist:radiohead album:"ok computer" -live`.
+    ///
+    /// Terms are joined by `AND` unless `OR` stands between them, `NOT` or
+    /// a leading `-` excludes a term. `OR` only joins terms of the same
+    /// field, or terms without one. Unknown fields are searched as text.
+    pub fn parse(query_str: &str) -> Self {
+        // Each group holds the terms joined by OR
+        let mut text_groups: Vec<Vec<String>> = Vec::new();
+        let mut text_terms: Vec<Vec<String>> = Vec::new();
+        let mut excluded_text: Vec<String> = Vec::new();
+        let mut filters: Vec<FieldFilter> = Vec::new();
+
+        let mut negate_next = false;
+        let mut or_next = false;
+        // Whether the last term had a field, and which
+        let mut last_field: Option<Option<SearchField>> = None;
+
+        for token in tokenize_query(query_str) {
+            match token.as_str() {
+                "AND" => continue,
+                "OR" => {
+                    or_next = last_field.is_some();
+                    continue;
+                }
+                "NOT" => {
+                    negate_next = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let (negated, token) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest.to_string()),
+                _ => (negate_next, token),
+            };
+            negate_next = false;
+
+            let (field, term) = match token.split_once(':') {
+                Some((name, term)) => match SearchField::parse(name) {
+                    Some(field) => (Some(field), term),
+                    None => (None, token.as_str()),
+                },
+                None => (None, token.as_str()),
+            };
+            let term = term.trim_matches('"');
+            if term.is_empty() {
+                continue;
+            }
+            let phrase = fts_phrase(term);
+
+            let join = or_next && !negated && last_field == Some(field);
+            or_next = false;
+            last_field = Some(field);
+
+            match field {
+                None if negated => excluded_text.push(phrase),
+                None => {
+                    let term = deunicode(term).to_lowercase();
+                    match (text_groups.last_mut(), text_terms.last_mut()) {
+                        (Some(group), Some(terms)) if join => {
+                            group.push(phrase);
+                            terms.push(term);
+                        }
+                        _ => {
+                            text_groups.push(vec![phrase]);
+                            text_terms.push(vec![term]);
+                        }
+                    }
+                }
+                Some(field) => match filters.last_mut() {
+                    Some(filter) if join && !filter.negated => {
+                        filter.value = format!("{} OR {phrase}", filter.value);
+                    }
+                    _ => filters.push(FieldFilter {
+                        field,
+                        value: phrase,
+                        negated,
+                    }),
+                },
+            }
+        }
+
+        let text = (!text_groups.is_empty()).then(|| {
+            text_groups
+                .into_iter()
+                .map(|x| format!("({})", x.join(" OR ")))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        });
+        let excluded_text = (!excluded_text.is_empty()).then(|| excluded_text.join(" OR "));
+
+        SearchQuery {
+            text,
+            excluded_text,
+            text_terms,
+            filters,
+        }
+    }
+}
+
+/// An item found by a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub collection_type: CollectionQueryType,
+    pub id: i64,
+    /// How well the free text matches the name of the item, higher is
+    /// better. `0` for items found only through their fields.
+    pub score: f64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SearchRow {
+    key: String,
+    score: f64,
+}
+
+/// The items of `collection_type` whose names match the FTS5 expression,
+/// with their best score.
+async fn match_entries(
+    main_db: &DatabaseConnection,
+    collection_type: &CollectionQueryType,
+    expression: &str,
+    limit: usize,
+) -> Result<HashMap<i64, f64>> {
+    // Every item is indexed with its original and transliterated name
+    let rows = SearchRow::find_by_statement(Statement::from_sql_and_values(
         DbBackend::Sqlite,
-        r#"INSERT INTO search_index (id, key, entry_type, doc) VALUES ('', ?, ?, ?), ('', ?, ?, ?);"#,
+        r#"SELECT key, -rank AS score FROM search_index WHERE doc MATCH ? AND entry_type = ? ORDER BY rank LIMIT ?;"#,
         [
-            id.to_string().into(),
-            entry_type.to_string().into(),
-            name.to_string().into(),
-            id.to_string().into(),
-            entry_type.to_string().into(),
-            deunicode(name).into(),
+            expression.into(),
+            collection_type.to_string().into(),
+            (limit * 2).to_string().into(),
         ],
-    )).all(main_db).await?;
+    ))
+    .all(main_db)
+    .await?;
 
-    Ok(())
+    let mut scores: HashMap<i64, f64> = HashMap::new();
+    for row in rows {
+        match row.key.parse::<i64>() {
+            Ok(id) => {
+                let score = scores.entry(id).or_insert(row.score);
+                *score = score.max(row.score);
+            }
+            Err(_) => warn!("Invalid document ID found!"),
+        }
+    }
+
+    Ok(scores)
+}
+
+/// How many edits a term may have to be matched with typos, longer terms
+/// tolerate more.
+fn max_edits(term: &str) -> usize {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// The Levenshtein distance between `a` and `b`, `None` if it is over
+/// `max`.
+fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, y) in b.iter().enumerate() {
+            current[j + 1] = (previous[j] + usize::from(x != y))
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+
+        // The distance only grows from here
+        if current.iter().min().is_some_and(|x| *x > max) {
+            return None;
+        }
+        previous = current;
+    }
+
+    Some(previous[b.len()]).filter(|x| *x <= max)
 }
 
 #[derive(Debug, FromQueryResult)]
-pub struct SearchResult {
-    pub key: String,
-    pub entry_type: String,
-    pub doc: String,
+struct VocabularyRow {
+    term: String,
+}
+
+/// FTS5 expressions matching the free text with typos, by how many edits
+/// each term may have. Only single words are matched with typos, and
+/// levels that tolerate nothing more than the one before are left out.
+async fn fuzzy_expressions(
+    main_db: &DatabaseConnection,
+    text_terms: &[Vec<String>],
+) -> Result<Vec<(usize, String)>> {
+    let terms: Vec<&str> = text_terms
+        .iter()
+        .flatten()
+        .map(String::as_str)
+        .filter(|x| max_edits(x) > 0 && x.chars().all(char::is_alphanumeric))
+        .collect();
+    let (Some(shortest), Some(longest)) = (
+        terms.iter().map(|x| x.chars().count()).min(),
+        terms.iter().map(|x| x.chars().count()).max(),
+    ) else {
+        return Ok(Vec::new());
+    };
+
+    let vocabulary = VocabularyRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        r#"SELECT DISTINCT term FROM search_index_vocab WHERE col = 'doc' AND length(term) BETWEEN ? AND ?;"#,
+        [
+            (shortest.saturating_sub(2) as i64).into(),
+            (longest as i64 + 2).into(),
+        ],
+    ))
+    .all(main_db)
+    .await?;
+
+    // The words of the index close to every term, with their distance
+    let candidates: HashMap<&str, Vec<(usize, &str)>> = terms
+        .iter()
+        .map(|term| {
+            let close = vocabulary
+                .iter()
+                .filter_map(|x| {
+                    edit_distance(term, &x.term, max_edits(term)).map(|d| (d, x.term.as_str()))
+                })
+                .collect();
+            (*term, close)
+        })
+        .collect();
+
+    let mut expressions = Vec::new();
+    for edits in 1..=2 {
+        if !candidates.values().flatten().any(|(d, _)| *d == edits) {
+            continue;
+        }
+
+        let expression = text_terms
+            .iter()
+            .map(|group| {
+                let alternatives: Vec<String> = group
+                    .iter()
+                    .map(|term| {
+                        let close: Vec<String> = candidates
+                            .get(term.as_str())
+                            .into_iter()
+                            .flatten()
+                            .filter(|(d, _)| *d <= edits)
+                            .map(|(_, x)| fts_phrase(x))
+                            .collect();
+                        if close.is_empty() {
+                            fts_phrase(term)
+                        } else {
+                            close.join(" OR ")
+                        }
+                    })
+                    .collect();
+                format!("({})", alternatives.join(" OR "))
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        expressions.push((edits, expression));
+    }
+
+    Ok(expressions)
+}
+
+/// The tracks of the items, `None` for entries without tracks.
+async fn tracks_of(
+    main_db: &DatabaseConnection,
+    collection_type: &CollectionQueryType,
+    ids: Vec<i32>,
+) -> Result<Option<Vec<i32>>> {
+    let file_ids = match collection_type {
+        CollectionQueryType::Track => ids,
+        CollectionQueryType::Artist => {
+            media_file_artists::Entity::find()
+                .select_only()
+                .column(media_file_artists::Column::MediaFileId)
+                .filter(media_file_artists::Column::ArtistId.is_in(ids))
+                .into_tuple()
+                .all(main_db)
+                .await?
+        }
+        CollectionQueryType::Album => {
+            media_file_albums::Entity::find()
+                .select_only()
+                .column(media_file_albums::Column::MediaFileId)
+                .filter(media_file_albums::Column::AlbumId.is_in(ids))
+                .into_tuple()
+                .all(main_db)
+                .await?
+        }
+        CollectionQueryType::Genre => {
+            media_file_genres::Entity::find()
+                .select_only()
+                .column(media_file_genres::Column::MediaFileId)
+                .filter(media_file_genres::Column::GenreId.is_in(ids))
+                .into_tuple()
+                .all(main_db)
+                .await?
+        }
+        CollectionQueryType::Playlist => {
+            media_file_playlists::Entity::find()
+                .select_only()
+                .column(media_file_playlists::Column::MediaFileId)
+                .filter(media_file_playlists::Column::PlaylistId.is_in(ids))
+                .into_tuple()
+                .all(main_db)
+                .await?
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(file_ids))
+}
+
+/// The items holding the tracks, `None` for entries without tracks.
+async fn items_of_tracks(
+    main_db: &DatabaseConnection,
+    collection_type: &CollectionQueryType,
+    file_ids: Vec<i32>,
+) -> Result<Option<Vec<i32>>> {
+    let ids = match collection_type {
+        CollectionQueryType::Track => file_ids,
+        CollectionQueryType::Artist => {
+            media_file_artists::Entity::find()
+                .select_only()
+                .column(media_file_artists::Column::ArtistId)
+                .filter(media_file_artists::Column::MediaFileId.is_in(file_ids))
+                .into_tuple()
+                .all(main_db)
+                .await?
+        }
+        CollectionQueryType::Album => {
+            media_file_albums::Entity::find()
+                .select_only()
+                .column(media_file_albums::Column::AlbumId)
+                .filter(media_file_albums::Column::MediaFileId.is_in(file_ids))
+                .into_tuple()
+                .all(main_db)
+                .await?
+        }
+        CollectionQueryType::Genre => {
+            media_file_genres::Entity::find()
+                .select_only()
+                .column(media_file_genres::Column::GenreId)
+                .filter(media_file_genres::Column::MediaFileId.is_in(file_ids))
+                .into_tuple()
+                .all(main_db)
+                .await?
+        }
+        CollectionQueryType::Playlist => {
+            media_file_playlists::Entity::find()
+                .select_only()
+                .column(media_file_playlists::Column::PlaylistId)
+                .filter(media_file_playlists::Column::MediaFileId.is_in(file_ids))
+                .into_tuple()
+                .all(main_db)
+                .await?
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(ids))
 }
 
+/// The items of `collection_type` a scoped term selects: those matching it
+/// for a field of the same type, otherwise those sharing tracks with the
+/// matching items, like the tracks of an artist. `None` if the two aren't
+/// related through tracks.
+async fn match_filter(
+    main_db: &DatabaseConnection,
+    collection_type: &CollectionQueryType,
+    filter: &FieldFilter,
+) -> Result<Option<HashMap<i64, f64>>> {
+    let field_type = filter.field.collection_type();
+    let matched = match_entries(main_db, &field_type, &filter.value, FIELD_MATCH_LIMIT).await?;
+    if field_type == *collection_type {
+        return Ok(Some(matched));
+    }
+
+    let matched_ids: Vec<i32> = matched.into_keys().map(|x| x as i32).collect();
+    let Some(file_ids) = tracks_of(main_db, &field_type, matched_ids).await? else {
+        return Ok(None);
+    };
+    let Some(ids) = items_of_tracks(main_db, collection_type, file_ids).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(ids.into_iter().map(|x| (i64::from(x), 0.0)).collect()))
+}
+
+/// Search the names of the library, see [`SearchQuery::parse`] for the
+/// syntax.
+///
+/// Types of entries with few results are searched again with the words of
+/// the free text one or two edits away, so `radiohed` still finds
+/// Radiohead, ranked below the exact matches.
+///
+/// # Arguments
+/// * `search_fields` - The types of entries to search, all of them if `None`.
+/// * `n` - How many results to return per type of entry.
+///
+/// # Returns
+/// * `Result<HashMap<CollectionQueryType, Vec<SearchResult>>>` - The results
+///   of every type of entry, the best first.
 pub async fn search_for(
     main_db: &DatabaseConnection,
     query_str: &str,
     search_fields: Option<Vec<CollectionQueryType>>,
     n: usize,
-) -> Result<HashMap<CollectionQueryType, Vec<i64>>> {
-    let mut results: HashMap<CollectionQueryType, Vec<i64>> = HashMap::new();
+) -> Result<HashMap<CollectionQueryType, Vec<SearchResult>>> {
+    let mut results: HashMap<CollectionQueryType, Vec<SearchResult>> = HashMap::new();
 
-    if query_str.is_empty() {
+    let query = SearchQuery::parse(query_str);
+    if query.text.is_none() && query.filters.iter().all(|x| x.negated) {
         return Ok(results);
     }
+    // Looked up once, when a type first has few results
+    let mut fuzzy: Option<Vec<(usize, String)>> = None;
 
-    for collection_type in [
+    'types: for collection_type in [
         CollectionQueryType::Track,
         CollectionQueryType::Artist,
         CollectionQueryType::Album,
@@ -122,23 +815,686 @@ pub async fn search_for(
             }
         }
 
-        let query_str = deunicode(query_str);
+        // Scoped terms narrow down the free text, so more of it is matched
+        // when there are some
+        let text_limit = if query.filters.is_empty() {
+            n
+        } else {
+            FIELD_MATCH_LIMIT
+        };
+        let mut scores = match &query.text {
+            Some(text) => {
+                let mut scores = match_entries(main_db, &collection_type, text, text_limit).await?;
 
-        let top_docs = SearchResult::find_by_statement(Statement::from_sql_and_values(
-            DbBackend::Sqlite,
-            r#"SELECT * FROM search_index WHERE doc MATCH ? AND entry_type = ? ORDER BY rank LIMIT ?;"#,
-            [ format!("\"{}\"", query_str.replace("\"", "\"\"")).into(), collection_type.to_string().into(), (n * 2).to_string().into() ],
-        )).all(main_db).await?;
-
-        for item in top_docs {
-            let id = item.key.parse::<i64>();
-            if let Ok(id) = id {
-                results.entry(collection_type.clone()).or_default().push(id);
-            } else {
-                warn!("Invalid document ID found!");
+                // Few results may come from a typo, look for close words
+                // then, the more edits the lower the score
+                if scores.len() < FUZZY_FALLBACK_THRESHOLD {
+                    if fuzzy.is_none() {
+                        fuzzy = Some(fuzzy_expressions(main_db, &query.text_terms).await?);
+                    }
+                    for (edits, expression) in fuzzy.iter().flatten() {
+                        let matched =
+                            match_entries(main_db, &collection_type, expression, text_limit)
+                                .await?;
+                        for (id, score) in matched {
+                            scores
+                                .entry(id)
+                                .or_insert(score * FUZZY_PENALTY.powi(*edits as i32));
+                        }
+                        if scores.len() >= FUZZY_FALLBACK_THRESHOLD {
+                            break;
+                        }
+                    }
+                }
+
+                Some(scores)
             }
+            None => None,
+        };
+
+        for filter in query.filters.iter().filter(|x| !x.negated) {
+            let Some(matched) = match_filter(main_db, &collection_type, filter).await? else {
+                continue 'types;
+            };
+
+            scores = Some(match scores {
+                Some(mut scores) => {
+                    scores.retain(|id, _| matched.contains_key(id));
+                    for (id, score) in scores.iter_mut() {
+                        *score += matched[id];
+                    }
+                    scores
+                }
+                None => matched,
+            });
+        }
+
+        let Some(mut scores) = scores else {
+            continue;
+        };
+
+        for filter in query.filters.iter().filter(|x| x.negated) {
+            if let Some(matched) = match_filter(main_db, &collection_type, filter).await? {
+                scores.retain(|id, _| !matched.contains_key(id));
+            }
+        }
+
+        if let Some(excluded_text) = &query.excluded_text {
+            let excluded =
+                match_entries(main_db, &collection_type, excluded_text, FIELD_MATCH_LIMIT).await?;
+            scores.retain(|id, _| !excluded.contains_key(id));
         }
+
+        let mut items: Vec<SearchResult> = scores
+            .into_iter()
+            .map(|(id, score)| SearchResult {
+                collection_type: collection_type.clone(),
+                id,
+                score,
+            })
+            .collect();
+        items.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        items.truncate(n);
+
+        if !items.is_empty() {
+            results.insert(collection_type, items);
+        }
+    }
+
+    // Hidden tracks stay in the index so they can be found again once shown
+    if let Some(tracks) = results.get_mut(&CollectionQueryType::Track) {
+        let hidden: HashSet<i64> = get_hidden_files(main_db)
+            .await?
+            .into_iter()
+            .map(i64::from)
+            .collect();
+        tracks.retain(|x| !hidden.contains(&x.id));
     }
 
     Ok(results)
 }
+
+/// How many suggestions are matched for every one returned, names indexed
+/// twice and hidden tracks are dropped afterwards.
+const SUGGESTION_OVERFETCH: usize = 4;
+
+/// A completion of what is being typed into the search box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSuggestion {
+    pub collection_type: CollectionQueryType,
+    pub id: i64,
+    /// The name of the item, as it is displayed.
+    pub text: String,
+    /// How well the name matches, higher is better.
+    pub score: f64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SuggestionRow {
+    key: String,
+    entry_type: String,
+    doc: String,
+    score: f64,
+}
+
+/// The FTS5 expression completing `prefix`: the last word is matched as a
+/// prefix, the words before it as they are. `None` if nothing is left to
+/// match.
+fn suggestion_expression(prefix: &str) -> Option<String> {
+    let words: Vec<String> = prefix
+        .split_whitespace()
+        .filter(|x| x.chars().any(char::is_alphanumeric))
+        .map(fts_phrase)
+        .collect();
+    let (last, rest) = words.split_last()?;
+
+    let mut expression = rest.to_vec();
+    // Words typed to the end are still completed, `radiohead ` also
+    // suggests `Radiohead Live`
+    expression.push(format!("{last}*"));
+
+    Some(expression.join(" "))
+}
+
+fn normalize_for_suggestion(text: &str) -> String {
+    deunicode(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[derive(Debug, FromQueryResult)]
+struct IndexedNameRow {
+    key: String,
+    entry_type: String,
+    doc: String,
+}
+
+/// The original names of the items, the first document indexed for each.
+async fn get_indexed_names<'a>(
+    main_db: &DatabaseConnection,
+    items: impl Iterator<Item = &'a (CollectionQueryType, i64)>,
+) -> Result<HashMap<(CollectionQueryType, i64), String>> {
+    let expression = items
+        .map(|(collection_type, id)| format!("(key:\"{id}\" AND entry_type:\"{collection_type}\")"))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    if expression.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = IndexedNameRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        r#"SELECT key, entry_type, doc FROM search_index WHERE search_index MATCH ? ORDER BY rowid;"#,
+        [expression.into()],
+    ))
+    .all(main_db)
+    .await?;
+
+    let mut names = HashMap::new();
+    for row in rows {
+        let (Ok(collection_type), Ok(id)) = (
+            row.entry_type.parse::<CollectionQueryType>(),
+            row.key.parse::<i64>(),
+        ) else {
+            continue;
+        };
+        names.entry((collection_type, id)).or_insert(row.doc);
+    }
+
+    Ok(names)
+}
+
+/// Suggest artists, albums and tracks whose names complete `prefix`, for
+/// showing while the query is typed.
+///
+/// Names starting with the whole prefix come first, then the best matches
+/// of the index, then the shorter names. Hidden tracks are not suggested.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `prefix` - What has been typed so far.
+/// * `limit` - How many suggestions to return at most.
+pub async fn search_suggest(
+    main_db: &DatabaseConnection,
+    prefix: &str,
+    limit: usize,
+) -> Result<Vec<SearchSuggestion>> {
+    let Some(expression) = suggestion_expression(prefix) else {
+        return Ok(Vec::new());
+    };
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let rows = SuggestionRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        r#"SELECT key, entry_type, doc, -rank AS score FROM search_index WHERE doc MATCH ? AND entry_type IN (?, ?, ?) ORDER BY rank LIMIT ?;"#,
+        [
+            expression.into(),
+            CollectionQueryType::Artist.to_string().into(),
+            CollectionQueryType::Album.to_string().into(),
+            CollectionQueryType::Track.to_string().into(),
+            (limit * SUGGESTION_OVERFETCH).to_string().into(),
+        ],
+    ))
+    .all(main_db)
+    .await?;
+
+    let hidden: HashSet<i64> = get_hidden_files(main_db)
+        .await?
+        .into_iter()
+        .map(i64::from)
+        .collect();
+
+    let mut suggestions: HashMap<(CollectionQueryType, i64), SearchSuggestion> = HashMap::new();
+    for row in rows {
+        let (Ok(collection_type), Ok(id)) = (
+            row.entry_type.parse::<CollectionQueryType>(),
+            row.key.parse::<i64>(),
+        ) else {
+            warn!("Invalid document ID found!");
+            continue;
+        };
+        if collection_type == CollectionQueryType::Track && hidden.contains(&id) {
+            continue;
+        }
+
+        let suggestion = suggestions
+            .entry((collection_type.clone(), id))
+            .or_insert_with(|| SearchSuggestion {
+                collection_type,
+                id,
+                text: row.doc,
+                score: row.score,
+            });
+        suggestion.score = suggestion.score.max(row.score);
+    }
+
+    // The matched document may be the transliterated or the split name,
+    // the original one is displayed
+    let names = get_indexed_names(main_db, suggestions.keys()).await?;
+    for (item, suggestion) in suggestions.iter_mut() {
+        if let Some(name) = names.get(item) {
+            suggestion.text = name.clone();
+        }
+    }
+
+    let prefix = normalize_for_suggestion(prefix);
+    let mut suggestions: Vec<(bool, SearchSuggestion)> = suggestions
+        .into_values()
+        .map(|x| (normalize_for_suggestion(&x.text).starts_with(&prefix), x))
+        .collect();
+    suggestions.sort_by(|(a_starts, a), (b_starts, b)| {
+        b_starts
+            .cmp(a_starts)
+            .then(b.score.total_cmp(&a.score))
+            .then(a.text.len().cmp(&b.text.len()))
+            .then(a.text.cmp(&b.text))
+    });
+
+    Ok(suggestions
+        .into_iter()
+        .take(limit)
+        .map(|(_, x)| x)
+        .collect())
+}
+
+/// Tables holding the items of each entry type, terms whose item is gone
+/// are orphans.
+const INDEXED_TABLES: [(CollectionQueryType, &str); 6] = [
+    (CollectionQueryType::Track, "media_files"),
+    (CollectionQueryType::Artist, "artists"),
+    (CollectionQueryType::Album, "albums"),
+    (CollectionQueryType::Genre, "genres"),
+    (CollectionQueryType::Playlist, "playlists"),
+    (CollectionQueryType::Mix, "mixes"),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchIndexStats {
+    pub documents: i64,
+    /// Bytes taken by the full-text index and the indexed text.
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchIndexOptimizeReport {
+    pub before: SearchIndexStats,
+    pub after: SearchIndexStats,
+    pub removed_orphans: u64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CountResult {
+    count: i64,
+}
+
+async fn query_count(main_db: &DatabaseConnection, sql: &str) -> Result<i64> {
+    let result =
+        CountResult::find_by_statement(Statement::from_string(DbBackend::Sqlite, sql.to_owned()))
+            .one(main_db)
+            .await?;
+
+    Ok(result.map(|x| x.count).unwrap_or_default())
+}
+
+pub async fn get_search_index_stats(main_db: &DatabaseConnection) -> Result<SearchIndexStats> {
+    let documents = query_count(main_db, "SELECT COUNT(*) AS count FROM search_index;").await?;
+    // FTS5 keeps the index and the text in its shadow tables
+    let index_bytes = query_count(
+        main_db,
+        "SELECT COALESCE(SUM(LENGTH(block)), 0) AS count FROM search_index_data;",
+    )
+    .await?;
+    let content_bytes = query_count(
+        main_db,
+        "SELECT COALESCE(SUM(
+            LENGTH(CAST(c0 AS BLOB)) + LENGTH(CAST(c1 AS BLOB))
+            + LENGTH(CAST(c2 AS BLOB)) + LENGTH(CAST(c3 AS BLOB))
+        ), 0) AS count FROM search_index_content;",
+    )
+    .await?;
+
+    Ok(SearchIndexStats {
+        documents,
+        size_bytes: index_bytes + content_bytes,
+    })
+}
+
+/// Drop the terms of items that no longer exist, then merge the segments
+/// of the full-text index into one, which also purges deleted documents.
+pub async fn optimize_search_index(
+    main_db: &DatabaseConnection,
+) -> Result<SearchIndexOptimizeReport> {
+    let before = get_search_index_stats(main_db).await?;
+
+    let mut removed_orphans = 0;
+    for (entry_type, table) in INDEXED_TABLES {
+        let result = main_db
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                format!(
+                    "DELETE FROM search_index WHERE entry_type = ? \
+                     AND CAST(key AS INTEGER) NOT IN (SELECT id FROM {table});"
+                ),
+                [entry_type.to_string().into()],
+            ))
+            .await?;
+        removed_orphans += result.rows_affected();
+    }
+
+    main_db
+        .execute_unprepared("INSERT INTO search_index(search_index) VALUES('optimize');")
+        .await?;
+
+    let after = get_search_index_stats(main_db).await?;
+    info!(
+        "Search index optimized: {} orphaned documents removed, {} -> {} bytes",
+        removed_orphans, before.size_bytes, after.size_bytes
+    );
+
+    Ok(SearchIndexOptimizeReport {
+        before,
+        after,
+        removed_orphans,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+    use crate::{
+        actions::{
+            collection::CollectionQueryType,
+            search::{
+                SearchField, SearchQuery, add_term, get_search_index_stats, optimize_search_index,
+                search_for, search_suggest, upgrade_search_index,
+            },
+            settings::{SEARCH_INDEX_VERSION_KEY, set_setting},
+            stats::set_hidden,
+        },
+        entities::artists,
+        test_support::{FakeTrack, connect_test_main_db, seed_fake_tracks, seed_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_optimize_removes_orphaned_documents() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 4).await?;
+
+        // A term left behind by a file that no longer exists
+        add_term(&db, CollectionQueryType::Track, 9999, "Ghost Track").await?;
+
+        let stats = get_search_index_stats(&db).await?;
+        assert!(stats.documents > 0);
+        assert!(stats.size_bytes > 0);
+
+        let report = optimize_search_index(&db).await?;
+        assert_eq!(report.before, stats);
+        // Every track is indexed with its original and transliterated title
+        assert_eq!(report.removed_orphans, 2);
+        assert_eq!(report.after.documents, stats.documents - 2);
+        assert!(report.after.size_bytes < report.before.size_bytes);
+
+        let results = search_for(&db, "Track", Some(vec![CollectionQueryType::Track]), 10).await?;
+        let mut track_ids: Vec<i64> = results.into_values().flatten().map(|x| x.id).collect();
+        track_ids.sort();
+        track_ids.dedup();
+        assert_eq!(
+            track_ids,
+            file_ids.into_iter().map(i64::from).collect::<Vec<_>>()
+        );
+
+        // Nothing is left to clean up the second time
+        let report = optimize_search_index(&db).await?;
+        assert_eq!(report.removed_orphans, 0);
+
+        Ok(())
+    }
+
+    async fn track_ids(db: &sea_orm::DatabaseConnection, query: &str) -> Result<Vec<i64>> {
+        Ok(
+            search_for(db, query, Some(vec![CollectionQueryType::Track]), 10)
+                .await?
+                .into_values()
+                .flatten()
+                .map(|x| x.id)
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_indexes_cjk_names() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        // Indexed by the first version, which kept every run of characters as
+        // one word
+        db.execute_unprepared(
+            "INSERT INTO search_index (id, key, entry_type, doc) VALUES \
+             ('', '7', 'track', '北京欢迎你'), ('', '7', 'track', 'Bei Jing Huan Ying Ni ');",
+        )
+        .await?;
+        set_setting(&db, SEARCH_INDEX_VERSION_KEY, Some("1")).await?;
+        assert!(track_ids(&db, "欢迎").await?.is_empty());
+
+        assert_eq!(upgrade_search_index(&db).await?, 1);
+        assert_eq!(upgrade_search_index(&db).await?, 0);
+
+        for query in ["北京欢迎你", "欢迎", "北", "beijing", "bei jing"] {
+            assert_eq!(track_ids(&db, query).await?, vec![7], "query: {query}");
+        }
+        assert!(track_ids(&db, "京北").await?.is_empty());
+
+        Ok(())
+    }
+
+    fn track(index: usize, title: &str, artist: &str, album: &str) -> FakeTrack {
+        FakeTrack {
+            title: title.to_string(),
+            artist: artist.to_string(),
+            album: album.to_string(),
+            directory: format!("{artist}/{album}"),
+            ..FakeTrack::nth(index)
+        }
+    }
+
+    async fn search_ids(
+        db: &sea_orm::DatabaseConnection,
+        query: &str,
+        collection_type: CollectionQueryType,
+    ) -> Result<Vec<i64>> {
+        let mut results = search_for(db, query, Some(vec![collection_type.clone()]), 10).await?;
+        let mut ids: Vec<i64> = results
+            .remove(&collection_type)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    #[test]
+    fn test_parse_search_query() {
+        let query = SearchQuery::parse(
+            r#"artist:radiohead OR artist:muse album:"ok computer" karma -live"#,
+        );
+
+        assert_eq!(query.text.as_deref(), Some(r#"("karma")"#));
+        assert_eq!(query.excluded_text.as_deref(), Some(r#""live""#));
+        assert_eq!(query.filters.len(), 2);
+        assert_eq!(query.filters[0].field, SearchField::Artist);
+        assert_eq!(query.filters[0].value, r#""radiohead" OR "muse""#);
+        assert_eq!(query.filters[1].field, SearchField::Album);
+        assert_eq!(query.filters[1].value, r#""ok computer""#);
+        assert!(!query.filters[1].negated);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_field() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let ids = seed_tracks(
+            &db,
+            &[
+                track(0, "Airbag", "Radiohead", "OK Computer"),
+                track(1, "Karma Police", "Radiohead", "OK Computer"),
+                track(2, "Creep", "Radiohead", "Pablo Honey"),
+                track(3, "Karma Police Live", "Radiohead", "OK Computer"),
+                track(4, "Karma", "Other Band", "Other Album"),
+            ],
+        )
+        .await?;
+        let ids: Vec<i64> = ids.into_iter().map(i64::from).collect();
+
+        assert_eq!(
+            search_ids(
+                &db,
+                r#"artist:radiohead album:"ok computer""#,
+                CollectionQueryType::Track
+            )
+            .await?,
+            vec![ids[0], ids[1], ids[3]]
+        );
+        assert_eq!(
+            search_ids(
+                &db,
+                "karma NOT artist:radiohead",
+                CollectionQueryType::Track
+            )
+            .await?,
+            vec![ids[4]]
+        );
+        assert_eq!(
+            search_ids(&db, "karma police -live", CollectionQueryType::Track).await?,
+            vec![ids[1]]
+        );
+        assert_eq!(
+            search_ids(&db, "airbag OR creep", CollectionQueryType::Track).await?,
+            vec![ids[0], ids[2]]
+        );
+
+        // Other types are selected through their tracks
+        let radiohead = artists::Entity::find()
+            .filter(artists::Column::Name.eq("Radiohead"))
+            .one(&db)
+            .await?
+            .unwrap();
+        assert_eq!(
+            search_ids(&db, r#"album:"pablo honey""#, CollectionQueryType::Artist).await?,
+            vec![i64::from(radiohead.id)]
+        );
+
+        // The free text ranks the results
+        let results: HashMap<_, _> = search_for(&db, "karma", None, 10).await?;
+        let tracks = &results[&CollectionQueryType::Track];
+        assert_eq!(tracks.len(), 3);
+        assert!(tracks.iter().all(|x| x.score > 0.0));
+        assert!(tracks.windows(2).all(|x| x[0].score >= x[1].score));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_suggest() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let ids = seed_tracks(
+            &db,
+            &[
+                track(0, "Radio Ga Ga", "Queen", "The Works"),
+                track(1, "Creep", "Radiohead", "Pablo Honey"),
+                track(2, "Airbag", "Radiohead", "OK Computer"),
+                track(3, "Radioactive", "Imagine Dragons", "Night Visions"),
+                track(4, "Jóga", "Björk", "Homogenic"),
+            ],
+        )
+        .await?;
+
+        let suggestions = search_suggest(&db, "radio", 10).await?;
+        let texts: Vec<&str> = suggestions.iter().map(|x| x.text.as_str()).collect();
+        assert_eq!(texts.len(), 3, "every item is suggested once: {texts:?}");
+        assert!(texts.contains(&"Radiohead"));
+        assert!(texts.contains(&"Radio Ga Ga"));
+        assert!(texts.contains(&"Radioactive"));
+        assert!(
+            suggestions
+                .iter()
+                .any(|x| x.collection_type == CollectionQueryType::Artist)
+        );
+
+        // The last word is completed, the ones before it must match
+        let suggestions = search_suggest(&db, "ok comp", 10).await?;
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].collection_type, CollectionQueryType::Album);
+        assert_eq!(suggestions[0].text, "OK Computer");
+
+        // Names are matched without their accents and displayed with them
+        let suggestions = search_suggest(&db, "bjo", 10).await?;
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].text, "Björk");
+
+        set_hidden(&db, ids[3], true).await?;
+        let suggestions = search_suggest(&db, "radio", 1).await?;
+        assert_eq!(suggestions.len(), 1);
+        assert!(
+            !search_suggest(&db, "radioac", 10)
+                .await?
+                .iter()
+                .any(|x| x.id == i64::from(ids[3]))
+        );
+
+        assert!(search_suggest(&db, "  - ", 10).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_tolerates_typos() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        seed_tracks(
+            &db,
+            &[
+                track(0, "Airbag", "Radiohead", "OK Computer"),
+                track(1, "Karma Police", "Radiohead", "OK Computer"),
+                track(2, "Teardrop", "Massive Attack", "Mezzanine"),
+            ],
+        )
+        .await?;
+
+        let radiohead = artists::Entity::find()
+            .filter(artists::Column::Name.eq("Radiohead"))
+            .one(&db)
+            .await?
+            .unwrap();
+        assert_eq!(
+            search_ids(&db, "radiohed", CollectionQueryType::Artist).await?,
+            vec![i64::from(radiohead.id)]
+        );
+        // Two edits for long words
+        assert_eq!(
+            search_ids(&db, "mezzanyn", CollectionQueryType::Album)
+                .await?
+                .len(),
+            1
+        );
+        // Short words must match exactly
+        assert!(
+            search_ids(&db, "kok", CollectionQueryType::Album)
+                .await?
+                .is_empty()
+        );
+
+        // Every word is still required, exact or close
+        let results = search_for(&db, "karma polic", None, 10).await?;
+        let tracks = &results[&CollectionQueryType::Track];
+        assert_eq!(tracks.len(), 1);
+        assert!(tracks[0].score > 0.0);
+
+        Ok(())
+    }
+}
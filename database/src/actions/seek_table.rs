@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use fsio::FsIo;
+use log::info;
+use sea_orm::{
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QuerySelect,
+};
+use tokio_util::sync::CancellationToken;
+
+use ::playback::seek_table::{SeekTable, build_seek_table};
+
+use crate::entities::{media_file_seek_table, media_files};
+use crate::parallel_media_files_processing;
+
+/// Extensions of the files that get a seek table. Other formats either seek
+/// well on their own or aren't handled by the index.
+const INDEXED_EXTENSIONS: [&str; 2] = ["ogg", "oga"];
+
+/// Files whose seek table is missing or was built for another version of
+/// the file.
+async fn get_unindexed_file_ids(main_db: &DatabaseConnection) -> Result<Vec<i32>> {
+    let indexed: HashMap<i32, String> = media_file_seek_table::Entity::find()
+        .select_only()
+        .column(media_file_seek_table::Column::MediaFileId)
+        .column(media_file_seek_table::Column::FileHash)
+        .into_tuple::<(i32, String)>()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let files: Vec<(i32, String, String)> = media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::Id)
+        .column(media_files::Column::Extension)
+        .column(media_files::Column::FileHash)
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    Ok(files
+        .into_iter()
+        .filter(|(_, extension, _)| {
+            INDEXED_EXTENSIONS
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(extension))
+        })
+        .filter(|(id, _, file_hash)| indexed.get(id) != Some(file_hash))
+        .map(|(id, _, _)| id)
+        .collect())
+}
+
+fn build_file_seek_table(
+    fsio: &FsIo,
+    lib_path: &Path,
+    file: &media_files::Model,
+    cancel_token: Option<CancellationToken>,
+) -> Result<Option<SeekTable>> {
+    if let Some(token) = &cancel_token
+        && token.is_cancelled()
+    {
+        return Err(anyhow!("Operation cancelled"));
+    }
+
+    let file_path = lib_path.join(&file.directory).join(&file.file_name);
+    let mut stream = fsio
+        .open(&file_path, "r")
+        .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+
+    build_seek_table(&mut stream)
+        .with_context(|| format!("Failed to index file: {}", file_path.display()))
+}
+
+async fn upsert_seek_table(
+    main_db: &DatabaseConnection,
+    file: &media_files::Model,
+    table: Option<SeekTable>,
+) -> Result<()> {
+    media_file_seek_table::Entity::delete_many()
+        .filter(media_file_seek_table::Column::MediaFileId.eq(file.id))
+        .exec(main_db)
+        .await?;
+
+    let model = media_file_seek_table::ActiveModel {
+        media_file_id: ActiveValue::Set(file.id),
+        file_hash: ActiveValue::Set(file.file_hash.clone()),
+        seek_table: ActiveValue::Set(table.map(|x| x.to_bytes()).unwrap_or_default()),
+        ..Default::default()
+    };
+    media_file_seek_table::Entity::insert(model)
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
+/// Build seek tables for the Ogg files of the library that don't have an
+/// up-to-date one. Files that can't be indexed are recorded with an empty
+/// table, so they are skipped until they change.
+pub async fn index_seek_tables<F>(
+    fsio: Arc<FsIo>,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    batch_size: usize,
+    progress_callback: F,
+    cancel_token: Option<CancellationToken>,
+) -> Result<usize>
+where
+    F: Fn(usize, usize) + Send + Sync + 'static,
+{
+    info!("Starting seek table indexing with batch size: {batch_size}");
+
+    let progress_callback = Arc::new(progress_callback);
+
+    let file_ids = get_unindexed_file_ids(main_db).await?;
+    let cursor_query = media_files::Entity::find().filter(media_files::Column::Id.is_in(file_ids));
+
+    let lib_path = Arc::new(lib_path.to_path_buf());
+    let node_id = Arc::new(());
+
+    parallel_media_files_processing!(
+        main_db,
+        batch_size,
+        progress_callback,
+        cancel_token,
+        cursor_query,
+        lib_path,
+        fsio,
+        node_id,
+        move |fsio, file, lib_path, cancel_token| {
+            build_file_seek_table(fsio, lib_path, file, cancel_token)
+        },
+        |db, file: media_files::Model, _node_id, result: Result<Option<SeekTable>>| async move {
+            match result {
+                Ok(table) => match upsert_seek_table(db, &file, table).await {
+                    Ok(_) => debug!("Indexed seek table for file: {}", file.id),
+                    Err(e) => error!("Failed to save seek table: {e:#?}"),
+                },
+                Err(e) => error!("Failed to build seek table: {e:#?}"),
+            }
+        }
+    )
+}
+
+/// Stored seek tables of the given files. Files without a usable table are
+/// left out.
+pub async fn get_seek_tables_by_file_ids(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, SeekTable>> {
+    Ok(media_file_seek_table::Entity::find()
+        .filter(media_file_seek_table::Column::MediaFileId.is_in(file_ids.iter().copied()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .filter_map(|x| Some((x.media_file_id, SeekTable::from_bytes(&x.seek_table)?)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc, time::Duration};
+
+    use anyhow::Result;
+    use sea_orm::{ActiveModelTrait, ActiveValue};
+    use tempfile::tempdir;
+
+    use ::fsio::FsIo;
+    use ::playback::seek_table::SeekTarget;
+
+    use crate::{
+        actions::seek_table::{get_seek_tables_by_file_ids, index_seek_tables},
+        entities::media_files,
+        test_support::{FakeTrack, connect_test_main_db, seed_fake_tracks, seed_tracks},
+    };
+
+    const SAMPLE_RATE: u32 = 44100;
+    const AUDIO_PAGE_LEN: u64 = 27 + 1 + 100;
+    /// Identification page plus the page with the comment and setup headers.
+    const HEADER_LEN: u64 = (27 + 1 + 30) + (27 + 2 + 20);
+
+    /// An Ogg page with a body of packets shorter than 255 bytes. The scanner
+    /// doesn't check CRCs, so the checksum is left empty.
+    fn ogg_page(header_type: u8, granule: i64, serial: u32, packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut page = b"OggS".to_vec();
+        page.push(0);
+        page.push(header_type);
+        page.extend(granule.to_le_bytes());
+        page.extend(serial.to_le_bytes());
+        page.extend(0u32.to_le_bytes());
+        page.extend(0u32.to_le_bytes());
+        page.push(packets.len() as u8);
+        page.extend(packets.iter().map(|x| x.len() as u8));
+        for packet in packets {
+            page.extend(packet);
+        }
+        page
+    }
+
+    /// A Vorbis stream with `pages` audio pages of half a second each.
+    fn vorbis_chain(serial: u32, pages: i64) -> Vec<u8> {
+        let mut identification = vec![1];
+        identification.extend(b"vorbis");
+        identification.extend(0u32.to_le_bytes());
+        identification.push(2);
+        identification.extend(SAMPLE_RATE.to_le_bytes());
+        identification.resize(30, 0);
+
+        let mut stream = ogg_page(0x02, 0, serial, &[identification]);
+        stream.extend(ogg_page(0, 0, serial, &[vec![3; 10], vec![5; 10]]));
+        for page in 1..=pages {
+            let header_type = if page == pages { 0x04 } else { 0 };
+            let granule = page * SAMPLE_RATE as i64 / 2;
+            stream.extend(ogg_page(header_type, granule, serial, &[vec![0; 100]]));
+        }
+        stream
+    }
+
+    #[tokio::test]
+    async fn test_index_chained_ogg() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+
+        let mut chained = vorbis_chain(1, 10);
+        chained.extend(vorbis_chain(2, 4));
+        fs::write(lib_dir.path().join("chained.ogg"), chained)?;
+
+        let ogg_track = FakeTrack {
+            file_name: "chained.ogg".to_string(),
+            directory: String::new(),
+            ..FakeTrack::nth(0)
+        };
+        let ogg_id = seed_tracks(&db, &[ogg_track]).await?[0];
+        media_files::ActiveModel {
+            id: ActiveValue::Unchanged(ogg_id),
+            extension: ActiveValue::Set("OGG".to_string()),
+            ..Default::default()
+        }
+        .update(&db)
+        .await?;
+        // FLAC files are left alone
+        let flac_ids = seed_fake_tracks(&db, 2).await?;
+
+        let fsio = Arc::new(FsIo::new());
+        let indexed =
+            index_seek_tables(Arc::clone(&fsio), &db, lib_dir.path(), 2, |_, _| {}, None).await?;
+        assert_eq!(indexed, 1);
+
+        let tables = get_seek_tables_by_file_ids(&db, &[ogg_id, flac_ids[0]]).await?;
+        assert_eq!(tables.len(), 1);
+        let table = &tables[&ogg_id];
+        assert_eq!(table.chains.len(), 2);
+        assert_eq!(table.chains[0].samples, 5 * SAMPLE_RATE as u64);
+        // A point every second, on every other page
+        assert_eq!(table.chains[0].points.len(), 5);
+
+        assert_eq!(
+            table.locate(Duration::from_millis(1250)),
+            Some(SeekTarget {
+                header_start: 0,
+                header_end: HEADER_LEN,
+                offset: HEADER_LEN + 2 * AUDIO_PAGE_LEN,
+                skip: SAMPLE_RATE as u64 / 4,
+            })
+        );
+
+        // Positions after the first chain land in the second one
+        let second_start = HEADER_LEN + 10 * AUDIO_PAGE_LEN;
+        assert_eq!(
+            table.locate(Duration::from_secs(6)),
+            Some(SeekTarget {
+                header_start: second_start,
+                header_end: second_start + HEADER_LEN,
+                offset: second_start + HEADER_LEN + 2 * AUDIO_PAGE_LEN,
+                skip: 0,
+            })
+        );
+
+        // Up-to-date tables aren't built again
+        let indexed = index_seek_tables(fsio, &db, lib_dir.path(), 2, |_, _| {}, None).await?;
+        assert_eq!(indexed, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unindexable_file_is_recorded() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        fs::write(lib_dir.path().join("broken.ogg"), b"not an ogg file")?;
+
+        let track = FakeTrack {
+            file_name: "broken.ogg".to_string(),
+            directory: String::new(),
+            ..FakeTrack::nth(0)
+        };
+        let file_id = seed_tracks(&db, &[track]).await?[0];
+        media_files::ActiveModel {
+            id: ActiveValue::Unchanged(file_id),
+            extension: ActiveValue::Set("ogg".to_string()),
+            ..Default::default()
+        }
+        .update(&db)
+        .await?;
+
+        let fsio = Arc::new(FsIo::new());
+        let indexed =
+            index_seek_tables(Arc::clone(&fsio), &db, lib_dir.path(), 2, |_, _| {}, None).await?;
+        assert_eq!(indexed, 1);
+        assert!(
+            get_seek_tables_by_file_ids(&db, &[file_id])
+                .await?
+                .is_empty()
+        );
+
+        // The empty table keeps the file from being scanned again
+        let indexed = index_seek_tables(fsio, &db, lib_dir.path(), 2, |_, _| {}, None).await?;
+        assert_eq!(indexed, 0);
+
+        Ok(())
+    }
+}
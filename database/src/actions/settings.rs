@@ -0,0 +1,126 @@
+//! Application settings that have to survive a restart, stored as plain
+//! key-value pairs. Every key is owned by the feature that reads it.
+
+use anyhow::Result;
+use migration::OnConflict;
+use sea_orm::ActiveValue;
+use sea_orm::prelude::*;
+
+use crate::entities::settings;
+
+/// Name of the audio output device to play through, the system default if
+/// missing.
+pub const OUTPUT_DEVICE_KEY: &str = "playback.output_device";
+
+/// Equalizer settings serialized as JSON, a disabled flat equalizer if
+/// missing.
+pub const EQUALIZER_KEY: &str = "playback.equalizer";
+
+/// Night mode settings serialized as JSON, a disabled night mode if missing.
+pub const NIGHT_MODE_KEY: &str = "playback.night_mode";
+
+/// ReplayGain settings serialized as JSON, ReplayGain off if missing.
+pub const REPLAY_GAIN_KEY: &str = "playback.replay_gain";
+
+/// The listening exposure of the last day something was played serialized
+/// as JSON, no exposure if missing.
+pub const LISTENING_EXPOSURE_KEY: &str = "playback.listening_exposure";
+
+/// Rules for splitting artist and genre tags serialized as JSON, the default
+/// rules if missing.
+pub const SPLITTING_RULES_KEY: &str = "library.splitting_rules";
+
+/// Languages to show multi-language tags in, comma separated with the most
+/// preferred first, the original tags if missing.
+pub const METADATA_LANGUAGES_KEY: &str = "library.metadata_languages";
+
+/// The local date the daily mixes were last generated on, as `YYYY-MM-DD`.
+pub const DAILY_MIXES_DATE_KEY: &str = "mix.daily_generated_on";
+
+/// Mix presets defined by the user serialized as JSON, only the built-in
+/// presets if missing.
+pub const MIX_PRESETS_KEY: &str = "mix.presets";
+
+/// The version of the documents in the search index, `1` if missing.
+pub const SEARCH_INDEX_VERSION_KEY: &str = "search.index_version";
+
+/// The version of the groups of artists, albums and genres, `1` if missing.
+pub const GROUP_NAME_VERSION_KEY: &str = "library.group_version";
+
+/// Indexes suggested from the plans of slow statements serialized as JSON,
+/// none if missing.
+pub const INDEX_SUGGESTIONS_KEY: &str = "database.index_suggestions";
+
+pub async fn get_setting(main_db: &DatabaseConnection, key: &str) -> Result<Option<String>> {
+    Ok(settings::Entity::find()
+        .filter(settings::Column::Key.eq(key))
+        .one(main_db)
+        .await?
+        .map(|x| x.value))
+}
+
+/// Store `value` under `key`, or remove the key if `value` is `None`.
+pub async fn set_setting(
+    main_db: &DatabaseConnection,
+    key: &str,
+    value: Option<&str>,
+) -> Result<()> {
+    let Some(value) = value else {
+        settings::Entity::delete_many()
+            .filter(settings::Column::Key.eq(key))
+            .exec(main_db)
+            .await?;
+        return Ok(());
+    };
+
+    let model = settings::ActiveModel {
+        key: ActiveValue::Set(key.to_string()),
+        value: ActiveValue::Set(value.to_string()),
+        ..Default::default()
+    };
+    settings::Entity::insert(model)
+        .on_conflict(
+            OnConflict::column(settings::Column::Key)
+                .update_column(settings::Column::Value)
+                .to_owned(),
+        )
+        .exec_without_returning(main_db)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        actions::settings::{OUTPUT_DEVICE_KEY, get_setting, set_setting},
+        test_support::connect_test_main_db,
+    };
+
+    #[tokio::test]
+    async fn test_set_and_clear_setting() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        assert_eq!(get_setting(&db, OUTPUT_DEVICE_KEY).await?, None);
+
+        set_setting(&db, OUTPUT_DEVICE_KEY, Some("USB DAC")).await?;
+        assert_eq!(
+            get_setting(&db, OUTPUT_DEVICE_KEY).await?.as_deref(),
+            Some("USB DAC")
+        );
+
+        // Setting a key again replaces the value
+        set_setting(&db, OUTPUT_DEVICE_KEY, Some("Speakers")).await?;
+        assert_eq!(
+            get_setting(&db, OUTPUT_DEVICE_KEY).await?.as_deref(),
+            Some("Speakers")
+        );
+        assert_eq!(get_setting(&db, "other").await?, None);
+
+        set_setting(&db, OUTPUT_DEVICE_KEY, None).await?;
+        assert_eq!(get_setting(&db, OUTPUT_DEVICE_KEY).await?, None);
+
+        Ok(())
+    }
+}
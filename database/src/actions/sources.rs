@@ -0,0 +1,406 @@
+//! Extra roots of a library, such as an SD card or a network share, that
+//! are scanned together with the library root.
+//!
+//! Files of a source store the absolute path of their directory, so every
+//! `lib_path.join(directory)` resolves them without knowing about sources.
+//! A source that can't be reached is marked unavailable instead of having
+//! its files removed, they come back as soon as the source does.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use log::{info, warn};
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, Condition, PaginatorTrait, QuerySelect, sea_query::Expr};
+
+use ::fsio::FsIo;
+
+use crate::actions::{index::perform_library_maintenance, metadata::remove_file_record};
+use crate::entities::{library_sources, media_files};
+
+fn to_unix_path_string(path: &Path) -> Result<String> {
+    let path = path
+        .to_str()
+        .with_context(|| format!("Invalid UTF-8 sequence in path: {path:?}"))?;
+
+    Ok(path.replace('\\', "/").trim_end_matches('/').to_string())
+}
+
+/// The `directory` column of a file in a source, given its directory
+/// relative to the source root.
+pub fn source_directory(source_path: &str, directory: &str) -> String {
+    if directory.is_empty() {
+        source_path.to_string()
+    } else {
+        format!("{source_path}/{directory}")
+    }
+}
+
+/// Whether the files of a root can be read right now.
+///
+/// An unmounted network share or SD card often leaves an empty mount point
+/// behind, so an empty directory only counts as reachable if nothing was
+/// imported from it yet.
+pub fn is_root_reachable(path: &Path, has_files: bool) -> bool {
+    match fs::read_dir(path) {
+        Ok(mut entries) => !has_files || entries.next().is_some(),
+        Err(_) => false,
+    }
+}
+
+/// Whether any file was imported from a source, or from the library root
+/// if `source_id` is `None`.
+pub async fn has_source_files(
+    main_db: &DatabaseConnection,
+    source_id: Option<i32>,
+) -> Result<bool> {
+    let condition = match source_id {
+        Some(source_id) => media_files::Column::SourceId.eq(source_id),
+        None => media_files::Column::SourceId.is_null(),
+    };
+
+    let count = media_files::Entity::find()
+        .filter(condition)
+        .count(main_db)
+        .await?;
+
+    Ok(count > 0)
+}
+
+fn is_nested(a: &str, b: &str) -> bool {
+    a == b || a.starts_with(&format!("{b}/")) || b.starts_with(&format!("{a}/"))
+}
+
+/// Add a directory as a source of the library.
+///
+/// # Arguments
+/// * `fsio` - The file system used to resolve the path.
+/// * `main_db` - A reference to the database connection.
+/// * `lib_path` - The root of the library.
+/// * `name` - A name to show for the source, e.g. `SD Card`.
+/// * `path` - The directory to add.
+///
+/// # Returns
+/// * `Result<Model>` - The new source, or an error if the path doesn't exist
+///   or overlaps the library root or another source.
+pub async fn add_library_source(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    name: &str,
+    path: &Path,
+) -> Result<library_sources::Model> {
+    let path = fsio
+        .canonicalize_path(path)
+        .with_context(|| format!("Source not found: {path:?}"))?;
+    if !path.is_dir() {
+        bail!("Source is not a directory: {path:?}");
+    }
+
+    let path = to_unix_path_string(&path)?;
+    let lib_path = to_unix_path_string(&fsio.canonicalize_path(lib_path)?)?;
+    if is_nested(&path, &lib_path) {
+        bail!("Source overlaps the library root: {path}");
+    }
+
+    for source in get_library_sources(main_db).await? {
+        if is_nested(&path, &source.path) {
+            bail!("Source overlaps {}: {path}", source.name);
+        }
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let source = library_sources::ActiveModel {
+        name: ActiveValue::Set(name.to_string()),
+        path: ActiveValue::Set(path),
+        available: ActiveValue::Set(true),
+        last_seen_at: ActiveValue::Set(Some(now.clone())),
+        created_at: ActiveValue::Set(now),
+        ..Default::default()
+    };
+
+    Ok(source.insert(main_db).await?)
+}
+
+/// Get all sources of the library.
+pub async fn get_library_sources(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<library_sources::Model>> {
+    Ok(library_sources::Entity::find().all(main_db).await?)
+}
+
+/// Remove a source together with the records of its files.
+///
+/// # Returns
+/// * `Result<bool>` - Whether the source existed.
+pub async fn remove_library_source(main_db: &DatabaseConnection, source_id: i32) -> Result<bool> {
+    let Some(source) = library_sources::Entity::find_by_id(source_id)
+        .one(main_db)
+        .await?
+    else {
+        return Ok(false);
+    };
+
+    let file_ids: Vec<i32> = media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::Id)
+        .filter(media_files::Column::SourceId.eq(source_id))
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    for file_id in &file_ids {
+        remove_file_record(main_db, *file_id).await?;
+    }
+
+    library_sources::Entity::delete_by_id(source_id)
+        .exec(main_db)
+        .await?;
+
+    if !file_ids.is_empty() {
+        perform_library_maintenance(main_db, None).await?;
+    }
+
+    info!(
+        "Removed source {}, {} files removed",
+        source.name,
+        file_ids.len()
+    );
+
+    Ok(true)
+}
+
+/// Check which sources can be reached right now and store the result.
+///
+/// # Returns
+/// * `Result<Vec<Model>>` - All sources with their updated availability.
+pub async fn refresh_source_availability(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<library_sources::Model>> {
+    let now = Utc::now().to_rfc3339();
+    let mut sources = Vec::new();
+
+    for source in get_library_sources(main_db).await? {
+        let has_files = has_source_files(main_db, Some(source.id)).await?;
+        let available = is_root_reachable(Path::new(&source.path), has_files);
+        // Nothing to update for a source that is still offline
+        if !available && !source.available {
+            sources.push(source);
+            continue;
+        }
+
+        if available && !source.available {
+            info!("Source {} is back online", source.name);
+        } else if !available {
+            warn!("Source {} is offline, its files are kept", source.name);
+        }
+
+        let mut active_model: library_sources::ActiveModel = source.into();
+        active_model.available = ActiveValue::Set(available);
+        if available {
+            active_model.last_seen_at = ActiveValue::Set(Some(now.clone()));
+        }
+
+        sources.push(active_model.update(main_db).await?);
+    }
+
+    Ok(sources)
+}
+
+/// Get the IDs of sources that were offline when last checked.
+pub async fn get_unavailable_source_ids(main_db: &DatabaseConnection) -> Result<HashSet<i32>> {
+    let source_ids = library_sources::Entity::find()
+        .select_only()
+        .column(library_sources::Column::Id)
+        .filter(library_sources::Column::Available.eq(false))
+        .into_tuple::<i32>()
+        .all(main_db)
+        .await?;
+
+    Ok(source_ids.into_iter().collect())
+}
+
+/// Files that can be played right now, that is files under the library
+/// root and files of available sources.
+pub async fn available_files_condition(main_db: &DatabaseConnection) -> Result<Condition> {
+    let unavailable = get_unavailable_source_ids(main_db).await?;
+
+    Ok(Condition::any()
+        .add(media_files::Column::SourceId.is_null())
+        .add(media_files::Column::SourceId.is_not_in(unavailable)))
+}
+
+/// Mark files as belonging to a source.
+pub async fn assign_files_to_source(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+    source_id: i32,
+) -> Result<()> {
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+
+    media_files::Entity::update_many()
+        .col_expr(media_files::Column::SourceId, Expr::value(source_id))
+        .filter(media_files::Column::Id.is_in(file_ids.to_vec()))
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+    use sea_orm::{EntityTrait, PaginatorTrait};
+    use tempfile::tempdir;
+
+    use ::fsio::FsIo;
+    use ::metadata::backend::MetadataBackends;
+
+    use crate::{
+        actions::{
+            metadata::{
+                empty_progress_callback, get_metadata_summary_by_file_ids, scan_audio_library,
+            },
+            sources::{
+                add_library_source, assign_files_to_source, get_library_sources,
+                get_unavailable_source_ids, is_root_reachable, refresh_source_availability,
+                remove_library_source,
+            },
+        },
+        entities::media_files,
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_add_library_source() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let fsio = FsIo::new();
+        let lib_dir = tempdir()?;
+        let source_dir = tempdir()?;
+
+        let source =
+            add_library_source(&fsio, &db, lib_dir.path(), "SD Card", source_dir.path()).await?;
+        assert_eq!(source.name, "SD Card");
+        assert!(source.available);
+        assert!(!source.path.ends_with('/'));
+
+        // Sources can't overlap the library root or each other
+        let nested = source_dir.path().join("Music");
+        fs::create_dir(&nested)?;
+        assert!(
+            add_library_source(&fsio, &db, lib_dir.path(), "Nested", &nested)
+                .await
+                .is_err()
+        );
+        assert!(
+            add_library_source(&fsio, &db, lib_dir.path(), "Root", lib_dir.path())
+                .await
+                .is_err()
+        );
+        assert!(
+            add_library_source(
+                &fsio,
+                &db,
+                lib_dir.path(),
+                "Missing",
+                &lib_dir.path().join("x")
+            )
+            .await
+            .is_err()
+        );
+
+        assert_eq!(get_library_sources(&db).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_offline_source_is_kept() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let fsio = FsIo::new();
+        let lib_dir = tempdir()?;
+        let source_dir = tempdir()?;
+
+        let source =
+            add_library_source(&fsio, &db, lib_dir.path(), "NAS", source_dir.path()).await?;
+        let file_ids = seed_fake_tracks(&db, 4).await?;
+        assign_files_to_source(&db, &file_ids[..2], source.id).await?;
+
+        // Unplug the source
+        source_dir.close()?;
+        let sources = refresh_source_availability(&db).await?;
+        assert!(!sources[0].available);
+        assert!(sources[0].last_seen_at.is_some());
+        assert!(get_unavailable_source_ids(&db).await?.contains(&source.id));
+
+        // Files under the root are gone, files of the offline source stay
+        fs::write(lib_dir.path().join("notes.txt"), "")?;
+        scan_audio_library(
+            &fsio,
+            &MetadataBackends::default(),
+            &db,
+            lib_dir.path(),
+            true,
+            false,
+            empty_progress_callback,
+            None,
+        )
+        .await?;
+
+        let remaining: Vec<i32> = media_files::Entity::find()
+            .all(&db)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        assert_eq!(remaining, file_ids[..2]);
+
+        let summaries = get_metadata_summary_by_file_ids(&db, remaining).await?;
+        assert!(summaries.iter().all(|x| !x.available));
+
+        // Removing the source removes its files as well
+        assert!(remove_library_source(&db, source.id).await?);
+        assert!(!remove_library_source(&db, source.id).await?);
+        assert!(media_files::Entity::find().all(&db).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unmounted_root_is_kept() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let fsio = FsIo::new();
+        let lib_dir = tempdir()?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        // An empty mount point looks like every file was deleted
+        assert!(!is_root_reachable(lib_dir.path(), true));
+        assert!(is_root_reachable(lib_dir.path(), false));
+        assert!(!is_root_reachable(&lib_dir.path().join("missing"), false));
+
+        scan_audio_library(
+            &fsio,
+            &MetadataBackends::default(),
+            &db,
+            lib_dir.path(),
+            true,
+            false,
+            empty_progress_callback,
+            None,
+        )
+        .await?;
+        assert_eq!(
+            media_files::Entity::find().count(&db).await?,
+            file_ids.len() as u64
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+//! Library-level rules for splitting artist and genre tags.
+//!
+//! The rules are read whenever files are indexed. Changing them doesn't touch
+//! the index by itself, [`reapply_splitting`] derives the artist and genre
+//! links again from the stored metadata, without reading the files.
+
+use anyhow::{Context, Result};
+use log::info;
+use sea_orm::{QueryOrder, QuerySelect, prelude::*};
+use tokio_util::sync::CancellationToken;
+
+use ::metadata::splitting::SplittingRules;
+
+use crate::actions::index::{cleanup_orphaned_records, index_media_files};
+use crate::actions::settings::{SPLITTING_RULES_KEY, get_setting, set_setting};
+use crate::entities::media_files;
+
+/// Files indexed again per batch by [`reapply_splitting`].
+const REAPPLY_BATCH_SIZE: usize = 200;
+
+/// The splitting rules of the library, the default rules if never changed.
+pub async fn get_splitting_rules(main_db: &DatabaseConnection) -> Result<SplittingRules> {
+    let Some(value) = get_setting(main_db, SPLITTING_RULES_KEY).await? else {
+        return Ok(SplittingRules::default());
+    };
+
+    serde_json::from_str(&value).with_context(|| "Failed to parse the saved splitting rules")
+}
+
+/// Store the splitting rules of the library, or go back to the default
+/// rules if `rules` is `None`.
+pub async fn set_splitting_rules(
+    main_db: &DatabaseConnection,
+    rules: Option<&SplittingRules>,
+) -> Result<()> {
+    let value = rules.map(serde_json::to_string).transpose()?;
+    set_setting(main_db, SPLITTING_RULES_KEY, value.as_deref()).await
+}
+
+/// Split the artist and genre tags of every file again with the current
+/// rules, and drop the artists and genres nobody links to anymore. Returns
+/// the number of files indexed again.
+pub async fn reapply_splitting(
+    main_db: &DatabaseConnection,
+    cancel_token: Option<&CancellationToken>,
+) -> Result<usize> {
+    let file_ids: Vec<i32> = media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::Id)
+        .order_by_asc(media_files::Column::Id)
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    for batch in file_ids.chunks(REAPPLY_BATCH_SIZE) {
+        if cancel_token.is_some_and(|x| x.is_cancelled()) {
+            info!("Reapplying the splitting rules cancelled");
+            return Ok(0);
+        }
+
+        index_media_files(main_db, batch.to_vec(), cancel_token).await?;
+    }
+
+    cleanup_orphaned_records(main_db).await?;
+
+    info!("Splitting rules reapplied to {} files", file_ids.len());
+    Ok(file_ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::EntityTrait;
+
+    use ::metadata::{artist::split_artists, genre::split_genres, splitting::SplittingRules};
+
+    use crate::{
+        actions::splitting::{get_splitting_rules, reapply_splitting, set_splitting_rules},
+        entities::{artists, genres},
+        test_support::{FakeTrack, connect_test_main_db, seed_tracks},
+    };
+
+    async fn names<E>(db: &sea_orm::DatabaseConnection, name: fn(E::Model) -> String) -> Vec<String>
+    where
+        E: EntityTrait,
+    {
+        let mut names: Vec<String> = E::find()
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_exceptions_are_never_split() {
+        let rules = SplittingRules {
+            artist_delimiters: vec!["/".to_string(), " & ".to_string()],
+            exceptions: vec!["AC/DC".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            split_artists("ac/dc/Alpha & Beta", &rules),
+            vec!["ac/dc".to_string(), "Alpha".to_string(), "Beta".to_string()],
+            "exceptions are matched ignoring case"
+        );
+        assert_eq!(
+            split_genres("Rock/AC/DC", &rules),
+            vec!["Rock".to_string(), "AC/DC".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reapply_splitting() -> Result<()> {
+        let db = connect_test_main_db().await?;
+
+        let mut track = FakeTrack::nth(0);
+        track.artist = "Alpha & Beta".to_string();
+        track.genre = "Drum/Bass".to_string();
+        seed_tracks(&db, &[track]).await?;
+
+        assert_eq!(get_splitting_rules(&db).await?, SplittingRules::default());
+        assert_eq!(
+            names::<artists::Entity>(&db, |x| x.name).await,
+            vec!["Alpha".to_string(), "Beta".to_string()]
+        );
+
+        let rules = SplittingRules {
+            exceptions: vec!["Alpha & Beta".to_string(), "Drum/Bass".to_string()],
+            ..Default::default()
+        };
+        set_splitting_rules(&db, Some(&rules)).await?;
+        assert_eq!(get_splitting_rules(&db).await?, rules);
+
+        assert_eq!(reapply_splitting(&db, None).await?, 1);
+        assert_eq!(
+            names::<artists::Entity>(&db, |x| x.name).await,
+            vec!["Alpha & Beta".to_string()],
+            "artists nobody links to anymore are removed"
+        );
+        assert_eq!(
+            names::<genres::Entity>(&db, |x| x.name).await,
+            vec!["Drum/Bass".to_string()]
+        );
+
+        set_splitting_rules(&db, None).await?;
+        assert_eq!(get_splitting_rules(&db).await?, SplittingRules::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,525 @@
+//! Stations, endless channels generated from the library.
+//!
+//! Stations are listed from what the library holds: its genres, its
+//! decades, the genres of a decade like "80s Synth", and the groups of
+//! tracks that sound alike on the library map. Unlike mixes they have no
+//! length, a station picks tracks from its pool at random for as long as it
+//! plays. The picks lean away from the artists skipped during the session
+//! and towards the artists played through, see [`StationFeedback`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use rand::Rng;
+use rand::rngs::StdRng;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::*;
+
+use crate::actions::file::get_file_by_id;
+use crate::actions::library_map::{LibraryMapPoint, get_library_map};
+use crate::actions::metadata::extract_number;
+use crate::actions::radio::{RadioSeed, get_radio_tracks};
+use crate::actions::stats::get_hidden_files;
+use crate::connection::RecommendationDbConnection;
+use crate::entities::{genres, media_file_artists, media_file_genres, media_files, media_metadata};
+
+/// How many tracks a station needs to be listed.
+pub const STATION_MIN_TRACKS: usize = 10;
+
+/// How many tracks that sound alike make the pool of a sound station.
+const SOUND_STATION_POOL: usize = 200;
+
+/// How much of a track has to play for it to count as played through
+/// rather than skipped.
+pub const STATION_PLAYED_THROUGH_RATIO: f64 = 0.8;
+
+/// How much a skip of an artist weighs its other tracks down, and how much
+/// a track played through weighs them up.
+const SKIP_FACTOR: f64 = 0.5;
+const PLAY_FACTOR: f64 = 1.25;
+
+/// Bounds of the weight of a track, so a few skips don't silence an artist
+/// for the whole session.
+const MIN_WEIGHT: f64 = 0.05;
+const MAX_WEIGHT: f64 = 4.0;
+
+/// What the tracks of a station are picked from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StationSource {
+    Genre(i32),
+    /// The first year of a decade, like `1980`.
+    Decade(i32),
+    GenreDecade {
+        genre_id: i32,
+        decade: i32,
+    },
+    /// The tracks that sound like a track, the one closest to the centre
+    /// of a group of the library map when listed.
+    Sound(i32),
+}
+
+/// Written like `genre:3:decade:1980`, the key of the station in requests.
+impl fmt::Display for StationSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StationSource::Genre(id) => write!(f, "genre:{id}"),
+            StationSource::Decade(decade) => write!(f, "decade:{decade}"),
+            StationSource::GenreDecade { genre_id, decade } => {
+                write!(f, "genre:{genre_id}:decade:{decade}")
+            }
+            StationSource::Sound(id) => write!(f, "sound:{id}"),
+        }
+    }
+}
+
+impl FromStr for StationSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let number = |x: &str| {
+            x.parse::<i32>()
+                .with_context(|| format!("Invalid station: {s}"))
+        };
+
+        match parts.as_slice() {
+            ["genre", id] => Ok(StationSource::Genre(number(id)?)),
+            ["decade", decade] => Ok(StationSource::Decade(number(decade)?)),
+            ["genre", id, "decade", decade] => Ok(StationSource::GenreDecade {
+                genre_id: number(id)?,
+                decade: number(decade)?,
+            }),
+            ["sound", id] => Ok(StationSource::Sound(number(id)?)),
+            _ => bail!("Unknown station: {s}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Station {
+    pub source: StationSource,
+    pub name: String,
+    /// How many tracks the station picks from.
+    pub track_count: usize,
+}
+
+/// Name a decade like people do, `80s` for 1980 and `2010s` for 2010.
+pub fn decade_name(decade: i32) -> String {
+    if (1900..2000).contains(&decade) {
+        format!("{}s", decade % 100)
+    } else {
+        format!("{decade}s")
+    }
+}
+
+/// The decade of every file with a plausible year in its `date` tag.
+async fn get_decades(main_db: &DatabaseConnection) -> Result<HashMap<i32, i32>> {
+    Ok(media_metadata::Entity::find()
+        .filter(media_metadata::Column::MetaKey.eq("date"))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .filter_map(|x| {
+            let year = extract_number(&x.meta_value).filter(|x| (1000..3000).contains(x))?;
+            Some((x.file_id, year - year.rem_euclid(10)))
+        })
+        .collect())
+}
+
+/// List the stations the library can fill, the largest first.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `limit` - How many stations to return at most.
+pub async fn list_stations(main_db: &DatabaseConnection, limit: usize) -> Result<Vec<Station>> {
+    let hidden: HashSet<i32> = get_hidden_files(main_db).await?.into_iter().collect();
+
+    let decades = get_decades(main_db).await?;
+    let genre_names: HashMap<i32, String> = genres::Entity::find()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x.name))
+        .collect();
+
+    let mut counts: BTreeMap<StationSource, usize> = BTreeMap::new();
+    for (file_id, decade) in &decades {
+        if !hidden.contains(file_id) {
+            *counts.entry(StationSource::Decade(*decade)).or_default() += 1;
+        }
+    }
+    for x in media_file_genres::Entity::find().all(main_db).await? {
+        if hidden.contains(&x.media_file_id) {
+            continue;
+        }
+
+        *counts.entry(StationSource::Genre(x.genre_id)).or_default() += 1;
+        if let Some(decade) = decades.get(&x.media_file_id) {
+            *counts
+                .entry(StationSource::GenreDecade {
+                    genre_id: x.genre_id,
+                    decade: *decade,
+                })
+                .or_default() += 1;
+        }
+    }
+
+    let mut stations: Vec<Station> = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= STATION_MIN_TRACKS)
+        .filter_map(|(source, track_count)| {
+            let name = match source {
+                StationSource::Genre(id) => genre_names.get(&id)?.clone(),
+                StationSource::Decade(decade) => decade_name(decade),
+                StationSource::GenreDecade { genre_id, decade } => {
+                    format!("{} {}", decade_name(decade), genre_names.get(&genre_id)?)
+                }
+                StationSource::Sound(_) => return None,
+            };
+
+            Some(Station {
+                source,
+                name,
+                track_count,
+            })
+        })
+        .collect();
+
+    // Groups of the map are named after their most common genre
+    let map = get_library_map(main_db, None).await?;
+    for cluster in map.clusters {
+        if cluster.size < STATION_MIN_TRACKS {
+            continue;
+        }
+
+        let distance = |x: &LibraryMapPoint| (x.x - cluster.x).powi(2) + (x.y - cluster.y).powi(2);
+        let centre = map
+            .points
+            .iter()
+            .filter(|x| x.cluster == cluster.id && !hidden.contains(&x.file_id))
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)));
+        let Some(centre) = centre else {
+            continue;
+        };
+
+        stations.push(Station {
+            source: StationSource::Sound(centre.file_id),
+            name: match cluster.genre {
+                Some(genre) => format!("{genre} Sounds"),
+                None => format!("Sound {}", cluster.id + 1),
+            },
+            track_count: cluster.size,
+        });
+    }
+
+    stations.sort_by(|a, b| {
+        b.track_count
+            .cmp(&a.track_count)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    stations.truncate(limit);
+
+    Ok(stations)
+}
+
+/// The tracks a station picks from, without the hidden ones.
+pub async fn get_station_pool(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+    source: StationSource,
+) -> Result<Vec<i32>> {
+    let genre_files = |genre_id: i32| async move {
+        media_file_genres::Entity::find()
+            .filter(media_file_genres::Column::GenreId.eq(genre_id))
+            .all(main_db)
+            .await
+            .map(|x| x.into_iter().map(|x| x.media_file_id).collect::<Vec<_>>())
+    };
+
+    let mut pool: Vec<i32> = match source {
+        StationSource::Genre(genre_id) => genre_files(genre_id).await?,
+        StationSource::Decade(decade) => get_decades(main_db)
+            .await?
+            .into_iter()
+            .filter(|(_, x)| *x == decade)
+            .map(|(file_id, _)| file_id)
+            .collect(),
+        StationSource::GenreDecade { genre_id, decade } => {
+            let decades = get_decades(main_db).await?;
+            genre_files(genre_id)
+                .await?
+                .into_iter()
+                .filter(|x| decades.get(x) == Some(&decade))
+                .collect()
+        }
+        StationSource::Sound(file_id) => get_radio_tracks(
+            main_db,
+            recommend_db,
+            RadioSeed::Track(file_id),
+            SOUND_STATION_POOL,
+        )
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect(),
+    };
+
+    let hidden: HashSet<i32> = get_hidden_files(main_db).await?.into_iter().collect();
+    pool.retain(|x| !hidden.contains(x));
+    pool.sort();
+    pool.dedup();
+
+    Ok(pool)
+}
+
+/// What the listener skipped and played through during a station session,
+/// by artist.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StationFeedback {
+    skipped: HashMap<i32, u32>,
+    played: HashMap<i32, u32>,
+}
+
+impl StationFeedback {
+    /// How likely a track of the artists is picked, `1` without feedback.
+    pub fn weight(&self, artist_ids: &[i32]) -> f64 {
+        artist_ids
+            .iter()
+            .map(|id| {
+                let skipped = self.skipped.get(id).copied().unwrap_or(0);
+                let played = self.played.get(id).copied().unwrap_or(0);
+                SKIP_FACTOR.powi(skipped as i32) * PLAY_FACTOR.powi(played as i32)
+            })
+            .product::<f64>()
+            .clamp(MIN_WEIGHT, MAX_WEIGHT)
+    }
+
+    pub fn record(&mut self, artist_ids: &[i32], skipped: bool) {
+        let counts = if skipped {
+            &mut self.skipped
+        } else {
+            &mut self.played
+        };
+        for id in artist_ids {
+            *counts.entry(*id).or_default() += 1;
+        }
+    }
+}
+
+async fn get_artists_of(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, Vec<i32>>> {
+    let mut artists: HashMap<i32, Vec<i32>> = HashMap::new();
+    for x in media_file_artists::Entity::find()
+        .filter(media_file_artists::Column::MediaFileId.is_in(file_ids.to_vec()))
+        .all(main_db)
+        .await?
+    {
+        artists
+            .entry(x.media_file_id)
+            .or_default()
+            .push(x.artist_id);
+    }
+
+    Ok(artists)
+}
+
+/// Remember how a track of a station ended: skipped if it stopped before
+/// [`STATION_PLAYED_THROUGH_RATIO`] of it played, played through otherwise.
+///
+/// # Arguments
+/// * `played` - How far the track played, in seconds.
+pub async fn record_station_playback(
+    main_db: &DatabaseConnection,
+    feedback: &mut StationFeedback,
+    file_id: i32,
+    played: f64,
+) -> Result<()> {
+    let Some(file) = get_file_by_id(main_db, file_id).await? else {
+        return Ok(());
+    };
+    let duration = file.duration.to_f64().unwrap_or_default();
+    let skipped = played < duration * STATION_PLAYED_THROUGH_RATIO;
+
+    let artists = get_artists_of(main_db, &[file_id]).await?;
+    feedback.record(
+        artists.get(&file_id).map(Vec::as_slice).unwrap_or_default(),
+        skipped,
+    );
+
+    Ok(())
+}
+
+/// Pick the next tracks of a station, at most `n` of them, leaving out the
+/// `avoided` ones like the recently queued tracks and those the listener
+/// excluded. Tracks are picked at random, weighted by the feedback of the
+/// session.
+pub async fn get_more_station_tracks(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+    source: StationSource,
+    feedback: &StationFeedback,
+    avoided: &HashSet<i32>,
+    n: usize,
+    rng: &mut StdRng,
+) -> Result<Vec<media_files::Model>> {
+    let mut pool = get_station_pool(main_db, recommend_db, source)
+        .await
+        .with_context(|| format!("Failed to get the tracks of station: {source}"))?;
+    pool.retain(|x| !avoided.contains(x));
+
+    let artists = get_artists_of(main_db, &pool).await?;
+
+    // Weighted sampling without replacement, every track gets a key of
+    // u^(1/weight) and the largest keys win
+    let mut keys: Vec<(f64, i32)> = pool
+        .into_iter()
+        .map(|file_id| {
+            let weight =
+                feedback.weight(artists.get(&file_id).map(Vec::as_slice).unwrap_or_default());
+            (rng.gen_range(f64::EPSILON..1.0).powf(1.0 / weight), file_id)
+        })
+        .collect();
+    keys.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keys.truncate(n);
+
+    let picked: Vec<i32> = keys.into_iter().map(|(_, x)| x).collect();
+    let mut files: HashMap<i32, media_files::Model> = media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(picked.clone()))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+
+    Ok(picked
+        .into_iter()
+        .filter_map(|x| files.remove(&x))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use anyhow::Result;
+    use rand::{SeedableRng, rngs::StdRng};
+    use sea_orm::{ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+    use tempfile::tempdir;
+
+    use crate::{
+        actions::stations::{
+            StationFeedback, StationSource, get_more_station_tracks, get_station_pool,
+            list_stations, record_station_playback,
+        },
+        connection::connect_recommendation_db,
+        entities::{artists, media_metadata},
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    /// Tag the first 20 files as released in 1985, the others in 2012.
+    async fn seed_dates(db: &DatabaseConnection, file_ids: &[i32]) -> Result<()> {
+        media_metadata::Entity::insert_many(file_ids.iter().enumerate().map(|(i, id)| {
+            media_metadata::ActiveModel {
+                file_id: ActiveValue::Set(*id),
+                meta_key: ActiveValue::Set("date".to_string()),
+                meta_value: ActiveValue::Set(
+                    if i < 20 { "1985-05-01" } else { "2012" }.to_string(),
+                ),
+                ..Default::default()
+            }
+        }))
+        .exec(db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn artist_id(db: &DatabaseConnection, name: &str) -> Result<i32> {
+        Ok(artists::Entity::find()
+            .filter(artists::Column::Name.eq(name))
+            .one(db)
+            .await?
+            .unwrap()
+            .id)
+    }
+
+    #[tokio::test]
+    async fn test_list_stations() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+
+        let file_ids = seed_fake_tracks(&db, 40).await?;
+        seed_dates(&db, &file_ids).await?;
+
+        // Every genre has 10 tracks, 5 per decade, too few for a genre of a
+        // decade
+        let stations = list_stations(&db, 50).await?;
+        let names: Vec<&str> = stations.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["2010s", "80s", "Ambient", "Electronic", "Jazz", "Rock"]
+        );
+        assert_eq!(stations[1].source, StationSource::Decade(1980));
+        assert_eq!(stations[1].track_count, 20);
+
+        for station in &stations {
+            let source: StationSource = station.source.to_string().parse()?;
+            assert_eq!(source, station.source);
+        }
+        assert_eq!(list_stations(&db, 2).await?.len(), 2);
+
+        let pool = get_station_pool(&db, &recommend_db, StationSource::Decade(1980)).await?;
+        assert_eq!(pool, file_ids[..20].to_vec());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stations_lean_away_from_skipped_artists() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let lib_dir = tempdir()?;
+        let recommend_db = connect_recommendation_db(lib_dir.path().to_str().unwrap(), None)?;
+
+        // The 80s hold 10 tracks of Aurora, then 10 of Boreal
+        let file_ids = seed_fake_tracks(&db, 40).await?;
+        seed_dates(&db, &file_ids).await?;
+        let aurora = artist_id(&db, "Aurora").await?;
+        let boreal = artist_id(&db, "Boreal").await?;
+
+        let mut feedback = StationFeedback::default();
+        for file_id in &file_ids[..5] {
+            record_station_playback(&db, &mut feedback, *file_id, 1.0).await?;
+        }
+        record_station_playback(&db, &mut feedback, file_ids[10], 1000.0).await?;
+        assert!(feedback.weight(&[aurora]) < 0.1);
+        assert!(feedback.weight(&[boreal]) > 1.0);
+
+        let avoided: HashSet<i32> = HashSet::from([file_ids[11]]);
+        let mut rng = StdRng::seed_from_u64(7);
+        let tracks = get_more_station_tracks(
+            &db,
+            &recommend_db,
+            StationSource::Decade(1980),
+            &feedback,
+            &avoided,
+            5,
+            &mut rng,
+        )
+        .await?;
+
+        assert_eq!(tracks.len(), 5);
+        assert!(tracks.iter().all(|x| x.id != file_ids[11]));
+        let from_boreal = tracks
+            .iter()
+            .filter(|x| file_ids[10..20].contains(&x.id))
+            .count();
+        assert!(from_boreal >= 4, "only {from_boreal} tracks of Boreal");
+
+        Ok(())
+    }
+}
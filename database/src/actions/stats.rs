@@ -1,10 +1,16 @@
-use anyhow::Result;
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
 use chrono::Utc;
 use sea_orm::prelude::*;
-use sea_orm::ActiveValue;
+use sea_orm::sea_query::{Expr, Query, SelectStatement};
+use sea_orm::{ActiveValue, FromQueryResult, QueryOrder, QuerySelect, Select};
 
 use crate::entities::media_file_stats;
 use crate::entities::media_files;
+use crate::entities::play_history;
+
+use super::utils::DatabaseExecutor;
 
 /// Set the liked status of a media file.
 ///
@@ -83,6 +89,497 @@ pub async fn get_liked(main_db: &DatabaseConnection, media_file_id: i32) -> Resu
     }
 }
 
+/// Flip the liked status of a media file.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file to update.
+///
+/// # Returns
+/// * `Result<Option<bool>>` - The new liked status, `None` if the media file
+///   does not exist.
+pub async fn toggle_like(main_db: &DatabaseConnection, media_file_id: i32) -> Result<Option<bool>> {
+    let liked = !get_liked(main_db, media_file_id).await?;
+    let stats = set_liked(main_db, media_file_id, liked).await?;
+
+    Ok(stats.map(|x| x.liked))
+}
+
+/// Get the IDs of all liked media files.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+///
+/// # Returns
+/// * `Result<Vec<i32>>` - The liked media file IDs, most recently changed first.
+pub async fn get_liked_files(main_db: &DatabaseConnection) -> Result<Vec<i32>> {
+    let file_ids = media_file_stats::Entity::find()
+        .select_only()
+        .column(media_file_stats::Column::MediaFileId)
+        .filter(media_file_stats::Column::Liked.eq(true))
+        .order_by_desc(media_file_stats::Column::UpdatedAt)
+        .into_tuple::<i32>()
+        .all(main_db)
+        .await?;
+
+    Ok(file_ids)
+}
+
+/// The highest rating a media file can have.
+pub const MAX_RATING: i32 = 5;
+
+/// Set the rating of a media file.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file to update.
+/// * `rating` - The new rating from 0 to [`MAX_RATING`], `None` to clear it.
+///
+/// # Returns
+/// * `Result<Model>` - The updated media file stats model or an error.
+pub async fn set_rating(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+    rating: Option<i32>,
+) -> Result<Option<media_file_stats::Model>> {
+    if let Some(rating) = rating
+        && !(0..=MAX_RATING).contains(&rating)
+    {
+        bail!("Rating must be between 0 and {MAX_RATING}, got: {rating}");
+    }
+
+    let media_file = media_files::Entity::find_by_id(media_file_id)
+        .one(main_db)
+        .await?;
+
+    if media_file.is_none() {
+        return Ok(None);
+    }
+
+    let stats = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
+        .one(main_db)
+        .await?;
+
+    let updated_stats = if let Some(stats) = stats {
+        let mut active_model: media_file_stats::ActiveModel = stats.into();
+
+        active_model.rating = ActiveValue::Set(rating);
+        active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
+
+        active_model.update(main_db).await?
+    } else {
+        let new_stats = media_file_stats::ActiveModel {
+            media_file_id: ActiveValue::Set(media_file_id),
+            liked: ActiveValue::Set(false),
+            skipped: ActiveValue::Set(0),
+            played_through: ActiveValue::Set(0),
+            rating: ActiveValue::Set(rating),
+            updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+            ..Default::default()
+        };
+
+        new_stats.insert(main_db).await?
+    };
+
+    Ok(Some(updated_stats))
+}
+
+/// Get the rating of a media file.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file.
+///
+/// # Returns
+/// * `Result<Option<i32>>` - The rating, `None` if the file is not rated.
+pub async fn get_rating(main_db: &DatabaseConnection, media_file_id: i32) -> Result<Option<i32>> {
+    let stats = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
+        .one(main_db)
+        .await?;
+
+    Ok(stats.and_then(|x| x.rating))
+}
+
+/// Take over the rating tag of a file, read from `POPM` frames or `RATING`
+/// comments, unless the file was already rated in the library.
+///
+/// # Arguments
+/// * `db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file.
+/// * `metadata` - The tags read from the file.
+pub async fn import_tag_rating<E>(
+    db: &E,
+    media_file_id: i32,
+    metadata: &[(String, String)],
+) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    let Some(rating) = metadata
+        .iter()
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("rating"))
+        .and_then(|(_, value)| value.trim().parse::<i32>().ok())
+        .filter(|x| (1..=MAX_RATING).contains(x))
+    else {
+        return Ok(());
+    };
+
+    let stats = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
+        .one(db)
+        .await?;
+
+    match stats {
+        Some(stats) if stats.rating.is_some() => {}
+        Some(stats) => {
+            let mut active_model: media_file_stats::ActiveModel = stats.into();
+            active_model.rating = ActiveValue::Set(Some(rating));
+            active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
+            active_model.update(db).await?;
+        }
+        None => {
+            media_file_stats::ActiveModel {
+                media_file_id: ActiveValue::Set(media_file_id),
+                liked: ActiveValue::Set(false),
+                skipped: ActiveValue::Set(0),
+                played_through: ActiveValue::Set(0),
+                rating: ActiveValue::Set(Some(rating)),
+                updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Set whether a media file is hidden from the library. Hidden files stay
+/// indexed but are left out of browsing, shuffle, mixes and search.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file to update.
+/// * `hidden` - The new hidden status.
+///
+/// # Returns
+/// * `Result<Option<Model>>` - The updated media file stats model, `None` if
+///   the file doesn't exist.
+pub async fn set_hidden(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+    hidden: bool,
+) -> Result<Option<media_file_stats::Model>> {
+    let media_file = media_files::Entity::find_by_id(media_file_id)
+        .one(main_db)
+        .await?;
+
+    if media_file.is_none() {
+        return Ok(None);
+    }
+
+    let stats = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
+        .one(main_db)
+        .await?;
+
+    let updated_stats = if let Some(stats) = stats {
+        let mut active_model: media_file_stats::ActiveModel = stats.into();
+
+        active_model.hidden = ActiveValue::Set(hidden);
+        active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
+
+        active_model.update(main_db).await?
+    } else {
+        let new_stats = media_file_stats::ActiveModel {
+            media_file_id: ActiveValue::Set(media_file_id),
+            liked: ActiveValue::Set(false),
+            skipped: ActiveValue::Set(0),
+            played_through: ActiveValue::Set(0),
+            hidden: ActiveValue::Set(hidden),
+            updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+            ..Default::default()
+        };
+
+        new_stats.insert(main_db).await?
+    };
+
+    Ok(Some(updated_stats))
+}
+
+/// Get the IDs of all hidden media files.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+///
+/// # Returns
+/// * `Result<Vec<i32>>` - The hidden media file IDs, most recently changed first.
+pub async fn get_hidden_files(main_db: &DatabaseConnection) -> Result<Vec<i32>> {
+    let file_ids = media_file_stats::Entity::find()
+        .select_only()
+        .column(media_file_stats::Column::MediaFileId)
+        .filter(media_file_stats::Column::Hidden.eq(true))
+        .order_by_desc(media_file_stats::Column::UpdatedAt)
+        .into_tuple::<i32>()
+        .all(main_db)
+        .await?;
+
+    Ok(file_ids)
+}
+
+/// A subquery selecting the IDs of all hidden media files, to leave them
+/// out of other queries with `not_in_subquery`.
+pub fn hidden_files_subquery() -> SelectStatement {
+    Query::select()
+        .column(media_file_stats::Column::MediaFileId)
+        .from(media_file_stats::Entity)
+        .and_where(media_file_stats::Column::Hidden.eq(true))
+        .to_owned()
+}
+
+/// A media file whose last playback attempt failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProblemTrack {
+    pub media_file_id: i32,
+    pub error: String,
+    /// When the playback failed, in RFC 3339.
+    pub failed_at: String,
+}
+
+/// Record why a media file failed to play. The error stays until the file
+/// is played through or the error is cleared.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file that failed to play.
+/// * `error` - What went wrong.
+///
+/// # Returns
+/// * `Result<Option<Model>>` - The updated media file stats model, `None` if
+///   the file doesn't exist.
+pub async fn record_playback_error(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+    error: &str,
+) -> Result<Option<media_file_stats::Model>> {
+    let media_file = media_files::Entity::find_by_id(media_file_id)
+        .one(main_db)
+        .await?;
+
+    if media_file.is_none() {
+        return Ok(None);
+    }
+
+    let stats = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
+        .one(main_db)
+        .await?;
+
+    let now = Utc::now().to_rfc3339();
+    let updated_stats = if let Some(stats) = stats {
+        let mut active_model: media_file_stats::ActiveModel = stats.into();
+
+        active_model.last_error = ActiveValue::Set(Some(error.to_string()));
+        active_model.last_error_at = ActiveValue::Set(Some(now.clone()));
+        active_model.updated_at = ActiveValue::Set(now);
+
+        active_model.update(main_db).await?
+    } else {
+        let new_stats = media_file_stats::ActiveModel {
+            media_file_id: ActiveValue::Set(media_file_id),
+            liked: ActiveValue::Set(false),
+            skipped: ActiveValue::Set(0),
+            played_through: ActiveValue::Set(0),
+            last_error: ActiveValue::Set(Some(error.to_string())),
+            last_error_at: ActiveValue::Set(Some(now.clone())),
+            updated_at: ActiveValue::Set(now),
+            ..Default::default()
+        };
+
+        new_stats.insert(main_db).await?
+    };
+
+    Ok(Some(updated_stats))
+}
+
+/// Forget the playback error of a media file, after it was fixed or
+/// replaced.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file.
+///
+/// # Returns
+/// * `Result<bool>` - Whether the file had an error to clear.
+pub async fn clear_playback_error(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+) -> Result<bool> {
+    let result = media_file_stats::Entity::update_many()
+        .col_expr(
+            media_file_stats::Column::LastError,
+            Expr::value(Option::<String>::None),
+        )
+        .col_expr(
+            media_file_stats::Column::LastErrorAt,
+            Expr::value(Option::<String>::None),
+        )
+        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
+        .filter(media_file_stats::Column::LastError.is_not_null())
+        .exec(main_db)
+        .await?;
+
+    Ok(result.rows_affected > 0)
+}
+
+/// Get the media files whose last playback failed.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+///
+/// # Returns
+/// * `Result<Vec<ProblemTrack>>` - The failed media files, most recent failure first.
+pub async fn get_problem_tracks(main_db: &DatabaseConnection) -> Result<Vec<ProblemTrack>> {
+    let tracks = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::LastError.is_not_null())
+        .order_by_desc(media_file_stats::Column::LastErrorAt)
+        .all(main_db)
+        .await?
+        .into_iter()
+        .filter_map(|x| {
+            Some(ProblemTrack {
+                media_file_id: x.media_file_id,
+                error: x.last_error?,
+                failed_at: x.last_error_at.unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    Ok(tracks)
+}
+
+/// Custom start and stop points of a media file, in milliseconds from the
+/// start of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TrackOffsets {
+    pub start_ms: Option<i32>,
+    pub end_ms: Option<i32>,
+}
+
+impl TrackOffsets {
+    pub fn is_empty(&self) -> bool {
+        self.start_ms.is_none() && self.end_ms.is_none()
+    }
+
+    /// The duration left of a track of `duration` seconds once trimmed.
+    pub fn trimmed_duration(&self, duration: f64) -> f64 {
+        let start = self.start_ms.unwrap_or(0) as f64 / 1000.0;
+        let end = self
+            .end_ms
+            .map(|x| x as f64 / 1000.0)
+            .unwrap_or(duration)
+            .min(duration);
+
+        (end - start).max(0.0)
+    }
+}
+
+/// Set the custom start and stop points of a media file.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file to update.
+/// * `offsets` - The new offsets, empty offsets play the whole file.
+///
+/// # Returns
+/// * `Result<Option<Model>>` - The updated media file stats model, `None` if
+///   the file doesn't exist.
+pub async fn set_track_offsets(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+    offsets: TrackOffsets,
+) -> Result<Option<media_file_stats::Model>> {
+    if offsets.start_ms.is_some_and(|x| x < 0) || offsets.end_ms.is_some_and(|x| x <= 0) {
+        bail!("Offsets must be positive, got: {offsets:?}");
+    }
+    if let (Some(start), Some(end)) = (offsets.start_ms, offsets.end_ms)
+        && start >= end
+    {
+        bail!("The start offset must come before the end offset, got: {offsets:?}");
+    }
+
+    let media_file = media_files::Entity::find_by_id(media_file_id)
+        .one(main_db)
+        .await?;
+
+    if media_file.is_none() {
+        return Ok(None);
+    }
+
+    let stats = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
+        .one(main_db)
+        .await?;
+
+    let updated_stats = if let Some(stats) = stats {
+        let mut active_model: media_file_stats::ActiveModel = stats.into();
+
+        active_model.start_offset_ms = ActiveValue::Set(offsets.start_ms);
+        active_model.end_offset_ms = ActiveValue::Set(offsets.end_ms);
+        active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
+
+        active_model.update(main_db).await?
+    } else {
+        let new_stats = media_file_stats::ActiveModel {
+            media_file_id: ActiveValue::Set(media_file_id),
+            liked: ActiveValue::Set(false),
+            skipped: ActiveValue::Set(0),
+            played_through: ActiveValue::Set(0),
+            start_offset_ms: ActiveValue::Set(offsets.start_ms),
+            end_offset_ms: ActiveValue::Set(offsets.end_ms),
+            updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+            ..Default::default()
+        };
+
+        new_stats.insert(main_db).await?
+    };
+
+    Ok(Some(updated_stats))
+}
+
+/// Get the custom start and stop points of media files.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_ids` - The IDs of the media files.
+///
+/// # Returns
+/// * `Result<HashMap<i32, TrackOffsets>>` - The offsets by file ID, files
+///   that play in full are left out.
+pub async fn get_track_offsets_by_file_ids(
+    main_db: &DatabaseConnection,
+    media_file_ids: &[i32],
+) -> Result<HashMap<i32, TrackOffsets>> {
+    let offsets = media_file_stats::Entity::find()
+        .select_only()
+        .column(media_file_stats::Column::MediaFileId)
+        .column(media_file_stats::Column::StartOffsetMs)
+        .column(media_file_stats::Column::EndOffsetMs)
+        .filter(media_file_stats::Column::MediaFileId.is_in(media_file_ids.iter().copied()))
+        .into_tuple::<(i32, Option<i32>, Option<i32>)>()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|(file_id, start_ms, end_ms)| (file_id, TrackOffsets { start_ms, end_ms }))
+        .filter(|(_, x)| !x.is_empty())
+        .collect();
+
+    Ok(offsets)
+}
+
 /// Increase the skipped count of a media file.
 ///
 /// # Arguments
@@ -152,8 +649,10 @@ pub async fn increase_played_through(
     let updated_stats = if let Some(stats) = stats {
         let mut active_model: media_file_stats::ActiveModel = stats.clone().into();
 
-        // Increase the played through count
+        // Increase the played through count, the file is evidently fine now
         active_model.played_through = ActiveValue::Set(stats.played_through + 1);
+        active_model.last_error = ActiveValue::Set(None);
+        active_model.last_error_at = ActiveValue::Set(None);
         active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
 
         // Update the media file stats in the database
@@ -174,3 +673,471 @@ pub async fn increase_played_through(
 
     Ok(updated_stats)
 }
+
+/// Record that a media file started playing.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file that started playing.
+///
+/// # Returns
+/// * `Result<Model>` - The new play history entry or an error.
+pub async fn record_playback_start(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+) -> Result<play_history::Model> {
+    let entry = play_history::ActiveModel {
+        media_file_id: ActiveValue::Set(media_file_id),
+        started_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+        ended_at: ActiveValue::Set(None),
+        completed: ActiveValue::Set(false),
+        skipped: ActiveValue::Set(false),
+        ..Default::default()
+    };
+
+    Ok(entry.insert(main_db).await?)
+}
+
+/// Close the latest open play history entry of a media file. If the start
+/// of the playback was never recorded, a zero length entry is created.
+async fn record_playback_end(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+    completed: bool,
+) -> Result<play_history::Model> {
+    let now = Utc::now().to_rfc3339();
+
+    let entry = play_history::Entity::find()
+        .filter(play_history::Column::MediaFileId.eq(media_file_id))
+        .filter(play_history::Column::EndedAt.is_null())
+        .order_by_desc(play_history::Column::Id)
+        .one(main_db)
+        .await?;
+
+    let entry = match entry {
+        Some(entry) => {
+            let mut active_model: play_history::ActiveModel = entry.into();
+            active_model.ended_at = ActiveValue::Set(Some(now));
+            active_model.completed = ActiveValue::Set(completed);
+            active_model.skipped = ActiveValue::Set(!completed);
+
+            active_model.update(main_db).await?
+        }
+        None => {
+            let new_entry = play_history::ActiveModel {
+                media_file_id: ActiveValue::Set(media_file_id),
+                started_at: ActiveValue::Set(now.clone()),
+                ended_at: ActiveValue::Set(Some(now)),
+                completed: ActiveValue::Set(completed),
+                skipped: ActiveValue::Set(!completed),
+                ..Default::default()
+            };
+
+            new_entry.insert(main_db).await?
+        }
+    };
+
+    Ok(entry)
+}
+
+/// Record that a media file was played through, and increase its played
+/// through count.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file that finished playing.
+///
+/// # Returns
+/// * `Result<Model>` - The updated media file stats model or an error.
+pub async fn record_playback_complete(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+) -> Result<media_file_stats::Model> {
+    record_playback_end(main_db, media_file_id, true).await?;
+    increase_played_through(main_db, media_file_id).await
+}
+
+/// Record that a media file was skipped before it ended, and increase its
+/// skipped count.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file that was skipped.
+///
+/// # Returns
+/// * `Result<Model>` - The updated media file stats model or an error.
+pub async fn record_playback_skip(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+) -> Result<media_file_stats::Model> {
+    record_playback_end(main_db, media_file_id, false).await?;
+    increase_skipped(main_db, media_file_id).await
+}
+
+/// Play history of a single media file.
+#[derive(Debug, Clone, PartialEq, FromQueryResult)]
+pub struct PlayCount {
+    pub media_file_id: i32,
+    /// How many times the file was played through.
+    pub play_count: i64,
+    /// When the file last started playing, in RFC 3339.
+    pub last_played_at: String,
+}
+
+fn play_count_query() -> Select<play_history::Entity> {
+    play_history::Entity::find()
+        .select_only()
+        .column(play_history::Column::MediaFileId)
+        .column_as(
+            Expr::col(play_history::Column::Completed).sum(),
+            "play_count",
+        )
+        .column_as(play_history::Column::StartedAt.max(), "last_played_at")
+        .group_by(play_history::Column::MediaFileId)
+}
+
+/// Get the media files that were played through the most.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `limit` - The maximum number of media files to return.
+///
+/// # Returns
+/// * `Result<Vec<PlayCount>>` - The play counts, most played first.
+pub async fn get_most_played(main_db: &DatabaseConnection, limit: u64) -> Result<Vec<PlayCount>> {
+    let result = play_count_query()
+        .having(Expr::expr(Expr::col(play_history::Column::Completed).sum()).gt(0))
+        .order_by_desc(Expr::cust("play_count"))
+        .order_by_desc(Expr::cust("last_played_at"))
+        .limit(limit)
+        .into_model::<PlayCount>()
+        .all(main_db)
+        .await?;
+
+    Ok(result)
+}
+
+/// Get the media files that started playing most recently, whether they
+/// were played through or not.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `limit` - The maximum number of media files to return.
+///
+/// # Returns
+/// * `Result<Vec<PlayCount>>` - The play counts, most recent first.
+pub async fn get_recently_played(
+    main_db: &DatabaseConnection,
+    limit: u64,
+) -> Result<Vec<PlayCount>> {
+    let result = play_count_query()
+        .order_by_desc(Expr::cust("last_played_at"))
+        .limit(limit)
+        .into_model::<PlayCount>()
+        .all(main_db)
+        .await?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::EntityTrait;
+
+    use crate::{
+        actions::{
+            file::{RandomFileFilter, get_media_files, get_random_files},
+            metadata::get_metadata_summary_by_files,
+            playlists::{evaluate_smart_playlist_rule, parse_smart_playlist_rules},
+            stats::{
+                TrackOffsets, clear_playback_error, get_hidden_files, get_liked, get_liked_files,
+                get_most_played, get_problem_tracks, get_rating, get_recently_played,
+                get_track_offsets_by_file_ids, import_tag_rating, record_playback_complete,
+                record_playback_error, record_playback_skip, record_playback_start, set_hidden,
+                set_liked, set_rating, set_track_offsets, toggle_like,
+            },
+        },
+        entities::{media_files, play_history},
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_set_hidden() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        assert!(get_hidden_files(&db).await?.is_empty());
+
+        let stats = set_hidden(&db, file_ids[0], true).await?.unwrap();
+        assert!(stats.hidden);
+
+        // Hiding a track keeps its liked status
+        set_liked(&db, file_ids[1], true).await?;
+        set_hidden(&db, file_ids[1], true).await?;
+        assert!(get_liked(&db, file_ids[1]).await?);
+
+        set_hidden(&db, file_ids[0], false).await?;
+        assert_eq!(get_hidden_files(&db).await?, vec![file_ids[1]]);
+        assert!(set_hidden(&db, -1, true).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hidden_files_are_not_listed() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 4).await?;
+
+        set_hidden(&db, file_ids[2], true).await?;
+
+        let listed: Vec<i32> = get_media_files(&db, 0, 10)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        assert_eq!(listed, vec![file_ids[0], file_ids[1], file_ids[3]]);
+
+        let random = get_random_files(&db, 10, &RandomFileFilter::default()).await?;
+        assert_eq!(random.len(), 3);
+        assert!(random.iter().all(|x| x.id != file_ids[2]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_record_playback() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        let entry = record_playback_start(&db, file_ids[0]).await?;
+        assert!(entry.ended_at.is_none());
+
+        let stats = record_playback_complete(&db, file_ids[0]).await?;
+        assert_eq!(stats.played_through, 1);
+
+        let entry = play_history::Entity::find_by_id(entry.id)
+            .one(&db)
+            .await?
+            .unwrap();
+        assert!(entry.ended_at.is_some());
+        assert!(entry.completed);
+        assert!(!entry.skipped);
+
+        // A skip without a recorded start still ends up in the history
+        let stats = record_playback_skip(&db, file_ids[1]).await?;
+        assert_eq!(stats.skipped, 1);
+
+        let history = play_history::Entity::find().all(&db).await?;
+        assert_eq!(history.len(), 2);
+        assert!(
+            history
+                .iter()
+                .any(|x| x.media_file_id == file_ids[1] && x.skipped)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_play_counts() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        for _ in 0..3 {
+            record_playback_start(&db, file_ids[0]).await?;
+            record_playback_complete(&db, file_ids[0]).await?;
+        }
+
+        record_playback_start(&db, file_ids[1]).await?;
+        record_playback_complete(&db, file_ids[1]).await?;
+
+        record_playback_start(&db, file_ids[2]).await?;
+        record_playback_skip(&db, file_ids[2]).await?;
+
+        let most_played = get_most_played(&db, 10).await?;
+        assert_eq!(
+            most_played
+                .iter()
+                .map(|x| (x.media_file_id, x.play_count))
+                .collect::<Vec<_>>(),
+            vec![(file_ids[0], 3), (file_ids[1], 1)]
+        );
+
+        let recently_played = get_recently_played(&db, 2).await?;
+        assert_eq!(recently_played.len(), 2);
+        assert_eq!(recently_played[0].media_file_id, file_ids[2]);
+        assert_eq!(recently_played[0].play_count, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_record_playback_error() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        assert!(get_problem_tracks(&db).await?.is_empty());
+
+        let stats = record_playback_error(&db, file_ids[0], "Unsupported codec")
+            .await?
+            .unwrap();
+        assert_eq!(stats.last_error.as_deref(), Some("Unsupported codec"));
+
+        // Recording an error keeps the other stats
+        set_liked(&db, file_ids[1], true).await?;
+        record_playback_error(&db, file_ids[1], "Truncated file").await?;
+        assert!(get_liked(&db, file_ids[1]).await?);
+
+        let problems = get_problem_tracks(&db).await?;
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].media_file_id, file_ids[1]);
+        assert_eq!(problems[0].error, "Truncated file");
+        assert_eq!(problems[1].media_file_id, file_ids[0]);
+
+        assert!(record_playback_error(&db, -1, "Missing").await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_playback_error_is_cleared() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        record_playback_error(&db, file_ids[0], "Unsupported codec").await?;
+        record_playback_error(&db, file_ids[1], "Unsupported codec").await?;
+
+        // The file was replaced and plays through now
+        record_playback_start(&db, file_ids[0]).await?;
+        record_playback_complete(&db, file_ids[0]).await?;
+
+        assert!(clear_playback_error(&db, file_ids[1]).await?);
+        assert!(!clear_playback_error(&db, file_ids[1]).await?);
+
+        assert!(get_problem_tracks(&db).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_toggle_like() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        assert_eq!(toggle_like(&db, file_ids[0]).await?, Some(true));
+        assert_eq!(toggle_like(&db, file_ids[1]).await?, Some(true));
+        assert_eq!(toggle_like(&db, file_ids[1]).await?, Some(false));
+        assert_eq!(toggle_like(&db, -1).await?, None);
+
+        assert_eq!(get_liked_files(&db).await?, vec![file_ids[0]]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_tag_rating() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+        let tags = |rating: &str| vec![("rating".to_string(), rating.to_string())];
+
+        import_tag_rating(&db, file_ids[0], &tags("4")).await?;
+        assert_eq!(get_rating(&db, file_ids[0]).await?, Some(4));
+
+        // Ratings given in the library win over the tags
+        set_rating(&db, file_ids[1], Some(2)).await?;
+        import_tag_rating(&db, file_ids[1], &tags("5")).await?;
+        assert_eq!(get_rating(&db, file_ids[1]).await?, Some(2));
+
+        import_tag_rating(&db, file_ids[2], &tags("128")).await?;
+        assert_eq!(get_rating(&db, file_ids[2]).await?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_rating() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        assert_eq!(get_rating(&db, file_ids[0]).await?, None);
+
+        let stats = set_rating(&db, file_ids[0], Some(4)).await?.unwrap();
+        assert_eq!(stats.rating, Some(4));
+        assert!(!stats.liked);
+        assert_eq!(get_rating(&db, file_ids[0]).await?, Some(4));
+
+        // Rating a track keeps its liked status
+        set_liked(&db, file_ids[1], true).await?;
+        let stats = set_rating(&db, file_ids[1], Some(2)).await?.unwrap();
+        assert!(stats.liked);
+
+        set_rating(&db, file_ids[1], None).await?;
+        assert_eq!(get_rating(&db, file_ids[1]).await?, None);
+
+        assert!(set_rating(&db, file_ids[2], Some(6)).await.is_err());
+        assert!(set_rating(&db, file_ids[2], Some(-1)).await.is_err());
+        assert!(set_rating(&db, -1, Some(3)).await?.is_none());
+
+        let rule = parse_smart_playlist_rules("rating >= 3")?;
+        assert_eq!(
+            evaluate_smart_playlist_rule(&db, &rule).await?,
+            vec![file_ids[0]]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_track_offsets() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        // The first fake track is two minutes long
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        let offsets = TrackOffsets {
+            start_ms: Some(5_000),
+            end_ms: Some(100_000),
+        };
+        let stats = set_track_offsets(&db, file_ids[0], offsets).await?.unwrap();
+        assert_eq!(stats.start_offset_ms, Some(5_000));
+        assert_eq!(stats.end_offset_ms, Some(100_000));
+
+        let invalid = TrackOffsets {
+            start_ms: Some(10_000),
+            end_ms: Some(10_000),
+        };
+        assert!(set_track_offsets(&db, file_ids[1], invalid).await.is_err());
+        assert!(
+            set_track_offsets(&db, -1, TrackOffsets::default())
+                .await?
+                .is_none()
+        );
+
+        let map = get_track_offsets_by_file_ids(&db, &file_ids).await?;
+        assert_eq!(map.len(), 1);
+        assert_eq!(map[&file_ids[0]], offsets);
+
+        // Durations shown for the file are the trimmed ones
+        let files = media_files::Entity::find().all(&db).await?;
+        let summaries = get_metadata_summary_by_files(&db, files).await?;
+        let summary = summaries.iter().find(|x| x.id == file_ids[0]).unwrap();
+        assert_eq!(summary.duration, 95.0);
+
+        // End points past the end of the file are clamped
+        let past_end = TrackOffsets {
+            start_ms: None,
+            end_ms: Some(600_000),
+        };
+        assert_eq!(past_end.trimmed_duration(120.0), 120.0);
+
+        // Empty offsets play the whole file again
+        set_track_offsets(&db, file_ids[0], TrackOffsets::default()).await?;
+        assert!(
+            get_track_offsets_by_file_ids(&db, &file_ids)
+                .await?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+}
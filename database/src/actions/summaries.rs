@@ -0,0 +1,264 @@
+//! Denormalized summaries of the metadata shown in track lists.
+//!
+//! Every write to `media_metadata` is followed by a refresh of the summaries
+//! of the files it touched, so lists read one row per track instead of
+//! pivoting the key/value rows. Files without a summary yet, like those
+//! indexed before the table existed, are pivoted on read and filled in by
+//! [`sync_media_summaries`].
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sea_orm::{
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect,
+    sea_query::Query,
+};
+
+use crate::entities::{media_files, media_metadata, media_summaries};
+
+use super::metadata::extract_number;
+use super::utils::DatabaseExecutor;
+
+/// The metadata keys a summary is built from.
+pub const SUMMARY_KEYS: [&str; 6] = [
+    "artist",
+    "album",
+    "genre",
+    "track_title",
+    "disc_number",
+    "track_number",
+];
+
+/// How many files are summarized per query.
+const SUMMARY_BATCH_SIZE: usize = 500;
+
+/// Build the summary of a file from its metadata.
+pub fn summarize_metadata(
+    file_id: i32,
+    metadata: &HashMap<String, String>,
+) -> media_summaries::Model {
+    let disc_number = metadata
+        .get("disc_number")
+        .and_then(|x| extract_number(x))
+        .unwrap_or(0);
+    let track_number = metadata
+        .get("track_number")
+        .and_then(|x| extract_number(x))
+        .unwrap_or(0);
+
+    media_summaries::Model {
+        media_file_id: file_id,
+        artist: metadata.get("artist").cloned().unwrap_or_default(),
+        album: metadata.get("album").cloned().unwrap_or_default(),
+        genre: metadata.get("genre").cloned().unwrap_or_default(),
+        title: metadata.get("track_title").cloned(),
+        track_number: disc_number * 1000 + track_number,
+    }
+}
+
+/// Pivot the summarized metadata of the files, without reading or writing
+/// their stored summaries.
+pub async fn pivot_media_summaries<E>(
+    db: &E,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, media_summaries::Model>>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    let mut metadata_map: HashMap<i32, HashMap<String, String>> =
+        file_ids.iter().map(|id| (*id, HashMap::new())).collect();
+
+    for chunk in file_ids.chunks(SUMMARY_BATCH_SIZE) {
+        let entries = media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.is_in(chunk.to_vec()))
+            .filter(media_metadata::Column::MetaKey.is_in(SUMMARY_KEYS))
+            .all(db)
+            .await?;
+
+        for entry in entries {
+            metadata_map
+                .entry(entry.file_id)
+                .or_default()
+                .insert(entry.meta_key, entry.meta_value);
+        }
+    }
+
+    Ok(metadata_map
+        .into_iter()
+        .map(|(id, metadata)| (id, summarize_metadata(id, &metadata)))
+        .collect())
+}
+
+/// Build the summaries of the files again from their metadata. Called after
+/// every write to the metadata of the files.
+pub async fn refresh_media_summaries<E>(db: &E, file_ids: &[i32]) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+
+    let summaries = pivot_media_summaries(db, file_ids).await?;
+
+    for chunk in file_ids.chunks(SUMMARY_BATCH_SIZE) {
+        media_summaries::Entity::delete_many()
+            .filter(media_summaries::Column::MediaFileId.is_in(chunk.to_vec()))
+            .exec(db)
+            .await?;
+
+        let models: Vec<media_summaries::ActiveModel> = chunk
+            .iter()
+            .filter_map(|id| summaries.get(id))
+            .map(|x| media_summaries::ActiveModel {
+                media_file_id: ActiveValue::Set(x.media_file_id),
+                artist: ActiveValue::Set(x.artist.clone()),
+                album: ActiveValue::Set(x.album.clone()),
+                genre: ActiveValue::Set(x.genre.clone()),
+                title: ActiveValue::Set(x.title.clone()),
+                track_number: ActiveValue::Set(x.track_number),
+            })
+            .collect();
+
+        if !models.is_empty() {
+            media_summaries::Entity::insert_many(models)
+                .exec(db)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the summaries of the files, pivoting the metadata of those without
+/// a stored summary.
+pub async fn get_media_summaries(
+    db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, media_summaries::Model>> {
+    let mut summaries = HashMap::with_capacity(file_ids.len());
+    for chunk in file_ids.chunks(SUMMARY_BATCH_SIZE) {
+        summaries.extend(
+            media_summaries::Entity::find()
+                .filter(media_summaries::Column::MediaFileId.is_in(chunk.to_vec()))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|x| (x.media_file_id, x)),
+        );
+    }
+
+    let missing: Vec<i32> = file_ids
+        .iter()
+        .filter(|id| !summaries.contains_key(id))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        summaries.extend(pivot_media_summaries(db, &missing).await?);
+    }
+
+    Ok(summaries)
+}
+
+/// Summarize the files that don't have a summary yet.
+///
+/// # Returns
+/// * `Result<usize>` - The number of files summarized.
+pub async fn sync_media_summaries(db: &DatabaseConnection) -> Result<usize> {
+    let missing: Vec<i32> = media_files::Entity::find()
+        .select_only()
+        .column(media_files::Column::Id)
+        .filter(
+            media_files::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(media_summaries::Column::MediaFileId)
+                    .from(media_summaries::Entity)
+                    .to_owned(),
+            ),
+        )
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    refresh_media_summaries(db, &missing).await?;
+
+    Ok(missing.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::EntityTrait;
+
+    use crate::{
+        actions::{
+            metadata::{TagChange, apply_tag_changes, get_metadata_summary_by_file_id},
+            summaries::sync_media_summaries,
+        },
+        entities::media_summaries,
+        test_support::{FakeTrack, connect_test_main_db, seed_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_summaries_follow_metadata_writes() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let track = FakeTrack::nth(3);
+        let file_id = seed_tracks(&db, &[track.clone()]).await?[0];
+
+        let summary = media_summaries::Entity::find_by_id(file_id)
+            .one(&db)
+            .await?
+            .unwrap();
+        assert_eq!(summary.artist, track.artist);
+        assert_eq!(summary.album, track.album);
+        assert_eq!(summary.title.as_deref(), Some(track.title.as_str()));
+        assert_eq!(summary.track_number, track.track_number as i32);
+
+        apply_tag_changes(
+            &db,
+            file_id,
+            &[
+                TagChange::Set {
+                    key: "track_title".to_string(),
+                    value: "Renamed".to_string(),
+                },
+                TagChange::Set {
+                    key: "disc_number".to_string(),
+                    value: "2/2".to_string(),
+                },
+                TagChange::Remove {
+                    key: "artist".to_string(),
+                },
+            ],
+        )
+        .await?;
+
+        let summary = get_metadata_summary_by_file_id(&db, file_id).await?;
+        assert_eq!(summary.title, "Renamed");
+        assert_eq!(summary.artist, "");
+        assert_eq!(summary.track_number, 2000 + track.track_number as i32);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_summaries_are_pivoted_and_synced() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let tracks: Vec<FakeTrack> = (0..3).map(FakeTrack::nth).collect();
+        let file_ids = seed_tracks(&db, &tracks).await?;
+
+        // Files indexed before summaries were stored
+        media_summaries::Entity::delete_many().exec(&db).await?;
+
+        let summary = get_metadata_summary_by_file_id(&db, file_ids[1]).await?;
+        assert_eq!(summary.title, tracks[1].title);
+        assert_eq!(summary.album, tracks[1].album);
+        assert_eq!(summary.genre, tracks[1].genre.to_uppercase());
+
+        assert_eq!(sync_media_summaries(&db).await?, 3);
+        assert_eq!(sync_media_summaries(&db).await?, 0);
+        assert_eq!(media_summaries::Entity::find().all(&db).await?.len(), 3);
+
+        Ok(())
+    }
+}
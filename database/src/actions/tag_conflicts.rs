@@ -0,0 +1,488 @@
+//! Differences between the tags stored for a file and the tags in the file.
+//!
+//! They appear when files are edited by other apps without a rescan
+//! picking them up, or when the stored tags are edited without writing
+//! them back. [`get_tag_conflicts`] lists the fields that differ for every
+//! file, and [`resolve_tag_conflicts`] settles them in bulk by keeping the
+//! file, the database, or the values of both.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use log::{error, warn};
+use sea_orm::{
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait,
+};
+
+use ::fsio::FsIo;
+use ::metadata::{
+    backend::MetadataBackends, describe::describe_file, reader::normalize_tag_key,
+    writer::validate_tag_changes,
+};
+
+use crate::actions::{
+    collection::CollectionQueryType,
+    file::get_file_by_id,
+    index::index_media_files,
+    metadata::{TagChange, get_raw_tag_keys, normalize_metadata, read_metadata, update_tags},
+    search::add_term,
+    summaries::refresh_media_summaries,
+};
+use crate::entities::{media_files, media_metadata};
+
+/// Several values of a tag are joined with this when written into a file,
+/// which holds a single value per tag.
+pub const TAG_VALUE_SEPARATOR: &str = "; ";
+
+/// A field whose values differ between the database and the file. Either
+/// side is empty if the field is only set on the other one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagConflict {
+    pub key: String,
+    pub database_values: Vec<String>,
+    pub file_values: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTagConflicts {
+    pub file_id: i32,
+    pub conflicts: Vec<TagConflict>,
+}
+
+/// Which side of a conflict is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagResolution {
+    /// Store the values of the file in the database.
+    File,
+    /// Write the values of the database into the file.
+    Database,
+    /// Keep the values of both sides, the stored ones first, on both
+    /// sides.
+    Merge,
+}
+
+impl FromStr for TagResolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "file" => Ok(TagResolution::File),
+            "database" | "db" => Ok(TagResolution::Database),
+            "merge" => Ok(TagResolution::Merge),
+            _ => bail!("Unknown resolution '{s}', expected file, database or merge"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TagResolutionReport {
+    /// Files whose conflicts were resolved.
+    pub resolved: Vec<i32>,
+    /// Files that couldn't be read or written, left as they were.
+    pub failed: Vec<i32>,
+}
+
+fn group_values(tags: &[(String, String)]) -> BTreeMap<&str, BTreeSet<&str>> {
+    let mut grouped: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (key, value) in tags {
+        grouped.entry(key).or_default().insert(value);
+    }
+
+    grouped
+}
+
+/// Compare stored tags with the tags read from a file, both with
+/// normalized keys. The order of the values of a field doesn't matter.
+pub fn diff_tags(database: &[(String, String)], file: &[(String, String)]) -> Vec<TagConflict> {
+    let database = group_values(database);
+    let file = group_values(file);
+
+    let keys: BTreeSet<&str> = database.keys().chain(file.keys()).copied().collect();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let database_values = database.get(key).cloned().unwrap_or_default();
+            let file_values = file.get(key).cloned().unwrap_or_default();
+            if database_values == file_values {
+                return None;
+            }
+
+            Some(TagConflict {
+                key: key.to_string(),
+                database_values: database_values.into_iter().map(String::from).collect(),
+                file_values: file_values.into_iter().map(String::from).collect(),
+            })
+        })
+        .collect()
+}
+
+async fn get_stored_tags(
+    main_db: &DatabaseConnection,
+    file_id: i32,
+) -> Result<Vec<(String, String)>> {
+    Ok(media_metadata::Entity::find()
+        .filter(media_metadata::Column::FileId.eq(file_id))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| (x.meta_key, x.meta_value))
+        .collect())
+}
+
+fn read_file_tags(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    lib_path: &Path,
+    file: &media_files::Model,
+) -> Result<Vec<(String, String)>> {
+    let path = lib_path.join(&file.directory).join(&file.file_name);
+    let node = fsio.canonicalize(&path)?;
+    let description = describe_file(&node, &None)?.with_backends(backends);
+    let metadata = read_metadata(&description)?;
+
+    Ok(normalize_metadata(&metadata.metadata).0)
+}
+
+async fn get_file_tag_conflicts(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    file_id: i32,
+) -> Result<Vec<TagConflict>> {
+    let file = get_file_by_id(main_db, file_id)
+        .await?
+        .with_context(|| format!("File not found: {file_id}"))?;
+
+    let stored = get_stored_tags(main_db, file_id).await?;
+    let read = read_file_tags(fsio, backends, lib_path, &file)?;
+
+    Ok(diff_tags(&stored, &read))
+}
+
+/// List the fields that differ between the database and the files. Files
+/// without conflicts are left out, files that can't be read are logged and
+/// skipped.
+pub async fn get_tag_conflicts(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    file_ids: &[i32],
+) -> Result<Vec<FileTagConflicts>> {
+    let mut result = Vec::new();
+    for file_id in file_ids {
+        match get_file_tag_conflicts(fsio, backends, main_db, lib_path, *file_id).await {
+            Ok(conflicts) if conflicts.is_empty() => {}
+            Ok(conflicts) => result.push(FileTagConflicts {
+                file_id: *file_id,
+                conflicts,
+            }),
+            Err(e) => warn!("Failed to compare the tags of file {file_id}: {e:#}"),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Replace the stored values of the conflicting fields with the values of
+/// the file, which is left untouched.
+async fn store_file_values(
+    main_db: &DatabaseConnection,
+    file: &media_files::Model,
+    conflicts: &[TagConflict],
+) -> Result<()> {
+    let txn = main_db.begin().await?;
+    for conflict in conflicts {
+        media_metadata::Entity::delete_many()
+            .filter(media_metadata::Column::FileId.eq(file.id))
+            .filter(media_metadata::Column::MetaKey.eq(conflict.key.as_str()))
+            .exec(&txn)
+            .await?;
+
+        let models: Vec<media_metadata::ActiveModel> = conflict
+            .file_values
+            .iter()
+            .map(|value| media_metadata::ActiveModel {
+                file_id: ActiveValue::Set(file.id),
+                meta_key: ActiveValue::Set(conflict.key.clone()),
+                meta_value: ActiveValue::Set(value.clone()),
+                ..Default::default()
+            })
+            .collect();
+        if !models.is_empty() {
+            media_metadata::Entity::insert_many(models)
+                .exec(&txn)
+                .await?;
+        }
+    }
+    refresh_media_summaries(&txn, &[file.id]).await?;
+    txn.commit().await?;
+
+    if let Some(conflict) = conflicts.iter().find(|x| x.key == "track_title") {
+        let title = conflict.file_values.first().unwrap_or(&file.file_name);
+        add_term(main_db, CollectionQueryType::Track, file.id, title).await?;
+    }
+
+    index_media_files(main_db, vec![file.id], None).await
+}
+
+fn same_values(a: &[String], b: &[String]) -> bool {
+    a.iter().collect::<BTreeSet<_>>() == b.iter().collect::<BTreeSet<_>>()
+}
+
+async fn resolve_file_tag_conflicts(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    file_id: i32,
+    keys: &[String],
+    resolution: TagResolution,
+) -> Result<()> {
+    let file = get_file_by_id(main_db, file_id)
+        .await?
+        .with_context(|| format!("File not found: {file_id}"))?;
+    let stored = get_stored_tags(main_db, file_id).await?;
+    let read = read_file_tags(fsio, backends, lib_path, &file)?;
+    let raw_keys = get_raw_tag_keys(main_db, file_id).await?;
+
+    // Fields that end up with the values of the file only need storing,
+    // the others are written into the file and stored along
+    let mut stored_from_file = Vec::new();
+    let mut changes = Vec::new();
+    for conflict in diff_tags(&stored, &read) {
+        if !keys.is_empty() && !keys.contains(&conflict.key) {
+            continue;
+        }
+
+        let values = match resolution {
+            TagResolution::File => conflict.file_values.clone(),
+            TagResolution::Database => conflict.database_values.clone(),
+            TagResolution::Merge => {
+                let mut values = conflict.database_values.clone();
+                for value in &conflict.file_values {
+                    if !values.contains(value) {
+                        values.push(value.clone());
+                    }
+                }
+                values
+            }
+        };
+
+        if same_values(&values, &conflict.file_values) {
+            stored_from_file.push(conflict);
+            continue;
+        }
+
+        let change = if values.is_empty() {
+            TagChange::Remove {
+                key: conflict.key.clone(),
+            }
+        } else {
+            TagChange::Set {
+                key: conflict.key.clone(),
+                value: values.join(TAG_VALUE_SEPARATOR),
+            }
+        };
+        // Tags without an item in the file stay conflicting
+        if let Err(e) = validate_tag_changes(std::slice::from_ref(&change), &raw_keys) {
+            warn!("Skipping a conflict of file {file_id}: {e:#}");
+            continue;
+        }
+        changes.push(change);
+    }
+
+    if !stored_from_file.is_empty() {
+        store_file_values(main_db, &file, &stored_from_file).await?;
+    }
+    if !changes.is_empty() {
+        update_tags(fsio, backends, main_db, lib_path, file_id, &changes).await?;
+    }
+
+    Ok(())
+}
+
+/// Resolve the conflicts of the files in bulk. Only the fields in `keys`
+/// are resolved, or every conflicting field if it is empty. Files are
+/// compared again first, so fields that stopped differing are left alone.
+pub async fn resolve_tag_conflicts(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    file_ids: &[i32],
+    keys: &[String],
+    resolution: TagResolution,
+) -> Result<TagResolutionReport> {
+    let keys: Vec<String> = keys.iter().map(|x| normalize_tag_key(x)).collect();
+
+    let mut report = TagResolutionReport::default();
+    for file_id in file_ids {
+        match resolve_file_tag_conflicts(
+            fsio, backends, main_db, lib_path, *file_id, &keys, resolution,
+        )
+        .await
+        {
+            Ok(_) => report.resolved.push(*file_id),
+            Err(e) => {
+                error!("Failed to resolve the tag conflicts of file {file_id}: {e:#}");
+                report.failed.push(*file_id);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use anyhow::Result;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+    use tempfile::tempdir;
+
+    use ::fsio::{FsIo, FsNode};
+    use ::metadata::backend::{MetadataBackends, TagReader};
+
+    use crate::{
+        actions::tag_conflicts::{
+            TagConflict, TagResolution, diff_tags, get_tag_conflicts, resolve_tag_conflicts,
+        },
+        entities::media_metadata,
+        test_support::{FakeTrack, connect_test_main_db, seed_fake_tracks},
+    };
+
+    /// Reads the tags of the first fake track, edited by another app.
+    struct EditedTagReader;
+
+    impl TagReader for EditedTagReader {
+        fn read_tags(&self, _fs_node: &FsNode) -> Result<Vec<(String, String)>> {
+            let track = FakeTrack::nth(0);
+            Ok(vec![
+                ("TRACK_TITLE".to_string(), "Edited Elsewhere".to_string()),
+                ("artist".to_string(), track.artist),
+                ("album".to_string(), track.album),
+                ("track_number".to_string(), track.track_number.to_string()),
+                ("date".to_string(), "2001".to_string()),
+            ])
+        }
+    }
+
+    fn tags(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_tags_ignores_value_order() {
+        let database = tags(&[("artist", "A"), ("artist", "B"), ("genre", "Rock")]);
+        let file = tags(&[("artist", "B"), ("artist", "A"), ("date", "2001")]);
+
+        assert_eq!(
+            diff_tags(&database, &file),
+            vec![
+                TagConflict {
+                    key: "date".to_string(),
+                    database_values: vec![],
+                    file_values: vec!["2001".to_string()],
+                },
+                TagConflict {
+                    key: "genre".to_string(),
+                    database_values: vec!["Rock".to_string()],
+                    file_values: vec![],
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_conflicts_with_file_values() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let fsio = FsIo::new();
+        let lib_dir = tempdir()?;
+        let backends = MetadataBackends::new(
+            MetadataBackends::default().hasher,
+            Arc::new(EditedTagReader),
+        );
+
+        let file_id = seed_fake_tracks(&db, 1).await?[0];
+        let track = FakeTrack::nth(0);
+        fs::create_dir_all(lib_dir.path().join(&track.directory))?;
+        fs::write(
+            lib_dir.path().join(&track.directory).join(&track.file_name),
+            "not really audio",
+        )?;
+
+        let missing_id = file_id + 1;
+        let conflicts = get_tag_conflicts(
+            &fsio,
+            &backends,
+            &db,
+            lib_dir.path(),
+            &[file_id, missing_id],
+        )
+        .await?;
+        assert_eq!(conflicts.len(), 1, "unknown files are skipped");
+        assert_eq!(conflicts[0].file_id, file_id);
+        let keys: Vec<&str> = conflicts[0]
+            .conflicts
+            .iter()
+            .map(|x| x.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["date", "genre", "track_title"]);
+
+        // Only the requested fields are resolved
+        let report = resolve_tag_conflicts(
+            &fsio,
+            &backends,
+            &db,
+            lib_dir.path(),
+            &[file_id],
+            &["Track_Title".to_string()],
+            TagResolution::File,
+        )
+        .await?;
+        assert_eq!(report.resolved, vec![file_id]);
+        let conflicts =
+            get_tag_conflicts(&fsio, &backends, &db, lib_dir.path(), &[file_id]).await?;
+        assert_eq!(conflicts[0].conflicts.len(), 2);
+
+        let report = resolve_tag_conflicts(
+            &fsio,
+            &backends,
+            &db,
+            lib_dir.path(),
+            &[file_id, missing_id],
+            &[],
+            TagResolution::File,
+        )
+        .await?;
+        assert_eq!(report.resolved, vec![file_id]);
+        assert_eq!(report.failed, vec![missing_id]);
+        assert!(
+            get_tag_conflicts(&fsio, &backends, &db, lib_dir.path(), &[file_id])
+                .await?
+                .is_empty()
+        );
+
+        let stored: Vec<(String, String)> = media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.eq(file_id))
+            .filter(media_metadata::Column::MetaKey.is_in(["track_title", "genre", "date"]))
+            .all(&db)
+            .await?
+            .into_iter()
+            .map(|x| (x.meta_key, x.meta_value))
+            .collect();
+        assert_eq!(stored.len(), 2);
+        assert!(stored.contains(&("track_title".to_string(), "Edited Elsewhere".to_string())));
+        assert!(stored.contains(&("date".to_string(), "2001".to_string())));
+
+        Ok(())
+    }
+}
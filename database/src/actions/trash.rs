@@ -0,0 +1,391 @@
+//! A holding area for removed files.
+//!
+//! Whenever the record of a file is removed, a snapshot of the record and
+//! everything hanging off it is kept for a while, so a cleanup that ran
+//! against a temporarily unmounted folder can be reversed. Restoring a file
+//! that was imported again in the meantime only brings back its stats, play
+//! history and analysis, since the scanner has recreated the rest.
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use log::info;
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+use ::metadata::reader::normalize_tag_key;
+
+use crate::actions::{
+    collection::CollectionQueryType, index::index_media_files, search::add_term,
+    summaries::refresh_media_summaries,
+};
+use crate::entities::{
+    deleted_files, media_analysis, media_cover_art, media_file_stats, media_files, media_metadata,
+    play_history,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DeletedFileSnapshot {
+    file: media_files::Model,
+    metadata: Vec<media_metadata::Model>,
+    stats: Option<media_file_stats::Model>,
+    history: Vec<play_history::Model>,
+    analysis: Option<media_analysis::Model>,
+}
+
+/// Keep a snapshot of a file before its record is removed.
+pub async fn move_to_trash(main_db: &DatabaseConnection, file_id: i32) -> Result<()> {
+    let Some(file) = media_files::Entity::find_by_id(file_id)
+        .one(main_db)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let snapshot = DeletedFileSnapshot {
+        metadata: media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.eq(file_id))
+            .all(main_db)
+            .await?,
+        stats: media_file_stats::Entity::find()
+            .filter(media_file_stats::Column::MediaFileId.eq(file_id))
+            .one(main_db)
+            .await?,
+        history: play_history::Entity::find()
+            .filter(play_history::Column::MediaFileId.eq(file_id))
+            .all(main_db)
+            .await?,
+        analysis: media_analysis::Entity::find()
+            .filter(media_analysis::Column::FileId.eq(file_id))
+            .one(main_db)
+            .await?,
+        file,
+    };
+
+    let deleted_file = deleted_files::ActiveModel {
+        file_id: ActiveValue::Set(file_id),
+        directory: ActiveValue::Set(snapshot.file.directory.clone()),
+        file_name: ActiveValue::Set(snapshot.file.file_name.clone()),
+        source_id: ActiveValue::Set(snapshot.file.source_id),
+        snapshot: ActiveValue::Set(serde_json::to_string(&snapshot)?),
+        deleted_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+        ..Default::default()
+    };
+    deleted_file.insert(main_db).await?;
+
+    Ok(())
+}
+
+/// Get the removed files that can still be restored, latest first.
+pub async fn get_deleted_files(main_db: &DatabaseConnection) -> Result<Vec<deleted_files::Model>> {
+    Ok(deleted_files::Entity::find()
+        .order_by_desc(deleted_files::Column::DeletedAt)
+        .order_by_desc(deleted_files::Column::Id)
+        .all(main_db)
+        .await?)
+}
+
+/// Bring back the stats, play history and analysis of a snapshot, unless
+/// the file already has them.
+async fn restore_file_data(
+    main_db: &DatabaseConnection,
+    file_id: i32,
+    snapshot: &DeletedFileSnapshot,
+) -> Result<()> {
+    if let Some(stats) = &snapshot.stats {
+        let exists = media_file_stats::Entity::find()
+            .filter(media_file_stats::Column::MediaFileId.eq(file_id))
+            .one(main_db)
+            .await?
+            .is_some();
+
+        if !exists {
+            let mut stats: media_file_stats::ActiveModel = stats.clone().into();
+            stats.id = ActiveValue::NotSet;
+            stats.media_file_id = ActiveValue::Set(file_id);
+            stats.insert(main_db).await?;
+        }
+    }
+
+    if !snapshot.history.is_empty() {
+        play_history::Entity::insert_many(snapshot.history.iter().map(|x| {
+            let mut history: play_history::ActiveModel = x.clone().into();
+            history.id = ActiveValue::NotSet;
+            history.media_file_id = ActiveValue::Set(file_id);
+            history
+        }))
+        .exec(main_db)
+        .await?;
+    }
+
+    if let Some(analysis) = &snapshot.analysis {
+        let exists = media_analysis::Entity::find()
+            .filter(media_analysis::Column::FileId.eq(file_id))
+            .one(main_db)
+            .await?
+            .is_some();
+
+        if !exists {
+            let mut analysis: media_analysis::ActiveModel = analysis.clone().into();
+            analysis.id = ActiveValue::NotSet;
+            analysis.file_id = ActiveValue::Set(file_id);
+            analysis.insert(main_db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Insert the file record and metadata of a snapshot, keeping the original
+/// ID if it is still free.
+async fn restore_file_record(
+    main_db: &DatabaseConnection,
+    snapshot: &DeletedFileSnapshot,
+) -> Result<i32> {
+    let mut file = snapshot.file.clone();
+
+    // The cover art may have been cleaned up together with the file
+    if let Some(cover_art_id) = file.cover_art_id
+        && media_cover_art::Entity::find_by_id(cover_art_id)
+            .one(main_db)
+            .await?
+            .is_none()
+    {
+        file.cover_art_id = None;
+    }
+
+    let id_taken = media_files::Entity::find_by_id(file.id)
+        .one(main_db)
+        .await?
+        .is_some();
+    let mut file: media_files::ActiveModel = file.into();
+    if id_taken {
+        file.id = ActiveValue::NotSet;
+    }
+
+    let file_id = media_files::Entity::insert(file)
+        .exec(main_db)
+        .await?
+        .last_insert_id;
+
+    if !snapshot.metadata.is_empty() {
+        media_metadata::Entity::insert_many(snapshot.metadata.iter().map(|x| {
+            let mut metadata: media_metadata::ActiveModel = x.clone().into();
+            metadata.id = ActiveValue::NotSet;
+            metadata.file_id = ActiveValue::Set(file_id);
+            // Snapshots taken before keys were normalized
+            metadata.meta_key = ActiveValue::Set(normalize_tag_key(&x.meta_key));
+            metadata
+        }))
+        .exec(main_db)
+        .await?;
+    }
+    refresh_media_summaries(main_db, &[file_id]).await?;
+
+    let title = snapshot
+        .metadata
+        .iter()
+        .find(|x| x.meta_key == "track_title")
+        .map(|x| x.meta_value.as_str())
+        .unwrap_or(&snapshot.file.file_name);
+    add_term(main_db, CollectionQueryType::Track, file_id, title).await?;
+
+    Ok(file_id)
+}
+
+/// Restore removed files.
+///
+/// # Returns
+/// * `Result<Vec<i32>>` - The IDs of the restored files, in the same order.
+///   A file that was imported again keeps its new ID.
+pub async fn restore_deleted_files(
+    main_db: &DatabaseConnection,
+    deleted_file_ids: &[i32],
+) -> Result<Vec<i32>> {
+    let mut file_ids = Vec::with_capacity(deleted_file_ids.len());
+    let mut new_file_ids = Vec::new();
+
+    for id in deleted_file_ids {
+        let Some(deleted_file) = deleted_files::Entity::find_by_id(*id).one(main_db).await? else {
+            continue;
+        };
+
+        let snapshot: DeletedFileSnapshot = serde_json::from_str(&deleted_file.snapshot)
+            .with_context(|| format!("Invalid snapshot of deleted file: {id}"))?;
+
+        let existing = media_files::Entity::find()
+            .filter(media_files::Column::Directory.eq(&deleted_file.directory))
+            .filter(media_files::Column::FileName.eq(&deleted_file.file_name))
+            .one(main_db)
+            .await?;
+
+        let file_id = match existing {
+            Some(file) => file.id,
+            None => {
+                let file_id = restore_file_record(main_db, &snapshot).await?;
+                new_file_ids.push(file_id);
+                file_id
+            }
+        };
+        restore_file_data(main_db, file_id, &snapshot).await?;
+
+        deleted_files::Entity::delete_by_id(*id)
+            .exec(main_db)
+            .await?;
+
+        info!(
+            "Restored {}/{}",
+            deleted_file.directory, deleted_file.file_name
+        );
+        file_ids.push(file_id);
+    }
+
+    // Link the restored files to their artists, albums and genres again
+    if !new_file_ids.is_empty() {
+        index_media_files(main_db, new_file_ids, None).await?;
+    }
+
+    Ok(file_ids)
+}
+
+/// Removed files kept for longer than `retention_days` were removed before
+/// this. `None` if the retention reaches before any representable time, so
+/// everything is kept.
+fn purge_cutoff(retention_days: u64) -> Option<String> {
+    let retention = Duration::try_days(i64::try_from(retention_days).ok()?)?;
+
+    Some(Utc::now().checked_sub_signed(retention)?.to_rfc3339())
+}
+
+/// Count the removed files that [`purge_deleted_files`] would drop.
+pub async fn count_expired_deleted_files(
+    main_db: &DatabaseConnection,
+    retention_days: u64,
+) -> Result<u64> {
+    let Some(cutoff) = purge_cutoff(retention_days) else {
+        return Ok(0);
+    };
+
+    Ok(deleted_files::Entity::find()
+        .filter(deleted_files::Column::DeletedAt.lte(cutoff))
+        .count(main_db)
+        .await?)
+}
+
+/// Drop removed files that have been kept for longer than `retention_days`.
+///
+/// # Returns
+/// * `Result<u64>` - The number of files dropped.
+pub async fn purge_deleted_files(main_db: &DatabaseConnection, retention_days: u64) -> Result<u64> {
+    let Some(cutoff) = purge_cutoff(retention_days) else {
+        return Ok(0);
+    };
+
+    let result = deleted_files::Entity::delete_many()
+        .filter(deleted_files::Column::DeletedAt.lte(cutoff))
+        .exec(main_db)
+        .await?;
+
+    if result.rows_affected > 0 {
+        info!("Purged {} deleted files", result.rows_affected);
+    }
+
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sea_orm::EntityTrait;
+
+    use crate::{
+        actions::{
+            metadata::{get_metadata_summary_by_file_ids, remove_file_record},
+            stats::{get_rating, set_rating},
+            trash::{
+                count_expired_deleted_files, get_deleted_files, purge_deleted_files,
+                restore_deleted_files,
+            },
+        },
+        entities::media_files,
+        test_support::{FakeTrack, connect_test_main_db, seed_fake_tracks, seed_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_restore_deleted_file() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+        set_rating(&db, file_ids[1], Some(5)).await?;
+
+        remove_file_record(&db, file_ids[1]).await?;
+        assert!(
+            media_files::Entity::find_by_id(file_ids[1])
+                .one(&db)
+                .await?
+                .is_none()
+        );
+
+        let deleted_files = get_deleted_files(&db).await?;
+        assert_eq!(deleted_files.len(), 1);
+        assert_eq!(deleted_files[0].file_id, file_ids[1]);
+
+        let restored = restore_deleted_files(&db, &[deleted_files[0].id]).await?;
+        assert_eq!(restored, vec![file_ids[1]]);
+        assert!(get_deleted_files(&db).await?.is_empty());
+
+        let summary = get_metadata_summary_by_file_ids(&db, restored).await?;
+        assert_eq!(summary[0].title, FakeTrack::nth(1).title);
+        assert_eq!(summary[0].artist, FakeTrack::nth(1).artist);
+        assert_eq!(get_rating(&db, file_ids[1]).await?, Some(5));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_reimported_file() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+        set_rating(&db, file_ids[0], Some(3)).await?;
+
+        remove_file_record(&db, file_ids[0]).await?;
+
+        // The folder is mounted again and the file is scanned as a new one
+        let new_file_ids = seed_tracks(&db, &[FakeTrack::nth(0)]).await?;
+        assert_ne!(new_file_ids[0], file_ids[0]);
+
+        let deleted_files = get_deleted_files(&db).await?;
+        let restored = restore_deleted_files(&db, &[deleted_files[0].id]).await?;
+        assert_eq!(restored, new_file_ids);
+        assert_eq!(get_rating(&db, new_file_ids[0]).await?, Some(3));
+        assert_eq!(media_files::Entity::find().all(&db).await?.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted_files() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 3).await?;
+
+        for file_id in &file_ids[..2] {
+            remove_file_record(&db, *file_id).await?;
+        }
+
+        assert_eq!(count_expired_deleted_files(&db, 30).await?, 0);
+        assert_eq!(purge_deleted_files(&db, 30).await?, 0);
+        assert_eq!(get_deleted_files(&db).await?.len(), 2);
+
+        // Retentions reaching before any representable time keep everything
+        for retention_days in [200_000_000, i64::MAX as u64, u64::MAX] {
+            assert_eq!(count_expired_deleted_files(&db, retention_days).await?, 0);
+            assert_eq!(purge_deleted_files(&db, retention_days).await?, 0);
+        }
+        assert_eq!(get_deleted_files(&db).await?.len(), 2);
+
+        assert_eq!(count_expired_deleted_files(&db, 0).await?, 2);
+        assert_eq!(purge_deleted_files(&db, 0).await?, 2);
+        assert!(get_deleted_files(&db).await?.is_empty());
+
+        Ok(())
+    }
+}
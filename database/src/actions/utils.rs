@@ -12,7 +12,51 @@ pub fn first_char(s: &str) -> char {
     deunicode(s).chars().next().unwrap_or('#')
 }
 
+/// Where every row of the gojūon table starts among the hiragana, and the
+/// kana naming it. Small and voiced kana belong to the row of their base
+/// kana, `ん` to the `わ` row.
+const KANA_ROWS: [(char, char); 12] = [
+    ('\u{3041}', 'あ'),
+    ('\u{304B}', 'か'),
+    ('\u{3055}', 'さ'),
+    ('\u{305F}', 'た'),
+    ('\u{306A}', 'な'),
+    ('\u{306F}', 'は'),
+    ('\u{307E}', 'ま'),
+    ('\u{3083}', 'や'),
+    ('\u{3089}', 'ら'),
+    ('\u{308E}', 'わ'),
+    ('\u{3094}', 'あ'),
+    ('\u{3095}', 'か'),
+];
+
+/// The row of a hiragana or katakana, as the first hiragana of the row.
+pub fn kana_row(c: char) -> Option<char> {
+    let hiragana = match c {
+        '\u{3041}'..='\u{3096}' => c,
+        // Katakana sit 0x60 code points after the matching hiragana
+        '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60)?,
+        _ => return None,
+    };
+
+    KANA_ROWS
+        .iter()
+        .rev()
+        .find(|(start, _)| hiragana >= *start)
+        .map(|(_, row)| *row)
+}
+
+/// The group of a collection, the first letter of its name.
+///
+/// Names starting with kana are grouped by their kana row. Every other
+/// script goes through `deunicode`, which gives the pinyin initial of Han
+/// characters and the transliteration of Cyrillic, so `北京` lands in `B`
+/// and `Мумий Тролль` in `M`. Kanji are always read as Chinese.
 pub fn generate_group_name(x: &str) -> String {
+    if let Some(row) = x.chars().next().and_then(kana_row) {
+        return row.to_string();
+    }
+
     let c = first_char(x);
 
     if c.is_lowercase() {
@@ -350,3 +394,86 @@ macro_rules! parallel_media_files_processing {
         Ok(total_tasks)
     }};
 }
+
+/// Run `parallel_media_files_processing!` over chunks of the query, saving a
+/// checkpoint under `$task` after each chunk so an interrupted run resumes
+/// after the last finished chunk.
+#[macro_export]
+macro_rules! checkpointed_media_files_processing {
+    (
+        $task:expr,
+        $main_db:expr,
+        $batch_size:expr,
+        $progress_callback:expr,
+        $cancel_token:expr,
+        $cursor_query:expr,
+        $lib_path:expr,
+        $fsio:expr,
+        $node_id: expr,
+        $process_fn:expr,
+        $result_handler:expr
+    ) => {{
+        use log::info;
+        use sea_orm::{ColumnTrait, PaginatorTrait, QueryFilter};
+        use std::sync::Arc;
+        use $crate::actions::checkpoint::{
+            clear_checkpoint, get_checkpoint, next_chunk_end, save_checkpoint,
+        };
+        use $crate::parallel_media_files_processing;
+
+        let checkpoint = get_checkpoint($main_db, $task).await?;
+        let mut position = checkpoint.as_ref().map_or(0, |x| x.position as i32);
+        let mut processed = checkpoint.map_or(0, |x| x.processed);
+        if position > 0 {
+            info!("Resuming {} after file ID: {position}", $task);
+        }
+
+        let is_cancelled = || $cancel_token.as_ref().is_some_and(|x| x.is_cancelled());
+        let remaining_query = $cursor_query.filter(media_files::Column::Id.gt(position));
+        let total_tasks = remaining_query.clone().count($main_db).await? as usize;
+        let mut finished_tasks = 0;
+
+        while let Some(chunk_end) =
+            next_chunk_end($main_db, remaining_query.clone(), position).await?
+        {
+            let progress_callback = Arc::clone(&$progress_callback);
+            let chunk_progress_callback = Arc::new(move |x: usize, _: usize| {
+                progress_callback(finished_tasks + x, total_tasks)
+            });
+            let chunk_query = remaining_query
+                .clone()
+                .filter(media_files::Column::Id.gt(position))
+                .filter(media_files::Column::Id.lte(chunk_end));
+
+            let chunk_tasks: Result<usize> = parallel_media_files_processing!(
+                $main_db,
+                $batch_size,
+                chunk_progress_callback,
+                $cancel_token,
+                chunk_query,
+                $lib_path,
+                $fsio,
+                $node_id,
+                $process_fn,
+                $result_handler
+            );
+            let chunk_tasks = chunk_tasks?;
+
+            // Files of a cancelled chunk may be left unhandled
+            if is_cancelled() {
+                break;
+            }
+
+            finished_tasks += chunk_tasks;
+            processed += chunk_tasks as i64;
+            position = chunk_end;
+            save_checkpoint($main_db, $task, "", position as i64, processed).await?;
+        }
+
+        if !is_cancelled() {
+            clear_checkpoint($main_db, $task).await?;
+        }
+
+        Ok(finished_tasks)
+    }};
+}
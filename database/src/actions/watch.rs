@@ -0,0 +1,292 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use log::{debug, error, info, warn};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use ::fsio::FsIo;
+use ::metadata::{
+    backend::MetadataBackends,
+    describe::{FileDescription, describe_file},
+    scanner::is_audio_file,
+};
+
+use crate::actions::{
+    file::get_file_ids_by_descriptions,
+    metadata::{process_files, remove_file_record},
+    sources::{has_source_files, is_root_reachable},
+};
+use crate::entities::media_files;
+use crate::events::{EventBus, LibraryEvent};
+
+/// Paths touched since the last time the library was updated.
+#[derive(Debug, Default)]
+pub struct LibraryChanges {
+    /// Created or modified files and directories.
+    pub updated: HashSet<PathBuf>,
+    /// Deleted files and directories, or the old side of a rename.
+    pub removed: HashSet<PathBuf>,
+}
+
+impl LibraryChanges {
+    pub fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    fn update(&mut self, path: &Path) {
+        self.removed.remove(path);
+        self.updated.insert(path.to_path_buf());
+    }
+
+    fn remove(&mut self, path: &Path) {
+        self.updated.remove(path);
+        self.removed.insert(path.to_path_buf());
+    }
+
+    /// Record a file system event, later events of a path win.
+    pub fn add_event(&mut self, event: &Event) {
+        match event.kind {
+            EventKind::Create(_) => event.paths.iter().for_each(|x| self.update(x)),
+            EventKind::Remove(_) => event.paths.iter().for_each(|x| self.remove(x)),
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                event.paths.iter().for_each(|x| self.remove(x))
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                if let [from, to] = event.paths.as_slice() {
+                    self.remove(from);
+                    self.update(to);
+                }
+            }
+            // Renames without a known side are resolved by checking whether
+            // the path still exists
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                for path in &event.paths {
+                    if path.exists() {
+                        self.update(path);
+                    } else {
+                        self.remove(path);
+                    }
+                }
+            }
+            EventKind::Modify(ModifyKind::Metadata(_)) => {}
+            EventKind::Modify(_) => event.paths.iter().for_each(|x| self.update(x)),
+            _ => {}
+        }
+    }
+}
+
+/// Split a path into the `directory` and `file_name` columns of
+/// `media_files`, relative to the library root.
+fn to_record_path(lib_path: &Path, path: &Path) -> Option<(String, String)> {
+    let rel_path = path.strip_prefix(lib_path).ok()?;
+    let file_name = rel_path.file_name()?.to_str()?.to_string();
+    let directory = rel_path
+        .parent()
+        .and_then(Path::to_str)
+        .unwrap_or_default()
+        .replace('\\', "/");
+
+    Some((directory, file_name))
+}
+
+async fn remove_paths(
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    paths: &HashSet<PathBuf>,
+) -> Result<usize> {
+    let mut removed = 0;
+
+    for path in paths {
+        // The path may have come back before the changes were applied
+        if path.exists() {
+            continue;
+        }
+
+        let Some((directory, file_name)) = to_record_path(lib_path, path) else {
+            continue;
+        };
+
+        // A removed path is either a single file, or a directory whose
+        // files all have to go
+        let directory_path = if directory.is_empty() {
+            file_name.clone()
+        } else {
+            format!("{directory}/{file_name}")
+        };
+        let files = media_files::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(media_files::Column::Directory.eq(directory))
+                            .add(media_files::Column::FileName.eq(file_name)),
+                    )
+                    .add(media_files::Column::Directory.eq(directory_path.clone()))
+                    .add(media_files::Column::Directory.starts_with(format!("{directory_path}/"))),
+            )
+            .all(main_db)
+            .await?;
+
+        for file in files {
+            info!("Removing {}/{}", file.directory, file.file_name);
+            remove_file_record(main_db, file.id).await?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+async fn update_paths(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    paths: &HashSet<PathBuf>,
+) -> Result<Vec<i32>> {
+    let mut nodes = Vec::new();
+    for path in paths {
+        let node = match fsio.canonicalize(path) {
+            Ok(node) => node,
+            // Already gone again, the removal is handled on its own
+            Err(_) => continue,
+        };
+
+        if node.is_dir {
+            match fsio.walk_dir(&node.path, true) {
+                Ok(children) => nodes.extend(children.into_iter().filter(is_audio_file)),
+                Err(e) => warn!("Failed to walk {:?}: {e}", node.path),
+            }
+        } else if is_audio_file(&node) {
+            nodes.push(node);
+        }
+    }
+
+    if nodes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let lib_path = Some(lib_path.to_path_buf());
+    let mut descriptions: Vec<Option<FileDescription>> = nodes
+        .iter()
+        .map(|node| {
+            describe_file(node, &lib_path)
+                .ok()
+                .map(|x| x.with_backends(backends))
+        })
+        .collect();
+
+    process_files(fsio, main_db, &mut descriptions)
+        .await
+        .with_context(|| "Unable to process changed files")?;
+
+    Ok(get_file_ids_by_descriptions(main_db, &descriptions).await?)
+}
+
+/// Bring the database in line with the changed paths, without scanning the
+/// whole library. Indexing the files is left to the subscribers of the
+/// published `LibraryChanged` event.
+pub async fn apply_library_changes(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    events: &EventBus,
+    changes: &LibraryChanges,
+) -> Result<()> {
+    // Unmounting the library shows up as every file being removed
+    if !is_root_reachable(lib_path, has_source_files(main_db, None).await?) {
+        warn!("Library root is unreachable, changes are ignored: {lib_path:?}");
+        return Ok(());
+    }
+
+    let removed = remove_paths(main_db, lib_path, &changes.removed).await?;
+    let updated = update_paths(fsio, backends, main_db, lib_path, &changes.updated).await?;
+
+    info!(
+        "Library updated: {} files updated, {removed} files removed",
+        updated.len()
+    );
+
+    if !updated.is_empty() || removed > 0 {
+        events.publish(LibraryEvent::LibraryChanged { updated, removed });
+    }
+
+    Ok(())
+}
+
+/// Watch the library root and apply changes incrementally until cancelled.
+///
+/// Events are collected until the library has been quiet for `debounce`,
+/// so copying a whole album results in a single update.
+pub async fn watch_audio_library(
+    fsio: &FsIo,
+    backends: &MetadataBackends,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    events: &EventBus,
+    debounce: Duration,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .with_context(|| "Failed to create file watcher")?;
+    watcher
+        .watch(lib_path, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {lib_path:?}"))?;
+
+    info!("Watching library: {lib_path:?}");
+
+    let mut changes = LibraryChanges::default();
+    loop {
+        let event = tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            event = rx.recv() => event,
+        };
+
+        let Some(event) = event else {
+            break;
+        };
+
+        match event {
+            Ok(event) => changes.add_event(&event),
+            Err(e) => error!("Watcher error: {e}"),
+        }
+
+        // Keep collecting until the library settles down
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => return Ok(()),
+                event = tokio::time::timeout(debounce, rx.recv()) => match event {
+                    Ok(Some(Ok(event))) => changes.add_event(&event),
+                    Ok(Some(Err(e))) => error!("Watcher error: {e}"),
+                    Ok(None) | Err(_) => break,
+                },
+            }
+        }
+
+        if changes.is_empty() {
+            continue;
+        }
+
+        debug!("Applying library changes: {changes:?}");
+        if let Err(e) =
+            apply_library_changes(fsio, backends, main_db, lib_path, events, &changes).await
+        {
+            error!("Failed to apply library changes: {e:#?}");
+        }
+        changes = LibraryChanges::default();
+    }
+
+    info!("Stopped watching library");
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use sea_orm::{ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+use ::analysis::utils::waveform::Waveform;
+
+use crate::entities::media_file_waveform;
+
+/// Store the peaks of a file, replacing the ones stored before. A missing
+/// waveform is stored as empty, so the file isn't analyzed again for it.
+pub async fn upsert_waveform(
+    main_db: &DatabaseConnection,
+    file_id: i32,
+    waveform: Option<&Waveform>,
+) -> Result<()> {
+    let waveform = match waveform {
+        Some(waveform) => waveform.to_bytes()?,
+        None => vec![],
+    };
+
+    media_file_waveform::Entity::delete_many()
+        .filter(media_file_waveform::Column::MediaFileId.eq(file_id))
+        .exec(main_db)
+        .await?;
+
+    let model = media_file_waveform::ActiveModel {
+        media_file_id: ActiveValue::Set(file_id),
+        waveform: ActiveValue::Set(waveform),
+        ..Default::default()
+    };
+    media_file_waveform::Entity::insert(model)
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
+/// The peaks of a file computed during analysis, `None` if it wasn't
+/// analyzed yet.
+pub async fn get_waveform(main_db: &DatabaseConnection, file_id: i32) -> Result<Option<Waveform>> {
+    media_file_waveform::Entity::find()
+        .filter(media_file_waveform::Column::MediaFileId.eq(file_id))
+        .one(main_db)
+        .await?
+        .map(|x| Waveform::from_bytes(&x.waveform))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use ::analysis::utils::waveform::Waveform;
+
+    use crate::{
+        actions::waveform::{get_waveform, upsert_waveform},
+        test_support::{connect_test_main_db, seed_fake_tracks},
+    };
+
+    #[tokio::test]
+    async fn test_store_waveform() -> Result<()> {
+        let db = connect_test_main_db().await?;
+        let file_ids = seed_fake_tracks(&db, 2).await?;
+
+        assert_eq!(get_waveform(&db, file_ids[0]).await?, None);
+
+        // Peaks are kept to 1/127, these survive the round trip exactly
+        let waveform = Waveform {
+            peaks: vec![(-1.0, 1.0), (-64.0 / 127.0, 32.0 / 127.0), (0.0, 0.0)],
+        };
+        upsert_waveform(&db, file_ids[0], Some(&waveform)).await?;
+        assert_eq!(get_waveform(&db, file_ids[0]).await?, Some(waveform));
+
+        // Analyzed again without a waveform
+        upsert_waveform(&db, file_ids[0], None).await?;
+        assert_eq!(
+            get_waveform(&db, file_ids[0]).await?,
+            Some(Waveform::default())
+        );
+        assert_eq!(get_waveform(&db, file_ids[1]).await?, None);
+
+        Ok(())
+    }
+}
@@ -21,7 +21,9 @@ use windows::Win32::Storage::FileSystem::{SetFileAttributesW, FILE_ATTRIBUTE_HID
 use migration::Migrator;
 use migration::MigratorTrait;
 
+use crate::actions::groups::upgrade_collection_groups;
 use crate::actions::mixes::initialize_mix_queries;
+use crate::actions::search::upgrade_search_index;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum StorageMode {
@@ -181,6 +183,8 @@ pub async fn connect_main_db(
 pub async fn initialize_db(conn: &sea_orm::DatabaseConnection, node_id: &str) -> Result<()> {
     Migrator::up(conn, None).await?;
     initialize_mix_queries(conn, node_id).await?;
+    upgrade_search_index(conn).await?;
+    upgrade_collection_groups(conn).await?;
     Ok(())
 }
 
@@ -24,6 +24,11 @@ pub struct Model {
     pub updated_at_hlc_ver: i32,
     #[sea_orm(column_type = "Text")]
     pub updated_at_hlc_nid: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[serde(default)]
+    pub album_artist: Option<String>,
+    #[serde(default)]
+    pub is_compilation: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
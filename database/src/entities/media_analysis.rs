@@ -1,8 +1,9 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "media_analysis")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -69,6 +70,21 @@ pub struct Model {
     pub mfcc10: Option<Decimal>,
     pub mfcc11: Option<Decimal>,
     pub mfcc12: Option<Decimal>,
+    pub bpm: Option<Decimal>,
+    pub feature_version: Option<i32>,
+    pub key: Option<i32>,
+    pub mode: Option<i32>,
+    pub integrated_loudness: Option<Decimal>,
+    pub true_peak: Option<Decimal>,
+    pub loudness_range: Option<Decimal>,
+    pub octave_band0: Option<Decimal>,
+    pub octave_band1: Option<Decimal>,
+    pub octave_band2: Option<Decimal>,
+    pub octave_band3: Option<Decimal>,
+    pub octave_band4: Option<Decimal>,
+    pub octave_band5: Option<Decimal>,
+    pub octave_band6: Option<Decimal>,
+    pub crest_factor: Option<Decimal>,
     pub hlc_uuid: String,
     #[sea_orm(column_type = "Text")]
     pub created_at_hlc_ts: String,
@@ -13,6 +13,7 @@ pub struct Model {
     #[sea_orm(column_type = "Blob")]
     pub binary: Vec<u8>,
     pub primary_color: Option<i32>,
+    pub perceptual_hash: Option<i64>,
     pub hlc_uuid: String,
     #[sea_orm(column_type = "Text")]
     pub created_at_hlc_ts: String,
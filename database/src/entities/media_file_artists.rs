@@ -10,6 +10,8 @@ pub struct Model {
     pub id: i32,
     pub media_file_id: i32,
     pub artist_id: i32,
+    /// `main`, `featured`, `composer` or `remixer`.
+    pub role: String,
     #[sea_orm(column_type = "Text")]
     pub hlc_uuid: String,
     #[sea_orm(column_type = "Text")]
@@ -1,8 +1,9 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "media_file_stats")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -13,6 +14,13 @@ pub struct Model {
     pub played_through: i32,
     #[sea_orm(column_type = "Text")]
     pub updated_at: String,
+    pub rating: Option<i32>,
+    pub start_offset_ms: Option<i32>,
+    pub end_offset_ms: Option<i32>,
+    pub hidden: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_error: Option<String>,
+    pub last_error_at: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
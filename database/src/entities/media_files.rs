@@ -32,6 +32,8 @@ pub struct Model {
     pub updated_at_hlc_ver: i32,
     #[sea_orm(column_type = "Text")]
     pub updated_at_hlc_nid: String,
+    /// `None` for files under the library root, see `library_sources`.
+    pub source_id: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -56,6 +58,8 @@ pub enum Relation {
     MediaFileStats,
     #[sea_orm(has_many = "super::media_metadata::Entity")]
     MediaMetadata,
+    #[sea_orm(has_many = "super::play_history::Entity")]
+    PlayHistory,
 }
 
 impl Related<super::media_analysis::Entity> for Entity {
@@ -100,4 +104,10 @@ impl Related<super::media_metadata::Entity> for Entity {
     }
 }
 
+impl Related<super::play_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayHistory.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
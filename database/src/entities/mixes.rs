@@ -25,6 +25,8 @@ pub struct Model {
     pub updated_at_hlc_ver: i32,
     #[sea_orm(column_type = "Text")]
     pub updated_at_hlc_nid: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub cover: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
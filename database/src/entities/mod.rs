@@ -4,7 +4,9 @@ pub mod prelude;
 
 pub mod albums;
 pub mod artists;
+pub mod deleted_files;
 pub mod genres;
+pub mod library_sources;
 pub mod log;
 pub mod media_analysis;
 pub mod media_cover_art;
@@ -13,13 +15,23 @@ pub mod media_file_artists;
 pub mod media_file_fingerprint;
 pub mod media_file_genres;
 pub mod media_file_playlists;
+pub mod media_file_seek_table;
 pub mod media_file_similarity;
 pub mod media_file_stats;
+pub mod media_file_waveform;
 pub mod media_files;
+pub mod media_lyrics;
 pub mod media_metadata;
+pub mod media_raw_tags;
+pub mod media_summaries;
 pub mod mix_queries;
 pub mod mixes;
+pub mod play_history;
 pub mod playback_queue;
+pub mod playlist_mirrors;
 pub mod playlists;
 pub mod search_index;
+pub mod settings;
+pub mod smart_playlists;
 pub mod sync_record;
+pub mod task_checkpoints;
@@ -2,7 +2,9 @@
 
 pub use super::albums::Entity as Albums;
 pub use super::artists::Entity as Artists;
+pub use super::deleted_files::Entity as DeletedFiles;
 pub use super::genres::Entity as Genres;
+pub use super::library_sources::Entity as LibrarySources;
 pub use super::log::Entity as Log;
 pub use super::media_analysis::Entity as MediaAnalysis;
 pub use super::media_cover_art::Entity as MediaCoverArt;
@@ -11,12 +13,22 @@ pub use super::media_file_artists::Entity as MediaFileArtists;
 pub use super::media_file_fingerprint::Entity as MediaFileFingerprint;
 pub use super::media_file_genres::Entity as MediaFileGenres;
 pub use super::media_file_playlists::Entity as MediaFilePlaylists;
+pub use super::media_file_seek_table::Entity as MediaFileSeekTable;
 pub use super::media_file_similarity::Entity as MediaFileSimilarity;
 pub use super::media_file_stats::Entity as MediaFileStats;
+pub use super::media_file_waveform::Entity as MediaFileWaveform;
 pub use super::media_files::Entity as MediaFiles;
+pub use super::media_lyrics::Entity as MediaLyrics;
 pub use super::media_metadata::Entity as MediaMetadata;
+pub use super::media_raw_tags::Entity as MediaRawTags;
+pub use super::media_summaries::Entity as MediaSummaries;
 pub use super::mix_queries::Entity as MixQueries;
 pub use super::mixes::Entity as Mixes;
+pub use super::play_history::Entity as PlayHistory;
 pub use super::playback_queue::Entity as PlaybackQueue;
+pub use super::playlist_mirrors::Entity as PlaylistMirrors;
 pub use super::playlists::Entity as Playlists;
 pub use super::search_index::Entity as SearchIndex;
+pub use super::settings::Entity as Settings;
+pub use super::smart_playlists::Entity as SmartPlaylists;
+pub use super::task_checkpoints::Entity as TaskCheckpoints;
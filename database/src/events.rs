@@ -0,0 +1,104 @@
+//! Events the library subsystems publish to each other.
+//!
+//! Producers such as the file watcher and the analyzer publish what they
+//! did on an [`EventBus`], and the search indexer, the recommendation index,
+//! the cover art scanner and the hub subscribe to what they care about,
+//! instead of the producers calling into each of them.
+
+use log::warn;
+use tokio::sync::broadcast::{self, Receiver, Sender, error::RecvError};
+use tokio_util::sync::CancellationToken;
+
+use ::playback::player::{PlaybackState, PlayingItem};
+
+/// Events kept for subscribers that fall behind, older ones are dropped.
+const EVENT_BUS_CAPACITY: usize = 256;
+
+/// A stage of a library scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanStage {
+    Files,
+    CoverArts,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LibraryEvent {
+    /// A batch of a scan finished. `total` is `0` while it isn't known.
+    ScanProgress {
+        path: String,
+        stage: ScanStage,
+        processed: usize,
+        total: usize,
+    },
+    /// Files were added to or removed from the library. The metadata of the
+    /// updated files is stored, but they aren't indexed yet.
+    LibraryChanged { updated: Vec<i32>, removed: usize },
+    /// The analysis of the files finished and was stored.
+    AnalysisFinished { file_ids: Vec<i32> },
+    /// The player started, paused or stopped, or moved to another item.
+    PlaybackStateChanged {
+        item: Option<PlayingItem>,
+        state: PlaybackState,
+    },
+    /// The playback queue changed. `file_ids` are the library tracks in it
+    /// from the current one on, followed by the ones before it.
+    TracksQueued { file_ids: Vec<i32> },
+    /// A playlist was created, renamed or removed, or its items changed.
+    PlaylistUpdated { playlist_id: i32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: Sender<LibraryEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(EVENT_BUS_CAPACITY)
+    }
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Send the event to every current subscriber. Nobody listening is fine.
+    pub fn publish(&self, event: LibraryEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive the events published from now on.
+    pub fn subscribe(&self) -> Receiver<LibraryEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// Wait for the next events, returning everything queued at once so
+/// subscribers can handle a burst in one go. `None` once the token is
+/// cancelled or every publisher is gone.
+pub async fn next_events(
+    receiver: &mut Receiver<LibraryEvent>,
+    cancel_token: &CancellationToken,
+) -> Option<Vec<LibraryEvent>> {
+    let first = loop {
+        let event = tokio::select! {
+            _ = cancel_token.cancelled() => return None,
+            event = receiver.recv() => event,
+        };
+
+        match event {
+            Ok(event) => break event,
+            Err(RecvError::Lagged(skipped)) => warn!("Event subscriber skipped {skipped} events"),
+            Err(RecvError::Closed) => return None,
+        }
+    };
+
+    let mut events = vec![first];
+    while let Ok(event) = receiver.try_recv() {
+        events.push(event);
+    }
+
+    Some(events)
+}
@@ -1,5 +1,10 @@
 pub mod actions;
 pub mod connection;
 pub mod entities;
+pub mod events;
 pub mod playing_item;
+pub mod progress;
+pub mod query_profile;
 pub mod sync;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
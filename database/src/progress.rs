@@ -0,0 +1,131 @@
+//! Estimates of the time left in long running tasks like scans and analysis.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Weight of the newest measurement in the moving average of the throughput.
+const SMOOTHING: f64 = 0.3;
+
+/// Progress reported sooner than this after the last measurement is folded
+/// into the next one, so bursts of tiny batches don't skew the throughput.
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+struct EtaState {
+    sampled_at: Instant,
+    sampled_processed: usize,
+    /// Items per second, `None` until the first measurement.
+    throughput: Option<f64>,
+}
+
+/// Estimates the time left in a task from an exponential moving average of
+/// its throughput. Shared by the progress callbacks of a task, which may be
+/// called from several threads.
+#[derive(Debug)]
+pub struct ProgressEta {
+    state: Mutex<EtaState>,
+}
+
+impl Default for ProgressEta {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressEta {
+    /// Start measuring, the task is assumed to start now.
+    pub fn new() -> Self {
+        Self::started_at(Instant::now())
+    }
+
+    /// Start measuring, the task is assumed to start at `now`.
+    pub fn started_at(now: Instant) -> Self {
+        Self {
+            state: Mutex::new(EtaState {
+                sampled_at: now,
+                sampled_processed: 0,
+                throughput: None,
+            }),
+        }
+    }
+
+    /// Record that `processed` of `total` items are done, and estimate the
+    /// time left. `None` while the throughput is unknown or if `total` is.
+    pub fn update(&self, processed: usize, total: usize) -> Option<Duration> {
+        self.update_at(processed, total, Instant::now())
+    }
+
+    /// Like [`ProgressEta::update`], measured at `now`.
+    pub fn update_at(&self, processed: usize, total: usize, now: Instant) -> Option<Duration> {
+        if total == 0 {
+            return None;
+        }
+        if processed >= total {
+            return Some(Duration::ZERO);
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        let elapsed = now.saturating_duration_since(state.sampled_at);
+        if elapsed >= MIN_SAMPLE_INTERVAL && processed >= state.sampled_processed {
+            let rate = (processed - state.sampled_processed) as f64 / elapsed.as_secs_f64();
+            state.throughput = Some(match state.throughput {
+                Some(throughput) => SMOOTHING * rate + (1.0 - SMOOTHING) * throughput,
+                None => rate,
+            });
+            state.sampled_at = now;
+            state.sampled_processed = processed;
+        }
+
+        match state.throughput {
+            Some(throughput) if throughput > 0.0 => {
+                Duration::try_from_secs_f64((total - processed) as f64 / throughput).ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::progress::ProgressEta;
+
+    #[test]
+    fn test_eta_follows_the_throughput() {
+        let start = Instant::now();
+        let eta = ProgressEta::started_at(start);
+
+        assert_eq!(eta.update_at(10, 0, start), None, "the total is unknown");
+        assert_eq!(
+            eta.update_at(0, 100, start),
+            None,
+            "the throughput is unknown"
+        );
+
+        // 10 items per second, 90 left
+        assert_eq!(
+            eta.update_at(10, 100, start + Duration::from_secs(1)),
+            Some(Duration::from_secs(9))
+        );
+
+        // Updates right after a measurement reuse its throughput
+        assert_eq!(
+            eta.update_at(20, 100, start + Duration::from_millis(1100)),
+            Some(Duration::from_secs(8))
+        );
+
+        // Halving the speed brings the average down, but not all the way
+        let slower = eta
+            .update_at(20, 100, start + Duration::from_secs(3))
+            .unwrap();
+        assert!(slower > Duration::from_secs(8));
+        assert!(slower < Duration::from_secs(16));
+
+        assert_eq!(
+            eta.update_at(100, 100, start + Duration::from_secs(4)),
+            Some(Duration::ZERO)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Statistics of the SQL statements run on a connection.
+//!
+//! Statements are grouped by their SQL text, with the bound values left
+//! out, so a query run once per file shows up as a single entry. With a
+//! slow threshold, statements that take longer are logged and their slowest
+//! run is kept, so its plan can be looked at afterwards.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::warn;
+use sea_orm::{DatabaseConnection, Statement};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryStats {
+    pub sql: String,
+    pub count: usize,
+    pub failed: usize,
+    pub total: Duration,
+    pub max: Duration,
+    /// Runs that took longer than the slow threshold.
+    pub slow: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct QueryProfile {
+    stats: Mutex<HashMap<String, QueryStats>>,
+    slow_threshold: Option<Duration>,
+    /// The slowest run of every slow statement, with its values.
+    slow_runs: Mutex<HashMap<String, (Duration, Statement)>>,
+}
+
+impl QueryProfile {
+    fn record(&self, statement: &Statement, elapsed: Duration, failed: bool) {
+        let sql = &statement.sql;
+        let slow = self.slow_threshold.is_some_and(|x| elapsed >= x);
+
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(sql.to_owned()).or_insert_with(|| QueryStats {
+            sql: sql.to_owned(),
+            ..Default::default()
+        });
+
+        entry.count += 1;
+        entry.total += elapsed;
+        entry.max = entry.max.max(elapsed);
+        if failed {
+            entry.failed += 1;
+        }
+        if !slow {
+            return;
+        }
+        entry.slow += 1;
+        drop(stats);
+
+        warn!("Slow SQL statement took {elapsed:?}: {sql}");
+        let mut slow_runs = self.slow_runs.lock().unwrap();
+        match slow_runs.get(sql) {
+            Some((slowest, _)) if *slowest >= elapsed => {}
+            _ => {
+                slow_runs.insert(sql.to_owned(), (elapsed, statement.clone()));
+            }
+        }
+    }
+
+    /// The slowest run of every statement that went over the slow
+    /// threshold, the slowest first.
+    pub fn slow_statements(&self) -> Vec<Statement> {
+        let mut runs: Vec<(Duration, Statement)> =
+            self.slow_runs.lock().unwrap().values().cloned().collect();
+        runs.sort_by(|a, b| b.0.cmp(&a.0));
+        runs.into_iter().map(|(_, statement)| statement).collect()
+    }
+
+    /// The `limit` statements that took the longest in total.
+    pub fn slowest(&self, limit: usize) -> Vec<QueryStats> {
+        let mut stats: Vec<QueryStats> = self.stats.lock().unwrap().values().cloned().collect();
+        stats.sort_by(|a, b| b.total.cmp(&a.total));
+        stats.truncate(limit);
+        stats
+    }
+
+    /// Number of statements run and the time spent in them.
+    pub fn summary(&self) -> (usize, Duration) {
+        let stats = self.stats.lock().unwrap();
+        (
+            stats.values().map(|x| x.count).sum(),
+            stats.values().map(|x| x.total).sum(),
+        )
+    }
+}
+
+/// Start recording every statement run on `db`, logging the ones that take
+/// at least `slow_threshold`.
+pub fn attach_query_profile(
+    db: &mut DatabaseConnection,
+    slow_threshold: Option<Duration>,
+) -> Arc<QueryProfile> {
+    let profile = Arc::new(QueryProfile {
+        slow_threshold,
+        ..Default::default()
+    });
+
+    let recorder = Arc::clone(&profile);
+    db.set_metric_callback(move |info| {
+        recorder.record(info.statement, info.elapsed, info.failed);
+    });
+
+    profile
+}
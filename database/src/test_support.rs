@@ -0,0 +1,213 @@
+//! Fixtures for testing database actions without a real library.
+//!
+//! Every connection is a private in-memory SQLite database with all
+//! migrations applied, so tests can run in parallel. The seed helpers insert
+//! fake tracks the same way the scanner does, and index them into artists,
+//! albums and genres.
+
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::prelude::FromPrimitive;
+use sea_orm::entity::prelude::*;
+use sea_orm::{ActiveValue, ConnectOptions, Database};
+use seq_macro::seq;
+use uuid::Uuid;
+
+use analysis::analysis::ANALYSIS_FEATURE_VERSION;
+
+use crate::actions::collection::CollectionQueryType;
+use crate::actions::index::index_media_files;
+use crate::actions::search::add_term;
+use crate::actions::summaries::refresh_media_summaries;
+use crate::connection::{MainDbConnection, initialize_db};
+use crate::entities::{media_analysis, media_files, media_metadata};
+
+/// Node ID of every test database.
+pub const TEST_NODE_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// How many fake tracks share an album.
+pub const TRACKS_PER_ALBUM: usize = 10;
+
+const FAKE_ARTISTS: [&str; 5] = ["Aurora", "Boreal", "Cirrus", "Dusk", "Ember"];
+const FAKE_GENRES: [&str; 4] = ["Ambient", "Electronic", "Jazz", "Rock"];
+
+/// Create an empty in-memory main database with all migrations applied.
+pub async fn connect_test_main_db() -> Result<MainDbConnection> {
+    // Every connection of the pool has to see the same database, so the
+    // shared cache is used with a unique name per fixture
+    let db_url = format!(
+        "sqlite:file:test-main-{}?mode=memory&cache=shared",
+        Uuid::new_v4()
+    );
+
+    let mut options = ConnectOptions::new(&db_url);
+    options.sqlx_logging(false);
+
+    let db = Database::connect(options).await?;
+    initialize_db(&db, TEST_NODE_ID)
+        .await
+        .with_context(|| "Failed to initialize test database")?;
+
+    Ok(db)
+}
+
+/// Metadata of a fake track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeTrack {
+    pub file_name: String,
+    pub directory: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub genre: String,
+    pub track_number: usize,
+    /// Duration in seconds.
+    pub duration: f64,
+}
+
+impl FakeTrack {
+    /// The `index`-th fake track. Artists and genres rotate, and every
+    /// `TRACKS_PER_ALBUM` consecutive tracks form an album.
+    pub fn nth(index: usize) -> Self {
+        let album_index = index / TRACKS_PER_ALBUM;
+        let artist = FAKE_ARTISTS[album_index % FAKE_ARTISTS.len()];
+
+        FakeTrack {
+            file_name: format!("{index:04}.flac"),
+            directory: format!("{artist}/Album {album_index}"),
+            title: format!("Track {index}"),
+            artist: artist.to_string(),
+            album: format!("Album {album_index}"),
+            genre: FAKE_GENRES[index % FAKE_GENRES.len()].to_string(),
+            track_number: index % TRACKS_PER_ALBUM + 1,
+            duration: 120.0 + (index % 7) as f64 * 30.0,
+        }
+    }
+}
+
+/// Insert the given tracks and index them, returning the new file IDs in
+/// the same order.
+pub async fn seed_tracks(main_db: &DatabaseConnection, tracks: &[FakeTrack]) -> Result<Vec<i32>> {
+    let mut file_ids = Vec::with_capacity(tracks.len());
+
+    for track in tracks {
+        let new_file = media_files::ActiveModel {
+            file_name: ActiveValue::Set(track.file_name.clone()),
+            directory: ActiveValue::Set(track.directory.clone()),
+            extension: ActiveValue::Set("flac".to_string()),
+            file_hash: ActiveValue::Set(format!("{:x}", Uuid::new_v4().as_u128())),
+            sample_rate: ActiveValue::Set(44100),
+            duration: ActiveValue::Set(
+                Decimal::from_f64(track.duration).with_context(|| "Invalid track duration")?,
+            ),
+            last_modified: ActiveValue::Set("1704067200".to_string()),
+            ..Default::default()
+        };
+        let file_id = media_files::Entity::insert(new_file)
+            .exec(main_db)
+            .await
+            .with_context(|| format!("Failed to seed file: {}", track.file_name))?
+            .last_insert_id;
+
+        let metadata = [
+            ("track_title", track.title.clone()),
+            ("artist", track.artist.clone()),
+            ("album", track.album.clone()),
+            ("genre", track.genre.clone()),
+            ("track_number", track.track_number.to_string()),
+        ];
+        media_metadata::Entity::insert_many(metadata.into_iter().map(|(key, value)| {
+            media_metadata::ActiveModel {
+                file_id: ActiveValue::Set(file_id),
+                meta_key: ActiveValue::Set(key.to_string()),
+                meta_value: ActiveValue::Set(value),
+                ..Default::default()
+            }
+        }))
+        .exec(main_db)
+        .await
+        .with_context(|| format!("Failed to seed metadata: {}", track.file_name))?;
+
+        add_term(main_db, CollectionQueryType::Track, file_id, &track.title).await?;
+
+        file_ids.push(file_id);
+    }
+
+    refresh_media_summaries(main_db, &file_ids).await?;
+    index_media_files(main_db, file_ids.clone(), None).await?;
+
+    Ok(file_ids)
+}
+
+/// Insert `count` fake tracks, see [`FakeTrack::nth`].
+pub async fn seed_fake_tracks(main_db: &DatabaseConnection, count: usize) -> Result<Vec<i32>> {
+    let tracks: Vec<FakeTrack> = (0..count).map(FakeTrack::nth).collect();
+    seed_tracks(main_db, &tracks).await
+}
+
+/// Insert a deterministic, randomly filled analysis result for a file.
+pub async fn seed_fake_analysis(main_db: &DatabaseConnection, file_id: i32) -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(file_id as u64);
+    let mut value = |min: f32, max: f32| Decimal::from_f32(rng.gen_range(min..max));
+
+    let mut analysis = media_analysis::ActiveModel {
+        file_id: ActiveValue::Set(file_id),
+        rms: ActiveValue::Set(value(0.0, 0.5)),
+        zcr: ActiveValue::Set(value(0.0, 0.3)),
+        energy: ActiveValue::Set(value(0.0, 1.0)),
+        spectral_centroid: ActiveValue::Set(value(0.0, 1.0)),
+        spectral_flatness: ActiveValue::Set(value(0.0, 1.0)),
+        spectral_slope: ActiveValue::Set(value(0.0, 1.0)),
+        spectral_rolloff: ActiveValue::Set(value(0.0, 1.0)),
+        spectral_spread: ActiveValue::Set(value(0.0, 1.0)),
+        spectral_skewness: ActiveValue::Set(value(0.0, 1.0)),
+        spectral_kurtosis: ActiveValue::Set(value(0.0, 1.0)),
+        perceptual_spread: ActiveValue::Set(value(0.0, 1.0)),
+        perceptual_sharpness: ActiveValue::Set(value(0.0, 1.0)),
+        bpm: ActiveValue::Set(value(70.0, 180.0)),
+        feature_version: ActiveValue::Set(Some(ANALYSIS_FEATURE_VERSION)),
+        integrated_loudness: ActiveValue::Set(value(-24.0, -6.0)),
+        true_peak: ActiveValue::Set(value(-3.0, 0.0)),
+        loudness_range: ActiveValue::Set(value(2.0, 15.0)),
+        ..Default::default()
+    };
+
+    seq!(N in 0..12 {
+        analysis.chroma~N = ActiveValue::Set(value(0.0, 1.0));
+    });
+
+    seq!(N in 0..24 {
+        analysis.perceptual_loudness~N = ActiveValue::Set(value(0.0, 1.0));
+    });
+
+    seq!(N in 0..13 {
+        analysis.mfcc~N = ActiveValue::Set(value(-1.0, 1.0));
+    });
+
+    analysis.key = ActiveValue::Set(Some(rng.gen_range(0..12)));
+    analysis.mode = ActiveValue::Set(Some(rng.gen_range(0..2)));
+
+    seq!(N in 0..7 {
+        analysis.octave_band~N = ActiveValue::Set(value(0.0, 0.3));
+    });
+    analysis.crest_factor = ActiveValue::Set(value(0.2, 0.7));
+
+    media_analysis::Entity::insert(analysis)
+        .exec(main_db)
+        .await
+        .with_context(|| format!("Failed to seed analysis: {file_id}"))?;
+
+    Ok(())
+}
+
+/// Insert `count` fake tracks together with their analysis results.
+pub async fn seed_fake_library(main_db: &DatabaseConnection, count: usize) -> Result<Vec<i32>> {
+    let file_ids = seed_fake_tracks(main_db, count).await?;
+
+    for file_id in &file_ids {
+        seed_fake_analysis(main_db, *file_id).await?;
+    }
+
+    Ok(file_ids)
+}
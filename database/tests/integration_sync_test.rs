@@ -197,6 +197,7 @@ async fn seed_album(
         updated_at_hlc_ts: Set(hlc.to_rfc3339()?),
         updated_at_hlc_ver: Set(hlc.version as i32),
         updated_at_hlc_nid: Set(hlc.node_id.to_string()),
+        ..Default::default()
     };
     model.insert(db).await.context("Failed to seed album")
 }
@@ -219,6 +220,7 @@ async fn seed_album_with_hlc(
         updated_at_hlc_ts: Set(hlc.to_rfc3339()?),
         updated_at_hlc_ver: Set(hlc.version as i32),
         updated_at_hlc_nid: Set(hlc.node_id.to_string()),
+        ..Default::default()
     };
     model.insert(db).await.context("Failed to seed album")
 }
@@ -236,6 +238,7 @@ async fn seed_cover_art(
         file_hash: Set(file_hash.to_string()),
         binary: Set(binary),
         primary_color: Set(Some(0xAAAAAA)),
+        perceptual_hash: Set(None),
         hlc_uuid: Set(Uuid::new_v4().to_string()),
         created_at_hlc_ts: Set(hlc.to_rfc3339()?),
         created_at_hlc_ver: Set(hlc.version as i32),
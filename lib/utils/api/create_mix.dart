@@ -5,8 +5,9 @@ Future<Mix> createMix(
   String group,
   bool scriptletMode,
   int mode,
-  Iterable<(String, String)> queries,
-) async {
+  Iterable<(String, String)> queries, {
+  String? cover,
+}) async {
   final createRequest = CreateMixRequest(
     name: name,
     group: group.isEmpty ? 'Favorite' : group,
@@ -14,6 +15,7 @@ Future<Mix> createMix(
     mode: mode,
     queries:
         queries.map((x) => MixQuery(operator: x.$1, parameter: x.$2)).toList(),
+    cover: cover,
   );
   createRequest.sendSignalToRust(); // GENERATED
 
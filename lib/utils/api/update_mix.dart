@@ -6,8 +6,9 @@ Future<Mix> updateMix(
   String group,
   bool scriptletMode,
   int mode,
-  Iterable<(String, String)> queries,
-) async {
+  Iterable<(String, String)> queries, {
+  String? cover,
+}) async {
   final updateRequest = UpdateMixRequest(
     mixId: mixId,
     name: name,
@@ -16,6 +17,7 @@ Future<Mix> updateMix(
     mode: mode,
     queries:
         queries.map((x) => MixQuery(operator: x.$1, parameter: x.$2)).toList(),
+    cover: cover,
   );
   updateRequest.sendSignalToRust(); // GENERATED
 
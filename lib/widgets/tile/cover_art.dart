@@ -123,9 +123,17 @@ class _CoverArtState extends State<CoverArt> {
 
         final processedPath = snapshot.data!;
 
+        // Thumbnails baked after scanning sit next to the full sized cover
+        // art, decoding them is much cheaper
+        final thumbnail =
+            cachedSize == null ? null : File('${processedPath}_$cachedSize');
+        final file = thumbnail != null && thumbnail.existsSync()
+            ? thumbnail
+            : File(processedPath);
+
         // Use a standard Image.file widget for the processed path
         return Image.file(
-          File(processedPath),
+          file,
           width: widget.size ?? double.infinity,
           height: widget.size ?? double.infinity,
           fit: BoxFit.cover,
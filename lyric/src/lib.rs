@@ -1,6 +1,7 @@
 pub mod lrc;
 pub mod parser;
 pub mod srt;
+pub mod sylt;
 pub mod ttml;
 pub mod types;
 pub mod utils;
@@ -4,10 +4,33 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 
 use crate::{lrc::parse_lrc, srt::parse_srt, ttml::parse_ttml, types::LyricFile, vtt::parse_vtt};
 
+/// Extensions of the lyric files kept next to audio files, in the order
+/// they are preferred.
+pub const SIDECAR_EXTENSIONS: [&str; 5] = ["ttml", "lrc", "lrcx", "vtt", "srt"];
+
+/// The preferred lyric file kept next to an audio file.
+pub fn find_lyrics_sidecar(path: &Path) -> Option<PathBuf> {
+    SIDECAR_EXTENSIONS.iter().find_map(|extension| {
+        let sidecar = path.with_extension(extension);
+        sidecar.is_file().then_some(sidecar)
+    })
+}
+
+/// Parse lyrics given the extension of the file they came from.
+pub fn parse_lyrics_by_format(format: &str, content: &str) -> Result<LyricFile> {
+    match format {
+        "ttml" => parse_ttml(content),
+        "lrc" | "lrcx" => parse_lrc(content),
+        "vtt" => parse_vtt(content),
+        "srt" => parse_srt(content),
+        _ => bail!("Unsupported lyric format: {format}"),
+    }
+}
+
 pub fn parse_audio_lyrics(path: PathBuf) -> Option<Result<LyricFile>> {
     // Try to find and parse the .ttml file
     if let Some(lyric) = parse_lyrics_with_extension(&path, "ttml", parse_ttml) {
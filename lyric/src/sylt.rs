@@ -0,0 +1,279 @@
+//! Synchronized lyrics stored in the `SYLT` frame of an ID3v2 tag.
+//!
+//! Symphonia skips `SYLT` frames, so the tag at the start of the file is
+//! walked here. Only millisecond timestamps are supported, MPEG frame
+//! timestamps would need the frame rate of the stream.
+
+use std::{fs::File, io::Read, path::Path};
+
+use anyhow::Result;
+
+use crate::types::TimeTag;
+
+const HEADER_SIZE: usize = 10;
+const TIMESTAMP_MILLISECONDS: u8 = 2;
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |acc, x| (acc << 7) | (*x as usize & 0x7f))
+}
+
+/// Drop the `0x00` inserted after every `0xFF` by the unsynchronisation
+/// scheme.
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut previous = 0;
+    for &byte in data {
+        if !(previous == 0xff && byte == 0x00) {
+            result.push(byte);
+        }
+        previous = byte;
+    }
+    result
+}
+
+/// Split a terminated string off the front of `data`, returning the text
+/// and the remaining bytes.
+fn split_text(data: &[u8], encoding: u8) -> Option<(String, &[u8])> {
+    if encoding == 1 || encoding == 2 {
+        let end = data.chunks_exact(2).position(|x| x == [0, 0])? * 2;
+        let units = &data[..end];
+
+        let (big_endian, units) = match units {
+            [0xfe, 0xff, rest @ ..] => (true, rest),
+            [0xff, 0xfe, rest @ ..] => (false, rest),
+            _ => (encoding == 2, units),
+        };
+        let units: Vec<u16> = units
+            .chunks_exact(2)
+            .map(|x| {
+                if big_endian {
+                    u16::from_be_bytes([x[0], x[1]])
+                } else {
+                    u16::from_le_bytes([x[0], x[1]])
+                }
+            })
+            .collect();
+
+        Some((String::from_utf16_lossy(&units), &data[end + 2..]))
+    } else {
+        let end = data.iter().position(|x| *x == 0)?;
+        let text = if encoding == 3 {
+            String::from_utf8_lossy(&data[..end]).into_owned()
+        } else {
+            // ISO-8859-1 maps one to one onto the first code points
+            data[..end].iter().map(|x| *x as char).collect()
+        };
+
+        Some((text, &data[end + 1..]))
+    }
+}
+
+/// Parse the body of a `SYLT` frame into `(milliseconds, text)` pairs.
+fn parse_sylt_frame(data: &[u8]) -> Option<Vec<(u32, String)>> {
+    // Encoding, language, timestamp format and content type
+    if data.len() < 6 || data[4] != TIMESTAMP_MILLISECONDS {
+        return None;
+    }
+    let encoding = data[0];
+
+    let (_descriptor, mut rest) = split_text(&data[6..], encoding)?;
+
+    let mut entries = Vec::new();
+    while !rest.is_empty() {
+        let Some((text, after_text)) = split_text(rest, encoding) else {
+            break;
+        };
+        let Some(timestamp) = after_text.get(..4) else {
+            break;
+        };
+
+        entries.push((
+            u32::from_be_bytes([timestamp[0], timestamp[1], timestamp[2], timestamp[3]]),
+            text,
+        ));
+        rest = &after_text[4..];
+    }
+
+    Some(entries)
+}
+
+/// Find the first usable `SYLT` frame of an ID3v2 tag.
+pub fn parse_sylt_tag(tag: &[u8]) -> Option<Vec<(u32, String)>> {
+    if tag.len() < HEADER_SIZE || &tag[..3] != b"ID3" {
+        return None;
+    }
+
+    let version = tag[3];
+    let flags = tag[5];
+    let size = syncsafe(&tag[6..10]);
+    let body = tag.get(HEADER_SIZE..HEADER_SIZE + size)?;
+
+    // Before ID3v2.4 unsynchronisation applies to the whole tag
+    let body = if flags & 0x80 != 0 && version < 4 {
+        resynchronise(body)
+    } else {
+        body.to_vec()
+    };
+
+    let mut position = 0;
+    if flags & 0x40 != 0 {
+        let extended_size = match version {
+            4 => syncsafe(body.get(..4)?),
+            _ => u32::from_be_bytes(body.get(..4)?.try_into().ok()?) as usize + 4,
+        };
+        position += extended_size;
+    }
+
+    // ID3v2.2 uses three character frame IDs and can't hold a `SYLT` frame
+    if version < 3 {
+        return None;
+    }
+
+    while position + HEADER_SIZE <= body.len() {
+        let header = &body[position..position + HEADER_SIZE];
+        if header[0] == 0 {
+            // Padding
+            break;
+        }
+
+        let frame_size = match version {
+            4 => syncsafe(&header[4..8]),
+            _ => u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize,
+        };
+        let start = position + HEADER_SIZE;
+        let frame = body.get(start..start + frame_size)?;
+        position = start + frame_size;
+
+        if &header[..4] != b"SYLT" {
+            continue;
+        }
+
+        let frame = if version == 4 && header[9] & 0x02 != 0 {
+            resynchronise(frame)
+        } else {
+            frame.to_vec()
+        };
+
+        if let Some(entries) = parse_sylt_frame(&frame) {
+            return Some(entries);
+        }
+    }
+
+    None
+}
+
+/// Render synchronized lyrics as LRC, so they can be stored and parsed like
+/// any other lyrics.
+pub fn sylt_to_lrc(entries: &[(u32, String)]) -> String {
+    entries
+        .iter()
+        .map(|(time, text)| {
+            let tag = TimeTag {
+                minutes: time / 60_000,
+                seconds: time / 1000 % 60,
+                milliseconds: time % 1000,
+            };
+            // Lines often start with the line break of the previous one
+            format!("{tag}{}\n", text.trim_matches(['\r', '\n']))
+        })
+        .collect()
+}
+
+/// Read the `SYLT` lyrics of a file as LRC.
+pub fn read_sylt_lyrics<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    let mut file = File::open(path)?;
+
+    let mut header = [0u8; HEADER_SIZE];
+    if file.read_exact(&mut header).is_err() || &header[..3] != b"ID3" {
+        return Ok(None);
+    }
+
+    let mut tag = header.to_vec();
+    tag.resize(HEADER_SIZE + syncsafe(&header[6..10]), 0);
+    file.read_exact(&mut tag[HEADER_SIZE..])?;
+
+    Ok(parse_sylt_tag(&tag).map(|x| sylt_to_lrc(&x)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id3v23_tag(frames: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (id, data) in frames {
+            body.extend_from_slice(*id);
+            body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            body.extend_from_slice(&[0, 0]);
+            body.extend_from_slice(data);
+        }
+        // Padding
+        body.extend_from_slice(&[0; 16]);
+
+        let size = body.len();
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend_from_slice(&[
+            (size >> 21) as u8 & 0x7f,
+            (size >> 14) as u8 & 0x7f,
+            (size >> 7) as u8 & 0x7f,
+            size as u8 & 0x7f,
+        ]);
+        tag.extend(body);
+        tag
+    }
+
+    fn sylt_frame(encoding: u8, lines: &[(&str, u32)]) -> Vec<u8> {
+        let mut frame = vec![encoding, b'e', b'n', b'g', TIMESTAMP_MILLISECONDS, 1];
+        frame.push(0);
+        for (text, time) in lines {
+            frame.extend_from_slice(text.as_bytes());
+            frame.push(0);
+            frame.extend_from_slice(&time.to_be_bytes());
+        }
+        frame
+    }
+
+    #[test]
+    fn test_parse_sylt_tag() {
+        let tag = id3v23_tag(&[
+            (b"TIT2", b"\x03Title\x00".to_vec()),
+            (
+                b"SYLT",
+                sylt_frame(3, &[("First", 1500), ("\nSecond", 62_340)]),
+            ),
+        ]);
+
+        let entries = parse_sylt_tag(&tag).unwrap();
+        assert_eq!(
+            entries,
+            vec![(1500, "First".to_string()), (62_340, "\nSecond".to_string())]
+        );
+        assert_eq!(
+            sylt_to_lrc(&entries),
+            "[00:01.50]First\n[01:02.34]Second\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_sylt_utf16() {
+        let mut frame = vec![1, b'e', b'n', b'g', TIMESTAMP_MILLISECONDS, 1, 0, 0];
+        frame.extend_from_slice(&[0xff, 0xfe]);
+        for unit in "歌".encode_utf16() {
+            frame.extend_from_slice(&unit.to_le_bytes());
+        }
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(&250u32.to_be_bytes());
+
+        let tag = id3v23_tag(&[(b"SYLT", frame)]);
+        assert_eq!(parse_sylt_tag(&tag).unwrap(), vec![(250, "歌".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_sylt_without_frame() {
+        let tag = id3v23_tag(&[(b"TIT2", b"\x03Title\x00".to_vec())]);
+        assert!(parse_sylt_tag(&tag).is_none());
+        assert!(parse_sylt_tag(b"not a tag").is_none());
+    }
+}
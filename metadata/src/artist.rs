@@ -1,65 +1,139 @@
+use std::fmt;
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use crate::splitting::SplittingRules;
+
 lazy_static! {
-    static ref SPLITTERS: Vec<&'static str> =
-        vec![", ", "; ", " × ", " x ", " / ", " ft.", " ft. ", " feat. ", " & "];
-    static ref WHITELIST: Vec<&'static str> = vec![];
-    static ref SPLITTERS_REGEX: Regex = {
-        let splitters_pattern = SPLITTERS
-            .iter()
-            .map(|s| regex::escape(s))
-            .collect::<Vec<String>>()
-            .join("|");
-        Regex::new(&splitters_pattern).unwrap()
-    };
+    /// Where the featured artists start, like `A feat. B` or `A (ft. B)`.
+    static ref FEATURING_REGEX: Regex =
+        Regex::new(r"(?i)\s+[(\[]?(?:feat\.?|ft\.?|featuring)\s+").unwrap();
+    /// The remixer in a title, like `Song (B Remix)`.
+    static ref REMIX_REGEX: Regex =
+        Regex::new(r"(?i)[(\[]\s*([^()\[\]]+?)\s+(?:remix|rmx)\s*[)\]]").unwrap();
 }
 
-pub fn split_artists(input: &str) -> Vec<String> {
-    let parts_with_splitters: Vec<&str> = SPLITTERS_REGEX.split(input).collect();
-    let mut parts: Vec<String> = Vec::new();
-    let mut start = 0; // Start index in characters
+/// What an artist did on a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtistRole {
+    Main,
+    Featured,
+    Composer,
+    Remixer,
+}
 
-    for i in 0..parts_with_splitters.len() {
-        let part = parts_with_splitters[i].trim();
-        if !part.is_empty() {
-            parts.push(part.to_string());
-        }
+impl ArtistRole {
+    pub const ALL: [ArtistRole; 4] = [
+        ArtistRole::Main,
+        ArtistRole::Featured,
+        ArtistRole::Composer,
+        ArtistRole::Remixer,
+    ];
 
-        // Add the splitter back if it's not the last part
-        if i < parts_with_splitters.len() - 1 {
-            // Find the splitter in the remaining part of the input
-            if let Some(mat) = SPLITTERS_REGEX.find(&input[start..]) {
-                let splitter = mat.as_str();
-                parts.push(splitter.to_string());
-                // Update the start index to the end of the matched splitter
-                start += mat.end();
-            }
+    /// The name stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArtistRole::Main => "main",
+            ArtistRole::Featured => "featured",
+            ArtistRole::Composer => "composer",
+            ArtistRole::Remixer => "remixer",
         }
     }
 
-    let mut i = 0;
-    while i < parts.len() {
-        if WHITELIST.contains(&parts[i].as_str()) {
-            i += 1;
-            continue;
+    pub fn parse(x: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|role| role.as_str().eq_ignore_ascii_case(x.trim()))
+    }
+}
+
+impl fmt::Display for ArtistRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An artist credited on a track.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArtistCredit {
+    pub name: String,
+    pub role: ArtistRole,
+}
+
+/// Split an artist tag into its artists by the delimiters of `rules`.
+pub fn split_artists(input: &str, rules: &SplittingRules) -> Vec<String> {
+    rules
+        .split(input, &rules.artist_delimiters)
+        .into_iter()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Split an artist tag into the main and the featured artists, so
+/// `A & B feat. C` gives `[A, B]` and `[C]`.
+pub fn split_featured_artists(input: &str, rules: &SplittingRules) -> (Vec<String>, Vec<String>) {
+    match FEATURING_REGEX.find(input) {
+        Some(mat) => {
+            let featured = input[mat.end()..].trim().trim_end_matches([')', ']']);
+            (
+                split_artists(&input[..mat.start()], rules),
+                split_artists(featured, rules),
+            )
         }
-        for j in (i + 1)..parts.len() {
-            if SPLITTERS.contains(&parts[j].as_str()) {
-                continue;
-            }
-            let combined = parts[i..=j].join("");
-            if WHITELIST.contains(&combined.as_str()) {
-                parts[i] = combined;
-                parts.drain((i + 1)..=j);
-                break;
+        None => (split_artists(input, rules), Vec::new()),
+    }
+}
+
+/// The remixer named in a title like `Song (B Remix)`.
+pub fn remixer_from_title(title: &str) -> Option<String> {
+    REMIX_REGEX
+        .captures(title)
+        .map(|x| x[1].trim().to_string())
+        .filter(|x| !x.is_empty())
+}
+
+/// Everyone credited on a track, split by `rules`. The remixer tag wins over
+/// the title, and an artist is listed once per role, main artists aren't
+/// featured again.
+pub fn artist_credits(
+    artist: &str,
+    title: &str,
+    composer: Option<&str>,
+    remixer: Option<&str>,
+    rules: &SplittingRules,
+) -> Vec<ArtistCredit> {
+    let (main, featured) = split_featured_artists(artist, rules);
+    let composers = composer
+        .map(|x| split_artists(x, rules))
+        .unwrap_or_default();
+    let remixers = match remixer {
+        Some(remixer) if !remixer.trim().is_empty() => split_artists(remixer, rules),
+        _ => remixer_from_title(title)
+            .map(|x| split_artists(&x, rules))
+            .unwrap_or_default(),
+    };
+
+    let mut credits: Vec<ArtistCredit> = Vec::new();
+    for (names, role) in [
+        (main, ArtistRole::Main),
+        (featured, ArtistRole::Featured),
+        (composers, ArtistRole::Composer),
+        (remixers, ArtistRole::Remixer),
+    ] {
+        for name in names {
+            let duplicated = credits.iter().any(|x| {
+                x.name == name
+                    && (x.role == role
+                        || (role == ArtistRole::Featured && x.role == ArtistRole::Main))
+            });
+            if !duplicated {
+                credits.push(ArtistCredit { name, role });
             }
         }
-        i += 1;
     }
 
-    parts
-        .into_iter()
-        .filter(|s| !SPLITTERS.contains(&s.as_str()))
-        .collect()
+    credits
 }
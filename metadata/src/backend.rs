@@ -0,0 +1,201 @@
+//! How files are hashed and their tags read while scanning. Platforms where
+//! reading whole files is slow, or where Symphonia misses tags, can swap in
+//! another implementation, and tests can inject fakes.
+
+use std::{
+    fmt,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    process::Command,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail};
+
+use ::fsio::{FsIo, FsNode};
+
+use crate::crc::media_crc32;
+use crate::reader::get_metadata;
+
+const CHUNK_SIZE: usize = 1024 * 400;
+
+/// Bytes read from each end of a file by `PartialHasher`.
+const PARTIAL_HASH_SIZE: u64 = 1024 * 64;
+
+pub trait FileHasher: Send + Sync {
+    /// The hash stored with the file, used to tell whether it changed and
+    /// to find files with the same content.
+    fn hash(&self, fsio: &FsIo, path: &Path) -> Result<String>;
+}
+
+pub trait TagReader: Send + Sync {
+    /// The tags of the file, keyed like `reader::standard_tag_key_to_string`.
+    fn read_tags(&self, fs_node: &FsNode) -> Result<Vec<(String, String)>>;
+}
+
+/// CRC32 of the whole file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc32Hasher;
+
+impl FileHasher for Crc32Hasher {
+    fn hash(&self, fsio: &FsIo, path: &Path) -> Result<String> {
+        let file = fsio.open(path, "r")?;
+        let mut reader = BufReader::new(file);
+        let mut buffer = vec![0; CHUNK_SIZE];
+        let mut crc: u32 = 0;
+
+        loop {
+            let bytes_read = reader.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            crc = media_crc32(&buffer, crc, 0, bytes_read);
+        }
+
+        Ok(format!("{crc:08x}"))
+    }
+}
+
+/// CRC32 of the head and tail of the file together with its size. Much
+/// cheaper on large files, at the cost of missing edits in the middle.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PartialHasher;
+
+impl FileHasher for PartialHasher {
+    fn hash(&self, fsio: &FsIo, path: &Path) -> Result<String> {
+        let mut file = fsio.open(path, "r")?;
+        let size = file.seek(SeekFrom::End(0))?;
+
+        let mut buffer = Vec::with_capacity(PARTIAL_HASH_SIZE as usize);
+
+        file.seek(SeekFrom::Start(0))?;
+        (&mut file)
+            .take(PARTIAL_HASH_SIZE)
+            .read_to_end(&mut buffer)?;
+        let mut crc = media_crc32(&buffer, 0, 0, buffer.len());
+
+        if size > PARTIAL_HASH_SIZE * 2 {
+            buffer.clear();
+            file.seek(SeekFrom::End(-(PARTIAL_HASH_SIZE as i64)))?;
+            file.read_to_end(&mut buffer)?;
+            crc = media_crc32(&buffer, crc, 0, buffer.len());
+        }
+
+        Ok(format!("{size:x}-{crc:08x}"))
+    }
+}
+
+/// Tags read by Symphonia, skipping the usual noise fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SymphoniaTagReader;
+
+impl TagReader for SymphoniaTagReader {
+    fn read_tags(&self, fs_node: &FsNode) -> Result<Vec<(String, String)>> {
+        get_metadata(fs_node, None)
+    }
+}
+
+/// The key a tag named by ffprobe is stored with, `None` for tags that
+/// aren't kept.
+fn ffprobe_tag_key(name: &str) -> Option<&'static str> {
+    let key = match name.to_lowercase().as_str() {
+        "title" => "track_title",
+        "artist" => "artist",
+        "album" => "album",
+        "album_artist" | "albumartist" => "album_artist",
+        "composer" => "composer",
+        "date" | "year" => "date",
+        "genre" => "genre",
+        "track" => "track_number",
+        "disc" => "disc_number",
+        "lyrics" => "lyrics",
+        "bpm" | "tbpm" => "bpm",
+        "isrc" => "ident_isrc",
+        "label" | "publisher" => "label",
+        "musicbrainz_trackid" => "musicbrainz_recording_id",
+        "musicbrainz_albumid" => "musicbrainz_album_id",
+        "musicbrainz_artistid" => "musicbrainz_artist_id",
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Tags read by running `ffprobe`, for formats Symphonia can't read the
+/// tags of.
+#[derive(Debug, Clone)]
+pub struct FfprobeTagReader {
+    pub program: String,
+}
+
+impl Default for FfprobeTagReader {
+    fn default() -> Self {
+        Self {
+            program: "ffprobe".to_string(),
+        }
+    }
+}
+
+impl TagReader for FfprobeTagReader {
+    fn read_tags(&self, fs_node: &FsNode) -> Result<Vec<(String, String)>> {
+        let output = Command::new(&self.program)
+            .args(["-v", "quiet", "-print_format", "json", "-show_format"])
+            .arg(&fs_node.path)
+            .output()
+            .with_context(|| format!("Failed to run {}", self.program))?;
+
+        if !output.status.success() {
+            bail!("{} failed on {:?}", self.program, fs_node.path);
+        }
+
+        parse_ffprobe_tags(&output.stdout)
+    }
+}
+
+pub fn parse_ffprobe_tags(output: &[u8]) -> Result<Vec<(String, String)>> {
+    let value: serde_json::Value = serde_json::from_slice(output)?;
+    let tags = value["format"]["tags"]
+        .as_object()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|(name, value)| {
+                    let key = ffprobe_tag_key(name)?;
+                    let value = match value {
+                        serde_json::Value::String(x) => x.clone(),
+                        x => x.to_string(),
+                    };
+                    Some((key.to_string(), value))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(tags)
+}
+
+/// The hasher and tag reader used while scanning.
+#[derive(Clone)]
+pub struct MetadataBackends {
+    pub hasher: Arc<dyn FileHasher>,
+    pub tag_reader: Arc<dyn TagReader>,
+}
+
+impl MetadataBackends {
+    pub fn new(hasher: Arc<dyn FileHasher>, tag_reader: Arc<dyn TagReader>) -> Self {
+        Self { hasher, tag_reader }
+    }
+}
+
+impl Default for MetadataBackends {
+    /// Changing the default hasher changes the hash of every file, which
+    /// makes the next scan read the whole library again.
+    fn default() -> Self {
+        Self::new(Arc::new(Crc32Hasher), Arc::new(SymphoniaTagReader))
+    }
+}
+
+impl fmt::Debug for MetadataBackends {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetadataBackends").finish_non_exhaustive()
+    }
+}
@@ -206,3 +206,104 @@ fn process_external_cover(fsio: &FsIo, cover_path: &Path) -> Option<CoverArt> {
         primary_color: color_to_int(&primary_color),
     })
 }
+
+/// Compute a 64 bit difference hash of an image.
+///
+/// The image is reduced to a 9x8 grayscale thumbnail and every bit records
+/// whether a pixel is brighter than its right neighbour. Visually similar
+/// images (rescaled, recompressed, screenshotted) end up a few bits apart.
+pub fn get_perceptual_hash(image_data: &[u8]) -> Result<u64> {
+    let img = image::load_from_memory(image_data)?;
+    let gray = img
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = gray.get_pixel(x, y)[0];
+            let right = gray.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+
+    Ok(hash)
+}
+
+pub fn perceptual_hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Shrink an image to fit in a `size`x`size` box, re-encoded as JPEG.
+///
+/// Used for surfaces that only show tiny artwork, where shipping the
+/// embedded original would waste bandwidth and memory.
+pub fn resize_cover_art(image_data: &[u8], size: u32) -> Result<Vec<u8>> {
+    Ok(resize_cover_art_to_sizes(image_data, &[size])?.remove(0))
+}
+
+/// Shrink an image to every size in `sizes`, like [`resize_cover_art`],
+/// decoding it only once.
+pub fn resize_cover_art_to_sizes(image_data: &[u8], sizes: &[u32]) -> Result<Vec<Vec<u8>>> {
+    let img = image::load_from_memory(image_data)?;
+
+    sizes
+        .iter()
+        .map(|size| {
+            let thumbnail = image::DynamicImage::ImageRgb8(img.thumbnail(*size, *size).to_rgb8());
+
+            let mut buffer = std::io::Cursor::new(Vec::new());
+            thumbnail.write_to(&mut buffer, image::ImageFormat::Jpeg)?;
+
+            Ok(buffer.into_inner())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, GrayImage, ImageFormat, Luma, imageops};
+
+    use crate::cover_art::{get_perceptual_hash, perceptual_hash_distance};
+
+    /// A smooth pattern of light and dark patches.
+    fn waves(size: u32, x_frequency: f32, y_frequency: f32) -> GrayImage {
+        GrayImage::from_fn(size, size, |x, y| {
+            let x = x as f32 / size as f32;
+            let y = y as f32 / size as f32;
+            Luma([(127.0 + 120.0 * (x * x_frequency).sin() * (y * y_frequency).cos()) as u8])
+        })
+    }
+
+    fn encode(image: GrayImage) -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::ImageLuma8(image)
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_perceptual_hash() {
+        let cover = get_perceptual_hash(&encode(waves(256, 9.0, 5.0))).unwrap();
+
+        let same = get_perceptual_hash(&encode(waves(256, 9.0, 5.0))).unwrap();
+        assert_eq!(perceptual_hash_distance(cover, same), 0);
+
+        let resized = imageops::resize(
+            &waves(256, 9.0, 5.0),
+            100,
+            100,
+            imageops::FilterType::Lanczos3,
+        );
+        let resized = get_perceptual_hash(&encode(resized)).unwrap();
+        assert!(perceptual_hash_distance(cover, resized) <= 4);
+
+        let other = get_perceptual_hash(&encode(waves(256, 4.0, 11.0))).unwrap();
+        assert!(perceptual_hash_distance(cover, other) >= 16);
+
+        assert!(get_perceptual_hash(b"not an image").is_err());
+    }
+}
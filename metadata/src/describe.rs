@@ -1,7 +1,6 @@
 use std::{
     ffi::OsStr,
     fmt,
-    io::{BufReader, Read},
     path::{Path, PathBuf},
     time::UNIX_EPOCH,
 };
@@ -12,7 +11,7 @@ use symphonia::core::codecs::CODEC_TYPE_NULL;
 use ::analysis::utils::audio_metadata_reader::{get_codec_information, get_format};
 use ::fsio::{FsIo, FsNode};
 
-use crate::crc::media_crc32;
+use crate::backend::MetadataBackends;
 
 fn to_unix_path_string(path_buf: PathBuf) -> Option<String> {
     let path = path_buf.as_path();
@@ -34,37 +33,33 @@ pub struct FileDescription {
     pub file_hash: Option<String>,
     pub last_modified: String,
     pub raw_node: FsNode,
+    pub backends: MetadataBackends,
 }
 
 impl FileDescription {
-    pub fn get_crc(&mut self, fsio: &FsIo) -> Result<String> {
+    /// Read the file with other backends than the default ones.
+    pub fn with_backends(mut self, backends: &MetadataBackends) -> Self {
+        self.backends = backends.clone();
+        self
+    }
+
+    pub fn get_hash(&mut self, fsio: &FsIo) -> Result<String> {
+        if let Some(hash) = &self.file_hash {
+            return Ok(hash.clone());
+        }
+
         let full_path = match &self.lib_path {
             Some(root_path) => root_path.join(&self.directory).join(&self.file_name),
             None => Path::new(&self.directory).join(&self.file_name),
         };
 
-        if self.file_hash.is_none() {
-            let file = fsio.open(&full_path, "r")?;
-            let mut reader = BufReader::new(file);
-            let mut buffer = vec![0; CHUNK_SIZE];
-            let mut crc: u32 = 0;
-
-            loop {
-                let bytes_read = reader.read(&mut buffer)?;
-                if bytes_read == 0 {
-                    break;
-                }
-                crc = media_crc32(&buffer, crc, 0, bytes_read);
-            }
-
-            let result = format!("{crc:08x}");
-            self.file_hash = Some(result.clone());
-            Ok(result)
-        } else if let Some(result) = self.file_hash.clone() {
-            Ok(result)
-        } else {
-            bail!("No file hash found")
-        }
+        let hash = self.backends.hasher.hash(fsio, &full_path)?;
+        self.file_hash = Some(hash.clone());
+        Ok(hash)
+    }
+
+    pub fn read_tags(&self) -> Result<Vec<(String, String)>> {
+        self.backends.tag_reader.read_tags(&self.raw_node)
     }
 
     pub fn get_codec_information(&mut self, fsio: &FsIo) -> Result<(u32, f64)> {
@@ -95,7 +90,44 @@ pub fn get_codec_information_from_node(fsio: &FsIo, fs_node: &FsNode) -> Result<
     Ok(codec_information)
 }
 
-const CHUNK_SIZE: usize = 1024 * 400;
+/// The codec of the first audio track of a file, as shown in its details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecDetails {
+    /// A short name like `flac` or `mp3`.
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub bits_per_sample: Option<u32>,
+}
+
+pub fn get_codec_details_from_node(fsio: &FsIo, fs_node: &FsNode) -> Result<CodecDetails> {
+    let full_path = match fs_node.path.to_str() {
+        Some(full_path) => full_path,
+        _none => bail!("Failed to convert file path while getting codec details"),
+    };
+
+    let format = get_format(fsio, full_path)
+        .with_context(|| format!("No supported format found: {full_path}"))?;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .with_context(|| "No supported audio tracks")?;
+
+    let params = &track.codec_params;
+    let codec = symphonia::default::get_codecs()
+        .get_codec(params.codec)
+        .map(|x| x.short_name.to_owned())
+        .unwrap_or_else(|| params.codec.to_string());
+
+    Ok(CodecDetails {
+        codec,
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|x| x.count() as u16),
+        bits_per_sample: params.bits_per_sample,
+    })
+}
 
 pub fn describe_file(
     fs_node: &FsNode,
@@ -144,6 +176,7 @@ pub fn describe_file(
         file_hash: None,
         last_modified,
         raw_node: fs_node.clone(),
+        backends: MetadataBackends::default(),
     })
 }
 
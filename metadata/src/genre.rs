@@ -1,3 +1,18 @@
-pub fn split_genres(x: &str) -> Vec<String> {
-    x.split("\\\\").map(|s| s.to_string()).collect()
+use crate::splitting::SplittingRules;
+
+/// Split a genre tag into its genres by the delimiters of `rules`, in order
+/// and without duplicates.
+pub fn split_genres(x: &str, rules: &SplittingRules) -> Vec<String> {
+    let mut genres: Vec<String> = Vec::new();
+    for part in rules
+        .split(x, &rules.genre_delimiters)
+        .into_iter()
+        .map(str::trim)
+    {
+        if !part.is_empty() && !genres.iter().any(|x| x == part) {
+            genres.push(part.to_string());
+        }
+    }
+
+    genres
 }
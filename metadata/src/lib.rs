@@ -1,7 +1,10 @@
 pub mod artist;
+pub mod backend;
 pub mod cover_art;
 pub mod crc;
 pub mod describe;
 pub mod genre;
 pub mod reader;
 pub mod scanner;
+pub mod splitting;
+pub mod writer;
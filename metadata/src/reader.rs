@@ -1,6 +1,10 @@
 use std::{path::Path, collections::HashMap, fs::File};
 
 use anyhow::{bail, Result};
+use lofty::config::ParseOptions;
+use lofty::file::AudioFile;
+use lofty::id3::v2::{ChannelType, Frame};
+use lofty::mpeg::MpegFile;
 use symphonia::core::formats::FormatOptions;
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value};
@@ -183,15 +187,41 @@ pub fn string_to_standard_tag_key(s: &str) -> Option<StandardTagKey> {
     STRING_TO_STANDARD_TAG_KEY.get(s).cloned()
 }
 
+/// The key a tag is stored with: trimmed, in lower case, with spaces and
+/// dashes turned into underscores, so `ALBUM`, `Album` and `album` are one
+/// key. The spelling in the file is kept apart for writing the tag back.
+pub fn normalize_tag_key(key: &str) -> String {
+    key.trim().to_ascii_lowercase().replace([' ', '-'], "_")
+}
+
+/// The rating from 0 to 5 stars of a `POPM` frame, `None` if unrated. Uses
+/// the ranges most players write with, 1, 64, 128, 196 and 255.
+pub fn popularimeter_to_rating(popularimeter: u64) -> Option<u8> {
+    match popularimeter {
+        0 => None,
+        1..=31 => Some(1),
+        32..=95 => Some(2),
+        96..=159 => Some(3),
+        160..=223 => Some(4),
+        _ => Some(5),
+    }
+}
+
 fn push_tags(
     revision: &MetadataRevision,
     metadata_list: &mut Vec<(String, String)>,
     field_blacklist: &[&str],
 ) {
     for tag in revision.tags() {
+        // Tags without a standard key keep the key of the file, they are
+        // normalized when stored. User defined ID3v2 frames drop the frame
+        // name, `TXXX:CUSTOM` is stored like a `CUSTOM` Vorbis comment.
         let std_key = match tag.std_key {
             Some(standard_key) => standard_tag_key_to_string(standard_key),
-            None => String::from(""),
+            None => match tag.key.strip_prefix("TXXX:") {
+                Some(description) if !description.trim().is_empty() => description.to_string(),
+                _ => tag.key.clone(),
+            },
         };
 
         if field_blacklist.contains(&std_key.as_str()) {
@@ -199,6 +229,13 @@ fn push_tags(
         }
 
         let value: String = match &tag.value {
+            // Popularimeters count from 0 to 255, ratings are stored in stars
+            Value::UnsignedInt(val) if tag.key.starts_with("POPM") => {
+                match popularimeter_to_rating(*val) {
+                    Some(rating) => rating.to_string(),
+                    None => continue,
+                }
+            }
             Value::String(val) => val.clone(),
             Value::UnsignedInt(val) => val.to_string(),
             Value::SignedInt(val) => val.to_string(),
@@ -211,6 +248,51 @@ fn push_tags(
     }
 }
 
+/// Read the ID3v2 frames of an MP3 file that Symphonia skips: `TCMP`
+/// compilation flags and `RVA2` volume adjustments, stored as ReplayGain
+/// gains. Tags already read from other frames are kept.
+fn push_id3v2_extended_tags(path: &Path, metadata_list: &mut Vec<(String, String)>) {
+    let Ok(mut file) = File::open(path) else {
+        return;
+    };
+    let Ok(mpeg_file) = MpegFile::read_from(&mut file, ParseOptions::new().read_properties(false))
+    else {
+        return;
+    };
+    let Some(tag) = mpeg_file.id3v2() else {
+        return;
+    };
+
+    let mut extended = Vec::new();
+    for frame in tag {
+        match frame {
+            Frame::Text(text) if frame.id_str() == "TCMP" => {
+                extended.push(("compilation", text.value.trim().to_string()));
+            }
+            Frame::RelativeVolumeAdjustment(adjustment) => {
+                let Some(master) = adjustment.channels.get(&ChannelType::MasterVolume) else {
+                    continue;
+                };
+                // Adjustments are stored in 1/512 dB
+                let gain = f64::from(master.volume_adjustment) / 512.0;
+                let key = if adjustment.identification.eq_ignore_ascii_case("album") {
+                    "replaygain_album_gain"
+                } else {
+                    "replaygain_track_gain"
+                };
+                extended.push((key, format!("{gain:+.2} dB")));
+            }
+            _ => {}
+        }
+    }
+
+    for (key, value) in extended {
+        if !metadata_list.iter().any(|(x, _)| x == key) {
+            metadata_list.push((key.to_string(), value));
+        }
+    }
+}
+
 fn probe_audio_file<P: AsRef<Path>>(file_path: P) -> Result<ProbeResult> {
     if !Path::new(file_path.as_ref()).exists() {
         bail!("File not found");
@@ -291,5 +373,13 @@ pub fn get_metadata(
         push_tags(metadata_rev, &mut metadata_list, &blacklist);
     }
 
+    let is_mp3 = fs_node
+        .path
+        .extension()
+        .is_some_and(|x| x.eq_ignore_ascii_case("mp3"));
+    if is_mp3 {
+        push_id3v2_extended_tags(&fs_node.path, &mut metadata_list);
+    }
+
     Ok(metadata_list)
 }
@@ -1,7 +1,7 @@
 use fsio::{FsIo, FsNode};
 use std::path::{Path, PathBuf};
 
-fn is_audio_file(entry: &FsNode) -> bool {
+pub fn is_audio_file(entry: &FsNode) -> bool {
     if let Some(ext) = entry.path.extension() {
         matches!(
             ext.to_str().unwrap_or("").to_lowercase().as_str(),
@@ -23,6 +23,7 @@ fn scan_audio_files<P: AsRef<Path>>(
 pub struct AudioScanner<'a> {
     root_path: PathBuf,
     iterator: Box<dyn Iterator<Item = FsNode> + Send + 'a>,
+    total: usize,
     ended: bool,
 }
 
@@ -31,10 +32,11 @@ impl<'a> AudioScanner<'a> {
         fsio: &'a FsIo,
         path: &'a P,
     ) -> Result<Self, fsio::FileIoError> {
-        let iterator = scan_audio_files(fsio, path)?;
+        let files: Vec<FsNode> = scan_audio_files(fsio, path)?.collect();
         Ok(AudioScanner {
             root_path: path.as_ref().to_path_buf(),
-            iterator: Box::new(iterator),
+            total: files.len(),
+            iterator: Box::new(files.into_iter()),
             ended: false,
         })
     }
@@ -52,6 +54,11 @@ impl<'a> AudioScanner<'a> {
         files
     }
 
+    /// The number of audio files found under the root, read or not.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
     pub fn has_ended(&self) -> bool {
         self.ended
     }
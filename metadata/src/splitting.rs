@@ -0,0 +1,86 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+/// How the scanner splits artist and genre tags into several names. The
+/// rules belong to a library, so a change only takes effect once the
+/// library is indexed again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplittingRules {
+    /// Separators between the artists of a tag, like `A & B`.
+    pub artist_delimiters: Vec<String>,
+    /// Separators between the genres of a tag, like `Rock; Pop`. `\\` is how
+    /// multiple genre tags of a file are joined.
+    pub genre_delimiters: Vec<String>,
+    /// Names that contain a delimiter but are never split, like `AC/DC`.
+    /// Matched ignoring ASCII case.
+    pub exceptions: Vec<String>,
+}
+
+impl Default for SplittingRules {
+    fn default() -> Self {
+        Self {
+            artist_delimiters: [
+                ", ", "; ", " × ", " x ", " / ", " ft.", " ft. ", " feat. ", " & ",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            genre_delimiters: ["\\\\", ";", "/"].into_iter().map(String::from).collect(),
+            exceptions: Vec::new(),
+        }
+    }
+}
+
+impl SplittingRules {
+    /// Split `input` at every delimiter that isn't part of an exception. When
+    /// several delimiters match at the same place, the first listed wins.
+    pub fn split<'a>(&self, input: &'a str, delimiters: &[String]) -> Vec<&'a str> {
+        let protected = self.exception_ranges(input);
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+        let mut position = 0;
+        while position < input.len() {
+            if let Some(range) = protected.iter().find(|x| x.contains(&position)) {
+                position = range.end;
+                continue;
+            }
+
+            let rest = &input[position..];
+            match delimiters
+                .iter()
+                .find(|x| !x.is_empty() && rest.starts_with(x.as_str()))
+            {
+                Some(delimiter) => {
+                    parts.push(&input[start..position]);
+                    position += delimiter.len();
+                    start = position;
+                }
+                None => position += rest.chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        parts.push(&input[start..]);
+
+        parts
+    }
+
+    /// Where the exceptions appear in `input`. ASCII lowercasing keeps the
+    /// byte offsets valid for the original string.
+    fn exception_ranges(&self, input: &str) -> Vec<Range<usize>> {
+        let lowercase = input.to_ascii_lowercase();
+
+        self.exceptions
+            .iter()
+            .filter(|x| !x.is_empty())
+            .flat_map(|exception| {
+                let exception = exception.to_ascii_lowercase();
+                lowercase
+                    .match_indices(&exception)
+                    .map(|(index, _)| index..index + exception.len())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
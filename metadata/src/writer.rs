@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use lofty::config::WriteOptions;
+use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::tag::{ItemKey, Tag};
+
+use crate::reader::normalize_tag_key;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagChange {
+    Set { key: String, value: String },
+    Remove { key: String },
+}
+
+impl TagChange {
+    pub fn key(&self) -> &str {
+        match self {
+            TagChange::Set { key, .. } | TagChange::Remove { key } => key,
+        }
+    }
+
+    /// The same change with its key normalized like stored keys.
+    pub fn normalized(&self) -> TagChange {
+        match self {
+            TagChange::Set { key, value } => TagChange::Set {
+                key: normalize_tag_key(key),
+                value: value.clone(),
+            },
+            TagChange::Remove { key } => TagChange::Remove {
+                key: normalize_tag_key(key),
+            },
+        }
+    }
+}
+
+/// The item a tag is written to, keyed like
+/// `reader::standard_tag_key_to_string`. `None` for tags that can't be
+/// written.
+pub fn tag_item_key(key: &str) -> Option<ItemKey> {
+    let item_key = match key {
+        "track_title" => ItemKey::TrackTitle,
+        "artist" => ItemKey::TrackArtist,
+        "album" => ItemKey::AlbumTitle,
+        "album_artist" => ItemKey::AlbumArtist,
+        "composer" => ItemKey::Composer,
+        "conductor" => ItemKey::Conductor,
+        "lyricist" => ItemKey::Lyricist,
+        "date" => ItemKey::RecordingDate,
+        "release_date" => ItemKey::ReleaseDate,
+        "original_date" => ItemKey::OriginalReleaseDate,
+        "genre" => ItemKey::Genre,
+        "mood" => ItemKey::Mood,
+        "track_number" => ItemKey::TrackNumber,
+        "track_total" => ItemKey::TrackTotal,
+        "disc_number" => ItemKey::DiscNumber,
+        "disc_total" => ItemKey::DiscTotal,
+        "comment" => ItemKey::Comment,
+        "lyrics" => ItemKey::Lyrics,
+        "bpm" => ItemKey::Bpm,
+        "label" => ItemKey::Label,
+        "copyright" => ItemKey::CopyrightMessage,
+        "ident_isrc" => ItemKey::Isrc,
+        "ident_barcode" => ItemKey::Barcode,
+        "ident_catalog_number" => ItemKey::CatalogNumber,
+        "musicbrainz_recording_id" => ItemKey::MusicBrainzRecordingId,
+        "musicbrainz_track_id" => ItemKey::MusicBrainzTrackId,
+        "musicbrainz_album_id" => ItemKey::MusicBrainzReleaseId,
+        "musicbrainz_release_group_id" => ItemKey::MusicBrainzReleaseGroupId,
+        "musicbrainz_artist_id" => ItemKey::MusicBrainzArtistId,
+        "musicbrainz_album_artist_id" => ItemKey::MusicBrainzReleaseArtistId,
+        _ => return None,
+    };
+
+    Some(item_key)
+}
+
+/// The item a change is written to. Keys without an item are written with
+/// the spelling the file used for them, looked up by the stored key in
+/// `raw_keys`.
+fn change_item_key(key: &str, raw_keys: &HashMap<String, String>) -> Option<ItemKey> {
+    tag_item_key(key).or_else(|| raw_keys.get(key).map(|x| ItemKey::Unknown(x.clone())))
+}
+
+/// Check that every change can be written before touching any file.
+pub fn validate_tag_changes(
+    changes: &[TagChange],
+    raw_keys: &HashMap<String, String>,
+) -> Result<()> {
+    for change in changes {
+        if change_item_key(change.key(), raw_keys).is_none() {
+            bail!("Tag '{}' can't be written to files", change.key());
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the changes into the main tag of the file, ID3v2 for MP3, Vorbis
+/// comments for FLAC and Ogg, MP4 atoms for M4A, creating the tag if the
+/// file has none. `raw_keys` holds the original spelling of the keys the
+/// file was read with, see `validate_tag_changes`.
+pub fn write_tags(
+    path: &Path,
+    changes: &[TagChange],
+    raw_keys: &HashMap<String, String>,
+) -> Result<()> {
+    validate_tag_changes(changes, raw_keys)?;
+
+    let mut tagged_file =
+        lofty::read_from_path(path).with_context(|| format!("Failed to read tags: {path:?}"))?;
+
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .with_context(|| format!("No tag to write to: {path:?}"))?;
+
+    for change in changes {
+        let Some(item_key) = change_item_key(change.key(), raw_keys) else {
+            continue;
+        };
+
+        match change {
+            TagChange::Set { value, .. } => {
+                if !tag.insert_text(item_key.clone(), value.clone()) {
+                    bail!(
+                        "Tag '{}' isn't supported by {:?} tags",
+                        change.key(),
+                        tag.tag_type()
+                    );
+                }
+            }
+            TagChange::Remove { .. } => tag.remove_key(&item_key),
+        }
+    }
+
+    tagged_file
+        .save_to_path(path, WriteOptions::default())
+        .with_context(|| format!("Failed to write tags: {path:?}"))?;
+
+    Ok(())
+}
@@ -24,6 +24,35 @@ mod m20250312_000023_create_media_file_fingerprint_table;
 mod m20250312_000024_create_media_file_similarity_table;
 mod m20250410_000025_add_hlc_columns;
 mod m20250529_000026_create_sync_record_table;
+mod m20250601_000027_add_column_perceptual_hash;
+mod m20250615_000028_add_column_bpm;
+mod m20250622_000029_add_column_feature_version;
+mod m20250629_000030_add_column_key_mode;
+mod m20250706_000031_add_loudness_columns;
+mod m20250713_000032_create_smart_playlists_table;
+mod m20250720_000033_create_play_history_table;
+mod m20250727_000034_add_column_rating;
+mod m20250803_000035_create_library_sources_table;
+mod m20250810_000036_create_deleted_files_table;
+mod m20250811_000037_add_column_queue_current;
+mod m20250812_000038_create_media_file_seek_table_table;
+mod m20250813_000039_create_settings_table;
+mod m20250814_000040_add_column_track_offsets;
+mod m20250815_000041_add_column_hidden;
+mod m20250816_000042_create_playlist_mirrors_table;
+mod m20250817_000043_create_media_lyrics_table;
+mod m20250818_000044_create_task_checkpoints_table;
+mod m20250819_000045_add_album_artist_columns;
+mod m20250820_000046_add_column_last_error;
+mod m20250821_000047_add_column_artist_role;
+mod m20250822_000048_create_media_summaries_table;
+mod m20250823_000049_normalize_metadata_keys;
+mod m20250824_000050_add_column_mix_cover;
+mod m20250825_000051_create_search_index_vocab;
+mod m20250826_000052_add_octave_band_columns;
+mod m20250827_000053_add_column_queue_position;
+mod m20250828_000054_create_media_file_waveform_table;
+mod m20250829_000055_add_unique_index_analysis_file_id;
 
 pub struct Migrator;
 
@@ -55,6 +84,35 @@ impl MigratorTrait for Migrator {
             Box::new(m20250312_000024_create_media_file_similarity_table::Migration),
             Box::new(m20250410_000025_add_hlc_columns::Migration),
             Box::new(m20250529_000026_create_sync_record_table::Migration),
+            Box::new(m20250601_000027_add_column_perceptual_hash::Migration),
+            Box::new(m20250615_000028_add_column_bpm::Migration),
+            Box::new(m20250622_000029_add_column_feature_version::Migration),
+            Box::new(m20250629_000030_add_column_key_mode::Migration),
+            Box::new(m20250706_000031_add_loudness_columns::Migration),
+            Box::new(m20250713_000032_create_smart_playlists_table::Migration),
+            Box::new(m20250720_000033_create_play_history_table::Migration),
+            Box::new(m20250727_000034_add_column_rating::Migration),
+            Box::new(m20250803_000035_create_library_sources_table::Migration),
+            Box::new(m20250810_000036_create_deleted_files_table::Migration),
+            Box::new(m20250811_000037_add_column_queue_current::Migration),
+            Box::new(m20250812_000038_create_media_file_seek_table_table::Migration),
+            Box::new(m20250813_000039_create_settings_table::Migration),
+            Box::new(m20250814_000040_add_column_track_offsets::Migration),
+            Box::new(m20250815_000041_add_column_hidden::Migration),
+            Box::new(m20250816_000042_create_playlist_mirrors_table::Migration),
+            Box::new(m20250817_000043_create_media_lyrics_table::Migration),
+            Box::new(m20250818_000044_create_task_checkpoints_table::Migration),
+            Box::new(m20250819_000045_add_album_artist_columns::Migration),
+            Box::new(m20250820_000046_add_column_last_error::Migration),
+            Box::new(m20250821_000047_add_column_artist_role::Migration),
+            Box::new(m20250822_000048_create_media_summaries_table::Migration),
+            Box::new(m20250823_000049_normalize_metadata_keys::Migration),
+            Box::new(m20250824_000050_add_column_mix_cover::Migration),
+            Box::new(m20250825_000051_create_search_index_vocab::Migration),
+            Box::new(m20250826_000052_add_octave_band_columns::Migration),
+            Box::new(m20250827_000053_add_column_queue_position::Migration),
+            Box::new(m20250828_000054_create_media_file_waveform_table::Migration),
+            Box::new(m20250829_000055_add_unique_index_analysis_file_id::Migration),
         ]
     }
 }
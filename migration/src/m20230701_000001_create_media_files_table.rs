@@ -68,4 +68,5 @@ pub enum MediaFiles {
     CoverArtId,
     SampleRate,
     Duration,
+    SourceId,
 }
@@ -172,4 +172,19 @@ pub enum MediaAnalysis {
     Mfcc10,
     Mfcc11,
     Mfcc12,
+    Bpm,
+    FeatureVersion,
+    Key,
+    Mode,
+    IntegratedLoudness,
+    TruePeak,
+    LoudnessRange,
+    OctaveBand0,
+    OctaveBand1,
+    OctaveBand2,
+    OctaveBand3,
+    OctaveBand4,
+    OctaveBand5,
+    OctaveBand6,
+    CrestFactor,
 }
@@ -51,4 +51,5 @@ pub enum MediaCoverArt {
     FileHash,
     Binary,
     PrimaryColor,
+    PerceptualHash,
 }
@@ -69,4 +69,5 @@ pub enum MediaFileArtists {
     Id,
     MediaFileId,
     ArtistId,
+    Role,
 }
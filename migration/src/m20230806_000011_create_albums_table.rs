@@ -47,4 +47,6 @@ pub enum Albums {
     Id,
     Name,
     Group,
+    AlbumArtist,
+    IsCompilation,
 }
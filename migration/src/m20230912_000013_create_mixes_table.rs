@@ -52,4 +52,5 @@ pub enum Mixes {
     ScriptletMode,
     CreatedAt,
     UpdatedAt,
+    Cover,
 }
@@ -70,4 +70,10 @@ pub enum MediaFileStats {
     Skipped,
     PlayedThrough,
     UpdatedAt,
+    Rating,
+    StartOffsetMs,
+    EndOffsetMs,
+    Hidden,
+    LastError,
+    LastErrorAt,
 }
@@ -54,4 +54,6 @@ pub enum PlaybackQueue {
     Table,
     Id,
     MediaFileId,
+    Current,
+    PositionMs,
 }
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230728_000008_create_media_cover_art_table::MediaCoverArt;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250601_000027_add_column_perceptual_hash"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaCoverArt::Table)
+                    .add_column(
+                        ColumnDef::new(MediaCoverArt::PerceptualHash)
+                            .big_integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaCoverArt::Table)
+                    .drop_column(MediaCoverArt::PerceptualHash)
+                    .to_owned(),
+            )
+            .await
+    }
+}
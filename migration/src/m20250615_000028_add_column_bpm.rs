@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230701_000003_create_media_analysis_table::MediaAnalysis;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250615_000028_add_column_bpm"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaAnalysis::Table)
+                    .add_column(ColumnDef::new(MediaAnalysis::Bpm).double().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaAnalysis::Table)
+                    .drop_column(MediaAnalysis::Bpm)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230701_000003_create_media_analysis_table::MediaAnalysis;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250622_000029_add_column_feature_version"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaAnalysis::Table)
+                    .add_column(
+                        ColumnDef::new(MediaAnalysis::FeatureVersion)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaAnalysis::Table)
+                    .drop_column(MediaAnalysis::FeatureVersion)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230701_000003_create_media_analysis_table::MediaAnalysis;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250706_000031_add_loudness_columns"
+    }
+}
+
+const COLUMNS: [MediaAnalysis; 3] = [
+    MediaAnalysis::IntegratedLoudness,
+    MediaAnalysis::TruePeak,
+    MediaAnalysis::LoudnessRange,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only takes a single change per `ALTER TABLE`
+        for column in COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MediaAnalysis::Table)
+                        .add_column(ColumnDef::new(column).double().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MediaAnalysis::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
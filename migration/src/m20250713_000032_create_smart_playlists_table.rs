@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250713_000032_create_smart_playlists_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SmartPlaylists::Table)
+                    .col(
+                        ColumnDef::new(SmartPlaylists::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SmartPlaylists::Name).string().not_null())
+                    .col(ColumnDef::new(SmartPlaylists::Group).string().not_null())
+                    .col(ColumnDef::new(SmartPlaylists::Rules).string().not_null())
+                    .col(
+                        ColumnDef::new(SmartPlaylists::CreatedAt)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SmartPlaylists::UpdatedAt)
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SmartPlaylists::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden, Clone, Copy)]
+pub enum SmartPlaylists {
+    Table,
+    Id,
+    Name,
+    Group,
+    Rules,
+    CreatedAt,
+    UpdatedAt,
+}
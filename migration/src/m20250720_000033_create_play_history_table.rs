@@ -0,0 +1,95 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250720_000033_create_play_history_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayHistory::Table)
+                    .col(
+                        ColumnDef::new(PlayHistory::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PlayHistory::MediaFileId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PlayHistory::StartedAt).string().not_null())
+                    .col(ColumnDef::new(PlayHistory::EndedAt).string().null())
+                    .col(
+                        ColumnDef::new(PlayHistory::Completed)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(PlayHistory::Skipped)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-play_history-file_id")
+                            .from(PlayHistory::Table, PlayHistory::MediaFileId)
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-play_history-media_file_id")
+                    .table(PlayHistory::Table)
+                    .col(PlayHistory::MediaFileId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-play_history-started_at")
+                    .table(PlayHistory::Table)
+                    .col(PlayHistory::StartedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PlayHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum PlayHistory {
+    Table,
+    Id,
+    MediaFileId,
+    StartedAt,
+    EndedAt,
+    Completed,
+    Skipped,
+}
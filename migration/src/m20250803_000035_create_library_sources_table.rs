@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250803_000035_create_library_sources_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LibrarySources::Table)
+                    .col(
+                        ColumnDef::new(LibrarySources::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LibrarySources::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(LibrarySources::Path)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LibrarySources::Available)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(ColumnDef::new(LibrarySources::LastSeenAt).string().null())
+                    .col(
+                        ColumnDef::new(LibrarySources::CreatedAt)
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Files of the library root itself have no source
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFiles::Table)
+                    .add_column(ColumnDef::new(MediaFiles::SourceId).integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-media_files-source_id")
+                    .table(MediaFiles::Table)
+                    .col(MediaFiles::SourceId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-media_files-source_id")
+                    .table(MediaFiles::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFiles::Table)
+                    .drop_column(MediaFiles::SourceId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(LibrarySources::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum LibrarySources {
+    Table,
+    Id,
+    Name,
+    Path,
+    Available,
+    LastSeenAt,
+    CreatedAt,
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250810_000036_create_deleted_files_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DeletedFiles::Table)
+                    .col(
+                        ColumnDef::new(DeletedFiles::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // No foreign key, the file is gone by the time this row
+                    // is read
+                    .col(ColumnDef::new(DeletedFiles::FileId).integer().not_null())
+                    .col(ColumnDef::new(DeletedFiles::Directory).string().not_null())
+                    .col(ColumnDef::new(DeletedFiles::FileName).string().not_null())
+                    .col(ColumnDef::new(DeletedFiles::SourceId).integer().null())
+                    .col(ColumnDef::new(DeletedFiles::Snapshot).text().not_null())
+                    .col(ColumnDef::new(DeletedFiles::DeletedAt).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-deleted_files-deleted_at")
+                    .table(DeletedFiles::Table)
+                    .col(DeletedFiles::DeletedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-deleted_files-deleted_at")
+                    .table(DeletedFiles::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(DeletedFiles::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum DeletedFiles {
+    Table,
+    Id,
+    FileId,
+    Directory,
+    FileName,
+    SourceId,
+    Snapshot,
+    DeletedAt,
+}
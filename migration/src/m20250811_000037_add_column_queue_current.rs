@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20231110_000019_create_playback_queue_table::PlaybackQueue;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250811_000037_add_column_queue_current"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PlaybackQueue::Table)
+                    .add_column(
+                        ColumnDef::new(PlaybackQueue::Current)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PlaybackQueue::Table)
+                    .drop_column(PlaybackQueue::Current)
+                    .to_owned(),
+            )
+            .await
+    }
+}
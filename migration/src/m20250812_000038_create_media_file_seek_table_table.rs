@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250812_000038_create_media_file_seek_table_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MediaFileSeekTable::Table)
+                    .col(
+                        ColumnDef::new(MediaFileSeekTable::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaFileSeekTable::MediaFileId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // The table is rebuilt once the file changes
+                    .col(
+                        ColumnDef::new(MediaFileSeekTable::FileHash)
+                            .string()
+                            .not_null(),
+                    )
+                    // Empty for files that can't be indexed, so they aren't
+                    // scanned again on every run
+                    .col(
+                        ColumnDef::new(MediaFileSeekTable::SeekTable)
+                            .var_binary(16777216)
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_file_seek_table_media_file_id")
+                            .from(MediaFileSeekTable::Table, MediaFileSeekTable::MediaFileId)
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaFileSeekTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum MediaFileSeekTable {
+    Table,
+    Id,
+    MediaFileId,
+    FileHash,
+    SeekTable,
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230912_000015_create_media_file_stats_table::MediaFileStats;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250814_000040_add_column_track_offsets"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .add_column(
+                        ColumnDef::new(MediaFileStats::StartOffsetMs)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .add_column(ColumnDef::new(MediaFileStats::EndOffsetMs).integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .drop_column(MediaFileStats::EndOffsetMs)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .drop_column(MediaFileStats::StartOffsetMs)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000005_create_playlists_table::Playlists;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250816_000042_create_playlist_mirrors_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlaylistMirrors::Table)
+                    .col(
+                        ColumnDef::new(PlaylistMirrors::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PlaylistMirrors::PlaylistId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PlaylistMirrors::Path)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // Hashes of both sides at the last sync, to tell which
+                    // side has been edited since
+                    .col(ColumnDef::new(PlaylistMirrors::FileHash).string().null())
+                    .col(
+                        ColumnDef::new(PlaylistMirrors::PlaylistHash)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PlaylistMirrors::Conflict)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(ColumnDef::new(PlaylistMirrors::SyncedAt).string().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_playlist_mirrors_playlist_id")
+                            .from(PlaylistMirrors::Table, PlaylistMirrors::PlaylistId)
+                            .to(Playlists::Table, Playlists::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PlaylistMirrors::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum PlaylistMirrors {
+    Table,
+    Id,
+    PlaylistId,
+    Path,
+    FileHash,
+    PlaylistHash,
+    Conflict,
+    SyncedAt,
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250817_000043_create_media_lyrics_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MediaLyrics::Table)
+                    .col(
+                        ColumnDef::new(MediaLyrics::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaLyrics::MediaFileId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // The lyrics are read again once the file or its sidecar
+                    // changes
+                    .col(ColumnDef::new(MediaLyrics::FileHash).string().not_null())
+                    .col(ColumnDef::new(MediaLyrics::SidecarPath).string().null())
+                    .col(ColumnDef::new(MediaLyrics::SidecarModified).string().null())
+                    // Empty for files without lyrics, so they aren't scanned
+                    // again on every run
+                    .col(ColumnDef::new(MediaLyrics::Format).string().not_null())
+                    .col(ColumnDef::new(MediaLyrics::Content).text().not_null())
+                    .col(
+                        ColumnDef::new(MediaLyrics::Synced)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_lyrics_media_file_id")
+                            .from(MediaLyrics::Table, MediaLyrics::MediaFileId)
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaLyrics::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum MediaLyrics {
+    Table,
+    Id,
+    MediaFileId,
+    FileHash,
+    SidecarPath,
+    SidecarModified,
+    Format,
+    Content,
+    Synced,
+}
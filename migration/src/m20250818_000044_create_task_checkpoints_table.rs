@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250818_000044_create_task_checkpoints_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TaskCheckpoints::Table)
+                    .col(
+                        ColumnDef::new(TaskCheckpoints::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskCheckpoints::Task)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // The root being scanned, empty for tasks walking the
+                    // whole library
+                    .col(ColumnDef::new(TaskCheckpoints::Scope).string().not_null())
+                    .col(
+                        ColumnDef::new(TaskCheckpoints::Position)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskCheckpoints::Processed)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskCheckpoints::UpdatedAt)
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TaskCheckpoints::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum TaskCheckpoints {
+    Table,
+    Id,
+    Task,
+    Scope,
+    Position,
+    Processed,
+    UpdatedAt,
+}
@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230912_000015_create_media_file_stats_table::MediaFileStats;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250820_000046_add_column_last_error"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .add_column(ColumnDef::new(MediaFileStats::LastError).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .add_column(ColumnDef::new(MediaFileStats::LastErrorAt).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .drop_column(MediaFileStats::LastErrorAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .drop_column(MediaFileStats::LastError)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230806_000010_create_media_file_artists_table::MediaFileArtists;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250821_000047_add_column_artist_role"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Links made before roles existed all come from the artist tag
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileArtists::Table)
+                    .add_column(
+                        ColumnDef::new(MediaFileArtists::Role)
+                            .string()
+                            .not_null()
+                            .default("main"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_media_file_artists_role")
+                    .table(MediaFileArtists::Table)
+                    .col(MediaFileArtists::Role)
+                    .col(MediaFileArtists::ArtistId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_media_file_artists_role")
+                    .table(MediaFileArtists::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileArtists::Table)
+                    .drop_column(MediaFileArtists::Role)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250822_000048_create_media_summaries_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing files are filled in by the next library maintenance, until
+        // then their summaries are read from `media_metadata`
+        manager
+            .create_table(
+                Table::create()
+                    .table(MediaSummaries::Table)
+                    .col(
+                        ColumnDef::new(MediaSummaries::MediaFileId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MediaSummaries::Artist).string().not_null())
+                    .col(ColumnDef::new(MediaSummaries::Album).string().not_null())
+                    .col(ColumnDef::new(MediaSummaries::Genre).string().not_null())
+                    // Empty for files without a title tag, which show their
+                    // file name instead
+                    .col(ColumnDef::new(MediaSummaries::Title).string().null())
+                    // The disc number times 1000 plus the track number
+                    .col(
+                        ColumnDef::new(MediaSummaries::TrackNumber)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_summaries_media_file_id")
+                            .from(MediaSummaries::Table, MediaSummaries::MediaFileId)
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaSummaries::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum MediaSummaries {
+    Table,
+    MediaFileId,
+    Artist,
+    Album,
+    Genre,
+    Title,
+    TrackNumber,
+}
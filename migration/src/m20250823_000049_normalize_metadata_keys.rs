@@ -0,0 +1,128 @@
+use chrono::Utc;
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250823_000049_normalize_metadata_keys"
+    }
+}
+
+/// SQL matching `metadata::reader::normalize_tag_key`. `trim()` alone only
+/// strips spaces, so it is given every character `str::trim` strips.
+const NORMALIZED_KEY: &str = concat!(
+    "lower(replace(replace(trim(meta_key, char(",
+    "9, 10, 11, 12, 13, 32, 133, 160, 5760, 8192, 8193, 8194, 8195, 8196, 8197, ",
+    "8198, 8199, 8200, 8201, 8202, 8232, 8233, 8239, 8287, 12288",
+    ")), ' ', '_'), '-', '_'))"
+);
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MediaRawTags::Table)
+                    .col(
+                        ColumnDef::new(MediaRawTags::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaRawTags::MediaFileId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(MediaRawTags::MetaKey).string().not_null())
+                    // The key as spelled in the file
+                    .col(ColumnDef::new(MediaRawTags::RawKey).string().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_raw_tags_media_file_id")
+                            .from(MediaRawTags::Table, MediaRawTags::MediaFileId)
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_media_raw_tags_file_key")
+                    .table(MediaRawTags::Table)
+                    .col(MediaRawTags::MediaFileId)
+                    .col(MediaRawTags::MetaKey)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Keep the spelling of the keys about to change
+        let db = manager.get_connection();
+        db.execute_unprepared(&format!(
+            "INSERT INTO media_raw_tags (media_file_id, meta_key, raw_key) \
+             SELECT file_id, {NORMALIZED_KEY}, MIN(meta_key) FROM media_metadata \
+             WHERE meta_key != {NORMALIZED_KEY} \
+             GROUP BY file_id, {NORMALIZED_KEY}"
+        ))
+        .await?;
+
+        // Of the values that end up stored twice, the first one is kept. The
+        // kept and the renamed rows get a newer HLC, so the other nodes take
+        // the merge, and see the dropped rows as deleted since they are gone
+        // after their last sync
+        let survivors = format!(
+            "SELECT MIN(id) FROM media_metadata GROUP BY file_id, {NORMALIZED_KEY}, meta_value"
+        );
+        db.execute_unprepared(&format!(
+            "UPDATE media_metadata \
+             SET updated_at_hlc_ts = '{}', updated_at_hlc_ver = updated_at_hlc_ver + 1 \
+             WHERE meta_key != {NORMALIZED_KEY} OR id IN ({survivors} HAVING COUNT(*) > 1)",
+            Utc::now().to_rfc3339()
+        ))
+        .await?;
+        db.execute_unprepared(&format!(
+            "DELETE FROM media_metadata WHERE id NOT IN ({survivors})"
+        ))
+        .await?;
+        db.execute_unprepared(&format!(
+            "UPDATE media_metadata SET meta_key = {NORMALIZED_KEY} \
+             WHERE meta_key != {NORMALIZED_KEY}"
+        ))
+        .await?;
+
+        // Summaries of the merged files are built again by the next library
+        // maintenance
+        db.execute_unprepared(
+            "DELETE FROM media_summaries WHERE media_file_id IN \
+             (SELECT media_file_id FROM media_raw_tags)",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaRawTags::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum MediaRawTags {
+    Table,
+    Id,
+    MediaFileId,
+    MetaKey,
+    RawKey,
+}
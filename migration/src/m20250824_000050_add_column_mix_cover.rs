@@ -0,0 +1,73 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{FromQueryResult, Statement, prelude::Uuid},
+};
+
+use crate::m20230912_000013_create_mixes_table::Mixes;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250824_000050_add_column_mix_cover"
+    }
+}
+
+#[derive(Iden)]
+enum MixHlcColumns {
+    HlcUuid,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct RowId {
+    id: i32,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Mixes::Table)
+                    .add_column(ColumnDef::new(Mixes::Cover).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Mixes created after the HLC columns were added stored the node ID
+        // as their UUID, give each of them its own
+        let db = manager.get_connection();
+        let rows_to_update: Vec<RowId> = RowId::find_by_statement(Statement::from_string(
+            db.get_database_backend(),
+            "SELECT id FROM mixes WHERE hlc_uuid = created_at_hlc_nid",
+        ))
+        .all(db)
+        .await?;
+
+        for row in rows_to_update {
+            manager
+                .exec_stmt(
+                    Query::update()
+                        .table(Mixes::Table)
+                        .value(MixHlcColumns::HlcUuid, Uuid::new_v4().to_string())
+                        .and_where(Expr::col(Mixes::Id).eq(row.id))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Mixes::Table)
+                    .drop_column(Mixes::Cover)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250825_000051_create_search_index_vocab"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Lists the words of the search index per column, for matching
+        // words with typos
+        db.execute_unprepared(
+            "CREATE VIRTUAL TABLE search_index_vocab USING fts5vocab(search_index, col);",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TABLE `search_index_vocab`")
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250828_000054_create_media_file_waveform_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MediaFileWaveform::Table)
+                    .col(
+                        ColumnDef::new(MediaFileWaveform::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaFileWaveform::MediaFileId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // Deflated peaks, empty if the file has no samples
+                    .col(
+                        ColumnDef::new(MediaFileWaveform::Waveform)
+                            .var_binary(16777216)
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_file_waveform_media_file_id")
+                            .from(MediaFileWaveform::Table, MediaFileWaveform::MediaFileId)
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaFileWaveform::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum MediaFileWaveform {
+    Table,
+    Id,
+    MediaFileId,
+    Waveform,
+}
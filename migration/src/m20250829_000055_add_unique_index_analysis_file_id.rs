@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230701_000003_create_media_analysis_table::MediaAnalysis;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250829_000055_add_unique_index_analysis_file_id"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Concurrent analyses could store a file twice, the newest row wins
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "DELETE FROM media_analysis WHERE id NOT IN \
+                 (SELECT MAX(id) FROM media_analysis GROUP BY file_id)",
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_media_analysis_file_id")
+                    .table(MediaAnalysis::Table)
+                    .col(MediaAnalysis::FileId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_media_analysis_file_id")
+                    .table(MediaAnalysis::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -1,6 +1,7 @@
 #[macro_use]
 mod gui_request;
 
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::OnceLock;
 
@@ -13,7 +14,9 @@ use rinf::{DartSignal, RustSignal};
 
 pub use tokio;
 
+use ::config::{RuneConfig, load_config};
 use ::database::connection::{MainDbConnection, RecommendationDbConnection};
+use ::database::events::EventBus;
 use ::discovery::client::CertValidator;
 use ::discovery::protocol::DiscoveryService;
 use ::discovery::server::PermissionManager;
@@ -30,8 +33,10 @@ use crate::utils::DatabaseConnections;
 use crate::utils::GlobalParams;
 use crate::utils::ParamsExtractor;
 use crate::utils::TaskTokens;
+use crate::utils::events::initialize_event_subscribers;
 use crate::utils::nid::get_or_create_node_id;
 use crate::utils::player::initialize_local_player;
+use crate::utils::startup::report_startup;
 
 pub async fn local_player_loop(
     fsio: Arc<FsIo>,
@@ -50,6 +55,19 @@ pub async fn local_player_loop(
 
         let recommend_db: Arc<RecommendationDbConnection> = db_connections.recommend_db;
 
+        let config = match load_config::<&str>(Some(Path::new(&lib_path)), &[]) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Failed to load config, using the defaults: {e:#}");
+                RuneConfig::default()
+            }
+        };
+        ::provider_client::configure(
+            config.network.offline,
+            config.network.max_requests_per_second,
+        );
+        let config = Arc::new(config);
+
         let lib_path: Arc<String> = Arc::new(lib_path);
         let config_path: Arc<String> = Arc::new(config_path);
 
@@ -64,6 +82,12 @@ pub async fn local_player_loop(
         let task_tokens: Arc<Mutex<TaskTokens>> = Arc::new(Mutex::new(TaskTokens::default()));
 
         info!("Initializing player");
+        report_startup(
+            &*broadcaster,
+            StartupStage::InitializingPlayer,
+            Some(&lib_path),
+            None,
+        );
         let player = Player::new(Some(main_cancel_token.clone()));
         let player: Arc<Mutex<Player>> = Arc::new(Mutex::new(player));
 
@@ -79,6 +103,16 @@ pub async fn local_player_loop(
             CertValidator::new(&**config_path).await.unwrap(),
         ));
 
+        info!("Initializing library events");
+        let events = Arc::new(EventBus::default());
+        initialize_event_subscribers(
+            main_db.clone(),
+            recommend_db.clone(),
+            &events,
+            broadcaster.clone(),
+            &main_cancel_token,
+        );
+
         info!("Initializing Player events");
         tokio::spawn(initialize_local_player(
             fsio.clone(),
@@ -87,8 +121,10 @@ pub async fn local_player_loop(
             player.clone(),
             scrobbler.clone(),
             broadcaster.clone(),
+            events.clone(),
             cert_validator.clone(),
             permission_manager.clone(),
+            main_cancel_token.clone(),
         ));
 
         info!("Initializing UI events");
@@ -110,6 +146,8 @@ pub async fn local_player_loop(
             permission_manager,
             server_manager: OnceLock::new(),
             running_mode: crate::utils::RunningMode::Client,
+            config,
+            events,
         };
 
         let global_params = Arc::new(global_params);
@@ -121,5 +159,12 @@ pub async fn local_player_loop(
             .expect("Failed to set server manager in global params");
 
         for_all_request_pairs2!(listen_local_gui_event, global_params, main_cancel_token);
+
+        report_startup(
+            &*global_params.broadcaster,
+            StartupStage::Ready,
+            Some(&global_params.lib_path),
+            None,
+        );
     });
 }
@@ -4,6 +4,7 @@ mod remote_request;
 use std::{
     collections::HashMap,
     sync::{Arc, OnceLock},
+    time::Duration,
 };
 
 use anyhow::{Context, Result, bail};
@@ -20,7 +21,9 @@ use tokio_util::sync::CancellationToken;
 use urlencoding::encode;
 use uuid::Uuid;
 
+use ::config::RuneConfig;
 use ::database::connection::{connect_fake_main_db, connect_fake_recommendation_db};
+use ::database::events::EventBus;
 use ::discovery::{
     client::{CertValidator, select_best_host},
     protocol::DiscoveryService,
@@ -86,6 +89,62 @@ pub fn decode_message(payload: &[u8]) -> Option<(String, Vec<u8>, Uuid)> {
     Some((msg_type, msg_payload, request_id))
 }
 
+const REMOTE_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Send a single request to another Rune instance over a short-lived
+/// connection and wait for the matching response payload.
+pub async fn send_remote_request(
+    host: &str,
+    config: Arc<ClientConfig>,
+    fingerprint: &str,
+    type_name: &str,
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    let url = format!(
+        "wss://{}:7863/ws?fingerprint={}&host={}",
+        host,
+        encode(fingerprint),
+        encode(host)
+    );
+
+    let (ws_stream, _) =
+        connect_async_tls_with_config(url, None, false, Some(Connector::Rustls(config)))
+            .await
+            .with_context(|| format!("Failed to connect to {host}"))?;
+    let (mut write, mut read) = ws_stream.split();
+
+    let request_id = Uuid::new_v4();
+    write
+        .send(TungsteniteMessage::Binary(
+            encode_message(type_name, payload, Some(request_id)).into(),
+        ))
+        .await
+        .with_context(|| format!("Failed to send {type_name}"))?;
+
+    let response = tokio::time::timeout(REMOTE_REQUEST_TIMEOUT, async {
+        while let Some(Ok(message)) = read.next().await {
+            // Broadcasts share the connection, only pick the response to our request
+            if let TungsteniteMessage::Binary(data) = message
+                && let Some((_, payload, uuid)) = decode_message(&data)
+                && uuid == request_id
+            {
+                return Some(payload);
+            }
+        }
+
+        None
+    })
+    .await
+    .with_context(|| format!("Timed out waiting for the response to {type_name}"))?
+    .with_context(|| "Connection closed before receiving a response")?;
+
+    if let Err(e) = write.close().await {
+        debug!("Error closing websocket connection: {e}");
+    }
+
+    Ok(response)
+}
+
 type MessageHandler = Box<dyn Fn(Vec<u8>) + Send + Sync>;
 type HandlerMap = Arc<Mutex<HashMap<String, MessageHandler>>>;
 
@@ -284,6 +343,7 @@ impl WebSocketDartBridge {
                         scan_token: None,
                         analyze_token: None,
                         deduplicate_token: None,
+                        radio_token: None,
                     })),
                     player: Arc::new(Mutex::new(MockPlayer {})),
                     sfx_player,
@@ -294,6 +354,9 @@ impl WebSocketDartBridge {
                     permission_manager,
                     server_manager: OnceLock::new(),
                     running_mode: RunningMode::Server,
+                    // Everything runs on the remote side, nothing to configure
+                    config: Arc::new(RuneConfig::default()),
+                    events: Arc::new(EventBus::default()),
                 };
 
                 let global_params = Arc::new(global_params);
@@ -358,13 +421,18 @@ pub async fn server_player_loop(url: &str, config_path: &str, alias: &str) -> Re
             bridge,
             ScanAudioLibraryProgress,
             ScanAudioLibraryResponse,
+            AlbumThumbnailProgress,
+            LibraryUpdated,
             SetMediaLibraryPathResponse,
             AnalyzeAudioLibraryProgress,
             AnalyzeAudioLibraryResponse,
             PlaybackStatus,
+            LyricLineUpdate,
             ScrobbleServiceStatusUpdated,
             CrashResponse,
             RealtimeFFT,
+            ListeningExposureUpdated,
+            PlaybackFailed,
             PlaylistUpdate
         );
 
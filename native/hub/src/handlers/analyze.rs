@@ -3,6 +3,7 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 
 use ::database::actions::analysis::{get_analyze_count, if_analyze_exists};
+use ::database::actions::library_map::get_library_map;
 use ::database::connection::MainDbConnection;
 
 use crate::utils::{GlobalParams, ParamsExtractor};
@@ -55,3 +56,55 @@ impl Signal for GetAnalyzeCountRequest {
         Ok(Some(GetAnalyzeCountResponse { count }))
     }
 }
+
+impl ParamsExtractor for FetchLibraryMapRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchLibraryMapRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchLibraryMapResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let clusters = match dart_signal.clusters {
+            0 => None,
+            x => Some(x as usize),
+        };
+
+        let map = get_library_map(&main_db, clusters)
+            .await
+            .context("Failed to build the library map")?;
+
+        Ok(Some(FetchLibraryMapResponse {
+            points: map
+                .points
+                .into_iter()
+                .map(|x| LibraryMapPoint {
+                    file_id: x.file_id,
+                    x: x.x,
+                    y: x.y,
+                    cluster: x.cluster as u32,
+                })
+                .collect(),
+            clusters: map
+                .clusters
+                .into_iter()
+                .map(|x| LibraryMapCluster {
+                    id: x.id as u32,
+                    size: x.size as u32,
+                    x: x.x,
+                    y: x.y,
+                    genre: x.genre,
+                })
+                .collect(),
+        }))
+    }
+}
@@ -208,6 +208,7 @@ impl Collection {
             collection_type: T::collection_type().into(),
             cover_art_map: HashMap::new(),
             readonly: model.readonly(),
+            subtitle: model.subtitle(),
         };
 
         Ok(collection)
@@ -228,6 +229,7 @@ impl Collection {
             collection_type: x.collection_type.into(),
             cover_art_map: HashMap::new(),
             readonly: x.readonly,
+            subtitle: x.subtitle,
         }
     }
 
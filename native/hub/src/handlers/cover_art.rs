@@ -1,11 +1,15 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use futures::future::join_all;
 
 use tokio::task;
 
 use ::database::{
+    actions::cover_art::{
+        ALBUM_GRID_THUMBNAIL_SIZES, get_album_ids_by_cover_art_ids, get_album_thumbnail_status,
+        search_cover_arts_by_image,
+    },
     connection::{MainDbConnection, RecommendationDbConnection},
     playing_item::dispatcher::PlayingItemActionDispatcher,
 };
@@ -133,3 +137,88 @@ impl Signal for GetPrimaryColorByTrackIdRequest {
         }
     }
 }
+
+impl ParamsExtractor for FetchAlbumThumbnailStatusRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchAlbumThumbnailStatusRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchAlbumThumbnailStatusResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let status = get_album_thumbnail_status(&main_db, &ALBUM_GRID_THUMBNAIL_SIZES)
+            .await
+            .with_context(|| "Failed to check the album thumbnails")?;
+
+        Ok(Some(FetchAlbumThumbnailStatusResponse {
+            ready: status.ready as i32,
+            total: status.total as i32,
+        }))
+    }
+}
+
+impl ParamsExtractor for SearchCoverArtByImageRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for SearchCoverArtByImageRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = SearchCoverArtByImageResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let matches = match search_cover_arts_by_image(
+            &main_db,
+            &request.image,
+            request.max_distance,
+            request.n as usize,
+        )
+        .await
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Ok(Some(SearchCoverArtByImageResponse {
+                    result: vec![],
+                    error: Some(format!("{e:#}")),
+                }));
+            }
+        };
+
+        let cover_art_ids: Vec<i32> = matches.iter().map(|(id, _)| *id).collect();
+        let mut album_ids = get_album_ids_by_cover_art_ids(&main_db, &cover_art_ids)
+            .await
+            .with_context(|| "Failed to get albums of matched cover arts")?;
+
+        Ok(Some(SearchCoverArtByImageResponse {
+            result: matches
+                .into_iter()
+                .map(|(cover_art_id, distance)| SimilarCoverArt {
+                    cover_art_id,
+                    distance,
+                    album_ids: album_ids.remove(&cover_art_id).unwrap_or_default(),
+                })
+                .collect(),
+            error: None,
+        }))
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use fsio::FsIo;
+use log::info;
+use tokio::sync::{Mutex, RwLock};
+
+use ::database::{
+    actions::file::{get_file_ids_by_hashes, get_files_by_ids},
+    connection::MainDbConnection,
+    playing_item::dispatcher::PlayingItemActionDispatcher,
+};
+use ::discovery::client::{CertValidator, select_best_host};
+use ::playback::{
+    player::{Playable, PlaybackState, PlayingItem},
+    strategies::AddMode,
+};
+
+use crate::{
+    Session, Signal,
+    backends::remote::send_remote_request,
+    messages::*,
+    server::generate_or_load_certificates,
+    utils::{GlobalParams, ParamsExtractor, files_to_playback_request},
+};
+
+impl ParamsExtractor for HandoffPlaybackRequest {
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<RwLock<CertValidator>>,
+        Arc<String>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.cert_validator),
+            Arc::clone(&all_params.config_path),
+        )
+    }
+}
+
+impl Signal for HandoffPlaybackRequest {
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<RwLock<CertValidator>>,
+        Arc<String>,
+    );
+    type Response = HandoffPlaybackResponse;
+
+    async fn handle(
+        &self,
+        (main_db, player, validator, config_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let status = player.lock().await.get_status();
+
+        if status.playlist.is_empty() {
+            return Ok(Some(HandoffPlaybackResponse {
+                success: false,
+                error: "Nothing to hand off, the queue is empty".to_owned(),
+                transferred: 0,
+            }));
+        }
+
+        let file_ids: Vec<i32> = status
+            .playlist
+            .iter()
+            .filter_map(|x| match x {
+                PlayingItem::InLibrary(id) => Some(*id),
+                _ => None,
+            })
+            .collect();
+        let hashes: HashMap<i32, String> = get_files_by_ids(&main_db, &file_ids)
+            .await?
+            .into_iter()
+            .map(|x| (x.id, x.file_hash))
+            .collect();
+
+        let items: Vec<HandoffItem> = status
+            .playlist
+            .iter()
+            .map(|x| HandoffItem {
+                item: x.clone().into(),
+                file_hash: match x {
+                    PlayingItem::InLibrary(id) => hashes.get(id).cloned().unwrap_or_default(),
+                    _ => String::new(),
+                },
+            })
+            .collect();
+        let transferred = items.len() as i32;
+
+        let request = ReceiveHandoffRequest {
+            items,
+            index: status.index.unwrap_or(0) as u32,
+            position_seconds: status.position.as_secs_f64(),
+            playback_mode: status.playback_mode.into(),
+            playing: status.state == PlaybackState::Playing,
+        };
+
+        let validator = validator.read().await.clone();
+        let client_config = Arc::new(Arc::new(validator).into_client_config());
+
+        let result = async {
+            let (fingerprint, _, _) =
+                generate_or_load_certificates(Path::new(&*config_path), &dart_signal.alias).await?;
+            let host = select_best_host(dart_signal.hosts.clone(), client_config.clone()).await?;
+
+            info!("Handing off {transferred} tracks to {host}");
+            let payload =
+                rinf::serialize(&request).with_context(|| "Failed to serialize request")?;
+            let response = send_remote_request(
+                &host,
+                client_config,
+                &fingerprint,
+                "ReceiveHandoffRequest",
+                &payload,
+            )
+            .await?;
+
+            rinf::deserialize::<ReceiveHandoffResponse>(&response)
+                .map_err(|e| anyhow::anyhow!("Deserialization failed: {e}"))
+        }
+        .await;
+
+        match result {
+            Ok(response) if response.success => {
+                // The other device has taken over, stop sounding here
+                player.lock().await.pause();
+
+                Ok(Some(HandoffPlaybackResponse {
+                    success: true,
+                    error: String::new(),
+                    transferred: response.matched,
+                }))
+            }
+            Ok(response) => Ok(Some(HandoffPlaybackResponse {
+                success: false,
+                error: response.error,
+                transferred: 0,
+            })),
+            Err(e) => Ok(Some(HandoffPlaybackResponse {
+                success: false,
+                error: format!("{e:#}"),
+                transferred: 0,
+            })),
+        }
+    }
+}
+
+impl ParamsExtractor for ReceiveHandoffRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for ReceiveHandoffRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+    );
+    type Response = ReceiveHandoffResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        // File ids are local to each library, so tracks are matched by content
+        let hashes: Vec<String> = request
+            .items
+            .iter()
+            .filter(|x| !x.file_hash.is_empty())
+            .map(|x| x.file_hash.clone())
+            .collect();
+        let local_ids = get_file_ids_by_hashes(&main_db, &hashes).await?;
+
+        // Only tracks found in the local library are played, a path sent by
+        // the other device is never opened here
+        let matched_items: Vec<(usize, PlayingItem)> = request
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| {
+                local_ids
+                    .get(&x.file_hash)
+                    .map(|id| (i, PlayingItem::InLibrary(*id)))
+            })
+            .collect();
+        let items: Vec<PlayingItem> = matched_items.iter().map(|(_, x)| x.clone()).collect();
+
+        let tracks = PlayingItemActionDispatcher::new()
+            .get_file_handle(&fsio, &main_db, &items)
+            .await?;
+        let playback_request = files_to_playback_request(&fsio, lib_path.as_ref(), &tracks);
+
+        if playback_request.is_empty() {
+            return Ok(Some(ReceiveHandoffResponse {
+                success: false,
+                error: "None of the handed off tracks exist on this device".to_owned(),
+                matched: 0,
+            }));
+        }
+
+        // Tracks missing here are dropped, so playback starts from the current
+        // track, or the first one after it that could be resolved
+        let resolved: HashSet<&PlayingItem> = playback_request.iter().map(|(x, _)| x).collect();
+        let current = matched_items
+            .iter()
+            .find(|(i, x)| *i >= request.index as usize && resolved.contains(x));
+        let index = current
+            .and_then(|(_, item)| playback_request.iter().position(|(x, _)| x == item))
+            .unwrap_or(playback_request.len() - 1);
+        let current_matched = current.is_some_and(|(i, _)| *i == request.index as usize);
+        let matched = playback_request.len() as i32;
+
+        let mut player = player.lock().await;
+        player.clear_playlist();
+        player.add_to_playlist(playback_request, AddMode::AppendToEnd);
+        player.set_playback_mode(request.playback_mode.into());
+        player.switch(index);
+        if current_matched {
+            player.seek(request.position_seconds);
+        }
+        if request.playing {
+            player.play();
+        } else {
+            player.pause();
+        }
+
+        Ok(Some(ReceiveHandoffResponse {
+            success: true,
+            error: String::new(),
+            matched,
+        }))
+    }
+}
@@ -10,7 +10,9 @@ use ::database::{
         collection::{
             CollectionQuery, CollectionQueryListMode, CollectionQueryType, UnifiedCollection,
         },
-        file::{get_media_files, get_random_files, get_reverse_listed_media_files},
+        file::{
+            RandomFileFilter, get_media_files, get_random_files, get_reverse_listed_media_files,
+        },
         metadata::{MetadataSummary, get_metadata_summary_by_files},
         mixes::query_mix_media_files,
     },
@@ -121,6 +123,7 @@ fn create_track_collection(
         queries,
         collection_type: CollectionQueryType::Track,
         readonly: false,
+        subtitle: None,
     }
 }
 
@@ -138,7 +141,9 @@ impl ComplexQuery for NaiveTrackComplexQuery {
             CollectionQueryListMode::Reverse => {
                 get_reverse_listed_media_files(main_db, 0, 25).await
             }
-            CollectionQueryListMode::Random => get_random_files(main_db, 25).await,
+            CollectionQueryListMode::Random => {
+                get_random_files(main_db, 25, &RandomFileFilter::default()).await
+            }
         }?;
 
         build_track_collections(main_db, tracks).await
@@ -1,4 +1,4 @@
-use std::{path::Path, sync::Arc};
+use std::{path::Path, sync::Arc, time::Instant};
 
 use anyhow::{Context, Result};
 use log::{debug, info, warn};
@@ -6,26 +6,45 @@ use tokio::{sync::Mutex, task};
 use tokio_util::sync::CancellationToken;
 
 use ::analysis::utils::computing_device::ComputingDevice;
+use ::config::RuneConfig;
 use ::database::{
     actions::{
         analysis::analysis_audio_library,
-        cover_art::scan_cover_arts,
+        checkpoint::list_checkpoints,
+        cover_art::{
+            ALBUM_GRID_THUMBNAIL_SIZES, get_album_thumbnail_status, prerender_album_thumbnails,
+            scan_cover_arts,
+        },
         fingerprint::{
             Configuration, compare_all_pairs, compute_file_fingerprints, mark_duplicate_files,
         },
+        lyrics::index_lyrics,
         metadata::scan_audio_library,
-        recommendation::sync_recommendation,
+        metadata_languages::{get_metadata_languages, set_metadata_languages},
+        recommendation::maintain_recommendation_db_if_needed,
+        seek_table::index_seek_tables,
+        splitting::{get_splitting_rules, reapply_splitting, set_splitting_rules},
+        trash::purge_deleted_files,
     },
     connection::{MainDbConnection, RecommendationDbConnection},
+    events::{EventBus, LibraryEvent, ScanStage},
+    progress::ProgressEta,
 };
 use ::fsio::FsIo;
+use ::metadata::{backend::MetadataBackends, splitting::SplittingRules};
 
 use crate::{
     Session, Signal, TaskTokens,
     messages::*,
-    utils::{Broadcaster, GlobalParams, ParamsExtractor, determine_batch_size},
+    utils::{Broadcaster, GlobalParams, ParamsExtractor, determine_batch_size, metrics::METRICS},
 };
 
+/// The estimated seconds left for a progress message.
+fn eta_seconds(eta: &ProgressEta, processed: usize, total: usize) -> Option<i32> {
+    eta.update(processed, total)
+        .map(|x| x.as_secs().try_into().unwrap_or(i32::MAX))
+}
+
 impl ParamsExtractor for CloseLibraryRequest {
     type Params = (Arc<String>, Arc<CancellationToken>, Arc<Mutex<TaskTokens>>);
 
@@ -60,11 +79,16 @@ impl Signal for CloseLibraryRequest {
             return Ok(None);
         }
 
+        // Stop everything started for this library, the player saves the
+        // queue position and stops once the main token is cancelled
         let mut tokens = task_tokens.lock().await;
-        if let Some(token) = tokens.scan_token.take() {
-            token.cancel();
-        }
-        if let Some(token) = tokens.analyze_token.take() {
+        let running = [
+            tokens.scan_token.take(),
+            tokens.analyze_token.take(),
+            tokens.deduplicate_token.take(),
+            tokens.radio_token.take(),
+        ];
+        for token in running.into_iter().flatten() {
             token.cancel();
         }
 
@@ -81,8 +105,11 @@ impl ParamsExtractor for ScanAudioLibraryRequest {
         Arc<FsIo>,
         Arc<MainDbConnection>,
         Arc<String>,
+        Arc<RecommendationDbConnection>,
         Arc<Mutex<TaskTokens>>,
         Arc<dyn Broadcaster>,
+        Arc<RuneConfig>,
+        Arc<EventBus>,
     );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
@@ -90,8 +117,11 @@ impl ParamsExtractor for ScanAudioLibraryRequest {
             Arc::clone(&all_params.fsio),
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
+            Arc::clone(&all_params.recommend_db),
             Arc::clone(&all_params.task_tokens),
             Arc::clone(&all_params.broadcaster),
+            Arc::clone(&all_params.config),
+            Arc::clone(&all_params.events),
         )
     }
 }
@@ -101,14 +131,17 @@ impl Signal for ScanAudioLibraryRequest {
         Arc<FsIo>,
         Arc<MainDbConnection>,
         Arc<String>,
+        Arc<RecommendationDbConnection>,
         Arc<Mutex<TaskTokens>>,
         Arc<dyn Broadcaster>,
+        Arc<RuneConfig>,
+        Arc<EventBus>,
     );
     type Response = ();
 
     async fn handle(
         &self,
-        (fsio, main_db, node_id, task_tokens, broadcaster): Self::Params,
+        (fsio, main_db, node_id, recommend_db, task_tokens, broadcaster, config, events): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<()>> {
@@ -129,29 +162,52 @@ impl Signal for ScanAudioLibraryRequest {
         let main_db_clone = Arc::clone(&main_db);
         let node_id_clone = Arc::clone(&node_id);
         let broadcaster_clone = Arc::clone(&broadcaster);
+        let events_clone = Arc::clone(&events);
 
         task::spawn_blocking(move || {
             let runtime = tokio::runtime::Runtime::new().unwrap();
             runtime.block_on(async move {
                 let result: Result<()> = async {
+                    let start = Instant::now();
+                    let eta = ProgressEta::new();
                     let file_processed = scan_audio_library(
                         &fsio,
+                        &MetadataBackends::default(),
                         &main_db_clone,
                         Path::new(&request_path),
                         true,
                         request_force,
-                        |progress| {
+                        |progress, total| {
                             broadcaster_clone.broadcast(&ScanAudioLibraryProgress {
                                 task: ScanTaskType::IndexFiles,
                                 path: request_path.clone(),
                                 progress: progress.try_into().unwrap(),
-                                total: 0,
+                                total: total.try_into().unwrap(),
+                                eta_seconds: eta_seconds(&eta, progress, total),
+                            });
+                            events_clone.publish(LibraryEvent::ScanProgress {
+                                path: request_path.clone(),
+                                stage: ScanStage::Files,
+                                processed: progress,
+                                total,
                             });
                         },
                         Some(new_token.clone()),
                     )
                     .await?;
 
+                    METRICS
+                        .scan_duration_seconds
+                        .observe_duration(start.elapsed());
+                    METRICS.scanned_files_total.add(file_processed as u64);
+
+                    if let Err(e) =
+                        purge_deleted_files(&main_db_clone, config.library.trash_retention_days)
+                            .await
+                    {
+                        warn!("Failed to purge deleted files: {e:#?}");
+                    }
+
                     if new_token.is_cancelled() {
                         info!("Operation cancelled during artist processing.");
 
@@ -163,12 +219,14 @@ impl Signal for ScanAudioLibraryRequest {
                         return Ok(());
                     }
 
-                    let batch_size = determine_batch_size(0.75);
+                    let batch_size = config.batch_size(config.library.cover_art_batch_size);
                     let cloned_broadcaster = Arc::clone(&broadcaster_clone);
+                    let cloned_events = Arc::clone(&events_clone);
                     let path_for_closure = request_path.clone();
+                    let eta = ProgressEta::new();
 
                     scan_cover_arts(
-                        fsio,
+                        Arc::clone(&fsio),
                         &main_db_clone,
                         Path::new(&request_path),
                         &node_id_clone,
@@ -179,17 +237,91 @@ impl Signal for ScanAudioLibraryRequest {
                                 path: path_for_closure.clone(),
                                 progress: now.try_into().unwrap(),
                                 total: total.try_into().unwrap(),
+                                eta_seconds: eta_seconds(&eta, now, total),
+                            });
+                            cloned_events.publish(LibraryEvent::ScanProgress {
+                                path: path_for_closure.clone(),
+                                stage: ScanStage::CoverArts,
+                                processed: now,
+                                total,
                             });
                         },
                         Some(new_token.clone()),
                     )
                     .await?;
 
+                    // Seek tables only speed up seeking, so a failure here
+                    // doesn't fail the scan
+                    if let Err(e) = index_seek_tables(
+                        Arc::clone(&fsio),
+                        &main_db_clone,
+                        Path::new(&request_path),
+                        batch_size,
+                        |_, _| {},
+                        Some(new_token.clone()),
+                    )
+                    .await
+                    {
+                        warn!("Failed to index seek tables: {e:#?}");
+                    }
+
+                    if let Err(e) = index_lyrics(
+                        Arc::clone(&fsio),
+                        &main_db_clone,
+                        Path::new(&request_path),
+                        batch_size,
+                        |_, _| {},
+                        Some(new_token.clone()),
+                    )
+                    .await
+                    {
+                        warn!("Failed to index lyrics: {e:#?}");
+                    }
+
+                    if let Err(e) =
+                        maintain_recommendation_db_if_needed(&main_db_clone, &recommend_db).await
+                    {
+                        warn!("Failed to maintain the recommendation database: {e:#?}");
+                    }
+
                     broadcaster_clone.broadcast(&ScanAudioLibraryResponse {
                         path: request_path.clone(),
                         progress: file_processed as i32,
                     });
 
+                    // Thumbnails only spare the album grid from decoding full
+                    // sized cover arts, so they are baked once the scan is
+                    // reported done
+                    match get_album_thumbnail_status(&main_db_clone, &ALBUM_GRID_THUMBNAIL_SIZES)
+                        .await
+                    {
+                        Ok(status) => {
+                            let ready = status.ready;
+                            let total = status.total;
+                            let path_for_closure = request_path.clone();
+                            let progress_broadcaster = Arc::clone(&broadcaster_clone);
+                            if let Err(e) = prerender_album_thumbnails(
+                                &fsio,
+                                &main_db_clone,
+                                &ALBUM_GRID_THUMBNAIL_SIZES,
+                                batch_size,
+                                move |now, _| {
+                                    progress_broadcaster.broadcast(&AlbumThumbnailProgress {
+                                        path: path_for_closure.clone(),
+                                        ready: (ready + now).try_into().unwrap_or(i32::MAX),
+                                        total: total.try_into().unwrap_or(i32::MAX),
+                                    });
+                                },
+                                Some(new_token.clone()),
+                            )
+                            .await
+                            {
+                                warn!("Failed to bake album thumbnails: {e:#?}");
+                            }
+                        }
+                        Err(e) => warn!("Failed to check the album thumbnails: {e:#?}"),
+                    }
+
                     Ok(())
                 }
                 .await;
@@ -217,9 +349,10 @@ impl ParamsExtractor for AnalyzeAudioLibraryRequest {
         Arc<FsIo>,
         Arc<MainDbConnection>,
         Arc<String>,
-        Arc<RecommendationDbConnection>,
+        Arc<EventBus>,
         Arc<Mutex<TaskTokens>>,
         Arc<dyn Broadcaster>,
+        Arc<RuneConfig>,
     );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
@@ -227,9 +360,10 @@ impl ParamsExtractor for AnalyzeAudioLibraryRequest {
             Arc::clone(&all_params.fsio),
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
-            Arc::clone(&all_params.recommend_db),
+            Arc::clone(&all_params.events),
             Arc::clone(&all_params.task_tokens),
             Arc::clone(&all_params.broadcaster),
+            Arc::clone(&all_params.config),
         )
     }
 }
@@ -239,15 +373,16 @@ impl Signal for AnalyzeAudioLibraryRequest {
         Arc<FsIo>,
         Arc<MainDbConnection>,
         Arc<String>,
-        Arc<RecommendationDbConnection>,
+        Arc<EventBus>,
         Arc<Mutex<TaskTokens>>,
         Arc<dyn Broadcaster>,
+        Arc<RuneConfig>,
     );
     type Response = ();
 
     async fn handle(
         &self,
-        (fsio, main_db, node_id, recommend_db, task_tokens, broadcaster): Self::Params,
+        (fsio, main_db, node_id, events, task_tokens, broadcaster, config): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -266,14 +401,20 @@ impl Signal for AnalyzeAudioLibraryRequest {
 
         let request_path = request.path.clone();
         let closure_request_path = request_path.clone();
-        let batch_size = determine_batch_size(request.workload_factor);
+        // A batch size in the config wins over the workload picked in the UI
+        let batch_size = match config.library.analysis_batch_size {
+            0 => determine_batch_size(request.workload_factor),
+            batch_size => batch_size,
+        };
         let computing_device = request.computing_device;
+        let eta = ProgressEta::new();
 
         task::spawn_blocking(move || {
             let runtime = tokio::runtime::Runtime::new().unwrap();
             runtime.block_on(async move {
                 let cloned_broadcaster = Arc::clone(&broadcaster);
                 let result = async {
+                    let start = Instant::now();
                     let total_files = analysis_audio_library(
                         fsio,
                         &main_db,
@@ -286,16 +427,19 @@ impl Signal for AnalyzeAudioLibraryRequest {
                                 path: closure_request_path.clone(),
                                 progress: progress.try_into().unwrap(),
                                 total: total.try_into().unwrap(),
+                                eta_seconds: eta_seconds(&eta, progress, total),
                             });
                         },
+                        Some(events.as_ref()),
                         Some(new_token.clone()),
                     )
                     .await
                     .with_context(|| "Audio analysis failed")?;
 
-                    sync_recommendation(&main_db, &recommend_db)
-                        .await
-                        .with_context(|| "Recommendation synchronization failed")?;
+                    METRICS
+                        .analysis_duration_seconds
+                        .observe_duration(start.elapsed());
+                    METRICS.analyzed_files_total.add(total_files as u64);
 
                     broadcaster.broadcast(&AnalyzeAudioLibraryResponse {
                         path: request_path.clone(),
@@ -453,6 +597,44 @@ impl Signal for DeduplicateAudioLibraryRequest {
     }
 }
 
+impl ParamsExtractor for ListTaskCheckpointsRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for ListTaskCheckpointsRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = ListTaskCheckpointsResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let checkpoints = list_checkpoints(&main_db)
+            .await
+            .with_context(|| "Failed to list task checkpoints")?
+            .into_iter()
+            .map(|x| TaskCheckpoint {
+                task: x.task,
+                scope: x.scope,
+                position: x.position,
+                processed: x.processed,
+                updated_at: x.updated_at,
+            })
+            .collect();
+
+        Ok(Some(ListTaskCheckpointsResponse {
+            path: dart_signal.path.clone(),
+            checkpoints,
+        }))
+    }
+}
+
 impl ParamsExtractor for CancelTaskRequest {
     type Params = (Arc<Mutex<TaskTokens>>,);
 
@@ -503,3 +685,164 @@ impl Signal for CancelTaskRequest {
         }))
     }
 }
+
+impl From<SplittingRules> for SplittingSettings {
+    fn from(x: SplittingRules) -> Self {
+        Self {
+            artist_delimiters: x.artist_delimiters,
+            genre_delimiters: x.genre_delimiters,
+            exceptions: x.exceptions,
+        }
+    }
+}
+
+impl From<SplittingSettings> for SplittingRules {
+    fn from(x: SplittingSettings) -> Self {
+        Self {
+            artist_delimiters: x.artist_delimiters,
+            genre_delimiters: x.genre_delimiters,
+            exceptions: x.exceptions,
+        }
+    }
+}
+
+impl ParamsExtractor for GetSplittingRulesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for GetSplittingRulesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = GetSplittingRulesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let rules = get_splitting_rules(&main_db)
+            .await
+            .with_context(|| "Failed to get the splitting rules")?;
+
+        Ok(Some(GetSplittingRulesResponse {
+            settings: rules.into(),
+        }))
+    }
+}
+
+impl ParamsExtractor for SetSplittingRulesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for SetSplittingRulesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = SetSplittingRulesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let rules: Option<SplittingRules> = dart_signal.settings.clone().map(|x| x.into());
+
+        set_splitting_rules(&main_db, rules.as_ref())
+            .await
+            .with_context(|| "Failed to save the splitting rules")?;
+
+        Ok(Some(SetSplittingRulesResponse {
+            settings: rules.unwrap_or_default().into(),
+        }))
+    }
+}
+
+impl ParamsExtractor for GetMetadataLanguagesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for GetMetadataLanguagesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = GetMetadataLanguagesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let languages = get_metadata_languages(&main_db)
+            .await
+            .with_context(|| "Failed to get the metadata languages")?;
+
+        Ok(Some(GetMetadataLanguagesResponse { languages }))
+    }
+}
+
+impl ParamsExtractor for SetMetadataLanguagesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for SetMetadataLanguagesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = SetMetadataLanguagesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let languages = set_metadata_languages(&main_db, &dart_signal.languages)
+            .await
+            .with_context(|| "Failed to save the metadata languages")?;
+
+        Ok(Some(SetMetadataLanguagesResponse { languages }))
+    }
+}
+
+impl ParamsExtractor for ReapplySplittingRequest {
+    type Params = (Arc<MainDbConnection>, Arc<CancellationToken>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.main_token),
+        )
+    }
+}
+
+impl Signal for ReapplySplittingRequest {
+    type Params = (Arc<MainDbConnection>, Arc<CancellationToken>);
+    type Response = ReapplySplittingResponse;
+
+    async fn handle(
+        &self,
+        (main_db, main_token): Self::Params,
+        _session: Option<Session>,
+        _: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let total = reapply_splitting(&main_db, Some(main_token.as_ref()))
+            .await
+            .with_context(|| "Failed to reapply the splitting rules")?;
+
+        Ok(Some(ReapplySplittingResponse {
+            total: total.try_into().unwrap_or(i32::MAX),
+        }))
+    }
+}
@@ -1,23 +1,30 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::{Context, Result, anyhow};
+use log::{error, warn};
 use sea_orm::DatabaseConnection;
+use tokio::task;
 
 use ::database::{
     actions::{
         cover_art::{bake_cover_art_by_file_ids, bake_cover_art_by_media_files},
-        file::{get_files_by_ids, get_media_files, list_files},
+        file::{get_file_by_id, get_files_by_ids, get_media_files, list_files},
         metadata::{get_metadata_summary_by_files, get_parsed_file_by_id},
+        tag_conflicts::{TagResolution, get_tag_conflicts, resolve_tag_conflicts},
     },
     connection::MainDbConnection,
 };
 use ::fsio::FsIo;
+use ::metadata::{backend::MetadataBackends, describe::get_codec_details_from_node};
 
 use crate::{
     Session, Signal,
     messages::*,
-    utils::{GlobalParams, ParamsExtractor, parse_media_files},
+    utils::{
+        GlobalParams, ParamsExtractor, file_manager::reveal_in_file_manager, parse_media_files,
+    },
 };
 
 impl ParamsExtractor for FetchMediaFilesRequest {
@@ -219,3 +226,231 @@ impl Signal for SearchMediaFileSummaryRequest {
         }))
     }
 }
+
+impl ParamsExtractor for FetchTagConflictsRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for FetchTagConflictsRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = FetchTagConflictsResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let files = get_tag_conflicts(
+            &fsio,
+            &MetadataBackends::default(),
+            &main_db,
+            Path::new(lib_path.as_str()),
+            &dart_signal.file_ids,
+        )
+        .await
+        .with_context(|| "Failed to compare the tags of the files")?;
+
+        Ok(Some(FetchTagConflictsResponse {
+            files: files
+                .into_iter()
+                .map(|x| MediaFileTagConflicts {
+                    file_id: x.file_id,
+                    conflicts: x
+                        .conflicts
+                        .into_iter()
+                        .map(|x| TagConflict {
+                            key: x.key,
+                            database_values: x.database_values,
+                            file_values: x.file_values,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for ResolveTagConflictsRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for ResolveTagConflictsRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = ResolveTagConflictsResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let resolution = match request.resolution {
+            TagConflictResolution::File => TagResolution::File,
+            TagConflictResolution::Database => TagResolution::Database,
+            TagConflictResolution::Merge => TagResolution::Merge,
+        };
+
+        let report = resolve_tag_conflicts(
+            &fsio,
+            &MetadataBackends::default(),
+            &main_db,
+            Path::new(lib_path.as_str()),
+            &request.file_ids,
+            &request.keys,
+            resolution,
+        )
+        .await
+        .with_context(|| "Failed to resolve the tag conflicts")?;
+
+        Ok(Some(ResolveTagConflictsResponse {
+            resolved_file_ids: report.resolved,
+            failed_file_ids: report.failed,
+        }))
+    }
+}
+
+impl ParamsExtractor for RevealMediaFileRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for RevealMediaFileRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = RevealMediaFileResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let file_id = dart_signal.file_id;
+
+        let file = get_file_by_id(&main_db, file_id)
+            .await?
+            .with_context(|| format!("File not found: {file_id}"))?;
+        let path = Path::new(lib_path.as_str())
+            .join(&file.directory)
+            .join(&file.file_name);
+
+        let result = match fsio.canonicalize_path(&path) {
+            Ok(path) => task::spawn_blocking(move || reveal_in_file_manager(&path))
+                .await
+                .with_context(|| "Failed to spawn the file manager task")?,
+            Err(e) => Err(anyhow!("File not reachable: {}: {e}", path.display())),
+        };
+
+        Ok(Some(match result {
+            Ok(_) => RevealMediaFileResponse {
+                file_id,
+                success: true,
+                error: None,
+            },
+            Err(e) => {
+                error!("Failed to reveal file {file_id}: {e:#}");
+                RevealMediaFileResponse {
+                    file_id,
+                    success: false,
+                    error: Some(format!("{e:#}")),
+                }
+            }
+        }))
+    }
+}
+
+impl ParamsExtractor for FetchMediaFileInfoRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for FetchMediaFileInfoRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = FetchMediaFileInfoResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let file_id = dart_signal.file_id;
+
+        let file = get_file_by_id(&main_db, file_id)
+            .await?
+            .with_context(|| format!("File not found: {file_id}"))?;
+        let path = Path::new(lib_path.as_str())
+            .join(&file.directory)
+            .join(&file.file_name);
+
+        // The stored details are still shown while the file is offline
+        let (path, size, codec) = match fsio.canonicalize(&path) {
+            Ok(node) => {
+                let codec = task::spawn_blocking({
+                    let fsio = Arc::clone(&fsio);
+                    let node = node.clone();
+                    move || get_codec_details_from_node(&fsio, &node)
+                })
+                .await
+                .with_context(|| "Failed to spawn the codec task")?;
+                if let Err(e) = &codec {
+                    warn!("Failed to read the codec of file {file_id}: {e:#}");
+                }
+                (node.path, Some(node.size), codec.ok())
+            }
+            Err(e) => {
+                warn!("File {file_id} not reachable: {e}");
+                (path, None, None)
+            }
+        };
+
+        Ok(Some(FetchMediaFileInfoResponse {
+            info: MediaFileInfo {
+                id: file.id,
+                path: path.to_string_lossy().into_owned(),
+                size,
+                codec: codec.as_ref().map(|x| x.codec.clone()).unwrap_or_default(),
+                sample_rate: codec
+                    .as_ref()
+                    .and_then(|x| x.sample_rate)
+                    .unwrap_or(file.sample_rate.max(0) as u32),
+                channels: codec.as_ref().and_then(|x| x.channels).map(u32::from),
+                bits_per_sample: codec.as_ref().and_then(|x| x.bits_per_sample),
+                duration: f64::try_from(file.duration).unwrap_or_default(),
+                file_hash: file.file_hash,
+                last_modified: file.last_modified,
+            },
+        }))
+    }
+}
@@ -5,7 +5,9 @@ use anyhow::{Context, Result};
 use ::database::{
     actions::{
         cover_art::bake_cover_art_by_media_files,
+        daily_mix::generate_daily_mixes,
         metadata::get_metadata_summary_by_files,
+        mix_presets::get_mix_presets,
         mixes::{
             add_item_to_mix, create_mix, get_all_mixes, get_mix_by_id, get_mix_queries_by_mix_id,
             query_mix_media_files, remove_mix, replace_mix_queries, update_mix,
@@ -49,6 +51,8 @@ impl Signal for FetchAllMixesRequest {
                     group: mix.group,
                     locked: mix.locked,
                     mode: mix.mode.expect("Mix mode not exists"),
+                    uuid: mix.hlc_uuid,
+                    cover: mix.cover,
                 })
                 .collect(),
         }))
@@ -86,6 +90,7 @@ impl Signal for CreateMixRequest {
             request.scriptlet_mode,
             request.mode,
             false,
+            request.cover.clone(),
         )
         .await
         .with_context(|| "Failed to create mix")?;
@@ -112,6 +117,8 @@ impl Signal for CreateMixRequest {
                 group: mix.group,
                 locked: mix.locked,
                 mode: mix.mode.expect("Mix mode not exists"),
+                uuid: mix.hlc_uuid,
+                cover: mix.cover,
             },
         }))
     }
@@ -149,6 +156,7 @@ impl Signal for UpdateMixRequest {
             Some(request.scriptlet_mode),
             Some(request.mode),
             Some(false),
+            Some(request.cover.clone()),
         )
         .await
         .with_context(|| "Failed to update mix metadata")?;
@@ -175,6 +183,8 @@ impl Signal for UpdateMixRequest {
                 group: mix.group,
                 locked: mix.locked,
                 mode: mix.mode.expect("Mix mode not exists"),
+                uuid: mix.hlc_uuid,
+                cover: mix.cover,
             },
         }))
     }
@@ -287,6 +297,8 @@ impl Signal for GetMixByIdRequest {
                 group: mix.group,
                 locked: mix.locked,
                 mode: mix.mode.expect("Mix mode not exists"),
+                uuid: mix.hlc_uuid,
+                cover: mix.cover,
             },
         }))
     }
@@ -397,3 +409,91 @@ impl Signal for FetchMixQueriesRequest {
         }))
     }
 }
+
+impl ParamsExtractor for FetchDailyMixesRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for FetchDailyMixesRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Response = FetchDailyMixesResponse;
+
+    async fn handle(
+        &self,
+        (main_db, node_id): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let mixes = generate_daily_mixes(&main_db, &node_id, dart_signal.force)
+            .await
+            .with_context(|| "Failed to generate daily mixes")?;
+
+        Ok(Some(FetchDailyMixesResponse {
+            mixes: mixes
+                .into_iter()
+                .map(|x| DailyMix {
+                    mix: Mix {
+                        id: x.mix.id,
+                        name: x.mix.name,
+                        group: x.mix.group,
+                        locked: x.mix.locked,
+                        mode: x.mix.mode.expect("Mix mode not exists"),
+                        uuid: x.mix.hlc_uuid,
+                        cover: x.mix.cover,
+                    },
+                    file_ids: x.file_ids,
+                })
+                .collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for FetchMixPresetsRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchMixPresetsRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchMixPresetsResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let presets = get_mix_presets(&main_db)
+            .await
+            .with_context(|| "Failed to fetch mix presets")?;
+
+        Ok(Some(FetchMixPresetsResponse {
+            presets: presets
+                .into_iter()
+                .map(|x| MixPreset {
+                    queries: x
+                        .queries()
+                        .into_iter()
+                        .map(|(operator, parameter)| MixQuery {
+                            operator,
+                            parameter,
+                        })
+                        .collect(),
+                    name: x.name,
+                    parameters: x.parameters,
+                    builtin: x.builtin,
+                })
+                .collect(),
+        }))
+    }
+}
@@ -3,6 +3,7 @@ mod collection;
 mod connection;
 mod cover_art;
 mod directory;
+mod handoff;
 mod library_home;
 mod library_manage;
 mod license;
@@ -11,10 +12,14 @@ mod lyric;
 mod media_file;
 mod mix;
 mod neighbors;
+mod page;
 mod playback;
 mod playlist;
 mod scrobble;
 mod search;
 mod sfx;
+mod source;
 mod stat;
 mod system;
+mod trash;
+mod vehicle;
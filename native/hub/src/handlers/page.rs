@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use ::database::{
+    actions::{
+        cover_art::bake_cover_art_by_file_ids,
+        pages::{self, get_album_page, get_artist_page},
+    },
+    connection::MainDbConnection,
+};
+use ::fsio::FsIo;
+
+use crate::{
+    Session, Signal,
+    messages::*,
+    utils::{GlobalParams, ParamsExtractor, parse_media_files},
+};
+
+impl From<pages::PageStats> for PageStats {
+    fn from(x: pages::PageStats) -> Self {
+        PageStats {
+            played_through: x.played_through,
+            skipped: x.skipped,
+            liked_tracks: x.liked_tracks,
+            last_played_at: x.last_played_at.unwrap_or_default(),
+        }
+    }
+}
+
+impl ParamsExtractor for FetchAlbumPageRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for FetchAlbumPageRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = FetchAlbumPageResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let Some(page) = get_album_page(&main_db, request.id)
+            .await
+            .with_context(|| format!("Failed to fetch album page: {}", request.id))?
+        else {
+            return Ok(Some(FetchAlbumPageResponse {
+                album: None,
+                tracks: Vec::new(),
+                total_duration: 0.0,
+                year: None,
+                cover_art_id: -1,
+                artists: Vec::new(),
+                stats: pages::PageStats::default().into(),
+                cover_art_map: HashMap::new(),
+            }));
+        };
+
+        let cover_art_map = if request.bake_cover_arts {
+            bake_cover_art_by_file_ids(&fsio, &main_db, page.tracks.iter().map(|x| x.id).collect())
+                .await?
+        } else {
+            HashMap::new()
+        };
+
+        let tracks = parse_media_files(&fsio, page.tracks, lib_path)
+            .await
+            .with_context(|| "Failed to parse media summaries")?;
+
+        Ok(Some(FetchAlbumPageResponse {
+            album: Some(Album {
+                id: page.album.id,
+                name: page.album.name,
+            }),
+            tracks,
+            total_duration: page.total_duration,
+            year: page.year,
+            cover_art_id: page.cover_art_id.unwrap_or(-1),
+            artists: page
+                .artists
+                .into_iter()
+                .map(|x| Artist {
+                    id: x.id,
+                    name: x.name,
+                })
+                .collect(),
+            stats: page.stats.into(),
+            cover_art_map,
+        }))
+    }
+}
+
+impl ParamsExtractor for FetchArtistPageRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for FetchArtistPageRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = FetchArtistPageResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let Some(page) = get_artist_page(&main_db, request.id)
+            .await
+            .with_context(|| format!("Failed to fetch artist page: {}", request.id))?
+        else {
+            return Ok(Some(FetchArtistPageResponse {
+                artist: None,
+                albums: Vec::new(),
+                tracks: Vec::new(),
+                total_duration: 0.0,
+                stats: pages::PageStats::default().into(),
+                cover_art_map: HashMap::new(),
+            }));
+        };
+
+        let cover_art_map = if request.bake_cover_arts {
+            bake_cover_art_by_file_ids(&fsio, &main_db, page.tracks.iter().map(|x| x.id).collect())
+                .await?
+        } else {
+            HashMap::new()
+        };
+
+        let tracks = parse_media_files(&fsio, page.tracks, lib_path)
+            .await
+            .with_context(|| "Failed to parse media summaries")?;
+
+        Ok(Some(FetchArtistPageResponse {
+            artist: Some(Artist {
+                id: page.artist.id,
+                name: page.artist.name,
+            }),
+            albums: page
+                .albums
+                .into_iter()
+                .map(|x| ArtistAlbum {
+                    album: Album {
+                        id: x.album.id,
+                        name: x.album.name,
+                    },
+                    year: x.year,
+                    cover_art_id: x.cover_art_id.unwrap_or(-1),
+                    track_count: x.track_count,
+                })
+                .collect(),
+            tracks,
+            total_duration: page.total_duration,
+            stats: page.stats.into(),
+            cover_art_map,
+        }))
+    }
+}
@@ -1,23 +1,49 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
+use chrono::{Local, Timelike};
 use fsio::FsIo;
 use tokio::sync::Mutex;
+use tokio::task;
+use tokio_util::sync::CancellationToken;
 
 use ::database::{
-    actions::{mixes::query_mix_media_files, stats::increase_skipped},
+    actions::{
+        file::{RandomWeighting, get_file_weights},
+        mixes::query_mix_media_files,
+        radio::{RADIO_LENGTH, RADIO_REPEAT_WINDOW, RadioSeed},
+        settings::{
+            EQUALIZER_KEY, NIGHT_MODE_KEY, OUTPUT_DEVICE_KEY, REPLAY_GAIN_KEY, get_setting,
+            set_setting,
+        },
+        stations::{StationSource, list_stations},
+        stats::record_playback_skip,
+    },
     connection::{MainDbConnection, RecommendationDbConnection},
-    playing_item::dispatcher::PlayingItemActionDispatcher,
+    playing_item::{MediaFileHandle, dispatcher::PlayingItemActionDispatcher},
 };
 use ::playback::{
+    audition::AuditionConfig,
+    crossfade::MAX_CROSSFADE,
+    equalizer::{EQUALIZER_BANDS, EqualizerConfig, EqualizerPreset, MAX_BAND_GAIN_DB},
+    exposure::{ExposureStatus, ExposureWarning, HearingProtectionConfig},
+    night_mode::NightModeConfig,
+    output_stream::list_output_devices,
     player::{Playable, PlayingItem},
-    strategies::AddMode,
+    replay_gain::ReplayGainConfig,
+    strategies::{AddMode, RepeatMode, ShuffleMode},
 };
 
 use crate::{
     Session, Signal,
     messages::*,
-    utils::{GlobalParams, ParamsExtractor, files_to_playback_request, find_nearest_index},
+    utils::{
+        GlobalParams, ParamsExtractor, TaskTokens, files_to_playback_request, find_nearest_index,
+        get_ordered_file_handles,
+        player::{get_saved_equalizer, get_saved_night_mode, get_saved_replay_gain},
+        radio::{Radio, RadioSource, keep_radio_playing},
+    },
 };
 
 impl From<PlayingItem> for PlayingItemRequest {
@@ -151,9 +177,9 @@ impl Signal for NextRequest {
         let item = player.lock().await.get_status().item;
 
         if let Some(PlayingItem::InLibrary(file_id)) = item {
-            increase_skipped(&main_db, file_id)
+            record_playback_skip(&main_db, file_id)
                 .await
-                .context("Unable to increase skipped count")?;
+                .context("Unable to record skipped playback")?;
         }
 
         player.lock().await.next();
@@ -185,9 +211,9 @@ impl Signal for PreviousRequest {
         let item = player.lock().await.get_status().item;
 
         if let Some(PlayingItem::InLibrary(file_id)) = item {
-            increase_skipped(&main_db, file_id)
+            record_playback_skip(&main_db, file_id)
                 .await
-                .context("Unable to increase skipped count")?;
+                .context("Unable to record skipped playback")?;
         }
 
         player.lock().await.previous();
@@ -219,6 +245,80 @@ impl Signal for SetPlaybackModeRequest {
     }
 }
 
+impl ParamsExtractor for SetRepeatModeRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for SetRepeatModeRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let mode = RepeatMode::from(dart_signal.mode);
+        player.lock().await.set_repeat_mode(mode);
+        Ok(Some(()))
+    }
+}
+
+impl ParamsExtractor for SetShuffleModeRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for SetShuffleModeRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (main_db, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let mode = ShuffleMode::from(dart_signal.mode);
+        let mut player = player.lock().await;
+
+        if mode == ShuffleMode::Weighted {
+            let file_ids: Vec<i32> = player
+                .get_playlist()
+                .into_iter()
+                .filter_map(|x| match x {
+                    PlayingItem::InLibrary(id) => Some(id),
+                    _ => None,
+                })
+                .collect();
+
+            let weights = get_file_weights(&main_db, &file_ids, RandomWeighting::Rating)
+                .await
+                .context("Failed to get shuffle weights")?;
+            player.update_shuffle_weights(
+                weights
+                    .into_iter()
+                    .map(|(id, weight)| (PlayingItem::InLibrary(id), weight))
+                    .collect(),
+            );
+        }
+
+        player.set_shuffle_mode(mode);
+        Ok(Some(()))
+    }
+}
+
 impl ParamsExtractor for SwitchRequest {
     type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
 
@@ -241,9 +341,9 @@ impl Signal for SwitchRequest {
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
         if let Some(PlayingItem::InLibrary(file_id)) = player.lock().await.get_status().item {
-            increase_skipped(&main_db, file_id)
+            record_playback_skip(&main_db, file_id)
                 .await
-                .context("Unable to increase skipped count")?;
+                .context("Unable to record skipped playback")?;
         }
 
         player
@@ -328,6 +428,72 @@ impl Signal for VolumeRequest {
     }
 }
 
+impl ParamsExtractor for ListOutputDevicesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for ListOutputDevicesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = ListOutputDevicesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let devices = list_output_devices()
+            .with_context(|| "Failed to list output devices")?
+            .into_iter()
+            .map(|x| OutputDevice {
+                name: x.name,
+                is_default: x.is_default,
+            })
+            .collect();
+        let selected = get_setting(&main_db, OUTPUT_DEVICE_KEY)
+            .await
+            .with_context(|| "Failed to get the selected output device")?;
+
+        Ok(Some(ListOutputDevicesResponse { devices, selected }))
+    }
+}
+
+impl ParamsExtractor for SetOutputDeviceRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for SetOutputDeviceRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Response = SetOutputDeviceResponse;
+
+    async fn handle(
+        &self,
+        (main_db, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let name = dart_signal.name.clone().filter(|x| !x.is_empty());
+
+        set_setting(&main_db, OUTPUT_DEVICE_KEY, name.as_deref())
+            .await
+            .with_context(|| "Failed to save the output device")?;
+        player.lock().await.set_output_device(name.clone());
+
+        Ok(Some(SetOutputDeviceResponse { name }))
+    }
+}
+
 impl ParamsExtractor for MovePlaylistItemRequest {
     type Params = (Arc<Mutex<dyn Playable>>,);
 
@@ -358,6 +524,91 @@ impl Signal for MovePlaylistItemRequest {
     }
 }
 
+impl ParamsExtractor for AddToQueueRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for AddToQueueRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+    );
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let items: Vec<PlayingItem> = dart_signal
+            .items
+            .clone()
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        let tracks = get_ordered_file_handles(&fsio, &main_db, &items)
+            .await
+            .with_context(|| "Failed to resolve queued items")?;
+
+        let mut player = player.lock().await;
+        let add_mode = match dart_signal.operate_mode {
+            PlaylistOperateMode::PlayNext => AddMode::PlayNext,
+            PlaylistOperateMode::AppendToEnd => AddMode::AppendToEnd,
+            PlaylistOperateMode::Replace => {
+                player.clear_playlist();
+                AddMode::AppendToEnd
+            }
+        };
+
+        player.add_to_playlist(
+            files_to_playback_request(&fsio, lib_path.as_ref(), &tracks),
+            add_mode,
+        );
+        Ok(Some(()))
+    }
+}
+
+impl ParamsExtractor for ClearQueueRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for ClearQueueRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        player.lock().await.clear_playlist();
+        Ok(Some(()))
+    }
+}
+
 impl ParamsExtractor for SetRealtimeFFTEnabledRequest {
     type Params = (Arc<Mutex<dyn Playable>>,);
 
@@ -406,6 +657,880 @@ impl Signal for SetAdaptiveSwitchingEnabledRequest {
     }
 }
 
+impl ParamsExtractor for SetGaplessEnabledRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for SetGaplessEnabledRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let enabled = dart_signal.enabled;
+        player.lock().await.set_gapless_enabled(enabled);
+        Ok(Some(()))
+    }
+}
+
+impl ParamsExtractor for SetCrossfadeRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for SetCrossfadeRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let seconds = dart_signal.seconds.clamp(0.0, MAX_CROSSFADE.as_secs_f32());
+        player
+            .lock()
+            .await
+            .set_crossfade(Duration::from_secs_f32(seconds));
+        Ok(Some(()))
+    }
+}
+
+impl ParamsExtractor for StartMixAuditionRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.recommend_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for StartMixAuditionRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+    );
+    type Response = StartMixAuditionResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, recommend_db, lib_path, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let tracks: Vec<MediaFileHandle> = query_mix_media_files(
+            &main_db,
+            &recommend_db,
+            request
+                .queries
+                .iter()
+                .map(|x| (x.operator.clone(), x.parameter.clone()))
+                .collect(),
+            0,
+            4096,
+        )
+        .await
+        .with_context(|| format!("Failed to query tracks: {:?}", request.queries))?
+        .into_iter()
+        .map(|x| x.into())
+        .collect();
+
+        let mut player = player.lock().await;
+
+        // Clearing the playlist ends a running audition, so it starts afterwards
+        player.clear_playlist();
+        player.set_audition(Some(AuditionConfig::default()));
+
+        let items: Vec<PlayingItemRequest> = tracks.iter().map(|x| x.clone().item.into()).collect();
+
+        if !tracks.is_empty() {
+            player.add_to_playlist(
+                files_to_playback_request(&fsio, lib_path.as_ref(), &tracks),
+                AddMode::AppendToEnd,
+            );
+            player.switch(0);
+            player.play();
+        }
+
+        Ok(Some(StartMixAuditionResponse {
+            playing_items: items,
+        }))
+    }
+}
+
+impl ParamsExtractor for StopMixAuditionRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for StopMixAuditionRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        _: &Self,
+    ) -> Result<Option<Self::Response>> {
+        player.lock().await.set_audition(None);
+        Ok(Some(()))
+    }
+}
+
+/// Replace the playlist with the radio of `source` and start playing, then
+/// keep queuing more tracks in the background.
+#[allow(clippy::too_many_arguments)]
+async fn start_radio(
+    fsio: Arc<FsIo>,
+    main_db: Arc<MainDbConnection>,
+    recommend_db: Arc<RecommendationDbConnection>,
+    lib_path: Arc<String>,
+    player: Arc<Mutex<dyn Playable>>,
+    task_tokens: Arc<Mutex<TaskTokens>>,
+    source: RadioSource,
+    options: &RadioOptions,
+) -> Result<Vec<PlayingItemRequest>> {
+    let mut radio = Radio::new(
+        source,
+        options
+            .repeat_window
+            .map(|x| x as usize)
+            .unwrap_or(RADIO_REPEAT_WINDOW),
+        options.excluded_file_ids.iter().copied().collect(),
+    );
+    let tracks = radio
+        .next_tracks(&main_db, &recommend_db, RADIO_LENGTH)
+        .await?;
+
+    // Only one radio keeps the playlist filled at a time
+    let token = CancellationToken::new();
+    if let Some(previous) = task_tokens.lock().await.radio_token.replace(token.clone()) {
+        previous.cancel();
+    }
+
+    {
+        let mut player = player.lock().await;
+        player.clear_playlist();
+
+        if !tracks.is_empty() {
+            player.add_to_playlist(
+                files_to_playback_request(&fsio, &lib_path.as_str(), &tracks),
+                AddMode::AppendToEnd,
+            );
+            player.switch(0);
+            player.play();
+        }
+    }
+
+    if !tracks.is_empty() {
+        task::spawn(keep_radio_playing(
+            fsio,
+            main_db,
+            recommend_db,
+            lib_path,
+            player,
+            radio,
+            token,
+        ));
+    }
+
+    Ok(tracks.into_iter().map(|x| x.item.into()).collect())
+}
+
+impl ParamsExtractor for StartTrackRadioRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<Mutex<TaskTokens>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.recommend_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.task_tokens),
+        )
+    }
+}
+
+impl Signal for StartTrackRadioRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<Mutex<TaskTokens>>,
+    );
+    type Response = StartTrackRadioResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, recommend_db, lib_path, player, task_tokens): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let file_id = dart_signal.file_id;
+        let playing_items = start_radio(
+            fsio,
+            main_db,
+            recommend_db,
+            lib_path,
+            player,
+            task_tokens,
+            RadioSource::Seed(RadioSeed::Track(file_id)),
+            &dart_signal.options,
+        )
+        .await
+        .with_context(|| format!("Failed to start the radio of track: {file_id}"))?;
+
+        Ok(Some(StartTrackRadioResponse { playing_items }))
+    }
+}
+
+impl ParamsExtractor for StartArtistRadioRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<Mutex<TaskTokens>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.recommend_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.task_tokens),
+        )
+    }
+}
+
+impl Signal for StartArtistRadioRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<Mutex<TaskTokens>>,
+    );
+    type Response = StartArtistRadioResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, recommend_db, lib_path, player, task_tokens): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let artist_id = dart_signal.artist_id;
+        let playing_items = start_radio(
+            fsio,
+            main_db,
+            recommend_db,
+            lib_path,
+            player,
+            task_tokens,
+            RadioSource::Seed(RadioSeed::Artist(artist_id)),
+            &dart_signal.options,
+        )
+        .await
+        .with_context(|| format!("Failed to start the radio of artist: {artist_id}"))?;
+
+        Ok(Some(StartArtistRadioResponse { playing_items }))
+    }
+}
+
+impl ParamsExtractor for StartAlbumRadioRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<Mutex<TaskTokens>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.recommend_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.task_tokens),
+        )
+    }
+}
+
+impl Signal for StartAlbumRadioRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<Mutex<TaskTokens>>,
+    );
+    type Response = StartAlbumRadioResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, recommend_db, lib_path, player, task_tokens): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let album_id = dart_signal.album_id;
+        let playing_items = start_radio(
+            fsio,
+            main_db,
+            recommend_db,
+            lib_path,
+            player,
+            task_tokens,
+            RadioSource::Seed(RadioSeed::Album(album_id)),
+            &dart_signal.options,
+        )
+        .await
+        .with_context(|| format!("Failed to start the radio of album: {album_id}"))?;
+
+        Ok(Some(StartAlbumRadioResponse { playing_items }))
+    }
+}
+
+impl ParamsExtractor for FetchStationsRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchStationsRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchStationsResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let stations = list_stations(&main_db, dart_signal.limit.max(0) as usize)
+            .await
+            .context("Failed to list the stations")?;
+
+        Ok(Some(FetchStationsResponse {
+            stations: stations
+                .into_iter()
+                .map(|x| Station {
+                    key: x.source.to_string(),
+                    name: x.name,
+                    track_count: x.track_count as i32,
+                })
+                .collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for StartStationRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<Mutex<TaskTokens>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.recommend_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.task_tokens),
+        )
+    }
+}
+
+impl Signal for StartStationRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<Mutex<TaskTokens>>,
+    );
+    type Response = StartStationResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, recommend_db, lib_path, player, task_tokens): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let key = &dart_signal.key;
+        let source: StationSource = key.parse()?;
+        let playing_items = start_radio(
+            fsio,
+            main_db,
+            recommend_db,
+            lib_path,
+            player,
+            task_tokens,
+            RadioSource::station(source),
+            &dart_signal.options,
+        )
+        .await
+        .with_context(|| format!("Failed to start the station: {key}"))?;
+
+        Ok(Some(StartStationResponse { playing_items }))
+    }
+}
+
+impl ParamsExtractor for StopRadioRequest {
+    type Params = (Arc<Mutex<TaskTokens>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.task_tokens),)
+    }
+}
+
+impl Signal for StopRadioRequest {
+    type Params = (Arc<Mutex<TaskTokens>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (task_tokens,): Self::Params,
+        _session: Option<Session>,
+        _: &Self,
+    ) -> Result<Option<Self::Response>> {
+        if let Some(token) = task_tokens.lock().await.radio_token.take() {
+            token.cancel();
+        }
+        Ok(Some(()))
+    }
+}
+
+impl From<NightModeConfig> for NightModeSettings {
+    fn from(x: NightModeConfig) -> Self {
+        NightModeSettings {
+            enabled: x.enabled,
+            start_hour: x.start_hour,
+            end_hour: x.end_hour,
+            threshold_db: x.threshold_db,
+            ratio: x.ratio,
+            makeup_db: x.makeup_db,
+            ceiling_db: x.ceiling_db,
+        }
+    }
+}
+
+impl From<&NightModeSettings> for NightModeConfig {
+    fn from(x: &NightModeSettings) -> Self {
+        NightModeConfig {
+            enabled: x.enabled,
+            start_hour: x.start_hour % 24,
+            end_hour: x.end_hour % 24,
+            threshold_db: x.threshold_db,
+            ratio: x.ratio,
+            makeup_db: x.makeup_db,
+            ceiling_db: x.ceiling_db,
+        }
+    }
+}
+
+impl ParamsExtractor for SetNightModeRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for SetNightModeRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Response = SetNightModeResponse;
+
+    async fn handle(
+        &self,
+        (main_db, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let config: NightModeConfig = (&dart_signal.settings).into();
+        let settings: NightModeSettings = config.into();
+
+        set_setting(
+            &main_db,
+            NIGHT_MODE_KEY,
+            Some(&serde_json::to_string(&settings)?),
+        )
+        .await
+        .with_context(|| "Failed to save the night mode settings")?;
+        player.lock().await.set_night_mode(config);
+
+        Ok(Some(SetNightModeResponse {
+            active: config.is_active_at(Local::now().hour()),
+        }))
+    }
+}
+
+impl ParamsExtractor for GetNightModeRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for GetNightModeRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = GetNightModeResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let config = get_saved_night_mode(&main_db).await?.unwrap_or_default();
+
+        Ok(Some(GetNightModeResponse {
+            settings: config.into(),
+            active: config.is_active_at(Local::now().hour()),
+        }))
+    }
+}
+
+impl From<EqualizerConfig> for EqualizerSettings {
+    fn from(x: EqualizerConfig) -> Self {
+        EqualizerSettings {
+            enabled: x.enabled,
+            preset: x.preset.to_string(),
+            preamp_db: x.preamp_db,
+            gains_db: x.gains_db.to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&EqualizerSettings> for EqualizerConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(x: &EqualizerSettings) -> Result<Self> {
+        let preset: EqualizerPreset = x.preset.parse()?;
+
+        // Missing bands stay flat, extra ones are ignored
+        let mut gains_db = [0.0; EQUALIZER_BANDS.len()];
+        for (gain, value) in gains_db.iter_mut().zip(&x.gains_db) {
+            *gain = *value;
+        }
+
+        Ok(EqualizerConfig::new(
+            x.enabled,
+            preset,
+            x.preamp_db,
+            gains_db,
+        ))
+    }
+}
+
+impl ParamsExtractor for SetEqualizerRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for SetEqualizerRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Response = SetEqualizerResponse;
+
+    async fn handle(
+        &self,
+        (main_db, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let config: EqualizerConfig = (&dart_signal.settings).try_into()?;
+        let settings: EqualizerSettings = config.into();
+
+        set_setting(
+            &main_db,
+            EQUALIZER_KEY,
+            Some(&serde_json::to_string(&settings)?),
+        )
+        .await
+        .with_context(|| "Failed to save the equalizer settings")?;
+        player.lock().await.set_equalizer(config);
+
+        Ok(Some(SetEqualizerResponse { settings }))
+    }
+}
+
+impl ParamsExtractor for GetEqualizerRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for GetEqualizerRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = GetEqualizerResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let config = get_saved_equalizer(&main_db).await?.unwrap_or_default();
+
+        Ok(Some(GetEqualizerResponse {
+            settings: config.into(),
+            bands: EQUALIZER_BANDS.to_vec(),
+            presets: [
+                EqualizerPreset::Flat,
+                EqualizerPreset::BassBoost,
+                EqualizerPreset::TrebleBoost,
+                EqualizerPreset::Vocal,
+                EqualizerPreset::Rock,
+                EqualizerPreset::Pop,
+                EqualizerPreset::Classical,
+                EqualizerPreset::Electronic,
+                EqualizerPreset::Custom,
+            ]
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+            max_gain_db: MAX_BAND_GAIN_DB,
+        }))
+    }
+}
+
+impl From<ExposureStatus> for ListeningExposureSummary {
+    fn from(x: ExposureStatus) -> Self {
+        ListeningExposureSummary {
+            date: x.date.map(|x| x.to_string()).unwrap_or_default(),
+            listening_seconds: x.listening_seconds,
+            average_level_db: x.average_level_db,
+            dose: x.dose,
+            warning: x.warning.to_string(),
+        }
+    }
+}
+
+impl TryFrom<&ListeningExposureSummary> for ExposureStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(x: &ListeningExposureSummary) -> Result<Self> {
+        Ok(ExposureStatus {
+            date: Some(x.date.parse()?),
+            listening_seconds: x.listening_seconds,
+            average_level_db: x.average_level_db,
+            dose: x.dose,
+            warning: ExposureWarning::from_dose(x.dose),
+        })
+    }
+}
+
+impl ParamsExtractor for SetHearingProtectionRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for SetHearingProtectionRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        player
+            .lock()
+            .await
+            .set_hearing_protection(HearingProtectionConfig {
+                full_scale_spl_db: dart_signal.full_scale_spl_db,
+                auto_reduce: dart_signal.auto_reduce,
+                reduced_volume: dart_signal.reduced_volume.clamp(0.0, 1.0),
+            });
+
+        Ok(Some(()))
+    }
+}
+
+impl From<ReplayGainConfig> for ReplayGainSettings {
+    fn from(x: ReplayGainConfig) -> Self {
+        ReplayGainSettings {
+            mode: x.mode.to_string(),
+            preamp_db: x.preamp_db,
+            prevent_clipping: x.prevent_clipping,
+        }
+    }
+}
+
+impl TryFrom<&ReplayGainSettings> for ReplayGainConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(x: &ReplayGainSettings) -> Result<Self> {
+        Ok(ReplayGainConfig {
+            mode: x.mode.parse()?,
+            preamp_db: x.preamp_db,
+            prevent_clipping: x.prevent_clipping,
+        })
+    }
+}
+
+impl ParamsExtractor for SetReplayGainRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for SetReplayGainRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (main_db, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let config: ReplayGainConfig = (&dart_signal.settings).try_into()?;
+        let settings: ReplayGainSettings = config.into();
+
+        set_setting(
+            &main_db,
+            REPLAY_GAIN_KEY,
+            Some(&serde_json::to_string(&settings)?),
+        )
+        .await
+        .with_context(|| "Failed to save the ReplayGain settings")?;
+        player.lock().await.set_replay_gain(config);
+
+        Ok(Some(()))
+    }
+}
+
+impl ParamsExtractor for GetReplayGainRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for GetReplayGainRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = GetReplayGainResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let config = get_saved_replay_gain(&main_db).await?.unwrap_or_default();
+
+        Ok(Some(GetReplayGainResponse {
+            settings: config.into(),
+        }))
+    }
+}
+
+impl ParamsExtractor for FetchListeningExposureRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for FetchListeningExposureRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = FetchListeningExposureResponse;
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let exposure = player.lock().await.get_status().exposure;
+
+        Ok(Some(FetchListeningExposureResponse {
+            exposure: exposure.into(),
+        }))
+    }
+}
+
 impl ParamsExtractor for OperatePlaybackWithMixQueryRequest {
     type Params = (
         Arc<FsIo>,
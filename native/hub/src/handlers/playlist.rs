@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -5,13 +6,28 @@ use anyhow::{Context, Result, anyhow};
 use database::actions::playlists::remove_item_from_playlist;
 use sea_orm::TransactionTrait;
 
+use ::database::actions::file::get_files_by_ids;
+use ::database::actions::metadata::get_metadata_summary_by_files;
+use ::database::actions::playlist_bundle::{
+    PlaylistBundleTrack, export_playlist_bundle, import_playlist_bundle, read_playlist_bundle,
+    write_playlist_bundle,
+};
+use ::database::actions::playlist_mirror::{
+    MirrorSide, MirrorSyncOutcome, add_playlist_mirror, get_playlist_mirror, list_playlist_mirrors,
+    remove_playlist_mirror, resolve_playlist_mirror_conflict, sync_playlist_mirrors,
+};
 use ::database::actions::playlists::{
-    add_item_to_playlist, create_m3u8_playlist, create_playlist, get_all_playlists,
-    get_playlist_by_id, remove_playlist, reorder_playlist_item_position, update_playlist,
+    add_item_to_playlist, create_m3u8_playlist, create_playlist, create_smart_playlist,
+    get_all_playlists, get_all_smart_playlists, get_playlist_by_id, get_smart_playlist_file_ids,
+    remove_playlist, remove_smart_playlist, reorder_playlist_item_position, update_playlist,
+    update_smart_playlist,
 };
 use ::database::connection::MainDbConnection;
+use ::database::entities::{playlist_mirrors, smart_playlists};
+use ::database::events::{EventBus, LibraryEvent};
+use ::fsio::FsIo;
 
-use crate::utils::{GlobalParams, ParamsExtractor};
+use crate::utils::{GlobalParams, ParamsExtractor, parse_media_files};
 use crate::{Session, Signal, messages::*};
 
 impl ParamsExtractor for FetchAllPlaylistsRequest {
@@ -49,22 +65,23 @@ impl Signal for FetchAllPlaylistsRequest {
 }
 
 impl ParamsExtractor for CreatePlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
+            Arc::clone(&all_params.events),
         )
     }
 }
 
 impl Signal for CreatePlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
     type Response = CreatePlaylistResponse;
     async fn handle(
         &self,
-        (main_db, node_id): Self::Params,
+        (main_db, node_id, events): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -80,6 +97,9 @@ impl Signal for CreatePlaylistRequest {
                 format!("Failed to create playlist: name={name}, group={group}")
             })?;
         txn.commit().await?;
+        events.publish(LibraryEvent::PlaylistUpdated {
+            playlist_id: playlist.id,
+        });
 
         Ok(Some(CreatePlaylistResponse {
             playlist: Playlist {
@@ -92,22 +112,23 @@ impl Signal for CreatePlaylistRequest {
 }
 
 impl ParamsExtractor for UpdatePlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
+            Arc::clone(&all_params.events),
         )
     }
 }
 
 impl Signal for UpdatePlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
     type Response = UpdatePlaylistResponse;
     async fn handle(
         &self,
-        (main_db, node_id): Self::Params,
+        (main_db, node_id, events): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -130,6 +151,9 @@ impl Signal for UpdatePlaylistRequest {
                 request.playlist_id, name, group
             )
         })?;
+        events.publish(LibraryEvent::PlaylistUpdated {
+            playlist_id: playlist.id,
+        });
 
         Ok(Some(UpdatePlaylistResponse {
             playlist: Playlist {
@@ -142,19 +166,22 @@ impl Signal for UpdatePlaylistRequest {
 }
 
 impl ParamsExtractor for RemovePlaylistRequest {
-    type Params = (Arc<MainDbConnection>,);
+    type Params = (Arc<MainDbConnection>, Arc<EventBus>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.main_db),)
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.events),
+        )
     }
 }
 
 impl Signal for RemovePlaylistRequest {
-    type Params = (Arc<MainDbConnection>,);
+    type Params = (Arc<MainDbConnection>, Arc<EventBus>);
     type Response = RemovePlaylistResponse;
     async fn handle(
         &self,
-        (main_db,): Self::Params,
+        (main_db, events): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -163,6 +190,9 @@ impl Signal for RemovePlaylistRequest {
         remove_playlist(&main_db, request.playlist_id)
             .await
             .with_context(|| format!("Removing playlist: id={}", request.playlist_id))?;
+        events.publish(LibraryEvent::PlaylistUpdated {
+            playlist_id: request.playlist_id,
+        });
 
         Ok(Some(RemovePlaylistResponse {
             playlist_id: request.playlist_id,
@@ -172,22 +202,23 @@ impl Signal for RemovePlaylistRequest {
 }
 
 impl ParamsExtractor for AddItemToPlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
+            Arc::clone(&all_params.events),
         )
     }
 }
 
 impl Signal for AddItemToPlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
     type Response = AddItemToPlaylistResponse;
     async fn handle(
         &self,
-        (main_db, node_id): Self::Params,
+        (main_db, node_id, events): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -207,25 +238,31 @@ impl Signal for AddItemToPlaylistRequest {
                 request.playlist_id, request.media_file_id, request.position
             )
         })?;
+        events.publish(LibraryEvent::PlaylistUpdated {
+            playlist_id: request.playlist_id,
+        });
 
         Ok(Some(AddItemToPlaylistResponse { success: true }))
     }
 }
 
 impl ParamsExtractor for ReorderPlaylistItemPositionRequest {
-    type Params = (Arc<MainDbConnection>,);
+    type Params = (Arc<MainDbConnection>, Arc<EventBus>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.main_db),)
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.events),
+        )
     }
 }
 
 impl Signal for ReorderPlaylistItemPositionRequest {
-    type Params = (Arc<MainDbConnection>,);
+    type Params = (Arc<MainDbConnection>, Arc<EventBus>);
     type Response = ReorderPlaylistItemPositionResponse;
     async fn handle(
         &self,
-        (main_db,): Self::Params,
+        (main_db, events): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -244,6 +281,9 @@ impl Signal for ReorderPlaylistItemPositionRequest {
                 request.playlist_id, request.media_file_id, request.new_position
             )
         })?;
+        events.publish(LibraryEvent::PlaylistUpdated {
+            playlist_id: request.playlist_id,
+        });
 
         Ok(Some(ReorderPlaylistItemPositionResponse { success: true }))
     }
@@ -287,22 +327,23 @@ impl Signal for GetPlaylistByIdRequest {
 }
 
 impl ParamsExtractor for CreateM3u8PlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
+            Arc::clone(&all_params.events),
         )
     }
 }
 
 impl Signal for CreateM3u8PlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
     type Response = CreateM3u8PlaylistResponse;
     async fn handle(
         &self,
-        (main_db, node_id): Self::Params,
+        (main_db, node_id, events): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -321,17 +362,23 @@ impl Signal for CreateM3u8PlaylistRequest {
         )
         .await
         {
-            Ok((playlist, import_result)) => Ok(Some(CreateM3u8PlaylistResponse {
-                playlist: Some(Playlist {
-                    id: playlist.id,
-                    name: playlist.name,
-                    group: playlist.group,
-                }),
-                imported_count: Some(import_result.matched_ids.len() as i32),
-                not_found_paths: import_result.unmatched_paths,
-                success: true,
-                error: String::new(),
-            })),
+            Ok((playlist, import_result)) => {
+                events.publish(LibraryEvent::PlaylistUpdated {
+                    playlist_id: playlist.id,
+                });
+
+                Ok(Some(CreateM3u8PlaylistResponse {
+                    playlist: Some(Playlist {
+                        id: playlist.id,
+                        name: playlist.name,
+                        group: playlist.group,
+                    }),
+                    imported_count: Some(import_result.matched_ids.len() as i32),
+                    not_found_paths: import_result.unmatched_paths,
+                    success: true,
+                    error: String::new(),
+                }))
+            }
             Err(e) => Ok(Some(CreateM3u8PlaylistResponse {
                 playlist: None,
                 imported_count: Some(0),
@@ -344,23 +391,24 @@ impl Signal for CreateM3u8PlaylistRequest {
 }
 
 impl ParamsExtractor for RemoveItemFromPlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
+            Arc::clone(&all_params.events),
         )
     }
 }
 
 impl Signal for RemoveItemFromPlaylistRequest {
-    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
     type Response = RemoveItemFromPlaylistResponse;
 
     async fn handle(
         &self,
-        (main_db, node_id): Self::Params,
+        (main_db, node_id, events): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -375,13 +423,574 @@ impl Signal for RemoveItemFromPlaylistRequest {
         )
         .await
         {
-            Ok(_) => Ok(Some(RemoveItemFromPlaylistResponse {
+            Ok(_) => {
+                events.publish(LibraryEvent::PlaylistUpdated {
+                    playlist_id: request.playlist_id,
+                });
+
+                Ok(Some(RemoveItemFromPlaylistResponse {
+                    success: true,
+                    error: String::new(),
+                }))
+            }
+            Err(e) => Ok(Some(RemoveItemFromPlaylistResponse {
+                success: false,
+                error: e.to_string(),
+            })),
+        }
+    }
+}
+
+impl ParamsExtractor for ExportPlaylistBundleRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for ExportPlaylistBundleRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Response = ExportPlaylistBundleResponse;
+    async fn handle(
+        &self,
+        (main_db, node_id): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let result = async {
+            let bundle =
+                export_playlist_bundle(&main_db, &node_id, request.playlist_id, &request.secret)
+                    .await?;
+            write_playlist_bundle(&bundle, Path::new(&request.path)).await?;
+            Ok::<_, anyhow::Error>(bundle.tracks.len())
+        }
+        .await;
+
+        match result {
+            Ok(count) => Ok(Some(ExportPlaylistBundleResponse {
+                exported_count: count as i32,
                 success: true,
                 error: String::new(),
             })),
-            Err(e) => Ok(Some(RemoveItemFromPlaylistResponse {
+            Err(e) => Ok(Some(ExportPlaylistBundleResponse {
+                exported_count: 0,
                 success: false,
-                error: e.to_string(),
+                error: format!("{e:#}"),
+            })),
+        }
+    }
+}
+
+impl ParamsExtractor for ImportPlaylistBundleRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.node_id),
+            Arc::clone(&all_params.events),
+        )
+    }
+}
+
+impl Signal for ImportPlaylistBundleRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<EventBus>);
+    type Response = ImportPlaylistBundleResponse;
+    async fn handle(
+        &self,
+        (main_db, node_id, events): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let result = async {
+            let bundle = read_playlist_bundle(Path::new(&request.path)).await?;
+            import_playlist_bundle(&main_db, &node_id, &bundle, &request.secret).await
+        }
+        .await;
+
+        match result {
+            Ok((playlist, import_result)) => {
+                events.publish(LibraryEvent::PlaylistUpdated {
+                    playlist_id: playlist.id,
+                });
+
+                Ok(Some(ImportPlaylistBundleResponse {
+                    playlist: Some(Playlist {
+                        id: playlist.id,
+                        name: playlist.name,
+                        group: playlist.group,
+                    }),
+                    imported_count: import_result.matched.len() as i32,
+                    unmatched_tracks: import_result
+                        .unmatched
+                        .into_iter()
+                        .map(to_unmatched_bundle_track)
+                        .collect(),
+                    duplicate_tracks: import_result
+                        .duplicates
+                        .into_iter()
+                        .map(to_unmatched_bundle_track)
+                        .collect(),
+                    success: true,
+                    error: String::new(),
+                }))
+            }
+            Err(e) => Ok(Some(ImportPlaylistBundleResponse {
+                playlist: None,
+                imported_count: 0,
+                unmatched_tracks: vec![],
+                duplicate_tracks: vec![],
+                success: false,
+                error: format!("{e:#}"),
+            })),
+        }
+    }
+}
+
+fn to_unmatched_bundle_track(track: PlaylistBundleTrack) -> UnmatchedBundleTrack {
+    UnmatchedBundleTrack {
+        title: track.title,
+        artist: track.artist,
+        album: track.album,
+    }
+}
+
+fn to_smart_playlist(playlist: smart_playlists::Model) -> SmartPlaylist {
+    SmartPlaylist {
+        id: playlist.id,
+        name: playlist.name,
+        group: playlist.group,
+        rules: playlist.rules,
+    }
+}
+
+impl ParamsExtractor for FetchAllSmartPlaylistsRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchAllSmartPlaylistsRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchAllSmartPlaylistsResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let smart_playlists = get_all_smart_playlists(&main_db)
+            .await
+            .with_context(|| "Failed to fetch all smart playlists")?;
+
+        Ok(Some(FetchAllSmartPlaylistsResponse {
+            smart_playlists: smart_playlists.into_iter().map(to_smart_playlist).collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for CreateSmartPlaylistRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for CreateSmartPlaylistRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = CreateSmartPlaylistResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        match create_smart_playlist(
+            &main_db,
+            request.name.clone(),
+            request.group.clone(),
+            request.rules.clone(),
+        )
+        .await
+        {
+            Ok(playlist) => Ok(Some(CreateSmartPlaylistResponse {
+                smart_playlist: Some(to_smart_playlist(playlist)),
+                success: true,
+                error: String::new(),
+            })),
+            Err(e) => Ok(Some(CreateSmartPlaylistResponse {
+                smart_playlist: None,
+                success: false,
+                error: format!("{e:#}"),
+            })),
+        }
+    }
+}
+
+impl ParamsExtractor for UpdateSmartPlaylistRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for UpdateSmartPlaylistRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = UpdateSmartPlaylistResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        match update_smart_playlist(
+            &main_db,
+            request.smart_playlist_id,
+            Some(request.name.clone()),
+            Some(request.group.clone()),
+            Some(request.rules.clone()),
+        )
+        .await
+        {
+            Ok(playlist) => Ok(Some(UpdateSmartPlaylistResponse {
+                smart_playlist: Some(to_smart_playlist(playlist)),
+                success: true,
+                error: String::new(),
+            })),
+            Err(e) => Ok(Some(UpdateSmartPlaylistResponse {
+                smart_playlist: None,
+                success: false,
+                error: format!("{e:#}"),
+            })),
+        }
+    }
+}
+
+impl ParamsExtractor for RemoveSmartPlaylistRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for RemoveSmartPlaylistRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = RemoveSmartPlaylistResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        remove_smart_playlist(&main_db, request.smart_playlist_id)
+            .await
+            .with_context(|| {
+                format!("Removing smart playlist: id={}", request.smart_playlist_id)
+            })?;
+
+        Ok(Some(RemoveSmartPlaylistResponse {
+            smart_playlist_id: request.smart_playlist_id,
+            success: true,
+        }))
+    }
+}
+
+impl ParamsExtractor for FetchSmartPlaylistItemsRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for FetchSmartPlaylistItemsRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = FetchSmartPlaylistItemsResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let file_ids = get_smart_playlist_file_ids(&main_db, request.smart_playlist_id)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to evaluate smart playlist: id={}",
+                    request.smart_playlist_id
+                )
+            })?;
+
+        let media_entries = get_files_by_ids(&main_db, &file_ids)
+            .await
+            .with_context(|| "Failed to get smart playlist files")?;
+        let media_summaries = get_metadata_summary_by_files(&main_db, media_entries)
+            .await
+            .with_context(|| "Unable to get media summaries")?;
+        let mut media_files = parse_media_files(&fsio, media_summaries, lib_path)
+            .await
+            .with_context(|| "Failed to parse media summaries")?;
+
+        // Keep the order of the evaluated rules
+        let positions: HashMap<i32, usize> = file_ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id, index))
+            .collect();
+        media_files.sort_by_key(|x| positions.get(&x.id).copied().unwrap_or(usize::MAX));
+
+        Ok(Some(FetchSmartPlaylistItemsResponse {
+            smart_playlist_id: request.smart_playlist_id,
+            media_files,
+        }))
+    }
+}
+
+impl From<playlist_mirrors::Model> for PlaylistMirror {
+    fn from(mirror: playlist_mirrors::Model) -> Self {
+        PlaylistMirror {
+            playlist_id: mirror.playlist_id,
+            path: mirror.path,
+            conflict: mirror.conflict,
+            synced_at: mirror.synced_at.unwrap_or_default(),
+        }
+    }
+}
+
+fn to_sync_result(
+    mirror: &playlist_mirrors::Model,
+    outcome: MirrorSyncOutcome,
+) -> PlaylistMirrorSyncResult {
+    let (outcome, unmatched_paths) = match outcome {
+        MirrorSyncOutcome::Unchanged => ("unchanged", vec![]),
+        MirrorSyncOutcome::Exported => ("exported", vec![]),
+        MirrorSyncOutcome::Imported { unmatched_paths } => ("imported", unmatched_paths),
+        MirrorSyncOutcome::Conflict => ("conflict", vec![]),
+    };
+
+    PlaylistMirrorSyncResult {
+        playlist_id: mirror.playlist_id,
+        path: mirror.path.clone(),
+        outcome: outcome.to_owned(),
+        unmatched_paths,
+    }
+}
+
+impl ParamsExtractor for FetchPlaylistMirrorsRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchPlaylistMirrorsRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchPlaylistMirrorsResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let mirrors = list_playlist_mirrors(&main_db)
+            .await
+            .with_context(|| "Failed to fetch playlist mirrors")?;
+
+        Ok(Some(FetchPlaylistMirrorsResponse {
+            mirrors: mirrors.into_iter().map(PlaylistMirror::from).collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for AddPlaylistMirrorRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for AddPlaylistMirrorRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<String>);
+    type Response = AddPlaylistMirrorResponse;
+    async fn handle(
+        &self,
+        (main_db, lib_path, node_id): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        match add_playlist_mirror(
+            &main_db,
+            Path::new(lib_path.as_str()),
+            &node_id,
+            request.playlist_id,
+            Path::new(&request.path),
+        )
+        .await
+        {
+            Ok((mirror, outcome)) => Ok(Some(AddPlaylistMirrorResponse {
+                result: Some(to_sync_result(&mirror, outcome)),
+                mirror: Some(mirror.into()),
+                success: true,
+                error: String::new(),
+            })),
+            Err(e) => Ok(Some(AddPlaylistMirrorResponse {
+                mirror: None,
+                result: None,
+                success: false,
+                error: format!("{e:#}"),
+            })),
+        }
+    }
+}
+
+impl ParamsExtractor for RemovePlaylistMirrorRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for RemovePlaylistMirrorRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = RemovePlaylistMirrorResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        remove_playlist_mirror(&main_db, request.playlist_id)
+            .await
+            .with_context(|| format!("Removing playlist mirror: id={}", request.playlist_id))?;
+
+        Ok(Some(RemovePlaylistMirrorResponse {
+            playlist_id: request.playlist_id,
+            success: true,
+        }))
+    }
+}
+
+impl ParamsExtractor for SyncPlaylistMirrorsRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for SyncPlaylistMirrorsRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<String>);
+    type Response = SyncPlaylistMirrorsResponse;
+    async fn handle(
+        &self,
+        (main_db, lib_path, node_id): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let outcomes = sync_playlist_mirrors(&main_db, Path::new(lib_path.as_str()), &node_id)
+            .await
+            .with_context(|| "Failed to sync playlist mirrors")?;
+
+        Ok(Some(SyncPlaylistMirrorsResponse {
+            results: outcomes
+                .into_iter()
+                .map(|(mirror, outcome)| to_sync_result(&mirror, outcome))
+                .collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for ResolvePlaylistMirrorConflictRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for ResolvePlaylistMirrorConflictRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>, Arc<String>);
+    type Response = ResolvePlaylistMirrorConflictResponse;
+    async fn handle(
+        &self,
+        (main_db, lib_path, node_id): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let keep = if request.keep_file {
+            MirrorSide::File
+        } else {
+            MirrorSide::Playlist
+        };
+
+        let result = resolve_playlist_mirror_conflict(
+            &main_db,
+            Path::new(lib_path.as_str()),
+            &node_id,
+            request.playlist_id,
+            keep,
+        )
+        .await;
+
+        match result {
+            Ok(outcome) => {
+                let mirror = get_playlist_mirror(&main_db, request.playlist_id).await?;
+
+                Ok(Some(ResolvePlaylistMirrorConflictResponse {
+                    result: mirror.map(|x| to_sync_result(&x, outcome)),
+                    success: true,
+                    error: String::new(),
+                }))
+            }
+            Err(e) => Ok(Some(ResolvePlaylistMirrorConflictResponse {
+                result: None,
+                success: false,
+                error: format!("{e:#}"),
             })),
         }
     }
@@ -5,6 +5,7 @@ use anyhow::{Context, Result};
 use ::database::actions::collection::CollectionQueryType;
 use ::database::actions::search::convert_to_collection_types;
 use ::database::actions::search::search_for;
+use ::database::actions::search::search_suggest;
 use ::database::connection::MainDbConnection;
 
 use crate::{
@@ -54,8 +55,8 @@ impl Signal for SearchForRequest {
         let mut playlists: Vec<i32> = Vec::new();
         let mut tracks: Vec<i32> = Vec::new();
 
-        for (collection_type, ids) in results {
-            let ids: Vec<i32> = ids.iter().map(|&x| x as i32).collect();
+        for (collection_type, items) in results {
+            let ids: Vec<i32> = items.iter().map(|x| x.id as i32).collect();
             match collection_type {
                 CollectionQueryType::Artist => artists.extend(ids),
                 CollectionQueryType::Album => albums.extend(ids),
@@ -73,3 +74,43 @@ impl Signal for SearchForRequest {
         }))
     }
 }
+
+impl ParamsExtractor for SearchSuggestRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for SearchSuggestRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = SearchSuggestResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let prefix = &request.prefix;
+        let limit = request.limit.max(0) as usize;
+
+        let suggestions = search_suggest(&main_db, prefix, limit)
+            .await
+            .with_context(|| format!("Search suggestions failed: prefix={prefix}"))?;
+
+        Ok(Some(SearchSuggestResponse {
+            prefix: prefix.clone(),
+            suggestions: suggestions
+                .into_iter()
+                .map(|x| SearchSuggestion {
+                    collection_type: x.collection_type.into(),
+                    id: x.id as i32,
+                    text: x.text,
+                })
+                .collect(),
+        }))
+    }
+}
@@ -0,0 +1,131 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+
+use ::database::{
+    actions::sources::{add_library_source, refresh_source_availability, remove_library_source},
+    connection::MainDbConnection,
+    entities::library_sources,
+};
+use ::fsio::FsIo;
+
+use crate::{
+    Session, Signal,
+    messages::*,
+    utils::{GlobalParams, ParamsExtractor},
+};
+
+impl From<library_sources::Model> for LibrarySource {
+    fn from(x: library_sources::Model) -> Self {
+        LibrarySource {
+            id: x.id,
+            name: x.name,
+            path: x.path,
+            available: x.available,
+        }
+    }
+}
+
+impl ParamsExtractor for FetchLibrarySourcesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchLibrarySourcesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchLibrarySourcesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let sources = refresh_source_availability(&main_db)
+            .await
+            .with_context(|| "Failed to fetch library sources")?;
+
+        Ok(Some(FetchLibrarySourcesResponse {
+            sources: sources.into_iter().map(Into::into).collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for AddLibrarySourceRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for AddLibrarySourceRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = AddLibrarySourceResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let response = match add_library_source(
+            &fsio,
+            &main_db,
+            Path::new(lib_path.as_str()),
+            &request.name,
+            Path::new(&request.path),
+        )
+        .await
+        {
+            Ok(source) => AddLibrarySourceResponse {
+                source: Some(source.into()),
+                success: true,
+                error: String::new(),
+            },
+            Err(e) => AddLibrarySourceResponse {
+                source: None,
+                success: false,
+                error: format!("{e:#}"),
+            },
+        };
+
+        Ok(Some(response))
+    }
+}
+
+impl ParamsExtractor for RemoveLibrarySourceRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for RemoveLibrarySourceRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = RemoveLibrarySourceResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let id = dart_signal.id;
+        let success = remove_library_source(&main_db, id)
+            .await
+            .with_context(|| format!("Failed to remove library source: id={id}"))?;
+
+        Ok(Some(RemoveLibrarySourceResponse { id, success }))
+    }
+}
@@ -1,16 +1,21 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::{Context, Result};
+use tokio::sync::Mutex;
 
 use ::database::{
-    actions::stats::{get_liked, set_liked},
+    actions::stats::{
+        clear_playback_error, get_hidden_files, get_liked, get_liked_files, get_problem_tracks,
+        get_rating, set_hidden, set_liked, set_rating, set_track_offsets, toggle_like,
+        TrackOffsets,
+    },
     connection::MainDbConnection,
 };
-use ::playback::player::PlayingItem;
+use ::playback::player::{Playable, PlayingItem};
 
 use crate::{
     messages::*,
-    utils::{GlobalParams, ParamsExtractor},
+    utils::{player::offsets_to_trim, GlobalParams, ParamsExtractor},
     Session, Signal,
 };
 
@@ -123,3 +128,380 @@ impl Signal for GetLikedRequest {
         Ok(None)
     }
 }
+
+impl ParamsExtractor for ToggleLikeRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for ToggleLikeRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = ToggleLikeResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let Some(item) = &dart_signal.item else {
+            return Ok(None);
+        };
+        let parsed_item: PlayingItem = item.clone().into();
+
+        let response = match parsed_item {
+            PlayingItem::InLibrary(file_id) => {
+                let liked = toggle_like(&main_db, file_id)
+                    .await
+                    .with_context(|| format!("Failed to toggle like: file_id={file_id}"))?;
+
+                ToggleLikeResponse {
+                    item: item.clone(),
+                    liked: liked.unwrap_or_default(),
+                    success: liked.is_some(),
+                }
+            }
+            PlayingItem::IndependentFile(_) | PlayingItem::Unknown => ToggleLikeResponse {
+                item: item.clone(),
+                liked: false,
+                success: false,
+            },
+        };
+
+        Ok(Some(response))
+    }
+}
+
+impl ParamsExtractor for FetchLikedFilesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchLikedFilesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchLikedFilesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let file_ids = get_liked_files(&main_db)
+            .await
+            .with_context(|| "Failed to fetch liked files")?;
+
+        Ok(Some(FetchLikedFilesResponse { file_ids }))
+    }
+}
+
+impl ParamsExtractor for SetRatingRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for SetRatingRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = SetRatingResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let Some(item) = &request.item else {
+            return Ok(None);
+        };
+        let parsed_item: PlayingItem = item.clone().into();
+
+        let response = match parsed_item {
+            PlayingItem::InLibrary(file_id) => {
+                match set_rating(&main_db, file_id, request.rating).await {
+                    Ok(Some(stats)) => SetRatingResponse {
+                        item: item.clone(),
+                        rating: stats.rating,
+                        success: true,
+                        error: String::new(),
+                    },
+                    Ok(None) => SetRatingResponse {
+                        item: item.clone(),
+                        rating: None,
+                        success: false,
+                        error: format!("File not found: {file_id}"),
+                    },
+                    Err(e) => SetRatingResponse {
+                        item: item.clone(),
+                        rating: None,
+                        success: false,
+                        error: format!("{e:#}"),
+                    },
+                }
+            }
+            PlayingItem::IndependentFile(_) | PlayingItem::Unknown => SetRatingResponse {
+                item: item.clone(),
+                rating: None,
+                success: false,
+                error: "Only library files can be rated".to_string(),
+            },
+        };
+
+        Ok(Some(response))
+    }
+}
+
+impl ParamsExtractor for GetRatingRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for GetRatingRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = GetRatingResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let Some(item) = &dart_signal.item else {
+            return Ok(None);
+        };
+        let parsed_item: PlayingItem = item.clone().into();
+
+        let rating = match parsed_item {
+            PlayingItem::InLibrary(file_id) => get_rating(&main_db, file_id)
+                .await
+                .with_context(|| format!("Failed to get rating: file_id={file_id}"))?,
+            PlayingItem::IndependentFile(_) | PlayingItem::Unknown => None,
+        };
+
+        Ok(Some(GetRatingResponse {
+            item: item.clone(),
+            rating,
+        }))
+    }
+}
+
+impl ParamsExtractor for SetHiddenRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for SetHiddenRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = SetHiddenResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let Some(item) = &request.item else {
+            return Ok(None);
+        };
+        let parsed_item: PlayingItem = item.clone().into();
+
+        let response = match parsed_item {
+            PlayingItem::InLibrary(file_id) => {
+                let stats = set_hidden(&main_db, file_id, request.hidden)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Failed to set hidden: file_id={}, hidden={}",
+                            file_id, request.hidden
+                        )
+                    })?;
+
+                SetHiddenResponse {
+                    item: item.clone(),
+                    hidden: stats.as_ref().is_some_and(|x| x.hidden),
+                    success: stats.is_some(),
+                }
+            }
+            PlayingItem::IndependentFile(_) | PlayingItem::Unknown => SetHiddenResponse {
+                item: item.clone(),
+                hidden: false,
+                success: false,
+            },
+        };
+
+        Ok(Some(response))
+    }
+}
+
+impl ParamsExtractor for FetchHiddenFilesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchHiddenFilesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchHiddenFilesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let file_ids = get_hidden_files(&main_db)
+            .await
+            .with_context(|| "Failed to fetch hidden files")?;
+
+        Ok(Some(FetchHiddenFilesResponse { file_ids }))
+    }
+}
+
+impl ParamsExtractor for FetchProblemTracksRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchProblemTracksRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchProblemTracksResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let tracks = get_problem_tracks(&main_db)
+            .await
+            .with_context(|| "Failed to fetch problem tracks")?;
+
+        Ok(Some(FetchProblemTracksResponse {
+            tracks: tracks
+                .into_iter()
+                .map(|x| ProblemTrack {
+                    file_id: x.media_file_id,
+                    error: x.error,
+                    failed_at: x.failed_at,
+                })
+                .collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for ClearPlaybackErrorRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for ClearPlaybackErrorRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = ClearPlaybackErrorResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let file_id = dart_signal.file_id;
+        let success = clear_playback_error(&main_db, file_id)
+            .await
+            .with_context(|| format!("Failed to clear playback error: file_id={file_id}"))?;
+
+        Ok(Some(ClearPlaybackErrorResponse { file_id, success }))
+    }
+}
+
+impl ParamsExtractor for SetTrackOffsetsRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for SetTrackOffsetsRequest {
+    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Response = SetTrackOffsetsResponse;
+
+    async fn handle(
+        &self,
+        (main_db, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let Some(item) = &request.item else {
+            return Ok(None);
+        };
+        let parsed_item: PlayingItem = item.clone().into();
+
+        let failure = |error: String| SetTrackOffsetsResponse {
+            item: item.clone(),
+            start_ms: None,
+            end_ms: None,
+            success: false,
+            error,
+        };
+
+        let PlayingItem::InLibrary(file_id) = parsed_item else {
+            return Ok(Some(failure(
+                "Only library files can be trimmed".to_string(),
+            )));
+        };
+
+        let offsets = TrackOffsets {
+            start_ms: request.start_ms,
+            end_ms: request.end_ms,
+        };
+        let response = match set_track_offsets(&main_db, file_id, offsets).await {
+            Ok(Some(stats)) => {
+                // Takes effect the next time the track is loaded
+                player
+                    .lock()
+                    .await
+                    .update_track_trims(HashMap::from([(parsed_item, offsets_to_trim(&offsets))]));
+
+                SetTrackOffsetsResponse {
+                    item: item.clone(),
+                    start_ms: stats.start_offset_ms,
+                    end_ms: stats.end_offset_ms,
+                    success: true,
+                    error: String::new(),
+                }
+            }
+            Ok(None) => failure(format!("File not found: {file_id}")),
+            Err(e) => failure(format!("{e:#}")),
+        };
+
+        Ok(Some(response))
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use ::database::{
+    actions::trash::{get_deleted_files, restore_deleted_files},
+    connection::MainDbConnection,
+};
+
+use crate::{
+    Session, Signal,
+    messages::*,
+    utils::{GlobalParams, ParamsExtractor},
+};
+
+impl ParamsExtractor for FetchDeletedFilesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchDeletedFilesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchDeletedFilesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let files = get_deleted_files(&main_db)
+            .await
+            .with_context(|| "Failed to fetch deleted files")?;
+
+        Ok(Some(FetchDeletedFilesResponse {
+            files: files
+                .into_iter()
+                .map(|x| DeletedFile {
+                    id: x.id,
+                    file_id: x.file_id,
+                    directory: x.directory,
+                    file_name: x.file_name,
+                    deleted_at: x.deleted_at,
+                })
+                .collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for RestoreDeletedFilesRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for RestoreDeletedFilesRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = RestoreDeletedFilesResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let response = match restore_deleted_files(&main_db, &dart_signal.ids).await {
+            Ok(file_ids) => RestoreDeletedFilesResponse {
+                file_ids,
+                success: true,
+                error: String::new(),
+            },
+            Err(e) => RestoreDeletedFilesResponse {
+                file_ids: Vec::new(),
+                success: false,
+                error: format!("{e:#}"),
+            },
+        };
+
+        Ok(Some(response))
+    }
+}
@@ -0,0 +1,371 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{Result, bail};
+use futures::future::try_join_all;
+
+use ::database::{
+    actions::{
+        collection::{CollectionQuery, CollectionQueryListMode, UnifiedCollection},
+        cover_art::bake_cover_art_thumbnails_by_media_files,
+        file::{get_files_by_ids, get_recently_played_files},
+        metadata::get_metadata_summary_by_files,
+        mixes::query_mix_media_files,
+    },
+    connection::{MainDbConnection, RecommendationDbConnection},
+    entities::{albums, artists, media_files, mixes, playlists},
+};
+use ::fsio::FsIo;
+
+use crate::{
+    Session, Signal,
+    messages::*,
+    utils::{GlobalParams, ParamsExtractor, RunningMode, process_cover_art_path},
+};
+
+/// Vehicle UIs cap list lengths anyway, and long lists are unsafe to scroll
+/// while driving.
+const MAX_VEHICLE_ITEMS: u32 = 100;
+const VEHICLE_COVER_ART_SIZE: u32 = 128;
+
+/// Nodes of the vehicle content tree. The tree is at most three levels
+/// deep: root, a category, then the collections of that category, whose
+/// children are always tracks.
+#[derive(Debug, PartialEq)]
+enum VehicleNode {
+    Root,
+    Recent,
+    Liked,
+    Category(&'static str),
+    Collection(&'static str, i32),
+}
+
+const CATEGORIES: [(&str, &str); 4] = [
+    ("album", "Albums"),
+    ("artist", "Artists"),
+    ("playlist", "Playlists"),
+    ("mix", "Mixes"),
+];
+
+fn parse_node(id: &str) -> Result<VehicleNode> {
+    match id {
+        "" | "root" => return Ok(VehicleNode::Root),
+        "recent" => return Ok(VehicleNode::Recent),
+        "liked" => return Ok(VehicleNode::Liked),
+        _ => {}
+    }
+
+    let (kind, collection_id) = match id.split_once(':') {
+        Some((kind, collection_id)) => (kind, Some(collection_id)),
+        None => (id, None),
+    };
+
+    let Some(&(kind, _)) = CATEGORIES.iter().find(|(x, _)| *x == kind) else {
+        bail!("Unknown vehicle content node: {id}");
+    };
+
+    match collection_id {
+        Some(x) => Ok(VehicleNode::Collection(kind, x.parse()?)),
+        None => Ok(VehicleNode::Category(kind)),
+    }
+}
+
+fn to_mix_queries(queries: Vec<(String, String)>) -> Vec<MixQuery> {
+    queries
+        .into_iter()
+        .map(|(operator, parameter)| MixQuery {
+            operator,
+            parameter,
+        })
+        .collect()
+}
+
+struct VehicleContext {
+    fsio: Arc<FsIo>,
+    main_db: Arc<MainDbConnection>,
+    recommend_db: Arc<RecommendationDbConnection>,
+    running_mode: RunningMode,
+    remote_host: Option<String>,
+}
+
+impl VehicleContext {
+    async fn track_nodes(&self, files: Vec<media_files::Model>) -> Result<Vec<VehicleContentNode>> {
+        let cover_arts = bake_cover_art_thumbnails_by_media_files(
+            &self.fsio,
+            &self.main_db,
+            &files,
+            VEHICLE_COVER_ART_SIZE,
+        )
+        .await?;
+        let summaries = get_metadata_summary_by_files(&self.main_db, files).await?;
+
+        Ok(summaries
+            .into_iter()
+            .map(|x| VehicleContentNode {
+                id: format!("track:{}", x.id),
+                title: x.title,
+                subtitle: x.artist,
+                cover_art_path: process_cover_art_path(
+                    cover_arts
+                        .get(&x.id)
+                        .map(|x| x.as_str())
+                        .unwrap_or_default(),
+                    &self.running_mode,
+                    &self.remote_host,
+                ),
+                browsable: false,
+                playable: true,
+                queries: vec![MixQuery {
+                    operator: "lib::track".to_owned(),
+                    parameter: x.id.to_string(),
+                }],
+            })
+            .collect())
+    }
+
+    async fn query_tracks(
+        &self,
+        queries: Vec<(String, String)>,
+        limit: usize,
+    ) -> Result<Vec<media_files::Model>> {
+        query_mix_media_files(&self.main_db, &self.recommend_db, queries, 0, limit).await
+    }
+
+    async fn collection_nodes<T: CollectionQuery>(
+        &self,
+        kind: &str,
+        limit: u32,
+    ) -> Result<Vec<VehicleContentNode>> {
+        let models = T::list(&self.main_db, limit.into(), CollectionQueryListMode::Name).await?;
+        let ids: Vec<i32> = models.iter().map(|x| x.id()).collect();
+        let collections = try_join_all(
+            models
+                .into_iter()
+                .map(|x| UnifiedCollection::from_model(&self.main_db, x, false)),
+        )
+        .await?;
+
+        // The file each collection takes its cover from
+        let cover_file_ids: HashMap<i32, i32> =
+            match T::get_cover_file_ids(&self.main_db, &ids).await? {
+                Some(x) => x,
+                // Mixes have to be evaluated one by one
+                None => {
+                    let files = try_join_all(collections.iter().map(|collection| {
+                        self.query_tracks(
+                            collection
+                                .queries
+                                .iter()
+                                .cloned()
+                                .chain([("filter::with_cover_art".to_owned(), "true".to_owned())])
+                                .collect(),
+                            1,
+                        )
+                    }))
+                    .await?;
+
+                    collections
+                        .iter()
+                        .zip(files)
+                        .filter_map(|(collection, files)| {
+                            files.first().map(|file| (collection.id, file.id))
+                        })
+                        .collect()
+                }
+            };
+
+        let file_ids: Vec<i32> = cover_file_ids.values().copied().collect();
+        let cover_files = get_files_by_ids(&self.main_db, &file_ids).await?;
+        let cover_art_paths = bake_cover_art_thumbnails_by_media_files(
+            &self.fsio,
+            &self.main_db,
+            &cover_files,
+            VEHICLE_COVER_ART_SIZE,
+        )
+        .await?;
+
+        Ok(collections
+            .into_iter()
+            .map(|collection| {
+                let cover_art_path = cover_file_ids
+                    .get(&collection.id)
+                    .and_then(|x| cover_art_paths.get(x))
+                    .cloned()
+                    .unwrap_or_default();
+
+                VehicleContentNode {
+                    id: format!("{kind}:{}", collection.id),
+                    title: collection.name,
+                    subtitle: String::new(),
+                    cover_art_path: process_cover_art_path(
+                        &cover_art_path,
+                        &self.running_mode,
+                        &self.remote_host,
+                    ),
+                    browsable: true,
+                    playable: true,
+                    queries: to_mix_queries(collection.queries),
+                }
+            })
+            .collect())
+    }
+
+    async fn collection_queries(&self, kind: &str, id: i32) -> Result<Vec<(String, String)>> {
+        match kind {
+            "album" => albums::Model::query_builder(&self.main_db, id).await,
+            "artist" => artists::Model::query_builder(&self.main_db, id).await,
+            "playlist" => playlists::Model::query_builder(&self.main_db, id).await,
+            "mix" => mixes::Model::query_builder(&self.main_db, id).await,
+            _ => bail!("Unknown collection kind: {kind}"),
+        }
+    }
+
+    async fn children(&self, node: VehicleNode, limit: u32) -> Result<Vec<VehicleContentNode>> {
+        match node {
+            VehicleNode::Root => {
+                // Recently played comes first, it is what drivers reach for most
+                let mut nodes = vec![
+                    VehicleContentNode {
+                        id: "recent".to_owned(),
+                        title: "Recently Played".to_owned(),
+                        subtitle: String::new(),
+                        cover_art_path: String::new(),
+                        browsable: true,
+                        playable: false,
+                        queries: vec![],
+                    },
+                    VehicleContentNode {
+                        id: "liked".to_owned(),
+                        title: "Liked".to_owned(),
+                        subtitle: String::new(),
+                        cover_art_path: String::new(),
+                        browsable: true,
+                        playable: true,
+                        queries: to_mix_queries(liked_queries()),
+                    },
+                ];
+                nodes.extend(CATEGORIES.iter().map(|(kind, title)| VehicleContentNode {
+                    id: kind.to_string(),
+                    title: title.to_string(),
+                    subtitle: String::new(),
+                    cover_art_path: String::new(),
+                    browsable: true,
+                    playable: false,
+                    queries: vec![],
+                }));
+
+                Ok(nodes)
+            }
+            VehicleNode::Recent => {
+                let files = get_recently_played_files(&self.main_db, limit.into()).await?;
+                self.track_nodes(files).await
+            }
+            VehicleNode::Liked => {
+                let files = self.query_tracks(liked_queries(), limit as usize).await?;
+                self.track_nodes(files).await
+            }
+            VehicleNode::Category(kind) => match kind {
+                "album" => self.collection_nodes::<albums::Model>(kind, limit).await,
+                "artist" => self.collection_nodes::<artists::Model>(kind, limit).await,
+                "playlist" => self.collection_nodes::<playlists::Model>(kind, limit).await,
+                "mix" => self.collection_nodes::<mixes::Model>(kind, limit).await,
+                _ => bail!("Unknown collection kind: {kind}"),
+            },
+            VehicleNode::Collection(kind, id) => {
+                let queries = self.collection_queries(kind, id).await?;
+                let files = self.query_tracks(queries, limit as usize).await?;
+                self.track_nodes(files).await
+            }
+        }
+    }
+}
+
+fn liked_queries() -> Vec<(String, String)> {
+    vec![
+        ("lib::directory.deep".to_owned(), "/".to_owned()),
+        ("filter::liked".to_owned(), "true".to_owned()),
+    ]
+}
+
+impl ParamsExtractor for FetchVehicleContentRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        RunningMode,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.recommend_db),
+            all_params.running_mode,
+        )
+    }
+}
+
+impl Signal for FetchVehicleContentRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<RecommendationDbConnection>,
+        RunningMode,
+    );
+    type Response = FetchVehicleContentResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, recommend_db, running_mode): Self::Params,
+        session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let context = VehicleContext {
+            fsio,
+            main_db,
+            recommend_db,
+            running_mode,
+            remote_host: session.map(|x| x.host),
+        };
+
+        let limit = match dart_signal.limit {
+            0 => MAX_VEHICLE_ITEMS,
+            x => x.min(MAX_VEHICLE_ITEMS),
+        };
+
+        let result = match parse_node(&dart_signal.parent_id) {
+            Ok(node) => context.children(node, limit).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(nodes) => Ok(Some(FetchVehicleContentResponse {
+                parent_id: dart_signal.parent_id.clone(),
+                nodes,
+                error: String::new(),
+            })),
+            Err(e) => Ok(Some(FetchVehicleContentResponse {
+                parent_id: dart_signal.parent_id.clone(),
+                nodes: vec![],
+                error: format!("{e:#}"),
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_node() {
+        assert_eq!(parse_node("root").unwrap(), VehicleNode::Root);
+        assert_eq!(parse_node("recent").unwrap(), VehicleNode::Recent);
+        assert_eq!(parse_node("album").unwrap(), VehicleNode::Category("album"));
+        assert_eq!(
+            parse_node("playlist:12").unwrap(),
+            VehicleNode::Collection("playlist", 12)
+        );
+        assert!(parse_node("genre:3").is_err());
+        assert!(parse_node("album:x").is_err());
+    }
+}
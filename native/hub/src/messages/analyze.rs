@@ -1,4 +1,4 @@
-use rinf::{DartSignal, RustSignal};
+use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, DartSignal)]
@@ -19,3 +19,32 @@ pub struct GetAnalyzeCountRequest {}
 pub struct GetAnalyzeCountResponse {
     pub count: u64,
 }
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchLibraryMapRequest {
+    /// Number of clusters, `0` picks one from the library size.
+    pub clusters: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct LibraryMapPoint {
+    pub file_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub cluster: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct LibraryMapCluster {
+    pub id: u32,
+    pub size: u32,
+    pub x: f32,
+    pub y: f32,
+    pub genre: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchLibraryMapResponse {
+    pub points: Vec<LibraryMapPoint>,
+    pub clusters: Vec<LibraryMapCluster>,
+}
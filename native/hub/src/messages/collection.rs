@@ -48,6 +48,7 @@ pub struct Collection {
     pub collection_type: CollectionType,
     pub cover_art_map: HashMap<i32, String>,
     pub readonly: bool,
+    pub subtitle: Option<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize, SignalPiece)]
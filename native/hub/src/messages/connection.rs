@@ -44,3 +44,26 @@ pub struct SetMediaLibraryPathResponse {
     pub error: Option<String>,
     pub not_ready: bool,
 }
+
+/// Sent once the UI is up, lets the hub start opening the library used last
+/// time before the user confirms it.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct StartupRequest {
+    pub config_path: String,
+}
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartupStage {
+    WaitingForLibrary,
+    ConnectingDatabases,
+    InitializingPlayer,
+    Ready,
+    Failed,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct StartupStatus {
+    pub stage: StartupStage,
+    pub path: Option<String>,
+    pub error: Option<String>,
+}
@@ -37,3 +37,33 @@ pub struct GetPrimaryColorByTrackIdResponse {
     pub item: PlayingItemRequest,
     pub primary_color: Option<i32>,
 }
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchAlbumThumbnailStatusRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchAlbumThumbnailStatusResponse {
+    /// Album cover arts with every thumbnail of the grid baked.
+    pub ready: i32,
+    pub total: i32,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SearchCoverArtByImageRequest {
+    pub image: Vec<u8>,
+    pub max_distance: u32,
+    pub n: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct SimilarCoverArt {
+    pub cover_art_id: i32,
+    pub distance: u32,
+    pub album_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SearchCoverArtByImageResponse {
+    pub result: Vec<SimilarCoverArt>,
+    pub error: Option<String>,
+}
@@ -0,0 +1,41 @@
+use rinf::{DartSignal, RustSignal, SignalPiece};
+use serde::{Deserialize, Serialize};
+
+use super::playback::PlayingItemRequest;
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct HandoffItem {
+    pub item: PlayingItemRequest,
+    /// Content hash of the file, used to find the same track in the
+    /// library of the receiving device.
+    pub file_hash: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct HandoffPlaybackRequest {
+    pub alias: String,
+    pub hosts: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct HandoffPlaybackResponse {
+    pub success: bool,
+    pub error: String,
+    pub transferred: i32,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ReceiveHandoffRequest {
+    pub items: Vec<HandoffItem>,
+    pub index: u32,
+    pub position_seconds: f64,
+    pub playback_mode: u32,
+    pub playing: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ReceiveHandoffResponse {
+    pub success: bool,
+    pub error: String,
+    pub matched: i32,
+}
@@ -29,6 +29,8 @@ pub struct ScanAudioLibraryProgress {
     pub progress: i32,
     pub total: i32,
     pub task: ScanTaskType,
+    /// Estimated seconds left in the task, missing while unknown.
+    pub eta_seconds: Option<i32>,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -37,6 +39,21 @@ pub struct ScanAudioLibraryResponse {
     pub progress: i32,
 }
 
+/// Sent while the thumbnails of the album grid are baked after a scan.
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct AlbumThumbnailProgress {
+    pub path: String,
+    /// Album cover arts with every thumbnail baked.
+    pub ready: i32,
+    pub total: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct LibraryUpdated {
+    pub updated_file_ids: Vec<i32>,
+    pub removed: i32,
+}
+
 #[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ComputingDeviceRequest {
     Cpu,
@@ -55,6 +72,8 @@ pub struct AnalyzeAudioLibraryProgress {
     pub path: String,
     pub progress: i32,
     pub total: i32,
+    /// Estimated seconds left in the analysis, missing while unknown.
+    pub eta_seconds: Option<i32>,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -101,3 +120,85 @@ pub struct CancelTaskResponse {
     pub r#type: CancelTaskType,
     pub success: bool,
 }
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Debug)]
+pub struct TaskCheckpoint {
+    pub task: String,
+    pub scope: String,
+    pub position: i64,
+    pub processed: i64,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ListTaskCheckpointsRequest {
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, RustSignal)]
+pub struct ListTaskCheckpointsResponse {
+    pub path: String,
+    pub checkpoints: Vec<TaskCheckpoint>,
+}
+
+/// How the scanner splits artist and genre tags into several names.
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Debug)]
+pub struct SplittingSettings {
+    pub artist_delimiters: Vec<String>,
+    pub genre_delimiters: Vec<String>,
+    /// Names that contain a delimiter but are never split, like `AC/DC`.
+    pub exceptions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct GetSplittingRulesRequest {}
+
+#[derive(Serialize, Deserialize, RustSignal)]
+pub struct GetSplittingRulesResponse {
+    pub settings: SplittingSettings,
+}
+
+/// Store the splitting rules of the library. They only apply to files
+/// indexed afterwards, until `ReapplySplittingRequest` is sent.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetSplittingRulesRequest {
+    /// Go back to the default rules if missing.
+    pub settings: Option<SplittingSettings>,
+}
+
+#[derive(Serialize, Deserialize, RustSignal)]
+pub struct SetSplittingRulesResponse {
+    pub settings: SplittingSettings,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct GetMetadataLanguagesRequest {}
+
+#[derive(Serialize, Deserialize, RustSignal)]
+pub struct GetMetadataLanguagesResponse {
+    /// Languages like `ja-Latn` or `romaji`, most preferred first.
+    pub languages: Vec<String>,
+}
+
+/// Pick the languages multi-language tags are shown in, the first variant
+/// a track has replaces its original title, artist or album. An empty list
+/// shows the original tags.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetMetadataLanguagesRequest {
+    pub languages: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, RustSignal)]
+pub struct SetMetadataLanguagesResponse {
+    pub languages: Vec<String>,
+}
+
+/// Derive the artist and genre links of every file again with the current
+/// splitting rules, from the stored tags.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ReapplySplittingRequest {}
+
+#[derive(Serialize, Deserialize, RustSignal)]
+pub struct ReapplySplittingResponse {
+    pub total: i32,
+}
@@ -27,3 +27,12 @@ pub struct GetLyricByTrackIdResponse {
     pub item: PlayingItemRequest,
     pub lines: Vec<LyricContentLine>,
 }
+
+/// Sent during playback whenever the line being sung changes, `index` is
+/// `None` before the first line and when the track has no synced lyrics.
+#[derive(Clone, Serialize, Deserialize, RustSignal)]
+pub struct LyricLineUpdate {
+    pub item: Option<PlayingItemRequest>,
+    pub index: Option<i32>,
+    pub line: Option<LyricContentLine>,
+}
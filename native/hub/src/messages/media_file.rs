@@ -22,6 +22,11 @@ pub struct MediaFile {
     pub duration: f64,
     pub cover_art_id: i32,
     pub track_number: i32,
+    /// Estimated musical key, e.g. `C# minor`, empty if not analyzed.
+    pub key: String,
+    /// False while the source of the file is offline, the file should be
+    /// shown greyed out.
+    pub available: bool,
 }
 
 #[derive(Serialize, Deserialize, DartSignal)]
@@ -70,3 +75,95 @@ pub struct SearchMediaFileSummaryRequest {
 pub struct SearchMediaFileSummaryResponse {
     pub result: Vec<MediaFileSummary>,
 }
+
+/// A field whose values differ between the database and the file.
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct TagConflict {
+    pub key: String,
+    pub database_values: Vec<String>,
+    pub file_values: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct MediaFileTagConflicts {
+    pub file_id: i32,
+    pub conflicts: Vec<TagConflict>,
+}
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagConflictResolution {
+    /// Store the values of the file in the database.
+    File,
+    /// Write the values of the database into the file.
+    Database,
+    /// Keep the values of both sides.
+    Merge,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchTagConflictsRequest {
+    pub file_ids: Vec<i32>,
+}
+
+/// Only the files with conflicts are listed.
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchTagConflictsResponse {
+    pub files: Vec<MediaFileTagConflicts>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ResolveTagConflictsRequest {
+    pub file_ids: Vec<i32>,
+    /// The fields to resolve, every conflicting field if empty.
+    pub keys: Vec<String>,
+    pub resolution: TagConflictResolution,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ResolveTagConflictsResponse {
+    pub resolved_file_ids: Vec<i32>,
+    pub failed_file_ids: Vec<i32>,
+}
+
+/// Show the file of a track selected in the file manager of the OS.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct RevealMediaFileRequest {
+    pub file_id: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RevealMediaFileResponse {
+    pub file_id: i32,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchMediaFileInfoRequest {
+    pub file_id: i32,
+}
+
+/// Everything the details dialog of a track shows about its file.
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct MediaFileInfo {
+    pub id: i32,
+    /// The absolute path of the file.
+    pub path: String,
+    /// Size in bytes, `None` while the file can't be reached.
+    pub size: Option<u64>,
+    /// A short name like `flac` or `mp3`, empty if the file can't be read.
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: Option<u32>,
+    pub bits_per_sample: Option<u32>,
+    /// Duration in seconds.
+    pub duration: f64,
+    pub file_hash: String,
+    /// Seconds since the Unix epoch.
+    pub last_modified: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchMediaFileInfoResponse {
+    pub info: MediaFileInfo,
+}
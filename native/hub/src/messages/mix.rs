@@ -31,6 +31,11 @@ pub struct Mix {
     pub group: String,
     pub locked: bool,
     pub mode: i32,
+    /// Stays the same across devices, unlike `id`.
+    pub uuid: String,
+    /// Path of the image shown for the mix, `None` to show the covers of its
+    /// tracks.
+    pub cover: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, DartSignal)]
@@ -48,6 +53,7 @@ pub struct CreateMixRequest {
     pub scriptlet_mode: bool,
     pub mode: i32,
     pub queries: Vec<MixQuery>,
+    pub cover: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -63,6 +69,7 @@ pub struct UpdateMixRequest {
     pub scriptlet_mode: bool,
     pub mode: i32,
     pub queries: Vec<MixQuery>,
+    pub cover: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -112,3 +119,37 @@ pub struct FetchMixQueriesRequest {
 pub struct FetchMixQueriesResponse {
     pub result: Vec<MixQuery>,
 }
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct DailyMix {
+    pub mix: Mix,
+    pub file_ids: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchDailyMixesRequest {
+    pub force: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchDailyMixesResponse {
+    pub mixes: Vec<DailyMix>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct MixPreset {
+    pub name: String,
+    /// Mix parameters like `lib::all(true);filter::tempo(60-100)`.
+    pub parameters: String,
+    /// The parameters already parsed, ready for a `MixQueryRequest`.
+    pub queries: Vec<MixQuery>,
+    pub builtin: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchMixPresetsRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchMixPresetsResponse {
+    pub presets: Vec<MixPreset>,
+}
@@ -5,6 +5,7 @@ mod collection;
 mod connection;
 mod cover_art;
 mod directory;
+mod handoff;
 mod library_home;
 mod library_manage;
 mod license;
@@ -13,13 +14,17 @@ mod lyric;
 mod media_file;
 mod mix;
 mod neighbors;
+mod page;
 mod playback;
 mod playlist;
 mod scrobble;
 mod search;
 mod sfx;
+mod source;
 mod stat;
 mod system;
+mod trash;
+mod vehicle;
 
 pub use album::*;
 pub use analyze::*;
@@ -28,6 +33,7 @@ pub use collection::*;
 pub use connection::*;
 pub use cover_art::*;
 pub use directory::*;
+pub use handoff::*;
 pub use library_home::*;
 pub use library_manage::*;
 pub use license::*;
@@ -36,10 +42,14 @@ pub use lyric::*;
 pub use media_file::*;
 pub use mix::*;
 pub use neighbors::*;
+pub use page::*;
 pub use playback::*;
 pub use playlist::*;
 pub use scrobble::*;
 pub use search::*;
 pub use sfx::*;
+pub use source::*;
 pub use stat::*;
 pub use system::*;
+pub use trash::*;
+pub use vehicle::*;
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use rinf::{DartSignal, RustSignal, SignalPiece};
+use serde::{Deserialize, Serialize};
+
+use super::album::Album;
+use super::artist::Artist;
+use super::media_file::MediaFile;
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct PageStats {
+    pub played_through: i32,
+    pub skipped: i32,
+    pub liked_tracks: i32,
+    /// RFC 3339 timestamp, empty if never played.
+    pub last_played_at: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchAlbumPageRequest {
+    pub id: i32,
+    pub bake_cover_arts: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchAlbumPageResponse {
+    /// `None` if the album doesn't exist.
+    pub album: Option<Album>,
+    /// Ordered by disc and track number.
+    pub tracks: Vec<MediaFile>,
+    /// Duration of all tracks in seconds.
+    pub total_duration: f64,
+    pub year: Option<i32>,
+    pub cover_art_id: i32,
+    pub artists: Vec<Artist>,
+    pub stats: PageStats,
+    pub cover_art_map: HashMap<i32, String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct ArtistAlbum {
+    pub album: Album,
+    pub year: Option<i32>,
+    pub cover_art_id: i32,
+    pub track_count: i32,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchArtistPageRequest {
+    pub id: i32,
+    pub bake_cover_arts: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchArtistPageResponse {
+    /// `None` if the artist doesn't exist.
+    pub artist: Option<Artist>,
+    /// Oldest first.
+    pub albums: Vec<ArtistAlbum>,
+    /// Grouped by album, then ordered by disc and track number.
+    pub tracks: Vec<MediaFile>,
+    /// Duration of all tracks in seconds.
+    pub total_duration: f64,
+    pub stats: PageStats,
+    pub cover_art_map: HashMap<i32, String>,
+}
@@ -3,10 +3,14 @@ use serde::{Deserialize, Serialize};
 
 use super::mix::MixQuery;
 
+/// Sent on every state change and every 100 ms while a track is loaded,
+/// often enough to drive a scrub bar.
 #[derive(Clone, Deserialize, Serialize, RustSignal)]
 pub struct PlaybackStatus {
     pub state: String,
+    /// Position in the current track.
     pub progress_seconds: f32,
+    /// `progress_seconds` as a fraction of `duration`, `0` if it is unknown.
     pub progress_percentage: f32,
     pub artist: Option<String>,
     pub album: Option<String>,
@@ -15,9 +19,19 @@ pub struct PlaybackStatus {
     pub index: Option<i32>,
     pub item: Option<String>,
     pub playback_mode: u32,
+    /// `0` off, `1` repeat one, `2` repeat all.
+    pub repeat_mode: u32,
+    /// `0` off, `1` uniform, `2` weighted by rating.
+    pub shuffle_mode: u32,
     pub ready: bool,
     pub cover_art_path: Option<String>,
     pub lib_path: String,
+    /// The device the track plays through, `None` while stopped.
+    pub output_device: Option<String>,
+    /// Sample rate in Hz the device is fed.
+    pub output_sample_rate: Option<u32>,
+    /// Bits per sample the device is fed, float formats included.
+    pub output_bit_depth: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, DartSignal)]
@@ -42,11 +56,26 @@ pub struct SetPlaybackModeRequest {
     pub mode: u32,
 }
 
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetRepeatModeRequest {
+    /// `0` off, `1` repeat one, `2` repeat all.
+    pub mode: u32,
+}
+
+/// Tracks added to the queue after a weighted shuffle is turned on count
+/// as unrated until the shuffle mode is set again.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetShuffleModeRequest {
+    /// `0` off, `1` uniform, `2` weighted by rating.
+    pub mode: u32,
+}
+
 #[derive(Serialize, Deserialize, DartSignal)]
 pub struct SwitchRequest {
     pub index: u32,
 }
 
+/// Seek within the current track, keeping it playing or paused.
 #[derive(Serialize, Deserialize, DartSignal)]
 pub struct SeekRequest {
     pub position_seconds: f64,
@@ -63,6 +92,15 @@ pub struct MovePlaylistItemRequest {
     pub new_index: u32,
 }
 
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct AddToQueueRequest {
+    pub items: Vec<PlayingItemRequest>,
+    pub operate_mode: PlaylistOperateMode,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ClearQueueRequest {}
+
 #[derive(Clone, Serialize, Deserialize, SignalPiece)]
 pub struct PlaylistItem {
     pub item: PlayingItemRequest,
@@ -77,6 +115,13 @@ pub struct PlaylistUpdate {
     pub items: Vec<PlaylistItem>,
 }
 
+/// A track failed to play and was skipped.
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct PlaybackFailed {
+    pub item: PlayingItemRequest,
+    pub error: String,
+}
+
 #[derive(Serialize, Deserialize, DartSignal)]
 pub struct SetRealtimeFFTEnabledRequest {
     pub enabled: bool,
@@ -87,6 +132,142 @@ pub struct SetAdaptiveSwitchingEnabledRequest {
     pub enabled: bool,
 }
 
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetGaplessEnabledRequest {
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetCrossfadeRequest {
+    /// Length of the crossfade between consecutive tracks in seconds, from
+    /// 0 (disabled) to 12.
+    pub seconds: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct NightModeSettings {
+    pub enabled: bool,
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub threshold_db: f32,
+    pub ratio: f32,
+    pub makeup_db: f32,
+    pub ceiling_db: f32,
+}
+
+/// Configure the night mode, kept across restarts.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetNightModeRequest {
+    pub settings: NightModeSettings,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetNightModeResponse {
+    /// Whether the night mode is applied right now.
+    pub active: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct GetNightModeRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct GetNightModeResponse {
+    pub settings: NightModeSettings,
+    /// Whether the night mode is applied right now.
+    pub active: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct EqualizerSettings {
+    pub enabled: bool,
+    /// One of `Flat`, `BassBoost`, `TrebleBoost`, `Vocal`, `Rock`, `Pop`,
+    /// `Classical`, `Electronic` or `Custom`.
+    pub preset: String,
+    pub preamp_db: f32,
+    /// Gain of every band in dB, only read for the `Custom` preset.
+    pub gains_db: Vec<f32>,
+}
+
+/// Configure the equalizer, kept across restarts.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetEqualizerRequest {
+    pub settings: EqualizerSettings,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetEqualizerResponse {
+    /// The applied settings, with the gains of the preset filled in.
+    pub settings: EqualizerSettings,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct GetEqualizerRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct GetEqualizerResponse {
+    pub settings: EqualizerSettings,
+    /// Center frequency of every band in Hz.
+    pub bands: Vec<f32>,
+    pub presets: Vec<String>,
+    pub max_gain_db: f32,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetHearingProtectionRequest {
+    /// Estimated level of a full scale signal at maximum volume, in dB SPL.
+    pub full_scale_spl_db: f32,
+    pub auto_reduce: bool,
+    pub reduced_volume: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct ReplayGainSettings {
+    /// One of `Off`, `Track` and `Album`.
+    pub mode: String,
+    /// Extra gain on top of the computed gain, in dB.
+    pub preamp_db: f32,
+    pub prevent_clipping: bool,
+}
+
+/// Configure the ReplayGain, kept across restarts.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetReplayGainRequest {
+    pub settings: ReplayGainSettings,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct GetReplayGainRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct GetReplayGainResponse {
+    pub settings: ReplayGainSettings,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchListeningExposureRequest {}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct ListeningExposureSummary {
+    /// Local date the exposure belongs to, `YYYY-MM-DD`.
+    pub date: String,
+    pub listening_seconds: f64,
+    pub average_level_db: f64,
+    /// Used fraction of the daily allowance, `1.0` is the WHO limit.
+    pub dose: f64,
+    /// One of `None`, `Half`, `Approaching` and `Exceeded`.
+    pub warning: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchListeningExposureResponse {
+    pub exposure: ListeningExposureSummary,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ListeningExposureUpdated {
+    pub exposure: ListeningExposureSummary,
+}
+
 #[derive(Deserialize, Serialize, RustSignal)]
 pub struct RealtimeFFT {
     pub value: Vec<f32>,
@@ -102,6 +283,35 @@ pub struct VolumeResponse {
     pub volume: f32,
 }
 
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ListOutputDevicesRequest {}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct OutputDevice {
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ListOutputDevicesResponse {
+    pub devices: Vec<OutputDevice>,
+    /// The selected device, `None` for the system default. It may be
+    /// missing from `devices` while unplugged.
+    pub selected: Option<String>,
+}
+
+/// Select the output device, kept across restarts.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetOutputDeviceRequest {
+    /// `None` follows the system default.
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetOutputDeviceResponse {
+    pub name: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PlaylistOperateMode {
     AppendToEnd,
@@ -140,3 +350,106 @@ pub struct OperatePlaybackWithMixQueryRequest {
 pub struct OperatePlaybackWithMixQueryResponse {
     pub playing_items: Vec<PlayingItemRequest>,
 }
+
+/// Replace the playlist with a mix and play a 20 second hook of every track,
+/// crossfading into the next one, to judge the mix before saving it.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct StartMixAuditionRequest {
+    pub queries: Vec<MixQuery>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct StartMixAuditionResponse {
+    pub playing_items: Vec<PlayingItemRequest>,
+}
+
+/// Go back to playing whole tracks, the playlist is kept.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct StopMixAuditionRequest {}
+
+/// How a radio keeps the playlist filled once it is started.
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct RadioOptions {
+    /// How many of the last queued tracks aren't queued again, `None` for
+    /// the default window.
+    pub repeat_window: Option<u32>,
+    /// Tracks the radio never queues.
+    pub excluded_file_ids: Vec<i32>,
+}
+
+/// Replace the playlist with the tracks that sound like a track, starting
+/// with the track itself, and start playing. More are queued as the
+/// playlist drains, until the playlist is replaced or the radio stopped.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct StartTrackRadioRequest {
+    pub file_id: i32,
+    pub options: RadioOptions,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct StartTrackRadioResponse {
+    pub playing_items: Vec<PlayingItemRequest>,
+}
+
+/// Like [`StartTrackRadioRequest`], around the tracks of an artist.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct StartArtistRadioRequest {
+    pub artist_id: i32,
+    pub options: RadioOptions,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct StartArtistRadioResponse {
+    pub playing_items: Vec<PlayingItemRequest>,
+}
+
+/// Like [`StartTrackRadioRequest`], around the tracks of an album.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct StartAlbumRadioRequest {
+    pub album_id: i32,
+    pub options: RadioOptions,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct StartAlbumRadioResponse {
+    pub playing_items: Vec<PlayingItemRequest>,
+}
+
+/// Stop queuing more tracks, the playlist is kept.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct StopRadioRequest {}
+
+/// An endless channel generated from the library, like a genre, a decade
+/// or a group of tracks that sound alike.
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct Station {
+    /// Identifies the station when starting it.
+    pub key: String,
+    pub name: String,
+    pub track_count: i32,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchStationsRequest {
+    pub limit: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchStationsResponse {
+    pub stations: Vec<Station>,
+}
+
+/// Replace the playlist with a station and start playing. Like the radios,
+/// more tracks are queued as the playlist drains, fewer of the artists
+/// skipped along the way, until the playlist is replaced or the radio
+/// stopped.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct StartStationRequest {
+    pub key: String,
+    pub options: RadioOptions,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct StartStationResponse {
+    pub playing_items: Vec<PlayingItemRequest>,
+}
@@ -1,6 +1,8 @@
 use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
+use super::media_file::MediaFile;
+
 #[derive(Clone, Serialize, Deserialize, SignalPiece)]
 pub struct Playlist {
     pub id: i32,
@@ -124,3 +126,183 @@ pub struct RemoveItemFromPlaylistResponse {
     pub success: bool,
     pub error: String,
 }
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ExportPlaylistBundleRequest {
+    pub playlist_id: i32,
+    pub path: String,
+    pub secret: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ExportPlaylistBundleResponse {
+    pub exported_count: i32,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ImportPlaylistBundleRequest {
+    pub path: String,
+    pub secret: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct UnmatchedBundleTrack {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ImportPlaylistBundleResponse {
+    pub playlist: Option<Playlist>,
+    pub imported_count: i32,
+    pub unmatched_tracks: Vec<UnmatchedBundleTrack>,
+    /// Tracks that resolved to a file an earlier track already took.
+    pub duplicate_tracks: Vec<UnmatchedBundleTrack>,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct SmartPlaylist {
+    pub id: i32,
+    pub name: String,
+    pub group: String,
+    /// Rules selecting the tracks, e.g. `genre = jazz AND bpm > 120`.
+    pub rules: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchAllSmartPlaylistsRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchAllSmartPlaylistsResponse {
+    pub smart_playlists: Vec<SmartPlaylist>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct CreateSmartPlaylistRequest {
+    pub name: String,
+    pub group: String,
+    pub rules: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct CreateSmartPlaylistResponse {
+    pub smart_playlist: Option<SmartPlaylist>,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct UpdateSmartPlaylistRequest {
+    pub smart_playlist_id: i32,
+    pub name: String,
+    pub group: String,
+    pub rules: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct UpdateSmartPlaylistResponse {
+    pub smart_playlist: Option<SmartPlaylist>,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct RemoveSmartPlaylistRequest {
+    pub smart_playlist_id: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RemoveSmartPlaylistResponse {
+    pub smart_playlist_id: i32,
+    pub success: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchSmartPlaylistItemsRequest {
+    pub smart_playlist_id: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchSmartPlaylistItemsResponse {
+    pub smart_playlist_id: i32,
+    pub media_files: Vec<MediaFile>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct PlaylistMirror {
+    pub playlist_id: i32,
+    pub path: String,
+    /// Both the file and the playlist changed, syncing is paused until the
+    /// conflict is resolved.
+    pub conflict: bool,
+    pub synced_at: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct PlaylistMirrorSyncResult {
+    pub playlist_id: i32,
+    pub path: String,
+    /// `unchanged`, `exported`, `imported` or `conflict`.
+    pub outcome: String,
+    pub unmatched_paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchPlaylistMirrorsRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchPlaylistMirrorsResponse {
+    pub mirrors: Vec<PlaylistMirror>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct AddPlaylistMirrorRequest {
+    pub playlist_id: i32,
+    pub path: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct AddPlaylistMirrorResponse {
+    pub mirror: Option<PlaylistMirror>,
+    pub result: Option<PlaylistMirrorSyncResult>,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct RemovePlaylistMirrorRequest {
+    pub playlist_id: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RemovePlaylistMirrorResponse {
+    pub playlist_id: i32,
+    pub success: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SyncPlaylistMirrorsRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SyncPlaylistMirrorsResponse {
+    pub results: Vec<PlaylistMirrorSyncResult>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ResolvePlaylistMirrorConflictRequest {
+    pub playlist_id: i32,
+    /// Keep the file when set, otherwise keep the playlist.
+    pub keep_file: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ResolvePlaylistMirrorConflictResponse {
+    pub result: Option<PlaylistMirrorSyncResult>,
+    pub success: bool,
+    pub error: String,
+}
@@ -1,6 +1,8 @@
-use rinf::{DartSignal, RustSignal};
+use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
+use super::collection::CollectionType;
+
 #[derive(Serialize, Deserialize, DartSignal)]
 pub struct SearchForRequest {
     pub query_str: String,
@@ -15,3 +17,23 @@ pub struct SearchForResponse {
     pub playlists: Vec<i32>,
     pub tracks: Vec<i32>,
 }
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SearchSuggestRequest {
+    pub prefix: String,
+    pub limit: i32,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct SearchSuggestion {
+    pub collection_type: CollectionType,
+    pub id: i32,
+    pub text: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SearchSuggestResponse {
+    /// The prefix the suggestions complete, so stale ones can be ignored.
+    pub prefix: String,
+    pub suggestions: Vec<SearchSuggestion>,
+}
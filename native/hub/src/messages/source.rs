@@ -0,0 +1,42 @@
+use rinf::{DartSignal, RustSignal, SignalPiece};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct LibrarySource {
+    pub id: i32,
+    pub name: String,
+    pub path: String,
+    pub available: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchLibrarySourcesRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchLibrarySourcesResponse {
+    pub sources: Vec<LibrarySource>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct AddLibrarySourceRequest {
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct AddLibrarySourceResponse {
+    pub source: Option<LibrarySource>,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct RemoveLibrarySourceRequest {
+    pub id: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RemoveLibrarySourceResponse {
+    pub id: i32,
+    pub success: bool,
+}
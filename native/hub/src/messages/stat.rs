@@ -1,4 +1,4 @@
-use rinf::{DartSignal, RustSignal};
+use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
 use super::playback::PlayingItemRequest;
@@ -26,3 +26,120 @@ pub struct GetLikedResponse {
     pub item: PlayingItemRequest,
     pub liked: bool,
 }
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ToggleLikeRequest {
+    pub item: Option<PlayingItemRequest>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ToggleLikeResponse {
+    pub item: PlayingItemRequest,
+    pub liked: bool,
+    pub success: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchLikedFilesRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchLikedFilesResponse {
+    pub file_ids: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetRatingRequest {
+    pub item: Option<PlayingItemRequest>,
+    /// From 0 to 5, `None` clears the rating.
+    pub rating: Option<i32>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetRatingResponse {
+    pub item: PlayingItemRequest,
+    pub rating: Option<i32>,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct GetRatingRequest {
+    pub item: Option<PlayingItemRequest>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct GetRatingResponse {
+    pub item: PlayingItemRequest,
+    pub rating: Option<i32>,
+}
+
+/// Hide a track from browsing, shuffle, mixes and search. It stays indexed
+/// and can still be played by ID.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetHiddenRequest {
+    pub item: Option<PlayingItemRequest>,
+    pub hidden: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetHiddenResponse {
+    pub item: PlayingItemRequest,
+    pub hidden: bool,
+    pub success: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchHiddenFilesRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchHiddenFilesResponse {
+    /// Most recently hidden first.
+    pub file_ids: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchProblemTracksRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchProblemTracksResponse {
+    /// Most recent failure first.
+    pub tracks: Vec<ProblemTrack>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct ProblemTrack {
+    pub file_id: i32,
+    pub error: String,
+    /// When the playback failed, in RFC 3339.
+    pub failed_at: String,
+}
+
+/// Forget the playback error of a track after it was fixed or replaced.
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct ClearPlaybackErrorRequest {
+    pub file_id: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ClearPlaybackErrorResponse {
+    pub file_id: i32,
+    pub success: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct SetTrackOffsetsRequest {
+    pub item: Option<PlayingItemRequest>,
+    /// Milliseconds from the start of the file, `None` for both plays the
+    /// whole file.
+    pub start_ms: Option<i32>,
+    pub end_ms: Option<i32>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetTrackOffsetsResponse {
+    pub item: PlayingItemRequest,
+    pub start_ms: Option<i32>,
+    pub end_ms: Option<i32>,
+    pub success: bool,
+    pub error: String,
+}
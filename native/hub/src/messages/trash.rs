@@ -0,0 +1,32 @@
+use rinf::{DartSignal, RustSignal, SignalPiece};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct DeletedFile {
+    pub id: i32,
+    /// ID the file had before it was removed.
+    pub file_id: i32,
+    pub directory: String,
+    pub file_name: String,
+    pub deleted_at: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchDeletedFilesRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchDeletedFilesResponse {
+    pub files: Vec<DeletedFile>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct RestoreDeletedFilesRequest {
+    pub ids: Vec<i32>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RestoreDeletedFilesResponse {
+    pub file_ids: Vec<i32>,
+    pub success: bool,
+    pub error: String,
+}
@@ -0,0 +1,30 @@
+use rinf::{DartSignal, RustSignal, SignalPiece};
+use serde::{Deserialize, Serialize};
+
+use super::mix::MixQuery;
+
+#[derive(Serialize, Deserialize, DartSignal)]
+pub struct FetchVehicleContentRequest {
+    /// Node to list, `root` for the top level.
+    pub parent_id: String,
+    pub limit: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct VehicleContentNode {
+    pub id: String,
+    pub title: String,
+    pub subtitle: String,
+    pub cover_art_path: String,
+    pub browsable: bool,
+    pub playable: bool,
+    /// Queries that start playback of this node, empty if not playable.
+    pub queries: Vec<MixQuery>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchVehicleContentResponse {
+    pub parent_id: String,
+    pub nodes: Vec<VehicleContentNode>,
+    pub error: String,
+}
@@ -1,4 +1,4 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{fs, net::SocketAddr, path::Path, sync::Arc};
 
 use anyhow::Result;
 use tokio::signal::ctrl_c;
@@ -10,12 +10,27 @@ use hub::server::{
 
 use crate::initialize_global_params;
 
+use ::config::load_config;
 use ::discovery::DiscoveryParams;
 
-pub async fn handle_server(addr: String, lib_path: String) -> Result<()> {
-    let config_path = get_config_dir()?;
+pub async fn handle_server(
+    addr: Option<String>,
+    lib_path: String,
+    overrides: Vec<String>,
+) -> Result<()> {
+    let config = load_config(Some(Path::new(&lib_path)), &overrides)?;
+    let addr = addr.unwrap_or_else(|| config.server.addr.clone());
+
+    let config_path = match &config.paths.config_dir {
+        Some(config_dir) => {
+            fs::create_dir_all(config_dir)?;
+            config_dir.clone()
+        }
+        None => get_config_dir()?,
+    };
     let device_info = load_device_info(&config_path).await?;
-    let global_params = initialize_global_params(&lib_path, config_path.to_str().unwrap()).await?;
+    let global_params =
+        initialize_global_params(&lib_path, config_path.to_str().unwrap(), config).await?;
 
     let server_manager = Arc::new(ServerManager::new(global_params).await?);
     let socket_addr: SocketAddr = addr.parse()?;
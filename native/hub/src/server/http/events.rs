@@ -0,0 +1,147 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{
+        ConnectInfo, Query, State,
+        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+    },
+    response::{IntoResponse, Response},
+};
+use log::{error, info, warn};
+use serde::Serialize;
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+
+use ::database::events::{LibraryEvent, ScanStage};
+use ::playback::player::PlayingItem;
+
+use crate::server::{ServerState, http::websocket::authorize_user};
+
+/// A library event as sent to the clients of `/events`, one JSON object
+/// per text message with the kind of the event in `type`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum EventMessage {
+    ScanProgress {
+        path: String,
+        stage: &'static str,
+        processed: usize,
+        total: usize,
+    },
+    LibraryChanged {
+        updated: Vec<i32>,
+        removed: usize,
+    },
+    TrackAnalyzed {
+        file_ids: Vec<i32>,
+    },
+    PlaybackStateChanged {
+        file_id: Option<i32>,
+        path: Option<String>,
+        state: String,
+    },
+    QueueChanged {
+        file_ids: Vec<i32>,
+    },
+    PlaylistUpdated {
+        playlist_id: i32,
+    },
+}
+
+impl From<LibraryEvent> for EventMessage {
+    fn from(event: LibraryEvent) -> Self {
+        match event {
+            LibraryEvent::ScanProgress {
+                path,
+                stage,
+                processed,
+                total,
+            } => EventMessage::ScanProgress {
+                path,
+                stage: match stage {
+                    ScanStage::Files => "files",
+                    ScanStage::CoverArts => "cover_arts",
+                },
+                processed,
+                total,
+            },
+            LibraryEvent::LibraryChanged { updated, removed } => {
+                EventMessage::LibraryChanged { updated, removed }
+            }
+            LibraryEvent::AnalysisFinished { file_ids } => EventMessage::TrackAnalyzed { file_ids },
+            LibraryEvent::PlaybackStateChanged { item, state } => {
+                let (file_id, path) = match item {
+                    Some(PlayingItem::InLibrary(file_id)) => (Some(file_id), None),
+                    Some(PlayingItem::IndependentFile(path)) => (None, Some(path)),
+                    Some(PlayingItem::Unknown) | None => (None, None),
+                };
+                EventMessage::PlaybackStateChanged {
+                    file_id,
+                    path,
+                    state: state.to_string(),
+                }
+            }
+            LibraryEvent::TracksQueued { file_ids } => EventMessage::QueueChanged { file_ids },
+            LibraryEvent::PlaylistUpdated { playlist_id } => {
+                EventMessage::PlaylistUpdated { playlist_id }
+            }
+        }
+    }
+}
+
+/// Push the library events as JSON, so other apps can follow scans,
+/// playback and playlist edits without polling. Authorized like `/ws`.
+pub async fn events_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<HashMap<String, String>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<Arc<ServerState>>,
+) -> Response {
+    match authorize_user(&state, &params).await {
+        Ok(user) => {
+            info!("Event feed authorized for {} @ {}", user.alias, addr);
+            // Subscribe before the upgrade, so nothing published meanwhile is lost
+            let receiver = state.events.subscribe();
+            ws.on_upgrade(move |socket| send_events(socket, receiver, user.alias))
+        }
+        Err(code) => {
+            warn!("Unauthorized event feed attempt from {addr}");
+            code.into_response()
+        }
+    }
+}
+
+async fn send_events(mut socket: WebSocket, mut receiver: Receiver<LibraryEvent>, alias: String) {
+    loop {
+        let event = tokio::select! {
+            event = receiver.recv() => event,
+            // Nothing is expected from the client, only whether it is gone
+            message = socket.recv() => match message {
+                Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+
+        let event = match event {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("[{alias}] Event feed skipped {skipped} events");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let text = match serde_json::to_string(&EventMessage::from(event)) {
+            Ok(text) => text,
+            Err(e) => {
+                error!("[{alias}] Failed to encode event: {e}");
+                continue;
+            }
+        };
+        if let Err(e) = socket.send(WsMessage::Text(text.into())).await {
+            error!("[{alias}] Failed to send event: {e}");
+            break;
+        }
+    }
+
+    info!("[{alias}] Event feed closed");
+}
@@ -11,6 +11,8 @@ use tower_http::services::ServeDir;
 
 use crate::server::ServerState;
 
+use super::metrics::track_stream;
+
 pub async fn file_handler(
     Path(file_path): Path<String>,
     State(state): State<Arc<ServerState>>,
@@ -62,7 +64,11 @@ pub async fn file_handler(
     match service.oneshot(request).await {
         Ok(response) => {
             let (parts, body) = response.into_parts();
-            let boxed_body = Body::new(body);
+            let boxed_body = if prefix == "library" {
+                track_stream(Body::new(body))
+            } else {
+                Body::new(body)
+            };
             Response::from_parts(parts, boxed_body)
         }
         Err(_) => StatusCode::NOT_FOUND.into_response(),
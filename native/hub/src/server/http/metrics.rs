@@ -0,0 +1,21 @@
+use axum::{body::Body, http::header, response::IntoResponse};
+use futures::StreamExt;
+
+use crate::utils::metrics::METRICS;
+
+pub async fn metrics_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.render(),
+    )
+}
+
+/// Count the body as an active stream until it is fully sent or the client
+/// goes away.
+pub fn track_stream(body: Body) -> Body {
+    let guard = METRICS.start_stream();
+    Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    }))
+}
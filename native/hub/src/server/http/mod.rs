@@ -1,7 +1,9 @@
 pub mod check_fingerprint;
 pub mod device_info;
+pub mod events;
 pub mod file;
 pub mod list;
+pub mod metrics;
 pub mod panel_alias;
 pub mod panel_auth_middleware;
 pub mod panel_broadcast;
@@ -12,4 +14,5 @@ pub mod panel_self;
 pub mod panel_status;
 pub mod ping;
 pub mod register;
+pub mod share;
 pub mod websocket;
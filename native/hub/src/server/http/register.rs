@@ -79,6 +79,7 @@ pub enum AppError {
     Internal(String),
     NotFound(String),
     Unauthorized(String),
+    Gone(String),
 }
 
 impl From<PermissionError> for AppError {
@@ -106,6 +107,7 @@ impl IntoResponse for AppError {
             AppError::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
             AppError::NotFound(e) => (StatusCode::NOT_FOUND, e),
             AppError::Unauthorized(e) => (StatusCode::UNAUTHORIZED, e),
+            AppError::Gone(e) => (StatusCode::GONE, e),
         };
 
         let body = Json(ErrorResponse { message });
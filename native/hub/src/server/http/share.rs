@@ -0,0 +1,195 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, Request, StatusCode, header::RANGE},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tower::ServiceExt;
+use tower_http::services::ServeDir;
+
+use ::database::actions::{metadata::get_metadata_summary_by_files, mixes::query_mix_media_files};
+
+use crate::server::{
+    ServerManager, ServerState,
+    share::{ShareError, ShareKind, ShareLink},
+};
+
+use super::{metrics::track_stream, register::AppError};
+
+const MAX_SHARED_TRACKS: usize = 500;
+
+impl From<ShareError> for AppError {
+    fn from(e: ShareError) -> Self {
+        match e {
+            ShareError::NotFound => AppError::NotFound(e.to_string()),
+            ShareError::Expired | ShareError::PlayLimitReached => AppError::Gone(e.to_string()),
+            ShareError::PlayNotStarted => AppError::Unauthorized(e.to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateShareRequest {
+    kind: ShareKind,
+    id: i32,
+    /// Link validity in seconds
+    ttl: u64,
+    max_plays: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct SharedTrack {
+    index: usize,
+    title: String,
+    artist: String,
+    album: String,
+    duration: f64,
+    url: String,
+}
+
+#[derive(Serialize)]
+pub struct ShareInfoResponse {
+    kind: ShareKind,
+    expires_at: u64,
+    remaining_plays: Option<u32>,
+    tracks: Vec<SharedTrack>,
+}
+
+async fn resolve_tracks(
+    server_manager: &ServerManager,
+    link: &ShareLink,
+) -> Result<Vec<::database::entities::media_files::Model>, AppError> {
+    let params = &server_manager.global_params;
+
+    query_mix_media_files(
+        &params.main_db,
+        &params.recommend_db,
+        vec![(
+            link.kind.query_operator().to_owned(),
+            link.target_id.to_string(),
+        )],
+        0,
+        MAX_SHARED_TRACKS,
+    )
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+pub async fn create_share_handler(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<CreateShareRequest>,
+) -> Result<Json<ShareLink>, AppError> {
+    let link = state.share_links.create(
+        request.kind,
+        request.id,
+        Duration::from_secs(request.ttl),
+        request.max_plays,
+    );
+
+    Ok(Json(link))
+}
+
+pub async fn list_shares_handler(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Vec<ShareLink>>, AppError> {
+    Ok(Json(state.share_links.list()))
+}
+
+pub async fn revoke_share_handler(
+    Path(token): Path<String>,
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    state.share_links.revoke(&token)?;
+
+    Ok(Json(json!({ "success": true })))
+}
+
+/// Public endpoint, the token itself is the credential.
+pub async fn share_info_handler(
+    Path(token): Path<String>,
+    State(state): State<Arc<ServerState>>,
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+) -> Result<Json<ShareInfoResponse>, AppError> {
+    let link = state.share_links.get(&token)?;
+    let files = resolve_tracks(&server_manager, &link).await?;
+    let summaries = get_metadata_summary_by_files(&server_manager.global_params.main_db, files)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Json(ShareInfoResponse {
+        kind: link.kind,
+        expires_at: link.expires_at,
+        remaining_plays: link.max_plays.map(|x| x.saturating_sub(link.plays)),
+        tracks: summaries
+            .into_iter()
+            .enumerate()
+            .map(|(index, x)| SharedTrack {
+                index,
+                title: x.title,
+                artist: x.artist,
+                album: x.album,
+                duration: x.duration,
+                url: format!("/share/{token}/{index}"),
+            })
+            .collect(),
+    }))
+}
+
+fn is_initial_request(headers: &HeaderMap) -> bool {
+    match headers.get(RANGE).and_then(|x| x.to_str().ok()) {
+        Some(range) => range.trim_start_matches("bytes=").starts_with("0-"),
+        None => true,
+    }
+}
+
+/// Public endpoint streaming a single track of a share link.
+///
+/// Only requests starting from the beginning of the file count as a play,
+/// so that seeking through ranged requests does not burn the play limit.
+/// Ranged requests from elsewhere are refused until a play of the track was
+/// counted, so they can't stream it for free.
+pub async fn share_stream_handler(
+    Path((token, index)): Path<(String, usize)>,
+    headers: HeaderMap,
+    State(state): State<Arc<ServerState>>,
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+) -> Result<Response, AppError> {
+    let link = state.share_links.get(&token)?;
+    let files = resolve_tracks(&server_manager, &link).await?;
+    let file = files
+        .get(index)
+        .ok_or_else(|| AppError::NotFound(format!("Track {index} not found")))?;
+
+    if is_initial_request(&headers) {
+        state.share_links.consume(&token, index)?;
+    } else {
+        state.share_links.resume(&token, index)?;
+    }
+
+    let lib_path = &state.app_state.lib_path;
+    let relative_path = std::path::Path::new(&file.directory).join(&file.file_name);
+
+    let mut request = Request::builder().uri(format!(
+        "/{}",
+        urlencoding::encode(&relative_path.to_string_lossy()).replace("%2F", "/")
+    ));
+    if let Some(range) = headers.get(RANGE) {
+        request = request.header(RANGE, range);
+    }
+    let request = request
+        .body(Body::empty())
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    match ServeDir::new(lib_path).oneshot(request).await {
+        Ok(response) => {
+            let (parts, body) = response.into_parts();
+            Ok(Response::from_parts(parts, track_stream(Body::new(body))))
+        }
+        Err(_) => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
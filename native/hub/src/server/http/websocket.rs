@@ -19,57 +19,61 @@ use crate::{
 };
 use discovery::server::{User, UserStatus};
 
+/// Find the approved user whose public key or fingerprint is passed as
+/// `auth`, `public_key` or `fingerprint` in the query.
+pub async fn authorize_user(
+    state: &ServerState,
+    params: &HashMap<String, String>,
+) -> Result<User, StatusCode> {
+    let auth_key = params
+        .get("auth")
+        .or_else(|| params.get("public_key"))
+        .or_else(|| params.get("fingerprint"))
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    if let Some(user) = state
+        .permission_manager
+        .read()
+        .await
+        .verify_by_public_key(auth_key)
+        .await
+    {
+        return match user.status {
+            UserStatus::Approved => Ok(user),
+            UserStatus::Blocked => Err(StatusCode::FORBIDDEN),
+            UserStatus::Pending => Err(StatusCode::UNAUTHORIZED),
+        };
+    }
+
+    if let Some(user) = state
+        .permission_manager
+        .read()
+        .await
+        .verify_by_fingerprint(auth_key)
+        .await
+    {
+        return match user.status {
+            UserStatus::Approved => Ok(user),
+            UserStatus::Blocked => Err(StatusCode::FORBIDDEN),
+            UserStatus::Pending => Err(StatusCode::UNAUTHORIZED),
+        };
+    }
+
+    Err(StatusCode::UNAUTHORIZED)
+}
+
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     Query(params): Query<HashMap<String, String>>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<Arc<ServerState>>,
 ) -> Response {
-    let auth_key = params
-        .get("auth")
-        .or_else(|| params.get("public_key"))
-        .or_else(|| params.get("fingerprint"))
-        .cloned();
-
     let host = params
         .get("host")
         .cloned()
         .unwrap_or("127.0.0.1".to_owned());
 
-    let auth_result = async {
-        let auth_key = auth_key.ok_or(StatusCode::BAD_REQUEST)?;
-
-        if let Some(user) = state
-            .permission_manager
-            .read()
-            .await
-            .verify_by_public_key(&auth_key)
-            .await
-        {
-            return match user.status {
-                UserStatus::Approved => Ok(user),
-                UserStatus::Blocked => Err(StatusCode::FORBIDDEN),
-                UserStatus::Pending => Err(StatusCode::UNAUTHORIZED),
-            };
-        }
-
-        if let Some(user) = state
-            .permission_manager
-            .read()
-            .await
-            .verify_by_fingerprint(&auth_key)
-            .await
-        {
-            return match user.status {
-                UserStatus::Approved => Ok(user),
-                UserStatus::Blocked => Err(StatusCode::FORBIDDEN),
-                UserStatus::Pending => Err(StatusCode::UNAUTHORIZED),
-            };
-        }
-
-        Err(StatusCode::UNAUTHORIZED)
-    }
-    .await;
+    let auth_result = authorize_user(&state, &params).await;
 
     match auth_result {
         Ok(user) => {
@@ -22,12 +22,14 @@ use cli::{
 use hub::{
     server::{ServerManager, WebSocketService},
     utils::{
-        GlobalParams, RunningMode, TaskTokens, initialize_databases, nid::get_or_create_node_id,
-        player::initialize_local_player,
+        GlobalParams, RunningMode, TaskTokens, events::initialize_event_subscribers,
+        initialize_databases, nid::get_or_create_node_id, player::initialize_local_player,
     },
 };
 
+use ::config::RuneConfig;
 use ::database::connection::{MainDbConnection, RecommendationDbConnection};
+use ::database::events::EventBus;
 use ::discovery::{client::CertValidator, protocol::DiscoveryService, server::PermissionManager};
 use ::fsio::FsIo;
 use ::playback::{player::Player, sfx_player::SfxPlayer};
@@ -44,10 +46,14 @@ struct Cli {
 enum Commands {
     /// Start the server
     Server {
-        #[arg(short, long, default_value = "127.0.0.1:7863")]
-        addr: String,
+        /// Address to listen on, defaults to `server.addr` of the config
+        #[arg(short, long)]
+        addr: Option<String>,
         #[arg(required = true, index = 1)]
         lib_path: String,
+        /// Override a config value, e.g. `features.metrics=false`
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        overrides: Vec<String>,
     },
     /// Initialize or change root password
     Chpwd,
@@ -92,7 +98,11 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Server { addr, lib_path } => handle_server(addr, lib_path).await?,
+        Commands::Server {
+            addr,
+            lib_path,
+            overrides,
+        } => handle_server(addr, lib_path, overrides).await?,
         Commands::Chpwd => handle_chpwd().await?,
         Commands::Broadcast => handle_broadcast().await?,
         Commands::Permission { action } => handle_permission(action).await?,
@@ -109,8 +119,20 @@ fn setup_logging() {
     tracing_subscriber::fmt().with_env_filter(filter).init();
 }
 
-async fn initialize_global_params(lib_path: &str, config_path: &str) -> Result<Arc<GlobalParams>> {
-    let db_path = format!("{lib_path}/.rune");
+async fn initialize_global_params(
+    lib_path: &str,
+    config_path: &str,
+    config: RuneConfig,
+) -> Result<Arc<GlobalParams>> {
+    ::provider_client::configure(
+        config.network.offline,
+        config.network.max_requests_per_second,
+    );
+
+    let db_path = match &config.paths.database_dir {
+        Some(database_dir) => database_dir.to_string_lossy().to_string(),
+        None => format!("{lib_path}/.rune"),
+    };
     let node_id = Arc::new(get_or_create_node_id(config_path).await?.to_string());
 
     let db_connections = initialize_databases(lib_path, Some(&db_path), &node_id).await?;
@@ -146,6 +168,16 @@ async fn initialize_global_params(lib_path: &str, config_path: &str) -> Result<A
     let permission_manager = Arc::new(RwLock::new(PermissionManager::new(config_path.as_str())?));
     let cert_validator = Arc::new(RwLock::new(CertValidator::new(config_path.as_str()).await?));
 
+    info!("Initializing library events");
+    let events = Arc::new(EventBus::default());
+    initialize_event_subscribers(
+        main_db.clone(),
+        recommend_db.clone(),
+        &events,
+        broadcaster.clone(),
+        &main_cancel_token,
+    );
+
     info!("Initializing Player events");
     tokio::spawn(initialize_local_player(
         fsio.clone(),
@@ -154,8 +186,10 @@ async fn initialize_global_params(lib_path: &str, config_path: &str) -> Result<A
         player.clone(),
         scrobbler.clone(),
         broadcaster.clone(),
+        events.clone(),
         cert_validator.clone(),
         permission_manager.clone(),
+        main_cancel_token.clone(),
     ));
 
     let global_params = Arc::new(GlobalParams {
@@ -176,6 +210,8 @@ async fn initialize_global_params(lib_path: &str, config_path: &str) -> Result<A
         permission_manager,
         server_manager: OnceLock::new(),
         running_mode: RunningMode::Server,
+        config: Arc::new(config),
+        events,
     });
 
     let server_manager = Arc::new(ServerManager::new(global_params.clone()).await?);
@@ -42,14 +42,29 @@ use crate::{
     server::{
         AppState, ServerState, WebSocketService,
         http::{
-            check_fingerprint::check_fingerprint_handler, device_info::device_info_handler,
-            file::file_handler, list::list_users_handler, panel_alias::update_alias_handler,
-            panel_auth_middleware::auth_middleware, panel_broadcast::toggle_broadcast_handler,
-            panel_delete_user::delete_user_handler, panel_login::login_handler,
-            panel_refresh::refresh_handler, panel_self::self_handler,
-            panel_status::update_user_status_handler, ping::ping_handler,
-            register::register_handler, websocket::websocket_handler,
+            check_fingerprint::check_fingerprint_handler,
+            device_info::device_info_handler,
+            events::events_handler,
+            file::file_handler,
+            list::list_users_handler,
+            metrics::metrics_handler,
+            panel_alias::update_alias_handler,
+            panel_auth_middleware::auth_middleware,
+            panel_broadcast::toggle_broadcast_handler,
+            panel_delete_user::delete_user_handler,
+            panel_login::login_handler,
+            panel_refresh::refresh_handler,
+            panel_self::self_handler,
+            panel_status::update_user_status_handler,
+            ping::ping_handler,
+            register::register_handler,
+            share::{
+                create_share_handler, list_shares_handler, revoke_share_handler,
+                share_info_handler, share_stream_handler,
+            },
+            websocket::websocket_handler,
         },
+        share::ShareLinkStore,
     },
     utils::{GlobalParams, ParamsExtractor, RinfRustSignal},
 };
@@ -154,6 +169,8 @@ impl ServerManager {
             permission_manager: self.global_params.permission_manager.clone(),
             device_scanner: self.global_params.device_scanner.clone(),
             fsio: Arc::clone(&self.fsio),
+            share_links: Arc::new(ShareLinkStore::new()),
+            events: Arc::clone(&self.global_params.events),
         });
 
         let governor_conf = GovernorConfigBuilder::default()
@@ -178,6 +195,11 @@ impl ServerManager {
                 "/panel/users/{fingerprint}/status",
                 put(update_user_status_handler),
             )
+            .route(
+                "/panel/shares",
+                get(list_shares_handler).post(create_share_handler),
+            )
+            .route("/panel/shares/{token}", delete(revoke_share_handler))
             .layer(middleware::from_fn(auth_middleware))
             .layer(Extension(self.clone()))
             .with_state(server_state.clone());
@@ -188,16 +210,28 @@ impl ServerManager {
                 config: governor_conf.into(),
             });
 
-        let app = Router::new()
+        let share_routes: Router<Arc<ServerState>> = Router::new()
+            .route("/share/{token}", get(share_info_handler))
+            .route("/share/{token}/{index}", get(share_stream_handler))
+            .layer(Extension(self.clone()));
+
+        let mut app = Router::new()
             .merge(register_route)
             .merge(auth_routes)
             .merge(protected_routes)
+            .merge(share_routes)
             .route("/ping", get(ping_handler))
             .route("/ws", get(websocket_handler))
+            .route("/events", get(events_handler))
             .route("/check-fingerprint", get(check_fingerprint_handler))
             .route("/files/{*file_path}", get(file_handler))
-            .route("/device-info", get(device_info_handler))
-            .with_state(server_state);
+            .route("/device-info", get(device_info_handler));
+
+        if self.global_params.config.features.metrics {
+            app = app.route("/metrics", get(metrics_handler));
+        }
+
+        let app = app.with_state(server_state);
 
         info!(
             "Library files path: {}",
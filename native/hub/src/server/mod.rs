@@ -3,6 +3,7 @@ mod server_request;
 pub mod api;
 pub mod http;
 mod manager;
+pub mod share;
 pub mod utils;
 
 use fsio::FsIo;
@@ -10,18 +11,22 @@ pub use manager::{
     ServerManager, generate_or_load_certificates, get_or_generate_alias, update_root_password,
 };
 
-use std::{collections::HashMap, future::Future, path::PathBuf, pin::Pin, sync::Arc};
+use std::{
+    collections::HashMap, future::Future, path::PathBuf, pin::Pin, sync::Arc, time::Instant,
+};
 
 use anyhow::Result;
 use log::error;
 use tokio::sync::{Mutex, RwLock, broadcast};
 
+use ::database::events::EventBus;
 use ::discovery::{protocol::DiscoveryService, server::PermissionManager, utils::DeviceInfo};
 
+use self::share::ShareLinkStore;
 use crate::{
     Session,
     backends::remote::encode_message,
-    utils::{Broadcaster, RinfRustSignal},
+    utils::{Broadcaster, RinfRustSignal, metrics::METRICS},
 };
 
 pub type HandlerFn = Box<
@@ -45,6 +50,8 @@ pub struct ServerState {
     pub permission_manager: Arc<RwLock<PermissionManager>>,
     pub device_scanner: Arc<DiscoveryService>,
     pub fsio: Arc<FsIo>,
+    pub share_links: Arc<ShareLinkStore>,
+    pub events: Arc<EventBus>,
 }
 
 pub struct WebSocketService {
@@ -86,7 +93,12 @@ impl WebSocketService {
     ) -> Option<(String, Result<Vec<u8>>)> {
         let handlers = self.handlers.lock().await;
         let handler = handlers.get(msg_type)?;
-        Some(handler(payload, session).await)
+
+        let start = Instant::now();
+        let (response_type, result) = handler(payload, session).await;
+        METRICS.observe_request(msg_type, start.elapsed(), result.is_ok());
+
+        Some((response_type, result))
     }
 }
 
@@ -0,0 +1,235 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rand::{
+    distributions::{Alphanumeric, DistString},
+    thread_rng,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareKind {
+    Track,
+    Album,
+    Playlist,
+}
+
+impl ShareKind {
+    /// The mix query operator that resolves the shared collection into tracks.
+    pub fn query_operator(&self) -> &'static str {
+        match self {
+            ShareKind::Track => "lib::track",
+            ShareKind::Album => "lib::album",
+            ShareKind::Playlist => "lib::playlist",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    pub token: String,
+    pub kind: ShareKind,
+    pub target_id: i32,
+    /// Expiration time (UNIX timestamp)
+    pub expires_at: u64,
+    pub max_plays: Option<u32>,
+    pub plays: u32,
+    /// Tracks with a counted play, which may be streamed from any offset.
+    #[serde(skip)]
+    started: HashSet<usize>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ShareError {
+    #[error("Share link not found")]
+    NotFound,
+    #[error("Share link expired")]
+    Expired,
+    #[error("Share link play limit reached")]
+    PlayLimitReached,
+    #[error("Share link track has to be played from the start first")]
+    PlayNotStarted,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+impl ShareLink {
+    fn check_expiry(&self, now: u64) -> Result<(), ShareError> {
+        if now >= self.expires_at {
+            return Err(ShareError::Expired);
+        }
+
+        Ok(())
+    }
+
+    fn check(&self, now: u64) -> Result<(), ShareError> {
+        self.check_expiry(now)?;
+
+        if let Some(max_plays) = self.max_plays {
+            if self.plays >= max_plays {
+                return Err(ShareError::PlayLimitReached);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Share links handed out by the server. Links only live as long as the
+/// server process, restarting the server revokes every link.
+#[derive(Debug, Default)]
+pub struct ShareLinkStore {
+    links: Mutex<HashMap<String, ShareLink>>,
+}
+
+impl ShareLinkStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create(
+        &self,
+        kind: ShareKind,
+        target_id: i32,
+        validity: Duration,
+        max_plays: Option<u32>,
+    ) -> ShareLink {
+        let link = ShareLink {
+            token: Alphanumeric.sample_string(&mut thread_rng(), TOKEN_LENGTH),
+            kind,
+            target_id,
+            expires_at: now_secs().saturating_add(validity.as_secs()),
+            max_plays,
+            plays: 0,
+            started: HashSet::new(),
+        };
+
+        let mut links = self.links.lock().unwrap();
+        links.retain(|_, x| x.check(now_secs()) == Ok(()));
+        links.insert(link.token.clone(), link.clone());
+
+        link
+    }
+
+    /// Look up a link without consuming a play.
+    pub fn get(&self, token: &str) -> Result<ShareLink, ShareError> {
+        let links = self.links.lock().unwrap();
+        let link = links.get(token).ok_or(ShareError::NotFound)?;
+        link.check(now_secs())?;
+
+        Ok(link.clone())
+    }
+
+    /// Look up a link and count one play of the track against its limit.
+    pub fn consume(&self, token: &str, index: usize) -> Result<ShareLink, ShareError> {
+        let mut links = self.links.lock().unwrap();
+        let link = links.get_mut(token).ok_or(ShareError::NotFound)?;
+        link.check(now_secs())?;
+        link.plays += 1;
+        link.started.insert(index);
+
+        Ok(link.clone())
+    }
+
+    /// Look up a link to continue streaming a track from another offset,
+    /// which only works once a play of the track has been counted.
+    pub fn resume(&self, token: &str, index: usize) -> Result<ShareLink, ShareError> {
+        let links = self.links.lock().unwrap();
+        let link = links.get(token).ok_or(ShareError::NotFound)?;
+        link.check_expiry(now_secs())?;
+        if !link.started.contains(&index) {
+            return Err(ShareError::PlayNotStarted);
+        }
+
+        Ok(link.clone())
+    }
+
+    pub fn revoke(&self, token: &str) -> Result<(), ShareError> {
+        self.links
+            .lock()
+            .unwrap()
+            .remove(token)
+            .map(|_| ())
+            .ok_or(ShareError::NotFound)
+    }
+
+    pub fn list(&self) -> Vec<ShareLink> {
+        let now = now_secs();
+        self.links
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|x| x.check(now).is_ok())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_play_limit() {
+        let store = ShareLinkStore::new();
+        let link = store.create(ShareKind::Track, 1, Duration::from_secs(60), Some(2));
+
+        assert!(store.consume(&link.token, 0).is_ok());
+        assert!(store.get(&link.token).is_ok());
+        assert!(store.consume(&link.token, 0).is_ok());
+        assert_eq!(
+            store.consume(&link.token, 0).unwrap_err(),
+            ShareError::PlayLimitReached
+        );
+    }
+
+    #[test]
+    fn test_resume_needs_counted_play() {
+        let store = ShareLinkStore::new();
+        let link = store.create(ShareKind::Album, 1, Duration::from_secs(60), Some(1));
+
+        assert_eq!(
+            store.resume(&link.token, 0).unwrap_err(),
+            ShareError::PlayNotStarted
+        );
+        store.consume(&link.token, 0).unwrap();
+        // Seeking through the counted play keeps working at the limit
+        assert!(store.resume(&link.token, 0).is_ok());
+        assert_eq!(
+            store.resume(&link.token, 1).unwrap_err(),
+            ShareError::PlayNotStarted
+        );
+    }
+
+    #[test]
+    fn test_huge_validity() {
+        let store = ShareLinkStore::new();
+        let link = store.create(ShareKind::Track, 1, Duration::MAX, None);
+
+        assert_eq!(link.expires_at, u64::MAX);
+        assert!(store.get(&link.token).is_ok());
+    }
+
+    #[test]
+    fn test_expired_and_revoked() {
+        let store = ShareLinkStore::new();
+        let expired = store.create(ShareKind::Album, 1, Duration::from_secs(0), None);
+        assert_eq!(store.get(&expired.token).unwrap_err(), ShareError::Expired);
+
+        let link = store.create(ShareKind::Playlist, 1, Duration::from_secs(60), None);
+        store.revoke(&link.token).unwrap();
+        assert_eq!(store.get(&link.token).unwrap_err(), ShareError::NotFound);
+    }
+}
@@ -2,8 +2,13 @@ use crate::implement_rinf_rust_signal_trait;
 use crate::messages::*;
 use rinf::RustSignal;
 
-implement_rinf_rust_signal_trait!(ScanAudioLibraryProgress, ScanAudioLibraryResponse);
-implement_rinf_rust_signal_trait!(SetMediaLibraryPathResponse);
+implement_rinf_rust_signal_trait!(
+    ScanAudioLibraryProgress,
+    ScanAudioLibraryResponse,
+    AlbumThumbnailProgress,
+    LibraryUpdated
+);
+implement_rinf_rust_signal_trait!(SetMediaLibraryPathResponse, StartupStatus);
 implement_rinf_rust_signal_trait!(AnalyzeAudioLibraryProgress, AnalyzeAudioLibraryResponse);
 implement_rinf_rust_signal_trait!(
     DeduplicateAudioLibraryProgress,
@@ -11,9 +16,12 @@ implement_rinf_rust_signal_trait!(
 );
 implement_rinf_rust_signal_trait!(
     PlaybackStatus,
+    LyricLineUpdate,
     ScrobbleServiceStatusUpdated,
     CrashResponse,
-    RealtimeFFT
+    RealtimeFFT,
+    ListeningExposureUpdated,
+    PlaybackFailed
 );
 implement_rinf_rust_signal_trait!(PlaylistUpdate);
 implement_rinf_rust_signal_trait!(TrustListUpdated);
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use log::error;
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
+
+use ::database::{
+    actions::recommendation::sync_recommendation_on_events,
+    connection::{MainDbConnection, RecommendationDbConnection},
+    events::{EventBus, LibraryEvent, next_events},
+};
+
+use crate::messages::*;
+use crate::utils::Broadcaster;
+
+/// Start the subscribers of the event bus, they stop with `cancel_token`.
+pub fn initialize_event_subscribers(
+    main_db: Arc<MainDbConnection>,
+    recommend_db: Arc<RecommendationDbConnection>,
+    events: &EventBus,
+    broadcaster: Arc<dyn Broadcaster>,
+    cancel_token: &CancellationToken,
+) {
+    // Subscribe right away, events published before the tasks run are kept
+    let recommendation_events = events.subscribe();
+    let broadcast_events = events.subscribe();
+
+    let cancel_token_for_recommendation = cancel_token.clone();
+    tokio::spawn(async move {
+        if let Err(e) = sync_recommendation_on_events(
+            &main_db,
+            &recommend_db,
+            recommendation_events,
+            cancel_token_for_recommendation,
+        )
+        .await
+        {
+            error!("Failed to keep recommendations in sync: {e:#?}");
+        }
+    });
+
+    tokio::spawn(broadcast_library_events(
+        broadcaster,
+        broadcast_events,
+        cancel_token.clone(),
+    ));
+}
+
+/// Tell the UI about library changes, so open pages can be refreshed.
+async fn broadcast_library_events(
+    broadcaster: Arc<dyn Broadcaster>,
+    mut receiver: Receiver<LibraryEvent>,
+    cancel_token: CancellationToken,
+) {
+    while let Some(events) = next_events(&mut receiver, &cancel_token).await {
+        for event in events {
+            if let LibraryEvent::LibraryChanged { updated, removed } = event {
+                broadcaster.broadcast(&LibraryUpdated {
+                    updated_file_ids: updated,
+                    removed: removed.try_into().unwrap_or(i32::MAX),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+use std::process::Command;
+
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+use anyhow::bail;
+
+/// Show the file selected in the file manager of the OS, without waiting
+/// for the file manager to exit.
+#[cfg(target_os = "windows")]
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    // Explorer parses the argument on its own, quoting it like other
+    // arguments would break paths with commas
+    Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()
+        .with_context(|| "Failed to start Explorer")?;
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .spawn()
+        .with_context(|| "Failed to start Finder")?;
+
+    Ok(())
+}
+
+/// File managers implementing the freedesktop interface select the file,
+/// the others only open its folder.
+#[cfg(target_os = "linux")]
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let uri =
+        format!("file://{}", urlencoding::encode(&path.to_string_lossy())).replace("%2F", "/");
+    let selected = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{uri}"))
+        .arg("string:")
+        .output()
+        .is_ok_and(|x| x.status.success());
+    if selected {
+        return Ok(());
+    }
+
+    let directory = path
+        .parent()
+        .with_context(|| format!("No containing folder: {}", path.display()))?;
+    Command::new("xdg-open")
+        .arg(directory)
+        .spawn()
+        .with_context(|| "Failed to start xdg-open")?;
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    bail!("No file manager to show {} in", path.display())
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// Process wide metrics, rendered in the Prometheus text format on
+/// `/metrics` while the server is running.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+const SCAN_BUCKETS: &[f64] = &[1.0, 5.0, 15.0, 60.0, 300.0, 900.0, 3600.0];
+const ANALYSIS_BUCKETS: &[f64] = &[10.0, 60.0, 300.0, 900.0, 3600.0, 14400.0];
+const REQUEST_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn add(&self, x: u64) {
+        self.0.fetch_add(x, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+pub struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<AtomicU64>,
+    /// Sum of all observations, stored as raw `f64` bits.
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: buckets.iter().map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0f64.to_bits()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, x: f64) {
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            if x <= *bound {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + x).to_bits())
+            });
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn observe_duration(&self, x: Duration) {
+        self.observe(x.as_secs_f64());
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {}",
+                count.load(Ordering::Relaxed)
+            );
+        }
+
+        let count = self.count.load(Ordering::Relaxed);
+        let sum = f64::from_bits(self.sum.load(Ordering::Relaxed));
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {count}"
+        );
+
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        let _ = writeln!(out, "{name}_sum{labels} {sum}");
+        let _ = writeln!(out, "{name}_count{labels} {count}");
+    }
+}
+
+/// Decrements the active stream gauge when dropped, move it into the
+/// response body so it lives as long as the transfer.
+pub struct StreamGuard;
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        METRICS.active_streams.dec();
+    }
+}
+
+#[derive(Debug)]
+pub struct Metrics {
+    pub scan_duration_seconds: Histogram,
+    pub scanned_files_total: Counter,
+    pub analysis_duration_seconds: Histogram,
+    pub analyzed_files_total: Counter,
+    pub active_streams: Gauge,
+    /// Latency of remote requests, by request type.
+    request_duration_seconds: Mutex<BTreeMap<String, Histogram>>,
+    request_errors_total: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            scan_duration_seconds: Histogram::new(SCAN_BUCKETS),
+            scanned_files_total: Counter::default(),
+            analysis_duration_seconds: Histogram::new(ANALYSIS_BUCKETS),
+            analyzed_files_total: Counter::default(),
+            active_streams: Gauge::default(),
+            request_duration_seconds: Mutex::new(BTreeMap::new()),
+            request_errors_total: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn observe_request(&self, request_type: &str, duration: Duration, success: bool) {
+        if let Ok(mut histograms) = self.request_duration_seconds.lock() {
+            histograms
+                .entry(request_type.to_string())
+                .or_insert_with(|| Histogram::new(REQUEST_BUCKETS))
+                .observe_duration(duration);
+        }
+
+        if !success && let Ok(mut errors) = self.request_errors_total.lock() {
+            *errors.entry(request_type.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn start_stream(&self) -> StreamGuard {
+        self.active_streams.inc();
+        StreamGuard
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let header = |out: &mut String, name: &str, kind: &str, help: &str| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+        };
+
+        header(
+            &mut out,
+            "rune_scan_duration_seconds",
+            "histogram",
+            "Duration of library scans.",
+        );
+        self.scan_duration_seconds
+            .render(&mut out, "rune_scan_duration_seconds", "");
+
+        header(
+            &mut out,
+            "rune_scanned_files_total",
+            "counter",
+            "Files checked by library scans.",
+        );
+        let _ = writeln!(
+            out,
+            "rune_scanned_files_total {}",
+            self.scanned_files_total.get()
+        );
+
+        header(
+            &mut out,
+            "rune_analysis_duration_seconds",
+            "histogram",
+            "Duration of library analysis runs.",
+        );
+        self.analysis_duration_seconds
+            .render(&mut out, "rune_analysis_duration_seconds", "");
+
+        header(
+            &mut out,
+            "rune_analyzed_files_total",
+            "counter",
+            "Files analyzed by library analysis runs.",
+        );
+        let _ = writeln!(
+            out,
+            "rune_analyzed_files_total {}",
+            self.analyzed_files_total.get()
+        );
+
+        header(
+            &mut out,
+            "rune_active_streams",
+            "gauge",
+            "Audio files currently being streamed.",
+        );
+        let _ = writeln!(out, "rune_active_streams {}", self.active_streams.get());
+
+        header(
+            &mut out,
+            "rune_request_duration_seconds",
+            "histogram",
+            "Latency of remote requests.",
+        );
+        if let Ok(histograms) = self.request_duration_seconds.lock() {
+            for (request_type, histogram) in histograms.iter() {
+                histogram.render(
+                    &mut out,
+                    "rune_request_duration_seconds",
+                    &format!("type=\"{request_type}\""),
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "rune_request_errors_total",
+            "counter",
+            "Remote requests that failed.",
+        );
+        if let Ok(errors) = self.request_errors_total.lock() {
+            for (request_type, count) in errors.iter() {
+                let _ = writeln!(
+                    out,
+                    "rune_request_errors_total{{type=\"{request_type}\"}} {count}"
+                );
+            }
+        }
+
+        out
+    }
+}
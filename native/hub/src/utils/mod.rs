@@ -1,6 +1,11 @@
 pub mod broadcastable;
+pub mod events;
+pub mod file_manager;
+pub mod metrics;
 pub mod nid;
 pub mod player;
+pub mod radio;
+pub mod startup;
 
 use std::{
     collections::HashMap,
@@ -11,13 +16,17 @@ use std::{
 
 use anyhow::{Context, Result};
 use fsio::FsIo;
-use log::{error, info};
+use log::{error, info, warn};
 use nid::get_or_create_node_id;
 use rinf::DartSignal;
 use scrobbling::manager::ScrobblingServiceManager;
+use startup::{
+    LastLibrary, PrewarmedLibrary, prewarm_last_library, report_startup, write_last_library,
+};
 use tokio::sync::{Mutex, RwLock};
 use tokio_util::sync::CancellationToken;
 
+use ::config::RuneConfig;
 use ::database::{
     actions::{
         cover_art::bake_cover_art_by_media_files, metadata::MetadataSummary,
@@ -28,7 +37,8 @@ use ::database::{
         connect_main_db, connect_recommendation_db, create_redirect,
     },
     entities::media_files,
-    playing_item::MediaFileHandle,
+    events::EventBus,
+    playing_item::{MediaFileHandle, dispatcher::PlayingItemActionDispatcher},
 };
 use ::discovery::{client::CertValidator, protocol::DiscoveryService, server::PermissionManager};
 use ::playback::{
@@ -60,11 +70,19 @@ pub async fn initialize_databases(
 ) -> Result<DatabaseConnections> {
     info!("Initializing databases");
 
+    // Opening LMDB is blocking, let it run while the main DB migrates
+    let recommend_path = path.to_string();
+    let recommend_db_path = db_path.map(|x| x.to_string());
+    let recommend_db = tokio::task::spawn_blocking(move || {
+        connect_recommendation_db(&recommend_path, recommend_db_path.as_deref())
+    });
+
     let main_db = connect_main_db(path, db_path, node_id)
         .await
         .with_context(|| "Failed to connect to main DB")?;
 
-    let recommend_db = connect_recommendation_db(path, db_path)
+    let recommend_db = recommend_db
+        .await?
         .with_context(|| "Failed to connect to recommendation DB")?;
 
     Ok(DatabaseConnections {
@@ -78,6 +96,7 @@ pub struct TaskTokens {
     pub scan_token: Option<CancellationToken>,
     pub analyze_token: Option<CancellationToken>,
     pub deduplicate_token: Option<CancellationToken>,
+    pub radio_token: Option<CancellationToken>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -104,6 +123,8 @@ pub struct GlobalParams {
     pub permission_manager: Arc<RwLock<PermissionManager>>,
     pub server_manager: OnceLock<Arc<ServerManager>>,
     pub running_mode: RunningMode,
+    pub config: Arc<RuneConfig>,
+    pub events: Arc<EventBus>,
 }
 
 impl Debug for GlobalParams {
@@ -143,139 +164,207 @@ impl Broadcaster for LocalGuiBroadcaster {
 
 pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>) -> Result<()> {
     let receiver = SetMediaLibraryPathRequest::get_dart_signal_receiver();
+    let startup_receiver = StartupRequest::get_dart_signal_receiver();
     let broadcaster: Arc<dyn Broadcaster> = Arc::new(LocalGuiBroadcaster);
+    let mut prewarmed: Option<PrewarmedLibrary> = None;
+
+    report_startup(&*broadcaster, StartupStage::WaitingForLibrary, None, None);
 
     loop {
-        while let Some(dart_signal) = receiver.recv().await {
-            #[cfg(not(target_os = "android"))]
-            let media_library_path = &dart_signal.message.path;
-            #[cfg(target_os = "android")]
-            let media_library_path = "";
-
-            let config_path = &dart_signal.message.config_path;
-            let alias = &dart_signal.message.alias;
-            let node_id = get_or_create_node_id(config_path).await?.to_string();
-            #[cfg(not(target_os = "android"))]
-            let fsio = Arc::new(FsIo::new());
-            #[cfg(target_os = "android")]
-            let fsio = Arc::new(FsIo::new(
-                Path::new(".rune/.android-fs.db"),
-                &dart_signal.message.path,
-            )?);
-
-            match &dart_signal.message.hosted_on {
-                OperationDestination::Local => {
-                    let database_path = dart_signal.message.db_path;
-                    let database_mode = dart_signal.message.mode;
-                    info!("Received path: {media_library_path}");
-
-                    let library_test = match check_library_state(media_library_path) {
-                        Ok(x) => x,
-                        Err(e) => {
-                            broadcaster.broadcast(&SetMediaLibraryPathResponse {
-                                path: media_library_path.to_string(),
-                                success: false,
-                                error: Some(format!("{e:#?}")),
-                                not_ready: false,
-                            });
-                            continue;
-                        }
-                    };
+        tokio::select! {
+            Some(dart_signal) = startup_receiver.recv() => {
+                let config_path = &dart_signal.message.config_path;
+                let node_id = get_or_create_node_id(config_path).await?.to_string();
+                if let Some(stale) = prewarmed.take() {
+                    stale.discard().await;
+                }
+                prewarmed = prewarm_last_library(config_path, node_id, &*broadcaster);
+            }
+            Some(dart_signal) = receiver.recv() => {
+                set_media_library_path(
+                    dart_signal.message,
+                    Arc::clone(&scrobbler),
+                    Arc::clone(&broadcaster),
+                    prewarmed.take(),
+                )
+                .await?;
+            }
+            else => break,
+        }
+    }
 
-                    if database_mode.is_none() {
-                        match &library_test {
-                            LibraryState::Uninitialized => {
-                                broadcaster.broadcast(&SetMediaLibraryPathResponse {
-                                    path: media_library_path.to_string(),
-                                    success: false,
-                                    error: None,
-                                    not_ready: true,
-                                });
-                                continue;
-                            }
-                            LibraryState::Initialized(_) => {}
-                        }
-                    }
+    Ok(())
+}
 
-                    if let Some(mode) = database_mode {
-                        if mode == LibraryInitializeMode::Redirected {
-                            if let Err(e) = create_redirect(media_library_path) {
-                                broadcaster.broadcast(&SetMediaLibraryPathResponse {
-                                    path: media_library_path.to_string(),
-                                    success: false,
-                                    error: Some(format!("{e:#?}")),
-                                    not_ready: false,
-                                });
-                                continue;
-                            }
-                        }
+async fn set_media_library_path(
+    request: SetMediaLibraryPathRequest,
+    scrobbler: Arc<Mutex<ScrobblingManager>>,
+    broadcaster: Arc<dyn Broadcaster>,
+    prewarmed: Option<PrewarmedLibrary>,
+) -> Result<()> {
+    #[cfg(not(target_os = "android"))]
+    let media_library_path = &request.path;
+    #[cfg(target_os = "android")]
+    let media_library_path = "";
+
+    let config_path = &request.config_path;
+    let alias = &request.alias;
+    let node_id = get_or_create_node_id(config_path).await?.to_string();
+    #[cfg(not(target_os = "android"))]
+    let fsio = Arc::new(FsIo::new());
+    #[cfg(target_os = "android")]
+    let fsio = Arc::new(FsIo::new(Path::new(".rune/.android-fs.db"), &request.path)?);
+
+    // Connections opened ahead of time are only reused when the user picked
+    // the same library again, otherwise they are closed before anything else
+    // opens the databases
+    let prewarmed = match prewarmed {
+        Some(prewarmed)
+            if matches!(request.hosted_on, OperationDestination::Local)
+                && request.mode.is_none()
+                && prewarmed.matches(media_library_path, &request.db_path, &node_id) =>
+        {
+            Some(prewarmed)
+        }
+        Some(prewarmed) => {
+            prewarmed.discard().await;
+            None
+        }
+        None => None,
+    };
+
+    let fail = |e: anyhow::Error| {
+        report_startup(
+            &*broadcaster,
+            StartupStage::Failed,
+            Some(media_library_path),
+            Some(format!("{e:#}")),
+        );
+        broadcaster.broadcast(&SetMediaLibraryPathResponse {
+            path: media_library_path.to_string(),
+            success: false,
+            error: Some(format!("{e:#?}")),
+            not_ready: false,
+        });
+    };
+
+    match &request.hosted_on {
+        OperationDestination::Local => {
+            let database_path = &request.db_path;
+            let database_mode = request.mode;
+            info!("Received path: {media_library_path}");
+
+            let library_test = match check_library_state(media_library_path) {
+                Ok(x) => x,
+                Err(e) => {
+                    fail(e);
+                    return Ok(());
+                }
+            };
+
+            if database_mode.is_none() {
+                match &library_test {
+                    LibraryState::Uninitialized => {
+                        report_startup(
+                            &*broadcaster,
+                            StartupStage::WaitingForLibrary,
+                            Some(media_library_path),
+                            None,
+                        );
+                        broadcaster.broadcast(&SetMediaLibraryPathResponse {
+                            path: media_library_path.to_string(),
+                            success: false,
+                            error: None,
+                            not_ready: true,
+                        });
+                        return Ok(());
                     }
+                    LibraryState::Initialized(_) => {}
+                }
+            }
 
-                    // Initialize databases
-                    match initialize_databases(media_library_path, Some(&database_path), &node_id)
-                        .await
-                    {
-                        Ok(db_connections) => {
-                            // Send success response to Dart
-                            broadcaster.broadcast(&SetMediaLibraryPathResponse {
-                                path: media_library_path.to_string(),
-                                success: true,
-                                error: None,
-                                not_ready: false,
-                            });
-
-                            // Clone the Arc for this iteration
-                            let scrobbler_clone = Arc::clone(&scrobbler);
-
-                            // Continue with main loop
-                            local_player_loop(
-                                fsio,
-                                media_library_path.to_string(),
-                                config_path.to_string(),
-                                db_connections,
-                                scrobbler_clone,
-                                broadcaster.clone(),
-                            )
-                            .await;
-                        }
-                        Err(e) => {
-                            error!("Database initialization failed: {e:#?}");
-                            // Send error response to Dart
-                            broadcaster.broadcast(&SetMediaLibraryPathResponse {
-                                path: media_library_path.to_string(),
-                                success: false,
-                                error: Some(format!("{e:#?}")),
-                                not_ready: false,
-                            });
-                        }
+            if let Some(mode) = database_mode {
+                if mode == LibraryInitializeMode::Redirected {
+                    if let Err(e) = create_redirect(media_library_path) {
+                        fail(e);
+                        return Ok(());
                     }
                 }
-                OperationDestination::Remote => {
-                    let config_path = &dart_signal.message.config_path;
-                    match server_player_loop(media_library_path, config_path, alias).await {
-                        Ok(_) => {
-                            broadcaster.broadcast(&SetMediaLibraryPathResponse {
-                                path: media_library_path.to_string(),
-                                success: true,
-                                error: None,
-                                not_ready: false,
-                            });
-                        }
-                        Err(e) => {
-                            broadcaster.broadcast(&SetMediaLibraryPathResponse {
-                                path: media_library_path.to_string(),
-                                success: false,
-                                error: Some(format!("{e:#?}")),
-                                not_ready: false,
-                            });
-                        }
+            }
+
+            let db_connections = match prewarmed {
+                Some(prewarmed) => {
+                    info!("Using prewarmed databases");
+                    prewarmed.connections().await
+                }
+                None => {
+                    report_startup(
+                        &*broadcaster,
+                        StartupStage::ConnectingDatabases,
+                        Some(media_library_path),
+                        None,
+                    );
+                    initialize_databases(media_library_path, Some(database_path), &node_id).await
+                }
+            };
+
+            match db_connections {
+                Ok(db_connections) => {
+                    let last_library = LastLibrary {
+                        path: media_library_path.to_string(),
+                        db_path: database_path.to_string(),
+                    };
+                    if let Err(e) = write_last_library(config_path, &last_library) {
+                        warn!("{e:#}");
                     }
+
+                    // Send success response to Dart
+                    broadcaster.broadcast(&SetMediaLibraryPathResponse {
+                        path: media_library_path.to_string(),
+                        success: true,
+                        error: None,
+                        not_ready: false,
+                    });
+
+                    // Continue with main loop
+                    local_player_loop(
+                        fsio,
+                        media_library_path.to_string(),
+                        config_path.to_string(),
+                        db_connections,
+                        scrobbler,
+                        broadcaster.clone(),
+                    )
+                    .await;
+                }
+                Err(e) => {
+                    error!("Database initialization failed: {e:#?}");
+                    fail(e);
                 }
             }
         }
-
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        OperationDestination::Remote => {
+            match server_player_loop(media_library_path, config_path, alias).await {
+                Ok(_) => {
+                    report_startup(
+                        &*broadcaster,
+                        StartupStage::Ready,
+                        Some(media_library_path),
+                        None,
+                    );
+                    broadcaster.broadcast(&SetMediaLibraryPathResponse {
+                        path: media_library_path.to_string(),
+                        success: true,
+                        error: None,
+                        not_ready: false,
+                    });
+                }
+                Err(e) => fail(e),
+            }
+        }
     }
+
+    Ok(())
 }
 
 pub async fn inject_cover_art_map(
@@ -319,6 +408,7 @@ pub async fn inject_cover_art_map(
         collection_type: collection.collection_type,
         cover_art_map,
         readonly: collection.readonly,
+        subtitle: collection.subtitle,
     })
 }
 
@@ -402,11 +492,16 @@ pub async fn parse_media_files(
     let mut media_files = Vec::with_capacity(media_summaries.len());
 
     for file in media_summaries {
-        let media_path = fsio.canonicalize_path(
-            &Path::new(lib_path.as_ref())
-                .join(&file.directory)
-                .join(&file.file_name),
-        );
+        let raw_path = Path::new(lib_path.as_ref())
+            .join(&file.directory)
+            .join(&file.file_name);
+        // Files of an offline source can't be resolved, keep them listed
+        // with their stored path
+        let media_path = if file.available {
+            fsio.canonicalize_path(&raw_path)
+        } else {
+            Ok(raw_path)
+        };
 
         match media_path {
             Ok(media_path) => {
@@ -434,6 +529,8 @@ pub async fn parse_media_files(
                     duration: file.duration,
                     cover_art_id: file.cover_art_id.unwrap_or(-1),
                     track_number: file.track_number,
+                    key: file.key.unwrap_or_default(),
+                    available: file.available,
                 };
 
                 media_files.push(media_file);
@@ -447,6 +544,26 @@ pub async fn parse_media_files(
     Ok(media_files)
 }
 
+/// Resolve the files of `items`, keeping their order and duplicates and
+/// skipping the ones that no longer exist.
+pub async fn get_ordered_file_handles(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    items: &[PlayingItem],
+) -> Result<Vec<MediaFileHandle>> {
+    let handles: HashMap<PlayingItem, MediaFileHandle> = PlayingItemActionDispatcher::new()
+        .get_file_handle(fsio, main_db, items)
+        .await?
+        .into_iter()
+        .map(|x| (x.item.clone(), x))
+        .collect();
+
+    Ok(items
+        .iter()
+        .filter_map(|x| handles.get(x).cloned())
+        .collect())
+}
+
 pub fn files_to_playback_request<P: AsRef<Path>>(
     fsio: &FsIo,
     lib_path: &P,
@@ -1,7 +1,7 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Error, Result, bail};
@@ -12,12 +12,29 @@ use tokio::{
     sync::{Mutex, RwLock},
     task,
 };
+use tokio_util::sync::CancellationToken;
 
 use ::database::{
     actions::{
-        logging::insert_log, playback_queue::replace_playback_queue, stats::increase_played_through,
+        analysis::get_loudness_by_file_ids,
+        logging::insert_log,
+        lyrics::{get_lyrics_by_file_id, parse_stored_lyrics},
+        playback_queue::{
+            get_playback_queue_position, get_saved_playback_queue, replace_playback_queue,
+            set_playback_queue_current, set_playback_queue_position,
+        },
+        seek_table::get_seek_tables_by_file_ids,
+        settings::{
+            EQUALIZER_KEY, LISTENING_EXPOSURE_KEY, NIGHT_MODE_KEY, OUTPUT_DEVICE_KEY,
+            REPLAY_GAIN_KEY, get_setting, set_setting,
+        },
+        stats::{
+            TrackOffsets, get_track_offsets_by_file_ids, record_playback_complete,
+            record_playback_error, record_playback_start,
+        },
     },
     connection::MainDbConnection,
+    events::{EventBus, LibraryEvent, next_events},
     playing_item::{
         PlayingItemMetadataSummary, dispatcher::PlayingItemActionDispatcher,
         library_item::extract_in_library_ids,
@@ -26,15 +43,24 @@ use ::database::{
 
 use ::discovery::client::CertValidator;
 use ::fsio::FsIo;
+use ::lyric::{lrc::parse_lrc, parser::parse_audio_lyrics, types::LyricFile};
+use ::metadata::reader::get_lyrics;
 use ::playback::{
     MediaMetadata, MediaPlayback, MediaPosition,
     controller::{MediaControlManager, get_default_cover_art_path, handle_media_control_event},
-    player::{Playable, PlayingItem, PlaylistStatus},
+    equalizer::EqualizerConfig,
+    exposure::ExposureStatus,
+    night_mode::NightModeConfig,
+    player::{Playable, PlaybackState, PlayingItem, PlaylistStatus},
+    replay_gain::{ReplayGainConfig, ReplayGainInfo},
+    seek_table::SeekTable,
+    strategies::AddMode,
+    trim::TrackTrim,
 };
 use ::scrobbling::{ScrobblingTrack, manager::ScrobblingServiceManager};
 
 use crate::messages::*;
-use crate::utils::Broadcaster;
+use crate::utils::{Broadcaster, files_to_playback_request, get_ordered_file_handles};
 
 pub fn metadata_summary_to_scrobbling_track(
     metadata: &PlayingItemMetadataSummary,
@@ -54,6 +80,266 @@ pub fn metadata_summary_to_scrobbling_track(
     }
 }
 
+async fn update_replay_gain_info(
+    main_db: &DatabaseConnection,
+    player: &Mutex<dyn Playable>,
+    file_ids: &[i32],
+) -> Result<()> {
+    let info: HashMap<PlayingItem, ReplayGainInfo> = get_loudness_by_file_ids(main_db, file_ids)
+        .await?
+        .into_iter()
+        .map(|(file_id, loudness)| {
+            (
+                PlayingItem::InLibrary(file_id),
+                ReplayGainInfo {
+                    track_loudness: loudness.track_loudness as f32,
+                    track_peak: loudness.track_peak as f32,
+                    album_loudness: loudness.album_loudness.map(|x| x as f32),
+                    album_peak: loudness.album_peak.map(|x| x as f32),
+                },
+            )
+        })
+        .collect();
+
+    if !info.is_empty() {
+        player.lock().await.update_replay_gain_info(info);
+    }
+
+    Ok(())
+}
+
+async fn update_seek_tables(
+    main_db: &DatabaseConnection,
+    player: &Mutex<dyn Playable>,
+    file_ids: &[i32],
+) -> Result<()> {
+    let tables: HashMap<PlayingItem, Arc<SeekTable>> =
+        get_seek_tables_by_file_ids(main_db, file_ids)
+            .await?
+            .into_iter()
+            .map(|(file_id, table)| (PlayingItem::InLibrary(file_id), Arc::new(table)))
+            .collect();
+
+    if !tables.is_empty() {
+        player.lock().await.update_seek_tables(tables);
+    }
+
+    Ok(())
+}
+
+pub fn offsets_to_trim(offsets: &TrackOffsets) -> TrackTrim {
+    TrackTrim {
+        start: Duration::from_millis(offsets.start_ms.unwrap_or(0).max(0) as u64),
+        end: offsets
+            .end_ms
+            .map(|x| Duration::from_millis(x.max(0) as u64)),
+    }
+}
+
+async fn update_track_trims(
+    main_db: &DatabaseConnection,
+    player: &Mutex<dyn Playable>,
+    file_ids: &[i32],
+) -> Result<()> {
+    let trims: HashMap<PlayingItem, TrackTrim> = get_track_offsets_by_file_ids(main_db, file_ids)
+        .await?
+        .into_iter()
+        .map(|(file_id, offsets)| (PlayingItem::InLibrary(file_id), offsets_to_trim(&offsets)))
+        .collect();
+
+    if !trims.is_empty() {
+        player.lock().await.update_track_trims(trims);
+    }
+
+    Ok(())
+}
+
+/// The persisted equalizer settings, `None` if they were never changed.
+pub async fn get_saved_equalizer(main_db: &MainDbConnection) -> Result<Option<EqualizerConfig>> {
+    let Some(value) = get_setting(main_db, EQUALIZER_KEY).await? else {
+        return Ok(None);
+    };
+
+    let settings: EqualizerSettings = serde_json::from_str(&value)
+        .with_context(|| "Failed to parse the saved equalizer settings")?;
+
+    Ok(Some((&settings).try_into()?))
+}
+
+pub async fn get_saved_night_mode(main_db: &MainDbConnection) -> Result<Option<NightModeConfig>> {
+    let Some(value) = get_setting(main_db, NIGHT_MODE_KEY).await? else {
+        return Ok(None);
+    };
+
+    let settings: NightModeSettings = serde_json::from_str(&value)
+        .with_context(|| "Failed to parse the saved night mode settings")?;
+
+    Ok(Some((&settings).into()))
+}
+
+pub async fn get_saved_replay_gain(main_db: &MainDbConnection) -> Result<Option<ReplayGainConfig>> {
+    let Some(value) = get_setting(main_db, REPLAY_GAIN_KEY).await? else {
+        return Ok(None);
+    };
+
+    let settings: ReplayGainSettings = serde_json::from_str(&value)
+        .with_context(|| "Failed to parse the saved ReplayGain settings")?;
+
+    Ok(Some((&settings).try_into()?))
+}
+
+pub async fn get_saved_exposure(main_db: &MainDbConnection) -> Result<Option<ExposureStatus>> {
+    let Some(value) = get_setting(main_db, LISTENING_EXPOSURE_KEY).await? else {
+        return Ok(None);
+    };
+
+    let summary: ListeningExposureSummary = serde_json::from_str(&value)
+        .with_context(|| "Failed to parse the saved listening exposure")?;
+
+    Ok(Some((&summary).try_into()?))
+}
+
+/// How often the listening exposure is saved while it changes.
+const EXPOSURE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+async fn save_exposure(
+    main_db: &DatabaseConnection,
+    exposure: &ListeningExposureSummary,
+) -> Result<()> {
+    set_setting(
+        main_db,
+        LISTENING_EXPOSURE_KEY,
+        Some(&serde_json::to_string(exposure)?),
+    )
+    .await
+}
+
+fn lyric_file_to_lines(lyric: LyricFile) -> Vec<LyricContentLine> {
+    lyric
+        .lyrics
+        .into_iter()
+        .map(|x| LyricContentLine {
+            start_time: x.start_time.into(),
+            end_time: x.end_time.into(),
+            sections: x
+                .word_time_tags
+                .into_iter()
+                .map(|tag| LyricContentLineSection {
+                    start_time: tag.0.into(),
+                    end_time: tag.1.into(),
+                    content: tag.2,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Synced lyric lines of an item. Library files use the lyrics stored while
+/// scanning, other files and files not indexed yet are read on demand.
+pub async fn load_lyric_lines(
+    fsio: &FsIo,
+    lib_path: &str,
+    main_db: &MainDbConnection,
+    item: &PlayingItem,
+) -> Result<Vec<LyricContentLine>> {
+    if let PlayingItem::InLibrary(file_id) = item
+        && let Some(lyrics) = get_lyrics_by_file_id(main_db, *file_id).await?
+    {
+        return Ok(lyric_file_to_lines(parse_stored_lyrics(&lyrics)?));
+    }
+
+    let dispatcher = PlayingItemActionDispatcher::new();
+    let paths = dispatcher
+        .get_file_path(fsio, &lib_path, main_db, std::slice::from_ref(item))
+        .await?;
+    let Some(path) = paths.get(item) else {
+        return Ok(Vec::new());
+    };
+
+    let lyrics = match get_lyrics(path).unwrap_or_default() {
+        Some(x) => Some(parse_lrc(&x)),
+        None => parse_audio_lyrics(path.to_path_buf()),
+    };
+
+    let Some(lyric) = lyrics else {
+        return Ok(Vec::new());
+    };
+    let lyric = lyric.with_context(|| format!("Unable to parse lyric: item={item:?}"))?;
+
+    Ok(lyric_file_to_lines(lyric))
+}
+
+/// Index of the line being sung at `position_ms`, lines are sorted by their
+/// start time.
+fn current_lyric_line(lines: &[LyricContentLine], position_ms: i32) -> Option<usize> {
+    lines
+        .partition_point(|x| x.start_time <= position_ms)
+        .checked_sub(1)
+}
+
+/// How often the position in the playing track is saved while playing, it
+/// is also saved on pause and when the library closes.
+const QUEUE_POSITION_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Save the position in the playing track unless it was just saved.
+async fn save_queue_position(
+    main_db: &DatabaseConnection,
+    position: Duration,
+    saved: &mut Option<(Duration, Instant)>,
+) {
+    if saved.is_some_and(|(x, _)| x == position) {
+        return;
+    }
+
+    match set_playback_queue_position(main_db, position.as_millis() as i64).await {
+        Ok(_) => *saved = Some((position, Instant::now())),
+        Err(e) => error!("Unable to save the playback position: {e:?}"),
+    }
+}
+
+/// Load the queue saved before the last shutdown or library switch, paused
+/// where the playing track was left.
+async fn restore_playback_queue(
+    fsio: &FsIo,
+    lib_path: &str,
+    main_db: &MainDbConnection,
+    player: &Mutex<dyn Playable>,
+) -> Result<()> {
+    let (file_ids, current) = get_saved_playback_queue(main_db).await?;
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+    let position_ms = get_playback_queue_position(main_db).await?;
+
+    let items: Vec<PlayingItem> = file_ids.into_iter().map(PlayingItem::InLibrary).collect();
+    let files = get_ordered_file_handles(fsio, main_db, &items).await?;
+    let tracks = files_to_playback_request(fsio, &lib_path, &files);
+
+    let player = player.lock().await;
+    // Don't override anything queued while the library was loading
+    if !player.get_playlist().is_empty() {
+        return Ok(());
+    }
+
+    // Files that disappeared are skipped, so look the track up again
+    let index = current.and_then(|file_id| {
+        tracks
+            .iter()
+            .position(|(item, _)| *item == PlayingItem::InLibrary(file_id))
+    });
+
+    info!("Restoring {} tracks of the playback queue", tracks.len());
+    player.add_to_playlist(tracks, AddMode::AppendToEnd);
+    if let Some(index) = index {
+        player.load(index);
+        if position_ms > 0 {
+            player.seek(position_ms as f64);
+        }
+    }
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn initialize_local_player(
     fsio: Arc<FsIo>,
@@ -62,14 +348,18 @@ pub async fn initialize_local_player(
     player: Arc<Mutex<dyn Playable>>,
     scrobbler: Arc<Mutex<dyn ScrobblingServiceManager>>,
     broadcaster: Arc<dyn Broadcaster>,
+    events: Arc<EventBus>,
     cert_validator: Arc<RwLock<CertValidator>>,
     permission_manager: Arc<RwLock<PermissionManager>>,
+    cancel_token: Arc<CancellationToken>,
 ) -> Result<()> {
     let status_receiver = player.lock().await.subscribe_status();
     let played_through_receiver = player.lock().await.subscribe_played_through();
     let playlist_receiver = player.lock().await.subscribe_playlist();
     let realtime_fft_receiver = player.lock().await.subscribe_realtime_fft();
     let crash_receiver = player.lock().await.subscribe_crash();
+    let playback_error_receiver = player.lock().await.subscribe_playback_error();
+    let exposure_receiver = player.lock().await.subscribe_exposure();
     let player_log_receiver = player.lock().await.subscribe_log();
     let mut certificate_receiver = cert_validator.read().await.subscribe_changes();
     let mut permission_receiver = permission_manager.read().await.subscribe_new_user();
@@ -80,10 +370,28 @@ pub async fn initialize_local_player(
     let main_db_for_playlist = Arc::clone(&main_db);
     let main_db_for_scrobble_log = Arc::clone(&main_db);
     let main_db_for_player_log = Arc::clone(&main_db);
+    let main_db_for_playback_error = Arc::clone(&main_db);
+    let main_db_for_exposure = Arc::clone(&main_db);
 
     let fsio_for_status = Arc::clone(&fsio);
     let fsio_for_playlist = Arc::clone(&fsio);
 
+    let player_for_playlist = Arc::clone(&player);
+
+    let cancel_token_for_status = Arc::clone(&cancel_token);
+    let cancel_token_for_playlist = Arc::clone(&cancel_token);
+    let cancel_token_for_analysis = Arc::clone(&cancel_token);
+
+    let events_for_playlist = Arc::clone(&events);
+    let events_for_analysis = Arc::clone(&events);
+    let main_db_for_analysis = Arc::clone(&main_db);
+    let player_for_analysis = Arc::clone(&player);
+
+    let fsio_for_queue = Arc::clone(&fsio);
+    let lib_path_for_queue = Arc::clone(&lib_path);
+    let main_db_for_queue = Arc::clone(&main_db);
+    let player_for_queue = Arc::clone(&player);
+
     let manager = Arc::new(Mutex::new(MediaControlManager::new()?));
 
     let os_controller_receiver = manager.lock().await.subscribe_controller_events();
@@ -100,6 +408,8 @@ pub async fn initialize_local_player(
     let broadcaster_for_realtime_fft = Arc::clone(&broadcaster);
     let broadcaster_for_scrobbler = Arc::clone(&broadcaster);
     let broadcaster_for_crash = Arc::clone(&broadcaster);
+    let broadcaster_for_playback_error = Arc::clone(&broadcaster);
+    let broadcaster_for_exposure = Arc::clone(&broadcaster);
     let broadcaster_for_certificate = Arc::clone(&broadcaster);
     let broadcaster_for_permission_manager = Arc::clone(&broadcaster);
 
@@ -112,10 +422,34 @@ pub async fn initialize_local_player(
         let mut cached_meta: Option<PlayingItemMetadataSummary> = None;
         let mut cached_cover_art: Option<String> = None;
         let mut last_status_item: Option<PlayingItem> = None;
-
-        while let Ok(status) = status_receiver.recv().await {
+        let mut cached_lyrics: Vec<LyricContentLine> = Vec::new();
+        let mut last_lyric_line: Option<usize> = None;
+        let mut last_playback_state: Option<(Option<PlayingItem>, PlaybackState)> = None;
+        let mut last_position: Option<Duration> = None;
+        let mut saved_position: Option<(Duration, Instant)> = None;
+
+        loop {
+            // Stop with the library, so nothing of it is played or written
+            // after the switch
+            let status = tokio::select! {
+                status = status_receiver.recv() => match status {
+                    Ok(status) => status,
+                    Err(_) => break,
+                },
+                _ = cancel_token_for_status.cancelled() => break,
+            };
             debug!("Player status updated: {status:?}");
 
+            // Position updates come several times a second, only publish real changes
+            let playback_state = (status.item.clone(), status.state.clone());
+            if last_playback_state.as_ref() != Some(&playback_state) {
+                events.publish(LibraryEvent::PlaybackStateChanged {
+                    item: playback_state.0.clone(),
+                    state: playback_state.1.clone(),
+                });
+                last_playback_state = Some(playback_state);
+            }
+
             let item = status.item.clone();
 
             let meta = match item {
@@ -123,6 +457,25 @@ pub async fn initialize_local_player(
                     let item_clone = item.clone();
 
                     if last_status_item != Some(item) {
+                        if let PlayingItem::InLibrary(file_id) = item_clone {
+                            if let Err(e) = record_playback_start(&main_db, file_id).await {
+                                error!("Unable to record playback start: {e:?}");
+                            }
+                            if let Err(e) = set_playback_queue_current(&main_db, file_id).await {
+                                error!("Unable to update the current queue item: {e:?}");
+                            }
+                        }
+
+                        cached_lyrics =
+                            match load_lyric_lines(&fsio, &lib_path, &main_db, &item_clone).await {
+                                Ok(lines) => lines,
+                                Err(e) => {
+                                    error!("Unable to load lyrics: {e:?}");
+                                    Vec::new()
+                                }
+                            };
+                        last_lyric_line = None;
+
                         let item_clone_for_status = item_clone.clone();
                         let item_vec = &[item_clone].to_vec();
 
@@ -196,11 +549,21 @@ pub async fn initialize_local_player(
                 none => {
                     // If the index is None, send empty metadata
                     last_status_item = none;
+                    cached_lyrics.clear();
                     PlayingItemMetadataSummary::default()
                 }
             };
 
             let position = status.position;
+            if let Some(PlayingItem::InLibrary(_)) = status.item {
+                last_position = Some(position);
+                let due = saved_position
+                    .is_none_or(|(_, at)| at.elapsed() >= QUEUE_POSITION_SAVE_INTERVAL);
+                if status.state != PlaybackState::Playing || due {
+                    save_queue_position(&main_db, position, &mut saved_position).await;
+                }
+            }
+
             let duration = meta.duration;
             let progress_percentage = if duration == 0. {
                 0.
@@ -219,9 +582,14 @@ pub async fn initialize_local_player(
                 item: status.item.map(Into::into),
                 index: status.index.map(|i| i as i32),
                 playback_mode: status.playback_mode.into(),
+                repeat_mode: status.repeat_mode.into(),
+                shuffle_mode: status.shuffle_mode.into(),
                 ready: status.ready,
                 cover_art_path: cached_cover_art.clone(),
                 lib_path: lib_path.as_str().to_string(),
+                output_device: status.output.as_ref().map(|x| x.device.clone()),
+                output_sample_rate: status.output.as_ref().map(|x| x.sample_rate),
+                output_bit_depth: status.output.as_ref().map(|x| x.bit_depth as u32),
             };
 
             if let Err(e) =
@@ -235,24 +603,109 @@ pub async fn initialize_local_player(
                     .for_each(|cause| eprintln!("because: {cause}"));
             }
 
+            // Only line changes are sent, the client animates words within
+            // the line on its own
+            let lyric_line = current_lyric_line(&cached_lyrics, position.as_millis() as i32);
+            if lyric_line != last_lyric_line {
+                last_lyric_line = lyric_line;
+                broadcaster_for_main.broadcast(&LyricLineUpdate {
+                    item: formated_status.item.clone(),
+                    index: lyric_line.map(|x| x as i32),
+                    line: lyric_line.map(|x| cached_lyrics[x].clone()),
+                });
+            }
+
             broadcaster_for_main.broadcast(&formated_status);
         }
+
+        if let Some(position) = last_position {
+            save_queue_position(&main_db, position, &mut saved_position).await;
+        }
+        if let Err(e) = manager
+            .lock()
+            .await
+            .controls
+            .set_playback(MediaPlayback::Stopped)
+        {
+            error!("Failed to reset media controls: {e:?}");
+        }
     });
 
     task::spawn(async move {
         let fsio = Arc::clone(&fsio_for_playlist);
         let main_db = Arc::clone(&main_db_for_playlist);
         let broadcaster = Arc::clone(&broadcaster_for_playlist);
+        let player = Arc::clone(&player_for_playlist);
+
+        loop {
+            let playlist = tokio::select! {
+                playlist = playlist_receiver.recv() => match playlist {
+                    Ok(playlist) => playlist,
+                    Err(_) => break,
+                },
+                _ = cancel_token_for_playlist.cancelled() => break,
+            };
 
-        while let Ok(playlist) = playlist_receiver.recv().await {
             send_playlist_update(Arc::clone(&fsio), &main_db, &playlist, &*broadcaster).await;
-            match replace_playback_queue(&main_db, extract_in_library_ids(playlist.items)).await {
+
+            let file_ids = extract_in_library_ids(playlist.items.clone());
+            let current = player.lock().await.get_status().index;
+            events_for_playlist.publish(LibraryEvent::TracksQueued {
+                file_ids: upcoming_file_ids(playlist.items, current),
+            });
+
+            if let Err(e) = update_replay_gain_info(&main_db, &player, &file_ids).await {
+                error!("Failed to update ReplayGain info: {e:#?}");
+            }
+            if let Err(e) = update_seek_tables(&main_db, &player, &file_ids).await {
+                error!("Failed to update seek tables: {e:#?}");
+            }
+            if let Err(e) = update_track_trims(&main_db, &player, &file_ids).await {
+                error!("Failed to update track trims: {e:#?}");
+            }
+
+            match replace_playback_queue(&main_db, file_ids).await {
                 Ok(_) => {}
                 Err(e) => error!("Failed to update playback queue record: {e:#?}"),
             };
         }
     });
 
+    // Tracks analyzed after they were queued, like the ones analyzed ahead
+    // of the library, get their loudness now
+    task::spawn(async move {
+        let main_db = Arc::clone(&main_db_for_analysis);
+        let player = Arc::clone(&player_for_analysis);
+        let mut receiver = events_for_analysis.subscribe();
+
+        while let Some(events) = next_events(&mut receiver, &cancel_token_for_analysis).await {
+            let analyzed: HashSet<i32> = events
+                .into_iter()
+                .filter_map(|x| match x {
+                    LibraryEvent::AnalysisFinished { file_ids } => Some(file_ids),
+                    _ => None,
+                })
+                .flatten()
+                .collect();
+            if analyzed.is_empty() {
+                continue;
+            }
+
+            let queued = player.lock().await.get_playlist();
+            let file_ids: Vec<i32> = extract_in_library_ids(queued)
+                .into_iter()
+                .filter(|x| analyzed.contains(x))
+                .collect();
+            if file_ids.is_empty() {
+                continue;
+            }
+
+            if let Err(e) = update_replay_gain_info(&main_db, &player, &file_ids).await {
+                error!("Failed to update ReplayGain info: {e:#?}");
+            }
+        }
+    });
+
     task::spawn(async move {
         let main_db = Arc::clone(&main_db_for_played_throudh);
         let dispatcher = Arc::clone(&dispatcher_for_played_through);
@@ -261,9 +714,9 @@ pub async fn initialize_local_player(
         while let Ok(item) = played_through_receiver.recv().await {
             match item {
                 PlayingItem::InLibrary(id) => {
-                    if let Err(e) = increase_played_through(&main_db, id)
+                    if let Err(e) = record_playback_complete(&main_db, id)
                         .await
-                        .with_context(|| "Unable to update played through count")
+                        .with_context(|| "Unable to record completed playback")
                     {
                         error!("{e:?}");
                     }
@@ -391,6 +844,47 @@ pub async fn initialize_local_player(
         }
     });
 
+    task::spawn(async move {
+        let main_db = Arc::clone(&main_db_for_playback_error);
+
+        while let Ok(failure) = playback_error_receiver.recv().await {
+            // Keep the error with the file, so it shows up in the problem tracks
+            if let PlayingItem::InLibrary(id) = failure.item
+                && let Err(e) = record_playback_error(&main_db, id, &failure.error)
+                    .await
+                    .with_context(|| "Unable to record playback error")
+            {
+                error!("{e:?}");
+            }
+
+            broadcaster_for_playback_error.broadcast(&PlaybackFailed {
+                item: failure.item.into(),
+                error: failure.error,
+            });
+        }
+    });
+
+    task::spawn(async move {
+        let main_db = Arc::clone(&main_db_for_exposure);
+        let mut last_saved: Option<Instant> = None;
+
+        while let Ok(value) = exposure_receiver.recv().await {
+            let exposure: ListeningExposureSummary = value.into();
+
+            // Updates arrive every few seconds while playing, losing up to a
+            // minute of dose on a crash is fine
+            if last_saved.is_none_or(|x| x.elapsed() >= EXPOSURE_SAVE_INTERVAL) {
+                last_saved = Some(Instant::now());
+
+                if let Err(e) = save_exposure(&main_db, &exposure).await {
+                    error!("Failed to save the listening exposure: {e:#?}");
+                }
+            }
+
+            broadcaster_for_exposure.broadcast(&ListeningExposureUpdated { exposure });
+        }
+    });
+
     task::spawn(async move {
         while let Ok(fingerprints) = certificate_receiver.recv().await {
             broadcaster_for_certificate.broadcast(&TrustListUpdated {
@@ -420,9 +914,60 @@ pub async fn initialize_local_player(
         }
     });
 
+    // Select the device before the restored queue opens a stream
+    match get_setting(&main_db_for_queue, OUTPUT_DEVICE_KEY).await {
+        Ok(Some(name)) => player_for_queue.lock().await.set_output_device(Some(name)),
+        Ok(None) => {}
+        Err(e) => error!("Failed to get the output device: {e:#?}"),
+    }
+
+    match get_saved_equalizer(&main_db_for_queue).await {
+        Ok(Some(config)) => player_for_queue.lock().await.set_equalizer(config),
+        Ok(None) => {}
+        Err(e) => error!("Failed to get the equalizer settings: {e:#?}"),
+    }
+
+    match get_saved_night_mode(&main_db_for_queue).await {
+        Ok(Some(config)) => player_for_queue.lock().await.set_night_mode(config),
+        Ok(None) => {}
+        Err(e) => error!("Failed to get the night mode settings: {e:#?}"),
+    }
+
+    match get_saved_replay_gain(&main_db_for_queue).await {
+        Ok(Some(config)) => player_for_queue.lock().await.set_replay_gain(config),
+        Ok(None) => {}
+        Err(e) => error!("Failed to get the ReplayGain settings: {e:#?}"),
+    }
+
+    match get_saved_exposure(&main_db_for_queue).await {
+        Ok(Some(status)) => player_for_queue.lock().await.restore_exposure(status),
+        Ok(None) => {}
+        Err(e) => error!("Failed to get the listening exposure: {e:#?}"),
+    }
+
+    // Restore the queue once the listeners above are ready to pick it up
+    if let Err(e) = restore_playback_queue(
+        &fsio_for_queue,
+        &lib_path_for_queue,
+        &main_db_for_queue,
+        &player_for_queue,
+    )
+    .await
+    {
+        error!("Failed to restore playback queue: {e:#?}");
+    }
+
     Ok(())
 }
 
+/// The library tracks of the queue from the current one on, followed by
+/// the ones before it.
+fn upcoming_file_ids(mut items: Vec<PlayingItem>, current: Option<usize>) -> Vec<i32> {
+    let current = current.unwrap_or(0).min(items.len());
+    items.rotate_left(current);
+    extract_in_library_ids(items)
+}
+
 pub async fn send_playlist_update(
     fsio: Arc<FsIo>,
     db: &DatabaseConnection,
@@ -0,0 +1,230 @@
+//! Endless radios and stations, queuing more tracks as the playlist drains.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{error, info};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use ::database::{
+    actions::{
+        radio::{RadioSeed, get_more_radio_tracks},
+        stations::{
+            StationFeedback, StationSource, get_more_station_tracks, record_station_playback,
+        },
+    },
+    connection::{MainDbConnection, RecommendationDbConnection},
+    playing_item::MediaFileHandle,
+};
+use ::fsio::FsIo;
+use ::playback::{
+    player::{Playable, PlayingItem},
+    strategies::AddMode,
+};
+
+use crate::utils::files_to_playback_request;
+
+/// More tracks are queued once fewer than this many are left after the
+/// playing one.
+const RADIO_REFILL_THRESHOLD: usize = 10;
+
+/// How many tracks are queued at a time after the first ones.
+const RADIO_REFILL_SIZE: usize = 20;
+
+/// What a radio queues tracks from.
+#[derive(Debug)]
+pub enum RadioSource {
+    /// The tracks that sound like a track, an artist or an album.
+    Seed(RadioSeed),
+    /// A station, leaning away from what is skipped during the session.
+    Station {
+        source: StationSource,
+        feedback: StationFeedback,
+        rng: StdRng,
+    },
+}
+
+impl RadioSource {
+    /// A station starting a new session, without feedback yet.
+    pub fn station(source: StationSource) -> Self {
+        RadioSource::Station {
+            source,
+            feedback: StationFeedback::default(),
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl fmt::Display for RadioSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RadioSource::Seed(seed) => write!(f, "the radio of {seed:?}"),
+            RadioSource::Station { source, .. } => write!(f, "the station {source}"),
+        }
+    }
+}
+
+/// A radio and the tracks it queued recently.
+#[derive(Debug)]
+pub struct Radio {
+    source: RadioSource,
+    repeat_window: usize,
+    excluded: HashSet<i32>,
+    /// The last `repeat_window` queued tracks, the latest last.
+    recent: VecDeque<i32>,
+    last_queued: Option<i32>,
+}
+
+impl Radio {
+    pub fn new(source: RadioSource, repeat_window: usize, excluded: HashSet<i32>) -> Self {
+        Self {
+            source,
+            repeat_window,
+            excluded,
+            recent: VecDeque::with_capacity(repeat_window),
+            last_queued: None,
+        }
+    }
+
+    /// The next `n` tracks of the radio, remembered as queued.
+    pub async fn next_tracks(
+        &mut self,
+        main_db: &MainDbConnection,
+        recommend_db: &RecommendationDbConnection,
+        n: usize,
+    ) -> Result<Vec<MediaFileHandle>> {
+        let avoided: HashSet<i32> = self.excluded.iter().chain(&self.recent).copied().collect();
+        let tracks = match &mut self.source {
+            RadioSource::Seed(seed) => {
+                get_more_radio_tracks(main_db, recommend_db, *seed, &avoided, n).await?
+            }
+            RadioSource::Station {
+                source,
+                feedback,
+                rng,
+            } => {
+                get_more_station_tracks(main_db, recommend_db, *source, feedback, &avoided, n, rng)
+                    .await?
+            }
+        };
+
+        for track in &tracks {
+            self.recent.push_back(track.id);
+            if self.recent.len() > self.repeat_window {
+                self.recent.pop_front();
+            }
+            self.last_queued = Some(track.id);
+        }
+
+        Ok(tracks.into_iter().map(|x| x.into()).collect())
+    }
+
+    /// Learn from how far a track the radio queued played before the next
+    /// one started. Only stations adapt, the other radios ignore it.
+    pub async fn track_ended(
+        &mut self,
+        main_db: &MainDbConnection,
+        file_id: i32,
+        played: Duration,
+    ) -> Result<()> {
+        let RadioSource::Station { feedback, .. } = &mut self.source else {
+            return Ok(());
+        };
+        if !self.recent.contains(&file_id) {
+            return Ok(());
+        }
+
+        record_station_playback(main_db, feedback, file_id, played.as_secs_f64()).await
+    }
+}
+
+/// Queue more tracks of `radio` whenever a new track starts close to the end
+/// of the playlist. Stops when `token` is cancelled, when the radio has
+/// nothing left to queue, or when the playlist no longer holds the last
+/// track the radio queued, since it was replaced.
+pub async fn keep_radio_playing(
+    fsio: Arc<FsIo>,
+    main_db: Arc<MainDbConnection>,
+    recommend_db: Arc<RecommendationDbConnection>,
+    lib_path: Arc<String>,
+    player: Arc<Mutex<dyn Playable>>,
+    mut radio: Radio,
+    token: CancellationToken,
+) {
+    let status_receiver = player.lock().await.subscribe_status();
+    let mut last_index = None;
+    // The playing track and how far it played
+    let mut playing: Option<(i32, Duration)> = None;
+
+    loop {
+        let status = tokio::select! {
+            _ = token.cancelled() => break,
+            status = status_receiver.recv() => match status {
+                Ok(status) => status,
+                Err(_) => break,
+            },
+        };
+
+        // Only check when a new track starts, the playlist is up to date by
+        // then
+        if status.index == last_index {
+            if let Some(PlayingItem::InLibrary(file_id)) = status.item {
+                playing = Some((file_id, status.position));
+            }
+            continue;
+        }
+        last_index = status.index;
+
+        if let Some((file_id, played)) = playing.take() {
+            if let Err(e) = radio.track_ended(&main_db, file_id, played).await {
+                error!("Failed to learn from the last track of the radio: {e:#}");
+            }
+        }
+        if let Some(PlayingItem::InLibrary(file_id)) = status.item {
+            playing = Some((file_id, status.position));
+        }
+        let Some(index) = status.index else {
+            continue;
+        };
+
+        let Some(last_queued) = radio.last_queued else {
+            break;
+        };
+        if !status
+            .playlist
+            .contains(&PlayingItem::InLibrary(last_queued))
+        {
+            info!("The playlist was replaced, stopping {}", radio.source);
+            break;
+        }
+
+        if status.playlist.len().saturating_sub(index + 1) >= RADIO_REFILL_THRESHOLD {
+            continue;
+        }
+
+        match radio
+            .next_tracks(&main_db, &recommend_db, RADIO_REFILL_SIZE)
+            .await
+        {
+            Ok(tracks) if tracks.is_empty() => {
+                info!("Nothing left to queue, stopping {}", radio.source);
+                break;
+            }
+            Ok(tracks) => {
+                player.lock().await.add_to_playlist(
+                    files_to_playback_request(&fsio, &lib_path.as_str(), &tracks),
+                    AddMode::AppendToEnd,
+                );
+            }
+            Err(e) => {
+                error!("Failed to queue more tracks of the radio: {e:#}");
+            }
+        }
+    }
+}
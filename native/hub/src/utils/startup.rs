@@ -0,0 +1,121 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use ::database::connection::{LibraryState, check_library_state};
+
+use crate::messages::*;
+use crate::utils::{Broadcaster, DatabaseConnections, initialize_databases};
+
+const LAST_LIBRARY_FILE: &str = "last_library.json";
+
+/// The library opened successfully the last time the hub started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastLibrary {
+    pub path: String,
+    pub db_path: String,
+}
+
+pub fn read_last_library(config_path: &str) -> Option<LastLibrary> {
+    let content = fs::read_to_string(Path::new(config_path).join(LAST_LIBRARY_FILE)).ok()?;
+
+    match serde_json::from_str(&content) {
+        Ok(library) => Some(library),
+        Err(e) => {
+            warn!("Ignoring invalid last library file: {e}");
+            None
+        }
+    }
+}
+
+pub fn write_last_library(config_path: &str, library: &LastLibrary) -> Result<()> {
+    let content = serde_json::to_string(library)?;
+    fs::write(Path::new(config_path).join(LAST_LIBRARY_FILE), content)
+        .context("Failed to write last library file")
+}
+
+pub fn report_startup(
+    broadcaster: &dyn Broadcaster,
+    stage: StartupStage,
+    path: Option<&str>,
+    error: Option<String>,
+) {
+    broadcaster.broadcast(&StartupStatus {
+        stage,
+        path: path.map(|x| x.to_string()),
+        error,
+    });
+}
+
+/// Database connections to the last used library, opened while the UI is
+/// still waiting for the user to confirm which library to use.
+pub struct PrewarmedLibrary {
+    library: LastLibrary,
+    node_id: String,
+    connections: JoinHandle<Result<DatabaseConnections>>,
+}
+
+impl PrewarmedLibrary {
+    pub fn matches(&self, path: &str, db_path: &str, node_id: &str) -> bool {
+        self.library.path == path && self.library.db_path == db_path && self.node_id == node_id
+    }
+
+    pub async fn connections(self) -> Result<DatabaseConnections> {
+        self.connections
+            .await
+            .map_err(|e| anyhow!("Prewarm task failed: {e}"))?
+    }
+
+    /// Wait for the background task and close what it opened, so that
+    /// nothing else touches the databases while another library is set up.
+    pub async fn discard(self) {
+        info!("Discarding prewarmed databases of {}", self.library.path);
+        if let Err(e) = self.connections().await {
+            warn!("{e:#}");
+        }
+    }
+}
+
+/// Start connecting to the databases of the last used library in the
+/// background. Libraries that would need to be initialized are left alone.
+pub fn prewarm_last_library(
+    config_path: &str,
+    node_id: String,
+    broadcaster: &dyn Broadcaster,
+) -> Option<PrewarmedLibrary> {
+    let library = read_last_library(config_path)?;
+
+    match check_library_state(&library.path) {
+        Ok(LibraryState::Initialized(_)) => {}
+        Ok(LibraryState::Uninitialized) => return None,
+        Err(e) => {
+            warn!("Last library is unavailable: {e:#}");
+            return None;
+        }
+    }
+
+    info!("Prewarming databases of {}", library.path);
+    report_startup(
+        broadcaster,
+        StartupStage::ConnectingDatabases,
+        Some(&library.path),
+        None,
+    );
+
+    let connections = {
+        let library = library.clone();
+        let node_id = node_id.clone();
+        tokio::spawn(async move {
+            initialize_databases(&library.path, Some(&library.db_path), &node_id).await
+        })
+    };
+
+    Some(PrewarmedLibrary {
+        library,
+        node_id,
+        connections,
+    })
+}
@@ -41,12 +41,52 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: None,
             local_only: false,
         },
+        RequestResponse {
+            request: "ListTaskCheckpointsRequest".to_string(),
+            response: Some("ListTaskCheckpointsResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "GetSplittingRulesRequest".to_string(),
+            response: Some("GetSplittingRulesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetSplittingRulesRequest".to_string(),
+            response: Some("SetSplittingRulesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "GetMetadataLanguagesRequest".to_string(),
+            response: Some("GetMetadataLanguagesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetMetadataLanguagesRequest".to_string(),
+            response: Some("SetMetadataLanguagesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "ReapplySplittingRequest".to_string(),
+            response: Some("ReapplySplittingResponse".to_string()),
+            local_only: false,
+        },
         // Playback
         RequestResponse {
             request: "VolumeRequest".to_string(),
             response: Some("VolumeResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "ListOutputDevicesRequest".to_string(),
+            response: Some("ListOutputDevicesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetOutputDeviceRequest".to_string(),
+            response: Some("SetOutputDeviceResponse".to_string()),
+            local_only: false,
+        },
         RequestResponse {
             request: "LoadRequest".to_string(),
             response: None,
@@ -92,11 +132,31 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: None,
             local_only: false,
         },
+        RequestResponse {
+            request: "SetRepeatModeRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetShuffleModeRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
         RequestResponse {
             request: "MovePlaylistItemRequest".to_string(),
             response: None,
             local_only: false,
         },
+        RequestResponse {
+            request: "AddToQueueRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
+        RequestResponse {
+            request: "ClearQueueRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
         RequestResponse {
             request: "SetRealtimeFFTEnabledRequest".to_string(),
             response: None,
@@ -107,6 +167,96 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: None,
             local_only: false,
         },
+        RequestResponse {
+            request: "SetGaplessEnabledRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetCrossfadeRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
+        RequestResponse {
+            request: "StartMixAuditionRequest".to_string(),
+            response: Some("StartMixAuditionResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "StopMixAuditionRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
+        RequestResponse {
+            request: "StartArtistRadioRequest".to_string(),
+            response: Some("StartArtistRadioResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "StartAlbumRadioRequest".to_string(),
+            response: Some("StartAlbumRadioResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "StartTrackRadioRequest".to_string(),
+            response: Some("StartTrackRadioResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "StopRadioRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
+        RequestResponse {
+            request: "FetchStationsRequest".to_string(),
+            response: Some("FetchStationsResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "StartStationRequest".to_string(),
+            response: Some("StartStationResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetNightModeRequest".to_string(),
+            response: Some("SetNightModeResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "GetNightModeRequest".to_string(),
+            response: Some("GetNightModeResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetEqualizerRequest".to_string(),
+            response: Some("SetEqualizerResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "GetEqualizerRequest".to_string(),
+            response: Some("GetEqualizerResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetHearingProtectionRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
+        RequestResponse {
+            request: "FetchListeningExposureRequest".to_string(),
+            response: Some("FetchListeningExposureResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetReplayGainRequest".to_string(),
+            response: None,
+            local_only: false,
+        },
+        RequestResponse {
+            request: "GetReplayGainRequest".to_string(),
+            response: Some("GetReplayGainResponse".to_string()),
+            local_only: false,
+        },
         // SFX
         RequestResponse {
             request: "SfxPlayRequest".to_string(),
@@ -124,6 +274,11 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: Some("GetAnalyzeCountResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "FetchLibraryMapRequest".to_string(),
+            response: Some("FetchLibraryMapResponse".to_string()),
+            local_only: false,
+        },
         // Media File
         RequestResponse {
             request: "FetchMediaFilesRequest".to_string(),
@@ -145,6 +300,26 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: Some("SearchMediaFileSummaryResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "FetchTagConflictsRequest".to_string(),
+            response: Some("FetchTagConflictsResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "ResolveTagConflictsRequest".to_string(),
+            response: Some("ResolveTagConflictsResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "RevealMediaFileRequest".to_string(),
+            response: Some("RevealMediaFileResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "FetchMediaFileInfoRequest".to_string(),
+            response: Some("FetchMediaFileInfoResponse".to_string()),
+            local_only: false,
+        },
         // Lyric
         RequestResponse {
             request: "GetLyricByTrackIdRequest".to_string(),
@@ -172,6 +347,17 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: Some("SearchCollectionSummaryResponse".to_string()),
             local_only: false,
         },
+        // Page
+        RequestResponse {
+            request: "FetchAlbumPageRequest".to_string(),
+            response: Some("FetchAlbumPageResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "FetchArtistPageRequest".to_string(),
+            response: Some("FetchArtistPageResponse".to_string()),
+            local_only: false,
+        },
         // Cover Art
         RequestResponse {
             request: "GetCoverArtIdsByMixQueriesRequest".to_string(),
@@ -183,6 +369,16 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: Some("GetPrimaryColorByTrackIdResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "FetchAlbumThumbnailStatusRequest".to_string(),
+            response: Some("FetchAlbumThumbnailStatusResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SearchCoverArtByImageRequest".to_string(),
+            response: Some("SearchCoverArtByImageResponse".to_string()),
+            local_only: false,
+        },
         // Playlist
         RequestResponse {
             request: "FetchAllPlaylistsRequest".to_string(),
@@ -199,6 +395,41 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: Some("CreateM3u8PlaylistResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "ExportPlaylistBundleRequest".to_string(),
+            response: Some("ExportPlaylistBundleResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "ImportPlaylistBundleRequest".to_string(),
+            response: Some("ImportPlaylistBundleResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "FetchPlaylistMirrorsRequest".to_string(),
+            response: Some("FetchPlaylistMirrorsResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "AddPlaylistMirrorRequest".to_string(),
+            response: Some("AddPlaylistMirrorResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "RemovePlaylistMirrorRequest".to_string(),
+            response: Some("RemovePlaylistMirrorResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "SyncPlaylistMirrorsRequest".to_string(),
+            response: Some("SyncPlaylistMirrorsResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "ResolvePlaylistMirrorConflictRequest".to_string(),
+            response: Some("ResolvePlaylistMirrorConflictResponse".to_string()),
+            local_only: true,
+        },
         RequestResponse {
             request: "UpdatePlaylistRequest".to_string(),
             response: Some("UpdatePlaylistResponse".to_string()),
@@ -224,6 +455,31 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: Some("GetPlaylistByIdResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "FetchAllSmartPlaylistsRequest".to_string(),
+            response: Some("FetchAllSmartPlaylistsResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "CreateSmartPlaylistRequest".to_string(),
+            response: Some("CreateSmartPlaylistResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "UpdateSmartPlaylistRequest".to_string(),
+            response: Some("UpdateSmartPlaylistResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "RemoveSmartPlaylistRequest".to_string(),
+            response: Some("RemoveSmartPlaylistResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "FetchSmartPlaylistItemsRequest".to_string(),
+            response: Some("FetchSmartPlaylistItemsResponse".to_string()),
+            local_only: false,
+        },
         // Mix
         RequestResponse {
             request: "FetchAllMixesRequest".to_string(),
@@ -270,6 +526,27 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: Some("OperatePlaybackWithMixQueryResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "FetchDailyMixesRequest".to_string(),
+            response: Some("FetchDailyMixesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "FetchMixPresetsRequest".to_string(),
+            response: Some("FetchMixPresetsResponse".to_string()),
+            local_only: false,
+        },
+        // Handoff
+        RequestResponse {
+            request: "HandoffPlaybackRequest".to_string(),
+            response: Some("HandoffPlaybackResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "ReceiveHandoffRequest".to_string(),
+            response: Some("ReceiveHandoffResponse".to_string()),
+            local_only: false,
+        },
         // Like
         RequestResponse {
             request: "SetLikedRequest".to_string(),
@@ -281,23 +558,105 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
             response: Some("GetLikedResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "ToggleLikeRequest".to_string(),
+            response: Some("ToggleLikeResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "FetchLikedFilesRequest".to_string(),
+            response: Some("FetchLikedFilesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetRatingRequest".to_string(),
+            response: Some("SetRatingResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "GetRatingRequest".to_string(),
+            response: Some("GetRatingResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetHiddenRequest".to_string(),
+            response: Some("SetHiddenResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "FetchHiddenFilesRequest".to_string(),
+            response: Some("FetchHiddenFilesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "FetchProblemTracksRequest".to_string(),
+            response: Some("FetchProblemTracksResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "ClearPlaybackErrorRequest".to_string(),
+            response: Some("ClearPlaybackErrorResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "SetTrackOffsetsRequest".to_string(),
+            response: Some("SetTrackOffsetsResponse".to_string()),
+            local_only: false,
+        },
         // Query and Search
         RequestResponse {
             request: "ComplexQueryRequest".to_string(),
             response: Some("ComplexQueryResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "FetchVehicleContentRequest".to_string(),
+            response: Some("FetchVehicleContentResponse".to_string()),
+            local_only: false,
+        },
         RequestResponse {
             request: "SearchForRequest".to_string(),
             response: Some("SearchForResponse".to_string()),
             local_only: false,
         },
+        RequestResponse {
+            request: "SearchSuggestRequest".to_string(),
+            response: Some("SearchSuggestResponse".to_string()),
+            local_only: false,
+        },
         // Directory
         RequestResponse {
             request: "FetchDirectoryTreeRequest".to_string(),
             response: Some("FetchDirectoryTreeResponse".to_string()),
             local_only: false,
         },
+        // Library sources
+        RequestResponse {
+            request: "FetchLibrarySourcesRequest".to_string(),
+            response: Some("FetchLibrarySourcesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "AddLibrarySourceRequest".to_string(),
+            response: Some("AddLibrarySourceResponse".to_string()),
+            local_only: true,
+        },
+        RequestResponse {
+            request: "RemoveLibrarySourceRequest".to_string(),
+            response: Some("RemoveLibrarySourceResponse".to_string()),
+            local_only: true,
+        },
+        // Trash
+        RequestResponse {
+            request: "FetchDeletedFilesRequest".to_string(),
+            response: Some("FetchDeletedFilesResponse".to_string()),
+            local_only: false,
+        },
+        RequestResponse {
+            request: "RestoreDeletedFilesRequest".to_string(),
+            response: Some("RestoreDeletedFilesResponse".to_string()),
+            local_only: true,
+        },
         // Scrobbler
         RequestResponse {
             request: "AuthenticateSingleServiceRequest".to_string(),
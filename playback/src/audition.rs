@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use crate::replay_gain::{ReplayGainConfig, ReplayGainMode};
+use crate::trim::TrackTrim;
+
+/// Where the hook of a track is taken from, as a share of its length.
+const HOOK_POSITION: f64 = 0.3;
+
+/// Settings of the mix audition mode. Instead of whole tracks, a short hook
+/// of every track is played, crossfading into the next one, so a mix can
+/// be judged in a few minutes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AuditionConfig {
+    /// How much of every track is played.
+    pub segment: Duration,
+    /// How long consecutive segments overlap.
+    pub crossfade: Duration,
+}
+
+impl Default for AuditionConfig {
+    fn default() -> Self {
+        Self {
+            segment: Duration::from_secs(20),
+            crossfade: Duration::from_secs(2),
+        }
+    }
+}
+
+impl AuditionConfig {
+    /// The part of a track of `duration` to play. The hook is assumed to be
+    /// about a third into the track, tracks shorter than a segment are
+    /// played whole.
+    pub fn hook_trim(&self, duration: Option<Duration>) -> TrackTrim {
+        let Some(duration) = duration else {
+            return TrackTrim {
+                start: Duration::ZERO,
+                end: Some(self.segment),
+            };
+        };
+
+        if duration <= self.segment {
+            return TrackTrim::default();
+        }
+
+        let start = duration.mul_f64(HOOK_POSITION).min(duration - self.segment);
+        TrackTrim {
+            start,
+            end: Some(start + self.segment),
+        }
+    }
+
+    /// Segments are leveled to the same loudness, so the volume of a track
+    /// doesn't sway the judgement.
+    pub fn replay_gain(&self) -> ReplayGainConfig {
+        ReplayGainConfig {
+            mode: ReplayGainMode::Track,
+            preamp_db: 0.0,
+            prevent_clipping: true,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// How many samples the sources render between two reads of their shared
+/// control, about 10 ms of stereo audio.
+pub const CONTROL_REFRESH_SAMPLES: usize = 1024;
+
+/// A float written by the player thread and read by the audio thread,
+/// stored as raw bits so the audio thread never has to take a lock while
+/// rendering.
+#[derive(Debug, Default)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        Self(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// A float summed up by the audio thread and drained by the player thread.
+#[derive(Debug, Default)]
+pub struct AtomicF64(AtomicU64);
+
+impl AtomicF64 {
+    pub fn add(&self, value: f64) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+                Some((f64::from_bits(x) + value).to_bits())
+            });
+    }
+
+    /// Take the sum, starting over from zero.
+    pub fn take(&self) -> f64 {
+        f64::from_bits(self.0.swap(0, Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_f32_round_trips() {
+        let value = AtomicF32::default();
+        assert_eq!(value.load(), 0.0);
+
+        value.store(-6.5);
+        assert_eq!(value.load(), -6.5);
+        assert_eq!(AtomicF32::new(1.0).load(), 1.0);
+    }
+
+    #[test]
+    fn test_atomic_f64_sums_until_taken() {
+        let sum = AtomicF64::default();
+        sum.add(0.25);
+        sum.add(1.5);
+
+        assert_eq!(sum.take(), 1.75);
+        assert_eq!(sum.take(), 0.0);
+    }
+}
@@ -0,0 +1,195 @@
+use std::f32::consts::FRAC_PI_2;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+use crate::control::{AtomicF32, CONTROL_REFRESH_SAMPLES};
+
+/// The longest crossfade between two tracks.
+pub const MAX_CROSSFADE: Duration = Duration::from_secs(12);
+
+/// Fade requested by the player thread, picked up by the audio thread.
+#[derive(Debug)]
+pub struct CrossfadeControl {
+    /// Level the source moves towards.
+    target: AtomicF32,
+    /// Length of the fade towards `target` in milliseconds.
+    duration_ms: AtomicU32,
+    /// Bumped on every request so the audio thread notices it.
+    generation: AtomicU32,
+}
+
+impl Default for CrossfadeControl {
+    fn default() -> Self {
+        Self {
+            target: AtomicF32::new(1.0),
+            duration_ms: AtomicU32::new(0),
+            generation: AtomicU32::new(0),
+        }
+    }
+}
+
+impl CrossfadeControl {
+    fn request(&self, target: f32, duration: Duration) {
+        self.target.store(target);
+        self.duration_ms
+            .store(duration.as_millis() as u32, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn fade_in(&self, duration: Duration) {
+        self.request(1.0, duration);
+    }
+
+    pub fn fade_out(&self, duration: Duration) {
+        self.request(0.0, duration);
+    }
+
+    /// Jump to full level, e.g. after a seek cut the crossfade short.
+    pub fn finish(&self) {
+        self.request(1.0, Duration::ZERO);
+    }
+}
+
+/// A source following the fades of a [`CrossfadeControl`] with an
+/// equal-power curve, so two tracks crossing each other keep a steady
+/// loudness.
+pub struct Crossfade<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    input: I,
+    control: Arc<CrossfadeControl>,
+    generation: u32,
+    /// Linear progress of the fade, `0.0` is silent and `1.0` full level.
+    level: f32,
+    target: f32,
+    step: f32,
+    samples_until_refresh: usize,
+}
+
+/// Wrap `input`, starting silent if `silent` is set, otherwise at full
+/// level.
+pub fn crossfade<I>(input: I, control: Arc<CrossfadeControl>, silent: bool) -> Crossfade<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    let level = if silent { 0.0 } else { 1.0 };
+
+    Crossfade {
+        input,
+        control,
+        // Make sure the first refresh picks up the pending request
+        generation: u32::MAX,
+        level,
+        target: level,
+        step: 0.0,
+        samples_until_refresh: 0,
+    }
+}
+
+impl<I> Crossfade<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    fn refresh(&mut self) {
+        let generation = self.control.generation.load(Ordering::Acquire);
+        if generation == self.generation {
+            return;
+        }
+        self.generation = generation;
+
+        self.target = self.control.target.load();
+        let duration_ms = self.control.duration_ms.load(Ordering::Relaxed);
+
+        // Samples are interleaved, so the fade length counts every channel
+        let samples = duration_ms as f32 / 1000.0
+            * self.input.sample_rate() as f32
+            * self.input.channels().max(1) as f32;
+        if samples < 1.0 {
+            self.level = self.target;
+            self.step = 0.0;
+        } else {
+            self.step = (self.target - self.level) / samples;
+        }
+    }
+}
+
+impl<I> Iterator for Crossfade<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?.to_f32();
+
+        if self.samples_until_refresh == 0 {
+            self.refresh();
+            self.samples_until_refresh = CONTROL_REFRESH_SAMPLES;
+        }
+        self.samples_until_refresh -= 1;
+
+        if self.step != 0.0 {
+            self.level += self.step;
+            if (self.step > 0.0 && self.level >= self.target)
+                || (self.step < 0.0 && self.level <= self.target)
+            {
+                self.level = self.target;
+                self.step = 0.0;
+            }
+        }
+
+        if self.level >= 1.0 {
+            Some(sample)
+        } else if self.level <= 0.0 {
+            Some(0.0)
+        } else {
+            Some(sample * (self.level * FRAC_PI_2).sin())
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for Crossfade<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+/// A processing step of the DSP chain.
+///
+/// Stages are fed one interleaved sample at a time together with the
+/// channel it belongs to, so they can keep per-channel filter state.
+pub trait DspStage: Send {
+    /// Called before the first sample and whenever the channel count or the
+    /// sample rate of the input changes.
+    fn prepare(&mut self, channels: u16, sample_rate: u32);
+
+    fn process(&mut self, sample: f32, channel: usize) -> f32;
+
+    /// Forget everything learned from previous samples, called after seeking.
+    fn reset(&mut self) {}
+}
+
+/// A source running every sample through a list of stages, in order.
+pub struct DspChain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    input: I,
+    stages: Vec<Box<dyn DspStage>>,
+    channels: u16,
+    sample_rate: u32,
+    channel: usize,
+}
+
+pub fn dsp_chain<I>(input: I, mut stages: Vec<Box<dyn DspStage>>) -> DspChain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    let channels = input.channels().max(1);
+    let sample_rate = input.sample_rate();
+    for stage in stages.iter_mut() {
+        stage.prepare(channels, sample_rate);
+    }
+
+    DspChain {
+        input,
+        stages,
+        channels,
+        sample_rate,
+        channel: 0,
+    }
+}
+
+impl<I> DspChain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    /// The format may only change on frame boundaries, checking it once
+    /// per interleaved frame is enough.
+    fn refresh_format(&mut self) {
+        let channels = self.input.channels().max(1);
+        let sample_rate = self.input.sample_rate();
+        if channels == self.channels && sample_rate == self.sample_rate {
+            return;
+        }
+
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+        for stage in self.stages.iter_mut() {
+            stage.prepare(channels, sample_rate);
+        }
+    }
+}
+
+impl<I> Iterator for DspChain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            self.refresh_format();
+        }
+
+        let mut sample = self.input.next()?.to_f32();
+        for stage in self.stages.iter_mut() {
+            sample = stage.process(sample, self.channel);
+        }
+
+        self.channel = (self.channel + 1) % self.channels as usize;
+
+        Some(sample)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for DspChain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+
+        self.channel = 0;
+        for stage in self.stages.iter_mut() {
+            stage.reset();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::control::{AtomicF32, CONTROL_REFRESH_SAMPLES};
+use crate::dsp::DspStage;
+
+/// Center frequencies of the bands in Hz, one octave apart.
+pub const EQUALIZER_BANDS: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+/// Largest boost or cut of a single band, in dB.
+pub const MAX_BAND_GAIN_DB: f32 = 12.0;
+
+/// Quality factor giving every band a bandwidth of about one octave.
+const BAND_Q: f32 = 1.41;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EqualizerPreset {
+    #[default]
+    Flat,
+    BassBoost,
+    TrebleBoost,
+    Vocal,
+    Rock,
+    Pop,
+    Classical,
+    Electronic,
+    /// Gains picked band by band by the user.
+    Custom,
+}
+
+impl EqualizerPreset {
+    /// Gains of every band in dB, `None` for the custom preset.
+    pub fn gains_db(&self) -> Option<[f32; 10]> {
+        let gains = match self {
+            EqualizerPreset::Flat => [0.0; 10],
+            EqualizerPreset::BassBoost => [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            EqualizerPreset::TrebleBoost => [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 4.0, 5.0, 6.0],
+            EqualizerPreset::Vocal => [-2.0, -2.0, -1.0, 1.0, 3.0, 3.0, 2.0, 1.0, 0.0, -1.0],
+            EqualizerPreset::Rock => [4.0, 3.0, 2.0, 0.0, -1.0, -1.0, 1.0, 2.0, 3.0, 4.0],
+            EqualizerPreset::Pop => [-1.0, 0.0, 2.0, 3.0, 4.0, 3.0, 2.0, 0.0, -1.0, -1.0],
+            EqualizerPreset::Classical => [3.0, 2.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0],
+            EqualizerPreset::Electronic => [5.0, 4.0, 1.0, 0.0, -2.0, 0.0, 1.0, 2.0, 4.0, 5.0],
+            EqualizerPreset::Custom => return None,
+        };
+
+        Some(gains)
+    }
+}
+
+impl std::str::FromStr for EqualizerPreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "flat" => Ok(EqualizerPreset::Flat),
+            "bassboost" => Ok(EqualizerPreset::BassBoost),
+            "trebleboost" => Ok(EqualizerPreset::TrebleBoost),
+            "vocal" => Ok(EqualizerPreset::Vocal),
+            "rock" => Ok(EqualizerPreset::Rock),
+            "pop" => Ok(EqualizerPreset::Pop),
+            "classical" => Ok(EqualizerPreset::Classical),
+            "electronic" => Ok(EqualizerPreset::Electronic),
+            "custom" => Ok(EqualizerPreset::Custom),
+            _ => Err(anyhow::anyhow!("Unknown equalizer preset: {s}")),
+        }
+    }
+}
+
+impl std::fmt::Display for EqualizerPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let preset_str = match self {
+            EqualizerPreset::Flat => "Flat",
+            EqualizerPreset::BassBoost => "BassBoost",
+            EqualizerPreset::TrebleBoost => "TrebleBoost",
+            EqualizerPreset::Vocal => "Vocal",
+            EqualizerPreset::Rock => "Rock",
+            EqualizerPreset::Pop => "Pop",
+            EqualizerPreset::Classical => "Classical",
+            EqualizerPreset::Electronic => "Electronic",
+            EqualizerPreset::Custom => "Custom",
+        };
+        write!(f, "{preset_str}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqualizerConfig {
+    pub enabled: bool,
+    pub preset: EqualizerPreset,
+    /// Gain applied before the bands to leave headroom for boosts, in dB.
+    pub preamp_db: f32,
+    /// Gain of every band of `EQUALIZER_BANDS`, in dB.
+    pub gains_db: [f32; 10],
+}
+
+impl Default for EqualizerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preset: EqualizerPreset::Flat,
+            preamp_db: 0.0,
+            gains_db: [0.0; 10],
+        }
+    }
+}
+
+impl EqualizerConfig {
+    /// A config using the gains of the preset, or `gains_db` for the custom
+    /// preset. Gains are clamped to `MAX_BAND_GAIN_DB`.
+    pub fn new(
+        enabled: bool,
+        preset: EqualizerPreset,
+        preamp_db: f32,
+        gains_db: [f32; 10],
+    ) -> Self {
+        let gains_db = preset
+            .gains_db()
+            .unwrap_or(gains_db)
+            .map(|x| x.clamp(-MAX_BAND_GAIN_DB, MAX_BAND_GAIN_DB));
+
+        Self {
+            enabled,
+            preset,
+            preamp_db: preamp_db.clamp(-MAX_BAND_GAIN_DB, MAX_BAND_GAIN_DB),
+            gains_db,
+        }
+    }
+
+    /// Whether the equalizer leaves the signal untouched.
+    pub fn is_bypassed(&self) -> bool {
+        !self.enabled || (self.preamp_db == 0.0 && self.gains_db.iter().all(|x| *x == 0.0))
+    }
+}
+
+/// Gains shared between the player thread and the audio thread, `version`
+/// tells the audio thread when to recompute the filters.
+#[derive(Debug, Default)]
+pub struct EqualizerControl {
+    enabled: AtomicBool,
+    preamp_db: AtomicF32,
+    gains_db: [AtomicF32; 10],
+    version: AtomicU32,
+}
+
+impl EqualizerControl {
+    pub fn update(&self, config: &EqualizerConfig) {
+        self.preamp_db.store(config.preamp_db);
+        for (gain, value) in self.gains_db.iter().zip(config.gains_db) {
+            gain.store(value);
+        }
+        self.enabled.store(!config.is_bypassed(), Ordering::Relaxed);
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    fn version(&self) -> u32 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    fn load(&self) -> Option<(f32, [f32; 10])> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let preamp_db = self.preamp_db.load();
+        let gains_db = std::array::from_fn(|i| self.gains_db[i].load());
+
+        Some((preamp_db, gains_db))
+    }
+}
+
+/// Normalized coefficients of a peaking biquad filter.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    const IDENTITY: Biquad = Biquad {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+
+    /// Peaking filter from the Audio EQ Cookbook.
+    fn peaking(frequency: f32, gain_db: f32, sample_rate: u32) -> Self {
+        // Bands above the Nyquist frequency can't be represented
+        if gain_db == 0.0 || frequency >= sample_rate as f32 / 2.0 {
+            return Self::IDENTITY;
+        }
+
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * BAND_Q);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha / a;
+
+        Biquad {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos_w0) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos_w0) / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+}
+
+/// Delay line of a biquad in transposed direct form II.
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    z1: f32,
+    z2: f32,
+}
+
+impl BiquadState {
+    #[inline]
+    fn process(&mut self, x: f32, filter: &Biquad) -> f32 {
+        let y = filter.b0 * x + self.z1;
+        self.z1 = filter.b1 * x - filter.a1 * y + self.z2;
+        self.z2 = filter.b2 * x - filter.a2 * y;
+        y
+    }
+}
+
+/// A stage running the signal through ten peaking filters, bypassed while
+/// the equalizer is disabled or flat.
+pub struct Equalizer {
+    control: Arc<EqualizerControl>,
+    version: u32,
+    sample_rate: u32,
+    preamp: f32,
+    gains_db: Option<[f32; 10]>,
+    filters: [Biquad; 10],
+    states: Vec<[BiquadState; 10]>,
+    samples_until_refresh: usize,
+}
+
+impl Equalizer {
+    pub fn new(control: Arc<EqualizerControl>) -> Self {
+        Self {
+            version: control.version(),
+            control,
+            sample_rate: 44100,
+            preamp: 1.0,
+            gains_db: None,
+            filters: [Biquad::IDENTITY; 10],
+            states: Vec::new(),
+            samples_until_refresh: 0,
+        }
+    }
+
+    fn reload(&mut self) {
+        self.version = self.control.version();
+
+        let Some((preamp_db, gains_db)) = self.control.load() else {
+            self.gains_db = None;
+            return;
+        };
+
+        // Stale filter state would click when the equalizer comes back on
+        if self.gains_db.is_none() {
+            self.reset();
+        }
+
+        self.preamp = 10f32.powf(preamp_db / 20.0);
+        self.gains_db = Some(gains_db);
+        self.rebuild_filters();
+    }
+
+    fn rebuild_filters(&mut self) {
+        let Some(gains_db) = self.gains_db else {
+            return;
+        };
+
+        for (i, filter) in self.filters.iter_mut().enumerate() {
+            *filter = Biquad::peaking(EQUALIZER_BANDS[i], gains_db[i], self.sample_rate);
+        }
+    }
+}
+
+impl DspStage for Equalizer {
+    fn prepare(&mut self, channels: u16, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.states = vec![[BiquadState::default(); 10]; channels.max(1) as usize];
+        self.reload();
+    }
+
+    #[inline]
+    fn process(&mut self, sample: f32, channel: usize) -> f32 {
+        if self.samples_until_refresh == 0 {
+            if self.control.version() != self.version {
+                self.reload();
+            }
+            self.samples_until_refresh = CONTROL_REFRESH_SAMPLES;
+        }
+        self.samples_until_refresh -= 1;
+
+        if self.gains_db.is_none() {
+            return sample;
+        }
+
+        let Some(states) = self.states.get_mut(channel) else {
+            return sample;
+        };
+
+        let mut y = sample * self.preamp;
+        for (state, filter) in states.iter_mut().zip(self.filters.iter()) {
+            y = state.process(y, filter);
+        }
+        y
+    }
+
+    fn reset(&mut self) {
+        for states in self.states.iter_mut() {
+            *states = [BiquadState::default(); 10];
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+use crate::control::AtomicF64;
+
+/// WHO safe listening reference: 80 dB(A) for 40 hours a week.
+const REFERENCE_LEVEL_DB: f64 = 80.0;
+const REFERENCE_SECONDS_PER_DAY: f64 = 40.0 * 3600.0 / 7.0;
+/// Equal energy rule, every 3 dB above the reference halves the allowed time.
+const EXCHANGE_RATE_DB: f64 = 3.0;
+/// Levels below this are considered harmless and do not count towards the dose.
+const SILENCE_LEVEL_DB: f64 = 40.0;
+
+/// How many samples are measured between two flushes into the shared
+/// accumulator.
+const FLUSH_SAMPLES: usize = 4096;
+
+/// Settings of the hearing protection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HearingProtectionConfig {
+    /// Estimated sound pressure level of a full scale signal at maximum
+    /// volume, in dB SPL. Depends on the headphones, 100 dB is typical.
+    pub full_scale_spl_db: f32,
+    /// Lower the volume once the daily allowance is used up.
+    pub auto_reduce: bool,
+    /// Volume the player falls back to when `auto_reduce` kicks in.
+    pub reduced_volume: f32,
+}
+
+impl Default for HearingProtectionConfig {
+    fn default() -> Self {
+        Self {
+            full_scale_spl_db: 100.0,
+            auto_reduce: false,
+            reduced_volume: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ExposureWarning {
+    #[default]
+    None,
+    /// Half of the daily allowance is used.
+    Half,
+    /// 80% of the daily allowance is used.
+    Approaching,
+    /// The daily allowance is used up.
+    Exceeded,
+}
+
+impl ExposureWarning {
+    pub fn from_dose(dose: f64) -> Self {
+        if dose >= 1.0 {
+            ExposureWarning::Exceeded
+        } else if dose >= 0.8 {
+            ExposureWarning::Approaching
+        } else if dose >= 0.5 {
+            ExposureWarning::Half
+        } else {
+            ExposureWarning::None
+        }
+    }
+}
+
+impl std::fmt::Display for ExposureWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let warning_str = match self {
+            ExposureWarning::None => "None",
+            ExposureWarning::Half => "Half",
+            ExposureWarning::Approaching => "Approaching",
+            ExposureWarning::Exceeded => "Exceeded",
+        };
+        write!(f, "{warning_str}")
+    }
+}
+
+/// Listening exposure of the current day.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExposureStatus {
+    pub date: Option<NaiveDate>,
+    /// Seconds of audible playback.
+    pub listening_seconds: f64,
+    /// Energy average level over the listening time, in dB SPL.
+    pub average_level_db: f64,
+    /// Used fraction of the daily allowance, `1.0` is the WHO limit.
+    pub dose: f64,
+    pub warning: ExposureWarning,
+}
+
+/// Daily noise dose bookkeeping. The player only keeps it in memory, hosts
+/// save the status and hand it back through [`ListeningExposure::restore`]
+/// so the dose survives a restart.
+#[derive(Debug, Default)]
+pub struct ListeningExposure {
+    date: Option<NaiveDate>,
+    listening_seconds: f64,
+    /// Sum of `seconds * 10^(level / 10)`, for the energy average.
+    energy_seconds: f64,
+    dose: f64,
+}
+
+impl ListeningExposure {
+    /// Continue from a saved status.
+    pub fn restore(status: &ExposureStatus) -> Self {
+        ListeningExposure {
+            date: status.date,
+            listening_seconds: status.listening_seconds,
+            energy_seconds: status.listening_seconds * 10f64.powf(status.average_level_db / 10.0),
+            dose: status.dose,
+        }
+    }
+
+    /// Account `seconds` of playback at `level_db` dB SPL on `date`,
+    /// starting over when the date changes.
+    pub fn add(&mut self, date: NaiveDate, seconds: f64, level_db: f64) {
+        if self.date != Some(date) {
+            *self = ListeningExposure {
+                date: Some(date),
+                ..Default::default()
+            };
+        }
+
+        if seconds <= 0.0 || level_db < SILENCE_LEVEL_DB {
+            return;
+        }
+
+        let allowed_seconds = REFERENCE_SECONDS_PER_DAY
+            / 2f64.powf((level_db - REFERENCE_LEVEL_DB) / EXCHANGE_RATE_DB);
+
+        self.listening_seconds += seconds;
+        self.energy_seconds += seconds * 10f64.powf(level_db / 10.0);
+        self.dose += seconds / allowed_seconds;
+    }
+
+    pub fn status(&self) -> ExposureStatus {
+        let average_level_db = if self.listening_seconds > 0.0 {
+            10.0 * (self.energy_seconds / self.listening_seconds).log10()
+        } else {
+            0.0
+        };
+
+        ExposureStatus {
+            date: self.date,
+            listening_seconds: self.listening_seconds,
+            average_level_db,
+            dose: self.dose,
+            warning: ExposureWarning::from_dose(self.dose),
+        }
+    }
+}
+
+/// Signal energy collected by the audio thread, drained periodically by the
+/// player thread.
+#[derive(Debug, Default)]
+pub struct ExposureAccumulator {
+    energy_seconds: AtomicF64,
+    seconds: AtomicF64,
+}
+
+impl ExposureAccumulator {
+    fn add(&self, mean_square: f64, seconds: f64) {
+        self.energy_seconds.add(mean_square * seconds);
+        self.seconds.add(seconds);
+    }
+
+    /// Take the measured duration in seconds and its mean square amplitude
+    /// (before the volume is applied), resetting the accumulator.
+    pub fn take(&self) -> (f64, f64) {
+        let energy_seconds = self.energy_seconds.take();
+        let seconds = self.seconds.take();
+
+        if seconds > 0.0 {
+            (seconds, energy_seconds / seconds)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+}
+
+/// A pass-through source measuring the energy of everything rendered.
+pub struct ExposureMeter<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    input: I,
+    accumulator: Arc<ExposureAccumulator>,
+    sum_squares: f64,
+    samples: usize,
+}
+
+pub fn exposure_meter<I>(input: I, accumulator: Arc<ExposureAccumulator>) -> ExposureMeter<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    ExposureMeter {
+        input,
+        accumulator,
+        sum_squares: 0.0,
+        samples: 0,
+    }
+}
+
+impl<I> ExposureMeter<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    fn flush(&mut self) {
+        if self.samples == 0 {
+            return;
+        }
+
+        let rate = (self.input.sample_rate() * self.input.channels().max(1) as u32) as f64;
+        self.accumulator.add(
+            self.sum_squares / self.samples as f64,
+            self.samples as f64 / rate,
+        );
+        self.sum_squares = 0.0;
+        self.samples = 0;
+    }
+}
+
+impl<I> Iterator for ExposureMeter<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let Some(sample) = self.input.next() else {
+            self.flush();
+            return None;
+        };
+
+        let x = sample.to_f32() as f64;
+        self.sum_squares += x * x;
+        self.samples += 1;
+
+        if self.samples >= FLUSH_SAMPLES {
+            self.flush();
+        }
+
+        Some(sample)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for ExposureMeter<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.flush();
+        self.input.try_seek(pos)
+    }
+}
+
+/// Convert a mean square amplitude at the given volume to dB SPL.
+pub fn mean_square_to_spl(mean_square: f64, volume: f32, full_scale_spl_db: f32) -> f64 {
+    let power = mean_square * (volume as f64).powi(2);
+    10.0 * power.max(1e-12).log10() + full_scale_spl_db as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 8, day).unwrap()
+    }
+
+    #[test]
+    fn test_reference_level_uses_up_the_allowance() {
+        let mut exposure = ListeningExposure::default();
+        exposure.add(date(1), REFERENCE_SECONDS_PER_DAY, REFERENCE_LEVEL_DB);
+
+        let status = exposure.status();
+        assert!((status.dose - 1.0).abs() < 1e-9);
+        assert_eq!(status.warning, ExposureWarning::Exceeded);
+    }
+
+    #[test]
+    fn test_three_db_louder_halves_the_allowance() {
+        let mut exposure = ListeningExposure::default();
+        exposure.add(date(1), REFERENCE_SECONDS_PER_DAY / 2.0, 83.0);
+        assert!((exposure.status().dose - 1.0).abs() < 1e-9);
+
+        let mut exposure = ListeningExposure::default();
+        exposure.add(date(1), REFERENCE_SECONDS_PER_DAY, 77.0);
+        assert!((exposure.status().dose - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_quiet_playback_does_not_count() {
+        let mut exposure = ListeningExposure::default();
+        exposure.add(date(1), 3600.0, SILENCE_LEVEL_DB - 1.0);
+
+        let status = exposure.status();
+        assert_eq!(status.date, Some(date(1)));
+        assert_eq!(status.listening_seconds, 0.0);
+        assert_eq!(status.dose, 0.0);
+        assert_eq!(status.warning, ExposureWarning::None);
+    }
+
+    #[test]
+    fn test_doses_accumulate_within_a_day() {
+        let mut exposure = ListeningExposure::default();
+        exposure.add(date(1), REFERENCE_SECONDS_PER_DAY * 0.3, REFERENCE_LEVEL_DB);
+        assert_eq!(exposure.status().warning, ExposureWarning::None);
+
+        exposure.add(date(1), REFERENCE_SECONDS_PER_DAY * 0.3, REFERENCE_LEVEL_DB);
+        assert_eq!(exposure.status().warning, ExposureWarning::Half);
+
+        exposure.add(date(1), REFERENCE_SECONDS_PER_DAY * 0.3, REFERENCE_LEVEL_DB);
+        let status = exposure.status();
+        assert!((status.dose - 0.9).abs() < 1e-9);
+        assert_eq!(status.warning, ExposureWarning::Approaching);
+    }
+
+    #[test]
+    fn test_new_day_starts_over() {
+        let mut exposure = ListeningExposure::default();
+        exposure.add(date(1), REFERENCE_SECONDS_PER_DAY, REFERENCE_LEVEL_DB);
+        exposure.add(date(2), 60.0, SILENCE_LEVEL_DB - 1.0);
+
+        let status = exposure.status();
+        assert_eq!(status.date, Some(date(2)));
+        assert_eq!(status.listening_seconds, 0.0);
+        assert_eq!(status.dose, 0.0);
+    }
+
+    #[test]
+    fn test_average_level_is_energy_weighted() {
+        let mut exposure = ListeningExposure::default();
+        exposure.add(date(1), 60.0, 70.0);
+        exposure.add(date(1), 60.0, 90.0);
+
+        // 10 * log10((10^7 + 10^9) / 2), dominated by the louder half
+        let status = exposure.status();
+        assert_eq!(status.listening_seconds, 120.0);
+        assert!((status.average_level_db - 87.0329).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_restore_continues_the_dose() {
+        let mut exposure = ListeningExposure::default();
+        exposure.add(date(1), 600.0, 75.0);
+        exposure.add(date(1), 300.0, 88.0);
+
+        let mut restored = ListeningExposure::restore(&exposure.status());
+        assert_eq!(restored.status().date, Some(date(1)));
+        assert!((restored.status().dose - exposure.status().dose).abs() < 1e-9);
+
+        exposure.add(date(1), 120.0, 82.0);
+        restored.add(date(1), 120.0, 82.0);
+
+        let expected = exposure.status();
+        let actual = restored.status();
+        assert!((actual.dose - expected.dose).abs() < 1e-9);
+        assert!((actual.average_level_db - expected.average_level_db).abs() < 1e-9);
+        assert_eq!(actual.listening_seconds, expected.listening_seconds);
+    }
+
+    #[test]
+    fn test_spl_follows_volume_and_calibration() {
+        // A full scale sine has a mean square of 0.5
+        assert!((mean_square_to_spl(0.5, 1.0, 100.0) - 96.9897).abs() < 1e-3);
+        // Half the volume is 6 dB less
+        assert!((mean_square_to_spl(0.5, 0.5, 100.0) - 90.9691).abs() < 1e-3);
+        // Silence does not produce infinities
+        assert!(mean_square_to_spl(0.0, 1.0, 100.0).is_finite());
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+/// How long before the end of a track the next one is opened and queued.
+pub const GAPLESS_PRELOAD_AHEAD: Duration = Duration::from_secs(10);
+
+/// State of a track queued behind the playing one, shared between the
+/// player thread and the audio thread.
+#[derive(Debug, Default)]
+pub struct GaplessControl {
+    started: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+impl GaplessControl {
+    /// Whether the output has reached the queued track.
+    pub fn is_started(&self) -> bool {
+        self.started.load(Ordering::Relaxed)
+    }
+
+    /// Drop the queued track, the output skips over it once it gets there.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A source that reports when it starts playing and can be skipped while
+/// it is still waiting in the queue of a sink.
+pub struct Gapless<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    input: I,
+    control: Arc<GaplessControl>,
+    started: bool,
+}
+
+pub fn gapless<I>(input: I, control: Arc<GaplessControl>) -> Gapless<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    Gapless {
+        input,
+        control,
+        started: false,
+    }
+}
+
+impl<I> Iterator for Gapless<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if !self.started {
+            if self.control.cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            self.started = true;
+            self.control.started.store(true, Ordering::Relaxed);
+        }
+
+        self.input.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for Gapless<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
@@ -1,25 +1,40 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::BufReader;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Local, Timelike};
 use log::{debug, error, info, warn};
 use rodio::source::SeekError;
-use rodio::{Decoder, PlayError, Sink, Source};
+use rodio::{PlayError, Sink, Source};
 use tokio::sync::mpsc;
 use tokio::time::{interval, sleep_until, Duration, Instant};
 use tokio_util::sync::CancellationToken;
 
+use crate::audition::AuditionConfig;
 use crate::buffered::rune_buffered;
-use crate::output_stream::{RuneOutputStream, RuneOutputStreamHandle};
+use crate::crossfade::{crossfade, CrossfadeControl, MAX_CROSSFADE};
+use crate::dsp::dsp_chain;
+use crate::equalizer::{Equalizer, EqualizerConfig, EqualizerControl};
+use crate::exposure::{
+    exposure_meter, mean_square_to_spl, ExposureAccumulator, ExposureStatus,
+    HearingProtectionConfig, ListeningExposure,
+};
+use crate::gapless::{gapless, GaplessControl, GAPLESS_PRELOAD_AHEAD};
+use crate::night_mode::{night_mode, NightModeConfig, NightModeControl};
+use crate::output_stream::{OutputStatus, RuneOutputStream, RuneOutputStreamHandle};
 use crate::player::PlayingItem;
+use crate::prefetch::prefetch;
 use crate::realtime_fft::RealTimeFFT;
+use crate::replay_gain::{replay_gain, ReplayGainConfig, ReplayGainControl, ReplayGainInfo};
+use crate::seek_table::{SeekTable, SeekableDecoder};
 use crate::shared_source::SharedSource;
 use crate::strategies::{
-    AddMode, PlaybackStrategy, RepeatAllStrategy, RepeatOneStrategy, SequentialStrategy,
-    ShuffleStrategy, UpdateReason,
+    AddMode, PlaybackStrategy, RepeatAllStrategy, RepeatMode, RepeatOneStrategy,
+    SequentialStrategy, ShuffleMode, ShuffleStrategy, UpdateReason,
 };
+use crate::trim::{trim, TrackTrim};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PlaybackMode {
@@ -41,6 +56,29 @@ impl From<u32> for PlaybackMode {
     }
 }
 
+impl PlaybackMode {
+    /// The repeat and shuffle modes this mode stands for.
+    pub fn order(self) -> (RepeatMode, ShuffleMode) {
+        match self {
+            PlaybackMode::Sequential => (RepeatMode::Off, ShuffleMode::Off),
+            PlaybackMode::RepeatOne => (RepeatMode::One, ShuffleMode::Off),
+            PlaybackMode::RepeatAll => (RepeatMode::All, ShuffleMode::Off),
+            PlaybackMode::Shuffle => (RepeatMode::All, ShuffleMode::Uniform),
+        }
+    }
+
+    /// The closest mode to a combination of repeat and shuffle modes, for
+    /// clients that only know about the four fixed modes.
+    pub fn from_order(repeat: RepeatMode, shuffle: ShuffleMode) -> Self {
+        match (repeat, shuffle) {
+            (_, ShuffleMode::Uniform | ShuffleMode::Weighted) => PlaybackMode::Shuffle,
+            (RepeatMode::Off, ShuffleMode::Off) => PlaybackMode::Sequential,
+            (RepeatMode::One, ShuffleMode::Off) => PlaybackMode::RepeatOne,
+            (RepeatMode::All, ShuffleMode::Off) => PlaybackMode::RepeatAll,
+        }
+    }
+}
+
 impl From<PlaybackMode> for u32 {
     fn from(mode: PlaybackMode) -> Self {
         match mode {
@@ -77,9 +115,31 @@ pub enum PlayerCommand {
         new_index: usize,
     },
     SetPlaybackMode(PlaybackMode),
+    SetRepeatMode(RepeatMode),
+    SetShuffleMode(ShuffleMode),
+    /// Weights of a weighted shuffle, items missing here weigh `1.0`.
+    UpdateShuffleWeights(HashMap<PlayingItem, f64>),
     SetVolume(f32),
+    /// Play through the output device with this name, or the system default
+    /// for `None`.
+    SetOutputDevice(Option<String>),
     SetRealtimeFFTEnabled(bool),
     SetAdaptiveSwitchingEnabled(bool),
+    SetGaplessEnabled(bool),
+    SetCrossfade(Duration),
+    SetNightMode(NightModeConfig),
+    SetEqualizer(EqualizerConfig),
+    SetHearingProtection(HearingProtectionConfig),
+    RestoreExposure(ExposureStatus),
+    SetReplayGain(ReplayGainConfig),
+    UpdateReplayGainInfo(HashMap<PlayingItem, ReplayGainInfo>),
+    UpdateSeekTables(HashMap<PlayingItem, Arc<SeekTable>>),
+    /// Applied the next time a track is opened, empty trims play the whole
+    /// file.
+    UpdateTrackTrims(HashMap<PlayingItem, TrackTrim>),
+    /// Start auditioning the playlist, or end it with `None`. Clearing the
+    /// playlist ends it too.
+    SetAudition(Option<AuditionConfig>),
 }
 
 #[derive(Debug, Clone)]
@@ -128,6 +188,13 @@ pub enum PlayerEvent {
         ready: bool,
     },
     VolumeUpdate(f32),
+    PlaybackOrderUpdate {
+        repeat_mode: RepeatMode,
+        shuffle_mode: ShuffleMode,
+    },
+    ExposureUpdate(ExposureStatus),
+    /// The device and format of the output stream of the loaded track.
+    OutputUpdate(OutputStatus),
     PlaylistUpdated(Vec<PlayingItem>),
     RealtimeFFT(Vec<f32>),
     Log(InternalLog),
@@ -139,6 +206,26 @@ pub struct PlaylistItem {
     pub path: PathBuf,
 }
 
+/// A track queued behind the playing one for gapless playback.
+#[derive(Debug)]
+struct PreloadedTrack {
+    index: usize,
+    item: PlayingItem,
+    path: PathBuf,
+    duration: Option<Duration>,
+    replay_gain_control: Arc<ReplayGainControl>,
+    crossfade_control: Arc<CrossfadeControl>,
+    control: Arc<GaplessControl>,
+}
+
+/// A running mix audition, with the settings it overrides until it ends.
+#[derive(Debug)]
+struct Audition {
+    config: AuditionConfig,
+    crossfade: Duration,
+    replay_gain: ReplayGainConfig,
+}
+
 #[derive(Debug, PartialEq)]
 enum InternalPlaybackState {
     Playing,
@@ -160,23 +247,23 @@ pub struct InternalLog {
 
 impl Source for SharedSource {
     fn current_frame_len(&self) -> Option<usize> {
-        self.inner.lock().unwrap().current_frame_len()
+        self.inner.current_frame_len()
     }
 
     fn channels(&self) -> u16 {
-        self.inner.lock().unwrap().channels()
+        self.inner.channels()
     }
 
     fn sample_rate(&self) -> u32 {
-        self.inner.lock().unwrap().sample_rate()
+        self.inner.sample_rate()
     }
 
     fn total_duration(&self) -> Option<Duration> {
-        self.inner.lock().unwrap().total_duration()
+        self.inner.total_duration()
     }
 
     fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
-        self.inner.lock().unwrap().try_seek(pos)
+        self.inner.try_seek(pos)
     }
 }
 
@@ -189,18 +276,45 @@ pub(crate) struct PlayerInternal {
     current_item: Option<PlayingItem>,
     current_track_index: Option<usize>,
     current_track_path: Option<PathBuf>,
+    current_duration: Option<Duration>,
     sink: Option<Sink>,
+    /// The previous track while it fades out under the current one.
+    fading_sink: Option<Sink>,
     _stream: Option<RuneOutputStream>,
+    stream_handle: Option<RuneOutputStreamHandle>,
     state: InternalPlaybackState,
     debounce_timer: Option<Instant>,
     cancellation_token: CancellationToken,
     playback_mode: PlaybackMode,
+    repeat_mode: RepeatMode,
+    shuffle_mode: ShuffleMode,
+    shuffle_weights: HashMap<PlayingItem, f64>,
     playback_strategy: Box<dyn PlaybackStrategy>,
     volume: f32,
+    output_device: Option<String>,
     stream_error_sender: mpsc::UnboundedSender<String>,
     stream_error_receiver: mpsc::UnboundedReceiver<String>,
     stream_retry_count: usize,
     adaptive_switching: bool,
+    gapless: bool,
+    preloaded: Option<PreloadedTrack>,
+    preload_failed: bool,
+    crossfade: Duration,
+    crossfade_control: Arc<CrossfadeControl>,
+    night_mode: NightModeConfig,
+    night_mode_control: Arc<NightModeControl>,
+    equalizer: EqualizerConfig,
+    equalizer_control: Arc<EqualizerControl>,
+    hearing_protection: HearingProtectionConfig,
+    exposure: ListeningExposure,
+    exposure_accumulator: Arc<ExposureAccumulator>,
+    volume_reduced_on: Option<chrono::NaiveDate>,
+    replay_gain: ReplayGainConfig,
+    replay_gain_info: HashMap<PlayingItem, ReplayGainInfo>,
+    replay_gain_control: Arc<ReplayGainControl>,
+    seek_tables: HashMap<PlayingItem, Arc<SeekTable>>,
+    track_trims: HashMap<PlayingItem, TrackTrim>,
+    audition: Option<Audition>,
 }
 
 impl PlayerInternal {
@@ -217,25 +331,53 @@ impl PlayerInternal {
             current_item: None,
             current_track_index: None,
             current_track_path: None,
+            current_duration: None,
             sink: None,
+            fading_sink: None,
             _stream: None,
+            stream_handle: None,
             realtime_fft: Arc::new(Mutex::new(RealTimeFFT::new(512))),
             state: InternalPlaybackState::Stopped,
             debounce_timer: None,
             cancellation_token,
             playback_mode: PlaybackMode::Sequential,
+            repeat_mode: RepeatMode::Off,
+            shuffle_mode: ShuffleMode::Off,
+            shuffle_weights: HashMap::new(),
             playback_strategy: Box::new(SequentialStrategy),
             volume: 1.0,
+            output_device: None,
             fft_enabled: Arc::new(Mutex::new(false)),
             stream_error_sender,
             stream_error_receiver,
             stream_retry_count: 0,
             adaptive_switching: false,
+            gapless: false,
+            preloaded: None,
+            preload_failed: false,
+            crossfade: Duration::ZERO,
+            crossfade_control: Arc::new(CrossfadeControl::default()),
+            night_mode: NightModeConfig::default(),
+            night_mode_control: Arc::new(NightModeControl::default()),
+            equalizer: EqualizerConfig::default(),
+            equalizer_control: Arc::new(EqualizerControl::default()),
+            hearing_protection: HearingProtectionConfig::default(),
+            exposure: ListeningExposure::default(),
+            exposure_accumulator: Arc::new(ExposureAccumulator::default()),
+            volume_reduced_on: None,
+            replay_gain: ReplayGainConfig::default(),
+            replay_gain_info: HashMap::new(),
+            replay_gain_control: Arc::new(ReplayGainControl::default()),
+            seek_tables: HashMap::new(),
+            track_trims: HashMap::new(),
+            audition: None,
         }
     }
 
     pub async fn run(&mut self) -> Result<()> {
         let mut progress_interval = interval(Duration::from_millis(100));
+        let mut night_mode_interval = interval(Duration::from_secs(30));
+        let mut exposure_interval = interval(Duration::from_secs(5));
 
         let fft_receiver = match self.realtime_fft.lock() {
             Ok(fft) => fft.subscribe(),
@@ -274,9 +416,24 @@ impl PlayerInternal {
                             Ok(())
                         },
                         PlayerCommand::SetPlaybackMode(mode) => self.set_playback_mode(mode),
+                        PlayerCommand::SetRepeatMode(mode) => self.set_repeat_mode(mode),
+                        PlayerCommand::SetShuffleMode(mode) => self.set_shuffle_mode(mode),
+                        PlayerCommand::UpdateShuffleWeights(weights) => self.update_shuffle_weights(weights),
                         PlayerCommand::SetVolume(volume) => self.set_volume(volume),
+                        PlayerCommand::SetOutputDevice(name) => self.set_output_device(name),
                         PlayerCommand::SetRealtimeFFTEnabled(enabled) => self.set_realtime_fft_enabled(enabled),
                         PlayerCommand::SetAdaptiveSwitchingEnabled(enabled) => self.set_adaptive_switching(enabled),
+                        PlayerCommand::SetGaplessEnabled(enabled) => self.set_gapless(enabled),
+                        PlayerCommand::SetCrossfade(duration) => self.set_crossfade(duration),
+                        PlayerCommand::SetNightMode(config) => self.set_night_mode(config),
+                        PlayerCommand::SetEqualizer(config) => self.set_equalizer(config),
+                        PlayerCommand::SetHearingProtection(config) => self.set_hearing_protection(config),
+                        PlayerCommand::RestoreExposure(status) => self.restore_exposure(status),
+                        PlayerCommand::SetReplayGain(config) => self.set_replay_gain(config),
+                        PlayerCommand::UpdateReplayGainInfo(info) => self.update_replay_gain_info(info),
+                        PlayerCommand::UpdateSeekTables(tables) => self.update_seek_tables(tables),
+                        PlayerCommand::UpdateTrackTrims(trims) => self.update_track_trims(trims),
+                        PlayerCommand::SetAudition(config) => self.set_audition(config),
                     }?;
                 },
                 Ok(fft_data) = fft_receiver.recv() => {
@@ -289,6 +446,12 @@ impl PlayerInternal {
                         self.send_progress()?;
                     }
                 },
+                _ = night_mode_interval.tick() => {
+                    self.refresh_night_mode();
+                },
+                _ = exposure_interval.tick() => {
+                    self.refresh_exposure()?;
+                },
                 _ = async {
                     if let Some(timer) = self.debounce_timer {
                         sleep_until(timer).await;
@@ -298,6 +461,7 @@ impl PlayerInternal {
                     }
                 }, if self.debounce_timer.is_some() => {
                     self.debounce_timer = None;
+                    self.forget_dequeued_tracks();
                     self.send_playlist_updated()?;
                 },
                 Some(error_message) = self.stream_error_receiver.recv() => {
@@ -348,64 +512,54 @@ impl PlayerInternal {
                 return Ok(());
             }
 
-            let item = &self.playlist[mapped_index];
-            let file = File::open(item.path.clone())
-                .with_context(|| format!("Failed to open file: {:?}", item.path))?;
-            let source = Decoder::new(BufReader::new(file));
-
-            if let Err(error) = source {
-                warn!("Failed to decode file {:?}: {:#?}", item.path, error);
-                self.next()?;
-                self.event_sender.send(PlayerEvent::Log(InternalLog {
-                    domain: "player::internal::decoder".to_string(),
-                    error: format!("{error:#?}"),
-                }))?;
-                return Ok(());
-            }
-
-            let source = SharedSource::new(rune_buffered(source.unwrap()));
-            let source_for_fft = Arc::clone(&source.inner);
-
-            let (stream, stream_handle) = RuneOutputStream::try_default_with_callback({
-                let error_sender = self.stream_error_sender.clone();
-                move |error| {
-                    let _ = error_sender.send(error.to_string());
+            // Anything queued behind the previous track goes with its sink,
+            // a manual skip also cuts a running crossfade short
+            self.discard_preloaded();
+            self.stop_fading_sink();
+
+            let item = self.playlist[mapped_index].clone();
+
+            // Use a fresh control so the gain of the new track never leaks
+            // into the previous sink
+            self.replay_gain_control = Arc::new(ReplayGainControl::default());
+            self.apply_replay_gain(Some(&item.item));
+
+            let source = match self.open_track(&item, Arc::clone(&self.replay_gain_control))? {
+                Ok(source) => source,
+                Err(error) => {
+                    self.event_sender
+                        .send(PlayerEvent::Error {
+                            item: item.item.clone(),
+                            index: mapped_index,
+                            path: item.path.clone(),
+                            error,
+                        })
+                        .context("Failed to send Error event")?;
+                    self.next()?;
+                    return Ok(());
                 }
-            })
-            .context("Failed to create output stream")?;
-            let sink = try_new_sink(&stream_handle).context("Failed to create sink")?;
+            };
 
-            // Create a channel to transfer FFT data
-            let (fft_tx, mut fft_rx) = mpsc::unbounded_channel();
-
-            // Create a new thread for calculating realtime FFT
-            let realtime_fft = Arc::clone(&self.realtime_fft);
-            let fft_enabled = Arc::clone(&self.fft_enabled);
-            tokio::spawn(async move {
-                while let Some(data) = fft_rx.recv().await {
-                    if let Ok(enabled) = fft_enabled.lock() {
-                        if *enabled {
-                            if let Ok(fft) = realtime_fft.lock() {
-                                fft.add_data(data);
-                            }
-                        }
+            let (stream, stream_handle) =
+                RuneOutputStream::try_named_with_callback(self.output_device.as_deref(), {
+                    let error_sender = self.stream_error_sender.clone();
+                    move |error| {
+                        let _ = error_sender.send(error.to_string());
                     }
-                }
-            });
+                })
+                .context("Failed to create output stream")?;
+            let sink = try_new_sink(&stream_handle).context("Failed to create sink")?;
+            self.event_sender
+                .send(PlayerEvent::OutputUpdate(stream.status().clone()))
+                .context("Failed to send OutputUpdate event")?;
 
             sink.set_volume(self.volume);
-            sink.append(source.periodic_access(
-                Duration::from_millis(12),
-                move |_sample: &mut SharedSource| {
-                    if let Ok(guard) = source_for_fft.lock() {
-                        let data: Option<Vec<i16>> = guard.current_samples();
-                        if let Some(data) = data {
-                            if fft_tx.send(data).is_err() {
-                                error!("Failed to send FFT data");
-                            }
-                        }
-                    }
-                },
+            self.current_duration = source.total_duration();
+            self.crossfade_control = Arc::new(CrossfadeControl::default());
+            sink.append(crossfade(
+                source,
+                Arc::clone(&self.crossfade_control),
+                false,
             ));
 
             if !play {
@@ -414,6 +568,7 @@ impl PlayerInternal {
 
             self.sink = Some(sink);
             self._stream = Some(stream);
+            self.stream_handle = Some(stream_handle);
             self.current_track_index = Some(index);
             self.current_item = Some(item.item.clone());
             self.current_track_path = Some(item.path.clone());
@@ -460,9 +615,333 @@ impl PlayerInternal {
         Ok(())
     }
 
+    /// Decode a track and wrap it in the processing chain. The inner error
+    /// tells why the file can't be decoded.
+    fn open_track(
+        &self,
+        item: &PlaylistItem,
+        replay_gain_control: Arc<ReplayGainControl>,
+    ) -> Result<std::result::Result<Box<dyn Source<Item = f32> + Send>, String>> {
+        let path = &item.path;
+        let file = File::open(path).with_context(|| format!("Failed to open file: {path:?}"))?;
+        let seek_table = self.seek_tables.get(&item.item).cloned();
+        let source = match SeekableDecoder::new(file, path, seek_table) {
+            Ok(source) => source,
+            Err(error) => {
+                warn!("Failed to decode file {path:?}: {error:#?}");
+                self.event_sender.send(PlayerEvent::Log(InternalLog {
+                    domain: "player::internal::decoder".to_string(),
+                    error: format!("{error:#?}"),
+                }))?;
+                return Ok(Err(format!("{error:#}")));
+            }
+        };
+
+        // Decoding runs on its own thread, so neither the audio callback nor
+        // the run loop waits for it
+        let source = SharedSource::new(rune_buffered(prefetch(source)?));
+
+        // Create a channel to transfer FFT data
+        let (fft_tx, mut fft_rx) = mpsc::unbounded_channel();
+
+        // Create a new thread for calculating realtime FFT
+        let realtime_fft = Arc::clone(&self.realtime_fft);
+        let fft_enabled = Arc::clone(&self.fft_enabled);
+        tokio::spawn(async move {
+            while let Some(data) = fft_rx.recv().await {
+                if let Ok(enabled) = fft_enabled.lock() {
+                    if *enabled {
+                        if let Ok(fft) = realtime_fft.lock() {
+                            fft.add_data(data);
+                        }
+                    }
+                }
+            }
+        });
+
+        // An audition plays the hook of the track instead of its own trim
+        let track_trim = match &self.audition {
+            Some(audition) => audition.config.hook_trim(source.total_duration()),
+            None => self
+                .track_trims
+                .get(&item.item)
+                .copied()
+                .unwrap_or_default(),
+        };
+        let equalizer = Equalizer::new(Arc::clone(&self.equalizer_control));
+
+        Ok(Ok(Box::new(trim(
+            exposure_meter(
+                night_mode(
+                    dsp_chain(
+                        replay_gain(
+                            source.periodic_access(
+                                Duration::from_millis(12),
+                                move |source: &mut SharedSource| {
+                                    if let Some(data) = source.inner.current_samples() {
+                                        if fft_tx.send(data).is_err() {
+                                            error!("Failed to send FFT data");
+                                        }
+                                    }
+                                },
+                            ),
+                            replay_gain_control,
+                        ),
+                        vec![Box::new(equalizer)],
+                    ),
+                    Arc::clone(&self.night_mode_control),
+                ),
+                Arc::clone(&self.exposure_accumulator),
+            ),
+            track_trim,
+        ))))
+    }
+
+    /// Queue the next track behind the playing one once it is about to
+    /// end, so the output moves on without reopening the sink.
+    fn preload_next(&mut self) -> Result<()> {
+        // A crossfade starts the next track on its own sink instead
+        if !self.gapless
+            || !self.crossfade.is_zero()
+            || self.preloaded.is_some()
+            || self.preload_failed
+            || self.state != InternalPlaybackState::Playing
+        {
+            return Ok(());
+        }
+
+        let (Some(sink), Some(index)) = (&self.sink, self.current_track_index) else {
+            return Ok(());
+        };
+
+        if let Some(duration) = self.current_duration
+            && duration.saturating_sub(sink.get_pos()) > GAPLESS_PRELOAD_AHEAD
+        {
+            return Ok(());
+        }
+
+        let Some(next_index) = self.playback_strategy.next(index, self.playlist.len()) else {
+            return Ok(());
+        };
+        let Some(item) = self
+            .playlist
+            .get(self.get_mapped_track_index(next_index))
+            .cloned()
+        else {
+            return Ok(());
+        };
+
+        let replay_gain_control = Arc::new(ReplayGainControl::default());
+        replay_gain_control.set_gain_db(self.replay_gain_db(Some(&item.item)));
+
+        // The next track is opened again the usual way if this fails
+        let source = match self.open_track(&item, Arc::clone(&replay_gain_control)) {
+            Ok(Ok(source)) => source,
+            Ok(Err(_)) => {
+                self.preload_failed = true;
+                return Ok(());
+            }
+            Err(e) => {
+                warn!("Failed to preload next track: {e:#?}");
+                self.preload_failed = true;
+                return Ok(());
+            }
+        };
+
+        let control = Arc::new(GaplessControl::default());
+        let crossfade_control = Arc::new(CrossfadeControl::default());
+        let duration = source.total_duration();
+        if let Some(sink) = &self.sink {
+            sink.append(gapless(
+                crossfade(source, Arc::clone(&crossfade_control), false),
+                Arc::clone(&control),
+            ));
+        }
+
+        debug!("Next track queued for gapless playback: {:?}", item.path);
+        self.preloaded = Some(PreloadedTrack {
+            index: next_index,
+            item: item.item,
+            path: item.path,
+            duration,
+            replay_gain_control,
+            crossfade_control,
+            control,
+        });
+
+        Ok(())
+    }
+
+    /// Take over the queued track once the output has reached it.
+    fn advance_to_preloaded(&mut self) -> Result<()> {
+        let Some(preloaded) = self.preloaded.take_if(|x| x.control.is_started()) else {
+            return Ok(());
+        };
+
+        info!("Gapless transition to: {:?}", preloaded.path);
+        self.replay_gain_control = preloaded.replay_gain_control;
+        self.crossfade_control = preloaded.crossfade_control;
+        self.take_over_track(
+            preloaded.index,
+            preloaded.item,
+            preloaded.path,
+            preloaded.duration,
+        )
+    }
+
+    /// Make a track that the output moved on to without a reload the
+    /// current one, reporting the end of the previous track.
+    fn take_over_track(
+        &mut self,
+        index: usize,
+        item: PlayingItem,
+        path: PathBuf,
+        duration: Option<Duration>,
+    ) -> Result<()> {
+        let playback_mode = self.playback_mode;
+        if let (Some(item), Some(index), Some(path)) = (
+            self.current_item.clone(),
+            self.current_track_index,
+            self.current_track_path.clone(),
+        ) {
+            self.event_sender
+                .send(PlayerEvent::EndOfTrack {
+                    item,
+                    index: self.get_mapped_track_index(index),
+                    path,
+                    playback_mode,
+                })
+                .with_context(|| "Failed to send EndOfTrack event")?;
+        }
+
+        self.current_track_index = Some(index);
+        self.current_item = Some(item.clone());
+        self.current_track_path = Some(path.clone());
+        self.current_duration = duration;
+        self.preload_failed = false;
+
+        self.event_sender
+            .send(PlayerEvent::Playing {
+                item,
+                index: self.get_mapped_track_index(index),
+                path,
+                playback_mode,
+                position: Duration::ZERO,
+            })
+            .with_context(|| "Failed to send Playing event")?;
+
+        Ok(())
+    }
+
+    /// Start the next track on a second sink once the playing one is about
+    /// to end, fading the two into each other.
+    fn start_crossfade(&mut self) -> Result<()> {
+        if self.crossfade.is_zero()
+            || self.fading_sink.is_some()
+            || self.preload_failed
+            || self.state != InternalPlaybackState::Playing
+        {
+            return Ok(());
+        }
+
+        let (Some(sink), Some(index), Some(duration), Some(stream_handle)) = (
+            &self.sink,
+            self.current_track_index,
+            self.current_duration,
+            self.stream_handle.clone(),
+        ) else {
+            return Ok(());
+        };
+
+        // Short tracks fade for at most half of their length
+        let remaining = duration.saturating_sub(sink.get_pos());
+        if remaining > self.crossfade.min(duration / 2) {
+            return Ok(());
+        }
+
+        let Some(next_index) = self.playback_strategy.next(index, self.playlist.len()) else {
+            return Ok(());
+        };
+        let Some(item) = self
+            .playlist
+            .get(self.get_mapped_track_index(next_index))
+            .cloned()
+        else {
+            return Ok(());
+        };
+
+        let replay_gain_control = Arc::new(ReplayGainControl::default());
+        replay_gain_control.set_gain_db(self.replay_gain_db(Some(&item.item)));
+
+        // The next track is opened again the usual way if this fails
+        let source = match self.open_track(&item, Arc::clone(&replay_gain_control)) {
+            Ok(Ok(source)) => source,
+            Ok(Err(_)) => {
+                self.preload_failed = true;
+                return Ok(());
+            }
+            Err(e) => {
+                warn!("Failed to open next track for crossfade: {e:#?}");
+                self.preload_failed = true;
+                return Ok(());
+            }
+        };
+
+        let next_sink = try_new_sink(&stream_handle).context("Failed to create sink")?;
+        next_sink.set_volume(self.volume);
+        let duration = source.total_duration();
+        let crossfade_control = Arc::new(CrossfadeControl::default());
+        crossfade_control.fade_in(remaining);
+        next_sink.append(crossfade(source, Arc::clone(&crossfade_control), true));
+
+        self.crossfade_control.fade_out(remaining);
+        self.fading_sink = self.sink.replace(next_sink);
+        self.replay_gain_control = replay_gain_control;
+        self.crossfade_control = crossfade_control;
+
+        info!(
+            "Crossfading into {:?} over {:.1}s",
+            item.path,
+            remaining.as_secs_f32()
+        );
+        self.take_over_track(next_index, item.item, item.path, duration)
+    }
+
+    fn stop_fading_sink(&mut self) {
+        if let Some(sink) = self.fading_sink.take() {
+            sink.stop();
+        }
+    }
+
+    /// Drop the previous track once it has faded out completely.
+    fn finish_crossfade(&mut self) {
+        if self.fading_sink.as_ref().is_some_and(|x| x.empty()) {
+            self.fading_sink = None;
+        }
+    }
+
+    fn discard_preloaded(&mut self) {
+        if let Some(preloaded) = self.preloaded.take() {
+            preloaded.control.cancel();
+        }
+        self.preload_failed = false;
+    }
+
+    /// The queued track may no longer be the next one after the playlist or
+    /// the playback mode changed, it is queued again on the next tick.
+    fn invalidate_preloaded(&mut self) -> Result<()> {
+        self.advance_to_preloaded()?;
+        self.discard_preloaded();
+
+        Ok(())
+    }
+
     fn play(&mut self) -> Result<()> {
         if let Some(sink) = &self.sink {
             sink.play();
+            if let Some(fading_sink) = &self.fading_sink {
+                fading_sink.play();
+            }
             info!("Playback started");
 
             if let Some(track_index) = self.current_track_index {
@@ -495,6 +974,10 @@ impl PlayerInternal {
     fn pause(&mut self) -> Result<()> {
         if let Some(sink) = &self.sink {
             sink.pause();
+            // The crossfade resumes where it was
+            if let Some(fading_sink) = &self.fading_sink {
+                fading_sink.pause();
+            }
             info!("Playback paused");
 
             let position = sink.get_pos();
@@ -515,6 +998,9 @@ impl PlayerInternal {
     }
 
     fn stop(&mut self) -> Result<()> {
+        self.discard_preloaded();
+        self.stop_fading_sink();
+
         if let Some(sink) = self.sink.take() {
             sink.stop();
             info!("Playback stopped");
@@ -599,6 +1085,12 @@ impl PlayerInternal {
     }
 
     fn seek(&mut self, position: f64) -> Result<()> {
+        // Seeking away from the start of a track leaves nothing to fade into
+        if self.fading_sink.is_some() {
+            self.stop_fading_sink();
+            self.crossfade_control.finish();
+        }
+
         if let Some(sink) = &self.sink {
             match sink.try_seek(std::time::Duration::from_secs_f64(position)) {
                 Ok(_) => {
@@ -685,6 +1177,10 @@ impl PlayerInternal {
                 }));
         }
 
+        if let Err(e) = self.invalidate_preloaded() {
+            error!("Failed to invalidate preloaded track: {e:#?}");
+        }
+        self.refresh_strategy_weights();
         self.playback_strategy.on_playlist_updated(
             self.playlist.len(),
             UpdateReason::AddToPlaylist {
@@ -698,7 +1194,9 @@ impl PlayerInternal {
     fn remove_from_playlist(&mut self, index: usize) -> Result<()> {
         if index < self.playlist.len() {
             debug!("Removing from playlist at index: {}", { index });
+            self.invalidate_preloaded()?;
             self.playlist.remove(index);
+            self.refresh_strategy_weights();
             self.playback_strategy.on_playlist_updated(
                 self.playlist.len(),
                 UpdateReason::RemoveFromPlaylist { index },
@@ -715,12 +1213,16 @@ impl PlayerInternal {
     }
 
     fn clear_playlist(&mut self) -> Result<()> {
+        self.set_audition(None)?;
+        self.discard_preloaded();
+        self.stop_fading_sink();
         self.playlist.clear();
         self.playback_strategy
             .on_playlist_updated(0, UpdateReason::ClearPlaylist);
         self.current_track_index = None;
         self.sink = None;
         self._stream = None;
+        self.stream_handle = None;
         info!("Playlist cleared");
         self.event_sender
             .send(PlayerEvent::Stopped)
@@ -732,25 +1234,83 @@ impl PlayerInternal {
     }
 
     fn set_playback_mode(&mut self, mode: PlaybackMode) -> Result<()> {
-        self.playback_mode = mode;
-        self.playback_strategy = match mode {
-            PlaybackMode::Sequential => Box::new(SequentialStrategy),
-            PlaybackMode::RepeatOne => Box::new(RepeatOneStrategy),
-            PlaybackMode::RepeatAll => Box::new(RepeatAllStrategy),
-            PlaybackMode::Shuffle => Box::new(ShuffleStrategy::new(self.playlist.len())),
+        let (repeat_mode, shuffle_mode) = mode.order();
+        self.set_playback_order(repeat_mode, shuffle_mode)
+    }
+
+    fn set_repeat_mode(&mut self, mode: RepeatMode) -> Result<()> {
+        self.set_playback_order(mode, self.shuffle_mode)
+    }
+
+    fn set_shuffle_mode(&mut self, mode: ShuffleMode) -> Result<()> {
+        self.set_playback_order(self.repeat_mode, mode)
+    }
+
+    fn set_playback_order(
+        &mut self,
+        repeat_mode: RepeatMode,
+        shuffle_mode: ShuffleMode,
+    ) -> Result<()> {
+        self.invalidate_preloaded()?;
+        self.repeat_mode = repeat_mode;
+        self.shuffle_mode = shuffle_mode;
+        self.playback_mode = PlaybackMode::from_order(repeat_mode, shuffle_mode);
+        self.playback_strategy = match (shuffle_mode, repeat_mode) {
+            (ShuffleMode::Off, RepeatMode::Off) => Box::new(SequentialStrategy),
+            (ShuffleMode::Off, RepeatMode::One) => Box::new(RepeatOneStrategy),
+            (ShuffleMode::Off, RepeatMode::All) => Box::new(RepeatAllStrategy),
+            (ShuffleMode::Uniform, _) => {
+                Box::new(ShuffleStrategy::new(self.playlist.len(), repeat_mode, None))
+            }
+            (ShuffleMode::Weighted, _) => Box::new(ShuffleStrategy::new(
+                self.playlist.len(),
+                repeat_mode,
+                Some(self.shuffle_weight_list()),
+            )),
         };
+        self.event_sender
+            .send(PlayerEvent::PlaybackOrderUpdate {
+                repeat_mode,
+                shuffle_mode,
+            })
+            .with_context(|| "Failed to send PlaybackOrderUpdate event")?;
         self.send_progress()?;
-        info!("Playback mode set to {:?}", { mode });
+        info!("Playback order set to {repeat_mode:?} repeat, {shuffle_mode:?} shuffle");
 
         Ok(())
     }
 
+    fn update_shuffle_weights(&mut self, weights: HashMap<PlayingItem, f64>) -> Result<()> {
+        self.shuffle_weights = weights;
+        self.refresh_strategy_weights();
+
+        Ok(())
+    }
+
+    /// Weights of the playlist items in playlist order.
+    fn shuffle_weight_list(&self) -> Vec<f64> {
+        self.playlist
+            .iter()
+            .map(|x| self.shuffle_weights.get(&x.item).copied().unwrap_or(1.0))
+            .collect()
+    }
+
+    fn refresh_strategy_weights(&mut self) {
+        let weights = self.shuffle_weight_list();
+        self.playback_strategy.update_weights(weights);
+    }
+
     fn get_mapped_track_index(&self, index: usize) -> usize {
         self.playback_strategy
             .get_mapped_track_index(index, self.playlist.len())
     }
 
     fn send_progress(&mut self) -> Result<()> {
+        self.advance_to_preloaded()?;
+        self.finish_crossfade();
+        self.start_crossfade()?;
+        self.preload_next()?;
+
         let id = self.current_item.clone();
         let index = self.current_track_index;
         let index = index.map(|x| self.get_mapped_track_index(x));
@@ -814,10 +1374,15 @@ impl PlayerInternal {
 
         debug!("Moving playlist item from index {old_index} to index {new_index}");
 
+        if let Err(e) = self.invalidate_preloaded() {
+            error!("Failed to invalidate preloaded track: {e:#?}");
+        }
+
         let item = self.playlist.remove(old_index);
         self.playlist.insert(new_index, item);
 
         // Update the playback strategy to reflect changes in the playlist
+        self.refresh_strategy_weights();
         self.playback_strategy.on_playlist_updated(
             self.playlist.len(),
             UpdateReason::MovePlaylistItem {
@@ -847,6 +1412,21 @@ impl PlayerInternal {
         self.debounce_timer = Some(Instant::now() + Duration::from_millis(100));
     }
 
+    /// Drop the measurements of tracks that left the queue, the host sends
+    /// them again for every track it queues.
+    fn forget_dequeued_tracks(&mut self) {
+        let queued: HashSet<&PlayingItem> = self
+            .playlist
+            .iter()
+            .map(|x| &x.item)
+            .chain(self.current_item.as_ref())
+            .collect();
+
+        self.replay_gain_info.retain(|item, _| queued.contains(item));
+        self.seek_tables.retain(|item, _| queued.contains(item));
+        self.track_trims.retain(|item, _| queued.contains(item));
+    }
+
     fn send_playlist_updated(&self) -> Result<()> {
         let playlist_items: Vec<PlayingItem> =
             self.playlist.iter().map(|item| item.item.clone()).collect();
@@ -857,11 +1437,37 @@ impl PlayerInternal {
         Ok(())
     }
 
+    fn set_output_device(&mut self, name: Option<String>) -> Result<()> {
+        if self.output_device == name {
+            return Ok(());
+        }
+        info!("Output device changed: {name:?}");
+        self.output_device = name;
+
+        // The stream is bound to its device, so the loaded track is opened
+        // again on the new one where it left off
+        let (Some(index), Some(sink)) = (self.current_track_index, &self.sink) else {
+            return Ok(());
+        };
+        let position = sink.get_pos();
+        let playing = self.state == InternalPlaybackState::Playing;
+
+        self.load(Some(index), playing, true)?;
+        if !position.is_zero() {
+            self.seek(position.as_secs_f64())?;
+        }
+
+        Ok(())
+    }
+
     fn set_volume(&mut self, volume: f32) -> Result<()> {
         self.volume = volume;
         if let Some(sink) = &self.sink {
             sink.set_volume(volume);
         }
+        if let Some(fading_sink) = &self.fading_sink {
+            fading_sink.set_volume(volume);
+        }
         self.event_sender
             .send(PlayerEvent::VolumeUpdate(volume))
             .with_context(|| "Failed to send VolumeUpdate event")?;
@@ -884,4 +1490,222 @@ impl PlayerInternal {
 
         Ok(())
     }
+
+    fn set_gapless(&mut self, x: bool) -> Result<()> {
+        self.gapless = x;
+        if !x {
+            self.invalidate_preloaded()?;
+        }
+
+        info!("Gapless playback status changed: {x}");
+
+        Ok(())
+    }
+
+    fn set_crossfade(&mut self, duration: Duration) -> Result<()> {
+        // Kept for when the audition ends
+        if let Some(audition) = &mut self.audition {
+            audition.crossfade = duration.min(MAX_CROSSFADE);
+            return Ok(());
+        }
+
+        self.crossfade = duration.min(MAX_CROSSFADE);
+        if !self.crossfade.is_zero() {
+            self.invalidate_preloaded()?;
+        }
+
+        info!("Crossfade duration changed: {:?}", self.crossfade);
+
+        Ok(())
+    }
+
+    fn set_night_mode(&mut self, config: NightModeConfig) -> Result<()> {
+        self.night_mode = config;
+        self.refresh_night_mode();
+
+        Ok(())
+    }
+
+    fn refresh_night_mode(&mut self) {
+        let active = self.night_mode.is_active_at(Local::now().hour());
+        if active != self.night_mode_control.is_active() {
+            info!("Night mode status changed: {active}");
+        }
+
+        self.night_mode_control.update(&self.night_mode, active);
+    }
+
+    fn set_equalizer(&mut self, config: EqualizerConfig) -> Result<()> {
+        self.equalizer = config;
+        // Every open sink shares the control, so the change is heard at once
+        self.equalizer_control.update(&self.equalizer);
+
+        info!(
+            "Equalizer changed: {} (enabled: {})",
+            config.preset, config.enabled
+        );
+
+        Ok(())
+    }
+
+    fn set_hearing_protection(&mut self, config: HearingProtectionConfig) -> Result<()> {
+        self.hearing_protection = config;
+
+        Ok(())
+    }
+
+    fn restore_exposure(&mut self, status: ExposureStatus) -> Result<()> {
+        if status.date != Some(Local::now().date_naive()) {
+            return Ok(());
+        }
+
+        self.exposure = ListeningExposure::restore(&status);
+        self.event_sender
+            .send(PlayerEvent::ExposureUpdate(self.exposure.status()))
+            .with_context(|| "Failed to send ExposureUpdate event")?;
+
+        Ok(())
+    }
+
+    fn set_replay_gain(&mut self, config: ReplayGainConfig) -> Result<()> {
+        // Kept for when the audition ends
+        if let Some(audition) = &mut self.audition {
+            audition.replay_gain = config;
+            return Ok(());
+        }
+
+        self.replay_gain = config;
+        info!("ReplayGain mode changed: {}", config.mode);
+
+        self.apply_replay_gain(self.current_item.as_ref());
+
+        Ok(())
+    }
+
+    fn update_replay_gain_info(
+        &mut self,
+        info: HashMap<PlayingItem, ReplayGainInfo>,
+    ) -> Result<()> {
+        self.replay_gain_info.extend(info);
+
+        // The measurements may arrive after the track has been loaded
+        self.apply_replay_gain(self.current_item.as_ref());
+
+        Ok(())
+    }
+
+    fn update_seek_tables(&mut self, tables: HashMap<PlayingItem, Arc<SeekTable>>) -> Result<()> {
+        // Tracks that are already open keep seeking the way they were opened
+        self.seek_tables.extend(tables);
+
+        Ok(())
+    }
+
+    fn update_track_trims(&mut self, trims: HashMap<PlayingItem, TrackTrim>) -> Result<()> {
+        for (item, trim) in trims {
+            if trim.is_empty() {
+                self.track_trims.remove(&item);
+            } else {
+                self.track_trims.insert(item, trim);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_audition(&mut self, config: Option<AuditionConfig>) -> Result<()> {
+        let previous = self.audition.take();
+        let (crossfade, replay_gain) = match &previous {
+            Some(audition) => (audition.crossfade, audition.replay_gain),
+            None => (self.crossfade, self.replay_gain),
+        };
+
+        match config {
+            Some(config) => {
+                self.audition = Some(Audition {
+                    config,
+                    crossfade,
+                    replay_gain,
+                });
+                self.crossfade = config.crossfade.min(MAX_CROSSFADE);
+                self.replay_gain = config.replay_gain();
+                info!("Mix audition started: {config:?}");
+            }
+            None if previous.is_some() => {
+                self.crossfade = crossfade;
+                self.replay_gain = replay_gain;
+                info!("Mix audition ended");
+            }
+            None => return Ok(()),
+        }
+
+        // The queued track was opened with the previous trim and crossfade
+        self.invalidate_preloaded()?;
+        self.apply_replay_gain(self.current_item.as_ref());
+
+        Ok(())
+    }
+
+    fn replay_gain_db(&self, item: Option<&PlayingItem>) -> f32 {
+        item.and_then(|x| self.replay_gain_info.get(x))
+            .map(|x| x.gain_db(&self.replay_gain))
+            .unwrap_or(0.0)
+    }
+
+    fn apply_replay_gain(&self, item: Option<&PlayingItem>) {
+        self.replay_gain_control
+            .set_gain_db(self.replay_gain_db(item));
+
+        if let Some(preloaded) = &self.preloaded {
+            preloaded
+                .replay_gain_control
+                .set_gain_db(self.replay_gain_db(Some(&preloaded.item)));
+        }
+    }
+
+    fn refresh_exposure(&mut self) -> Result<()> {
+        let (seconds, mean_square) = self.exposure_accumulator.take();
+        let today = Local::now().date_naive();
+
+        let previous = self.exposure.status();
+        let level_db = mean_square_to_spl(
+            mean_square,
+            self.volume,
+            self.hearing_protection.full_scale_spl_db,
+        );
+        self.exposure.add(today, seconds, level_db);
+
+        let status = self.exposure.status();
+        if status == previous {
+            return Ok(());
+        }
+
+        if status.warning > previous.warning {
+            warn!(
+                "Listening exposure warning: {} ({:.0}% of the daily allowance)",
+                status.warning,
+                status.dose * 100.0
+            );
+        }
+
+        // Only reduce once a day, so the user can still turn the volume back up
+        if self.hearing_protection.auto_reduce
+            && status.dose >= 1.0
+            && self.volume_reduced_on != Some(today)
+        {
+            self.volume_reduced_on = Some(today);
+
+            let reduced_volume = self.hearing_protection.reduced_volume.clamp(0.0, 1.0);
+            if self.volume > reduced_volume {
+                info!("Daily listening allowance used up, reducing volume to {reduced_volume}");
+                self.set_volume(reduced_volume)?;
+            }
+        }
+
+        self.event_sender
+            .send(PlayerEvent::ExposureUpdate(status))
+            .with_context(|| "Failed to send ExposureUpdate event")?;
+
+        Ok(())
+    }
 }
@@ -1,14 +1,26 @@
+mod control;
 mod internal;
 mod realtime_fft;
 mod sfx_internal;
 mod shared_source;
 
+pub mod audition;
 pub mod buffered;
 pub mod controller;
+pub mod crossfade;
+pub mod dsp;
+pub mod equalizer;
+pub mod exposure;
+pub mod gapless;
+pub mod night_mode;
 pub mod output_stream;
 pub mod player;
+pub mod prefetch;
+pub mod replay_gain;
+pub mod seek_table;
 pub mod sfx_player;
 pub mod strategies;
+pub mod trim;
 
 #[cfg(target_os = "android")]
 mod dummy_souvlaki;
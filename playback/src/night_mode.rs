@@ -0,0 +1,351 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+use crate::control::{AtomicF32, CONTROL_REFRESH_SAMPLES};
+
+/// Settings of the night mode, which tames loud passages and caps the peak
+/// level during the configured hours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NightModeConfig {
+    pub enabled: bool,
+    /// Local hour (0-23) the night mode starts at.
+    pub start_hour: u32,
+    /// Local hour (0-23) the night mode ends at, may be earlier than
+    /// `start_hour` to wrap around midnight.
+    pub end_hour: u32,
+    /// Level above which the compressor kicks in, in dBFS.
+    pub threshold_db: f32,
+    /// Compression ratio above the threshold, e.g. `4.0` for 4:1.
+    pub ratio: f32,
+    /// Gain applied after compression to bring quiet passages up, in dB.
+    pub makeup_db: f32,
+    /// Hard ceiling of the output level, in dBFS.
+    pub ceiling_db: f32,
+}
+
+impl Default for NightModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_hour: 22,
+            end_hour: 7,
+            threshold_db: -24.0,
+            ratio: 4.0,
+            makeup_db: 6.0,
+            ceiling_db: -6.0,
+        }
+    }
+}
+
+impl NightModeConfig {
+    /// Whether the night mode should be applied at the given local hour.
+    /// Equal start and end hours cover the whole day.
+    pub fn is_active_at(&self, hour: u32) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if self.start_hour == self.end_hour {
+            true
+        } else if self.start_hour < self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+/// Parameters shared between the player thread and the audio thread.
+#[derive(Debug, Default)]
+pub struct NightModeControl {
+    active: AtomicBool,
+    threshold_db: AtomicF32,
+    ratio: AtomicF32,
+    makeup_db: AtomicF32,
+    ceiling_db: AtomicF32,
+}
+
+impl NightModeControl {
+    pub fn update(&self, config: &NightModeConfig, active: bool) {
+        self.threshold_db.store(config.threshold_db);
+        self.ratio.store(config.ratio.max(1.0));
+        self.makeup_db.store(config.makeup_db);
+        self.ceiling_db.store(config.ceiling_db.min(0.0));
+        self.active.store(active, Ordering::Relaxed);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn load(&self) -> Option<CompressorParams> {
+        if !self.is_active() {
+            return None;
+        }
+
+        Some(CompressorParams {
+            threshold_db: self.threshold_db.load(),
+            ratio: self.ratio.load(),
+            makeup: db_to_amplitude(self.makeup_db.load()),
+            ceiling: db_to_amplitude(self.ceiling_db.load()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CompressorParams {
+    threshold_db: f32,
+    ratio: f32,
+    makeup: f32,
+    ceiling: f32,
+}
+
+const ATTACK_SECONDS: f32 = 0.005;
+const RELEASE_SECONDS: f32 = 0.25;
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+fn amplitude_to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.max(1e-6).log10()
+}
+
+/// Static curve of the compressor, the gain for a level in dBFS.
+fn compressor_gain_db(level_db: f32, threshold_db: f32, ratio: f32) -> f32 {
+    if level_db > threshold_db {
+        (threshold_db - level_db) * (1.0 - 1.0 / ratio)
+    } else {
+        0.0
+    }
+}
+
+/// A source applying a stereo-linked feed-forward compressor followed by a
+/// hard ceiling, bypassed whenever the night mode is inactive.
+pub struct NightMode<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    input: I,
+    control: Arc<NightModeControl>,
+    params: Option<CompressorParams>,
+    envelope: f32,
+    attack: f32,
+    release: f32,
+    samples_until_refresh: usize,
+}
+
+pub fn night_mode<I>(input: I, control: Arc<NightModeControl>) -> NightMode<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    // Samples are interleaved, so the time constants count every channel
+    let rate = (input.sample_rate() * input.channels().max(1) as u32) as f32;
+
+    NightMode {
+        input,
+        params: control.load(),
+        control,
+        envelope: 0.0,
+        attack: (-1.0 / (ATTACK_SECONDS * rate)).exp(),
+        release: (-1.0 / (RELEASE_SECONDS * rate)).exp(),
+        samples_until_refresh: CONTROL_REFRESH_SAMPLES,
+    }
+}
+
+impl<I> NightMode<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    fn follow(&mut self, x: f32) {
+        let level = x.abs();
+        let coefficient = if level > self.envelope {
+            self.attack
+        } else {
+            self.release
+        };
+        self.envelope = coefficient * self.envelope + (1.0 - coefficient) * level;
+    }
+
+    fn process(&mut self, x: f32, params: CompressorParams) -> f32 {
+        self.follow(x);
+
+        let gain_db = compressor_gain_db(
+            amplitude_to_db(self.envelope),
+            params.threshold_db,
+            params.ratio,
+        );
+
+        let y = x * db_to_amplitude(gain_db) * params.makeup;
+        y.clamp(-params.ceiling, params.ceiling)
+    }
+}
+
+impl<I> Iterator for NightMode<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?.to_f32();
+
+        if self.samples_until_refresh == 0 {
+            self.params = self.control.load();
+            self.samples_until_refresh = CONTROL_REFRESH_SAMPLES;
+        }
+        self.samples_until_refresh -= 1;
+
+        match self.params {
+            Some(params) => Some(self.process(sample, params)),
+            None => {
+                // Keep following the signal so that enabling the night mode
+                // mid-track does not start from silence
+                self.follow(sample);
+                Some(sample)
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for NightMode<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.envelope = 0.0;
+        self.input.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    fn config(start_hour: u32, end_hour: u32) -> NightModeConfig {
+        NightModeConfig {
+            enabled: true,
+            start_hour,
+            end_hour,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_disabled_is_never_active() {
+        let config = NightModeConfig {
+            enabled: false,
+            ..config(0, 0)
+        };
+
+        assert!((0..24).all(|hour| !config.is_active_at(hour)));
+    }
+
+    #[test]
+    fn test_window_within_a_day() {
+        let config = config(9, 17);
+
+        assert!(!config.is_active_at(8));
+        assert!(config.is_active_at(9));
+        assert!(config.is_active_at(16));
+        assert!(!config.is_active_at(17));
+        assert!(!config.is_active_at(23));
+    }
+
+    #[test]
+    fn test_window_across_midnight() {
+        let config = config(22, 7);
+
+        assert!(!config.is_active_at(21));
+        assert!(config.is_active_at(22));
+        assert!(config.is_active_at(23));
+        assert!(config.is_active_at(0));
+        assert!(config.is_active_at(6));
+        assert!(!config.is_active_at(7));
+        assert!(!config.is_active_at(12));
+    }
+
+    #[test]
+    fn test_equal_hours_cover_the_whole_day() {
+        let config = config(3, 3);
+
+        assert!((0..24).all(|hour| config.is_active_at(hour)));
+    }
+
+    #[test]
+    fn test_compressor_gain_curve() {
+        // Nothing happens up to the threshold
+        assert_eq!(compressor_gain_db(-30.0, -24.0, 4.0), 0.0);
+        assert_eq!(compressor_gain_db(-24.0, -24.0, 4.0), 0.0);
+        // 12 dB over the threshold at 4:1 leaves 3 dB over it
+        assert!((compressor_gain_db(-12.0, -24.0, 4.0) + 9.0).abs() < 1e-5);
+        // 2:1 halves the overshoot
+        assert!((compressor_gain_db(0.0, -20.0, 2.0) + 10.0).abs() < 1e-5);
+        // 1:1 does not compress at all
+        assert_eq!(compressor_gain_db(0.0, -24.0, 1.0), 0.0);
+    }
+
+    fn render(samples: Vec<f32>, active: bool) -> Vec<f32> {
+        let control = Arc::new(NightModeControl::default());
+        control.update(&NightModeConfig::default(), active);
+
+        night_mode(SamplesBuffer::new(1, 44100, samples), control).collect()
+    }
+
+    #[test]
+    fn test_inactive_night_mode_passes_through() {
+        let input: Vec<f32> = (0..4410).map(|x| (x as f32 * 0.05).sin()).collect();
+
+        assert_eq!(render(input.clone(), false), input);
+    }
+
+    #[test]
+    fn test_loud_signal_stays_below_the_ceiling() {
+        let ceiling = db_to_amplitude(NightModeConfig::default().ceiling_db);
+        let output = render(vec![1.0; 44100], true);
+
+        assert!(output.iter().all(|x| x.abs() <= ceiling + 1e-6));
+        // Once the envelope settled, the gain follows the static curve
+        let settled = output.last().unwrap();
+        let expected = db_to_amplitude(compressor_gain_db(0.0, -24.0, 4.0) + 6.0);
+        assert!((settled - expected.min(ceiling)).abs() < 1e-3);
+    }
+}
@@ -1,5 +1,6 @@
 use std::sync::{Arc, Weak};
 
+use log::warn;
 use rodio::cpal::traits::{HostTrait, StreamTrait};
 use rodio::cpal::Sample;
 use rodio::dynamic_mixer::{self, DynamicMixerController};
@@ -9,9 +10,58 @@ use rodio::{PlayError, StreamError};
 
 pub struct RuneOutputStream {
     mixer: Arc<DynamicMixerController<f32>>,
+    status: OutputStatus,
     _stream: cpal::Stream,
 }
 
+/// The device and format an output stream actually plays through, which may
+/// differ from the requested ones after a fallback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputStatus {
+    pub device: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Bits per sample of the format the device is fed.
+    pub bit_depth: u16,
+    pub is_float: bool,
+}
+
+impl OutputStatus {
+    fn new(device: &cpal::Device, config: &SupportedStreamConfig) -> Self {
+        let sample_format = config.sample_format();
+        Self {
+            device: device.name().unwrap_or_default(),
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+            bit_depth: (sample_format.sample_size() * 8) as u16,
+            is_float: sample_format.is_float(),
+        }
+    }
+}
+
+/// An audio output device as reported by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputDevice {
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// The output devices of the default host. Devices whose name can't be read
+/// are left out, since they couldn't be selected again later.
+pub fn list_output_devices() -> Result<Vec<OutputDevice>, cpal::DevicesError> {
+    let host = cpal::default_host();
+    let default_name = host.default_output_device().and_then(|x| x.name().ok());
+
+    Ok(host
+        .output_devices()?
+        .filter_map(|x| x.name().ok())
+        .map(|name| OutputDevice {
+            is_default: default_name.as_ref() == Some(&name),
+            name,
+        })
+        .collect())
+}
+
 #[derive(Clone)]
 pub struct RuneOutputStreamHandle {
     mixer: Weak<DynamicMixerController<f32>>,
@@ -43,16 +93,25 @@ impl RuneOutputStream {
     where
         E: FnMut(cpal::StreamError) + Send + 'static + Clone,
     {
-        let (mixer, _stream) =
+        let (mixer, _stream, config) =
             device.try_new_output_stream_config_with_callback(config, error_callback)?;
         _stream.play().map_err(StreamError::PlayStreamError)?;
-        let out = Self { mixer, _stream };
+        let out = Self {
+            mixer,
+            status: OutputStatus::new(device, &config),
+            _stream,
+        };
         let handle = RuneOutputStreamHandle {
             mixer: Arc::downgrade(&out.mixer),
         };
         Ok((out, handle))
     }
 
+    /// The device and format the stream plays through.
+    pub fn status(&self) -> &OutputStatus {
+        &self.status
+    }
+
     pub fn try_default_with_callback<E>(
         error_callback: E,
     ) -> Result<(Self, RuneOutputStreamHandle), StreamError>
@@ -77,6 +136,38 @@ impl RuneOutputStream {
                 .ok_or(original_err)
         })
     }
+
+    /// Open the output device called `name`, falling back to the default
+    /// device if it is missing or fails, so playback never stops because a
+    /// DAC was unplugged.
+    pub fn try_named_with_callback<E>(
+        name: Option<&str>,
+        error_callback: E,
+    ) -> Result<(Self, RuneOutputStreamHandle), StreamError>
+    where
+        E: FnMut(cpal::StreamError) + Send + 'static + Clone,
+    {
+        let Some(name) = name else {
+            return Self::try_default_with_callback(error_callback);
+        };
+
+        let device = cpal::default_host()
+            .output_devices()
+            .ok()
+            .and_then(|mut devices| devices.find(|x| x.name().is_ok_and(|x| x == name)));
+
+        match device {
+            Some(device) => Self::try_from_device_with_callback(&device, error_callback.clone())
+                .or_else(|e| {
+                    warn!("Failed to open output device {name}: {e}, using the default device");
+                    Self::try_default_with_callback(error_callback)
+                }),
+            None => {
+                warn!("Output device {name} not found, using the default device");
+                Self::try_default_with_callback(error_callback)
+            }
+        }
+    }
 }
 
 impl RuneOutputStreamHandle {
@@ -99,11 +190,21 @@ pub(crate) trait CpalDeviceExt {
     where
         E: FnMut(cpal::StreamError) + Send + 'static + Clone;
 
+    /// Like [`CpalDeviceExt::new_output_stream_with_format_and_callback`],
+    /// trying the other supported formats if `config` fails. Returns the
+    /// format the stream was built with.
     fn try_new_output_stream_config_with_callback<E>(
         &self,
         config: cpal::SupportedStreamConfig,
         error_callback: E,
-    ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), StreamError>
+    ) -> Result<
+        (
+            Arc<DynamicMixerController<f32>>,
+            cpal::Stream,
+            cpal::SupportedStreamConfig,
+        ),
+        StreamError,
+    >
     where
         E: FnMut(cpal::StreamError) + Send + 'static + Clone;
 }
@@ -236,19 +337,28 @@ impl CpalDeviceExt for cpal::Device {
         &self,
         config: SupportedStreamConfig,
         error_callback: E,
-    ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), StreamError>
+    ) -> Result<
+        (
+            Arc<DynamicMixerController<f32>>,
+            cpal::Stream,
+            SupportedStreamConfig,
+        ),
+        StreamError,
+    >
     where
         E: FnMut(cpal::StreamError) + Send + 'static + Clone,
     {
-        self.new_output_stream_with_format_and_callback(config, error_callback.clone())
+        self.new_output_stream_with_format_and_callback(config.clone(), error_callback.clone())
+            .map(|(mixer, stream)| (mixer, stream, config))
             .or_else(|err| {
                 supported_output_formats(self)?
                     .find_map(|format| {
                         self.new_output_stream_with_format_and_callback(
-                            format,
+                            format.clone(),
                             error_callback.clone(),
                         )
                         .ok()
+                        .map(|(mixer, stream)| (mixer, stream, format))
                     })
                     .ok_or(StreamError::BuildStreamError(err))
             })
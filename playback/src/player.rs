@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -8,8 +9,16 @@ use simple_channel::{SimpleChannel, SimpleReceiver, SimpleSender};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
+use crate::audition::AuditionConfig;
+use crate::equalizer::EqualizerConfig;
+use crate::exposure::{ExposureStatus, HearingProtectionConfig};
 use crate::internal::{InternalLog, PlaybackMode, PlayerCommand, PlayerEvent, PlayerInternal};
-use crate::strategies::AddMode;
+use crate::night_mode::NightModeConfig;
+use crate::output_stream::OutputStatus;
+use crate::replay_gain::{ReplayGainConfig, ReplayGainInfo};
+use crate::seek_table::SeekTable;
+use crate::strategies::{AddMode, RepeatMode, ShuffleMode};
+use crate::trim::TrackTrim;
 
 #[derive(Debug, Clone)]
 pub struct PlayerStatus {
@@ -20,8 +29,13 @@ pub struct PlayerStatus {
     pub state: PlaybackState,
     pub playlist: Vec<PlayingItem>,
     pub playback_mode: PlaybackMode,
+    pub repeat_mode: RepeatMode,
+    pub shuffle_mode: ShuffleMode,
     pub ready: bool,
     pub volume: f32,
+    pub exposure: ExposureStatus,
+    /// The device and format playing the loaded track, `None` when stopped.
+    pub output: Option<OutputStatus>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -59,6 +73,14 @@ pub struct PlaylistStatus {
     pub items: Vec<PlayingItem>,
 }
 
+/// A track that couldn't be played and was skipped.
+#[derive(Debug, Clone)]
+pub struct PlaybackError {
+    pub item: PlayingItem,
+    pub path: PathBuf,
+    pub error: String,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PlaybackState {
     Playing,
@@ -91,9 +113,26 @@ pub trait Playable: Send {
     fn clear_playlist(&self);
     fn move_playlist_item(&self, old_index: usize, new_index: usize);
     fn set_playback_mode(&mut self, mode: PlaybackMode);
+    fn set_repeat_mode(&mut self, mode: RepeatMode);
+    fn set_shuffle_mode(&mut self, mode: ShuffleMode);
+    fn update_shuffle_weights(&self, weights: HashMap<PlayingItem, f64>);
     fn set_volume(&mut self, volume: f32);
+    fn set_output_device(&mut self, name: Option<String>);
     fn set_realtime_fft_enabled(&mut self, enabled: bool);
     fn set_adaptive_switching_enabled(&mut self, enabled: bool);
+    fn set_gapless_enabled(&mut self, enabled: bool);
+    fn set_crossfade(&mut self, duration: Duration);
+    fn set_night_mode(&mut self, config: NightModeConfig);
+    fn set_equalizer(&mut self, config: EqualizerConfig);
+    fn set_hearing_protection(&mut self, config: HearingProtectionConfig);
+    /// Continue the listening exposure saved before a restart, ignored if
+    /// it belongs to an earlier day.
+    fn restore_exposure(&mut self, status: ExposureStatus);
+    fn set_replay_gain(&mut self, config: ReplayGainConfig);
+    fn update_replay_gain_info(&self, info: HashMap<PlayingItem, ReplayGainInfo>);
+    fn update_seek_tables(&self, tables: HashMap<PlayingItem, Arc<SeekTable>>);
+    fn update_track_trims(&self, trims: HashMap<PlayingItem, TrackTrim>);
+    fn set_audition(&mut self, config: Option<AuditionConfig>);
     fn terminate(&self);
     fn get_status(&self) -> PlayerStatus;
     fn get_playlist(&self) -> Vec<PlayingItem>;
@@ -102,6 +141,8 @@ pub trait Playable: Send {
     fn subscribe_playlist(&self) -> SimpleReceiver<PlaylistStatus>;
     fn subscribe_realtime_fft(&self) -> SimpleReceiver<Vec<f32>>;
     fn subscribe_crash(&self) -> SimpleReceiver<String>;
+    fn subscribe_playback_error(&self) -> SimpleReceiver<PlaybackError>;
+    fn subscribe_exposure(&self) -> SimpleReceiver<ExposureStatus>;
     fn subscribe_log(&self) -> SimpleReceiver<InternalLog>;
 }
 
@@ -114,7 +155,9 @@ pub struct Player {
     played_through_sender: SimpleSender<PlayingItem>,
     log_sender: SimpleSender<InternalLog>,
     realtime_fft_sender: SimpleSender<Vec<f32>>,
+    exposure_sender: SimpleSender<ExposureStatus>,
     crash_sender: SimpleSender<String>,
+    playback_error_sender: SimpleSender<PlaybackError>,
     cancellation_token: CancellationToken,
 }
 
@@ -139,8 +182,12 @@ impl Player {
         let (playlist_sender, _) = SimpleChannel::channel(16);
         // Create a broadcast channel for realtime FFT updates
         let (realtime_fft_sender, _) = SimpleChannel::channel(32);
+        // Create a broadcast channel for listening exposure updates
+        let (exposure_sender, _) = SimpleChannel::channel(16);
         // Create a broadcast channel player crash report
         let (crash_sender, _) = SimpleChannel::channel(16);
+        // Create a broadcast channel for tracks that failed to play
+        let (playback_error_sender, _) = SimpleChannel::channel(16);
         let (log_sender, _) = SimpleChannel::channel(16);
 
         // Create a cancellation token
@@ -154,9 +201,13 @@ impl Player {
             position: Duration::new(0, 0),
             state: PlaybackState::Stopped,
             playback_mode: PlaybackMode::Sequential,
+            repeat_mode: RepeatMode::Off,
+            shuffle_mode: ShuffleMode::Off,
             playlist: Vec::new(),
             ready: false,
             volume: 1.0,
+            exposure: ExposureStatus::default(),
+            output: None,
         }));
 
         let commands = Arc::new(Mutex::new(cmd_tx));
@@ -168,7 +219,9 @@ impl Player {
             playlist_sender: playlist_sender.clone(),
             played_through_sender: played_through_sender.clone(),
             realtime_fft_sender: realtime_fft_sender.clone(),
+            exposure_sender: exposure_sender.clone(),
             crash_sender: crash_sender.clone(),
+            playback_error_sender: playback_error_sender.clone(),
             log_sender: log_sender.clone(),
             cancellation_token: cancellation_token.clone(),
         };
@@ -247,6 +300,7 @@ impl Player {
                         status.path = None;
                         status.position = Duration::new(0, 0);
                         status.state = PlaybackState::Stopped;
+                        status.output = None;
                     }
                     PlayerEvent::Progress {
                         item,
@@ -288,6 +342,7 @@ impl Player {
                         error,
                     } => {
                         error!("Error at index {index}({item:?}): {path:?} - {error}");
+                        playback_error_sender.send(PlaybackError { item, path, error });
                     }
                     PlayerEvent::PlaylistUpdated(playlist) => {
                         status.playlist = playlist.clone();
@@ -302,6 +357,20 @@ impl Player {
                     PlayerEvent::VolumeUpdate(value) => {
                         status.volume = value;
                     }
+                    PlayerEvent::PlaybackOrderUpdate {
+                        repeat_mode,
+                        shuffle_mode,
+                    } => {
+                        status.repeat_mode = repeat_mode;
+                        status.shuffle_mode = shuffle_mode;
+                    }
+                    PlayerEvent::ExposureUpdate(value) => {
+                        status.exposure = value.clone();
+                        exposure_sender.send(value);
+                    }
+                    PlayerEvent::OutputUpdate(value) => {
+                        status.output = Some(value);
+                    }
                     PlayerEvent::Log(log) => {
                         log_sender.send(log);
                     }
@@ -382,10 +451,26 @@ impl Playable for Player {
         self.command(PlayerCommand::SetPlaybackMode(mode));
     }
 
+    fn set_repeat_mode(&mut self, mode: RepeatMode) {
+        self.command(PlayerCommand::SetRepeatMode(mode));
+    }
+
+    fn set_shuffle_mode(&mut self, mode: ShuffleMode) {
+        self.command(PlayerCommand::SetShuffleMode(mode));
+    }
+
+    fn update_shuffle_weights(&self, weights: HashMap<PlayingItem, f64>) {
+        self.command(PlayerCommand::UpdateShuffleWeights(weights));
+    }
+
     fn set_volume(&mut self, volume: f32) {
         self.command(PlayerCommand::SetVolume(volume));
     }
 
+    fn set_output_device(&mut self, name: Option<String>) {
+        self.command(PlayerCommand::SetOutputDevice(name));
+    }
+
     fn set_realtime_fft_enabled(&mut self, enabled: bool) {
         self.command(PlayerCommand::SetRealtimeFFTEnabled(enabled));
     }
@@ -394,6 +479,50 @@ impl Playable for Player {
         self.command(PlayerCommand::SetAdaptiveSwitchingEnabled(enabled));
     }
 
+    fn set_gapless_enabled(&mut self, enabled: bool) {
+        self.command(PlayerCommand::SetGaplessEnabled(enabled));
+    }
+
+    fn set_crossfade(&mut self, duration: Duration) {
+        self.command(PlayerCommand::SetCrossfade(duration));
+    }
+
+    fn set_night_mode(&mut self, config: NightModeConfig) {
+        self.command(PlayerCommand::SetNightMode(config));
+    }
+
+    fn set_equalizer(&mut self, config: EqualizerConfig) {
+        self.command(PlayerCommand::SetEqualizer(config));
+    }
+
+    fn set_hearing_protection(&mut self, config: HearingProtectionConfig) {
+        self.command(PlayerCommand::SetHearingProtection(config));
+    }
+
+    fn restore_exposure(&mut self, status: ExposureStatus) {
+        self.command(PlayerCommand::RestoreExposure(status));
+    }
+
+    fn set_replay_gain(&mut self, config: ReplayGainConfig) {
+        self.command(PlayerCommand::SetReplayGain(config));
+    }
+
+    fn update_replay_gain_info(&self, info: HashMap<PlayingItem, ReplayGainInfo>) {
+        self.command(PlayerCommand::UpdateReplayGainInfo(info));
+    }
+
+    fn update_seek_tables(&self, tables: HashMap<PlayingItem, Arc<SeekTable>>) {
+        self.command(PlayerCommand::UpdateSeekTables(tables));
+    }
+
+    fn update_track_trims(&self, trims: HashMap<PlayingItem, TrackTrim>) {
+        self.command(PlayerCommand::UpdateTrackTrims(trims));
+    }
+
+    fn set_audition(&mut self, config: Option<AuditionConfig>) {
+        self.command(PlayerCommand::SetAudition(config));
+    }
+
     fn terminate(&self) {
         self.cancellation_token.cancel();
     }
@@ -426,6 +555,14 @@ impl Playable for Player {
         self.crash_sender.subscribe()
     }
 
+    fn subscribe_playback_error(&self) -> SimpleReceiver<PlaybackError> {
+        self.playback_error_sender.subscribe()
+    }
+
+    fn subscribe_exposure(&self) -> SimpleReceiver<ExposureStatus> {
+        self.exposure_sender.subscribe()
+    }
+
     fn subscribe_log(&self) -> SimpleReceiver<InternalLog> {
         self.log_sender.subscribe()
     }
@@ -447,9 +584,24 @@ impl Playable for MockPlayer {
     fn clear_playlist(&self) {}
     fn move_playlist_item(&self, _old_index: usize, _new_index: usize) {}
     fn set_playback_mode(&mut self, _mode: PlaybackMode) {}
+    fn set_repeat_mode(&mut self, _mode: RepeatMode) {}
+    fn set_shuffle_mode(&mut self, _mode: ShuffleMode) {}
+    fn update_shuffle_weights(&self, _weights: HashMap<PlayingItem, f64>) {}
     fn set_volume(&mut self, _volume: f32) {}
+    fn set_output_device(&mut self, _name: Option<String>) {}
     fn set_realtime_fft_enabled(&mut self, _enabled: bool) {}
     fn set_adaptive_switching_enabled(&mut self, _enabled: bool) {}
+    fn set_gapless_enabled(&mut self, _enabled: bool) {}
+    fn set_crossfade(&mut self, _duration: Duration) {}
+    fn set_night_mode(&mut self, _config: NightModeConfig) {}
+    fn set_equalizer(&mut self, _config: EqualizerConfig) {}
+    fn set_hearing_protection(&mut self, _config: HearingProtectionConfig) {}
+    fn restore_exposure(&mut self, _status: ExposureStatus) {}
+    fn set_replay_gain(&mut self, _config: ReplayGainConfig) {}
+    fn update_replay_gain_info(&self, _info: HashMap<PlayingItem, ReplayGainInfo>) {}
+    fn update_seek_tables(&self, _tables: HashMap<PlayingItem, Arc<SeekTable>>) {}
+    fn update_track_trims(&self, _trims: HashMap<PlayingItem, TrackTrim>) {}
+    fn set_audition(&mut self, _config: Option<AuditionConfig>) {}
     fn terminate(&self) {}
     fn get_status(&self) -> PlayerStatus {
         PlayerStatus {
@@ -460,8 +612,12 @@ impl Playable for MockPlayer {
             state: PlaybackState::Stopped,
             playlist: Vec::new(),
             playback_mode: PlaybackMode::Sequential,
+            repeat_mode: RepeatMode::Off,
+            shuffle_mode: ShuffleMode::Off,
             ready: false,
             volume: 1.0,
+            exposure: ExposureStatus::default(),
+            output: None,
         }
     }
     fn get_playlist(&self) -> Vec<PlayingItem> {
@@ -482,6 +638,12 @@ impl Playable for MockPlayer {
     fn subscribe_crash(&self) -> SimpleReceiver<String> {
         SimpleChannel::channel(1).1
     }
+    fn subscribe_playback_error(&self) -> SimpleReceiver<PlaybackError> {
+        SimpleChannel::channel(1).1
+    }
+    fn subscribe_exposure(&self) -> SimpleReceiver<ExposureStatus> {
+        SimpleChannel::channel(1).1
+    }
     fn subscribe_log(&self) -> SimpleReceiver<InternalLog> {
         SimpleChannel::channel(1).1
     }
@@ -0,0 +1,539 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use rodio::Source;
+use rodio::source::SeekError;
+use rtrb::{Consumer, Producer, RingBuffer};
+use thread_priority::{ThreadPriority, set_current_thread_priority};
+
+/// Audio decoded ahead of the output.
+const PREFETCH_AHEAD: Duration = Duration::from_millis(1500);
+
+/// Audio decoded before the output may start, after opening or seeking.
+const PREFETCH_PREFILL: Duration = Duration::from_millis(150);
+
+/// Longest silence waiting for the prefill before the output plays whatever
+/// is decoded.
+const PREFILL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Samples decoded between checks for commands.
+const DECODE_BATCH: usize = 4096;
+
+/// Pause of the decoder thread while the buffer is full.
+const IDLE_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Length of the frames reported to rodio, per channel. The format never
+/// changes within a track, so any length works.
+const FRAME_LEN: usize = 1024;
+
+enum PrefetchCommand {
+    Seek(Duration),
+    /// The output dropped everything decoded before the seek.
+    Resume,
+}
+
+/// Where the output is between opening or seeking and playing.
+#[derive(Debug, Clone, Copy)]
+enum SeekState {
+    Playing,
+    /// The decoder is seeking, `queued` is a later seek to send after it.
+    Seeking {
+        queued: Option<Duration>,
+    },
+    /// The buffer is filling up from the new position.
+    Prefilling {
+        since: Instant,
+    },
+}
+
+/// A decoder running on its own thread, ahead of the output.
+///
+/// The audio callback only pops samples from a lock-free ring buffer, so a
+/// busy runtime or a slow disk can't keep it waiting. When the buffer runs
+/// dry anyway, or while a seek or the prefill is pending, the output gets
+/// silence instead of blocking. Samples and silence always come in whole
+/// frames, so the channels never swap. The format of the start of the track
+/// is kept for the whole track.
+pub struct PrefetchedDecoder {
+    consumer: Consumer<i16>,
+    commands: Sender<PrefetchCommand>,
+    seek_results: Receiver<Result<(), SeekError>>,
+    /// Set once the decoder reached the end of the track.
+    finished: Arc<AtomicBool>,
+    channels: u16,
+    sample_rate: u32,
+    total_duration: Option<Duration>,
+    prefill: usize,
+    state: SeekState,
+    /// Samples of the current frame already returned.
+    frame_offset: usize,
+    /// The current frame is silence, it isn't mixed with decoded samples.
+    silent_frame: bool,
+}
+
+fn decoder_stopped() -> SeekError {
+    SeekError::NotSupported {
+        underlying_source: "stopped decoder thread",
+    }
+}
+
+fn samples_for(duration: Duration, sample_rate: u32, channels: u16) -> usize {
+    (duration.as_secs_f64() * sample_rate as f64) as usize * channels.max(1) as usize
+}
+
+/// Move `source` to a decoder thread with a raised priority. The output
+/// stays silent until the start of the track is decoded.
+pub fn prefetch<S>(source: S) -> Result<PrefetchedDecoder>
+where
+    S: Source<Item = i16> + Send + 'static,
+{
+    let channels = source.channels();
+    let sample_rate = source.sample_rate();
+    let capacity = samples_for(PREFETCH_AHEAD, sample_rate, channels).max(DECODE_BATCH);
+    let (producer, consumer) = RingBuffer::new(capacity);
+    let (commands, command_receiver) = mpsc::channel();
+    let (seek_result_sender, seek_results) = mpsc::channel();
+    let finished = Arc::new(AtomicBool::new(false));
+
+    let decoder = PrefetchedDecoder {
+        consumer,
+        commands,
+        seek_results,
+        finished: Arc::clone(&finished),
+        channels,
+        sample_rate,
+        total_duration: source.total_duration(),
+        prefill: samples_for(PREFETCH_PREFILL, sample_rate, channels).min(capacity),
+        state: SeekState::Prefilling {
+            since: Instant::now(),
+        },
+        frame_offset: 0,
+        silent_frame: false,
+    };
+
+    thread::Builder::new()
+        .name("rune-decoder".to_owned())
+        .spawn(move || {
+            if let Err(e) = set_current_thread_priority(ThreadPriority::Max) {
+                debug!("Failed to raise the priority of the decoder thread: {e:?}");
+            }
+            decode(
+                source,
+                producer,
+                command_receiver,
+                seek_result_sender,
+                finished,
+            );
+        })
+        .context("Failed to start the decoder thread")?;
+
+    Ok(decoder)
+}
+
+/// Fill the buffer until the output is dropped, following its seeks.
+fn decode<S>(
+    mut source: S,
+    mut producer: Producer<i16>,
+    commands: Receiver<PrefetchCommand>,
+    seek_results: Sender<Result<(), SeekError>>,
+    finished: Arc<AtomicBool>,
+) where
+    S: Source<Item = i16>,
+{
+    let channels = source.channels().max(1) as usize;
+    let mut batch = Vec::with_capacity(DECODE_BATCH);
+
+    loop {
+        // Nothing to decode at the end, so only a seek can wake it up
+        let command = if finished.load(Ordering::Acquire) {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            }
+        } else {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        };
+
+        if let Some(PrefetchCommand::Seek(pos)) = command {
+            let result = source.try_seek(pos);
+            finished.store(false, Ordering::Release);
+            if seek_results.send(result).is_err() {
+                return;
+            }
+
+            // Wait for the output to drop the samples from before the seek
+            loop {
+                match commands.recv() {
+                    Ok(PrefetchCommand::Resume) => break,
+                    Ok(PrefetchCommand::Seek(_)) => {
+                        warn!("Decoder asked to seek before it resumed");
+                    }
+                    Err(_) => return,
+                }
+            }
+            continue;
+        }
+
+        if producer.is_abandoned() {
+            return;
+        }
+
+        let batch_len = producer.slots().min(DECODE_BATCH) / channels * channels;
+        if batch_len == 0 {
+            thread::sleep(IDLE_INTERVAL);
+            continue;
+        }
+
+        batch.extend(source.by_ref().take(batch_len));
+        if batch.len() < batch_len {
+            // A frame cut short by the end of the track is dropped
+            batch.truncate(batch.len() / channels * channels);
+            finished.store(true, Ordering::Release);
+        }
+
+        // The batch shows up at once, so the output never sees half a frame.
+        // Only this thread pushes, so the slots are still free
+        if let Ok(chunk) = producer.write_chunk_uninit(batch.len()) {
+            chunk.fill_from_iter(batch.drain(..));
+        }
+        batch.clear();
+    }
+}
+
+impl PrefetchedDecoder {
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    fn emit(&mut self, sample: i16) -> Option<i16> {
+        self.frame_offset = (self.frame_offset + 1) % self.channels.max(1) as usize;
+        Some(sample)
+    }
+
+    /// Follow a pending seek or prefill without waiting for it, `true` once
+    /// the output may play the buffer.
+    fn poll_state(&mut self) -> bool {
+        match self.state {
+            SeekState::Playing => return true,
+            SeekState::Seeking { queued } => match self.seek_results.try_recv() {
+                Ok(result) => {
+                    if let Err(e) = result {
+                        warn!("Decoder failed to seek: {e}");
+                    }
+
+                    // The decoder waits until the old samples are gone
+                    while self.consumer.pop().is_ok() {}
+                    if self.commands.send(PrefetchCommand::Resume).is_err() {
+                        self.state = SeekState::Playing;
+                        return true;
+                    }
+
+                    self.state = match queued {
+                        Some(pos) if self.commands.send(PrefetchCommand::Seek(pos)).is_ok() => {
+                            SeekState::Seeking { queued: None }
+                        }
+                        _ => SeekState::Prefilling {
+                            since: Instant::now(),
+                        },
+                    };
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.state = SeekState::Playing,
+            },
+            SeekState::Prefilling { since } => {
+                if self.consumer.slots() >= self.prefill || self.is_finished() {
+                    self.state = SeekState::Playing;
+                } else if since.elapsed() > PREFILL_TIMEOUT {
+                    warn!("Decoder is slow, starting the output before the prefill");
+                    self.state = SeekState::Playing;
+                }
+            }
+        }
+
+        matches!(self.state, SeekState::Playing)
+    }
+}
+
+impl Iterator for PrefetchedDecoder {
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        let playing = self.poll_state();
+
+        if self.frame_offset > 0 {
+            // A seek may have dropped the rest of the frame, which is then
+            // filled up with silence
+            if playing
+                && !self.silent_frame
+                && let Ok(sample) = self.consumer.pop()
+            {
+                return self.emit(sample);
+            }
+            self.silent_frame = true;
+            return self.emit(0);
+        }
+
+        if playing {
+            if let Ok(sample) = self.consumer.pop() {
+                self.silent_frame = false;
+                return self.emit(sample);
+            }
+
+            if self.is_finished() {
+                // The last samples may have been pushed right before the flag
+                let sample = self.consumer.pop().ok()?;
+                self.silent_frame = false;
+                return self.emit(sample);
+            }
+        }
+
+        // The decoder fell behind or is seeking, a short gap beats stalling
+        // the output
+        self.silent_frame = true;
+        self.emit(0)
+    }
+}
+
+impl Source for PrefetchedDecoder {
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        let playing = matches!(self.state, SeekState::Playing);
+        if playing && self.is_finished() && self.consumer.is_empty() {
+            return Some(0);
+        }
+
+        Some(FRAME_LEN * self.channels.max(1) as usize)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+
+    /// Ask the decoder to seek and return right away, the output stays
+    /// silent until the new position is decoded. Failed seeks are logged
+    /// and keep playing from where the decoder is.
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        if let SeekState::Seeking { queued } = &mut self.state {
+            *queued = Some(pos);
+            return Ok(());
+        }
+
+        if self.commands.send(PrefetchCommand::Seek(pos)).is_err() {
+            return Err(decoder_stopped());
+        }
+        while self.consumer.pop().is_ok() {}
+        self.state = SeekState::Seeking { queued: None };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_SAMPLE_RATE: u32 = 1000;
+
+    /// A ramp counting frames up from `1`, so silence is told apart. The
+    /// second channel is negated, so swapped channels are told apart too.
+    struct Ramp {
+        position: usize,
+        len: usize,
+        channels: u16,
+        dropped: Arc<AtomicBool>,
+    }
+
+    impl Ramp {
+        fn new(len: usize) -> (Self, Arc<AtomicBool>) {
+            Self::with_channels(len, 1)
+        }
+
+        fn stereo(frames: usize) -> (Self, Arc<AtomicBool>) {
+            Self::with_channels(frames * 2, 2)
+        }
+
+        fn with_channels(len: usize, channels: u16) -> (Self, Arc<AtomicBool>) {
+            let dropped = Arc::new(AtomicBool::new(false));
+            let ramp = Ramp {
+                position: 0,
+                len,
+                channels,
+                dropped: Arc::clone(&dropped),
+            };
+
+            (ramp, dropped)
+        }
+    }
+
+    impl Drop for Ramp {
+        fn drop(&mut self) {
+            self.dropped.store(true, Ordering::Release);
+        }
+    }
+
+    impl Iterator for Ramp {
+        type Item = i16;
+
+        fn next(&mut self) -> Option<i16> {
+            if self.position >= self.len {
+                return None;
+            }
+            let channels = self.channels as usize;
+            let frame = (self.position / channels + 1) as i16;
+            let channel = self.position % channels;
+            self.position += 1;
+
+            Some(if channel == 0 { frame } else { -frame })
+        }
+    }
+
+    impl Source for Ramp {
+        fn current_frame_len(&self) -> Option<usize> {
+            None
+        }
+
+        fn channels(&self) -> u16 {
+            self.channels
+        }
+
+        fn sample_rate(&self) -> u32 {
+            TEST_SAMPLE_RATE
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            None
+        }
+
+        fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+            let frame = pos.as_millis() as usize;
+            self.position = (frame * self.channels as usize).min(self.len);
+            Ok(())
+        }
+    }
+
+    /// The next sample that isn't silence, `None` at the end.
+    fn next_audible(decoder: &mut PrefetchedDecoder) -> Option<i16> {
+        let started_at = Instant::now();
+        loop {
+            match decoder.next() {
+                Some(0) => {
+                    assert!(started_at.elapsed() < Duration::from_secs(5));
+                    thread::sleep(Duration::from_millis(1));
+                }
+                sample => return sample,
+            }
+        }
+    }
+
+    /// The left sample of the next stereo frame, `None` at the end.
+    fn next_frame(decoder: &mut PrefetchedDecoder) -> Option<i16> {
+        let (left, right) = (decoder.next()?, decoder.next().unwrap());
+        assert_eq!(left, -right, "Channels swapped");
+        Some(left)
+    }
+
+    #[test]
+    fn test_plays_until_end() {
+        let (ramp, _) = Ramp::new(3000);
+        let mut decoder = prefetch(ramp).unwrap();
+
+        for expected in 1..=3000 {
+            assert_eq!(next_audible(&mut decoder), Some(expected));
+        }
+        assert_eq!(next_audible(&mut decoder), None);
+        assert_eq!(decoder.current_frame_len(), Some(0));
+    }
+
+    #[test]
+    fn test_seek_does_not_block() {
+        let (ramp, _) = Ramp::new(10000);
+        let mut decoder = prefetch(ramp).unwrap();
+        assert_eq!(next_audible(&mut decoder), Some(1));
+
+        decoder.try_seek(Duration::from_secs(5)).unwrap();
+        // A second seek before the first one lands wins
+        decoder.try_seek(Duration::from_secs(8)).unwrap();
+        assert_eq!(next_audible(&mut decoder), Some(8001));
+        assert_eq!(next_audible(&mut decoder), Some(8002));
+    }
+
+    #[test]
+    fn test_seek_after_end() {
+        let (ramp, _) = Ramp::new(100);
+        let mut decoder = prefetch(ramp).unwrap();
+        while next_audible(&mut decoder).is_some() {}
+
+        decoder.try_seek(Duration::ZERO).unwrap();
+        assert_eq!(next_audible(&mut decoder), Some(1));
+    }
+
+    #[test]
+    fn test_dropping_stops_decoder() {
+        let (ramp, dropped) = Ramp::new(usize::MAX);
+        let decoder = prefetch(ramp).unwrap();
+        drop(decoder);
+
+        let started_at = Instant::now();
+        while !dropped.load(Ordering::Acquire) {
+            assert!(started_at.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_stereo_frames_stay_aligned() {
+        let (ramp, _) = Ramp::stereo(10000);
+        let mut decoder = prefetch(ramp).unwrap();
+        // Reading right away runs into the prefill and underruns, which
+        // must only ever give whole silent frames
+        for _ in 0..1000 {
+            next_frame(&mut decoder).unwrap();
+        }
+
+        // Seeking halfway through a frame fills it up with silence
+        let mut expected = 0;
+        for target in (1..=20).map(|x| x * 200) {
+            decoder.next().unwrap();
+            decoder.try_seek(Duration::from_millis(target)).unwrap();
+            assert_eq!(decoder.next(), Some(0));
+
+            let started_at = Instant::now();
+            expected = loop {
+                match next_frame(&mut decoder).unwrap() {
+                    0 => assert!(started_at.elapsed() < Duration::from_secs(5)),
+                    frame => break frame,
+                }
+            };
+            assert_eq!(expected, target as i16 + 1);
+        }
+
+        while let Some(frame) = next_frame(&mut decoder) {
+            if frame != 0 {
+                expected += 1;
+                assert_eq!(frame, expected);
+            }
+        }
+        assert_eq!(expected, 10000);
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+use crate::control::{AtomicF32, CONTROL_REFRESH_SAMPLES};
+
+/// Loudness every track is brought to, the ReplayGain 2.0 reference.
+pub const REFERENCE_LOUDNESS_LUFS: f32 = -18.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    /// Normalize every track on its own.
+    Track,
+    /// Normalize whole albums, keeping the loudness differences between
+    /// tracks of the same album. Falls back to the track gain.
+    Album,
+}
+
+impl std::str::FromStr for ReplayGainMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(ReplayGainMode::Off),
+            "track" => Ok(ReplayGainMode::Track),
+            "album" => Ok(ReplayGainMode::Album),
+            _ => Err(anyhow::anyhow!("Unknown ReplayGain mode: {s}")),
+        }
+    }
+}
+
+impl std::fmt::Display for ReplayGainMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mode_str = match self {
+            ReplayGainMode::Off => "Off",
+            ReplayGainMode::Track => "Track",
+            ReplayGainMode::Album => "Album",
+        };
+        write!(f, "{mode_str}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGainConfig {
+    pub mode: ReplayGainMode,
+    /// Extra gain applied on top of the computed gain, in dB.
+    pub preamp_db: f32,
+    /// Lower the gain so the true peak of the track stays below full scale.
+    pub prevent_clipping: bool,
+}
+
+impl Default for ReplayGainConfig {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::Off,
+            preamp_db: 0.0,
+            prevent_clipping: true,
+        }
+    }
+}
+
+/// Measured loudness of a playing item, taken from the analysis results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGainInfo {
+    /// Integrated loudness of the track in LUFS.
+    pub track_loudness: f32,
+    /// True peak of the track in dBTP.
+    pub track_peak: f32,
+    pub album_loudness: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGainInfo {
+    /// Gain to apply in dB, `0.0` if the mode is off.
+    pub fn gain_db(&self, config: &ReplayGainConfig) -> f32 {
+        let (loudness, peak) = match config.mode {
+            ReplayGainMode::Off => return 0.0,
+            ReplayGainMode::Track => (self.track_loudness, self.track_peak),
+            ReplayGainMode::Album => (
+                self.album_loudness.unwrap_or(self.track_loudness),
+                self.album_peak.unwrap_or(self.track_peak),
+            ),
+        };
+
+        let gain = REFERENCE_LOUDNESS_LUFS - loudness + config.preamp_db;
+        if config.prevent_clipping {
+            gain.min(-peak)
+        } else {
+            gain
+        }
+    }
+}
+
+/// Gain shared between the player thread and the audio thread, so it can
+/// be updated while a track is playing.
+#[derive(Debug)]
+pub struct ReplayGainControl {
+    gain: AtomicF32,
+}
+
+impl Default for ReplayGainControl {
+    fn default() -> Self {
+        Self {
+            gain: AtomicF32::new(1.0),
+        }
+    }
+}
+
+impl ReplayGainControl {
+    pub fn set_gain_db(&self, gain_db: f32) {
+        let gain = 10f32.powf(gain_db / 20.0);
+        self.gain.store(gain);
+    }
+
+    fn load(&self) -> f32 {
+        self.gain.load()
+    }
+}
+
+/// A source scaling every sample by the gain of the shared control.
+pub struct ReplayGain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    input: I,
+    control: Arc<ReplayGainControl>,
+    gain: f32,
+    samples_until_refresh: usize,
+}
+
+pub fn replay_gain<I>(input: I, control: Arc<ReplayGainControl>) -> ReplayGain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    ReplayGain {
+        input,
+        gain: control.load(),
+        control,
+        samples_until_refresh: CONTROL_REFRESH_SAMPLES,
+    }
+}
+
+impl<I> Iterator for ReplayGain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?.to_f32();
+
+        self.samples_until_refresh -= 1;
+        if self.samples_until_refresh == 0 {
+            self.gain = self.control.load();
+            self.samples_until_refresh = CONTROL_REFRESH_SAMPLES;
+        }
+
+        Some(sample * self.gain)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for ReplayGain<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
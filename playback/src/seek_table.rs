@@ -0,0 +1,545 @@
+//! Seek tables of Ogg Vorbis files.
+//!
+//! Seeking in an Ogg stream bisects the file by page granule positions,
+//! which reads a lot of pages on long files and gets lost in chained
+//! streams. A seek table records where the pages start once, so a seek can
+//! jump straight to the right page and decode from there, with the header
+//! pages of its logical stream spliced in front.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Decoder, Source};
+
+/// Bumped whenever the encoding of [`SeekTable::to_bytes`] changes.
+pub const SEEK_TABLE_VERSION: u8 = 1;
+
+/// Seek points are kept at least this far apart, the remainder is decoded
+/// and dropped after the jump.
+const POINT_INTERVAL_SECS: u64 = 1;
+
+/// Vorbis streams start with the identification, comment and setup
+/// headers.
+const VORBIS_HEADER_PACKETS: usize = 3;
+
+const PAGE_HEADER_LEN: usize = 27;
+
+/// Start of a page no packet continues into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekPoint {
+    /// First sample of the page, counted from the start of its chain.
+    pub sample: u64,
+    /// Byte offset of the page in the file.
+    pub offset: u64,
+}
+
+/// One logical stream of a possibly chained Ogg file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeekChain {
+    /// Byte range of the pages carrying the Vorbis headers.
+    pub header_start: u64,
+    pub header_end: u64,
+    pub sample_rate: u32,
+    /// Number of samples per channel in the chain.
+    pub samples: u64,
+    pub points: Vec<SeekPoint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeekTable {
+    pub chains: Vec<SeekChain>,
+}
+
+/// Where to continue decoding for a seek.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeekTarget {
+    pub header_start: u64,
+    pub header_end: u64,
+    /// Byte offset of the first page to decode.
+    pub offset: u64,
+    /// Samples per channel to drop after the page start.
+    pub skip: u64,
+}
+
+struct PageHeader {
+    header_type: u8,
+    granule: i64,
+    serial: u32,
+    segments: Vec<u8>,
+}
+
+impl PageHeader {
+    fn len(&self) -> u64 {
+        (PAGE_HEADER_LEN + self.segments.len()) as u64 + self.body_len()
+    }
+
+    fn body_len(&self) -> u64 {
+        self.segments.iter().map(|x| *x as u64).sum()
+    }
+
+    fn is_continued(&self) -> bool {
+        self.header_type & 0x01 != 0
+    }
+
+    fn is_first(&self) -> bool {
+        self.header_type & 0x02 != 0
+    }
+
+    /// Number of packets that end on this page.
+    fn packets_ended(&self) -> usize {
+        self.segments.iter().filter(|x| **x < 255).count()
+    }
+}
+
+/// Read the page header at the current position, `None` at the end of the
+/// file.
+fn read_page_header<R: Read>(reader: &mut R) -> io::Result<Option<PageHeader>> {
+    let mut header = [0u8; PAGE_HEADER_LEN];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    if &header[0..4] != b"OggS" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing Ogg capture pattern",
+        ));
+    }
+
+    let mut segments = vec![0u8; header[26] as usize];
+    reader.read_exact(&mut segments)?;
+
+    Ok(Some(PageHeader {
+        header_type: header[5],
+        granule: i64::from_le_bytes(header[6..14].try_into().unwrap()),
+        serial: u32::from_le_bytes(header[14..18].try_into().unwrap()),
+        segments,
+    }))
+}
+
+/// Sample rate from a Vorbis identification header, `None` for any other
+/// codec.
+fn vorbis_sample_rate(packet: &[u8]) -> Option<u32> {
+    if packet.len() < 16 || packet[0] != 1 || &packet[1..7] != b"vorbis" {
+        return None;
+    }
+
+    Some(u32::from_le_bytes(packet[12..16].try_into().unwrap())).filter(|x| *x > 0)
+}
+
+struct ChainBuilder {
+    chain: SeekChain,
+    serial: u32,
+    header_packets: usize,
+    last_granule: u64,
+}
+
+impl ChainBuilder {
+    fn finish(mut self) -> SeekChain {
+        self.chain.samples = self.last_granule;
+        self.chain
+    }
+}
+
+/// Index the pages of an Ogg Vorbis file. Returns `None` if the file is not
+/// Ogg Vorbis, multiplexes several streams or is damaged, those are left to
+/// the native seeking of the decoder.
+pub fn build_seek_table<R: Read + Seek>(reader: &mut R) -> io::Result<Option<SeekTable>> {
+    let mut chains = Vec::new();
+    let mut current: Option<ChainBuilder> = None;
+    let mut offset = 0u64;
+
+    loop {
+        reader.seek(SeekFrom::Start(offset))?;
+        let page = match read_page_header(reader) {
+            Ok(Some(page)) => page,
+            Ok(None) => break,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => return Ok(None),
+            // A truncated last page ends the file
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+
+        if page.is_first() {
+            let mut packet = vec![0u8; page.body_len().min(30) as usize];
+            reader.read_exact(&mut packet)?;
+            let Some(sample_rate) = vorbis_sample_rate(&packet) else {
+                return Ok(None);
+            };
+
+            if let Some(builder) = current.take() {
+                chains.push(builder.finish());
+            }
+            current = Some(ChainBuilder {
+                chain: SeekChain {
+                    header_start: offset,
+                    header_end: offset,
+                    sample_rate,
+                    samples: 0,
+                    points: Vec::new(),
+                },
+                serial: page.serial,
+                header_packets: 0,
+                last_granule: 0,
+            });
+        }
+
+        let Some(builder) = current.as_mut() else {
+            return Ok(None);
+        };
+        if page.serial != builder.serial {
+            return Ok(None);
+        }
+
+        if builder.header_packets < VORBIS_HEADER_PACKETS {
+            // The setup header always ends a page, audio starts on the next
+            builder.header_packets += page.packets_ended();
+            builder.chain.header_end = offset + page.len();
+        } else {
+            let interval = builder.chain.sample_rate as u64 * POINT_INTERVAL_SECS;
+            let due = builder
+                .chain
+                .points
+                .last()
+                .is_none_or(|x| builder.last_granule >= x.sample + interval);
+
+            if !page.is_continued() && due {
+                builder.chain.points.push(SeekPoint {
+                    sample: builder.last_granule,
+                    offset,
+                });
+            }
+
+            // Pages on which no packet ends have a granule position of -1
+            if page.granule >= 0 {
+                builder.last_granule = page.granule as u64;
+            }
+        }
+
+        offset += page.len();
+    }
+
+    if let Some(builder) = current.take() {
+        chains.push(builder.finish());
+    }
+
+    if chains.iter().all(|x| x.points.is_empty()) {
+        return Ok(None);
+    }
+
+    Ok(Some(SeekTable { chains }))
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.bytes.len() < N {
+            return None;
+        }
+
+        let (head, tail) = self.bytes.split_at(N);
+        self.bytes = tail;
+        head.try_into().ok()
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+}
+
+impl SeekTable {
+    /// Encode the table for storage, see [`SeekTable::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![SEEK_TABLE_VERSION];
+        bytes.extend((self.chains.len() as u32).to_le_bytes());
+
+        for chain in &self.chains {
+            bytes.extend(chain.header_start.to_le_bytes());
+            bytes.extend(chain.header_end.to_le_bytes());
+            bytes.extend(chain.sample_rate.to_le_bytes());
+            bytes.extend(chain.samples.to_le_bytes());
+            bytes.extend((chain.points.len() as u32).to_le_bytes());
+            for point in &chain.points {
+                bytes.extend(point.sample.to_le_bytes());
+                bytes.extend(point.offset.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    /// Decode a stored table, `None` if it was written by another version
+    /// or is truncated.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (version, bytes) = bytes.split_first()?;
+        if *version != SEEK_TABLE_VERSION {
+            return None;
+        }
+
+        let mut reader = ByteReader { bytes };
+        let chain_count = reader.u32()?;
+        let mut chains = Vec::new();
+
+        for _ in 0..chain_count {
+            let header_start = reader.u64()?;
+            let header_end = reader.u64()?;
+            let sample_rate = reader.u32()?;
+            let samples = reader.u64()?;
+            let point_count = reader.u32()?;
+
+            let mut points = Vec::new();
+            for _ in 0..point_count {
+                points.push(SeekPoint {
+                    sample: reader.u64()?,
+                    offset: reader.u64()?,
+                });
+            }
+
+            chains.push(SeekChain {
+                header_start,
+                header_end,
+                sample_rate,
+                samples,
+                points,
+            });
+        }
+
+        Some(SeekTable { chains })
+    }
+
+    /// Find the page to decode from for `position`, counted from the start
+    /// of the file. Positions past the end fall into the last chain.
+    pub fn locate(&self, position: Duration) -> Option<SeekTarget> {
+        let mut chain_start = Duration::ZERO;
+
+        for (index, chain) in self.chains.iter().enumerate() {
+            if chain.sample_rate == 0 {
+                continue;
+            }
+
+            let length = Duration::from_secs_f64(chain.samples as f64 / chain.sample_rate as f64);
+            if position >= chain_start + length && index + 1 < self.chains.len() {
+                chain_start += length;
+                continue;
+            }
+
+            let target = ((position - chain_start).as_secs_f64() * chain.sample_rate as f64) as u64;
+            let point_index = chain.points.partition_point(|x| x.sample <= target);
+            let point = chain.points.get(point_index.checked_sub(1)?)?;
+
+            return Some(SeekTarget {
+                header_start: chain.header_start,
+                header_end: chain.header_end,
+                offset: point.offset,
+                skip: target - point.sample,
+            });
+        }
+
+        None
+    }
+}
+
+/// A reader presenting the header pages of a chain followed by the file
+/// from a page offset, as if it were a complete file.
+pub struct SplicedReader<R> {
+    inner: R,
+    header: Vec<u8>,
+    /// Offset in `inner` that follows the header.
+    body_start: u64,
+    body_len: u64,
+    position: u64,
+}
+
+impl<R: Read + Seek> SplicedReader<R> {
+    /// Present the whole of `inner` unchanged.
+    pub fn whole(inner: R) -> io::Result<Self> {
+        Self::new(inner, Vec::new(), 0)
+    }
+
+    pub fn new(mut inner: R, header: Vec<u8>, body_start: u64) -> io::Result<Self> {
+        let len = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(body_start))?;
+
+        Ok(Self {
+            inner,
+            header,
+            body_start,
+            body_len: len.saturating_sub(body_start),
+            position: 0,
+        })
+    }
+
+    fn len(&self) -> u64 {
+        self.header.len() as u64 + self.body_len
+    }
+}
+
+impl<R: Read + Seek> Read for SplicedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let header_len = self.header.len() as u64;
+
+        let read = if self.position < header_len {
+            let start = self.position as usize;
+            let count = buf.len().min(self.header.len() - start);
+            buf[..count].copy_from_slice(&self.header[start..start + count]);
+            count
+        } else {
+            self.inner.seek(SeekFrom::Start(
+                self.body_start + self.position - header_len,
+            ))?;
+            self.inner.read(buf)?
+        };
+
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for SplicedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(x) => Some(x),
+            SeekFrom::End(x) => self.len().checked_add_signed(x),
+            SeekFrom::Current(x) => self.position.checked_add_signed(x),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek to a negative position",
+            )),
+        }
+    }
+}
+
+type FileDecoder = Decoder<BufReader<SplicedReader<File>>>;
+
+fn open_decoder(mut file: File, target: Option<&SeekTarget>) -> anyhow::Result<FileDecoder> {
+    let reader = match target {
+        Some(target) => {
+            let mut header = vec![0u8; (target.header_end - target.header_start) as usize];
+            file.seek(SeekFrom::Start(target.header_start))?;
+            file.read_exact(&mut header)?;
+            SplicedReader::new(file, header, target.offset)?
+        }
+        None => SplicedReader::whole(file)?,
+    };
+
+    Ok(Decoder::new(BufReader::new(reader))?)
+}
+
+/// A decoder that seeks through a [`SeekTable`] when the track has one.
+pub struct SeekableDecoder {
+    decoder: FileDecoder,
+    path: PathBuf,
+    table: Option<Arc<SeekTable>>,
+    /// Whether `decoder` starts in the middle of the file.
+    spliced: bool,
+    total_duration: Option<Duration>,
+}
+
+impl SeekableDecoder {
+    /// Decode `file`, opened from `path`. The path is used to open the file
+    /// again when seeking.
+    pub fn new(file: File, path: &Path, table: Option<Arc<SeekTable>>) -> anyhow::Result<Self> {
+        let decoder = open_decoder(file, None)?;
+
+        Ok(Self {
+            total_duration: decoder.total_duration(),
+            decoder,
+            path: path.to_path_buf(),
+            table,
+            spliced: false,
+        })
+    }
+
+    fn reopen(&mut self, target: Option<&SeekTarget>) -> anyhow::Result<()> {
+        self.decoder = open_decoder(File::open(&self.path)?, target)?;
+        self.spliced = target.is_some();
+
+        Ok(())
+    }
+
+    fn seek_with_table(&mut self, target: &SeekTarget) -> anyhow::Result<()> {
+        self.reopen(Some(target))?;
+
+        let channels = self.decoder.channels().max(1) as u64;
+        for _ in 0..target.skip * channels {
+            if self.decoder.next().is_none() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Iterator for SeekableDecoder {
+    type Item = <FileDecoder as Iterator>::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.decoder.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.decoder.size_hint()
+    }
+}
+
+impl Source for SeekableDecoder {
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoder.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.decoder.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.decoder.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        if let Some(target) = self.table.as_ref().and_then(|x| x.locate(pos))
+            && self.seek_with_table(&target).is_ok()
+        {
+            return Ok(());
+        }
+
+        // The native seek needs the real file again
+        if self.spliced
+            && let Err(e) = self.reopen(None)
+        {
+            return Err(SeekError::Other(e.into()));
+        }
+
+        self.decoder.try_seek(pos)
+    }
+}
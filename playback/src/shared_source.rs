@@ -1,27 +1,20 @@
-use std::fs::File;
-use std::io::BufReader;
-use std::sync::{Arc, Mutex};
-
-use rodio::Decoder;
-
 use crate::buffered::RuneBuffered;
+use crate::prefetch::PrefetchedDecoder;
 
 pub struct SharedSource {
-    pub inner: Arc<Mutex<RuneBuffered<Decoder<BufReader<File>>>>>,
+    pub inner: RuneBuffered<PrefetchedDecoder>,
 }
 
 impl SharedSource {
-    pub fn new(source: RuneBuffered<Decoder<BufReader<File>>>) -> Self {
-        Self {
-            inner: Arc::new(Mutex::new(source)),
-        }
+    pub fn new(source: RuneBuffered<PrefetchedDecoder>) -> Self {
+        Self { inner: source }
     }
 }
 
 impl Iterator for SharedSource {
-    type Item = <RuneBuffered<Decoder<BufReader<File>>> as Iterator>::Item;
+    type Item = <RuneBuffered<PrefetchedDecoder> as Iterator>::Item;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.lock().unwrap().next()
+        self.inner.next()
     }
 }
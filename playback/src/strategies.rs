@@ -1,3 +1,4 @@
+use rand::Rng;
 use rand::seq::SliceRandom;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -14,12 +15,102 @@ pub enum UpdateReason {
     MovePlaylistItem { old_index: usize, new_index: usize },
 }
 
+/// What happens when a track or the whole playlist ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    One,
+    All,
+}
+
+impl From<u32> for RepeatMode {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => RepeatMode::One,
+            2 => RepeatMode::All,
+            _ => RepeatMode::Off,
+        }
+    }
+}
+
+impl From<RepeatMode> for u32 {
+    fn from(mode: RepeatMode) -> Self {
+        match mode {
+            RepeatMode::Off => 0,
+            RepeatMode::One => 1,
+            RepeatMode::All => 2,
+        }
+    }
+}
+
+impl std::str::FromStr for RepeatMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(RepeatMode::Off),
+            "one" => Ok(RepeatMode::One),
+            "all" => Ok(RepeatMode::All),
+            _ => Err(anyhow::anyhow!("Unknown repeat mode: {s}")),
+        }
+    }
+}
+
+/// The order the playlist is played in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShuffleMode {
+    #[default]
+    Off,
+    /// Every order is equally likely.
+    Uniform,
+    /// Tracks with a higher weight tend to come earlier, see
+    /// [`PlaybackStrategy::update_weights`].
+    Weighted,
+}
+
+impl From<u32> for ShuffleMode {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => ShuffleMode::Uniform,
+            2 => ShuffleMode::Weighted,
+            _ => ShuffleMode::Off,
+        }
+    }
+}
+
+impl From<ShuffleMode> for u32 {
+    fn from(mode: ShuffleMode) -> Self {
+        match mode {
+            ShuffleMode::Off => 0,
+            ShuffleMode::Uniform => 1,
+            ShuffleMode::Weighted => 2,
+        }
+    }
+}
+
+impl std::str::FromStr for ShuffleMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(ShuffleMode::Off),
+            "uniform" => Ok(ShuffleMode::Uniform),
+            "weighted" => Ok(ShuffleMode::Weighted),
+            _ => Err(anyhow::anyhow!("Unknown shuffle mode: {s}")),
+        }
+    }
+}
+
 pub trait PlaybackStrategy {
     fn next(&self, current_index: usize, playlist_len: usize) -> Option<usize>;
     fn previous(&self, current_index: usize, playlist_len: usize) -> Option<usize>;
     fn on_playlist_end(&self, playlist_len: usize) -> Option<usize>;
     fn get_mapped_track_index(&self, index: usize, playlist_len: usize) -> usize;
     fn on_playlist_updated(&mut self, playlist_len: usize, reason: UpdateReason);
+    /// Weights of the playlist items, in playlist order, used the next time
+    /// the order is generated.
+    fn update_weights(&mut self, _weights: Vec<f64>) {}
 }
 
 pub struct SequentialStrategy;
@@ -27,6 +118,10 @@ pub struct RepeatOneStrategy;
 pub struct RepeatAllStrategy;
 pub struct ShuffleStrategy {
     random_map: Vec<usize>,
+    repeat: RepeatMode,
+    /// Weights of the playlist items for a weighted shuffle, `None` for a
+    /// uniform one.
+    weights: Option<Vec<f64>>,
 }
 
 /// Generates a random sequence from 0 to max_value, keeping 0 at the first position
@@ -53,6 +148,28 @@ pub fn get_random_sequence(max_value: usize) -> Vec<usize> {
     result
 }
 
+/// Like [`get_random_sequence`], but the position of every other value is
+/// drawn with a probability proportional to its weight, so heavier values
+/// tend to come earlier. Values without a positive weight come last.
+pub fn get_weighted_random_sequence(weights: &[f64]) -> Vec<usize> {
+    if weights.len() <= 1 {
+        return vec![0];
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut keyed: Vec<(f64, usize)> = (1..weights.len())
+        .map(|i| {
+            let weight = weights[i].max(f64::MIN_POSITIVE);
+            (rng.r#gen::<f64>().powf(1.0 / weight), i)
+        })
+        .collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut result: Vec<usize> = vec![0];
+    result.extend(keyed.into_iter().map(|(_, i)| i));
+    result
+}
+
 impl PlaybackStrategy for SequentialStrategy {
     fn next(&self, current_index: usize, playlist_len: usize) -> Option<usize> {
         if current_index + 1 < playlist_len {
@@ -122,20 +239,26 @@ impl PlaybackStrategy for RepeatAllStrategy {
 }
 
 impl ShuffleStrategy {
-    pub fn new(playlist_len: usize) -> Self {
+    pub fn new(playlist_len: usize, repeat: RepeatMode, weights: Option<Vec<f64>>) -> Self {
         let mut strategy = ShuffleStrategy {
             random_map: Vec::new(),
+            repeat,
+            weights,
         };
         strategy.update_random_map(playlist_len);
         strategy
     }
 
     fn update_random_map(&mut self, playlist_len: usize) {
-        if playlist_len > 0 {
-            self.random_map = get_random_sequence(playlist_len - 1);
-        } else {
+        if playlist_len == 0 {
             self.random_map.clear();
+            return;
         }
+
+        self.random_map = match &self.weights {
+            Some(weights) if weights.len() == playlist_len => get_weighted_random_sequence(weights),
+            _ => get_random_sequence(playlist_len - 1),
+        };
     }
 
     fn insert_randomized(&mut self, start: usize, count: usize) {
@@ -153,7 +276,9 @@ impl ShuffleStrategy {
 
 impl PlaybackStrategy for ShuffleStrategy {
     fn next(&self, current_index: usize, playlist_len: usize) -> Option<usize> {
-        if current_index + 1 < playlist_len {
+        if self.repeat == RepeatMode::One {
+            Some(current_index)
+        } else if current_index + 1 < playlist_len {
             Some(current_index + 1)
         } else {
             None
@@ -161,7 +286,9 @@ impl PlaybackStrategy for ShuffleStrategy {
     }
 
     fn previous(&self, current_index: usize, _playlist_len: usize) -> Option<usize> {
-        if current_index > 0 {
+        if self.repeat == RepeatMode::One {
+            Some(current_index)
+        } else if current_index > 0 {
             Some(current_index - 1)
         } else {
             None
@@ -169,7 +296,10 @@ impl PlaybackStrategy for ShuffleStrategy {
     }
 
     fn on_playlist_end(&self, _playlist_len: usize) -> Option<usize> {
-        Some(0)
+        match self.repeat {
+            RepeatMode::All => Some(0),
+            _ => None,
+        }
     }
 
     fn get_mapped_track_index(&self, index: usize, _playlist_len: usize) -> usize {
@@ -196,13 +326,13 @@ impl PlaybackStrategy for ShuffleStrategy {
                     self.insert_randomized(self.random_map.len(), new_tracks_count);
                 }
             },
-            _ => {
-                if playlist_len > 0 {
-                    self.random_map = get_random_sequence(playlist_len - 1);
-                } else {
-                    self.random_map.clear();
-                }
-            }
+            _ => self.update_random_map(playlist_len),
+        }
+    }
+
+    fn update_weights(&mut self, weights: Vec<f64>) {
+        if self.weights.is_some() {
+            self.weights = Some(weights);
         }
     }
 }
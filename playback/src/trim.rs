@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::{Sample, Source};
+
+/// Custom start and stop points of a track, counted from the start of the
+/// file. Positions and durations of a trimmed track count from `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TrackTrim {
+    pub start: Duration,
+    /// `None` plays to the end of the file.
+    pub end: Option<Duration>,
+}
+
+impl TrackTrim {
+    pub fn is_empty(&self) -> bool {
+        self.start.is_zero() && self.end.is_none()
+    }
+}
+
+/// Number of samples over all channels that `duration` spans, in whole
+/// frames.
+fn samples_in<I: Source>(input: &I, duration: Duration) -> u64
+where
+    I::Item: Sample,
+{
+    let frames = (duration.as_secs_f64() * input.sample_rate() as f64) as u64;
+    frames * input.channels() as u64
+}
+
+/// Play `input` between the points of `trim` only.
+pub fn trim<I>(mut input: I, trim: TrackTrim) -> Trim<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    if !trim.start.is_zero() && input.try_seek(trim.start).is_err() {
+        // Formats that can't seek are decoded up to the start instead
+        for _ in 0..samples_in(&input, trim.start) {
+            if input.next().is_none() {
+                break;
+            }
+        }
+    }
+
+    let remaining = trim
+        .end
+        .map(|end| samples_in(&input, end.saturating_sub(trim.start)));
+
+    Trim {
+        input,
+        trim,
+        remaining,
+    }
+}
+
+pub struct Trim<I> {
+    input: I,
+    trim: TrackTrim,
+    /// Samples left before the end point, `None` without one.
+    remaining: Option<u64>,
+}
+
+impl<I> Iterator for Trim<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if let Some(remaining) = &mut self.remaining {
+            if *remaining == 0 {
+                return None;
+            }
+            *remaining -= 1;
+        }
+
+        self.input.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.input.size_hint();
+        match self.remaining {
+            Some(remaining) => (
+                lower.min(remaining as usize),
+                Some(upper.map_or(remaining as usize, |x| x.min(remaining as usize))),
+            ),
+            None => (lower, upper),
+        }
+    }
+}
+
+impl<I> Source for Trim<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        match (self.input.current_frame_len(), self.remaining) {
+            (Some(len), Some(remaining)) => Some(len.min(remaining as usize)),
+            (None, Some(remaining)) => Some(remaining as usize),
+            (len, None) => len,
+        }
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let end = match (self.trim.end, self.input.total_duration()) {
+            (Some(end), Some(total)) => Some(end.min(total)),
+            (end, total) => end.or(total),
+        };
+
+        end.map(|x| x.saturating_sub(self.trim.start))
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let target = self.trim.start + pos;
+        self.input.try_seek(target)?;
+
+        self.remaining = self
+            .trim
+            .end
+            .map(|end| samples_in(&self.input, end.saturating_sub(target)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use anyhow::Result;
+use reqwest::StatusCode;
+use reqwest::header::{CACHE_CONTROL, ETAG, HeaderMap, HeaderValue, LAST_MODIFIED};
+use serde::de::DeserializeOwned;
+use tokio::time::Instant;
+
+/// A response read to the end, as stored in the cache.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    status: StatusCode,
+    body: Vec<u8>,
+}
+
+impl CachedResponse {
+    pub fn new(status: StatusCode, body: Vec<u8>) -> Self {
+        Self { status, body }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// How long a response stays fresh according to its `Cache-Control`
+/// header, `None` if it must not be stored at all.
+pub(crate) fn freshness(headers: &HeaderMap, default_ttl: Duration) -> Option<Duration> {
+    let Some(cache_control) = headers.get(CACHE_CONTROL).and_then(|x| x.to_str().ok()) else {
+        return Some(default_ttl);
+    };
+
+    let mut ttl = default_ttl;
+    for directive in cache_control
+        .split(',')
+        .map(|x| x.trim().to_ascii_lowercase())
+    {
+        if directive == "no-store" {
+            return None;
+        }
+        if directive == "no-cache" {
+            ttl = Duration::ZERO;
+        } else if let Some(seconds) = directive
+            .strip_prefix("max-age=")
+            .and_then(|x| x.trim_matches('"').parse().ok())
+        {
+            ttl = Duration::from_secs(seconds);
+        }
+    }
+
+    Some(ttl)
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CacheEntry {
+    pub response: CachedResponse,
+    pub expires_at: Instant,
+    pub etag: Option<HeaderValue>,
+    pub last_modified: Option<HeaderValue>,
+}
+
+impl CacheEntry {
+    /// Cache a successful response, `None` if its headers forbid it.
+    pub fn new(
+        response: CachedResponse,
+        headers: &HeaderMap,
+        now: Instant,
+        default_ttl: Duration,
+    ) -> Option<Self> {
+        let ttl = freshness(headers, default_ttl)?;
+
+        Some(Self {
+            response,
+            expires_at: now + ttl,
+            etag: headers.get(ETAG).cloned(),
+            last_modified: headers.get(LAST_MODIFIED).cloned(),
+        })
+    }
+
+    pub fn is_fresh(&self, now: Instant) -> bool {
+        now < self.expires_at
+    }
+
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+
+    /// The entry after the server confirmed it with `304 Not Modified`.
+    pub fn revalidated(mut self, headers: &HeaderMap, now: Instant, default_ttl: Duration) -> Self {
+        self.expires_at = now + freshness(headers, default_ttl).unwrap_or(Duration::ZERO);
+        if let Some(etag) = headers.get(ETAG) {
+            self.etag = Some(etag.clone());
+        }
+        if let Some(last_modified) = headers.get(LAST_MODIFIED) {
+            self.last_modified = Some(last_modified.clone());
+        }
+
+        self
+    }
+}
+
+/// Responses by URL, the oldest stored dropped first once full.
+#[derive(Debug)]
+pub(crate) struct ResponseCache {
+    capacity: usize,
+    entries: HashMap<String, CacheEntry>,
+    order: VecDeque<String>,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        self.entries.get(key).cloned()
+    }
+
+    pub fn insert(&mut self, key: String, entry: CacheEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.insert(key.clone(), entry).is_none() {
+            self.order.push_back(key);
+        }
+
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(cache_control: &str) -> HeaderMap {
+        HeaderMap::from_iter([(CACHE_CONTROL, HeaderValue::from_str(cache_control).unwrap())])
+    }
+
+    #[test]
+    fn test_freshness() {
+        let default_ttl = Duration::from_secs(60);
+
+        assert_eq!(freshness(&HeaderMap::new(), default_ttl), Some(default_ttl));
+        assert_eq!(
+            freshness(&headers("public, max-age=3600"), default_ttl),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            freshness(&headers("no-cache"), default_ttl),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(freshness(&headers("private, no-store"), default_ttl), None);
+    }
+
+    #[test]
+    fn test_oldest_entries_are_dropped() {
+        let now = Instant::now();
+        let entry = CacheEntry::new(
+            CachedResponse::new(StatusCode::OK, b"{}".to_vec()),
+            &HeaderMap::new(),
+            now,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+
+        let mut cache = ResponseCache::new(2);
+        cache.insert("a".to_owned(), entry.clone());
+        cache.insert("b".to_owned(), entry.clone());
+        cache.insert("a".to_owned(), entry.clone());
+        cache.insert("c".to_owned(), entry);
+
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+        assert!(cache.get("c").is_some());
+        assert!(cache.get("c").unwrap().is_fresh(now));
+        assert!(!cache.get("c").unwrap().has_validators());
+    }
+}
@@ -0,0 +1,367 @@
+//! The HTTP client shared by the online integrations, like MusicBrainz,
+//! AcoustID, Shazam and the scrobbling services.
+//!
+//! Every integration gets its own [`ProviderClient`] with the rate limit of
+//! its service, and all of them share:
+//!
+//! * the offline switch, see [`set_offline`]: while it is on, requests fail
+//!   with [`OfflineError`] before anything is sent;
+//! * a global rate limit across the services, see [`configure`];
+//! * retries with exponential backoff, honouring `Retry-After`;
+//! * a response cache for lookups, revalidated with `ETag` and
+//!   `Last-Modified` once stale.
+
+mod cache;
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{debug, warn};
+use reqwest::header::{HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, RETRY_AFTER, USER_AGENT};
+use reqwest::{Client, IntoUrl, Method, Request, RequestBuilder, Response, StatusCode};
+use tokio::time::{Instant, sleep_until};
+
+use cache::{CacheEntry, ResponseCache};
+
+pub use cache::CachedResponse;
+
+/// Requests per second across all services, unless configured otherwise.
+pub const DEFAULT_MAX_REQUESTS_PER_SECOND: f64 = 10.0;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_CACHE_CAPACITY: usize = 256;
+/// How long responses without caching headers are considered fresh.
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Longer `Retry-After` delays are cut down to this.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Minimum interval between any two requests in microseconds, `0` for none.
+static GLOBAL_INTERVAL_MICROS: AtomicU64 =
+    AtomicU64::new((1_000_000.0 / DEFAULT_MAX_REQUESTS_PER_SECOND) as u64);
+static GLOBAL_NEXT_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Turn network access of all integrations off or back on.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Limit the requests per second across all services, `0` for no limit.
+pub fn set_max_requests_per_second(max_requests_per_second: f64) {
+    let micros = if max_requests_per_second > 0.0 {
+        (1_000_000.0 / max_requests_per_second) as u64
+    } else {
+        0
+    };
+    GLOBAL_INTERVAL_MICROS.store(micros, Ordering::Relaxed);
+}
+
+/// Apply the `[network]` settings of the configuration.
+pub fn configure(offline: bool, max_requests_per_second: f64) {
+    set_offline(offline);
+    set_max_requests_per_second(max_requests_per_second);
+}
+
+/// Returned instead of sending requests while the offline switch is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OfflineError;
+
+impl fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Network access is turned off")
+    }
+}
+
+impl std::error::Error for OfflineError {}
+
+/// Take the next free slot of a rate limit and move it `interval` further.
+fn reserve_slot(next: &Mutex<Option<Instant>>, interval: Duration) -> Instant {
+    let now = Instant::now();
+    let mut next = next.lock().unwrap();
+    let slot = next.map_or(now, |x| x.max(now));
+    *next = Some(slot + interval);
+
+    slot
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// The delay asked for in `Retry-After`, only the seconds form is read.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds: u64 = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+/// `429` and `503` mean the request was not processed and are always
+/// retried, other server errors only for idempotent requests.
+fn should_retry(status: StatusCode, idempotent: bool) -> bool {
+    match status {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => true,
+        status => idempotent && status.is_server_error(),
+    }
+}
+
+/// Builds a [`ProviderClient`].
+#[derive(Debug)]
+pub struct ProviderClientBuilder {
+    name: String,
+    user_agent: Option<String>,
+    min_interval: Duration,
+    max_retries: u32,
+    cache_capacity: usize,
+    cache_ttl: Duration,
+}
+
+impl ProviderClientBuilder {
+    /// Sent with every request unless the request sets its own.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// The rate limit of the service, on top of the global one.
+    pub fn min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// How many responses are cached, `0` turns the cache off.
+    pub fn cache_capacity(mut self, cache_capacity: usize) -> Self {
+        self.cache_capacity = cache_capacity;
+        self
+    }
+
+    /// How long responses without caching headers are considered fresh.
+    pub fn cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    pub fn build(self) -> Result<ProviderClient> {
+        let mut builder = Client::builder().gzip(true);
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.default_headers(
+                [(USER_AGENT, HeaderValue::from_str(user_agent)?)]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+
+        Ok(ProviderClient {
+            inner: Arc::new(ProviderClientInner {
+                client: builder.build()?,
+                name: self.name,
+                min_interval: self.min_interval,
+                max_retries: self.max_retries,
+                cache_ttl: self.cache_ttl,
+                next_request: Mutex::new(None),
+                cache: Mutex::new(ResponseCache::new(self.cache_capacity)),
+            }),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ProviderClientInner {
+    client: Client,
+    name: String,
+    min_interval: Duration,
+    max_retries: u32,
+    cache_ttl: Duration,
+    next_request: Mutex<Option<Instant>>,
+    cache: Mutex<ResponseCache>,
+}
+
+/// The HTTP client of an online service. Clones share the rate limit and
+/// the cache.
+#[derive(Debug, Clone)]
+pub struct ProviderClient {
+    inner: Arc<ProviderClientInner>,
+}
+
+impl ProviderClient {
+    /// Start building the client of a service, `name` shows up in the logs.
+    pub fn builder(name: impl Into<String>) -> ProviderClientBuilder {
+        ProviderClientBuilder {
+            name: name.into(),
+            user_agent: None,
+            min_interval: Duration::ZERO,
+            max_retries: DEFAULT_MAX_RETRIES,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            cache_ttl: DEFAULT_CACHE_TTL,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.inner.client.get(url)
+    }
+
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.inner.client.post(url)
+    }
+
+    /// Wait for the rate limit of the service, then for the global one.
+    async fn wait_for_slot(&self) {
+        sleep_until(reserve_slot(
+            &self.inner.next_request,
+            self.inner.min_interval,
+        ))
+        .await;
+
+        let global_interval = Duration::from_micros(GLOBAL_INTERVAL_MICROS.load(Ordering::Relaxed));
+        sleep_until(reserve_slot(&GLOBAL_NEXT_REQUEST, global_interval)).await;
+    }
+
+    /// Hold back the next requests to the service until `until`.
+    fn delay_requests(&self, until: Instant) {
+        let mut next = self.inner.next_request.lock().unwrap();
+        *next = Some(next.map_or(until, |x| x.max(until)));
+    }
+
+    /// Send a request once the rate limits allow, retrying the failures
+    /// that are worth retrying. The response may still be an error status.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        self.execute(request.build()?).await
+    }
+
+    async fn execute(&self, request: Request) -> Result<Response> {
+        let idempotent = matches!(
+            *request.method(),
+            Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+        );
+
+        let mut attempt = 0;
+        let mut request = request;
+        loop {
+            if is_offline() {
+                return Err(OfflineError.into());
+            }
+
+            // Requests with streamed bodies can't be cloned and are sent once
+            let retry = request.try_clone();
+
+            self.wait_for_slot().await;
+            let result = self.inner.client.execute(request).await;
+
+            let Some(retry) = retry.filter(|_| attempt < self.inner.max_retries) else {
+                return Ok(result?);
+            };
+            let delay = match &result {
+                Ok(response) if should_retry(response.status(), idempotent) => {
+                    retry_after(response).unwrap_or_else(|| backoff(attempt))
+                }
+                // Nothing was sent if the connection failed
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => backoff(attempt),
+                _ => return Ok(result?),
+            };
+
+            attempt += 1;
+            warn!(
+                "Request to {} failed, retrying in {delay:?} ({attempt}/{})",
+                self.inner.name, self.inner.max_retries
+            );
+            self.delay_requests(Instant::now() + delay);
+            request = retry;
+        }
+    }
+
+    /// Send a lookup, answered from the cache while the cached response is
+    /// fresh. Stale responses are revalidated with their `ETag` and
+    /// `Last-Modified`, and still served while offline. Only successful
+    /// responses are cached.
+    pub async fn fetch(&self, request: RequestBuilder) -> Result<CachedResponse> {
+        let mut request = request.build()?;
+        if request.method() != Method::GET {
+            let response = self.execute(request).await?;
+            return Ok(CachedResponse::new(
+                response.status(),
+                response.bytes().await?.to_vec(),
+            ));
+        }
+
+        let key = request.url().to_string();
+        let cached = self.inner.cache.lock().unwrap().get(&key);
+
+        if let Some(entry) = &cached {
+            if entry.is_fresh(Instant::now()) {
+                debug!("Answered a request to {} from the cache", self.inner.name);
+                return Ok(entry.response.clone());
+            }
+
+            let headers = request.headers_mut();
+            if let Some(etag) = &entry.etag {
+                headers.insert(IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+
+        let response = match self.execute(request).await {
+            Ok(response) => response,
+            Err(e) if e.is::<OfflineError>() => {
+                return match cached {
+                    Some(entry) => Ok(entry.response),
+                    None => Err(e),
+                };
+            }
+            Err(e) => return Err(e),
+        };
+
+        let now = Instant::now();
+        let status = response.status();
+        if let (StatusCode::NOT_MODIFIED, Some(entry)) =
+            (status, cached.filter(|x| x.has_validators()))
+        {
+            let entry = entry.revalidated(response.headers(), now, self.inner.cache_ttl);
+            let result = entry.response.clone();
+            self.inner.cache.lock().unwrap().insert(key, entry);
+
+            return Ok(result);
+        }
+
+        let headers = response.headers().clone();
+        let result = CachedResponse::new(status, response.bytes().await?.to_vec());
+        let entry = status
+            .is_success()
+            .then(|| CacheEntry::new(result.clone(), &headers, now, self.inner.cache_ttl))
+            .flatten();
+        if let Some(entry) = entry {
+            self.inner.cache.lock().unwrap().insert(key, entry);
+        }
+
+        Ok(result)
+    }
+}
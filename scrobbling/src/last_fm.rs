@@ -4,7 +4,8 @@ use std::{collections::HashMap, time::SystemTime};
 use anyhow::{bail, Result};
 use async_trait::async_trait;
 use md5;
-use reqwest::{Client, Response};
+use provider_client::ProviderClient;
+use reqwest::Response;
 
 use crate::{AuthResponse, ScrobblingClient, ScrobblingTrack};
 
@@ -13,13 +14,13 @@ pub struct LastFmClient {
     api_key: String,
     api_secret: String,
     pub session_key: Option<String>,
-    client: Client,
+    client: ProviderClient,
     base_url: String,
 }
 
 impl LastFmClient {
     pub fn new(api_key: String, api_secret: String) -> Result<Self> {
-        let client = Client::builder().build()?;
+        let client = ProviderClient::builder("Last.fm").build()?;
         Ok(LastFmClient {
             api_key,
             api_secret,
@@ -56,13 +57,12 @@ impl ScrobblingClient for LastFmClient {
         let api_sig = self.generate_signature(&mut params);
         params.insert("api_sig".to_string(), api_sig);
 
-        let response = self
+        let request = self
             .client
             .post(&self.base_url)
             .form(&params)
-            .query(&[("format", "json")])
-            .send()
-            .await?;
+            .query(&[("format", "json")]);
+        let response = self.client.send(request).await?;
 
         if response.status().is_success() {
             let auth_response: AuthResponse = response.json().await?;
@@ -113,13 +113,12 @@ impl ScrobblingClient for LastFmClient {
         let api_sig = self.generate_signature(&mut params);
         params.insert("api_sig".to_string(), api_sig);
 
-        let response = self
+        let request = self
             .client
             .post(&self.base_url)
             .form(&params)
-            .query(&[("format", "json")])
-            .send()
-            .await?;
+            .query(&[("format", "json")]);
+        let response = self.client.send(request).await?;
 
         Ok(response)
     }
@@ -154,13 +153,12 @@ impl ScrobblingClient for LastFmClient {
         let api_sig = self.generate_signature(&mut params);
         params.insert("api_sig".to_string(), api_sig);
 
-        let response = self
+        let request = self
             .client
             .post(&self.base_url)
             .form(&params)
-            .query(&[("format", "json")])
-            .send()
-            .await?;
+            .query(&[("format", "json")]);
+        let response = self.client.send(request).await?;
 
         Ok(response)
     }
@@ -3,20 +3,21 @@ use std::{collections::HashMap, time::SystemTime};
 
 use anyhow::{bail, Result};
 use async_trait::async_trait;
-use reqwest::{Client, Response};
+use provider_client::ProviderClient;
+use reqwest::Response;
 
 use crate::{AuthResponse, ScrobblingClient, ScrobblingTrack};
 
 #[derive(Clone)]
 pub struct LibreFmClient {
     pub session_key: Option<String>,
-    client: Client,
+    client: ProviderClient,
     base_url: String,
 }
 
 impl LibreFmClient {
     pub fn new() -> Result<Self> {
-        let client = Client::builder().build()?;
+        let client = ProviderClient::builder("Libre.fm").build()?;
         Ok(LibreFmClient {
             session_key: None,
             client,
@@ -44,13 +45,12 @@ impl ScrobblingClient for LibreFmClient {
         params.insert("authtoken".to_string(), authtoken);
         params.insert("api_key".to_string(), "0".repeat(32));
 
-        let response = self
+        let request = self
             .client
             .post(&self.base_url)
             .form(&params)
-            .query(&[("format", "json")])
-            .send()
-            .await?;
+            .query(&[("format", "json")]);
+        let response = self.client.send(request).await?;
 
         if response.status().is_success() {
             let auth_response: AuthResponse = response.json().await?;
@@ -98,13 +98,12 @@ impl ScrobblingClient for LibreFmClient {
             params.insert("duration".to_string(), duration.to_string());
         }
 
-        let response = self
+        let request = self
             .client
             .post(&self.base_url)
             .form(&params)
-            .query(&[("format", "json")])
-            .send()
-            .await?;
+            .query(&[("format", "json")]);
+        let response = self.client.send(request).await?;
 
         Ok(response)
     }
@@ -136,13 +135,12 @@ impl ScrobblingClient for LibreFmClient {
             params.insert("albumArtist".to_string(), album_artist.clone());
         }
 
-        let response = self
+        let request = self
             .client
             .post(&self.base_url)
             .form(&params)
-            .query(&[("format", "json")])
-            .send()
-            .await?;
+            .query(&[("format", "json")]);
+        let response = self.client.send(request).await?;
 
         Ok(response)
     }
@@ -1,6 +1,7 @@
 use anyhow::{bail, Result};
 use async_trait::async_trait;
-use reqwest::{header::HeaderValue, Client, Response};
+use provider_client::ProviderClient;
+use reqwest::{header::HeaderValue, Response};
 use serde_json::{Map, Value};
 use std::{
     collections::HashMap,
@@ -43,14 +44,14 @@ impl From<&ScrobblingTrack> for Value {
 
 #[derive(Clone)]
 pub struct ListenBrainzClient {
-    client: Client,
+    client: ProviderClient,
     base_url: String,
     pub session_key: Option<String>,
 }
 
 impl ListenBrainzClient {
     pub fn new() -> Result<Self> {
-        let client = Client::builder().build()?;
+        let client = ProviderClient::builder("ListenBrainz").build()?;
         Ok(ListenBrainzClient {
             client,
             base_url: "https://api.listenbrainz.org".to_string(),
@@ -64,16 +65,15 @@ impl ListenBrainzClient {
         body: &HashMap<&str, serde_json::Value>,
     ) -> Result<Response> {
         if let Some(token) = &self.session_key {
-            let response = self
+            let request = self
                 .client
                 .post(format!("{}/{}", self.base_url, endpoint))
                 .json(body)
                 .header(
                     "Authorization",
                     HeaderValue::from_str(&format!("Token {token}"))?,
-                )
-                .send()
-                .await?;
+                );
+            let response = self.client.send(request).await?;
 
             if response.status().is_success() {
                 Ok(response)
@@ -89,15 +89,14 @@ impl ListenBrainzClient {
 #[async_trait]
 impl ScrobblingClient for ListenBrainzClient {
     async fn authenticate(&mut self, _username: &str, password: &str) -> Result<()> {
-        let response = self
+        let request = self
             .client
             .get(format!("{}/1/validate-token", self.base_url))
             .header(
                 "Authorization",
                 HeaderValue::from_str(&format!("Token {password}"))?,
-            )
-            .send()
-            .await?;
+            );
+        let response = self.client.send(request).await?;
 
         if response.status().is_success() {
             let json: Value = response.json().await?;
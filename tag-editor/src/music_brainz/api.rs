@@ -2,14 +2,16 @@ use std::collections::HashMap;
 use std::time::Duration;
 
 use anyhow::{bail, Result};
-use log::{error, warn};
-use reqwest::Client;
+use once_cell::sync::OnceCell;
+use provider_client::ProviderClient;
 use rusty_chromaprint::Configuration;
 use serde::{Deserialize, Serialize};
-use tokio::time::sleep;
 
 use super::fingerprint::encode_fingerprint;
 
+/// AcoustID allows three requests per second for each client.
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(334);
+
 #[derive(Serialize, Debug)]
 pub struct AcoustIdRequest<'a> {
     pub format: &'a str,
@@ -81,13 +83,23 @@ pub struct ErrorDetail {
     pub message: String,
 }
 
-async fn identify_implementation(
+fn client() -> Result<&'static ProviderClient> {
+    static CLIENT: OnceCell<ProviderClient> = OnceCell::new();
+
+    CLIENT.get_or_try_init(|| {
+        ProviderClient::builder("AcoustID")
+            .min_interval(MIN_REQUEST_INTERVAL)
+            .build()
+    })
+}
+
+pub async fn identify(
     api_key: &str,
     fingerprint: Vec<u32>,
     config: &Configuration,
     duration: u32,
 ) -> Result<Vec<AcoustIdResult>> {
-    let client = Client::builder().gzip(true).build()?;
+    let client = client()?;
 
     let encoded_fingerprint = encode_fingerprint(fingerprint, config, false, true);
 
@@ -100,9 +112,11 @@ async fn identify_implementation(
     form_data.insert("meta", "recordings releases tracks");
 
     let response = client
-        .post("https://api.acoustid.org/v2/lookup")
-        .form(&form_data)
-        .send()
+        .send(
+            client
+                .post("https://api.acoustid.org/v2/lookup")
+                .form(&form_data),
+        )
         .await?;
 
     let response_text = response.text().await?;
@@ -114,30 +128,3 @@ async fn identify_implementation(
         AcoustIdResponse::Error { error, .. } => bail!("Error {}: {}", error.code, error.message),
     }
 }
-
-pub async fn identify(
-    api_key: &str,
-    fingerprint: Vec<u32>,
-    config: &Configuration,
-    duration: u32,
-) -> Result<Vec<AcoustIdResult>> {
-    let mut attempts = 0;
-    loop {
-        if attempts > 0 {
-            sleep(Duration::from_secs(1)).await;
-        }
-
-        match identify_implementation(api_key, fingerprint.clone(), config, duration).await {
-            Ok(results) => return Ok(results),
-            Err(e) if attempts < 3 => {
-                attempts += 1;
-                warn!("Attempt {attempts} failed: {e}. Retrying...");
-                continue;
-            }
-            Err(e) => {
-                error!("Failed after {attempts} attempts: {e}");
-                return Err(e);
-            }
-        }
-    }
-}
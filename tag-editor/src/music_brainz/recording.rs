@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use provider_client::ProviderClient;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+const API_ROOT: &str = "https://musicbrainz.org/ws/2";
+
+/// MusicBrainz allows a single request per second for each client.
+pub const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MbRecording {
+    pub id: String,
+    pub title: String,
+    /// How well the recording matches a search, from 0 to 100.
+    pub score: Option<u32>,
+    /// Length in milliseconds.
+    pub length: Option<u64>,
+    #[serde(rename = "artist-credit", default)]
+    pub artist_credit: Vec<MbArtistCredit>,
+    #[serde(default)]
+    pub releases: Vec<MbRelease>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MbArtistCredit {
+    pub name: String,
+    #[serde(default)]
+    pub joinphrase: String,
+    pub artist: MbArtist,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MbArtist {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MbRelease {
+    pub id: String,
+    pub title: String,
+    pub date: Option<String>,
+    #[serde(rename = "artist-credit", default)]
+    pub artist_credit: Vec<MbArtistCredit>,
+    #[serde(default)]
+    pub media: Vec<MbMedium>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MbMedium {
+    pub position: Option<u32>,
+    #[serde(rename = "track-count")]
+    pub track_count: Option<u32>,
+    /// Only the track of the recording, called `track` in search results
+    /// and `tracks` in lookups.
+    #[serde(alias = "tracks", default)]
+    pub track: Vec<MbTrack>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MbTrack {
+    pub id: String,
+    pub number: Option<String>,
+    pub position: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordingSearchResponse {
+    #[serde(default)]
+    recordings: Vec<MbRecording>,
+}
+
+/// Join the credited names the way MusicBrainz displays them.
+pub fn credited_name(credits: &[MbArtistCredit]) -> String {
+    credits
+        .iter()
+        .map(|x| format!("{}{}", x.name, x.joinphrase))
+        .collect()
+}
+
+/// Quote a value for the Lucene syntax of the search API.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A client of the MusicBrainz web service that keeps to its rate limit.
+pub struct MusicBrainzClient {
+    client: ProviderClient,
+}
+
+impl MusicBrainzClient {
+    /// `user_agent` has to identify the application and a way to contact
+    /// its author, requests without one are rejected.
+    pub fn new(user_agent: &str) -> Result<Self> {
+        let client = ProviderClient::builder("MusicBrainz")
+            .user_agent(user_agent)
+            .min_interval(MIN_REQUEST_INTERVAL)
+            .build()?;
+
+        Ok(Self { client })
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Option<T>> {
+        let request = self
+            .client
+            .get(format!("{API_ROOT}/{path}"))
+            .query(query)
+            .query(&[("fmt", "json")]);
+        let response = self.client.fetch(request).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if !status.is_success() => bail!("MusicBrainz returned {status}"),
+            _ => Ok(Some(response.json()?)),
+        }
+    }
+
+    /// Search recordings by artist and title, best matches first.
+    pub async fn search_recordings(
+        &self,
+        artist: &str,
+        title: &str,
+        limit: usize,
+    ) -> Result<Vec<MbRecording>> {
+        let query = format!("artist:{} AND recording:{}", quote(artist), quote(title));
+        let limit = limit.to_string();
+
+        let response: Option<RecordingSearchResponse> = self
+            .get("recording", &[("query", &query), ("limit", &limit)])
+            .await?;
+
+        Ok(response.map(|x| x.recordings).unwrap_or_default())
+    }
+
+    /// Look up a recording with its artists and releases.
+    pub async fn lookup_recording(&self, id: &str) -> Result<Option<MbRecording>> {
+        self.get(
+            &format!("recording/{id}"),
+            &[("inc", "artist-credits+releases+media")],
+        )
+        .await
+    }
+}
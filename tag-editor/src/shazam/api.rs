@@ -3,15 +3,18 @@ use std::time::Duration;
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine as _};
 use chrono::Utc;
-use once_cell::sync::Lazy;
+use once_cell::sync::OnceCell;
+use provider_client::ProviderClient;
 use rand::seq::SliceRandom;
-use reqwest::{header, Client, StatusCode};
+use reqwest::{header, StatusCode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::rate_limiter::RateLimiter;
 use super::spectrogram::Signature;
 
+/// Shazam answers too frequent requests with `429 Too Many Requests`.
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(3);
+
 #[derive(Serialize, Debug)]
 struct IdentifyRequest<'a> {
     geolocation: Geolocation,
@@ -79,7 +82,12 @@ pub struct Action {
 }
 
 pub async fn identify(signature: Signature) -> Result<(Vec<Match>, Option<Track>)> {
-    static LIMITER: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new(Duration::from_secs(3)));
+    static CLIENT: OnceCell<ProviderClient> = OnceCell::new();
+    let client = CLIENT.get_or_try_init(|| {
+        ProviderClient::builder("Shazam")
+            .min_interval(MIN_REQUEST_INTERVAL)
+            .build()
+    })?;
 
     let sample_rate = signature.sample_rate;
     let signature_data = signature.encode();
@@ -104,7 +112,6 @@ pub async fn identify(signature: Signature) -> Result<(Vec<Match>, Option<Track>
         timezone: "Europe/Berlin",
     };
 
-    let client = Client::new();
     let url = format!(
         "http://amp.shazam.com/discovery/v5/en/US/android/-/tag/{}/{}",
         Uuid::new_v4().to_string().to_uppercase(),
@@ -113,49 +120,36 @@ pub async fn identify(signature: Signature) -> Result<(Vec<Match>, Option<Track>
     let query =
         "?sync=true&webv3=true&sampling=true&connected=&shazamapiversion=v3&sharehub=true&video=v3";
 
-    let mut attempts = 0;
-    loop {
-        LIMITER.acquire().await;
-
-        let response = client
-            .post(format!("{url}{query}"))
-            .header(
-                header::USER_AGENT,
-                header::HeaderValue::from_static(
-                    USER_AGENTS
-                        .choose(&mut rand::thread_rng())
-                        .ok_or_else(|| anyhow::anyhow!("Failed to choose a user agent"))?,
-                ),
-            )
-            .header(
-                header::CONTENT_LANGUAGE,
-                header::HeaderValue::from_static("en_US"),
-            )
-            .header(
-                header::CONTENT_TYPE,
-                header::HeaderValue::from_static("application/json"),
-            )
-            .json(&request)
-            .send()
-            .await?;
+    let http_request = client
+        .post(format!("{url}{query}"))
+        .header(
+            header::USER_AGENT,
+            header::HeaderValue::from_static(
+                USER_AGENTS
+                    .choose(&mut rand::thread_rng())
+                    .ok_or_else(|| anyhow::anyhow!("Failed to choose a user agent"))?,
+            ),
+        )
+        .header(
+            header::CONTENT_LANGUAGE,
+            header::HeaderValue::from_static("en_US"),
+        )
+        .header(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        )
+        .json(&request);
+    let response = client.send(http_request).await?;
 
-        match response.status() {
-            StatusCode::OK => {
-                let resp_data: IdentifyResponse = response.json().await?;
-                return Ok((resp_data.matches, resp_data.track));
-            }
-            StatusCode::TOO_MANY_REQUESTS if attempts < 3 => {
-                attempts += 1;
-                tokio::time::sleep(Duration::from_secs(3)).await;
-                continue;
-            }
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Failed to identify track, status: {}",
-                    response.status()
-                ));
-            }
+    match response.status() {
+        StatusCode::OK => {
+            let resp_data: IdentifyResponse = response.json().await?;
+            Ok((resp_data.matches, resp_data.track))
         }
+        status => Err(anyhow::anyhow!(
+            "Failed to identify track, status: {}",
+            status
+        )),
     }
 }
 
@@ -11,7 +11,6 @@
 
 pub mod api;
 pub mod hanning;
-pub mod rate_limiter;
 pub mod ring;
 pub mod signature;
 pub mod spectrogram;